@@ -1,3 +1,5 @@
+use ureq::serde_json::Value;
+
 #[allow(dead_code)]
 pub(crate) const SERVER_NAME_MTXCHAT: &str = "_Chat UI test_";
 
@@ -29,4 +31,7 @@ pub struct Msg {
     pub body: Option<String>,
     pub sender: Option<String>,
     pub ts: Option<u64>,
+    pub event_id: Option<String>,
+    /// the content of an `m.room.encrypted` event, still to be decrypted
+    pub encrypted: Option<Value>,
 }
@@ -0,0 +1,1070 @@
+// Olm/Megolm end-to-end encryption
+//
+// A `Crypto` belongs to one logged-in device. It holds the Olm account (the identity and one-time
+// keys of this device), the pairwise Olm sessions with other devices, and the Megolm group
+// sessions that encrypted rooms use. Each piece is pickled into the PDDB dict `mtxchat.crypto` as
+// soon as it changes, so the keys live in whichever basis is unlocked, as the other mtxchat state does.
+//
+// This device shares its room key when it sends a message. It sends the key only to the devices of
+// joined members, and it makes a new key when one of them leaves. Room keys from other devices
+// arrive as Olm-encrypted to-device events in the sync, and are only taken from there: the Olm
+// session says which device sent the key, and each message the key decrypts has to come from a user
+// who owns that device.
+mod verify;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http_client::Client;
+use pddb::Pddb;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use ureq::serde_json::{self, json, Map, Value};
+use url::Url;
+pub use verify::SasEvent;
+use vodozemac::megolm::{
+    GroupSession, GroupSessionPickle, InboundGroupSession, InboundGroupSessionPickle, MegolmMessage,
+    SessionConfig as MegolmConfig, SessionKey,
+};
+use vodozemac::olm::{Account, AccountPickle, OlmMessage, Session, SessionConfig as OlmConfig, SessionPickle};
+use vodozemac::{Curve25519PublicKey, Ed25519PublicKey, Ed25519Signature};
+
+use crate::web;
+
+/// PDDB Dict for the olm account, sessions and known devices
+const MTXCHAT_CRYPTO: &str = "mtxchat.crypto";
+
+const ACCOUNT_KEY: &str = "account";
+const DEVICES_PREFIX: &str = "devices.";
+const OLM_PREFIX: &str = "olm.";
+const MEGOLM_IN_PREFIX: &str = "megolm.in.";
+const MEGOLM_OUT_PREFIX: &str = "megolm.out.";
+
+pub const OLM_ALGORITHM: &str = "m.olm.v1.curve25519-aes-sha2";
+pub const MEGOLM_ALGORITHM: &str = "m.megolm.v1.aes-sha2";
+const SIGNED_CURVE25519: &str = "signed_curve25519";
+
+/// a fresh megolm session is started after this many messages, or this long
+const MEGOLM_ROTATION_MSGS: u32 = 100;
+const MEGOLM_ROTATION_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+/// older olm sessions with a device are dropped beyond this many
+const OLM_SESSIONS_MAX: usize = 4;
+const KEYS_TIMEOUT_MS: u32 = 10_000;
+
+#[derive(Serialize, Deserialize)]
+struct AccountRecord {
+    user_id: String,
+    device_id: String,
+    /// the device keys have been uploaded
+    published: bool,
+    pickle: AccountPickle,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Device {
+    pub curve25519: String,
+    pub ed25519: String,
+    /// confirmed by comparing emoji
+    pub verified: bool,
+}
+
+struct Outbound {
+    session: GroupSession,
+    created: u64,
+    /// "user|device" of each device the session key went to
+    shared: HashSet<String>,
+}
+
+/// A room key from another device (or this one)
+struct Inbound {
+    session: InboundGroupSession,
+    /// curve25519 key of the device that shared the room key
+    sender_key: String,
+    /// ed25519 key of that device
+    signing_key: String,
+    /// the event each message index decrypted, as a message replayed under a new event is refused
+    indexes: HashMap<u32, String>,
+}
+
+impl Inbound {
+    /// Decrypts `message`, which arrived in `room_id` as `event_id`, to the event inside, and says
+    /// whether the message index is new
+    fn open(&mut self, room_id: &str, event_id: &str, message: &MegolmMessage) -> Option<(Value, bool)> {
+        let decrypted = match self.session.decrypt(message) {
+            Ok(decrypted) => decrypted,
+            Err(e) => {
+                log::warn!("failed to decrypt in {}: {:?}", room_id, e);
+                return None;
+            }
+        };
+        let event: Value = serde_json::from_slice(&decrypted.plaintext).ok()?;
+        // a message can't be replayed into another room
+        if event["room_id"] != room_id {
+            log::warn!("encrypted event for {} arrived in {}", event["room_id"], room_id);
+            return None;
+        }
+        // each message index is one event; the same ciphertext under another event is a replay
+        match self.indexes.get(&decrypted.message_index) {
+            Some(seen) if seen != event_id => {
+                log::warn!(
+                    "message {} of session {} replayed as {}",
+                    decrypted.message_index,
+                    self.session.session_id(),
+                    event_id
+                );
+                None
+            }
+            Some(_) => Some((event, false)),
+            None => {
+                self.indexes.insert(decrypted.message_index, event_id.to_string());
+                Some((event, true))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct InboundRecord {
+    pickle: InboundGroupSessionPickle,
+    sender_key: String,
+    signing_key: String,
+    indexes: HashMap<u32, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutboundRecord {
+    pickle: GroupSessionPickle,
+    created: u64,
+    shared: Vec<String>,
+}
+
+pub struct Crypto {
+    user_id: String,
+    device_id: String,
+    url: Url,
+    token: String,
+    client: Client,
+    pddb: Pddb,
+    account: Account,
+    published: bool,
+    /// olm sessions by the curve25519 key of the other device, newest last
+    olm: HashMap<String, Vec<Session>>,
+    outbound: HashMap<String, Outbound>,
+    /// inbound megolm sessions by room id, then session id
+    inbound: HashMap<String, HashMap<String, Inbound>>,
+    /// devices by user id, then device id
+    devices: HashMap<String, HashMap<String, Device>>,
+    /// users whose device lists changed since they were last queried
+    stale: HashSet<String>,
+    encrypted_rooms: HashMap<String, bool>,
+    verifications: HashMap<String, verify::Verification>,
+    txn: u32,
+}
+
+impl Crypto {
+    /// Loads the olm account of this device, or makes one and uploads its keys
+    pub fn new(user_id: &str, device_id: &str, url: &Url, token: &str) -> Option<Crypto> {
+        let pddb = Pddb::new();
+        let (account, published) = match load::<AccountRecord>(&pddb, ACCOUNT_KEY) {
+            Some(record) if record.user_id == user_id && record.device_id == device_id => {
+                (Account::from_pickle(record.pickle), record.published)
+            }
+            record => {
+                if record.is_some() {
+                    // a new login is a new device, and nothing of the old one's is any use to it
+                    log::info!("device changed to {}, starting a new olm account", device_id);
+                    pddb.delete_dict(MTXCHAT_CRYPTO, None).ok();
+                }
+                let mut account = Account::new();
+                account.generate_one_time_keys(account.max_number_of_one_time_keys() / 2);
+                (account, false)
+            }
+        };
+        let mut crypto = Crypto {
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+            url: url.clone(),
+            token: token.to_string(),
+            client: Client::new(),
+            pddb,
+            account,
+            published,
+            olm: HashMap::new(),
+            outbound: HashMap::new(),
+            inbound: HashMap::new(),
+            devices: HashMap::new(),
+            stale: HashSet::new(),
+            encrypted_rooms: HashMap::new(),
+            verifications: HashMap::new(),
+            txn: 0,
+        };
+        crypto.save_account();
+        if !crypto.published && !crypto.upload_keys() {
+            log::warn!("failed to upload device keys");
+            return None;
+        }
+        // other clients show this as the session key of the device
+        log::info!("olm identity {}", crypto.account.ed25519_key().to_base64());
+        Some(crypto)
+    }
+
+    // --------------------------------
+    // keys
+
+    fn key_id(&self, algorithm: &str) -> String { format!("{}:{}", algorithm, self.device_id) }
+
+    fn sign(&self, value: &mut Value) {
+        let signature = self.account.sign(&canonical(value)).to_base64();
+        if let Value::Object(map) = value {
+            map.insert(
+                "signatures".to_string(),
+                json!({ self.user_id.as_str(): { self.key_id("ed25519"): signature } }),
+            );
+        }
+    }
+
+    fn device_keys(&self) -> Value {
+        let mut keys = json!({
+            "algorithms": [OLM_ALGORITHM, MEGOLM_ALGORITHM],
+            "device_id": self.device_id,
+            "keys": {
+                self.key_id("curve25519"): self.account.curve25519_key().to_base64(),
+                self.key_id("ed25519"): self.account.ed25519_key().to_base64(),
+            },
+            "user_id": self.user_id,
+        });
+        self.sign(&mut keys);
+        keys
+    }
+
+    /// Uploads the unpublished one-time keys, and the device keys if they haven't been
+    fn upload_keys(&mut self) -> bool {
+        let mut body = Map::new();
+        if !self.published {
+            body.insert("device_keys".to_string(), self.device_keys());
+        }
+        let mut one_time_keys = Map::new();
+        for (key_id, key) in self.account.one_time_keys() {
+            let mut signed = json!({ "key": key.to_base64() });
+            self.sign(&mut signed);
+            one_time_keys.insert(format!("{}:{}", SIGNED_CURVE25519, key_id.to_base64()), signed);
+        }
+        body.insert("one_time_keys".to_string(), Value::Object(one_time_keys));
+        match self.post("_matrix/client/v3/keys/upload", &Value::Object(body)) {
+            Some(response) => {
+                log::info!("one-time keys on server: {}", response["one_time_key_counts"][SIGNED_CURVE25519]);
+                self.account.mark_keys_as_published();
+                self.published = true;
+                self.save_account();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tops up the one-time keys on the server, given how many the sync says are left
+    pub fn replenish_one_time_keys(&mut self, count: u64) {
+        let target = self.account.max_number_of_one_time_keys() as u64 / 2;
+        if count < target {
+            self.account.generate_one_time_keys((target - count) as usize);
+            self.save_account();
+            if !self.upload_keys() {
+                log::warn!("failed to upload one-time keys");
+            }
+        }
+    }
+
+    /// Notes the users the sync says have added or removed devices
+    pub fn devices_changed(&mut self, users: &[String]) { self.stale.extend(users.iter().cloned()); }
+
+    /// Makes sure the devices of `users` are known and current
+    fn query_devices(&mut self, users: &[String]) {
+        let mut query = Map::new();
+        for user in users {
+            if !self.devices.contains_key(user) {
+                match load::<HashMap<String, Device>>(&self.pddb, &devices_key(user)) {
+                    Some(devices) => {
+                        self.devices.insert(user.clone(), devices);
+                    }
+                    None => {
+                        query.insert(user.clone(), json!([]));
+                    }
+                }
+            }
+            if self.stale.contains(user) {
+                query.insert(user.clone(), json!([]));
+            }
+        }
+        if query.is_empty() {
+            return;
+        }
+        let response = match self.post(
+            "_matrix/client/v3/keys/query",
+            &json!({ "device_keys": query, "timeout": KEYS_TIMEOUT_MS }),
+        ) {
+            Some(response) => response,
+            None => return,
+        };
+        if let Some(users) = response["device_keys"].as_object() {
+            for (user, devices) in users {
+                let old = self.devices.remove(user).unwrap_or_default();
+                let mut current = HashMap::new();
+                for (device_id, keys) in devices.as_object().into_iter().flatten() {
+                    match parse_device(user, device_id, keys) {
+                        Some(mut device) => {
+                            // keys that changed are not the keys that were verified
+                            device.verified = old
+                                .get(device_id)
+                                .map(|d| d.verified && d.ed25519 == device.ed25519)
+                                .unwrap_or(false);
+                            current.insert(device_id.clone(), device);
+                        }
+                        None => log::warn!("ignoring device {} of {}: bad keys", device_id, user),
+                    }
+                }
+                store(&self.pddb, &devices_key(user), &current);
+                self.devices.insert(user.clone(), current);
+                self.stale.remove(user);
+            }
+        }
+    }
+
+    fn set_verified(&mut self, user_id: &str, device_id: &str) {
+        if let Some(devices) = self.devices.get_mut(user_id) {
+            if let Some(device) = devices.get_mut(device_id) {
+                device.verified = true;
+                store(&self.pddb, &devices_key(user_id), devices);
+            }
+        }
+    }
+
+    /// Checks that `user_id` owns a device with the curve25519 key `sender_key` and the ed25519 key
+    /// `signing_key`, looking the devices of `user_id` up if they aren't known
+    fn owns_device(&mut self, user_id: &str, sender_key: &str, signing_key: &str) -> bool {
+        if user_id == self.user_id
+            && sender_key == self.account.curve25519_key().to_base64()
+            && signing_key == self.account.ed25519_key().to_base64()
+        {
+            return true;
+        }
+        self.query_devices(&[user_id.to_string()]);
+        self.devices
+            .get(user_id)
+            .into_iter()
+            .flat_map(|devices| devices.values())
+            .any(|device| device.curve25519 == sender_key && device.ed25519 == signing_key)
+    }
+
+    // --------------------------------
+    // olm
+
+    fn sessions(&mut self, curve25519: &str) -> &mut Vec<Session> {
+        if !self.olm.contains_key(curve25519) {
+            let sessions = load::<Vec<SessionPickle>>(&self.pddb, &format!("{}{}", OLM_PREFIX, curve25519))
+                .unwrap_or_default()
+                .into_iter()
+                .map(Session::from_pickle)
+                .collect();
+            self.olm.insert(curve25519.to_string(), sessions);
+        }
+        self.olm.get_mut(curve25519).unwrap()
+    }
+
+    fn save_sessions(&mut self, curve25519: &str) {
+        if let Some(sessions) = self.olm.get_mut(curve25519) {
+            let excess = sessions.len().saturating_sub(OLM_SESSIONS_MAX);
+            sessions.drain(..excess);
+            let pickles: Vec<SessionPickle> = sessions.iter().map(|s| s.pickle()).collect();
+            store(&self.pddb, &format!("{}{}", OLM_PREFIX, curve25519), &pickles);
+        }
+    }
+
+    /// Starts olm sessions with the devices that have none, from claimed one-time keys
+    fn claim_sessions(&mut self, targets: &[(String, String, Device)]) {
+        let mut claim = Map::new();
+        for (user, device_id, device) in targets {
+            if self.sessions(&device.curve25519).is_empty() {
+                insert2(&mut claim, user, device_id, json!(SIGNED_CURVE25519));
+            }
+        }
+        if claim.is_empty() {
+            return;
+        }
+        let response = match self.post(
+            "_matrix/client/v3/keys/claim",
+            &json!({ "one_time_keys": claim, "timeout": KEYS_TIMEOUT_MS }),
+        ) {
+            Some(response) => response,
+            None => return,
+        };
+        for (user, device_id, device) in targets {
+            let one_time_key = match response["one_time_keys"][user][device_id].as_object() {
+                Some(keys) => match keys.values().next() {
+                    Some(key) => key,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let signed = Ed25519PublicKey::from_base64(&device.ed25519)
+                .map(|ed25519| verify_signed(one_time_key, user, &format!("ed25519:{}", device_id), &ed25519))
+                .unwrap_or(false);
+            let keys = (
+                Curve25519PublicKey::from_base64(&device.curve25519),
+                one_time_key["key"].as_str().map(Curve25519PublicKey::from_base64),
+            );
+            match (signed, keys) {
+                (true, (Ok(identity_key), Some(Ok(one_time_key)))) => {
+                    let session = self.account.create_outbound_session(
+                        OlmConfig::version_1(),
+                        identity_key,
+                        one_time_key,
+                    );
+                    self.sessions(&device.curve25519).push(session);
+                    self.save_sessions(&device.curve25519);
+                }
+                _ => log::warn!("bad one-time key for {} {}", user, device_id),
+            }
+        }
+    }
+
+    /// Wraps a to-device event for one device in an `m.room.encrypted` olm message
+    fn olm_encrypt(
+        &mut self,
+        user: &str,
+        device: &Device,
+        event_type: &str,
+        content: &Value,
+    ) -> Option<Value> {
+        let payload = json!({
+            "type": event_type,
+            "content": content,
+            "sender": self.user_id,
+            "sender_device": self.device_id,
+            "keys": { "ed25519": self.account.ed25519_key().to_base64() },
+            "recipient": user,
+            "recipient_keys": { "ed25519": device.ed25519 },
+        });
+        let sender_key = self.account.curve25519_key().to_base64();
+        let (message_type, body) =
+            self.sessions(&device.curve25519).last_mut()?.encrypt(payload.to_string()).to_parts();
+        self.save_sessions(&device.curve25519);
+        Some(json!({
+            "algorithm": OLM_ALGORITHM,
+            "sender_key": sender_key,
+            "ciphertext": { device.curve25519.as_str(): { "type": message_type, "body": body } },
+        }))
+    }
+
+    /// Decrypts an olm to-device event, starting a session if it's the first from that device.
+    /// Returns the decrypted event and the curve25519 key of the device that sent it.
+    fn olm_decrypt(&mut self, sender: &str, content: &Value) -> Option<(Value, String)> {
+        if content["algorithm"] != OLM_ALGORITHM {
+            return None;
+        }
+        let sender_key = content["sender_key"].as_str()?.to_string();
+        let ciphertext = &content["ciphertext"][self.account.curve25519_key().to_base64().as_str()];
+        let body = ciphertext["body"].as_str()?;
+        let message = OlmMessage::from_parts(ciphertext["type"].as_u64()? as usize, body).ok()?;
+        let mut plaintext = None;
+        for session in self.sessions(&sender_key).iter_mut().rev() {
+            if let Ok(decrypted) = session.decrypt(&message) {
+                plaintext = Some(decrypted);
+                break;
+            }
+        }
+        let plaintext = match (plaintext, &message) {
+            (Some(plaintext), _) => plaintext,
+            (None, OlmMessage::PreKey(pre_key)) => {
+                let identity_key = Curve25519PublicKey::from_base64(&sender_key).ok()?;
+                match self.account.create_inbound_session(identity_key, pre_key) {
+                    Ok(result) => {
+                        self.sessions(&sender_key).push(result.session);
+                        // the one-time key it used is gone now
+                        self.save_account();
+                        result.plaintext
+                    }
+                    Err(e) => {
+                        log::warn!("failed to start olm session with {}: {:?}", sender, e);
+                        return None;
+                    }
+                }
+            }
+            (None, _) => {
+                log::warn!("no olm session decrypts a message from {}", sender);
+                return None;
+            }
+        };
+        self.save_sessions(&sender_key);
+        let payload: Value = serde_json::from_slice(&plaintext).ok()?;
+        // the claims inside must match who the server says sent it, and who it was for
+        if payload["sender"] != sender
+            || payload["recipient"] != self.user_id.as_str()
+            || payload["recipient_keys"]["ed25519"] != self.account.ed25519_key().to_base64().as_str()
+        {
+            log::warn!("olm message from {} was not meant for this device", sender);
+            return None;
+        }
+        // and the signing key it claims has to be that of the device the session is with
+        let signing_key = payload["keys"]["ed25519"].as_str()?;
+        if !self.owns_device(sender, &sender_key, signing_key) {
+            log::warn!("olm message from {} claims keys of a device they don't have", sender);
+            return None;
+        }
+        Some((payload, sender_key))
+    }
+
+    /// Handles the to-device events of a sync, returning any verification steps the user sees
+    pub fn to_device(&mut self, events: &[Value]) -> Vec<SasEvent> {
+        let mut sas = Vec::new();
+        for event in events {
+            let (sender, event_type) = match (event["sender"].as_str(), event["type"].as_str()) {
+                (Some(sender), Some(event_type)) => (sender, event_type),
+                _ => continue,
+            };
+            if event_type == "m.room.encrypted" {
+                if let Some((payload, sender_key)) = self.olm_decrypt(sender, &event["content"]) {
+                    match payload["type"].as_str() {
+                        Some("m.room_key") => self.room_key(&sender_key, &payload),
+                        Some(event_type) => {
+                            sas.extend(self.handle_to_device(sender, event_type, &payload["content"]))
+                        }
+                        None => {}
+                    }
+                }
+            } else {
+                sas.extend(self.handle_to_device(sender, event_type, &event["content"]));
+            }
+        }
+        sas
+    }
+
+    fn handle_to_device(&mut self, sender: &str, event_type: &str, content: &Value) -> Option<SasEvent> {
+        match event_type {
+            "m.room_key" => {
+                // nothing ties a room key sent in the clear to a device
+                log::warn!("ignoring room key from {} sent without olm", sender);
+                None
+            }
+            t if t.starts_with("m.key.verification.") => self.verification_event(sender, t, content),
+            _ => {
+                log::info!("ignoring to-device {} from {}", event_type, sender);
+                None
+            }
+        }
+    }
+
+    // --------------------------------
+    // megolm
+
+    fn load_inbound(&mut self, room_id: &str) -> &mut HashMap<String, Inbound> {
+        if !self.inbound.contains_key(room_id) {
+            let sessions = load::<HashMap<String, InboundRecord>>(
+                &self.pddb,
+                &format!("{}{}", MEGOLM_IN_PREFIX, room_id),
+            )
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, record)| {
+                let inbound = Inbound {
+                    session: InboundGroupSession::from_pickle(record.pickle),
+                    sender_key: record.sender_key,
+                    signing_key: record.signing_key,
+                    indexes: record.indexes,
+                };
+                (id, inbound)
+            })
+            .collect();
+            self.inbound.insert(room_id.to_string(), sessions);
+        }
+        self.inbound.get_mut(room_id).unwrap()
+    }
+
+    fn save_inbound(&self, room_id: &str) {
+        if let Some(sessions) = self.inbound.get(room_id) {
+            let records: HashMap<&String, InboundRecord> = sessions
+                .iter()
+                .map(|(id, inbound)| {
+                    let record = InboundRecord {
+                        pickle: inbound.session.pickle(),
+                        sender_key: inbound.sender_key.clone(),
+                        signing_key: inbound.signing_key.clone(),
+                        indexes: inbound.indexes.clone(),
+                    };
+                    (id, record)
+                })
+                .collect();
+            store(&self.pddb, &format!("{}{}", MEGOLM_IN_PREFIX, room_id), &records);
+        }
+    }
+
+    fn add_inbound(&mut self, room_id: &str, inbound: Inbound) {
+        let sessions = self.load_inbound(room_id);
+        // the first device to share a session keeps it
+        if sessions.contains_key(&inbound.session.session_id()) {
+            return;
+        }
+        sessions.insert(inbound.session.session_id(), inbound);
+        self.save_inbound(room_id);
+    }
+
+    /// Takes the room key in the olm-decrypted `payload` from the device with `sender_key`
+    fn room_key(&mut self, sender_key: &str, payload: &Value) {
+        let content = &payload["content"];
+        if content["algorithm"] != MEGOLM_ALGORITHM {
+            return;
+        }
+        // checked against the device by `olm_decrypt`
+        let signing_key = match payload["keys"]["ed25519"].as_str() {
+            Some(key) => key,
+            None => return,
+        };
+        let (room_id, session_id, session_key) = match (
+            content["room_id"].as_str(),
+            content["session_id"].as_str(),
+            content["session_key"].as_str(),
+        ) {
+            (Some(r), Some(i), Some(k)) => (r, i, k),
+            _ => return,
+        };
+        match SessionKey::from_base64(session_key) {
+            Ok(key) => {
+                let session = InboundGroupSession::new(&key, MegolmConfig::version_1());
+                if session.session_id() == session_id {
+                    log::info!("room key {} for {}", session_id, room_id);
+                    let inbound = Inbound {
+                        session,
+                        sender_key: sender_key.to_string(),
+                        signing_key: signing_key.to_string(),
+                        indexes: HashMap::new(),
+                    };
+                    self.add_inbound(room_id, inbound);
+                } else {
+                    log::warn!("room key for {} has the wrong session id", room_id);
+                }
+            }
+            Err(e) => log::warn!("bad room key for {}: {:?}", room_id, e),
+        }
+    }
+
+    /// Decrypts the content of the `m.room.encrypted` room event `event_id` from `sender` to the body
+    /// of the message inside
+    pub fn decrypt(
+        &mut self,
+        room_id: &str,
+        sender: &str,
+        event_id: &str,
+        content: &Value,
+    ) -> Option<String> {
+        if content["algorithm"] != MEGOLM_ALGORITHM {
+            return None;
+        }
+        let session_id = content["session_id"].as_str()?;
+        let message = MegolmMessage::from_base64(content["ciphertext"].as_str()?).ok()?;
+        let inbound = match self.load_inbound(room_id).get_mut(session_id) {
+            Some(inbound) => inbound,
+            None => {
+                log::info!("no room key yet for session {}", session_id);
+                return None;
+            }
+        };
+        let (event, new_index) = inbound.open(room_id, event_id, &message)?;
+        if new_index {
+            self.save_inbound(room_id);
+        }
+        // the sender has to own the device that shared the room key
+        let (sender_key, signing_key) = match self.load_inbound(room_id).get(session_id) {
+            Some(inbound) => (inbound.sender_key.clone(), inbound.signing_key.clone()),
+            None => return None,
+        };
+        if !self.owns_device(sender, &sender_key, &signing_key) {
+            log::warn!("encrypted event from {} uses a room key from another user's device", sender);
+            return None;
+        }
+        event["content"]["body"].as_str().map(|body| body.to_string())
+    }
+
+    fn load_outbound(&mut self, room_id: &str) {
+        if !self.outbound.contains_key(room_id) {
+            if let Some(record) =
+                load::<OutboundRecord>(&self.pddb, &format!("{}{}", MEGOLM_OUT_PREFIX, room_id))
+            {
+                let outbound = Outbound {
+                    session: GroupSession::from_pickle(record.pickle),
+                    created: record.created,
+                    shared: record.shared.into_iter().collect(),
+                };
+                self.outbound.insert(room_id.to_string(), outbound);
+            }
+        }
+    }
+
+    fn save_outbound(&self, room_id: &str) {
+        if let Some(outbound) = self.outbound.get(room_id) {
+            let record = OutboundRecord {
+                pickle: outbound.session.pickle(),
+                created: outbound.created,
+                shared: outbound.shared.iter().cloned().collect(),
+            };
+            store(&self.pddb, &format!("{}{}", MEGOLM_OUT_PREFIX, room_id), &record);
+        }
+    }
+
+    /// Starts a new outbound session for the room if there is none, or the current one is spent
+    fn rotate_outbound(&mut self, room_id: &str, members: &[String]) {
+        self.load_outbound(room_id);
+        let rotate = match self.outbound.get(room_id) {
+            Some(outbound) => {
+                outbound.session.message_index() >= MEGOLM_ROTATION_MSGS
+                    || now_ms().saturating_sub(outbound.created) > MEGOLM_ROTATION_MS
+                    // someone who has the key left the room
+                    || outbound.shared.iter().any(|s| !members.iter().any(|m| shared_user(s) == m))
+            }
+            None => true,
+        };
+        if rotate {
+            let session = GroupSession::new(MegolmConfig::version_1());
+            log::info!("new megolm session {} for {}", session.session_id(), room_id);
+            // keep a copy of the key, so this device can read its own messages
+            let inbound = Inbound {
+                session: InboundGroupSession::new(&session.session_key(), MegolmConfig::version_1()),
+                sender_key: self.account.curve25519_key().to_base64(),
+                signing_key: self.account.ed25519_key().to_base64(),
+                indexes: HashMap::new(),
+            };
+            self.add_inbound(room_id, inbound);
+            self.outbound
+                .insert(room_id.to_string(), Outbound { session, created: now_ms(), shared: HashSet::new() });
+            self.save_outbound(room_id);
+        }
+    }
+
+    /// Sends the room key to the devices of `members` that don't have it yet
+    fn share_room_key(&mut self, room_id: &str, members: &[String]) -> bool {
+        let (session_id, session_key, shared) = match self.outbound.get(room_id) {
+            Some(o) => (o.session.session_id(), o.session.session_key().to_base64(), &o.shared),
+            None => return false,
+        };
+        let mut targets = Vec::new();
+        for user in members {
+            for (device_id, device) in self.devices.get(user).into_iter().flatten() {
+                let ours = *user == self.user_id && *device_id == self.device_id;
+                if !ours && !shared.contains(&shared_id(user, device_id)) {
+                    targets.push((user.clone(), device_id.clone(), device.clone()));
+                }
+            }
+        }
+        if targets.is_empty() {
+            return true;
+        }
+        self.claim_sessions(&targets);
+        let room_key = json!({
+            "algorithm": MEGOLM_ALGORITHM,
+            "room_id": room_id,
+            "session_id": session_id,
+            "session_key": session_key,
+        });
+        let mut messages = Map::new();
+        let mut shared = Vec::new();
+        for (user, device_id, device) in &targets {
+            match self.olm_encrypt(user, device, "m.room_key", &room_key) {
+                Some(content) => {
+                    insert2(&mut messages, user, device_id, content);
+                    shared.push(shared_id(user, device_id));
+                }
+                // it has no one-time keys left; it can ask for the key later
+                None => log::warn!("no olm session with {} {}", user, device_id),
+            }
+        }
+        if messages.is_empty() {
+            return true;
+        }
+        let path = format!("_matrix/client/v3/sendToDevice/m.room.encrypted/{}", self.txn_id());
+        if self.put(&path, &json!({ "messages": messages })).is_none() {
+            return false;
+        }
+        if let Some(outbound) = self.outbound.get_mut(room_id) {
+            outbound.shared.extend(shared);
+        }
+        self.save_outbound(room_id);
+        true
+    }
+
+    /// Encrypts a text message for the room, sharing the room key first as needed, and returns the
+    /// content of the `m.room.encrypted` event to send
+    pub fn encrypt(&mut self, room_id: &str, text: &str) -> Option<Value> {
+        let members = self.joined_members(room_id)?;
+        self.query_devices(&members);
+        self.rotate_outbound(room_id, &members);
+        if !self.share_room_key(room_id, &members) {
+            log::warn!("failed to share the room key for {}", room_id);
+            return None;
+        }
+        let plaintext = json!({
+            "type": "m.room.message",
+            "content": { "msgtype": "m.text", "body": text },
+            "room_id": room_id,
+        });
+        let sender_key = self.account.curve25519_key().to_base64();
+        let outbound = self.outbound.get_mut(room_id)?;
+        let ciphertext = outbound.session.encrypt(plaintext.to_string()).to_base64();
+        let session_id = outbound.session.session_id();
+        self.save_outbound(room_id);
+        Some(json!({
+            "algorithm": MEGOLM_ALGORITHM,
+            "sender_key": sender_key,
+            "ciphertext": ciphertext,
+            "session_id": session_id,
+            "device_id": self.device_id,
+        }))
+    }
+
+    // --------------------------------
+    // rooms
+
+    /// Returns true if the room has encryption turned on, or if that can't be found out; either
+    /// way nothing should go to it in the clear
+    pub fn room_encrypted(&mut self, room_id: &str) -> bool {
+        if let Some(encrypted) = self.encrypted_rooms.get(room_id) {
+            return *encrypted;
+        }
+        self.url.set_path(&format!("_matrix/client/v3/rooms/{}/state/m.room.encryption", room_id));
+        self.url.set_query(None);
+        let encrypted = match web::get_json_auth(&self.url, &self.token, &self.client) {
+            Ok(_) => true,
+            Err(http_client::Error::Status(404, _)) => false,
+            Err(e) => {
+                log::warn!("failed to get encryption state of {}: {:?}", room_id, e);
+                return true;
+            }
+        };
+        log::info!("{} encrypted: {}", room_id, encrypted);
+        self.encrypted_rooms.insert(room_id.to_string(), encrypted);
+        encrypted
+    }
+
+    fn joined_members(&mut self, room_id: &str) -> Option<Vec<String>> {
+        self.url.set_path(&format!("_matrix/client/v3/rooms/{}/joined_members", room_id));
+        self.url.set_query(None);
+        let response = web::handle_response(web::get_json_auth(&self.url, &self.token, &self.client))?;
+        Some(response["joined"].as_object()?.keys().cloned().collect())
+    }
+
+    // --------------------------------
+    // persistence and requests
+
+    fn save_account(&self) {
+        let record = AccountRecord {
+            user_id: self.user_id.clone(),
+            device_id: self.device_id.clone(),
+            published: self.published,
+            pickle: self.account.pickle(),
+        };
+        store(&self.pddb, ACCOUNT_KEY, &record);
+    }
+
+    fn txn_id(&mut self) -> String {
+        self.txn = self.txn.wrapping_add(1);
+        format!("{}.{}", now_ms(), self.txn)
+    }
+
+    fn post(&mut self, path: &str, body: &Value) -> Option<Value> {
+        self.url.set_path(path);
+        self.url.set_query(None);
+        web::handle_response(web::post_string_auth(&self.url, &body.to_string(), &self.token, &self.client))
+    }
+
+    fn put(&mut self, path: &str, body: &Value) -> Option<Value> {
+        self.url.set_path(path);
+        self.url.set_query(None);
+        web::handle_response(web::put_string_auth(&self.url, &body.to_string(), &self.token, &self.client))
+    }
+
+    fn send_to_device(&mut self, event_type: &str, user: &str, device_id: &str, content: Value) -> bool {
+        let path = format!("_matrix/client/v3/sendToDevice/{}/{}", event_type, self.txn_id());
+        self.put(&path, &json!({ "messages": { user: { device_id: content } } })).is_some()
+    }
+}
+
+/// The canonical JSON that signatures cover: no `signatures` or `unsigned`, keys sorted (which
+/// the serde_json `Map` does already), no spaces
+fn canonical(value: &Value) -> String {
+    let mut value = value.clone();
+    if let Value::Object(map) = &mut value {
+        map.remove("signatures");
+        map.remove("unsigned");
+    }
+    value.to_string()
+}
+
+fn verify_signed(value: &Value, user_id: &str, key_id: &str, key: &Ed25519PublicKey) -> bool {
+    value["signatures"][user_id][key_id]
+        .as_str()
+        .and_then(|s| Ed25519Signature::from_base64(s).ok())
+        .map(|signature| key.verify(canonical(value).as_bytes(), &signature).is_ok())
+        .unwrap_or(false)
+}
+
+/// Checks the device keys from a key query are for the right device, and signed by themselves
+fn parse_device(user_id: &str, device_id: &str, keys: &Value) -> Option<Device> {
+    if keys["user_id"] != user_id || keys["device_id"] != device_id {
+        return None;
+    }
+    let ed25519 = keys["keys"][format!("ed25519:{}", device_id).as_str()].as_str()?;
+    let curve25519 = keys["keys"][format!("curve25519:{}", device_id).as_str()].as_str()?;
+    let key = Ed25519PublicKey::from_base64(ed25519).ok()?;
+    if !verify_signed(keys, user_id, &format!("ed25519:{}", device_id), &key) {
+        return None;
+    }
+    Some(Device { curve25519: curve25519.to_string(), ed25519: ed25519.to_string(), verified: false })
+}
+
+fn devices_key(user_id: &str) -> String { format!("{}{}", DEVICES_PREFIX, user_id) }
+
+fn shared_id(user_id: &str, device_id: &str) -> String { format!("{}|{}", user_id, device_id) }
+
+fn shared_user(shared_id: &str) -> &str { shared_id.split('|').next().unwrap_or_default() }
+
+/// Inserts `value` at `map[a][b]`
+fn insert2(map: &mut Map<String, Value>, a: &str, b: &str, value: Value) {
+    if let Value::Object(inner) = map.entry(a).or_insert_with(|| json!({})) {
+        inner.insert(b.to_string(), value);
+    }
+}
+
+fn now_ms() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0) }
+
+fn load<T: DeserializeOwned>(pddb: &Pddb, key: &str) -> Option<T> {
+    let mut pddb_key = pddb.get(MTXCHAT_CRYPTO, key, None, true, false, None, None::<fn()>).ok()?;
+    let mut buffer = Vec::new();
+    if let Err(e) = pddb_key.read_to_end(&mut buffer) {
+        log::warn!("failed pddb_key read {}:{} {:?}", MTXCHAT_CRYPTO, key, e);
+        return None;
+    }
+    match serde_json::from_slice(&buffer) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("failed to parse {}:{} {:?}", MTXCHAT_CRYPTO, key, e);
+            None
+        }
+    }
+}
+
+fn store<T: Serialize>(pddb: &Pddb, key: &str, value: &T) {
+    let buffer = match serde_json::to_vec(value) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            log::warn!("failed to serialize {}:{} {:?}", MTXCHAT_CRYPTO, key, e);
+            return;
+        }
+    };
+    // delete key first to ensure data in a prior longer key is gone
+    pddb.delete_key(MTXCHAT_CRYPTO, key, None).ok();
+    match pddb.get(MTXCHAT_CRYPTO, key, None, true, true, Some(buffer.len()), None::<fn()>) {
+        Ok(mut pddb_key) => {
+            if let Err(e) = pddb_key.write_all(&buffer) {
+                log::warn!("Error writing {}:{} {:?}", MTXCHAT_CRYPTO, key, e);
+            }
+        }
+        Err(e) => log::warn!("failed to set pddb {}:{}  {:?}", MTXCHAT_CRYPTO, key, e),
+    }
+    pddb.sync().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "@alice:example.org";
+    const DEVICE_KEY: &str = "ed25519:ALICEDEVICE";
+    const ROOM: &str = "!room:example.org";
+
+    fn signed(account: &Account, mut value: Value) -> Value {
+        let signature = account.sign(&canonical(&value)).to_base64();
+        value["signatures"] = json!({ USER: { DEVICE_KEY: signature } });
+        value
+    }
+
+    fn room_key() -> (GroupSession, Inbound) {
+        let session = GroupSession::new(MegolmConfig::version_1());
+        let inbound = Inbound {
+            session: InboundGroupSession::new(&session.session_key(), MegolmConfig::version_1()),
+            sender_key: String::new(),
+            signing_key: String::new(),
+            indexes: HashMap::new(),
+        };
+        (session, inbound)
+    }
+
+    fn message(session: &mut GroupSession, room_id: &str, body: &str) -> MegolmMessage {
+        session.encrypt(
+            json!({ "type": "m.room.message", "room_id": room_id, "content": { "body": body } }).to_string(),
+        )
+    }
+
+    #[test]
+    fn canonical_json() {
+        let value = json!({
+            "b": 1,
+            "a": { "d": [1, "two"], "c": "x y", "unsigned": true },
+            "signatures": { USER: {} },
+            "unsigned": { "age": 5 },
+        });
+        // only the top-level signatures and unsigned go
+        assert_eq!(canonical(&value), r#"{"a":{"c":"x y","d":[1,"two"],"unsigned":true},"b":1}"#);
+        assert_eq!(canonical(&json!("text")), r#""text""#);
+    }
+
+    #[test]
+    fn signatures() {
+        let account = Account::new();
+        let key = account.ed25519_key();
+        let value = signed(&account, json!({ "user_id": USER, "keys": { DEVICE_KEY: key.to_base64() } }));
+        assert!(verify_signed(&value, USER, DEVICE_KEY, &key));
+
+        // unsigned isn't covered
+        let mut unsigned = value.clone();
+        unsigned["unsigned"] = json!({ "device_display_name": "phone" });
+        assert!(verify_signed(&unsigned, USER, DEVICE_KEY, &key));
+
+        let mut tampered = value.clone();
+        tampered["user_id"] = json!("@mallory:example.org");
+        assert!(!verify_signed(&tampered, USER, DEVICE_KEY, &key));
+        // a signature under another user or key id, or by another key, doesn't count
+        assert!(!verify_signed(&value, "@mallory:example.org", DEVICE_KEY, &key));
+        assert!(!verify_signed(&value, USER, "ed25519:OTHERDEVICE", &key));
+        assert!(!verify_signed(&value, USER, DEVICE_KEY, &Account::new().ed25519_key()));
+        let mut garbled = value.clone();
+        garbled["signatures"][USER][DEVICE_KEY] = json!("not a signature");
+        assert!(!verify_signed(&garbled, USER, DEVICE_KEY, &key));
+    }
+
+    #[test]
+    fn replays_are_refused() {
+        let (mut session, mut inbound) = room_key();
+        let first = message(&mut session, ROOM, "one");
+        let (event, new_index) = inbound.open(ROOM, "$1", &first).unwrap();
+        assert_eq!(event["content"]["body"], "one");
+        assert!(new_index);
+        // the same event again, as after a fresh sync
+        assert_eq!(inbound.open(ROOM, "$1", &first).map(|(_, new_index)| new_index), Some(false));
+        // the same message under another event
+        assert!(inbound.open(ROOM, "$2", &first).is_none());
+
+        let second = message(&mut session, ROOM, "two");
+        let (event, new_index) = inbound.open(ROOM, "$2", &second).unwrap();
+        assert_eq!(event["content"]["body"], "two");
+        assert!(new_index);
+    }
+
+    #[test]
+    fn other_rooms_are_refused() {
+        let (mut session, mut inbound) = room_key();
+        let elsewhere = message(&mut session, "!other:example.org", "hi");
+        assert!(inbound.open(ROOM, "$1", &elsewhere).is_none());
+        assert!(inbound.indexes.is_empty());
+        // nor does a room key open another session's messages
+        let (mut other, _) = room_key();
+        assert!(inbound.open(ROOM, "$2", &message(&mut other, ROOM, "hi")).is_none());
+    }
+}
@@ -0,0 +1,390 @@
+// Interactive device verification by emoji (SAS)
+//
+// This device only answers verifications: another device sends the request and starts, this one
+// accepts. The two sides then do a key exchange and each shows seven emoji. If the user says the
+// emoji match, the sides exchange MACs of their device keys and each marks the other as verified.
+// The method is the spec's `m.sas.v1` with `curve25519-hkdf-sha256` key agreement and
+// `hkdf-hmac-sha256.v2` MACs.
+use sha2::{Digest, Sha256};
+use ureq::serde_json::{json, Value};
+use vodozemac::sas::{EstablishedSas, Mac, Sas};
+use vodozemac::Curve25519PublicKey;
+
+use super::{canonical, Crypto};
+
+const SAS_V1: &str = "m.sas.v1";
+const KEY_AGREEMENT: &str = "curve25519-hkdf-sha256";
+const HASH: &str = "sha256";
+const MAC_METHOD: &str = "hkdf-hmac-sha256.v2";
+const SAS_EMOJI: &str = "emoji";
+
+/// The 64 emoji and their names, from the spec
+const EMOJI: [(&str, &str); 64] = [
+    ("🐶", "Dog"),
+    ("🐱", "Cat"),
+    ("🦁", "Lion"),
+    ("🐎", "Horse"),
+    ("🦄", "Unicorn"),
+    ("🐷", "Pig"),
+    ("🐘", "Elephant"),
+    ("🐰", "Rabbit"),
+    ("🐼", "Panda"),
+    ("🐓", "Rooster"),
+    ("🐧", "Penguin"),
+    ("🐢", "Turtle"),
+    ("🐟", "Fish"),
+    ("🐙", "Octopus"),
+    ("🦋", "Butterfly"),
+    ("🌷", "Flower"),
+    ("🌳", "Tree"),
+    ("🌵", "Cactus"),
+    ("🍄", "Mushroom"),
+    ("🌏", "Globe"),
+    ("🌙", "Moon"),
+    ("☁️", "Cloud"),
+    ("🔥", "Fire"),
+    ("🍌", "Banana"),
+    ("🍎", "Apple"),
+    ("🍓", "Strawberry"),
+    ("🌽", "Corn"),
+    ("🍕", "Pizza"),
+    ("🎂", "Cake"),
+    ("❤️", "Heart"),
+    ("😀", "Smiley"),
+    ("🤖", "Robot"),
+    ("🎩", "Hat"),
+    ("👓", "Glasses"),
+    ("🔧", "Spanner"),
+    ("🎅", "Santa"),
+    ("👍", "Thumbs Up"),
+    ("☂️", "Umbrella"),
+    ("⌛", "Hourglass"),
+    ("⏰", "Clock"),
+    ("🎁", "Gift"),
+    ("💡", "Light Bulb"),
+    ("📕", "Book"),
+    ("✏️", "Pencil"),
+    ("📎", "Paperclip"),
+    ("✂️", "Scissors"),
+    ("🔒", "Lock"),
+    ("🔑", "Key"),
+    ("🔨", "Hammer"),
+    ("☎️", "Telephone"),
+    ("🏁", "Flag"),
+    ("🚂", "Train"),
+    ("🚲", "Bicycle"),
+    ("✈️", "Aeroplane"),
+    ("🚀", "Rocket"),
+    ("🏆", "Trophy"),
+    ("⚽", "Ball"),
+    ("🎸", "Guitar"),
+    ("🎺", "Trumpet"),
+    ("🔔", "Bell"),
+    ("⚓", "Anchor"),
+    ("🎧", "Headphones"),
+    ("📁", "Folder"),
+    ("📌", "Pin"),
+];
+
+/// The steps of a verification that need the user
+pub enum SasEvent {
+    /// The emoji to compare with the other device, as (emoji, name). Answer with
+    /// `Crypto::sas_confirm()`.
+    Compare {
+        transaction_id: String,
+        user_id: String,
+        device_id: String,
+        emoji: Vec<(&'static str, &'static str)>,
+    },
+    Verified {
+        user_id: String,
+        device_id: String,
+    },
+    Cancelled {
+        user_id: String,
+        device_id: String,
+        reason: String,
+    },
+}
+
+pub(super) struct Verification {
+    user_id: String,
+    device_id: String,
+    state: State,
+    /// their MAC, if it came before the user confirmed
+    their_mac: Option<Value>,
+}
+
+enum State {
+    Ready,
+    Accepted(Sas),
+    /// showing the emoji
+    Keyed(EstablishedSas),
+    /// the emoji matched and our MAC is sent
+    Confirmed(EstablishedSas),
+}
+
+fn offers(content: &Value, field: &str, method: &str) -> bool {
+    content[field].as_array().map(|a| a.iter().any(|m| m == method)).unwrap_or(false)
+}
+
+impl Crypto {
+    pub(super) fn verification_event(
+        &mut self,
+        sender: &str,
+        event_type: &str,
+        content: &Value,
+    ) -> Option<SasEvent> {
+        let txn = content["transaction_id"].as_str()?.to_string();
+        let known = self.verifications.get(&txn).map(|v| (v.user_id.clone(), v.device_id.clone()));
+        if let Some((user_id, _)) = &known {
+            if user_id != sender {
+                log::warn!("{} sent {} for a verification with {}", sender, event_type, user_id);
+                return None;
+            }
+        }
+        match (event_type, known) {
+            ("m.key.verification.request", None) => {
+                let from_device = content["from_device"].as_str()?;
+                if !offers(content, "methods", SAS_V1) {
+                    return Some(self.sas_cancel(
+                        sender,
+                        from_device,
+                        &txn,
+                        "m.unknown_method",
+                        "only emoji",
+                    ));
+                }
+                log::info!("verification request from {} {}", sender, from_device);
+                let ready = json!({
+                    "from_device": self.device_id,
+                    "methods": [SAS_V1],
+                    "transaction_id": txn,
+                });
+                self.send_to_device("m.key.verification.ready", sender, from_device, ready);
+                self.verifications.insert(
+                    txn,
+                    Verification {
+                        user_id: sender.to_string(),
+                        device_id: from_device.to_string(),
+                        state: State::Ready,
+                        their_mac: None,
+                    },
+                );
+                None
+            }
+            // it may start without a request, when it is an older client
+            ("m.key.verification.start", _) => {
+                let from_device = content["from_device"].as_str()?;
+                let supported = content["method"] == SAS_V1
+                    && offers(content, "key_agreement_protocols", KEY_AGREEMENT)
+                    && offers(content, "hashes", HASH)
+                    && offers(content, "message_authentication_codes", MAC_METHOD)
+                    && offers(content, "short_authentication_string", SAS_EMOJI);
+                if !supported {
+                    self.verifications.remove(&txn);
+                    return Some(self.sas_cancel(
+                        sender,
+                        from_device,
+                        &txn,
+                        "m.unknown_method",
+                        "only emoji",
+                    ));
+                }
+                let sas = Sas::new();
+                // commits to our key before seeing theirs
+                let commitment = vodozemac::base64_encode(Sha256::digest(
+                    format!("{}{}", sas.public_key().to_base64(), canonical(content)).as_bytes(),
+                ));
+                let accept = json!({
+                    "transaction_id": txn,
+                    "method": SAS_V1,
+                    "key_agreement_protocol": KEY_AGREEMENT,
+                    "hash": HASH,
+                    "message_authentication_code": MAC_METHOD,
+                    "short_authentication_string": [SAS_EMOJI],
+                    "commitment": commitment,
+                });
+                self.send_to_device("m.key.verification.accept", sender, from_device, accept);
+                self.verifications.insert(
+                    txn,
+                    Verification {
+                        user_id: sender.to_string(),
+                        device_id: from_device.to_string(),
+                        state: State::Accepted(sas),
+                        their_mac: None,
+                    },
+                );
+                None
+            }
+            ("m.key.verification.key", Some((user_id, device_id))) => {
+                let their_key = content["key"].as_str()?.to_string();
+                let verification = self.verifications.get_mut(&txn)?;
+                let sas = match std::mem::replace(&mut verification.state, State::Ready) {
+                    State::Accepted(sas) => sas,
+                    _ => {
+                        self.verifications.remove(&txn);
+                        return Some(self.sas_cancel(
+                            &user_id,
+                            &device_id,
+                            &txn,
+                            "m.unexpected_message",
+                            "key",
+                        ));
+                    }
+                };
+                let our_key = sas.public_key().to_base64();
+                let established = match Curve25519PublicKey::from_base64(&their_key)
+                    .ok()
+                    .and_then(|key| sas.diffie_hellman(key).ok())
+                {
+                    Some(established) => established,
+                    None => {
+                        self.verifications.remove(&txn);
+                        return Some(self.sas_cancel(&user_id, &device_id, &txn, "m.invalid_message", "key"));
+                    }
+                };
+                // they started, so their side comes first
+                let info = format!(
+                    "MATRIX_KEY_VERIFICATION_SAS|{}|{}|{}|{}|{}|{}|{}",
+                    user_id, device_id, their_key, self.user_id, self.device_id, our_key, txn
+                );
+                let emoji =
+                    established.bytes(&info).emoji_indices().iter().map(|i| EMOJI[*i as usize]).collect();
+                verification.state = State::Keyed(established);
+                let key = json!({ "transaction_id": txn, "key": our_key });
+                self.send_to_device("m.key.verification.key", &user_id, &device_id, key);
+                Some(SasEvent::Compare { transaction_id: txn, user_id, device_id, emoji })
+            }
+            ("m.key.verification.mac", Some(_)) => {
+                let verification = self.verifications.get_mut(&txn)?;
+                if matches!(verification.state, State::Confirmed(_)) {
+                    self.check_mac(&txn, content)
+                } else {
+                    verification.their_mac = Some(content.clone());
+                    None
+                }
+            }
+            ("m.key.verification.done", Some(_)) => {
+                self.verifications.remove(&txn);
+                None
+            }
+            ("m.key.verification.cancel", Some((user_id, device_id))) => {
+                self.verifications.remove(&txn);
+                let reason =
+                    content["reason"].as_str().or_else(|| content["code"].as_str()).unwrap_or_default();
+                Some(SasEvent::Cancelled { user_id, device_id, reason: reason.to_string() })
+            }
+            _ => {
+                log::info!("ignoring {} for verification {}", event_type, txn);
+                None
+            }
+        }
+    }
+
+    /// Sends our MAC if the user says the emoji match, or cancels if they don't
+    pub fn sas_confirm(&mut self, transaction_id: &str, matched: bool) -> Option<SasEvent> {
+        let mut verification = self.verifications.remove(transaction_id)?;
+        let sas = match verification.state {
+            State::Keyed(sas) => sas,
+            _ => return None,
+        };
+        if !matched {
+            return Some(self.sas_cancel(
+                &verification.user_id,
+                &verification.device_id,
+                transaction_id,
+                "m.mismatched_sas",
+                "emoji did not match",
+            ));
+        }
+        let key_id = format!("ed25519:{}", self.device_id);
+        let base = format!(
+            "MATRIX_KEY_VERIFICATION_MAC{}{}{}{}{}",
+            self.user_id, self.device_id, verification.user_id, verification.device_id, transaction_id
+        );
+        let ed25519 = self.account.ed25519_key().to_base64();
+        let device_mac = sas.calculate_mac(&ed25519, &format!("{}{}", base, key_id)).to_base64();
+        let mac = json!({
+            "transaction_id": transaction_id,
+            "mac": { key_id.as_str(): device_mac },
+            "keys": sas.calculate_mac(&key_id, &format!("{}KEY_IDS", base)).to_base64(),
+        });
+        self.send_to_device("m.key.verification.mac", &verification.user_id, &verification.device_id, mac);
+        let their_mac = verification.their_mac.take();
+        verification.state = State::Confirmed(sas);
+        self.verifications.insert(transaction_id.to_string(), verification);
+        their_mac.and_then(|content| self.check_mac(transaction_id, &content))
+    }
+
+    /// Checks their MAC covers the device key we know for them, and if so marks it verified
+    fn check_mac(&mut self, transaction_id: &str, content: &Value) -> Option<SasEvent> {
+        let verification = self.verifications.remove(transaction_id)?;
+        let (user_id, device_id) = (verification.user_id, verification.device_id);
+        let sas = match verification.state {
+            State::Confirmed(sas) => sas,
+            _ => return None,
+        };
+        let base = format!(
+            "MATRIX_KEY_VERIFICATION_MAC{}{}{}{}{}",
+            user_id, device_id, self.user_id, self.device_id, transaction_id
+        );
+        let macs = content["mac"].as_object()?;
+        let mut key_ids: Vec<&str> = macs.keys().map(|k| k.as_str()).collect();
+        key_ids.sort_unstable();
+        let matches = |input: &str, info: &str, mac: &Value| {
+            mac.as_str()
+                .and_then(|m| Mac::from_base64(m).ok())
+                .map(|m| sas.verify_mac(input, info, &m).is_ok())
+                .unwrap_or(false)
+        };
+        let keys_ok = matches(&key_ids.join(","), &format!("{}KEY_IDS", base), &content["keys"]);
+        // any cross-signing keys are covered by the KEY_IDS MAC, but only the device key is checked
+        self.query_devices(std::slice::from_ref(&user_id));
+        let device_key_id = format!("ed25519:{}", device_id);
+        let device_ok = match self.devices.get(&user_id).and_then(|d| d.get(&device_id)) {
+            Some(device) => matches(
+                &device.ed25519,
+                &format!("{}{}", base, device_key_id),
+                &content["mac"][device_key_id.as_str()],
+            ),
+            None => false,
+        };
+        if !(keys_ok && device_ok) {
+            return Some(self.sas_cancel(
+                &user_id,
+                &device_id,
+                transaction_id,
+                "m.key_mismatch",
+                "keys did not match",
+            ));
+        }
+        log::info!("verified {} {}", user_id, device_id);
+        self.set_verified(&user_id, &device_id);
+        self.send_to_device(
+            "m.key.verification.done",
+            &user_id,
+            &device_id,
+            json!({ "transaction_id": transaction_id }),
+        );
+        Some(SasEvent::Verified { user_id, device_id })
+    }
+
+    fn sas_cancel(
+        &mut self,
+        user_id: &str,
+        device_id: &str,
+        transaction_id: &str,
+        code: &str,
+        reason: &str,
+    ) -> SasEvent {
+        log::info!("cancelling verification with {} {}: {}", user_id, device_id, code);
+        let cancel = json!({ "transaction_id": transaction_id, "code": code, "reason": reason });
+        self.send_to_device("m.key.verification.cancel", user_id, device_id, cancel);
+        SasEvent::Cancelled {
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
@@ -1,22 +1,26 @@
 pub mod api;
+mod crypto;
 mod listen;
+mod rooms;
 mod web;
 
 use std::fmt::Write as _;
 use std::io::{Error, ErrorKind, Read, Write as StdWrite};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub use api::*;
 use chat::Chat;
+use crypto::Crypto;
+use http_client::Client;
 use listen::listen;
 use locales::t;
 use modals::Modals;
 use pddb::Pddb;
+use rooms::Rooms;
 use ticktimer_server::Ticktimer;
-use tls::xtls::TlsConnector;
 use trng::*;
-use ureq::Agent;
 use url::Url;
 
 use crate::web::get_username;
@@ -25,7 +29,9 @@ use crate::web::get_username;
 const MTXCHAT_STATE: &str = "mtxchat.state";
 const MTXCHAT_DIALOGUE: &str = "mtxchat.dialogue";
 
-const FILTER_KEY: &str = "_filter";
+const DEVICE_ID_KEY: &str = "_device_id";
+// renamed whenever web::FilterRequest changes, so that a stale filter isn't reused
+const FILTER_KEY: &str = "_filter_rooms";
 const PASSWORD_KEY: &str = "password";
 const ROOM_ID_KEY: &str = "_room_id";
 const ROOM_NAME_KEY: &str = "room_name";
@@ -75,15 +81,19 @@ pub struct MtxChat<'a> {
     user_id: Option<String>,
     user_name: Option<String>,
     user_domain: Option<String>,
-    agent: Agent,
+    device_id: Option<String>,
+    client: Client,
     token: Option<String>,
     logged_in: bool,
     room_id: Option<String>,
     room_name: Option<String>,
     room_domain: Option<String>,
+    rooms: Arc<Mutex<Rooms>>,
     filter: Option<String>,
     since: Option<String>,
     listening: bool,
+    listen_stop: Arc<AtomicBool>,
+    crypto: Option<Arc<Mutex<Crypto>>>,
     modals: Modals,
     new_username: bool,
     new_room: bool,
@@ -106,15 +116,19 @@ impl<'a> MtxChat<'a> {
             user_id: None,
             user_name: None,
             user_domain: Some(DOMAIN_MATRIX.to_string()),
-            agent: ureq::builder().tls_connector(Arc::new(TlsConnector {})).build(),
+            device_id: None,
+            client: Client::new(),
             token: None,
             logged_in: false,
             room_id: None,
             room_name: None,
             room_domain: None,
+            rooms: Arc::new(Mutex::new(Rooms::default())),
             filter: None,
             since: None,
             listening: false,
+            listen_stop: Arc::new(AtomicBool::new(false)),
+            crypto: None,
             modals,
             new_username: false,
             new_room: false,
@@ -143,6 +157,7 @@ impl<'a> MtxChat<'a> {
             };
             match key {
                 // update cached values
+                DEVICE_ID_KEY => self.device_id = Some(value.to_string()),
                 FILTER_KEY => self.filter = Some(value.to_string()),
                 PASSWORD_KEY => (),
                 ROOM_ID_KEY => self.room_id = Some(value.to_string()),
@@ -184,6 +199,7 @@ impl<'a> MtxChat<'a> {
             }
             match key {
                 // update cached values
+                DEVICE_ID_KEY => self.device_id = None,
                 FILTER_KEY => self.filter = None,
                 PASSWORD_KEY => (),
                 ROOM_ID_KEY => self.room_id = None,
@@ -237,8 +253,14 @@ impl<'a> MtxChat<'a> {
         log::info!("Attempting connect to Matrix server");
         if self.wifi() {
             if self.login() {
-                if let Some(_room_id) = self.get_room_id() {
-                    self.dialogue_set(self.room_alias().as_deref());
+                *self.rooms.lock().unwrap() = Rooms::load(&self.pddb);
+                // the room last shown, or else one to begin with
+                let room_id = match self.get(ROOM_ID_KEY).unwrap_or(None) {
+                    Some(room_id) => Some(room_id),
+                    None => self.get_room_id(),
+                };
+                if let Some(room_id) = room_id {
+                    self.room_select(&room_id);
                     self.listen();
                     if self.new_room {
                         self.new_room = false;
@@ -273,14 +295,16 @@ impl<'a> MtxChat<'a> {
         self.chat.set_status_text(t!("mtxchat.busy.login", locales::LANG));
         self.chat.set_busy_state(true);
         self.token = self.get(TOKEN_KEY).unwrap_or(None);
+        self.device_id = self.get(DEVICE_ID_KEY).unwrap_or(None);
         self.logged_in = false;
+        self.crypto = None;
 
         let mut url = Url::parse("https://matrix.org").unwrap();
         if let Ok(Some(host)) = self.get(USER_DOMAIN_KEY) {
             url.set_host(Some(&host)).expect("failed to set host");
         }
         if let Some(token) = &self.token {
-            if let Some(user_id) = web::whoami(&mut url, &token, &mut self.agent) {
+            if let Some((user_id, device_id)) = web::whoami(&mut url, &token, &self.client) {
                 let i = match user_id.find('@') {
                     Some(index) => index + 1,
                     None => 0,
@@ -292,18 +316,28 @@ impl<'a> MtxChat<'a> {
                 self.set(USER_ID_KEY, &user_id).expect("failed to save user id");
                 self.set(USER_NAME_KEY, &user_id[i..j]).expect("failed to save user name");
                 self.set(USER_DOMAIN_KEY, &user_id[j + 1..]).expect("failed to save user domain");
+                if let Some(device_id) = device_id {
+                    self.set_debug(DEVICE_ID_KEY, &device_id);
+                }
                 self.logged_in = true;
             }
         }
         if !self.logged_in {
-            if web::get_login_type(&mut url, &mut self.agent) {
+            if web::get_login_type(&mut url, &self.client) {
                 self.login_modal();
                 let log_entry = match (&self.user_id, self.get(PASSWORD_KEY).unwrap_or(None)) {
                     (Some(user_id), Some(password)) => {
-                        if let Some(new_token) =
-                            web::authenticate_user(&mut url, &user_id, &password, &mut self.agent)
-                        {
+                        if let Some((new_token, device_id)) = web::authenticate_user(
+                            &mut url,
+                            &user_id,
+                            &password,
+                            self.device_id.as_deref(),
+                            &self.client,
+                        ) {
                             self.set_debug(TOKEN_KEY, &new_token);
+                            if let Some(device_id) = device_id {
+                                self.set_debug(DEVICE_ID_KEY, &device_id);
+                            }
                             self.logged_in = true;
                             "authenticated user"
                         } else {
@@ -320,6 +354,7 @@ impl<'a> MtxChat<'a> {
         }
         if self.logged_in {
             log::info!("logged_in");
+            self.crypto_start(&url);
         } else {
             log::info!("login failed");
             // unset credentials to facilitate re-attempt
@@ -383,9 +418,31 @@ impl<'a> MtxChat<'a> {
 
     pub fn logout(&mut self) {
         self.unset_debug(TOKEN_KEY);
+        self.crypto = None;
         // TODO logout with server
     }
 
+    // loads or creates the olm account of this device, without which encrypted rooms are
+    // unreadable and can't be posted to
+    fn crypto_start(&mut self, url: &Url) {
+        let log_entry = match (&self.user_id, &self.device_id, &self.token) {
+            (Some(user_id), Some(device_id), Some(token)) => {
+                self.chat.set_status_text(t!("mtxchat.busy.keys", locales::LANG));
+                match Crypto::new(user_id, device_id, url, token) {
+                    Some(crypto) => {
+                        self.crypto = Some(Arc::new(Mutex::new(crypto)));
+                        "encryption ready"
+                    }
+                    None => "failed to set up encryption",
+                }
+            }
+            (None, _, _) => "No user id set",
+            (_, None, _) => "No device id set",
+            (_, _, None) => "No token set",
+        };
+        log::info!("{log_entry}");
+    }
+
     pub fn room_alias(&self) -> Option<String> {
         let log_entry = match (&self.room_name, &self.room_domain) {
             (Some(room_name), Some(room_domain)) => {
@@ -408,7 +465,7 @@ impl<'a> MtxChat<'a> {
                 url.set_host(Some(user_domain)).expect("failed to set host");
                 self.chat.set_status_text(t!("mtxchat.busy.room_id", locales::LANG));
                 self.chat.set_busy_state(true);
-                if let Some(room_id) = web::get_room_id(&mut url, &room_alias, &token, &mut self.agent) {
+                if let Some(room_id) = web::get_room_id(&mut url, &room_alias, &token, &self.client) {
                     self.set_debug(ROOM_ID_KEY, &room_id);
                     self.chat.set_busy_state(false);
                     return Some(room_id);
@@ -446,8 +503,6 @@ impl<'a> MtxChat<'a> {
         };
         if let Ok(payloads) = builder.build() {
             self.unset_debug(ROOM_ID_KEY);
-            self.unset_debug(SINCE_KEY);
-            self.unset_debug(FILTER_KEY);
             if let Ok(content) = payloads.content()[0].content.as_str() {
                 self.set(ROOM_NAME_KEY, content).expect("failed to save server");
                 self.new_room = content.ne(&old_room);
@@ -456,7 +511,55 @@ impl<'a> MtxChat<'a> {
                 self.set(ROOM_DOMAIN_KEY, content).expect("failed to save server");
             }
         }
-        log::info!("# ROOM_NAME_KEY set '{}' => clearing ROOM_ID_KEY, SINCE_KEY, FILTER_KEY", ROOM_NAME_KEY);
+        log::info!("# ROOM_NAME_KEY set '{}' => clearing ROOM_ID_KEY", ROOM_NAME_KEY);
+    }
+
+    /// Presents the joined rooms, most recently active first and with their unread counts, to pick
+    /// the room to show. The last item asks for the alias of another room.
+    pub fn rooms_modal(&mut self) {
+        let rooms: Vec<(String, String)> =
+            self.rooms.lock().unwrap().sorted().iter().map(|r| (r.room_id.clone(), r.label())).collect();
+        for (_, label) in rooms.iter() {
+            self.modals.add_list_item(label).expect("failed radio room");
+        }
+        self.modals.add_list_item(t!("mtxchat.rooms.other", locales::LANG)).expect("failed radio other");
+        self.modals
+            .get_radiobutton(t!("mtxchat.rooms.title", locales::LANG))
+            .expect("failed radiobutton modal");
+        match self.modals.get_radio_index() {
+            Ok(index) if index < rooms.len() => self.room_select(&rooms[index].0),
+            Ok(_) => {
+                if let Some(room_id) = self.get_room_id() {
+                    self.room_select(&room_id);
+                }
+            }
+            Err(e) => log::warn!("failed to pick a room: {:?}", e),
+        }
+    }
+
+    /// Shows a room in the chat ui, and marks it read
+    pub fn room_select(&mut self, room_id: &str) {
+        self.set_debug(ROOM_ID_KEY, room_id);
+        let alias = self.room_alias();
+        let (dialogue, name, last_event) = {
+            let mut rooms = self.rooms.lock().unwrap();
+            let dialogue = rooms.dialogue(room_id, alias.as_deref());
+            rooms.set_current(room_id);
+            rooms.save(&self.pddb);
+            let room = rooms.get(room_id);
+            (dialogue, room.map(|r| r.display_name()), room.and_then(|r| r.last_event.clone()))
+        };
+        self.dialogue_set(Some(&dialogue));
+        if let Some(name) = name {
+            self.chat.set_status_idle_text(&name);
+        }
+        if let (true, Some(token), Some(user_domain), Some(event_id)) =
+            (self.logged_in, &self.token, &self.user_domain, last_event)
+        {
+            let mut url = Url::parse("https://matrix.org").unwrap();
+            url.set_host(Some(user_domain)).expect("failed to set host");
+            web::read_markers(&mut url, room_id, &event_id, token, &self.client);
+        }
     }
 
     pub fn dialogue_set(&self, room_alias: Option<&str>) {
@@ -466,32 +569,24 @@ impl<'a> MtxChat<'a> {
     pub fn help(&self) { self.chat.help(); }
 
     pub fn get_filter(&mut self) -> bool {
-        let log_entry = match (
-            &self.filter,
-            &self.logged_in,
-            &self.token,
-            &self.user_id,
-            &self.user_domain,
-            &self.room_id,
-        ) {
-            (Some(_filter), _, _, _, _, _) => "filter already set",
-            (_, true, Some(token), Some(user_id), Some(user_domain), Some(room_id)) => {
+        let log_entry = match (&self.filter, &self.logged_in, &self.token, &self.user_id, &self.user_domain) {
+            (Some(_filter), _, _, _, _) => "filter already set",
+            (_, true, Some(token), Some(user_id), Some(user_domain)) => {
                 let mut url = Url::parse("https://matrix.org").unwrap();
                 url.set_host(Some(user_domain)).expect("failed to set host");
-                log::info!("get_filter {} : {} : {} : {}", &user_id, &url.as_str(), &room_id, &token);
-                if let Some(new_filter) =
-                    web::get_filter(&user_id, &mut url, &room_id, &token, &mut self.agent)
-                {
+                log::info!("get_filter {} : {} : {}", &user_id, &url.as_str(), &token);
+                if let Some(new_filter) = web::get_filter(&user_id, &mut url, &token, &self.client) {
+                    // a full sync under the new filter fetches the room state it asks for
+                    self.unset_debug(SINCE_KEY);
                     if self.set_debug(FILTER_KEY, &new_filter) { "set filter" } else { "failed to set" }
                 } else {
                     "failed to get filter"
                 }
             }
-            (_, false, _, _, _, _) => "Not logged in",
-            (_, _, None, _, _, _) => "No token set",
-            (_, _, _, None, _, _) => "No user id set",
-            (_, _, _, _, None, _) => "No user domain set",
-            (_, _, _, _, _, None) => "No room id set",
+            (_, false, _, _, _) => "Not logged in",
+            (_, _, None, _, _) => "No token set",
+            (_, _, _, None, _) => "No user id set",
+            (_, _, _, _, None) => "No user domain set",
         };
         log::warn!("{log_entry}");
         self.filter.is_some()
@@ -499,44 +594,51 @@ impl<'a> MtxChat<'a> {
 
     pub fn listen(&mut self) {
         self.get_filter();
-        let log_entry = match (
-            self.listening,
-            self.logged_in,
-            &self.token,
-            &self.room_id,
-            &self.filter,
-            &self.room_alias(),
-        ) {
-            (false, true, Some(token), Some(room_id), Some(filter), Some(room_alias)) => {
+        let log_entry = match (self.listening, self.logged_in, &self.token, &self.user_domain, &self.filter) {
+            (false, true, Some(token), Some(user_domain), Some(filter)) => {
                 self.listening = true;
+                self.listen_stop = Arc::new(AtomicBool::new(false));
                 std::thread::spawn({
+                    // all the joined rooms are synced from the user's own server
                     let mut url = Url::parse("https://matrix.org").unwrap();
-                    if let Ok(host) = self.get(ROOM_DOMAIN_KEY) {
-                        url.set_host(host.as_deref()).expect("failed to set host");
-                    }
+                    url.set_host(Some(user_domain)).expect("failed to set host");
                     let token = token.clone();
-                    let room_id = room_id.clone();
+                    let user_name = self.user_name.clone().unwrap_or_default();
+                    // the listen thread keeps SINCE_KEY up to date
+                    self.since = self.get(SINCE_KEY).unwrap_or(None);
                     let since = self.since.clone();
                     let filter = filter.clone();
-                    let room_alias = room_alias.clone();
                     let chat_cid = self.chat.cid().clone();
+                    let rooms = self.rooms.clone();
+                    let crypto = self.crypto.clone();
+                    let stop = self.listen_stop.clone();
                     move || {
-                        listen(&mut url, &token, &room_id, since.as_deref(), &filter, &room_alias, chat_cid);
+                        listen(
+                            &mut url,
+                            &token,
+                            &user_name,
+                            since.as_deref(),
+                            &filter,
+                            chat_cid,
+                            rooms,
+                            crypto,
+                            stop,
+                        );
                     }
                 });
                 "Started listening"
             }
-            (true, _, _, _, _, _) => "Already listening",
-            (_, false, _, _, _, _) => "Not logged in",
-            (_, _, None, _, _, _) => "No token set",
-            (_, _, _, None, _, _) => "No room id set",
-            (_, _, _, _, None, _) => "No filter set",
-            (_, _, _, _, _, None) => "No room alias set",
+            (true, _, _, _, _) => "Already listening",
+            (_, false, _, _, _) => "Not logged in",
+            (_, _, None, _, _) => "No token set",
+            (_, _, _, None, _) => "No user domain set",
+            (_, _, _, _, None) => "No filter set",
         };
         log::info!("{log_entry}");
     }
 
     pub fn listen_over(&mut self, since: &str) {
+        self.listen_stop.store(true, Ordering::SeqCst);
         self.listening = false;
         log::info!("Stopped listening");
         if since.len() > 0 {
@@ -564,11 +666,40 @@ impl<'a> MtxChat<'a> {
                 log::info!("txn_id = {}", txn_id);
                 let mut url = Url::parse("https://matrix.org").unwrap();
                 url.set_host(Some(user_domain)).expect("failed to set host");
+                // an encrypted room must never get the message in the clear
+                let encrypted = match &self.crypto {
+                    Some(crypto) => {
+                        let mut crypto = crypto.lock().unwrap();
+                        if crypto.room_encrypted(room_id) {
+                            self.chat.set_status_text(t!("mtxchat.busy.encrypting", locales::LANG));
+                            Some(crypto.encrypt(room_id, text))
+                        } else {
+                            None
+                        }
+                    }
+                    None => None,
+                };
                 let mut success = false;
-                for _ in 0..SEND_RETRIES {
-                    if web::send_message(&mut url, &room_id, &text, &txn_id, token, &mut self.agent) {
-                        success = true;
-                        break;
+                match encrypted {
+                    Some(Some(content)) => {
+                        for _ in 0..SEND_RETRIES {
+                            if web::send_encrypted(&mut url, &room_id, &content, &txn_id, token, &self.client)
+                            {
+                                success = true;
+                                break;
+                            }
+                        }
+                    }
+                    Some(None) => {
+                        self.chat.set_status_text(t!("mtxchat.e2ee.failed", locales::LANG));
+                    }
+                    None => {
+                        for _ in 0..SEND_RETRIES {
+                            if web::send_message(&mut url, &room_id, &text, &txn_id, token, &self.client) {
+                                success = true;
+                                break;
+                            }
+                        }
                     }
                 }
                 let r = if success { "SENT" } else { "FAILED TO SEND" };
@@ -595,7 +726,7 @@ impl<'a> MtxChat<'a> {
 
         if let Some(conf) = self.netmgr.get_ipv4_config() {
             if conf.dhcp == com_rs::DhcpState::Bound {
-                return true;
+                return self.online();
             }
         }
 
@@ -608,7 +739,7 @@ impl<'a> MtxChat<'a> {
                 if let Some(conf) = self.netmgr.get_ipv4_config() {
                     if conf.dhcp == com_rs::DhcpState::Bound {
                         self.chat.set_busy_state(false);
-                        return true;
+                        return self.online();
                     }
                 }
                 self.tt.sleep_ms(1000).unwrap();
@@ -620,6 +751,26 @@ impl<'a> MtxChat<'a> {
         false
     }
 
+    // returns false, with an explanation, if the network is behind a captive portal,
+    // as the server connection would otherwise fail without a meaningful error
+    //
+    fn online(&self) -> bool {
+        match self.netmgr.connectivity() {
+            Ok(net::Connectivity { state: net::ConnectivityState::Captive, portal }) => {
+                let url = portal.map(|p| p.to_string()).unwrap_or_default();
+                self.modals
+                    .show_notification(
+                        &t!("mtxchat.wifi.captive", locales::LANG).replace("{url}", &url),
+                        None,
+                    )
+                    .unwrap();
+                self.chat.set_status_text(t!("mtxchat.wifi.captive_status", locales::LANG));
+                false
+            }
+            _ => true,
+        }
+    }
+
     // returns true if "Connect to WiFi?" yes option is chosen
     //
     fn wifi_try_modal(&self) -> bool {
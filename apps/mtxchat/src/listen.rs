@@ -1,62 +1,242 @@
-use std::sync::Arc;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use chat::ChatOp;
+use http_client::Client;
+use llio::{Llio, VibePattern};
 use locales::t;
-use tls::xtls::TlsConnector;
+use modals::Modals;
 use url::Url;
 use xous::CID;
 use xous_ipc::Buffer;
 
-use crate::{get_username, web, MTX_LONG_TIMEOUT_MS};
+use crate::crypto::{Crypto, SasEvent};
+use crate::rooms::Rooms;
+use crate::{get_username, web, MTXCHAT_STATE, MTX_LONG_TIMEOUT_MS, SINCE_KEY};
 
+const RETRY_MIN_MS: usize = 2_000;
+const RETRY_MAX_MS: usize = 60_000;
+
+// Syncs until `stop` is set, posting the messages of every joined room to its Dialogue in the
+// chat ui and handing room keys and verification steps to `crypto`. The sync must keep going for
+// those to arrive at all.
 pub fn listen(
     url: &mut Url,
     token: &str,
-    room_id: &str,
+    user_name: &str,
     since: Option<&str>,
     filter: &str,
-    dialogue_id: &str,
     chat_cid: CID,
+    rooms: Arc<Mutex<Rooms>>,
+    crypto: Option<Arc<Mutex<Crypto>>>,
+    stop: Arc<AtomicBool>,
 ) {
-    log::info!("client_sync for {} ms...", MTX_LONG_TIMEOUT_MS);
+    let xns = xous_names::XousNames::new().unwrap();
+    let modals = Modals::new(&xns).expect("can't connect to Modals server");
+    let llio = Llio::new(&xns);
+    let tt = ticktimer_server::Ticktimer::new().unwrap();
+    let pddb = pddb::Pddb::new();
+    let client = Client::new();
+    let mut since = since.map(|s| s.to_string());
+    let mut retry_ms = RETRY_MIN_MS;
+    while !stop.load(Ordering::SeqCst) {
+        log::info!("client_sync for {} ms...", MTX_LONG_TIMEOUT_MS);
+        let sync = match web::client_sync(url, filter, since.as_deref(), MTX_LONG_TIMEOUT_MS, &token, &client)
+        {
+            Some(sync) => sync,
+            None => {
+                tt.sleep_ms(retry_ms).unwrap();
+                retry_ms = (retry_ms * 2).min(RETRY_MAX_MS);
+                continue;
+            }
+        };
+        retry_ms = RETRY_MIN_MS;
 
-    let mut agent = ureq::builder().tls_connector(Arc::new(TlsConnector {})).build();
-    if let Some((_since, events)) =
-        web::client_sync(url, filter, since, MTX_LONG_TIMEOUT_MS, &room_id, &token, &mut agent)
-    {
-        // TODO utilize "since"
-        // and you probably want to have a look at Dialogue::MAX_BYTES
+        // the keys come first, as the messages of this same sync may need them
+        let mut verifications = Vec::new();
+        if let Some(crypto) = &crypto {
+            let mut crypto = crypto.lock().unwrap();
+            crypto.devices_changed(&sync.changed);
+            if let Some(count) = sync.one_time_keys {
+                crypto.replenish_one_time_keys(count);
+            }
+            verifications = crypto.to_device(&sync.to_device);
+        }
+        if stop.load(Ordering::SeqCst) {
+            // logged out, or quitting
+            break;
+        }
 
         // TODO resolve suspected race condition
         // This progress modal is masking a bug by slowing the loop down
         // Precursor "Guru Mediation" `voilated: nonNull::new_unchecked`
-        chat::cf_set_status_text(chat_cid, t!("mtxchat.busy.rx_events", locales::LANG));
-        chat::cf_set_busy_state(chat_cid, true);
+        if sync.rooms.iter().any(|room| !room.msgs.is_empty()) {
+            chat::cf_set_status_text(chat_cid, t!("mtxchat.busy.rx_events", locales::LANG));
+            chat::cf_set_busy_state(chat_cid, true);
+        }
         let mut event_count = 0;
-        for event in events {
-            let sender = event.sender.unwrap_or("anon".to_string());
-            let body = event.body.unwrap_or("...".to_string());
-            let post = chat::Post {
-                dialogue_id: xous_ipc::String::from_str(&dialogue_id),
-                author: xous_ipc::String::from_str(&get_username(&sender)),
-                timestamp: event.ts.unwrap_or(0),
-                text: xous_ipc::String::from_str(&body),
-                attach_url: None,
+        let mut mentioned: Vec<String> = Vec::new();
+        for room in sync.rooms.iter() {
+            let (dialogue_id, mut mention) = {
+                let mut rooms = rooms.lock().unwrap();
+                (rooms.dialogue(&room.room_id, room.alias.as_deref()), rooms.update(room))
+            };
+            for event in room.msgs.iter() {
+                let sender = event.sender.clone().unwrap_or("anon".to_string());
+                let body = match (&event.encrypted, &crypto, &event.sender, &event.event_id) {
+                    (Some(content), Some(crypto), Some(sender), Some(event_id)) => {
+                        crypto.lock().unwrap().decrypt(&room.room_id, sender, event_id, content)
+                    }
+                    (Some(_), ..) => None,
+                    (None, ..) => event.body.clone(),
+                };
+                let body = match (body, &event.encrypted) {
+                    (Some(body), Some(_)) => {
+                        // the server can't see a mention in an encrypted message
+                        if mentions(&body, user_name) && !get_username(&sender).eq(user_name) {
+                            rooms.lock().unwrap().mention(&room.room_id);
+                            mention = true;
+                        }
+                        body
+                    }
+                    (Some(body), None) => body,
+                    (None, Some(_)) => t!("mtxchat.e2ee.undecryptable", locales::LANG).to_string(),
+                    (None, None) => "...".to_string(),
+                };
+                post(chat_cid, &dialogue_id, &get_username(&sender), event.ts.unwrap_or(0), &body);
+                event_count += 1;
+                chat::cf_set_status_text(
+                    chat_cid,
+                    &format!("{} {}", t!("mtxchat.busy.rx_events", locales::LANG), event_count),
+                );
+            }
+            let (current, name) = {
+                let rooms = rooms.lock().unwrap();
+                (rooms.is_current(&room.room_id), rooms.get(&room.room_id).map(|r| r.display_name()))
             };
-            match Buffer::into_buf(post) {
-                Ok(buf) => buf.send(chat_cid, ChatOp::PostAdd as u32).map(|_| ()),
-                Err(_) => Err(xous::Error::InternalError),
+            if current {
+                // what arrives in the room on screen has been read
+                if let Some(event_id) = &room.last_event {
+                    web::read_markers(url, &room.room_id, event_id, token, &client);
+                }
+            } else if mention {
+                mentioned.extend(name);
             }
-            .expect("failed to convert post into buffer");
-            event_count += 1;
+        }
+        {
+            let mut rooms = rooms.lock().unwrap();
+            for room_id in sync.left.iter() {
+                rooms.remove(room_id);
+            }
+            rooms.save(&pddb);
+        }
+        chat::cf_set_busy_state(chat_cid, false);
+        if event_count > 0 {
+            // trigger the chat ui to save the dialogues to the pddb
+            xous::send_message(
+                chat_cid,
+                xous::Message::new_scalar(ChatOp::DialogueSave as usize, 0, 0, 0, 0),
+            )
+            .expect("failed to send new inbound msgs");
+        }
+        if mentioned.len() > 0 {
+            llio.vibe(VibePattern::Double).ok();
             chat::cf_set_status_text(
                 chat_cid,
-                &format!("{} {}", t!("mtxchat.busy.rx_events", locales::LANG), event_count),
+                &t!("mtxchat.rooms.mention", locales::LANG).replace("{room}", &mentioned.join(", ")),
             );
         }
+
+        if let Some(crypto) = &crypto {
+            for event in verifications {
+                verify(event, crypto, &modals, chat_cid);
+            }
+        }
+
+        save_since(&pddb, &sync.next_batch);
+        since = Some(sync.next_batch);
+    }
+    log::info!("stopped listening");
+}
+
+// true if `body` mentions the user by name
+fn mentions(body: &str, user_name: &str) -> bool {
+    user_name.len() > 0 && body.to_lowercase().contains(&user_name.to_lowercase())
+}
+
+fn post(chat_cid: CID, dialogue_id: &str, author: &str, timestamp: u64, text: &str) {
+    let post = chat::Post {
+        dialogue_id: xous_ipc::String::from_str(&dialogue_id),
+        author: xous_ipc::String::from_str(author),
+        timestamp,
+        text: xous_ipc::String::from_str(text),
+        attach_url: None,
+    };
+    match Buffer::into_buf(post) {
+        Ok(buf) => buf.send(chat_cid, ChatOp::PostAdd as u32).map(|_| ()),
+        Err(_) => Err(xous::Error::InternalError),
+    }
+    .expect("failed to convert post into buffer");
+}
+
+// Shows the emoji of a verification in the room on screen, and asks whether the other device shows
+// the same
+fn verify(event: SasEvent, crypto: &Mutex<Crypto>, modals: &Modals, chat_cid: CID) {
+    match event {
+        SasEvent::Compare { transaction_id, user_id, device_id, emoji } => {
+            let text = emoji.iter().map(|(e, name)| format!("{} {}", e, name)).collect::<Vec<_>>().join("\n");
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            post(chat_cid, "", t!("mtxchat.verify.author", locales::LANG), timestamp, &text);
+            let prompt = t!("mtxchat.verify.compare", locales::LANG)
+                .replace("{device}", &format!("{} {}", user_id, device_id))
+                .replace("{emoji}", &text);
+            modals.add_list_item(t!("mtxchat.verify.match", locales::LANG)).expect("failed radio match");
+            modals
+                .add_list_item(t!("mtxchat.verify.mismatch", locales::LANG))
+                .expect("failed radio mismatch");
+            modals.get_radiobutton(&prompt).expect("failed radiobutton modal");
+            let matched = matches!(modals.get_radio_index(), Ok(0));
+            let next = crypto.lock().unwrap().sas_confirm(&transaction_id, matched);
+            if let Some(next) = next {
+                verify(next, crypto, modals, chat_cid);
+            }
+        }
+        SasEvent::Verified { user_id, device_id } => {
+            modals
+                .show_notification(
+                    &t!("mtxchat.verify.done", locales::LANG)
+                        .replace("{device}", &format!("{} {}", user_id, device_id)),
+                    None,
+                )
+                .expect("notification failed");
+        }
+        SasEvent::Cancelled { user_id, device_id, reason } => {
+            modals
+                .show_notification(
+                    &t!("mtxchat.verify.cancelled", locales::LANG)
+                        .replace("{device}", &format!("{} {}", user_id, device_id))
+                        .replace("{reason}", &reason),
+                    None,
+                )
+                .expect("notification failed");
+        }
+    }
+}
+
+fn save_since(pddb: &pddb::Pddb, since: &str) {
+    // delete key first to ensure data in a prior longer key is gone
+    pddb.delete_key(MTXCHAT_STATE, SINCE_KEY, None).ok();
+    match pddb.get(MTXCHAT_STATE, SINCE_KEY, None, true, true, None, None::<fn()>) {
+        Ok(mut pddb_key) => match pddb_key.write(since.as_bytes()) {
+            Ok(_) => {
+                pddb.sync().ok();
+            }
+            Err(e) => log::warn!("Error writing {}:{} {:?}", MTXCHAT_STATE, SINCE_KEY, e),
+        },
+        Err(e) => log::warn!("failed to set pddb {}:{}  {:?}", MTXCHAT_STATE, SINCE_KEY, e),
     }
-    chat::cf_set_busy_state(chat_cid, false);
-    // trigger the chat ui to save the dialogue to the pddb
-    xous::send_message(chat_cid, xous::Message::new_scalar(ChatOp::DialogueSave as usize, 0, 0, 0, 0))
-        .expect("failed to send new inbound msgs");
 }
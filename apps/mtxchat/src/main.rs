@@ -120,11 +120,8 @@ fn wrapped_main() -> ! {
                         }
                         Some(MenuOp::Noop) => {}
                         Some(MenuOp::Room) => {
-                            if let Some(room) = mtxchat.get_room_id() {
-                                mtxchat.listen_over("");
-                                mtxchat.dialogue_set(Some(room.as_str()));
-                                mtxchat.listen();
-                            }
+                            // every joined room is synced, so there is no need to restart listening
+                            mtxchat.rooms_modal();
                         }
                         _ => (),
                     }
@@ -0,0 +1,184 @@
+use std::io::{Read, Write};
+
+use pddb::Pddb;
+use serde::{Deserialize, Serialize};
+use ureq::serde_json;
+
+use crate::web::RoomSync;
+use crate::MTXCHAT_STATE;
+
+const ROOMS_KEY: &str = "_rooms";
+
+/// A joined room, as last seen in a sync
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Room {
+    pub room_id: String,
+    pub name: Option<String>,
+    pub alias: Option<String>,
+    /// the other members, which name a room without a name or alias
+    pub heroes: Vec<String>,
+    /// the pddb key of the room's Dialogue, which never changes once the room is first seen
+    pub dialogue: String,
+    /// notifications unread
+    pub unread: u64,
+    /// unread notifications which mention the user
+    pub highlight: u64,
+    /// the most recent event, which the read markers move up to
+    pub last_event: Option<String>,
+    /// timestamp of the most recent message, which orders the room list
+    pub last_ts: u64,
+}
+
+impl Room {
+    fn new(room_id: &str, alias: Option<&str>) -> Self {
+        Room {
+            room_id: room_id.to_string(),
+            alias: alias.map(|a| a.to_string()),
+            // Dialogues were keyed by room alias before there was more than one room
+            dialogue: alias.unwrap_or(room_id).to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        match (&self.name, &self.alias) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(alias)) => alias.to_string(),
+            (None, None) if self.heroes.len() > 0 => self.heroes.join(", "),
+            (None, None) => self.room_id.to_string(),
+        }
+    }
+
+    /// The room as listed in the room picker, with its unread count
+    pub fn label(&self) -> String {
+        match (self.unread, self.highlight) {
+            (0, _) => self.display_name(),
+            (unread, 0) => format!("{} ({})", self.display_name(), unread),
+            (unread, _) => format!("{} ({} @)", self.display_name(), unread),
+        }
+    }
+}
+
+/// The joined rooms, shared by the ui and the listen thread
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Rooms {
+    rooms: Vec<Room>,
+    /// the room_id of the room shown in the chat ui
+    #[serde(skip)]
+    current: Option<String>,
+}
+
+impl Rooms {
+    pub fn load(pddb: &Pddb) -> Self {
+        match pddb.get(MTXCHAT_STATE, ROOMS_KEY, None, true, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut bytes = Vec::new();
+                match pddb_key.read_to_end(&mut bytes) {
+                    Ok(_) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                        log::warn!("failed to deserialize {}:{} {:?}", MTXCHAT_STATE, ROOMS_KEY, e);
+                        Rooms::default()
+                    }),
+                    Err(e) => {
+                        log::warn!("failed to read {}:{} {:?}", MTXCHAT_STATE, ROOMS_KEY, e);
+                        Rooms::default()
+                    }
+                }
+            }
+            Err(_) => Rooms::default(),
+        }
+    }
+
+    pub fn save(&self, pddb: &Pddb) {
+        let bytes = match serde_json::to_vec(self) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::warn!("failed to serialize rooms: {:?}", e);
+                return;
+            }
+        };
+        // delete key first to ensure data in a prior longer key is gone
+        pddb.delete_key(MTXCHAT_STATE, ROOMS_KEY, None).ok();
+        match pddb.get(MTXCHAT_STATE, ROOMS_KEY, None, true, true, None, None::<fn()>) {
+            Ok(mut pddb_key) => match pddb_key.write(&bytes) {
+                Ok(_) => {
+                    pddb.sync().ok();
+                }
+                Err(e) => log::warn!("Error writing {}:{} {:?}", MTXCHAT_STATE, ROOMS_KEY, e),
+            },
+            Err(e) => log::warn!("failed to set pddb {}:{}  {:?}", MTXCHAT_STATE, ROOMS_KEY, e),
+        }
+    }
+
+    pub fn get(&self, room_id: &str) -> Option<&Room> { self.rooms.iter().find(|r| r.room_id.eq(room_id)) }
+
+    fn get_mut(&mut self, room_id: &str, alias: Option<&str>) -> &mut Room {
+        let index = match self.rooms.iter().position(|r| r.room_id.eq(room_id)) {
+            Some(index) => index,
+            None => {
+                self.rooms.push(Room::new(room_id, alias));
+                self.rooms.len() - 1
+            }
+        };
+        &mut self.rooms[index]
+    }
+
+    /// Returns the pddb key of the Dialogue of a room, adding the room if it is new
+    pub fn dialogue(&mut self, room_id: &str, alias: Option<&str>) -> String {
+        self.get_mut(room_id, alias).dialogue.clone()
+    }
+
+    /// Makes a room the one in the chat ui, which reads all of it
+    pub fn set_current(&mut self, room_id: &str) {
+        let room = self.get_mut(room_id, None);
+        room.unread = 0;
+        room.highlight = 0;
+        self.current = Some(room_id.to_string());
+    }
+
+    pub fn is_current(&self, room_id: &str) -> bool { self.current.as_deref() == Some(room_id) }
+
+    /// Updates a room from a sync, returning true if the sync brought new mentions of the user
+    pub fn update(&mut self, sync: &RoomSync) -> bool {
+        let current = self.is_current(&sync.room_id);
+        let room = self.get_mut(&sync.room_id, sync.alias.as_deref());
+        if sync.name.is_some() {
+            room.name = sync.name.clone();
+        }
+        if sync.alias.is_some() {
+            room.alias = sync.alias.clone();
+        }
+        if sync.heroes.len() > 0 {
+            room.heroes = sync.heroes.clone();
+        }
+        if sync.last_event.is_some() {
+            room.last_event = sync.last_event.clone();
+        }
+        room.last_ts = sync.msgs.iter().filter_map(|m| m.ts).fold(room.last_ts, u64::max);
+        let highlight = room.highlight;
+        if current {
+            // the read markers are moved up to what is on screen
+            room.unread = 0;
+            room.highlight = 0;
+        } else {
+            room.unread = sync.unread.unwrap_or(room.unread);
+            room.highlight = sync.highlight.unwrap_or(room.highlight);
+        }
+        room.highlight > highlight
+    }
+
+    /// Counts a mention the server could not see, in an encrypted message
+    pub fn mention(&mut self, room_id: &str) {
+        if !self.is_current(room_id) {
+            self.get_mut(room_id, None).highlight += 1;
+        }
+    }
+
+    pub fn remove(&mut self, room_id: &str) { self.rooms.retain(|r| r.room_id.ne(room_id)); }
+
+    /// The rooms, most recently active first
+    pub fn sorted(&self) -> Vec<&Room> {
+        let mut rooms: Vec<&Room> = self.rooms.iter().collect();
+        rooms.sort_by(|a, b| b.last_ts.cmp(&a.last_ts));
+        rooms
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
-use ureq::serde_json::{Map, Value};
-use ureq::{Agent, ErrorKind};
+use std::time::Duration;
+
+use http_client::{Client, Error};
+use ureq::serde_json::{json, Map, Value};
 use url::Url;
 
 use crate::Msg;
@@ -9,9 +11,16 @@ const ACCEPT: &str = "Accept";
 const ACCEPT_JSON: &str = "application/json";
 const AUTHORIZATION: &str = "Authorization";
 const BEARER: &str = "Bearer ";
+/// how long past its timeout a sync may take to come back
+const SYNC_GRACE: Duration = Duration::from_secs(15);
 
 pub const MTX_LOGIN_PASSWORD: &str = "m.login.password";
 const MTX_ID_USER: &str = "m.id.user";
+const MTX_DEVICE_NAME: &str = "Precursor mtxchat";
+const MTX_ROOM_MESSAGE: &str = "m.room.message";
+const MTX_ROOM_ENCRYPTED: &str = "m.room.encrypted";
+const MTX_ROOM_NAME: &str = "m.room.name";
+const MTX_ROOM_ALIAS: &str = "m.room.canonical_alias";
 
 pub fn get_username(user: &str) -> String {
     let i = match user.find('@') {
@@ -35,7 +44,7 @@ fn serialize<T: ?Sized + Serialize>(object: &T) -> Option<String> {
     }
 }
 
-pub fn handle_response(maybe_response: Result<ureq::Response, ureq::Error>) -> Option<Value> {
+pub fn handle_response(maybe_response: Result<ureq::Response, Error>) -> Option<Value> {
     match maybe_response {
         Ok(response) => {
             if let Ok(body) = response.into_json() {
@@ -45,88 +54,81 @@ pub fn handle_response(maybe_response: Result<ureq::Response, ureq::Error>) -> O
                 None
             }
         }
-        Err(ureq::Error::Status(code, response)) => {
+        Err(Error::Status(code, err_body)) => {
             // the server returned an unexpected status code (such as 400, 500 etc)
-            let err_body = response.into_string().unwrap();
             log::info!("ERROR code {} err_body = {}", code, err_body);
             None
         }
-        Err(ureq::Error::Transport(kind)) => {
-            match kind.kind() {
-                ErrorKind::ConnectionFailed => log::warn!("TLS failure"),
-                _ => log::info!("Transport error: {:?}", kind),
-            };
+        Err(e) => {
+            log::warn!("{}", e);
             None
         }
     }
 }
 
-pub fn get_json(url: &Url, agent: &mut Agent) -> Result<ureq::Response, ureq::Error> {
-    agent.get(&url.as_str()).set(ACCEPT, ACCEPT_JSON).call()
+pub fn get_json(url: &Url, client: &Client) -> Result<ureq::Response, Error> {
+    client.send(client.request("GET", url.as_str()).set(ACCEPT, ACCEPT_JSON), None)
 }
 
-pub fn get_json_auth(url: &Url, token: &str, agent: &mut Agent) -> Result<ureq::Response, ureq::Error> {
-    let mut authorization = String::from(BEARER);
-    authorization.push_str(token);
-    agent.get(&url.as_str()).set(ACCEPT, ACCEPT_JSON).set(AUTHORIZATION, &authorization).call()
+pub fn get_json_auth(url: &Url, token: &str, client: &Client) -> Result<ureq::Response, Error> {
+    client.send(auth_request(client, "GET", url, token), None)
 }
 
-pub fn post_string(url: &Url, request_body: &str, agent: &mut Agent) -> Result<ureq::Response, ureq::Error> {
-    agent.post(&url.as_str()).set(ACCEPT, ACCEPT_JSON).send_string(request_body)
+pub fn post_string(url: &Url, request_body: &str, client: &Client) -> Result<ureq::Response, Error> {
+    client.send(client.request("POST", url.as_str()).set(ACCEPT, ACCEPT_JSON), Some(request_body.as_bytes()))
 }
 
 pub fn post_string_auth(
     url: &Url,
     request_body: &str,
     token: &str,
-    agent: &mut Agent,
-) -> Result<ureq::Response, ureq::Error> {
-    let mut authorization = String::from(BEARER);
-    authorization.push_str(token);
-    agent
-        .post(&url.as_str())
-        .set(ACCEPT, ACCEPT_JSON)
-        .set(AUTHORIZATION, &authorization)
-        .send_string(request_body)
+    client: &Client,
+) -> Result<ureq::Response, Error> {
+    client.send(auth_request(client, "POST", url, token), Some(request_body.as_bytes()))
 }
 
 pub fn put_string_auth(
     url: &Url,
     request_body: &str,
     token: &str,
-    agent: &mut Agent,
-) -> Result<ureq::Response, ureq::Error> {
+    client: &Client,
+) -> Result<ureq::Response, Error> {
+    client.send(auth_request(client, "PUT", url, token), Some(request_body.as_bytes()))
+}
+
+fn auth_request(client: &Client, method: &str, url: &Url, token: &str) -> ureq::Request {
     let mut authorization = String::from(BEARER);
     authorization.push_str(token);
-    agent
-        .put(&url.as_str())
-        .set(ACCEPT, ACCEPT_JSON)
-        .set(AUTHORIZATION, &authorization)
-        .send_string(request_body)
+    client.request(method, url.as_str()).set(ACCEPT, ACCEPT_JSON).set(AUTHORIZATION, &authorization)
 }
 
 // --------------------------------
 
-pub fn whoami(url: &mut Url, token: &str, agent: &mut Agent) -> Option<String> {
+// returns the user_id, and the device_id if the server says
+pub fn whoami(url: &mut Url, token: &str, client: &Client) -> Option<(String, Option<String>)> {
     url.set_path("_matrix/client/r0/account/whoami");
-    if let Some(value) = handle_response(get_json_auth(&url, token, agent)) {
+    if let Some(value) = handle_response(get_json_auth(&url, token, client)) {
         if let Value::Object(body) = value {
-            if let Some(Value::String(device_id)) = body.get("device_id") {
-                log::info!("device_id = {}", device_id);
-            }
+            let device_id = match body.get("device_id") {
+                Some(Value::String(device_id)) => {
+                    log::info!("device_id = {}", device_id);
+                    Some(device_id.to_string())
+                }
+                _ => None,
+            };
             if let Some(Value::String(user_id)) = body.get("user_id") {
                 log::info!("user_id = {}", user_id);
-                return Some(user_id.to_string());
+                return Some((user_id.to_string(), device_id));
             }
         }
     }
     None
 }
 
-pub fn get_login_type(url: &mut Url, agent: &mut Agent) -> bool {
+pub fn get_login_type(url: &mut Url, client: &Client) -> bool {
     url.set_path("_matrix/client/r0/login");
     let mut found = false;
-    if let Some(value) = handle_response(get_json(&url, agent)) {
+    if let Some(value) = handle_response(get_json(&url, client)) {
         if let Value::Object(body) = value {
             if let Some(Value::Array(flows)) = body.get("flows") {
                 for flow in flows.iter() {
@@ -156,25 +158,42 @@ struct AuthRequest {
     type_: String,
     identifier: AuthIdentifier,
     password: String,
+    // logging in again as the same device keeps its encryption keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_id: Option<String>,
+    initial_device_display_name: String,
 }
 
 impl AuthRequest {
-    pub fn new(user: &str, password: &str) -> Self {
+    pub fn new(user: &str, password: &str, device_id: Option<&str>) -> Self {
         let identifier = AuthIdentifier { type_: MTX_ID_USER.to_string(), user: user.to_string() };
-        AuthRequest { type_: MTX_LOGIN_PASSWORD.to_string(), identifier, password: password.to_string() }
+        AuthRequest {
+            type_: MTX_LOGIN_PASSWORD.to_string(),
+            identifier,
+            password: password.to_string(),
+            device_id: device_id.map(|d| d.to_string()),
+            initial_device_display_name: MTX_DEVICE_NAME.to_string(),
+        }
     }
 }
 
-// fn authenticate_user() -> Result<String, ureq::Error> {
-pub fn authenticate_user(url: &mut Url, user: &str, password: &str, agent: &mut Agent) -> Option<String> {
-    let mut maybe_token: Option<String> = None;
+// returns the access_token, and the device_id if the server says
+pub fn authenticate_user(
+    url: &mut Url,
+    user: &str,
+    password: &str,
+    device_id: Option<&str>,
+    client: &Client,
+) -> Option<(String, Option<String>)> {
+    let mut maybe_token: Option<(String, Option<String>)> = None;
     url.set_path("_matrix/client/r0/login");
-    let auth_request = AuthRequest::new(user, password);
+    let auth_request = AuthRequest::new(user, password, device_id);
     if let Some(request_body) = serialize(&auth_request) {
-        if let Some(value) = handle_response(post_string(&url, &request_body, agent)) {
+        if let Some(value) = handle_response(post_string(&url, &request_body, client)) {
             if let Value::Object(body) = value {
                 if let Some(Value::String(access_token)) = body.get("access_token") {
-                    maybe_token = Some(access_token.to_string())
+                    let device_id = body.get("device_id").and_then(|d| d.as_str()).map(|d| d.to_string());
+                    maybe_token = Some((access_token.to_string(), device_id))
                 }
             }
         }
@@ -182,12 +201,12 @@ pub fn authenticate_user(url: &mut Url, user: &str, password: &str, agent: &mut
     maybe_token
 }
 
-pub fn get_room_id(url: &mut Url, room_server: &str, token: &str, agent: &mut Agent) -> Option<String> {
+pub fn get_room_id(url: &mut Url, room_server: &str, token: &str, client: &Client) -> Option<String> {
     let mut path = String::from("_matrix/client/v3/directory/room/");
     path.push_str(&room_server);
     url.set_path(&path);
     log::info!("get_room_id = {}", url);
-    if let Some(value) = handle_response(get_json_auth(&url, token, agent)) {
+    if let Some(value) = handle_response(get_json_auth(&url, token, client)) {
         if let Value::Object(body) = value {
             if let Some(Value::String(room_id)) = body.get("room_id") {
                 Some(room_id.to_string())
@@ -223,16 +242,12 @@ impl EventFilter {
 struct RoomEventFilter {
     limit: i32,
     types: Vec<String>,
-    rooms: Vec<String>,
 }
 
 impl RoomEventFilter {
-    pub fn new(limit: i32, room_id: &str, type_0: &str) -> Self {
-        let mut types = Vec::new();
-        types.push(type_0.to_string());
-        let mut rooms: Vec<String> = Vec::new();
-        rooms.push(room_id.to_string());
-        RoomEventFilter { limit, types, rooms }
+    pub fn new(limit: i32, types: &[&str]) -> Self {
+        let types = types.iter().map(|t| t.to_string()).collect();
+        RoomEventFilter { limit, types }
     }
 }
 
@@ -240,20 +255,19 @@ impl RoomEventFilter {
 struct RoomFilter {
     account_data: EventFilter, // Should be RoomEventFilter
     ephemeral: EventFilter,
-    rooms: Vec<String>,
-    state: EventFilter, // Should be StateFilter
+    state: RoomEventFilter, // Should be StateFilter
     timeline: RoomEventFilter,
 }
 
 impl RoomFilter {
-    pub fn new(room_id: &str) -> Self {
+    pub fn new() -> Self {
         let account_data = EventFilter::new(0);
         let ephemeral = EventFilter::new(0);
-        let mut rooms: Vec<String> = Vec::new();
-        rooms.push(room_id.to_string());
-        let state = EventFilter::new(0);
-        let timeline = RoomEventFilter::new(10, room_id, "m.room.message");
-        RoomFilter { account_data, ephemeral, rooms, state, timeline }
+        // just enough state to name the rooms
+        let state = RoomEventFilter::new(10, &[MTX_ROOM_NAME, MTX_ROOM_ALIAS]);
+        let timeline =
+            RoomEventFilter::new(10, &[MTX_ROOM_MESSAGE, MTX_ROOM_ENCRYPTED, MTX_ROOM_NAME, MTX_ROOM_ALIAS]);
+        RoomFilter { account_data, ephemeral, state, timeline }
     }
 }
 
@@ -266,34 +280,30 @@ struct FilterRequest {
 }
 
 impl FilterRequest {
-    pub fn new(room_id: &str) -> Self {
+    pub fn new() -> Self {
         let account_data = EventFilter::new(0);
         let mut event_fields: Vec<String> = Vec::new();
         event_fields.push("type".to_string());
+        event_fields.push("event_id".to_string());
         event_fields.push("sender".to_string());
-        event_fields.push("content.body".to_string());
+        // all of it, as encrypted events carry their ciphertext there
+        event_fields.push("content".to_string());
         event_fields.push("origin_server_ts".to_string());
         let presence = EventFilter::new(0);
-        let room = RoomFilter::new(room_id);
+        let room = RoomFilter::new();
         FilterRequest { account_data, event_fields, presence, room }
     }
 }
 
-pub fn get_filter(
-    user: &str,
-    url: &mut Url,
-    room_id: &str,
-    token: &str,
-    agent: &mut Agent,
-) -> Option<String> {
+pub fn get_filter(user: &str, url: &mut Url, token: &str, client: &Client) -> Option<String> {
     let mut path = String::from("_matrix/client/v3/user/");
     path.push_str(&user);
     path.push_str("/filter");
     url.set_path(&path);
     log::info!("get_filter = {}", url.as_str());
-    let filter_request = FilterRequest::new(room_id);
+    let filter_request = FilterRequest::new();
     if let Some(request_body) = serialize(&filter_request) {
-        if let Some(value) = handle_response(post_string_auth(url, &request_body, token, agent)) {
+        if let Some(value) = handle_response(post_string_auth(url, &request_body, token, client)) {
             if let Value::Object(body) = value {
                 if let Some(Value::String(filter_id)) = body.get("filter_id") {
                     log::info!("filter_id = {}", filter_id);
@@ -316,59 +326,167 @@ pub fn get_filter(
     }
 }
 
-fn get_messages(body: Map<String, Value>, room_id: &str) -> Vec<Msg> {
-    log::info!("heap usage: {}", crate::heap_usage());
+fn get_messages(events: &[Value]) -> Vec<Msg> {
     let mut msgs = Vec::<Msg>::new();
-    if let Some(Value::Object(rooms)) = body.get("rooms") {
-        if let Some(Value::Object(join)) = rooms.get("join") {
-            if let Some(Value::Object(room)) = join.get(room_id) {
-                if let Some(Value::Object(timeline)) = room.get("timeline") {
-                    if let Some(Value::Array(events)) = timeline.get("events") {
-                        for event in events.iter() {
-                            log::trace!("{:?}", event);
-                            if let Some(Value::String(type_)) = event.get("type") {
-                                if type_.eq("m.room.message") {
-                                    msgs.push(Msg {
-                                        type_: type_.to_string(),
-                                        body: event
-                                            .get("content")
-                                            .map(|c| c.get("body").map(|b| b.to_string()))
-                                            .flatten(),
-                                        sender: event.get("sender").map(|s| s.to_string()),
-                                        ts: event.get("origin_server_ts").map(|t| t.as_u64()).flatten(),
-                                    });
-                                }
-                            }
-                        } // event
-                    }
-                }
+    for event in events.iter() {
+        log::trace!("{:?}", event);
+        if let Some(Value::String(type_)) = event.get("type") {
+            if type_.eq(MTX_ROOM_MESSAGE) {
+                msgs.push(Msg {
+                    type_: type_.to_string(),
+                    body: event.get("content").map(|c| c.get("body").map(|b| b.to_string())).flatten(),
+                    sender: event.get("sender").and_then(|s| s.as_str()).map(|s| s.to_string()),
+                    ts: event.get("origin_server_ts").map(|t| t.as_u64()).flatten(),
+                    event_id: event.get("event_id").and_then(|e| e.as_str()).map(|e| e.to_string()),
+                    encrypted: None,
+                });
+            } else if type_.eq(MTX_ROOM_ENCRYPTED) {
+                msgs.push(Msg {
+                    type_: type_.to_string(),
+                    body: None,
+                    sender: event.get("sender").and_then(|s| s.as_str()).map(|s| s.to_string()),
+                    ts: event.get("origin_server_ts").map(|t| t.as_u64()).flatten(),
+                    event_id: event.get("event_id").and_then(|e| e.as_str()).map(|e| e.to_string()),
+                    encrypted: event.get("content").cloned(),
+                });
             }
         }
-    }
+    } // event
     msgs
 }
 
+/// A joined room, as it changed in a sync
+pub struct RoomSync {
+    pub room_id: String,
+    pub msgs: Vec<Msg>,
+    /// the `m.room.name`, if it was in this sync
+    pub name: Option<String>,
+    /// the `m.room.canonical_alias`, if it was in this sync
+    pub alias: Option<String>,
+    /// the other members, which name a room without an `m.room.name`
+    pub heroes: Vec<String>,
+    /// notifications the server counts as unread
+    pub unread: Option<u64>,
+    /// unread notifications which mention the user
+    pub highlight: Option<u64>,
+    /// the most recent timeline event
+    pub last_event: Option<String>,
+}
+
+fn get_room(room_id: &str, room: &Map<String, Value>) -> RoomSync {
+    let mut events: Vec<Value> = Vec::new();
+    for section in ["state", "timeline"] {
+        if let Some(Value::Array(section_events)) = room.get(section).and_then(|s| s.get("events")) {
+            events.extend(section_events.iter().cloned());
+        }
+    }
+    let mut name = None;
+    let mut alias = None;
+    for event in events.iter() {
+        let content = event.get("content");
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some(MTX_ROOM_NAME) => {
+                name = content.and_then(|c| c.get("name")).and_then(|n| n.as_str()).map(|n| n.to_string())
+            }
+            Some(MTX_ROOM_ALIAS) => {
+                alias = content.and_then(|c| c.get("alias")).and_then(|a| a.as_str()).map(|a| a.to_string())
+            }
+            _ => (),
+        }
+    }
+    let timeline = match room.get("timeline").and_then(|t| t.get("events")) {
+        Some(Value::Array(timeline)) => timeline.as_slice(),
+        _ => &[],
+    };
+    let heroes = match room.get("summary").and_then(|s| s.get("m.heroes")) {
+        Some(Value::Array(heroes)) => heroes.iter().filter_map(|h| h.as_str()).map(get_username).collect(),
+        _ => Vec::new(),
+    };
+    let counts = room.get("unread_notifications");
+    RoomSync {
+        room_id: room_id.to_string(),
+        msgs: get_messages(timeline),
+        name,
+        alias,
+        heroes,
+        unread: counts.and_then(|c| c.get("notification_count")).and_then(|c| c.as_u64()),
+        highlight: counts.and_then(|c| c.get("highlight_count")).and_then(|c| c.as_u64()),
+        last_event: timeline
+            .last()
+            .and_then(|e| e.get("event_id"))
+            .and_then(|e| e.as_str())
+            .map(|e| e.to_string()),
+    }
+}
+
+pub struct SyncResponse {
+    pub next_batch: String,
+    /// the joined rooms with something new
+    pub rooms: Vec<RoomSync>,
+    /// the rooms which the user has left
+    pub left: Vec<String>,
+    /// to-device events, which carry room keys and verification
+    pub to_device: Vec<Value>,
+    /// users whose devices changed
+    pub changed: Vec<String>,
+    /// signed_curve25519 one-time keys the server has left for this device
+    pub one_time_keys: Option<u64>,
+}
+
+fn get_sync(body: &Map<String, Value>, next_batch: &str) -> SyncResponse {
+    log::info!("heap usage: {}", crate::heap_usage());
+    let rooms = match body.get("rooms").and_then(|r| r.get("join")) {
+        Some(Value::Object(join)) => join
+            .iter()
+            .filter_map(|(room_id, room)| room.as_object().map(|room| get_room(room_id, room)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let left = match body.get("rooms").and_then(|r| r.get("leave")) {
+        Some(Value::Object(leave)) => leave.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    let to_device = match body.get("to_device").and_then(|t| t.get("events")) {
+        Some(Value::Array(events)) => events.clone(),
+        _ => Vec::new(),
+    };
+    let changed = match body.get("device_lists").and_then(|d| d.get("changed")) {
+        Some(Value::Array(users)) => users.iter().filter_map(|u| u.as_str()).map(|u| u.to_string()).collect(),
+        _ => Vec::new(),
+    };
+    let one_time_keys = body
+        .get("device_one_time_keys_count")
+        .and_then(|c| c.get("signed_curve25519"))
+        .and_then(|c| c.as_u64())
+        .or_else(|| body.get("device_one_time_keys_count").map(|_| 0));
+    SyncResponse { next_batch: next_batch.to_string(), rooms, left, to_device, changed, one_time_keys }
+}
+
 pub fn client_sync(
     url: &mut Url,
     filter: &str,
     since: Option<&str>,
     timeout: i32,
-    room_id: &str,
     token: &str,
-    agent: &mut Agent,
-) -> Option<(String, Vec<Msg>)> {
+    client: &Client,
+) -> Option<SyncResponse> {
     log::info!("heap usage: {}", crate::heap_usage());
     url.set_path("_matrix/client/r0/sync");
+    // the url is reused for each sync
+    url.set_query(None);
     url.query_pairs_mut().append_pair("filter", &filter);
     url.query_pairs_mut().append_pair("timeout", &timeout.to_string());
     if let Some(since) = since {
         url.query_pairs_mut().append_pair("since", since);
     }
     log::info!("client_sync = {}", url.as_str());
-    if let Some(value) = handle_response(get_json_auth(&url, token, agent)) {
+    // the server holds the request for up to `timeout`, longer than the client's read timeout
+    let request = auth_request(client, "GET", url, token)
+        .timeout(Duration::from_millis(timeout.max(0) as u64) + SYNC_GRACE);
+    if let Some(value) = handle_response(client.send(request, None)) {
         if let Value::Object(body) = value {
             if let Some(Value::String(next_batch)) = body.get("next_batch") {
-                Some((next_batch.to_string(), get_messages(body, room_id)))
+                Some(get_sync(&body, next_batch))
             } else {
                 log::info!("invalid response for client_sync");
                 None
@@ -402,30 +520,73 @@ pub fn send_message(
     text: &str,
     txn_id: &str,
     token: &str,
-    agent: &mut Agent,
+    client: &Client,
+) -> bool {
+    let message_request = MessageRequest::new(text);
+    if let Some(request_body) = serialize(&message_request) {
+        send_event(url, room_id, MTX_ROOM_MESSAGE, &request_body, txn_id, token, client)
+    } else {
+        log::info!("Error unable to serialize request for send_message");
+        false
+    }
+}
+
+pub fn send_encrypted(
+    url: &mut Url,
+    room_id: &str,
+    content: &Value,
+    txn_id: &str,
+    token: &str,
+    client: &Client,
+) -> bool {
+    send_event(url, room_id, MTX_ROOM_ENCRYPTED, &content.to_string(), txn_id, token, client)
+}
+
+fn send_event(
+    url: &mut Url,
+    room_id: &str,
+    event_type: &str,
+    request_body: &str,
+    txn_id: &str,
+    token: &str,
+    client: &Client,
 ) -> bool {
     log::info!("heap usage: {}", crate::heap_usage());
     let mut path = String::from("_matrix/client/r0/rooms/");
     path.push_str(&room_id);
-    path.push_str("/send/m.room.message/");
+    path.push_str("/send/");
+    path.push_str(event_type);
+    path.push('/');
     path.push_str(&txn_id);
     url.set_path(&path);
-    log::info!("send_message = {}", url);
-    let message_request = MessageRequest::new(text);
-    if let Some(request_body) = serialize(&message_request) {
-        if let Some(value) = handle_response(put_string_auth(url, &request_body, token, agent)) {
-            if let Value::Object(_body) = value {
-                true
-            } else {
-                log::info!("invalid response for send_message");
-                false
-            }
+    url.set_query(None);
+    log::info!("send_event = {}", url);
+    if let Some(value) = handle_response(put_string_auth(url, request_body, token, client)) {
+        if let Value::Object(_body) = value {
+            true
         } else {
-            log::info!("Error for send_message");
+            log::info!("invalid response for send_event");
             false
         }
     } else {
-        log::info!("Error unable to serialize request for send_message");
+        log::info!("Error for send_event");
+        false
+    }
+}
+
+// moves the read markers of a room up to `event_id`, which clears its unread count on the server
+pub fn read_markers(url: &mut Url, room_id: &str, event_id: &str, token: &str, client: &Client) -> bool {
+    let mut path = String::from("_matrix/client/v3/rooms/");
+    path.push_str(&room_id);
+    path.push_str("/read_markers");
+    url.set_path(&path);
+    url.set_query(None);
+    log::info!("read_markers = {}", url);
+    let request_body = json!({ "m.fully_read": event_id, "m.read": event_id }).to_string();
+    if let Some(Value::Object(_body)) = handle_response(post_string_auth(url, &request_body, token, client)) {
+        true
+    } else {
+        log::info!("Error for read_markers");
         false
     }
 }
@@ -36,6 +36,10 @@ pub enum ChatOp {
     /// Find a Post by timestamp and Author
     PostFind,
     PostFlag,
+    /// Search the Posts of the current Dialogue, or of all Dialogues, and show the matches
+    Search,
+    /// Present a Modal to search the Posts (app menu item)
+    SearchModal,
     /// Set status bar text
     SetStatusText,
     /// Run or stop the busy animation.
@@ -74,6 +78,16 @@ pub struct Find {
     pub key: Option<usize>, // the return post key if found.
 }
 
+#[derive(Archive, Serialize, Deserialize, Debug)]
+pub struct Search {
+    pub query: xous_ipc::String<128>,
+    /// match whole words only
+    pub word: bool,
+    /// search all Dialogues in the pddb dict, rather than just the current Dialogue
+    pub all: bool,
+    pub count: usize, // the return number of matches.
+}
+
 #[derive(Archive, Serialize, Deserialize, Debug)]
 pub struct Dialogue {
     pub dict: xous_ipc::String<128>,
@@ -82,6 +96,7 @@ pub struct Dialogue {
 
 #[derive(Archive, Serialize, Deserialize, Debug)]
 pub struct Post {
+    /// the pddb key of the Dialogue, empty for the current Dialogue
     pub dialogue_id: xous_ipc::String<128>,
     pub author: xous_ipc::String<128>,
     pub timestamp: u64,
@@ -1,20 +1,25 @@
 pub mod attach;
 pub mod author;
 pub mod post;
+pub mod search;
+pub mod store;
 
 use core::slice::{Iter, IterMut};
+use std::cmp::min;
 use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
 
+use attach::Attach;
 use author::Author;
 use gam::Gam;
-use post::Post;
+use post::{Post, PostV1};
 use rkyv::{Archive, Deserialize, Serialize};
+use search::Query;
 
 use crate::ui::VisualProperties;
-use crate::{default_textview, now};
+use crate::{default_textview, now, PostFlag};
 
-// TODO do better than just allocate lots!
+/// The most a Dialogue could hold in the single-key format, before paged storage
 pub const MAX_BYTES: usize = 65536;
 
 /// A Dialogue is a generic representation of a series of Posts
@@ -35,6 +40,30 @@ pub struct Dialogue {
     last_author_id: u16,
 }
 
+/// The layout of a Dialogue of version 1 Posts, as held in the single-key format
+#[derive(Archive, Serialize, Deserialize, Debug)]
+pub(crate) struct DialogueV1 {
+    pub(crate) title: String,
+    pub(crate) posts: Vec<PostV1>,
+    pub(crate) authors: HashMap<u16, Author>,
+    pub(crate) author_lookup: HashMap<String, u16>,
+    pub(crate) last_timestamp: u64,
+    pub(crate) last_author_id: u16,
+}
+
+impl From<DialogueV1> for Dialogue {
+    fn from(old: DialogueV1) -> Self {
+        Self {
+            title: old.title,
+            posts: old.posts.into_iter().map(Post::from).collect(),
+            authors: old.authors,
+            author_lookup: old.author_lookup,
+            last_timestamp: old.last_timestamp,
+            last_author_id: old.last_author_id,
+        }
+    }
+}
+
 impl Dialogue {
     /// Creates a new Dialogue with a single Author.
     /// Author id=0 is assigned to the user of this Chat App.
@@ -55,8 +84,6 @@ impl Dialogue {
 
     /// Add a new Post to the Dialogue
     ///
-    /// TODO protect against Dialogue::MAX_BYTES overflow
-    ///
     /// note: posts are sorted by timestamp, so:
     /// - `post_add` at beginning or end is fast (middle triggers a binary partition)
     /// - if adding multiple posts then add oldest/newest last!
@@ -73,14 +100,12 @@ impl Dialogue {
         author: &str,
         timestamp: u64,
         text: &str,
-        _attach_url: Option<&str>,
+        attach_url: Option<&str>,
         vp: Option<(&VisualProperties, &Gam)>,
     ) -> Result<(), Error> {
         match self.author_id(author) {
             Some(author_id) => {
-                let mut new = Post::new(
-                    author_id, timestamp, text, None, // TODO implement
-                );
+                let mut new = Post::new(author_id, timestamp, text, attach_url.map(Attach::new));
                 if self.posts.len() == 0 {
                     self.posts.push(new);
                     return Ok(());
@@ -132,6 +157,37 @@ impl Dialogue {
         }
     }
 
+    /// Delete a Post from the Dialogue
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the index of the Post to delete
+    pub fn post_del(&mut self, index: usize) -> Result<(), Error> {
+        if index < self.posts.len() {
+            self.posts.remove(index);
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::NotFound, "no Post at index"))
+        }
+    }
+
+    /// Set or clear a flag on a Post in the Dialogue
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the index of the Post
+    /// * `flag` - the flag to set or clear
+    /// * `set` - true to set the flag, false to clear it
+    pub fn post_flag(&mut self, index: usize, flag: PostFlag, set: bool) -> Result<(), Error> {
+        match self.posts.get_mut(index) {
+            Some(post) => {
+                post.flag_set(flag, set);
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::NotFound, "no Post at index")),
+        }
+    }
+
     /// Returns Some(index) of a matching Post by Author and Timestamp, or None
     ///
     /// # Arguments
@@ -157,6 +213,32 @@ impl Dialogue {
         None
     }
 
+    /// Return Some(index) of the Post with a Timestamp and Author id, or None
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - the Post timestamp
+    /// * `author_id` - the (internal) id of the Post Author
+    pub fn post_index(&self, timestamp: u64, author_id: u16) -> Option<usize> {
+        let i = self.posts.partition_point(|p| p.timestamp() < timestamp);
+        self.posts[i..]
+            .iter()
+            .take_while(|p| p.timestamp() == timestamp)
+            .position(|p| p.author_id() == author_id)
+            .map(|n| i + n)
+    }
+
+    /// Return the indexes of the Posts matching a Query, oldest first
+    ///
+    /// Deleted and Hidden Posts are passed over.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the text to look for
+    pub fn post_search(&self, query: &Query) -> Vec<usize> {
+        self.posts.iter().enumerate().filter(|(_, p)| query.matches_post(p)).map(|(i, _)| i).collect()
+    }
+
     /// Return Some<Post> by index in the Dialogue, or None.
     ///
     /// # Arguments
@@ -169,6 +251,20 @@ impl Dialogue {
         if self.posts.len() == 0 { None } else { Some(self.posts.len() - 1) }
     }
 
+    /// Return the index of the nearest Post that is not Hidden, starting at `index` and
+    /// searching towards newer Posts if `forward`, or towards older Posts otherwise.
+    pub fn post_visible(&self, index: usize, forward: bool) -> Option<usize> {
+        if self.posts.len() == 0 {
+            return None;
+        }
+        let visible = |i: &usize| !self.posts[*i].flag_is(PostFlag::Hidden);
+        if forward {
+            (index..self.posts.len()).find(visible)
+        } else {
+            (0..=min(index, self.posts.len() - 1)).rev().find(visible)
+        }
+    }
+
     /// Return a slice of posts
     pub fn posts_as_slice(&self) -> &[Post] { &self.posts }
 
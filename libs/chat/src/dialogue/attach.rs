@@ -1,9 +1,44 @@
 use rkyv::{Archive, Deserialize, Serialize};
 
+/// The url scheme of an attachment held in the pddb, as in `pddb:dict/key`
+pub const PDDB_SCHEME: &str = "pddb:";
+
+/// An attachment to a Post, by url
 #[derive(Archive, Serialize, Deserialize, Debug)]
 pub enum Attach {
+    Png(String),
+    Jpg(String),
+    Other(String),
+}
+
+impl Attach {
+    /// Creates an Attach of the kind suggested by the extension of the url
+    pub fn new(url: &str) -> Self {
+        let path = url.split(&['?', '#'][..]).next().unwrap_or(url).to_lowercase();
+        if path.ends_with(".png") {
+            Attach::Png(url.to_string())
+        } else if path.ends_with(".jpg") || path.ends_with(".jpeg") {
+            Attach::Jpg(url.to_string())
+        } else {
+            Attach::Other(url.to_string())
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        match self {
+            Attach::Png(url) | Attach::Jpg(url) | Attach::Other(url) => url.as_str(),
+        }
+    }
+
+    /// Returns the pddb dict and key of an attachment held in the pddb, or None
+    pub fn pddb_location(&self) -> Option<(&str, &str)> {
+        self.url().strip_prefix(PDDB_SCHEME).and_then(|path| path.split_once('/'))
+    }
+}
+
+/// The layout of Attach in a version 1 Post, which held no url
+#[derive(Archive, Serialize, Deserialize, Debug)]
+pub(crate) enum AttachV1 {
     Png(),
     Jpg(),
 }
-
-impl Attach {}
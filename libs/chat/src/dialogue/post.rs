@@ -2,9 +2,13 @@ use enumset::EnumSet;
 use graphics_server::Rectangle;
 use rkyv::{Archive, Deserialize, Serialize};
 
-use super::attach::Attach;
+use super::attach::{Attach, AttachV1};
 use crate::PostFlag;
 
+/// The version of the Post layout, tagged in the header of each archive that holds Posts.
+/// Version 1 Posts were held untagged, in the single-key format of a Dialogue.
+pub const VERSION: u8 = 2;
+
 #[derive(Archive, Serialize, Deserialize, Debug)]
 pub struct Post {
     author_id: u16,
@@ -21,6 +25,8 @@ impl Post {
         Self { author_id, timestamp, text: text.to_string(), attach, flags: 0, bounding_box: None }
     }
 
+    pub fn attach(&self) -> Option<&Attach> { self.attach.as_ref() }
+
     pub fn author_id(&self) -> u16 { self.author_id }
 
     pub fn flag_is(&self, flag: PostFlag) -> bool { self.flags_get().contains(flag) }
@@ -29,7 +35,46 @@ impl Post {
 
     pub fn flags_set(&mut self, flags: EnumSet<PostFlag>) { self.flags = flags.as_u16(); }
 
+    /// Set or clear a single flag
+    ///
+    /// The flags change how the Post is drawn, so its bounds are computed afresh
+    pub fn flag_set(&mut self, flag: PostFlag, set: bool) {
+        let mut flags = self.flags_get();
+        if set {
+            flags.insert(flag);
+        } else {
+            flags.remove(flag);
+        }
+        self.flags_set(flags);
+        self.bounding_box = None;
+    }
+
     pub fn text(&self) -> &str { self.text.as_str() }
 
     pub fn timestamp(&self) -> u64 { self.timestamp }
 }
+
+/// The layout of a version 1 Post, before an Attach held its url
+#[derive(Archive, Serialize, Deserialize, Debug)]
+pub(crate) struct PostV1 {
+    pub(crate) author_id: u16,
+    pub(crate) timestamp: u64,
+    pub(crate) text: String,
+    pub(crate) attach: Option<AttachV1>,
+    pub(crate) flags: u16,
+    pub(crate) bounding_box: Option<Rectangle>,
+}
+
+impl From<PostV1> for Post {
+    /// A version 1 Attach has no url to keep, so it is dropped
+    fn from(old: PostV1) -> Self {
+        Self {
+            author_id: old.author_id,
+            timestamp: old.timestamp,
+            text: old.text,
+            attach: None,
+            flags: old.flags,
+            bounding_box: old.bounding_box,
+        }
+    }
+}
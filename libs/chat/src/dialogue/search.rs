@@ -0,0 +1,136 @@
+use super::post::Post;
+use crate::PostFlag;
+
+/// What to look for in the text of Posts
+#[derive(Debug, Clone)]
+pub struct Query {
+    text: String,
+    word: bool,
+}
+
+impl Query {
+    /// A case-insensitive search for `text`, as a whole word if `word` or anywhere in a Post otherwise
+    pub fn new(text: &str, word: bool) -> Self { Self { text: text.trim().to_lowercase(), word } }
+
+    pub fn text(&self) -> &str { self.text.as_str() }
+
+    pub fn is_empty(&self) -> bool { self.text.is_empty() }
+
+    /// Returns true if the Query is found in the text
+    pub fn matches(&self, text: &str) -> bool {
+        if self.text.is_empty() {
+            return false;
+        }
+        let text = text.to_lowercase();
+        if !self.word {
+            return text.contains(&self.text);
+        }
+        // a word is bounded by the ends of the text, or by anything that is not alphanumeric
+        text.match_indices(&self.text).any(|(i, found)| {
+            let start = text[..i].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+            let end = text[i + found.len()..].chars().next().is_none_or(|c| !c.is_alphanumeric());
+            start && end
+        })
+    }
+
+    /// Returns true if the Query is found in a Post that is neither Deleted nor Hidden
+    pub fn matches_post(&self, post: &Post) -> bool {
+        !post.flag_is(PostFlag::Deleted) && !post.flag_is(PostFlag::Hidden) && self.matches(post.text())
+    }
+}
+
+/// A Post matching a Query, by what stays the same as pages are read and Posts are added
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// the pddb key of the Dialogue
+    pub dialogue: String,
+    pub timestamp: u64,
+    pub author_id: u16,
+}
+
+/// The Matches of a Query, and the one shown
+#[derive(Debug)]
+pub struct Results {
+    pub query: Query,
+    /// grouped by Dialogue, oldest first, with the Dialogue current at the start of the search last
+    pub matches: Vec<Match>,
+    pub current: usize,
+    /// the pddb key of the Dialogue current at the start of the search, which is restored at the end
+    pub origin: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substring() {
+        let query = Query::new("cat", false);
+        assert!(query.matches("cat"));
+        assert!(query.matches("concatenate"));
+        assert!(query.matches("Scattered CATS"));
+        assert!(!query.matches("c a t"));
+        assert!(!query.matches(""));
+    }
+
+    #[test]
+    fn whole_word() {
+        let query = Query::new("cat", true);
+        assert!(query.matches("cat"));
+        assert!(query.matches("the Cat sat"));
+        assert!(!query.matches("concatenate"));
+        assert!(!query.matches("cats"));
+        assert!(!query.matches("cat2"));
+        // the first place it's found isn't a word, but a later one is
+        assert!(query.matches("cats and a cat"));
+        // a phrase is a word too
+        assert!(Query::new("good morning", true).matches("Good morning, all"));
+        assert!(!Query::new("good morning", true).matches("good mornings"));
+    }
+
+    #[test]
+    fn punctuation_bounds_words() {
+        let query = Query::new("cat", true);
+        for text in ["cat.", "(cat)", "cat's", "\"cat\"", "a-cat-b", "cat_", "#cat", "\tcat\n"] {
+            assert!(query.matches(text), "{:?}", text);
+        }
+        assert!(Query::new("c++", true).matches("i like c++."));
+        assert!(Query::new("c++", false).matches("c++17"));
+    }
+
+    #[test]
+    fn non_ascii() {
+        assert!(Query::new("café", true).matches("un CAFÉ, s'il vous plaît"));
+        assert!(!Query::new("caf", true).matches("café"));
+        assert!(Query::new("caf", false).matches("café"));
+        assert!(Query::new("привет", true).matches("Привет, мир"));
+        assert!(!Query::new("мир", true).matches("мирный"));
+        // no spaces between words, so only substrings are found
+        assert!(Query::new("東京", false).matches("東京タワー"));
+        assert!(!Query::new("東京", true).matches("東京タワー"));
+        assert!(Query::new("東京", true).matches("東京。"));
+        // lower case that changes the length of the text before the match
+        assert!(Query::new("cat", true).matches("İİ cat"));
+        assert!(Query::new("🐈", true).matches("my 🐈 sleeps"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(Query::new("  ", false).is_empty());
+        assert!(!Query::new("  ", false).matches("anything"));
+        assert!(!Query::new("", true).matches(""));
+        assert_eq!(Query::new("  Cat ", true).text(), "cat");
+    }
+
+    #[test]
+    fn deleted_and_hidden_posts() {
+        let query = Query::new("cat", false);
+        let mut post = Post::new(1, 10, "a cat", None);
+        assert!(query.matches_post(&post));
+        post.flag_set(PostFlag::Hidden, true);
+        assert!(!query.matches_post(&post));
+        post.flag_set(PostFlag::Hidden, false);
+        post.flag_set(PostFlag::Deleted, true);
+        assert!(!query.matches_post(&post));
+    }
+}
@@ -0,0 +1,582 @@
+use std::convert::TryFrom;
+use std::hash::Hasher;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::mem;
+
+use pddb::Pddb;
+use rkyv::de::deserializers::AllocDeserializer;
+use rkyv::ser::serializers::WriteSerializer;
+use rkyv::ser::Serializer;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use super::post::{self, Post};
+use super::{Dialogue, DialogueV1};
+
+/// The most Posts held in one page
+pub const PAGE_POSTS: usize = 32;
+
+/// The version of the paged format, which follows the 2 zero bytes that mark an Index
+const VERSION: u8 = 1;
+
+/// The bytes ahead of the Index archive: 2 zero bytes, the VERSION, the Post version and the u16
+/// archive pos. A Dialogue in the single-key format starts with its archive pos, which is never 0.
+const INDEX_RESERVED: usize = 6;
+
+/// A page of Posts, held in its own pddb key after the Post version
+#[derive(Archive, Serialize, Deserialize, Debug)]
+struct Page {
+    posts: Vec<Post>,
+}
+
+/// Where to find a page of Posts, and what it held when last written
+#[derive(Archive, Serialize, Deserialize, Debug, Clone)]
+struct PageInfo {
+    /// the id in the pddb key of the page
+    id: u32,
+    /// the timestamp of the oldest Post in the page
+    first_ts: u64,
+    /// the number of Posts in the page
+    count: u32,
+    /// a hash over the Posts in the page, to pass over a page that is unchanged
+    hash: u64,
+}
+
+/// The Index held in the pddb key of a Dialogue, with everything but the Posts
+#[derive(Archive, Serialize, Deserialize, Debug)]
+struct Index {
+    dialogue: Dialogue,
+    /// oldest page first
+    pages: Vec<PageInfo>,
+    next_id: u32,
+}
+
+/// The pddb keys read and written by a Store
+///
+/// Implemented by the Pddb, and by keys held in memory in the tests
+pub trait Keys {
+    /// Read all of a key, or a NotFound Error if there is no such key
+    fn read(&self, dict: &str, key: &str) -> Result<Vec<u8>, Error>;
+
+    /// Write a key afresh, so that nothing is left of a prior longer value
+    fn write(&self, dict: &str, key: &str, bytes: &[u8]) -> Result<(), Error>;
+
+    fn delete(&self, dict: &str, key: &str);
+
+    fn sync(&self);
+}
+
+impl Keys for Pddb {
+    fn read(&self, dict: &str, key: &str) -> Result<Vec<u8>, Error> {
+        let mut pddb_key = self.get(dict, key, None, true, false, None, None::<fn()>).map_err(|e| {
+            log::warn!("failed to get {}:{} {e}", dict, key);
+            Error::new(ErrorKind::NotFound, "missing")
+        })?;
+        let mut bytes = Vec::<u8>::new();
+        pddb_key.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn write(&self, dict: &str, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.delete_key(dict, key, None).ok();
+        let mut pddb_key = self.get(dict, key, None, true, true, Some(bytes.len()), None::<fn()>)?;
+        pddb_key.write_all(bytes)?;
+        log::info!("Wrote {} bytes to {}:{}", bytes.len(), dict, key);
+        Ok(())
+    }
+
+    fn delete(&self, dict: &str, key: &str) { self.delete_key(dict, key, None).ok(); }
+
+    fn sync(&self) { self.sync().ok(); }
+}
+
+/// Paged storage of a Dialogue in the pddb
+///
+/// The pddb key of a Dialogue holds an Index of pages, and each page of up to PAGE_POSTS Posts is
+/// held in a key of its own, in the dict `<dict>.pages`. Only the most recent page is read when the
+/// Dialogue is opened, and older pages are read on demand with `load_older()`. On `save()`, only the
+/// pages with a changed Post are written.
+///
+/// A Dialogue in the single-key format holds version 1 Posts. It is read in full, and written back
+/// paged.
+#[derive(Debug)]
+pub struct Store {
+    dict: String,
+    key: String,
+    /// oldest page first
+    pages: Vec<PageInfo>,
+    /// the index of the oldest page read into the Dialogue
+    loaded: usize,
+    next_id: u32,
+}
+
+impl Store {
+    /// Creates the Store of a new Dialogue, which is not written until `save()`
+    pub fn new(dict: &str, key: &str) -> Self {
+        Self { dict: dict.to_string(), key: key.to_string(), pages: Vec::new(), loaded: 0, next_id: 0 }
+    }
+
+    /// Read a Dialogue from the pddb, with its most recent page of Posts
+    ///
+    /// Returns a NotFound Error if there is no such pddb key
+    pub fn open<K: Keys>(keys: &K, dict: &str, key: &str) -> Result<(Dialogue, Self), Error> {
+        let bytes = keys.read(dict, key).map_err(|e| {
+            log::warn!("failed to read {}: {e}", key);
+            e
+        })?;
+        let mut store = Store::new(dict, key);
+        if bytes.len() >= INDEX_RESERVED && bytes[0] == 0 && bytes[1] == 0 {
+            if bytes[2] != VERSION {
+                log::warn!("unknown Dialogue version {} in {}:{}", bytes[2], dict, key);
+                return Err(Error::new(ErrorKind::InvalidData, "version"));
+            }
+            let index: Index = unarchive_posts(&bytes, 3)?;
+            store.loaded = index.pages.len();
+            store.pages = index.pages;
+            store.next_id = index.next_id;
+            let mut dialogue = index.dialogue;
+            store.load_older(keys, &mut dialogue);
+            Ok((dialogue, store))
+        } else {
+            let mut dialogue = Dialogue::from(unarchive::<DialogueV1>(&bytes, 0)?);
+            log::info!("migrating {}:{} to paged storage", dict, key);
+            // the Posts are all held in the Dialogue, so the next save tries again
+            if let Err(e) = store.save(keys, &mut dialogue) {
+                log::warn!("failed to migrate {}:{} {e}", dict, key);
+            }
+            Ok((dialogue, store))
+        }
+    }
+
+    /// Read the next older page of Posts into the start of the Dialogue
+    ///
+    /// Returns Some(number of Posts read), or None when there are no older pages
+    pub fn load_older<K: Keys>(&mut self, keys: &K, dialogue: &mut Dialogue) -> Option<usize> {
+        if self.loaded == 0 {
+            return None;
+        }
+        self.loaded -= 1;
+        let page_key = self.page_key(self.pages[self.loaded].id);
+        let page =
+            keys.read(&self.pages_dict(), &page_key).and_then(|bytes| unarchive_posts::<Page>(&bytes, 0));
+        match page {
+            Ok(page) => {
+                let mut posts = page.posts;
+                let count = posts.len();
+                posts.append(&mut dialogue.posts);
+                dialogue.posts = posts;
+                Some(count)
+            }
+            Err(e) => {
+                // the page is passed over, and dropped by the next save
+                log::warn!("failed to read page {}:{} {e}", self.pages_dict(), page_key);
+                self.pages.remove(self.loaded);
+                Some(0)
+            }
+        }
+    }
+
+    /// Read all the older pages
+    ///
+    /// Returns the number of Posts read
+    pub fn load_all<K: Keys>(&mut self, keys: &K, dialogue: &mut Dialogue) -> usize {
+        let mut count = 0;
+        while let Some(n) = self.load_older(keys, dialogue) {
+            count += n;
+        }
+        count
+    }
+
+    /// Read older pages until a Post at `timestamp` would fall among the Posts read
+    ///
+    /// Returns the number of Posts read
+    pub fn cover<K: Keys>(&mut self, keys: &K, dialogue: &mut Dialogue, timestamp: u64) -> usize {
+        let mut count = 0;
+        while self.pages.get(self.loaded).map_or(self.loaded > 0, |page| timestamp < page.first_ts) {
+            match self.load_older(keys, dialogue) {
+                Some(n) => count += n,
+                None => break,
+            }
+        }
+        count
+    }
+
+    /// Write the changed pages of the Dialogue to the pddb, followed by the Index
+    ///
+    /// A page that fails to be written is left out of the Index, and its Posts are kept in the
+    /// Dialogue, to be written along with a neighbouring page by the next save.
+    ///
+    /// Returns the first Error in writing a page or the Index
+    pub fn save<K: Keys>(&mut self, keys: &K, dialogue: &mut Dialogue) -> Result<(), Error> {
+        let mut posts = mem::take(&mut dialogue.posts).into_iter().peekable();
+        let mut pages = Vec::<PageInfo>::new();
+        let mut kept = Vec::<Post>::new();
+        let mut result = Ok(());
+        // the Posts of each page read are those older than the next page
+        let old_pages = self.pages.split_off(self.loaded);
+        for (i, old) in old_pages.iter().enumerate() {
+            let mut group = Vec::<Post>::new();
+            while let Some(post) =
+                posts.next_if(|p| old_pages.get(i + 1).map_or(true, |next| p.timestamp() < next.first_ts))
+            {
+                group.push(post);
+            }
+            if group.len() == 0 {
+                self.page_delete(keys, old.id);
+            } else {
+                result = result.and(self.page_write(keys, Some(old), group, &mut pages, &mut kept));
+            }
+        }
+        // no page was read, so all the Posts are new
+        let rest: Vec<Post> = posts.collect();
+        if rest.len() > 0 {
+            result = result.and(self.page_write(keys, None, rest, &mut pages, &mut kept));
+        }
+        self.pages.append(&mut pages);
+
+        // the Index holds the Dialogue without its Posts
+        let index = Index {
+            dialogue: mem::replace(dialogue, Dialogue::new("")),
+            pages: self.pages.clone(),
+            next_id: self.next_id,
+        };
+        let written = archive(&index, vec![0u8, 0u8, VERSION, post::VERSION])
+            .and_then(|bytes| keys.write(&self.dict, &self.key, &bytes));
+        *dialogue = index.dialogue;
+        dialogue.posts = kept;
+        match written {
+            Ok(()) => keys.sync(),
+            Err(e) => {
+                log::warn!("failed to write {}:{} {e}", self.dict, self.key);
+                return Err(e);
+            }
+        }
+        result
+    }
+
+    /// Write a group of Posts as one or more pages, passing over a page that is unchanged
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - the page that held the first of the Posts, or None for new Posts
+    /// * `group` - the Posts, oldest first
+    /// * `pages` - receives the pages written, but not those that failed
+    /// * `kept` - receives all the Posts
+    ///
+    /// Returns the first Error in writing a page
+    fn page_write<K: Keys>(
+        &mut self,
+        keys: &K,
+        mut old: Option<&PageInfo>,
+        group: Vec<Post>,
+        pages: &mut Vec<PageInfo>,
+        kept: &mut Vec<Post>,
+    ) -> Result<(), Error> {
+        let mut result = Ok(());
+        let mut group = group.into_iter().peekable();
+        while group.peek().is_some() {
+            let page = Page { posts: group.by_ref().take(PAGE_POSTS).collect() };
+            let hash = page_hash(&page.posts);
+            let count = page.posts.len() as u32;
+            let (id, unchanged) = match old.take() {
+                Some(info) => (info.id, info.hash == hash && info.count == count),
+                None => {
+                    self.next_id += 1;
+                    (self.next_id, false)
+                }
+            };
+            let written = if unchanged {
+                Ok(())
+            } else {
+                archive(&page, vec![post::VERSION])
+                    .and_then(|bytes| keys.write(&self.pages_dict(), &self.page_key(id), &bytes))
+            };
+            let first_ts = page.posts.first().map_or(0, |p| p.timestamp());
+            kept.extend(page.posts);
+            match written {
+                Ok(()) => pages.push(PageInfo { id, first_ts, count, hash }),
+                Err(e) => {
+                    log::warn!("failed to write page {}:{} {e}", self.pages_dict(), self.page_key(id));
+                    // nothing is left of an old page, which may otherwise hold stale Posts
+                    self.page_delete(keys, id);
+                    result = result.and(Err(e));
+                }
+            }
+        }
+        result
+    }
+
+    fn page_delete<K: Keys>(&self, keys: &K, id: u32) { keys.delete(&self.pages_dict(), &self.page_key(id)); }
+
+    fn pages_dict(&self) -> String { format!("{}.pages", self.dict) }
+
+    /// The pddb key of a page, which must stay clear of the key length limit
+    fn page_key(&self, id: u32) -> String {
+        let mut hasher = Fnv::default();
+        hasher.write(self.key.as_bytes());
+        format!("{:016x}.{}", hasher.finish(), id)
+    }
+}
+
+/// A hash over what is saved of each Post in a page
+fn page_hash(posts: &[Post]) -> u64 {
+    let mut hasher = Fnv::default();
+    for post in posts {
+        hasher.write_u64(post.timestamp());
+        hasher.write_u16(post.author_id());
+        hasher.write_u16(post.flags);
+        hasher.write(post.text().as_bytes());
+        if let Some(attach) = post.attach() {
+            hasher.write(attach.url().as_bytes());
+        }
+    }
+    hasher.finish()
+}
+
+/// FNV-1a, which unlike the std hasher is stable between builds
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self { Fnv(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Serialize after the bytes of `header`, followed by the u16 archive pos
+fn archive<T>(value: &T, header: Vec<u8>) -> Result<Vec<u8>, Error>
+where
+    T: Serialize<WriteSerializer<Vec<u8>>>,
+{
+    let mut buf = header;
+    let reserved = buf.len() + 2;
+    buf.push(0u8);
+    buf.push(0u8);
+    let mut serializer = WriteSerializer::with_pos(buf, reserved);
+    let pos = serializer
+        .serialize_value(value)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+    let mut bytes = serializer.into_inner();
+    // copy pos u16 into the 2 reserved bytes to enable the rkyv archive to be deserialised
+    let pos = u16::try_from(pos).map_err(|_| Error::new(ErrorKind::InvalidData, "data > u16"))?;
+    bytes[reserved - 2..reserved].copy_from_slice(&pos.to_be_bytes());
+    Ok(bytes)
+}
+
+/// Deserialize an archive with its u16 pos at `offset`
+fn unarchive<T>(bytes: &[u8], offset: usize) -> Result<T, Error>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, AllocDeserializer>,
+{
+    if bytes.len() < offset + 2 {
+        return Err(Error::new(ErrorKind::InvalidData, "short"));
+    }
+    let pos: usize = u16::from_be_bytes([bytes[offset], bytes[offset + 1]]).into();
+    if pos >= bytes.len() {
+        return Err(Error::new(ErrorKind::InvalidData, "pos"));
+    }
+    let archive = unsafe { rkyv::archived_value::<T>(bytes, pos) };
+    archive.deserialize(&mut AllocDeserializer {}).map_err(|e| {
+        log::warn!("failed to deserialize {e}");
+        Error::new(ErrorKind::InvalidData, "deserialize")
+    })
+}
+
+/// Deserialize an archive of Posts, tagged with the Post version at `offset`
+fn unarchive_posts<T>(bytes: &[u8], offset: usize) -> Result<T, Error>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, AllocDeserializer>,
+{
+    match bytes.get(offset) {
+        Some(&post::VERSION) => unarchive(bytes, offset + 1),
+        Some(version) => {
+            log::warn!("unknown Post version {}", version);
+            Err(Error::new(ErrorKind::InvalidData, "version"))
+        }
+        None => Err(Error::new(ErrorKind::InvalidData, "short")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::dialogue::attach::AttachV1;
+    use crate::dialogue::author::Author;
+    use crate::dialogue::post::PostV1;
+
+    /// pddb keys held in memory
+    #[derive(Default)]
+    struct MemKeys {
+        keys: RefCell<HashMap<(String, String), Vec<u8>>>,
+        /// the keys written, in order
+        written: RefCell<Vec<String>>,
+    }
+
+    impl Keys for MemKeys {
+        fn read(&self, dict: &str, key: &str) -> Result<Vec<u8>, Error> {
+            self.keys
+                .borrow()
+                .get(&(dict.to_string(), key.to_string()))
+                .cloned()
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "missing"))
+        }
+
+        fn write(&self, dict: &str, key: &str, bytes: &[u8]) -> Result<(), Error> {
+            self.keys.borrow_mut().insert((dict.to_string(), key.to_string()), bytes.to_vec());
+            self.written.borrow_mut().push(key.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, dict: &str, key: &str) {
+            self.keys.borrow_mut().remove(&(dict.to_string(), key.to_string()));
+        }
+
+        fn sync(&self) {}
+    }
+
+    /// A Dialogue with a Post each second from alice, starting at 1
+    fn dialogue_of(count: u64) -> Dialogue {
+        let mut dialogue = Dialogue::new("room");
+        for ts in 1..=count {
+            dialogue.post_add("alice", ts, &format!("post {ts}"), None, None).unwrap();
+        }
+        dialogue
+    }
+
+    fn post_v1(author_id: u16, timestamp: u64, text: &str, attach: Option<AttachV1>) -> PostV1 {
+        PostV1 { author_id, timestamp, text: text.to_string(), attach, flags: 0, bounding_box: None }
+    }
+
+    #[test]
+    fn single_key_v1() {
+        let mut authors = HashMap::new();
+        authors.insert(0, Author::new("me"));
+        authors.insert(2, Author::new("alice"));
+        let mut author_lookup = HashMap::new();
+        author_lookup.insert("alice".to_string(), 2);
+        let old = DialogueV1 {
+            title: "room".to_string(),
+            posts: vec![post_v1(2, 10, "hello", None), post_v1(0, 20, "a picture", Some(AttachV1::Png()))],
+            authors,
+            author_lookup,
+            last_timestamp: 20,
+            last_author_id: 2,
+        };
+        // the single-key format: the u16 archive pos, then the archive
+        let bytes = archive(&old, Vec::new()).unwrap();
+        assert_ne!(bytes[..2], [0u8, 0u8]);
+
+        let mut dialogue = Dialogue::from(unarchive::<DialogueV1>(&bytes, 0).unwrap());
+        assert_eq!(dialogue.title, "room");
+        assert_eq!(dialogue.posts.len(), 2);
+        assert_eq!(dialogue.posts[0].text(), "hello");
+        assert_eq!(dialogue.posts[0].author_id(), 2);
+        assert_eq!(dialogue.posts[1].timestamp(), 20);
+        assert!(dialogue.posts[1].attach().is_none());
+        assert_eq!(dialogue.author_id("alice"), Some(2));
+        assert_eq!(dialogue.author_id("bob"), Some(3));
+    }
+
+    #[test]
+    fn post_version_tag() {
+        let page = Page { posts: vec![Post::new(1, 10, "hello", None)] };
+        let bytes = archive(&page, vec![post::VERSION]).unwrap();
+        let read: Page = unarchive_posts(&bytes, 0).unwrap();
+        assert_eq!(read.posts[0].text(), "hello");
+
+        let bytes = archive(&page, vec![post::VERSION + 1]).unwrap();
+        assert!(unarchive_posts::<Page>(&bytes, 0).is_err());
+        assert!(unarchive_posts::<Page>(&[], 0).is_err());
+    }
+
+    #[test]
+    fn migrate_single_key() {
+        let mut authors = HashMap::new();
+        authors.insert(0, Author::new("me"));
+        let old = DialogueV1 {
+            title: "room".to_string(),
+            posts: (1..=40).map(|ts| post_v1(0, ts, &format!("post {ts}"), None)).collect(),
+            authors,
+            author_lookup: HashMap::new(),
+            last_timestamp: 40,
+            last_author_id: 1,
+        };
+        let keys = MemKeys::default();
+        keys.write("chat", "room", &archive(&old, Vec::new()).unwrap()).unwrap();
+
+        let (dialogue, store) = Store::open(&keys, "chat", "room").unwrap();
+        assert_eq!(dialogue.posts.len(), 40);
+        assert_eq!(store.pages.len(), 2);
+        let index = keys.read("chat", "room").unwrap();
+        assert_eq!(index[..4], [0u8, 0u8, VERSION, post::VERSION]);
+
+        // read back paged, with only the most recent page
+        let (mut dialogue, mut store) = Store::open(&keys, "chat", "room").unwrap();
+        assert_eq!(dialogue.posts.len(), 40 - PAGE_POSTS);
+        assert_eq!(store.load_all(&keys, &mut dialogue), PAGE_POSTS);
+        assert_eq!(dialogue.posts[0].text(), "post 1");
+        assert_eq!(dialogue.posts[39].text(), "post 40");
+    }
+
+    #[test]
+    fn paging() {
+        let keys = MemKeys::default();
+        let mut store = Store::new("chat", "room");
+        let mut dialogue = dialogue_of(80);
+        store.save(&keys, &mut dialogue).unwrap();
+        assert_eq!(dialogue.posts.len(), 80);
+        let counts: Vec<u32> = store.pages.iter().map(|page| page.count).collect();
+        assert_eq!(counts, [32, 32, 16]);
+
+        let (mut dialogue, mut store) = Store::open(&keys, "chat", "room").unwrap();
+        assert_eq!(dialogue.posts.len(), 16);
+        assert_eq!(store.load_older(&keys, &mut dialogue), Some(32));
+        assert_eq!(dialogue.posts[0].timestamp(), 33);
+
+        // a new Post rewrites only the most recent page, and the Index
+        keys.written.borrow_mut().clear();
+        dialogue.post_add("alice", 81, "post 81", None, None).unwrap();
+        store.save(&keys, &mut dialogue).unwrap();
+        assert_eq!(dialogue.posts.len(), 49);
+        let last = store.page_key(store.pages[2].id);
+        assert_eq!(*keys.written.borrow(), [last, "room".to_string()]);
+
+        let (mut dialogue, mut store) = Store::open(&keys, "chat", "room").unwrap();
+        assert_eq!(store.cover(&keys, &mut dialogue, 40), 32);
+        assert_eq!(dialogue.posts.len(), 49);
+        assert_eq!(store.cover(&keys, &mut dialogue, 1), 32);
+        assert_eq!(store.load_older(&keys, &mut dialogue), None);
+        assert_eq!(dialogue.posts.len(), 81);
+    }
+
+    #[test]
+    fn page_write_failure() {
+        let keys = MemKeys::default();
+        let mut store = Store::new("chat", "room");
+        let mut dialogue = dialogue_of(PAGE_POSTS as u64);
+        // a page over 64k has no u16 archive pos
+        let big = "x".repeat(70000);
+        dialogue.post_add("alice", 100, &big, None, None).unwrap();
+        assert!(store.save(&keys, &mut dialogue).is_err());
+        assert_eq!(store.pages.len(), 1);
+        assert_eq!(dialogue.posts.len(), PAGE_POSTS + 1);
+        assert!(keys.read("chat.pages", &store.page_key(2)).is_err());
+
+        let (mut dialogue, mut store) = Store::open(&keys, "chat", "room").unwrap();
+        assert_eq!(store.load_all(&keys, &mut dialogue), 0);
+        assert_eq!(dialogue.posts.len(), PAGE_POSTS);
+        assert_eq!(dialogue.posts.last().map(|p| p.timestamp()), Some(PAGE_POSTS as u64));
+    }
+}
@@ -11,8 +11,10 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub use api::*;
 use gam::MenuItem;
 use graphics_server::api::GlyphStyle;
-use graphics_server::{Point, Rectangle, TextBounds, TextView};
-use num_traits::FromPrimitive;
+use graphics_server::{DrawStyle, PixelColor, Point, Rectangle, TextBounds, TextView};
+use locales::t;
+use num_traits::{FromPrimitive, ToPrimitive};
+use dialogue::search::Query;
 use ui::VisualProperties;
 pub use ui::BUSY_ANIMATION_RATE_MS;
 use xous::{msg_scalar_unpack, Error, CID, SID};
@@ -43,10 +45,79 @@ pub(crate) fn default_textview(
     bubble_tv.margin = vp.bubble_margin;
     bubble_tv.ellipsis = false;
     bubble_tv.insertion = None;
-    write!(bubble_tv.text, "{}", post.text()).expect("couldn't write history text to TextView");
+    if post.flag_is(PostFlag::Deleted) {
+        // a tombstone keeps the place of a deleted Post, without its text
+        bubble_tv.style = GlyphStyle::Small;
+        write!(bubble_tv.text, "{}", t!("chat.post.deleted", locales::LANG))
+            .expect("couldn't write history text to TextView");
+    } else if post.flag_is(PostFlag::Draft) {
+        // the draft label is set apart from the text of the Post in its own style
+        bubble_tv
+            .write_span(t!("chat.post.draft", locales::LANG), GlyphStyle::Bold)
+            .expect("couldn't write history text to TextView");
+        write!(bubble_tv.text, "\n{}", post.text()).expect("couldn't write history text to TextView");
+    } else {
+        write!(bubble_tv.text, "{}", post.text()).expect("couldn't write history text to TextView");
+    }
     bubble_tv
 }
 
+/// Return true if the Post is to be aligned to the right of the screen
+fn align_right(post: &crate::dialogue::post::Post, dialogue: &crate::dialogue::Dialogue) -> bool {
+    match dialogue.author(post.author_id()) {
+        Some(author) => author.flag_is(AuthorFlag::Right),
+        None => false,
+    }
+}
+
+/// Return the Rectangle of the tile showing the attachment of a Post, below its text bubble
+///
+/// # Arguments
+///
+/// * `vp` - the visual properties to be applied to the tile
+/// * `post` - the post with the attachment
+/// * `dialogue` - containing the Post for context info
+/// * `top_y` - the vertical position on screen of the top of the tile
+fn attach_tile(
+    vp: &VisualProperties,
+    post: &crate::dialogue::post::Post,
+    dialogue: &crate::dialogue::Dialogue,
+    top_y: i16,
+) -> Rectangle {
+    let left_x = if align_right(post, dialogue) {
+        vp.layout_screensize.x - vp.margin.x - vp.bubble_width as i16
+    } else {
+        vp.margin.x
+    };
+    Rectangle::new(
+        Point::new(left_x, top_y),
+        Point::new(left_x + vp.bubble_width as i16, top_y + vp.attach_height),
+    )
+}
+
+/// Return the Rectangle of the mark beside the text bubble of a Post matching a search
+///
+/// # Arguments
+///
+/// * `vp` - the visual properties to be applied to the mark
+/// * `post` - the post matching the search
+/// * `dialogue` - containing the Post for context info
+/// * `bubble` - the Rectangle of the text bubble, as drawn
+fn match_mark(
+    vp: &VisualProperties,
+    post: &crate::dialogue::post::Post,
+    dialogue: &crate::dialogue::Dialogue,
+    bubble: Rectangle,
+) -> Rectangle {
+    // the mark sits in the margin, on the outer side of the bubble
+    let left_x = if align_right(post, dialogue) { vp.layout_screensize.x - vp.margin.x + 1 } else { 0 };
+    Rectangle::new_with_style(
+        Point::new(left_x, bubble.tl().y),
+        Point::new(left_x + vp.margin.x - 2, bubble.br().y),
+        DrawStyle { fill_color: Some(PixelColor::Dark), stroke_color: None, stroke_width: 0 },
+    )
+}
+
 /// Return a TextView bubble representing a Dialogue Post
 ///
 /// # Arguments
@@ -69,19 +140,12 @@ fn bubble(
     let mut bubble_tv = default_textview(post, hilite, vp);
 
     // set alignment of bubble left/right
-    let mut align_right = false;
-    let mut anchor_x = vp.margin.x; // default to align left
-    if let Some(author) = dialogue.author(post.author_id()) {
-        if author.flag_is(AuthorFlag::Right) {
-            // align right
-            align_right = true;
-            anchor_x = vp.layout_screensize.x - vp.margin.x;
-        }
-    }
+    let right = align_right(post, dialogue);
+    let anchor_x = if right { vp.layout_screensize.x - vp.margin.x } else { vp.margin.x };
     // set the text bounds of the bubble and the growth direction
     let anchor = Point::new(anchor_x, anchor_y);
     let width = vp.bubble_width;
-    let text_bounds = match (topdown, align_right) {
+    let text_bounds = match (topdown, right) {
         (true, true) => TextBounds::GrowableFromTr(anchor, width),
         (true, false) => TextBounds::GrowableFromTl(anchor, width),
         (false, true) => TextBounds::GrowableFromBr(anchor, width),
@@ -276,12 +340,49 @@ impl Chat {
         }
     }
 
-    /// Set various status flags on a Post in the current Dialogue
+    /// Set or clear a status flag on a Post in the current Dialogue
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the index of the Post
+    /// * `flag` - Deleted shows a tombstone in place of the Post, Draft marks a Post not yet sent, and Hidden
+    ///   leaves the Post out of the layout
+    /// * `set` - true to set the flag, false to clear it
+    pub fn post_flag(&self, index: usize, flag: PostFlag, set: bool) -> Result<(), Error> {
+        xous::send_message(
+            self.cid,
+            xous::Message::new_scalar(
+                ChatOp::PostFlag as usize,
+                index,
+                flag.to_usize().unwrap(),
+                if set { 1 } else { 0 },
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// Returns the number of Posts matching a search, and steps the Chat UI thru them
+    ///
+    /// The most recent match in the current Dialogue is selected, and ↑ & ↓ step to the previous
+    /// and next match. Stepping on from the most recent match ends the search.
     ///
-    /// TODO: not implemented
-    pub fn post_flag(&self, _key: &str) -> Result<(), Error> {
-        log::warn!("not implemented");
-        Err(xous::Error::InternalError)
+    /// # Arguments
+    ///
+    /// * `query` - the text to look for, ignoring case
+    /// * `word` - match whole words only
+    /// * `all` - search all Dialogues in the pddb dict, rather than just the current Dialogue
+    ///
+    /// Error if unable to send the msg to the Chat UI server
+    pub fn search(&self, query: &str, word: bool, all: bool) -> Result<usize, Error> {
+        let search = Search { query: xous_ipc::String::from_str(query), word, all, count: 0 };
+        match Buffer::into_buf(search) {
+            Ok(mut buf) => match buf.lend_mut(self.cid, ChatOp::Search as u32) {
+                Ok(..) => Ok(buf.to_original::<api::Search, _>().unwrap().count),
+                Err(_) => Err(xous::Error::InternalError),
+            },
+            Err(_) => Err(xous::Error::InternalError),
+        }
     }
 
     /// Redraw our Chat UI.
@@ -453,8 +554,11 @@ pub fn server(
             }),
             Some(ChatOp::DialogueSave) => {
                 log::info!("ChatOp::DialogueSave");
-                ui.dialogue_save().expect("failed to save Dialogue");
-                ui.dialogue_read().expect("failed to read Dialogue");
+                // the Posts that failed to be written are held in the Dialogue, until the next save
+                match ui.dialogue_save() {
+                    Ok(_) => ui.dialogue_read().expect("failed to read Dialogue"),
+                    Err(e) => log::warn!("failed to save Dialogue: {e}"),
+                }
                 if allow_redraw {
                     ui.redraw().expect("CHAT couldn't redraw");
                 }
@@ -467,6 +571,8 @@ pub fn server(
                     Some(key) => Some(key.to_string()),
                     None => None,
                 };
+                // the Chat App has moved on from where the search began
+                ui.search_end(false);
                 ui.dialogue_set(dialogue.dict.as_str().unwrap(), dialogue_key.as_deref());
             }
             Some(ChatOp::GamChangeFocus) => {
@@ -532,13 +638,21 @@ pub fn server(
                         '↑' => {
                             log::info!("click ↑ : previous post");
                             ui.set_menu_mode(true); // ← & → activate menus
-                            ui.post_select(POST_SELECTED_PREV);
+                            if ui.search_active() {
+                                ui.search_step(false);
+                            } else {
+                                ui.post_select(POST_SELECTED_PREV);
+                            }
                             ui.redraw().expect("failed to redraw chat");
                             ui.event(Event::Up);
                         }
                         '↓' => {
                             log::info!("click ↓ : next post");
-                            ui.post_select(POST_SELECTED_NEXT);
+                            if ui.search_active() {
+                                ui.search_step(true);
+                            } else {
+                                ui.post_select(POST_SELECTED_NEXT);
+                            }
                             ui.redraw().expect("failed to redraw chat");
                             ui.event(Event::Down);
                         }
@@ -581,17 +695,17 @@ pub fn server(
             Some(ChatOp::PostAdd) => {
                 log::info!("ChatOp::PostAdd");
                 match dialogue_key {
-                    Some(ref dialogue_id) => {
+                    Some(_) => {
                         let buffer =
                             unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                         match buffer.to_original::<api::Post, _>() {
                             Ok(post) => ui
                                 .post_add(
-                                    &dialogue_id,
+                                    post.dialogue_id.as_str().unwrap(),
                                     post.author.as_str().unwrap(),
                                     post.timestamp,
                                     post.text.as_str().unwrap(),
-                                    None, // TODO implement
+                                    post.attach_url.as_ref().map(|url| url.as_str().unwrap()),
                                 )
                                 .unwrap(),
                             Err(e) => log::warn!("failed to deserialize Post: {:?}", e),
@@ -603,7 +717,17 @@ pub fn server(
             Some(ChatOp::PostDel) => {
                 xous::msg_scalar_unpack!(msg, index, _, _, _, {
                     log::info!("ChatOp::PostDel {index}");
-                    ui.post_del(index).expect("failed to delete post {index}");
+                    match ui.post_del(index) {
+                        Ok(_) => {
+                            if let Err(e) = ui.dialogue_save() {
+                                log::warn!("failed to save Dialogue: {e}");
+                            }
+                            if allow_redraw {
+                                ui.redraw().expect("CHAT couldn't redraw");
+                            }
+                        }
+                        Err(e) => log::warn!("failed to delete post {index}: {e}"),
+                    }
                 });
             }
             Some(ChatOp::PostFind) => {
@@ -617,7 +741,41 @@ pub fn server(
                 }
             }
             Some(ChatOp::PostFlag) => {
-                log::warn!("ChatOp::PostFlag not implemented");
+                xous::msg_scalar_unpack!(msg, index, flag, set, _, {
+                    log::info!("ChatOp::PostFlag {index}");
+                    match FromPrimitive::from_usize(flag) {
+                        Some(flag) => match ui.post_flag(index, flag, set != 0) {
+                            Ok(_) => {
+                                if let Err(e) = ui.dialogue_save() {
+                                    log::warn!("failed to save Dialogue: {e}");
+                                }
+                                if allow_redraw {
+                                    ui.redraw().expect("CHAT couldn't redraw");
+                                }
+                            }
+                            Err(e) => log::warn!("failed to flag post {index}: {e}"),
+                        },
+                        None => log::warn!("unknown PostFlag {flag}"),
+                    }
+                });
+            }
+            Some(ChatOp::Search) => {
+                log::info!("ChatOp::Search");
+                let mut buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                if let Ok(mut search) = buffer.to_original::<Search, _>() {
+                    let query = Query::new(search.query.as_str().unwrap(), search.word);
+                    search.count = ui.search(query, search.all);
+                    buffer.replace(search).expect("couldn't serialize return");
+                    if allow_redraw {
+                        ui.redraw().expect("CHAT couldn't redraw");
+                    }
+                } else {
+                    log::warn!("failed to serialize Search");
+                }
+            }
+            Some(ChatOp::SearchModal) => {
+                log::info!("ChatOp::SearchModal");
+                ui.search_modal();
             }
             Some(ChatOp::MenuAdd) => {
                 log::warn!("ChatOp::MenuAdd not implemented");
@@ -1,18 +1,15 @@
 use std::cmp::min;
-use std::convert::TryFrom;
+use std::collections::HashMap;
 use std::fmt::Write as TextWrite;
-use std::io::{Error, ErrorKind, Read, Write};
+use std::io::{Error, ErrorKind};
 
-use dialogue::{post::Post, Dialogue};
-use gam::{menu_matic, MenuMatic, UxRegistration};
+use dialogue::search::{Match, Query, Results};
+use dialogue::{attach::Attach, post::Post, store::Store, Dialogue};
+use gam::{menu_matic, MenuMatic, MenuPayload, UxRegistration};
 use graphics_server::api::GlyphStyle;
 use graphics_server::{DrawStyle, Gid, Line, PixelColor, Point, Rectangle, TextBounds, TextView};
 use locales::t;
 use modals::Modals;
-use rkyv::de::deserializers::AllocDeserializer;
-use rkyv::ser::serializers::WriteSerializer;
-use rkyv::ser::Serializer;
-use rkyv::Deserialize;
 use ticktimer_server::Ticktimer;
 use xous::{MessageEnvelope, CID};
 use xous_names::XousNames;
@@ -34,7 +31,8 @@ pub struct VisualProperties {
     pub margin: Point,        // margin to edge of canvas
     pub bubble_margin: Point, // margin of text in bubbles
     pub bubble_radius: u16,
-    pub bubble_space: i16, // spacing between text bubbles
+    pub bubble_space: i16,  // spacing between text bubbles
+    pub attach_height: i16, // height of the tile showing an attachment
 }
 #[allow(dead_code)]
 pub(crate) struct Ui {
@@ -49,6 +47,15 @@ pub(crate) struct Ui {
     pddb_dict: Option<String>,
     pddb_key: Option<String>,
     dialogue: Option<Dialogue>,
+    // the pages of the current Dialogue in the pddb
+    store: Option<Store>,
+    // other Dialogues of the same dict, holding Posts added since the last save
+    background: HashMap<String, (Dialogue, Store)>,
+    // images attached to Posts in the current Dialogue, by url
+    #[cfg(feature = "ditherpunk")]
+    bitmaps: HashMap<String, gam::Bitmap>,
+    // the Posts matching a search, stepped thru with ↑ & ↓
+    search: Option<Results>,
 
     // Callbacks:
     // callback to our own server
@@ -124,7 +131,15 @@ impl Ui {
         let screensize = gam.get_canvas_bounds(canvas).expect("couldn't get dimensions of content canvas");
         // TODO this is a stub - implement F1-4 actions and autocompletes
         let _icontray = Icontray::new(Some(xous::connect(sid).unwrap()), ["F1", "F2", "F3", "F4"]);
-        let menu_mgr = menu_matic(Vec::<MenuItem>::new(), app_menu, Some(xous::create_server().unwrap()))
+        let self_cid = xous::connect(sid).unwrap();
+        let search_item = MenuItem {
+            name: xous_ipc::String::from_str(t!("chat.search.item", locales::LANG)),
+            action_conn: Some(self_cid),
+            action_opcode: ChatOp::SearchModal as u32,
+            action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+            close_on_select: true,
+        };
+        let menu_mgr = menu_matic(vec![search_item], app_menu, Some(xous::create_server().unwrap()))
             .expect("couldn't create MenuMatic manager");
         let pddb = pddb::Pddb::new();
         pddb.try_mount();
@@ -157,6 +172,7 @@ impl Ui {
             bubble_margin: Point::new(4, 4),
             bubble_radius: 4,
             bubble_space: 4,
+            attach_height: (screensize.y - status_height as i16) / 3,
         };
         Ui {
             input: None,
@@ -165,7 +181,12 @@ impl Ui {
             pddb_dict: None,
             pddb_key: None,
             dialogue: None,
-            self_cid: xous::connect(sid).unwrap(),
+            store: None,
+            background: HashMap::new(),
+            #[cfg(feature = "ditherpunk")]
+            bitmaps: HashMap::new(),
+            search: None,
+            self_cid,
             app_cid,
             opcode_event,
             gam,
@@ -185,43 +206,29 @@ impl Ui {
         }
     }
 
-    /// Read the current Dialogue from pddb
+    /// Read the current Dialogue from pddb, with its most recent page of Posts
     pub fn dialogue_read(&mut self) -> Result<(), Error> {
         match (&self.pddb_dict, &self.pddb_key) {
             (Some(dict), Some(key)) => {
-                match self.pddb.get(&dict, &key, None, true, false, None, None::<fn()>) {
-                    Ok(mut pddb_key) => {
-                        let mut bytes = [0u8; dialogue::MAX_BYTES + 2];
-                        match pddb_key.read(&mut bytes) {
-                            Ok(_) => {
-                                // extract pos u16 from the first 2 bytes
-                                let pos: u16 = u16::from_be_bytes([bytes[0], bytes[1]]);
-                                let pos: usize = pos.into();
-                                // deserialize the Dialogue
-                                let archive = unsafe { rkyv::archived_value::<Dialogue>(&bytes, pos) };
-                                self.dialogue = match archive.deserialize(&mut AllocDeserializer {}) {
-                                    Ok(dialogue) => {
-                                        // show most recent posts onscreen
-                                        self.layout_selected = dialogue.post_last();
-                                        self.layout_range.clear();
-                                        self.layout_topdown = false;
-                                        Some(dialogue)
-                                    }
-                                    Err(e) => {
-                                        log::warn!("failed to deserialize Dialogue {}:{} {}", dict, key, e);
-                                        None
-                                    }
-                                };
-                                log::debug!("get '{}' = '{:?}'", key, self.dialogue);
-                            }
-                            Err(e) => log::warn!("failed to read {}: {e}", key),
-                        }
+                match Store::open(&self.pddb, dict, key) {
+                    Ok((dialogue, store)) => {
+                        // show most recent posts onscreen
+                        self.layout_selected =
+                            dialogue.post_last().and_then(|last| dialogue.post_visible(last, false));
+                        self.layout_range.clear();
+                        self.layout_topdown = false;
+                        self.dialogue = Some(dialogue);
+                        self.store = Some(store);
                     }
-                    Err(e) => {
-                        log::warn!("failed to get {}: {e}", key);
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
                         return Err(Error::new(ErrorKind::InvalidData, "missing"));
                     }
+                    Err(_) => {
+                        self.dialogue = None;
+                        self.store = None;
+                    }
                 }
+                log::debug!("get '{}' = '{:?}'", key, self.dialogue);
                 Ok(())
             }
             _ => {
@@ -231,47 +238,44 @@ impl Ui {
         }
     }
 
-    /// Save the current Dialogue to pddb
-    pub fn dialogue_save(&self) -> Result<(), Error> {
-        match (&self.dialogue, &self.pddb_dict, &self.pddb_key) {
-            (Some(dialogue), Some(dict), Some(key)) => {
-                let hint = Some(dialogue::MAX_BYTES + 2);
-                match self.pddb.get(&dict, &key, None, true, true, hint, None::<fn()>) {
-                    Ok(mut pddb_key) => {
-                        let mut buf = Vec::<u8>::new();
-                        // reserve 2 bytes to hold a u16 (see below)
-                        let reserved = 2;
-                        buf.push(0u8);
-                        buf.push(0u8);
-
-                        // serialize the Dialogue
-                        let mut serializer = WriteSerializer::with_pos(buf, reserved);
-                        let pos = serializer.serialize_value(dialogue).unwrap();
-                        let mut bytes = serializer.into_inner();
-
-                        // copy pop u16 into the first 2 bytes to enable the rkyv archive to be deserialised
-                        let pos: u16 = u16::try_from(pos).expect("data > u16");
-                        let pos_bytes = pos.to_be_bytes();
-                        bytes[0] = pos_bytes[0];
-                        bytes[1] = pos_bytes[1];
-                        match pddb_key.write(&bytes) {
-                            Ok(len) => {
-                                self.pddb.sync().ok();
-                                log::info!("Wrote {} bytes to {}:{}", len, dict, key);
-                            }
-                            Err(e) => {
-                                log::warn!("Error writing {}:{}: {:?}", dict, key, e);
-                            }
-                        }
-                    }
-                    Err(e) => log::warn!("failed to create {}:{}\n{}", dict, key, e),
+    /// Save the current Dialogue to pddb, along with any other Dialogue that has new Posts
+    ///
+    /// Only the pages holding a new or changed Post are written.
+    ///
+    /// Returns the first Error in writing a Dialogue, whose unwritten Posts are kept for the next save.
+    pub fn dialogue_save(&mut self) -> Result<(), Error> {
+        let mut result = Ok(());
+        let pddb = &self.pddb;
+        // a Dialogue other than the current one is held until it is written
+        self.background.retain(|_, (dialogue, store)| match store.save(pddb, dialogue) {
+            Ok(()) => false,
+            Err(e) => {
+                if result.is_ok() {
+                    result = Err(e);
                 }
-                Ok(())
+                true
             }
+        });
+        match (&mut self.dialogue, &mut self.store) {
+            (Some(dialogue), Some(store)) => result.and(store.save(&self.pddb, dialogue)),
             _ => {
                 log::warn!("missing dict, key or dialogue");
-                Ok(())
+                result
+            }
+        }
+    }
+
+    /// Read the next older page of the current Dialogue
+    ///
+    /// Returns Some(number of Posts read), or None when there are no older pages
+    fn dialogue_older(&mut self) -> Option<usize> {
+        match (&mut self.dialogue, &mut self.store) {
+            (Some(dialogue), Some(store)) => {
+                let count = store.load_older(&self.pddb, dialogue);
+                self.layout_range.clear();
+                count
             }
+            _ => None,
         }
     }
 
@@ -282,6 +286,10 @@ impl Ui {
     /// * `pddb_dict` - the pddb dict holding all Dialogues for this Chat App
     /// * `pddb_key` - the pddb key holding a Dialogue
     pub fn dialogue_set(&mut self, pddb_dict: &str, pddb_key: Option<&str>) {
+        // Posts held for other Dialogues must not be lost on the switch
+        self.dialogue_save().ok();
+        #[cfg(feature = "ditherpunk")]
+        self.bitmaps.clear();
         self.pddb_dict = Some(pddb_dict.to_string());
         self.pddb_key = pddb_key.map(|key| key.to_string());
         if self.pddb_key.is_none() {
@@ -296,6 +304,7 @@ impl Ui {
             Err(_) => {
                 if let Some(key) = &self.pddb_key {
                     self.dialogue = Some(Dialogue::new(&key));
+                    self.store = Some(Store::new(pddb_dict, key));
                     match self.dialogue_save() {
                         Ok(_) => log::info!("Dialogue created {}:{}", pddb_dict, key),
                         Err(e) => {
@@ -348,7 +357,166 @@ impl Ui {
     /// * `item` - an item action not handled by the Chat UI
     pub fn menu_add(&self, item: MenuItem) { self.menu_mgr.add_item(item); }
 
-    /// Add a new Post to the current Dialogue
+    /// Read all the older pages of the current Dialogue, keeping the same Post selected
+    fn dialogue_all(&mut self) {
+        while let Some(count) = self.dialogue_older() {
+            self.layout_selected = self.layout_selected.map(|selected| selected + count);
+        }
+    }
+
+    /// Search the Posts of the current Dialogue, or of all Dialogues in the dict, and select the most
+    /// recent match in the current Dialogue
+    ///
+    /// Returns the number of matching Posts
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the text to look for
+    /// * `all` - search all Dialogues in the pddb dict, rather than just the current Dialogue
+    pub fn search(&mut self, query: Query, all: bool) -> usize {
+        self.search_end(true);
+        if query.is_empty() {
+            return 0;
+        }
+        // other Dialogues are searched in the pddb, so must be up to date
+        self.dialogue_save().ok();
+        let mut matches = Vec::<Match>::new();
+        let key = self.pddb_key.clone().unwrap_or_default();
+        if let (true, Some(dict)) = (all, &self.pddb_dict) {
+            match self.pddb.list_keys(dict, None) {
+                Ok(keys) => {
+                    for other in keys.iter().filter(|other| other.ne(&&key)) {
+                        if let Ok((mut dialogue, mut store)) = Store::open(&self.pddb, dict, other) {
+                            store.load_all(&self.pddb, &mut dialogue);
+                            matches.extend(search_matches(&dialogue, other, &query));
+                        }
+                    }
+                }
+                Err(e) => log::warn!("failed to list pddb keys: {e}"),
+            }
+        }
+        self.dialogue_all();
+        if let Some(dialogue) = &self.dialogue {
+            matches.extend(search_matches(dialogue, &key, &query));
+        }
+        log::info!("search '{}' matched {} posts", query.text(), matches.len());
+        let count = matches.len();
+        if count > 0 {
+            self.search = Some(Results { query, matches, current: count - 1, origin: self.pddb_key.clone() });
+            self.search_show();
+        }
+        count
+    }
+
+    /// Present a Modal to search the Posts, in the current Dialogue or in all Dialogues
+    pub fn search_modal(&mut self) {
+        let text = match self
+            .modals
+            .alert_builder(t!("chat.search.title", locales::LANG))
+            .field(Some(t!("chat.search.query", locales::LANG).to_string()), None)
+            .build()
+        {
+            Ok(payloads) => payloads.content()[0].content.as_str().unwrap_or("").to_string(),
+            Err(e) => {
+                log::warn!("failed search modal: {:?}", e);
+                return;
+            }
+        };
+        let word = t!("chat.search.word", locales::LANG);
+        let all = t!("chat.search.all", locales::LANG);
+        self.modals.add_list(vec![word, all]).expect("failed modal add_list");
+        let options = self.modals.get_checkbox(t!("chat.search.options", locales::LANG)).unwrap_or_default();
+        let query = Query::new(&text, options.iter().any(|option| option.eq(word)));
+        if self.search(query, options.iter().any(|option| option.eq(all))) == 0 && !text.trim().is_empty() {
+            self.modals
+                .show_notification(t!("chat.search.none", locales::LANG), None)
+                .expect("notification failed");
+        }
+        self.redraw().expect("couldn't redraw screen");
+    }
+
+    /// Returns true while the Posts matching a search are stepped thru with ↑ & ↓
+    pub fn search_active(&self) -> bool { self.search.is_some() }
+
+    /// Select the next (newer) or previous (older) match of the search
+    ///
+    /// Stepping on from the most recent match ends the search.
+    pub fn search_step(&mut self, forward: bool) {
+        let end = match self.search.as_mut() {
+            Some(search) if forward && search.current + 1 < search.matches.len() => {
+                search.current += 1;
+                false
+            }
+            Some(search) if !forward && search.current > 0 => {
+                search.current -= 1;
+                false
+            }
+            Some(_) => forward,
+            None => return,
+        };
+        if end {
+            self.search_end(true);
+        } else {
+            self.search_show();
+        }
+    }
+
+    /// Show the current match of the search, in its Dialogue
+    fn search_show(&mut self) {
+        let (found, status) = match &self.search {
+            Some(search) => match search.matches.get(search.current) {
+                Some(found) => (
+                    found.clone(),
+                    format!(
+                        "{} {}/{} '{}'",
+                        t!("chat.search.status", locales::LANG),
+                        search.current + 1,
+                        search.matches.len(),
+                        search.query.text()
+                    ),
+                ),
+                None => return,
+            },
+            None => return,
+        };
+        if self.pddb_key.as_deref() != Some(found.dialogue.as_str()) {
+            if let Some(dict) = self.pddb_dict.clone() {
+                self.dialogue_set(&dict, Some(&found.dialogue));
+            }
+        }
+        // the match may be in a page not read yet
+        let count = match (&mut self.dialogue, &mut self.store) {
+            (Some(dialogue), Some(store)) => store.cover(&self.pddb, dialogue, found.timestamp),
+            _ => 0,
+        };
+        if count > 0 {
+            self.layout_range.clear();
+        }
+        if let Some(index) =
+            self.dialogue.as_ref().and_then(|d| d.post_index(found.timestamp, found.author_id))
+        {
+            self.layout_selected = Some(index);
+        }
+        self.set_status_text(&status);
+    }
+
+    /// End the search, and return to the Dialogue current at its start if `restore`
+    pub fn search_end(&mut self, restore: bool) {
+        if let Some(search) = self.search.take() {
+            if let (true, Some(origin), Some(dict)) = (restore, search.origin, self.pddb_dict.clone()) {
+                if self.pddb_key.as_deref() != Some(origin.as_str()) {
+                    self.dialogue_set(&dict, Some(&origin));
+                }
+            }
+            let idle = self.status_idle_text.clone();
+            self.set_status_text(&idle);
+        }
+    }
+
+    /// Add a new Post to a Dialogue
+    ///
+    /// A Post for a Dialogue other than the current one is held until the next `dialogue_save`,
+    /// so that a Chat App can keep several Dialogues up to date in the background.
     ///
     /// note: posts are sorted by timestamp, so:
     /// - `post_add` at beginning or end is fast (middle triggers a binary partition)
@@ -356,6 +524,7 @@ impl Ui {
     ///
     /// # Arguments
     ///
+    /// * `dialogue_id` - the pddb key of the Dialogue, or "" for the current Dialogue
     /// * `author` - the name of the Author of the Post
     /// * `timestamp` - the timestamp of the Post
     /// * `text` - the text content of the Post
@@ -368,33 +537,61 @@ impl Ui {
         text: &str,
         attach_url: Option<&str>,
     ) -> Result<(), Error> {
-        match (&self.pddb_key, &mut self.dialogue) {
-            (Some(pddb_key), Some(ref mut dialogue)) => {
-                if dialogue_id.len() == 0 || pddb_key.eq(&dialogue_id) {
-                    dialogue
-                        .post_add(author, timestamp, text, attach_url, Some((&self.vp, &self.gam)))
-                        .unwrap();
-                } else {
-                    log::warn!(
-                        "dropping Post as dialogue_id does not match pddb_key: '{}' vs '{}'",
-                        pddb_key,
-                        dialogue_id
-                    );
+        let current = match (&self.pddb_dict, &self.pddb_key) {
+            (Some(_), Some(pddb_key)) => dialogue_id.len() == 0 || pddb_key.eq(&dialogue_id),
+            _ => {
+                log::warn!("no pddb_key set to match dialogue_id");
+                return Ok(());
+            }
+        };
+        if !current && !self.background.contains_key(dialogue_id) {
+            let dict = self.pddb_dict.as_deref().unwrap_or_default();
+            let paged = match Store::open(&self.pddb, dict, dialogue_id) {
+                Ok(paged) => paged,
+                Err(_) => (Dialogue::new(dialogue_id), Store::new(dict, dialogue_id)),
+            };
+            self.background.insert(dialogue_id.to_string(), paged);
+        }
+        let paged = match current {
+            true => self.dialogue.as_mut().zip(self.store.as_mut()),
+            false => self.background.get_mut(dialogue_id).map(|(dialogue, store)| (dialogue, store)),
+        };
+        match paged {
+            Some((dialogue, store)) => {
+                // an older Post belongs among older pages, which must be read first
+                let count = store.cover(&self.pddb, dialogue, timestamp);
+                if current && count > 0 {
+                    self.layout_selected = self.layout_selected.map(|selected| selected + count);
+                    self.layout_range.clear();
                 }
+                dialogue.post_add(author, timestamp, text, attach_url, Some((&self.vp, &self.gam))).unwrap()
             }
-            (None, _) => log::warn!("no pddb_key set to match dialogue_id"),
-            (_, None) => log::warn!("no Dialogue available to add Post"),
+            None => log::warn!("no Dialogue available to add Post"),
+        }
+        if current {
+            self.post_announce(author, text);
         }
         Ok(())
     }
 
     /// Delete a Post from the current Dialogue
     ///
-    /// TODO: implement post_delete()
-
-    pub fn post_del(&self, _index: usize) -> Result<(), Error> {
-        log::warn!("not implemented");
-        Err(Error::new(ErrorKind::Other, "not implemented"))
+    /// # Arguments
+    ///
+    /// * `index` - index of the Post to delete
+    pub fn post_del(&mut self, index: usize) -> Result<(), Error> {
+        match &mut self.dialogue {
+            Some(dialogue) => {
+                dialogue.post_del(index)?;
+                self.layout_range.clear();
+                self.layout_selected = match (self.layout_selected, dialogue.post_last()) {
+                    (Some(selected), Some(last)) => dialogue.post_visible(min(selected, last), false),
+                    _ => None,
+                };
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::NotFound, "no Dialogue")),
+        }
     }
 
     /// Returns Some(index) of a matching Post by Author and Timestamp, or None
@@ -422,12 +619,27 @@ impl Ui {
         }
     }
 
-    /// Set various status flags on a Post in the current Dialogue
+    /// Set or clear a status flag on a Post in the current Dialogue
     ///
-    /// TODO: not implemented
-    pub fn post_flag(&self, _key: u32) -> Result<(), Error> {
-        log::warn!("not implemented");
-        Err(Error::new(ErrorKind::Other, "not implemented"))
+    /// # Arguments
+    ///
+    /// * `index` - index of the Post
+    /// * `flag` - the flag to set or clear
+    /// * `set` - true to set the flag, false to clear it
+    pub fn post_flag(&mut self, index: usize, flag: PostFlag, set: bool) -> Result<(), Error> {
+        match &mut self.dialogue {
+            Some(dialogue) => {
+                dialogue.post_flag(index, flag, set)?;
+                // the Post may have changed height, or gone from view
+                self.layout_range.clear();
+                if flag == PostFlag::Hidden && set && self.layout_selected == Some(index) {
+                    self.layout_selected =
+                        dialogue.post_visible(index, false).or_else(|| dialogue.post_visible(index, true));
+                }
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::NotFound, "no Dialogue")),
+        }
     }
 
     /// Set the Selected Post to an arbitrary index
@@ -436,37 +648,77 @@ impl Ui {
     ///
     /// * `index` - POST_SELECT_NEXT or POST_SELECT_PREV or an arbitraty index
     pub fn post_select(&mut self, index: usize) {
+        if index == POST_SELECTED_PREV {
+            // read older pages until there is a Post to move up to, or there are no more
+            while let Some(selected) = self.layout_selected {
+                let prev = match &self.dialogue {
+                    Some(dialogue) => selected.checked_sub(1).and_then(|i| dialogue.post_visible(i, false)),
+                    None => None,
+                };
+                if prev.is_some() {
+                    break;
+                }
+                match self.dialogue_older() {
+                    Some(count) => self.layout_selected = Some(selected + count),
+                    None => break,
+                }
+            }
+        }
         self.layout_selected = match &self.dialogue {
             Some(dialogue) => {
                 match dialogue.post_last() {
                     Some(last_post) => {
+                        // Hidden posts are passed over
                         match (index, self.layout_selected) {
                             (POST_SELECTED_NEXT, Some(selected)) => {
-                                if selected >= last_post {
-                                    self.event(Event::Bottom);
-                                    Some(last_post)
-                                } else {
-                                    Some(selected + 1)
+                                match dialogue.post_visible(selected + 1, true) {
+                                    Some(next) if selected < last_post => Some(next),
+                                    _ => {
+                                        self.event(Event::Bottom);
+                                        Some(min(selected, last_post))
+                                    }
                                 }
                             }
                             (POST_SELECTED_PREV, Some(selected)) => {
-                                if selected == 0 {
-                                    self.event(Event::Top);
-                                    Some(selected)
-                                } else {
-                                    Some(selected - 1)
+                                match dialogue.post_visible(selected.saturating_sub(1), false) {
+                                    Some(prev) if selected > 0 => Some(prev),
+                                    _ => {
+                                        self.event(Event::Top);
+                                        Some(selected)
+                                    }
                                 }
                             }
-                            (index, _) => Some(min(index, last_post)), // arbitrary post
+                            (index, _) => {
+                                // arbitrary post
+                                let index = min(index, last_post);
+                                dialogue
+                                    .post_visible(index, false)
+                                    .or_else(|| dialogue.post_visible(index, true))
+                            }
                         }
                     }
                     None => None,
                 }
             }
             None => None,
+        };
+        let selected = self.layout_selected.and_then(|selected| self.post_get(selected)).map(|post| {
+            let author = match self.dialogue.as_ref().and_then(|d| d.author(post.author_id())) {
+                Some(author) => author.name.clone(),
+                None => String::new(),
+            };
+            (author, post.text().to_string())
+        });
+        if let Some((author, text)) = selected {
+            self.post_announce(&author, &text);
         }
     }
 
+    /// Speak a Post through the GAM screen reader, if it is on
+    fn post_announce(&self, author: &str, text: &str) {
+        self.gam.announce(self.token, &format!("{}: {}", author, text)).ok();
+    }
+
     pub fn get_menu_mode(&self) -> bool { self.menu_mode }
 
     pub fn set_menu_mode(&mut self, menu_mode: bool) { self.menu_mode = menu_mode; }
@@ -646,6 +898,9 @@ impl Ui {
                 let mut total_height = 0;
                 self.layout_range.clear();
                 for (i, post) in search_window.enumerate() {
+                    if post.flag_is(PostFlag::Hidden) {
+                        continue;
+                    }
                     let next_height = if let Some(bb) = post.bounding_box {
                         bb.height() + self.vp.bubble_space as u32 + self.vp.bubble_margin.y as u32
                     } else {
@@ -672,6 +927,11 @@ impl Ui {
                             0
                         }
                     };
+                    // an attachment is shown in a tile below the text bubble
+                    let next_height = match post.attach() {
+                        Some(_) => next_height + (self.vp.attach_height + self.vp.bubble_space) as u32,
+                        None => next_height,
+                    };
                     if total_height + next_height > self.vp.layout_screensize.y as u32 {
                         if self.layout_topdown {
                             self.layout_range = (starting_at..starting_at + i).collect();
@@ -744,10 +1004,30 @@ impl Ui {
                         continue;
                     }
                 };
+                if post.flag_is(PostFlag::Hidden) {
+                    continue;
+                }
+                // bottom-up, the attachment tile is drawn first, as it sits below the text bubble
+                if let (false, Some(attach)) = (self.layout_topdown, post.attach()) {
+                    let tile = attach_tile(&self.vp, post, dialogue, y - self.vp.attach_height);
+                    #[cfg(feature = "ditherpunk")]
+                    attach_draw(&self.gam, &self.pddb, &mut self.bitmaps, &self.vp, attach, tile);
+                    #[cfg(not(feature = "ditherpunk"))]
+                    attach_draw(&self.gam, &self.vp, attach, tile);
+                    y -= self.vp.attach_height + self.vp.bubble_space;
+                }
                 let highlight =
                     if let Some(selected) = self.layout_selected { selected == post_index } else { false };
                 let mut bubble_tv = bubble(&self.vp, self.layout_topdown, post, dialogue, highlight, y);
                 self.gam.post_textview(&mut bubble_tv).expect("couldn't render bubble textview");
+                // a Post matching the search is marked beside its text bubble
+                if let (Some(search), Some(actual_r)) = (&self.search, bubble_tv.bounds_computed) {
+                    if search.query.matches_post(post) {
+                        self.gam
+                            .draw_rectangle(self.vp.canvas, match_mark(&self.vp, post, dialogue, actual_r))
+                            .expect("couldn't draw search mark");
+                    }
+                }
                 // double check the actual bounds against expected bounds
                 match bubble_tv.bounds_computed {
                     Some(actual_r) => {
@@ -762,6 +1042,15 @@ impl Ui {
                         }
                         if self.layout_topdown {
                             y += actual_r.height() as i16;
+                            if let Some(attach) = post.attach() {
+                                y += self.vp.bubble_space;
+                                let tile = attach_tile(&self.vp, post, dialogue, y);
+                                #[cfg(feature = "ditherpunk")]
+                                attach_draw(&self.gam, &self.pddb, &mut self.bitmaps, &self.vp, attach, tile);
+                                #[cfg(not(feature = "ditherpunk"))]
+                                attach_draw(&self.gam, &self.vp, attach, tile);
+                                y += self.vp.attach_height;
+                            }
                         } else {
                             y -= actual_r.height() as i16;
                         }
@@ -806,3 +1095,87 @@ impl Ui {
         }
     }
 }
+
+/// Draw the attachment of a Post in its tile: a png held in the pddb as an image, and anything
+/// else as a placeholder showing the url
+#[cfg(feature = "ditherpunk")]
+fn attach_draw(
+    gam: &gam::Gam,
+    pddb: &pddb::Pddb,
+    bitmaps: &mut HashMap<String, gam::Bitmap>,
+    vp: &VisualProperties,
+    attach: &Attach,
+    tile: Rectangle,
+) {
+    if !attach_visible(vp, tile) {
+        return;
+    }
+    if let (Attach::Png(url), Some((dict, key))) = (attach, attach.pddb_location()) {
+        if !bitmaps.contains_key(url) {
+            let fit = Point::new(tile.width() as i16 - 2 * vp.bubble_margin.x, tile.height() as i16);
+            match pddb.get(dict, key, None, false, false, None, None::<fn()>) {
+                Ok(pddb_key) => match gam::DecodePng::new(pddb_key) {
+                    Ok(mut png) => {
+                        bitmaps.insert(url.to_string(), gam::Bitmap::from_png(&mut png, Some(fit)));
+                    }
+                    Err(e) => log::warn!("failed to decode png {}: {:?}", url, e),
+                },
+                Err(e) => log::warn!("failed to get {}: {e}", url),
+            }
+        }
+        if let Some(bm) = bitmaps.get_mut(url) {
+            // the bitmap is kept at the origin, and moved into the tile just to be drawn
+            let offset = Point::new(tile.tl.x + vp.bubble_margin.x, tile.tl.y);
+            bm.translate(offset);
+            gam.draw_bitmap(vp.canvas, bm).expect("couldn't draw attachment");
+            bm.translate(Point::new(-offset.x, -offset.y));
+            return;
+        }
+    }
+    attach_placeholder(gam, vp, attach, tile);
+}
+
+/// Draw the attachment of a Post in its tile, as a placeholder showing the url
+#[cfg(not(feature = "ditherpunk"))]
+fn attach_draw(gam: &gam::Gam, vp: &VisualProperties, attach: &Attach, tile: Rectangle) {
+    if attach_visible(vp, tile) {
+        attach_placeholder(gam, vp, attach, tile);
+    }
+}
+
+/// Return true if the tile lies wholly within the layout area, below the status bar
+fn attach_visible(vp: &VisualProperties, tile: Rectangle) -> bool {
+    tile.tl.y >= vp.status_height as i16 && tile.br.y <= vp.total_screensize.y
+}
+
+fn attach_placeholder(gam: &gam::Gam, vp: &VisualProperties, attach: &Attach, tile: Rectangle) {
+    let mut tv = TextView::new(vp.canvas, TextBounds::BoundingBox(tile));
+    tv.border_width = 1;
+    tv.draw_border = true;
+    tv.clear_area = true;
+    tv.rounded_border = Some(vp.bubble_radius);
+    tv.style = GlyphStyle::Small;
+    tv.margin = vp.bubble_margin;
+    tv.ellipsis = true;
+    tv.insertion = None;
+    let kind = match attach {
+        Attach::Png(_) | Attach::Jpg(_) => t!("chat.attach.image", locales::LANG),
+        Attach::Other(_) => t!("chat.attach.file", locales::LANG),
+    };
+    write!(tv.text, "{}\n{}", kind, attach.url()).ok();
+    gam.post_textview(&mut tv).expect("couldn't draw attachment placeholder");
+}
+
+/// Returns the Matches of a Query in a Dialogue, oldest first
+fn search_matches(dialogue: &Dialogue, key: &str, query: &Query) -> Vec<Match> {
+    dialogue
+        .post_search(query)
+        .into_iter()
+        .filter_map(|i| dialogue.post_get(i))
+        .map(|post| Match {
+            dialogue: key.to_string(),
+            timestamp: post.timestamp(),
+            author_id: post.author_id(),
+        })
+        .collect()
+}
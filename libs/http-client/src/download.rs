@@ -0,0 +1,177 @@
+// Resumable downloads into the PDDB
+//
+// The body is written straight into a PDDB key as it arrives, so it never has to fit in RAM. While
+// a download is incomplete a sibling key, `<key>.partial`, holds the validator (ETag or
+// Last-Modified) of the response being saved. The next attempt, whether a retry or a later call
+// after a reboot, asks for the rest with `Range` and `If-Range`; if the resource changed meanwhile
+// the server sends all of it again and the key is started over.
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::{Client, Error};
+
+const PARTIAL_SUFFIX: &str = ".partial";
+const CHUNK_LEN: usize = 4096;
+
+impl Client {
+    /// Downloads `url` into `key` of `dict`, calling `progress` with the bytes saved so far and the
+    /// total, if the server said. Returns the length of the key once complete.
+    pub fn download_to_pddb(
+        &self,
+        url: &str,
+        dict: &str,
+        key: &str,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<u64, Error> {
+        let pddb = pddb::Pddb::new();
+        let partial = format!("{}{}", key, PARTIAL_SUFFIX);
+        let mut attempt = 1;
+        loop {
+            match self.download_once(&pddb, url, dict, key, &partial, &mut progress) {
+                Ok(len) => {
+                    pddb.delete_key(dict, &partial, None).ok();
+                    pddb.sync().ok();
+                    return Ok(len);
+                }
+                Err(e @ Error::Transport(_)) | Err(e @ Error::Io(_)) if attempt < self.policy.attempts => {
+                    // whatever arrived is kept, so the next try only fetches the remainder
+                    log::info!("download of {} interrupted ({}), resuming", url, e);
+                    pddb.sync().ok();
+                }
+                Err(Error::Status(code, body))
+                    if attempt < self.policy.attempts && crate::retryable(code) =>
+                {
+                    log::info!("download of {} returned {}: {}", url, code, body);
+                }
+                Err(e) => {
+                    pddb.sync().ok();
+                    return Err(self.diagnose(e));
+                }
+            }
+            std::thread::sleep(self.policy.backoff(attempt));
+            attempt += 1;
+        }
+    }
+
+    fn download_once(
+        &self,
+        pddb: &pddb::Pddb,
+        url: &str,
+        dict: &str,
+        key: &str,
+        partial: &str,
+        progress: &mut impl FnMut(u64, Option<u64>),
+    ) -> Result<u64, Error> {
+        let validator = read_validator(pddb, dict, partial);
+        let saved = match validator {
+            Some(_) => pddb
+                .get(dict, key, None, false, false, None, None::<fn()>)
+                .and_then(|k| k.attributes())
+                .map(|a| a.len as u64)
+                .unwrap_or(0),
+            None => 0,
+        };
+        let mut request = self.request("GET", url);
+        if let (Some(validator), true) = (&validator, saved > 0) {
+            request = request.set("Range", &format!("bytes={}-", saved)).set("If-Range", validator);
+        }
+        // the retries happen around the whole download, so that a broken body resumes too
+        let response = match request.call() {
+            Ok(response) => response,
+            // asked for the bytes after the end: it was all there already
+            Err(ureq::Error::Status(416, _)) if saved > 0 => return Ok(saved),
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(Error::Status(code, response.into_string().unwrap_or_default()));
+            }
+            Err(ureq::Error::Transport(e)) => return Err(Error::Transport(e.to_string())),
+        };
+
+        let content_len = response.header("Content-Length").and_then(|s| s.parse::<u64>().ok());
+        let (offset, total) = match response.status() {
+            206 => match response.header("Content-Range").and_then(parse_content_range) {
+                Some((start, total)) if start == saved => (saved, total),
+                _ => {
+                    return Err(Error::Transport(format!(
+                        "unexpected Content-Range {:?}",
+                        response.header("Content-Range")
+                    )));
+                }
+            },
+            _ => {
+                // the whole resource: start the key over, and remember what is being saved
+                pddb.delete_key(dict, key, None).ok();
+                match response.header("ETag").or_else(|| response.header("Last-Modified")) {
+                    Some(validator) => write_validator(pddb, dict, partial, validator)?,
+                    None => {
+                        pddb.delete_key(dict, partial, None).ok();
+                    }
+                }
+                (0, content_len)
+            }
+        };
+
+        let mut file = pddb
+            .get(dict, key, None, true, true, total.map(|t| t as usize), None::<fn()>)
+            .map_err(Error::Pddb)?;
+        file.seek(SeekFrom::Start(offset)).map_err(Error::Pddb)?;
+        let mut written = offset;
+        progress(written, total);
+        let mut reader = response.into_reader();
+        let mut chunk = [0u8; CHUNK_LEN];
+        loop {
+            let len = reader.read(&mut chunk)?;
+            if len == 0 {
+                break;
+            }
+            file.write_all(&chunk[..len]).map_err(Error::Pddb)?;
+            written += len as u64;
+            progress(written, total);
+        }
+        match total {
+            Some(total) if written < total => {
+                Err(Error::Io(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "body ended early")))
+            }
+            _ => Ok(written),
+        }
+    }
+}
+
+fn read_validator(pddb: &pddb::Pddb, dict: &str, partial: &str) -> Option<String> {
+    let mut key = pddb.get(dict, partial, None, false, false, None, None::<fn()>).ok()?;
+    let mut validator = String::new();
+    key.read_to_string(&mut validator).ok()?;
+    Some(validator).filter(|v| !v.is_empty())
+}
+
+fn write_validator(pddb: &pddb::Pddb, dict: &str, partial: &str, validator: &str) -> Result<(), Error> {
+    pddb.delete_key(dict, partial, None).ok();
+    let mut key = pddb
+        .get(dict, partial, None, true, true, Some(validator.len()), None::<fn()>)
+        .map_err(Error::Pddb)?;
+    key.write_all(validator.as_bytes()).map_err(Error::Pddb)?;
+    pddb.sync().map_err(Error::Pddb)
+}
+
+/// Parses `bytes <start>-<end>/<total>` into the start and, unless it is `*`, the total
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_content_range;
+
+    #[test]
+    fn content_range() {
+        assert_eq!(parse_content_range("bytes 100-199/1000"), Some((100, Some(1000))));
+        assert_eq!(parse_content_range(" bytes 0-0/1 "), Some((0, Some(1))));
+        // the total may be unknown
+        assert_eq!(parse_content_range("bytes 512-1023/*"), Some((512, None)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+        assert_eq!(parse_content_range("bytes 0-9"), None);
+        assert_eq!(parse_content_range(""), None);
+    }
+}
@@ -0,0 +1,269 @@
+// HTTP/1.1 client for apps
+//
+// A thin layer over `ureq` and the `tls` connector that gives networked apps one place for the
+// things each of them used to get slightly wrong: bearer tokens, JSON bodies, timeouts, retrying
+// when the Wi-Fi drops out, and telling a captive portal apart from a broken server. Large bodies
+// can be streamed to the PDDB with `Client::download_to_pddb()`, which resumes where it left off.
+pub mod download;
+
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use net::ConnectivityState;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tls::xtls::TlsConnector;
+
+const ACCEPT: &str = "Accept";
+const ACCEPT_JSON: &str = "application/json";
+const AUTHORIZATION: &str = "Authorization";
+const BEARER: &str = "Bearer ";
+const CONTENT_TYPE: &str = "Content-Type";
+const RETRY_AFTER: &str = "Retry-After";
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// captive portals redirect everything, so following many redirects only hides them
+pub const MAX_REDIRECTS: u32 = 3;
+
+#[derive(Debug)]
+pub enum Error {
+    /// the device is not on a network that reaches the internet
+    Offline,
+    /// a captive portal is intercepting requests; the sign-in page, if known
+    CaptivePortal(Option<String>),
+    /// the server answered with an error status, and this body
+    Status(u16, String),
+    Transport(String),
+    Json(String),
+    Io(std::io::Error),
+    Pddb(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Offline => write!(f, "network is offline"),
+            Error::CaptivePortal(Some(portal)) => write!(f, "sign in to the network at {}", portal),
+            Error::CaptivePortal(None) => write!(f, "sign in to the network first"),
+            Error::Status(code, body) => write!(f, "HTTP {}: {}", code, body),
+            Error::Transport(e) => write!(f, "transport error: {}", e),
+            Error::Json(e) => write!(f, "bad JSON: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Pddb(e) => write!(f, "PDDB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self { Error::Io(e) }
+}
+
+/// When and how often failed requests are repeated
+///
+/// Transport errors and the statuses that mean "try again later" (408, 429, 502, 503 and 504) are
+/// retried after a backoff that doubles each time, or after the server's `Retry-After` if shorter
+/// than `max_backoff`. Requests that are not idempotent are only retried if `retry_post` is set.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// total tries, including the first
+    pub attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self { RetryPolicy { attempts: 1, ..Default::default() } }
+
+    /// The pause before try number `attempt` (counting from 1 for the first retry)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_backoff.checked_mul(factor).unwrap_or(self.max_backoff).min(self.max_backoff)
+    }
+}
+
+pub struct Client {
+    agent: ureq::Agent,
+    policy: RetryPolicy,
+    token: Option<String>,
+    /// asked about connectivity when a request fails
+    net: net::NetManager,
+}
+
+impl Client {
+    pub fn new() -> Client { Client::with_policy(RetryPolicy::default()) }
+
+    pub fn with_policy(policy: RetryPolicy) -> Client {
+        let agent = ureq::builder()
+            .tls_connector(Arc::new(TlsConnector {}))
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .redirects(MAX_REDIRECTS)
+            .build();
+        Client { agent, policy, token: None, net: net::NetManager::new() }
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request, or nothing if `None`
+    pub fn set_bearer(&mut self, token: Option<&str>) { self.token = token.map(|t| t.to_string()); }
+
+    pub fn policy(&self) -> &RetryPolicy { &self.policy }
+
+    /// A request carrying the bearer token, for anything the helpers below don't cover. Send it with
+    /// `Client::send()` to get the retry policy.
+    pub fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.token {
+            Some(token) => request.set(AUTHORIZATION, &format!("{}{}", BEARER, token)),
+            None => request,
+        }
+    }
+
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        let response = self.send(self.request("GET", url).set(ACCEPT, ACCEPT_JSON), None)?;
+        self.into_json(response)
+    }
+
+    pub fn post_json<B: Serialize, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T, Error> {
+        self.send_json("POST", url, body)
+    }
+
+    pub fn put_json<B: Serialize, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T, Error> {
+        self.send_json("PUT", url, body)
+    }
+
+    pub fn delete(&self, url: &str) -> Result<(), Error> {
+        self.send(self.request("DELETE", url), None).map(|_| ())
+    }
+
+    pub fn get_string(&self, url: &str) -> Result<String, Error> {
+        let response = self.send(self.request("GET", url), None)?;
+        response.into_string().map_err(|e| self.diagnose(Error::Io(e)))
+    }
+
+    /// Streams the body of a GET; the body itself is not retried if it breaks off part way
+    pub fn get_reader(&self, url: &str) -> Result<Box<dyn Read + Send + Sync + 'static>, Error> {
+        Ok(self.send(self.request("GET", url), None)?.into_reader())
+    }
+
+    fn send_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        url: &str,
+        body: &B,
+    ) -> Result<T, Error> {
+        let body = serde_json::to_vec(body).map_err(|e| Error::Json(e.to_string()))?;
+        let request = self.request(method, url).set(ACCEPT, ACCEPT_JSON).set(CONTENT_TYPE, ACCEPT_JSON);
+        let response = self.send(request, Some(&body))?;
+        self.into_json(response)
+    }
+
+    fn into_json<T: DeserializeOwned>(&self, response: ureq::Response) -> Result<T, Error> {
+        // a portal answering in place of the server usually shows up as HTML where JSON was expected
+        response.into_json::<T>().map_err(|e| self.diagnose(Error::Json(e.to_string())))
+    }
+
+    /// Sends `request` with `body`, following the retry policy
+    pub fn send(&self, request: ureq::Request, body: Option<&[u8]>) -> Result<ureq::Response, Error> {
+        let idempotent = !matches!(request.method(), "POST" | "PATCH");
+        let attempts = if idempotent || self.policy.retry_post { self.policy.attempts.max(1) } else { 1 };
+        let mut attempt = 1;
+        loop {
+            let result = match body {
+                Some(body) => request.clone().send_bytes(body),
+                None => request.clone().call(),
+            };
+            let wait = match result {
+                Ok(response) => return Ok(response),
+                Err(ureq::Error::Status(code, response)) => {
+                    let retry_after = response
+                        .header(RETRY_AFTER)
+                        .and_then(|s| s.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    if attempt >= attempts || !retryable(code) {
+                        let body = response.into_string().unwrap_or_default();
+                        return Err(Error::Status(code, body));
+                    }
+                    log::info!("{} {} returned {}, retrying", request.method(), request.url(), code);
+                    retry_after.filter(|d| *d <= self.policy.max_backoff)
+                }
+                Err(ureq::Error::Transport(e)) => {
+                    log::info!("{} {} failed: {}", request.method(), request.url(), e);
+                    if attempt >= attempts {
+                        return Err(self.diagnose(Error::Transport(e.to_string())));
+                    }
+                    // no point waiting out the backoff on a network that won't come back by itself
+                    match self.connectivity() {
+                        Some(e @ Error::CaptivePortal(_)) => return Err(e),
+                        _ => None,
+                    }
+                }
+            };
+            std::thread::sleep(wait.unwrap_or_else(|| self.policy.backoff(attempt)));
+            attempt += 1;
+        }
+    }
+
+    /// Replaces `e` with `Error::Offline` or `Error::CaptivePortal` if that is what the net service
+    /// thinks is going on
+    pub(crate) fn diagnose(&self, e: Error) -> Error { self.connectivity().unwrap_or(e) }
+
+    fn connectivity(&self) -> Option<Error> {
+        let connectivity = self.net.connectivity().ok()?;
+        match connectivity.state {
+            ConnectivityState::Captive => {
+                Some(Error::CaptivePortal(connectivity.portal.map(|p| p.to_string())))
+            }
+            ConnectivityState::Offline => Some(Error::Offline),
+            _ => None,
+        }
+    }
+}
+
+fn retryable(code: u16) -> bool { matches!(code, 408 | 429 | 502 | 503 | 504) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(4), Duration::from_secs(4));
+        assert_eq!(policy.backoff(5), Duration::from_secs(8));
+        assert_eq!(policy.backoff(6), Duration::from_secs(8));
+        // no overflow, however long it goes on
+        assert_eq!(policy.backoff(40), Duration::from_secs(8));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(8));
+        // the first retry waits the initial backoff, even if called with 0
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        for code in [408, 429, 502, 503, 504] {
+            assert!(retryable(code), "{}", code);
+        }
+        for code in [200, 301, 400, 401, 403, 404, 409, 500, 501, 505] {
+            assert!(!retryable(code), "{}", code);
+        }
+    }
+}
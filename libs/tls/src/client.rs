@@ -0,0 +1,317 @@
+// Client certificate authentication (mutual tls)
+//
+// The device key pair is generated on-device, and only the Certificate Signing Request
+// ever leaves the device. The certificate chain issued in response is saved to the pddb
+// and presented to any host that requests client authentication.
+//
+// The private key is wrapped (AES-KWP) with the user's root key before it is saved to the
+// pddb, so it is not exposed by unlocking the basis alone. Connections to the root-keys server
+// are rationed, so the wrapping is done by the net crate's key wrap server on our behalf
+// (see `net::KeyWrapper`). Using the identity may prompt for the user's password.
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Write};
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use x509_parser::pem::Pem;
+use x509_parser::prelude::parse_x509_certificate;
+
+use crate::Tls;
+
+/// PDDB Dict for the tls client identity
+const TLS_CLIENT_DICT: &str = "tls.client";
+/// PDDB key for the wrapped PKCS#8 DER encoded device private key (u16 plaintext length prefixed)
+const TLS_CLIENT_KEY: &str = "key";
+/// PDDB key for the PEM encoded Certificate Signing Request
+const TLS_CLIENT_CSR: &str = "csr";
+/// PDDB key for the issued certificate chain (u16 length prefixed DER certificates)
+const TLS_CLIENT_CHAIN: &str = "chain";
+/// the maximum length of the certificate chain saved in the pddb
+pub const MAX_CHAIN_BYTES: usize = 8192;
+
+impl Tls {
+    /// Generates a new device key pair and Certificate Signing Request
+    ///
+    /// Any existing client identity (key, csr & certificate chain) is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `common_name` - the subject CN for the CSR (i.e. the device or user name)
+    ///
+    /// # Returns
+    ///
+    /// the PEM encoded Certificate Signing Request, to be signed by the issuing CA
+    pub fn client_csr_new(&self, common_name: &str) -> Result<String, Error> {
+        let (csr, key) = new_csr(common_name)?;
+        let wrapped = key_wrapper()?.wrap(&key).map_err(|e| {
+            log::warn!("failed to wrap client key: {:?}", e);
+            Error::new(ErrorKind::PermissionDenied, "failed to wrap client key")
+        })?;
+        self.del_client_identity().ok();
+        self.client_write(TLS_CLIENT_KEY, &encode_wrapped_key(key.len(), &wrapped)?)?;
+        self.client_write(TLS_CLIENT_CSR, csr.as_bytes())?;
+        log::info!("generated client key pair & csr for {common_name}");
+        Ok(csr)
+    }
+
+    /// Returns the PEM encoded Certificate Signing Request saved in the pddb
+    pub fn client_csr(&self) -> Option<String> {
+        self.client_read(TLS_CLIENT_CSR).and_then(|bytes| String::from_utf8(bytes).ok())
+    }
+
+    /// Saves the certificate chain issued in response to the Certificate Signing Request
+    ///
+    /// # Arguments
+    ///
+    /// * `pem` - the PEM encoded certificate chain, leaf certificate first
+    ///
+    /// # Returns
+    ///
+    /// the number of certificates saved
+    pub fn save_client_chain_pem(&self, pem: &[u8]) -> Result<usize, Error> {
+        let chain: Vec<CertificateDer> = Pem::iter_from_buffer(pem)
+            .filter_map(|pem| pem.ok())
+            .filter(|pem| pem.label == "CERTIFICATE")
+            .map(|pem| CertificateDer::from(pem.contents))
+            .collect();
+        self.save_client_chain(&chain)?;
+        Ok(chain.len())
+    }
+
+    /// Saves the certificate chain issued in response to the Certificate Signing Request
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - the DER encoded certificate chain, leaf certificate first
+    pub fn save_client_chain(&self, chain: &[CertificateDer]) -> Result<(), Error> {
+        if chain.len() == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "empty certificate chain"));
+        }
+        if self.client_read(TLS_CLIENT_KEY).is_none() {
+            return Err(Error::new(ErrorKind::NotFound, "no client key pair (generate a csr first)"));
+        }
+        if let Err(e) = parse_x509_certificate(chain[0].as_ref()) {
+            log::warn!("failed to parse leaf certificate: {e}");
+            return Err(Error::from(ErrorKind::InvalidData));
+        }
+        self.client_write(TLS_CLIENT_CHAIN, &encode_chain(chain)?)
+    }
+
+    /// Returns the certificate chain saved in the pddb, leaf certificate first
+    pub fn client_chain(&self) -> Option<Vec<CertificateDer<'static>>> {
+        decode_chain(&self.client_read(TLS_CLIENT_CHAIN)?)
+    }
+
+    /// Returns the client identity (certificate chain & private key) if one has been issued
+    ///
+    /// # Returns
+    ///
+    /// Ok(None) if no certificate chain has been saved, or an Error if there is a chain but
+    /// the private key is missing or cannot be unwrapped
+    pub fn client_identity(
+        &self,
+    ) -> Result<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>, Error> {
+        let chain = match self.client_chain() {
+            Some(chain) => chain,
+            None => return Ok(None),
+        };
+        let bytes = self
+            .client_read(TLS_CLIENT_KEY)
+            .ok_or(Error::new(ErrorKind::NotFound, "client certificate without a client key"))?;
+        let (len, wrapped) = decode_wrapped_key(&bytes)?;
+        let key = key_wrapper()?.unwrap(wrapped, len).map_err(|e| {
+            log::warn!("failed to unwrap client key: {:?}", e);
+            Error::new(ErrorKind::PermissionDenied, "failed to unwrap client key")
+        })?;
+        Ok(Some((chain, PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key)))))
+    }
+
+    /// Deletes the client identity (key, csr & certificate chain) from the pddb
+    pub fn del_client_identity(&self) -> Result<(), Error> {
+        match self.pddb.delete_dict(TLS_CLIENT_DICT, None) {
+            Ok(_) => {
+                log::info!("Deleted {}\n", TLS_CLIENT_DICT);
+                self.pddb.sync().or_else(|e| Ok::<(), Error>(log::warn!("{e}"))).ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to delete {}: {:?}", TLS_CLIENT_DICT, e);
+                Err(e)
+            }
+        }
+    }
+
+    fn client_write(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.pddb.delete_key(TLS_CLIENT_DICT, key, None).ok();
+        match self.pddb.get(TLS_CLIENT_DICT, key, None, true, true, Some(bytes.len()), None::<fn()>) {
+            Ok(mut pddb_key) => match pddb_key.write(bytes) {
+                Ok(len) => {
+                    self.pddb.sync().ok();
+                    log::info!("Wrote {} bytes to {}:{}", len, TLS_CLIENT_DICT, key);
+                    Ok(())
+                }
+                Err(e) => {
+                    log::warn!("Error writing {}:{}: {:?}", TLS_CLIENT_DICT, key, e);
+                    Err(e)
+                }
+            },
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_CLIENT_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    fn client_read(&self, key: &str) -> Option<Vec<u8>> {
+        match self.pddb.get(TLS_CLIENT_DICT, key, None, false, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut bytes = Vec::<u8>::new();
+                match pddb_key.read_to_end(&mut bytes) {
+                    Ok(_) => Some(bytes),
+                    Err(e) => {
+                        log::warn!("failed to read {}:{}: {e}", TLS_CLIENT_DICT, key);
+                        None
+                    }
+                }
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+fn key_wrapper() -> Result<net::KeyWrapper, Error> {
+    let xns = xous_names::XousNames::new().unwrap();
+    net::KeyWrapper::new(&xns).map_err(|e| {
+        log::warn!("failed to connect to the key wrapper: {:?}", e);
+        Error::new(ErrorKind::Other, "failed to connect to the key wrapper")
+    })
+}
+
+/// Generates a P-256 key pair and a Certificate Signing Request for `common_name`
+///
+/// # Returns
+///
+/// the PEM encoded CSR and the PKCS#8 DER encoded private key
+fn new_csr(common_name: &str) -> Result<(String, Vec<u8>), Error> {
+    let mut params = rcgen::CertificateParams::new(Vec::<String>::new());
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params.distinguished_name.push(rcgen::DnType::CommonName, common_name);
+    let cert = rcgen::Certificate::from_params(params).map_err(|e| {
+        log::warn!("failed to generate key pair: {e}");
+        Error::new(ErrorKind::Other, "failed to generate key pair")
+    })?;
+    let csr = cert.serialize_request_pem().map_err(|e| {
+        log::warn!("failed to serialize csr: {e}");
+        Error::new(ErrorKind::Other, "failed to serialize csr")
+    })?;
+    Ok((csr, cert.serialize_private_key_der()))
+}
+
+/// Encodes a certificate chain as u16 length prefixed DER certificates
+fn encode_chain(chain: &[CertificateDer]) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::<u8>::new();
+    for cert in chain {
+        match u16::try_from(cert.len()) {
+            Ok(len) => {
+                bytes.extend_from_slice(&len.to_be_bytes());
+                bytes.extend_from_slice(cert.as_ref());
+            }
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, "certificate > u16")),
+        }
+    }
+    if bytes.len() > MAX_CHAIN_BYTES {
+        return Err(Error::new(ErrorKind::InvalidData, "certificate chain too long"));
+    }
+    Ok(bytes)
+}
+
+/// Decodes a certificate chain encoded by `encode_chain`; None if it is empty or truncated
+fn decode_chain(bytes: &[u8]) -> Option<Vec<CertificateDer<'static>>> {
+    let mut chain = Vec::<CertificateDer>::new();
+    let mut rest = bytes;
+    while rest.len() >= 2 {
+        let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        if rest.len() < 2 + len {
+            log::warn!("truncated client certificate chain");
+            return None;
+        }
+        chain.push(CertificateDer::from(rest[2..2 + len].to_vec()));
+        rest = &rest[2 + len..];
+    }
+    if chain.len() > 0 { Some(chain) } else { None }
+}
+
+/// Prefixes a wrapped key with the length of the plaintext key, which the unwrap needs
+fn encode_wrapped_key(len: usize, wrapped: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = u16::try_from(len).map_err(|_| Error::new(ErrorKind::InvalidData, "client key > u16"))?;
+    let mut bytes = len.to_be_bytes().to_vec();
+    bytes.extend_from_slice(wrapped);
+    Ok(bytes)
+}
+
+fn decode_wrapped_key(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+    if bytes.len() <= 2 {
+        return Err(Error::new(ErrorKind::InvalidData, "truncated client key"));
+    }
+    Ok((u16::from_be_bytes([bytes[0], bytes[1]]) as usize, &bytes[2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_parser::certification_request::X509CertificationRequest;
+    use x509_parser::prelude::FromDer;
+
+    use super::*;
+
+    fn cert(cn: &str) -> CertificateDer<'static> {
+        let mut params = rcgen::CertificateParams::new(vec![cn.to_string()]);
+        params.distinguished_name.push(rcgen::DnType::CommonName, cn);
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        CertificateDer::from(cert.serialize_der().unwrap())
+    }
+
+    #[test]
+    fn csr_names_the_device_and_matches_the_key() {
+        let (csr, key) = new_csr("precursor-1234").unwrap();
+        let (_, pem) = x509_parser::pem::parse_x509_pem(csr.as_bytes()).unwrap();
+        assert_eq!(pem.label, "CERTIFICATE REQUEST");
+        let (_, req) = X509CertificationRequest::from_der(&pem.contents).unwrap();
+        let cn: Vec<_> = req.certification_request_info.subject.iter_common_name().collect();
+        assert_eq!(cn.len(), 1);
+        assert_eq!(cn[0].as_str().unwrap(), "precursor-1234");
+        // the saved key is the one the csr was signed with
+        let key_pair = rcgen::KeyPair::from_der(&key).unwrap();
+        assert_eq!(
+            key_pair.public_key_raw(),
+            req.certification_request_info.subject_pki.subject_public_key.data.as_ref()
+        );
+    }
+
+    #[test]
+    fn chain_round_trips() {
+        let chain = vec![cert("leaf.example"), cert("ca.example")];
+        let bytes = encode_chain(&chain).unwrap();
+        assert_eq!(bytes.len(), 4 + chain[0].len() + chain[1].len());
+        assert_eq!(decode_chain(&bytes).unwrap(), chain);
+    }
+
+    #[test]
+    fn chain_rejects_oversize_and_truncation() {
+        let leaf = cert("leaf.example");
+        let many = vec![leaf.clone(); MAX_CHAIN_BYTES / leaf.len() + 1];
+        assert!(encode_chain(&many).is_err());
+        let bytes = encode_chain(&[leaf]).unwrap();
+        assert!(decode_chain(&bytes[..bytes.len() - 1]).is_none());
+        assert!(decode_chain(&[]).is_none());
+    }
+
+    #[test]
+    fn wrapped_key_keeps_its_length() {
+        let bytes = encode_wrapped_key(138, &[7u8; 152]).unwrap();
+        let (len, wrapped) = decode_wrapped_key(&bytes).unwrap();
+        assert_eq!(len, 138);
+        assert_eq!(wrapped, &[7u8; 152][..]);
+        assert!(decode_wrapped_key(&bytes[..2]).is_err());
+        assert!(encode_wrapped_key(70_000, &[]).is_err());
+    }
+}
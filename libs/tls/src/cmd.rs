@@ -34,10 +34,234 @@ pub fn shellchat<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Option
             log::info!("starting TLS trusted listing");
             let tls = Tls::new();
             for ota in tls.trusted() {
-                write!(ret, "🏛 {}\n", ota).ok();
+                let expiry = match ota.pddb_key() {
+                    Ok(key) => tls.ta_expiry_status(&key),
+                    Err(_) => crate::Expiry::Unknown,
+                };
+                match expiry {
+                    crate::Expiry::Expired(_) => {
+                        write!(ret, "🏛 {}\n\t⚠ {}\n", ota, t!("tls.list_expired", locales::LANG)).ok()
+                    }
+                    crate::Expiry::ExpiringSoon(not_after) => write!(
+                        ret,
+                        "🏛 {}\n\t⏳ {} {}\n",
+                        ota,
+                        t!("tls.list_expiring", locales::LANG),
+                        days_until(not_after)
+                    )
+                    .ok(),
+                    _ => write!(ret, "🏛 {}\n", ota).ok(),
+                };
+            }
+            if crate::wall_clock().is_none() {
+                write!(ret, "{}\n", t!("tls.list_no_clock", locales::LANG)).ok();
             }
             log::info!("finished TLS trusted listing");
         }
+        // list hosts pinned by SPKI hash
+        Some("pins") => {
+            let tls = Tls::new();
+            for host in tls.pinned() {
+                write!(ret, "📌 {}\n", host).ok();
+                for pin in tls.get_pins(&host).unwrap_or_default() {
+                    write!(ret, "\t{}\n", crate::pin::pin_hex(&pin)).ok();
+                }
+            }
+        }
+        // forget the pins for a host
+        Some("unpin") => match tokens.next() {
+            Some(host) => {
+                let tls = Tls::new();
+                match tls.del_pins(host) {
+                    Ok(_) => write!(ret, "{} {host}", t!("tls.unpin_done", locales::LANG)).ok(),
+                    Err(e) => write!(ret, "{host}: {e}").ok(),
+                };
+            }
+            None => {
+                write!(ret, "\tunpin <host>\t{}\n", t!("tls.unpin_cmd", locales::LANG)).ok();
+            }
+        },
+        // enable/disable trust-on-first-use pinning
+        Some("tofu") => {
+            let tls = Tls::new();
+            match tokens.next() {
+                Some("on") => {
+                    tls.set_tofu(true).ok();
+                }
+                Some("off") => {
+                    tls.set_tofu(false).ok();
+                }
+                _ => {}
+            }
+            let state = if tls.tofu() { "on" } else { "off" };
+            write!(ret, "{} {state}", t!("tls.tofu_state", locales::LANG)).ok();
+        }
+        // manage the client certificate presented to hosts requesting mutual tls
+        Some("client") => {
+            let tls = Tls::new();
+            match tokens.next() {
+                // generate a new device key pair & certificate signing request
+                Some("csr") => {
+                    let cn = tokens.next().unwrap_or("precursor");
+                    match tls.client_csr_new(cn) {
+                        Ok(csr) => {
+                            log::info!("{csr}");
+                            write!(ret, "{}\n{csr}", t!("tls.client_csr_done", locales::LANG)).ok();
+                        }
+                        Err(e) => write!(ret, "{e}\n").ok(),
+                    };
+                }
+                // fetch the PEM certificate chain issued in response to the csr
+                Some("import") => match tokens.next() {
+                    Some(url) => {
+                        let agent =
+                            ureq::builder().tls_connector(Arc::new(crate::xtls::TlsConnector {})).build();
+                        match agent.get(url).call() {
+                            Ok(response) => {
+                                let mut pem = Vec::<u8>::new();
+                                match response
+                                    .into_reader()
+                                    .take(crate::client::MAX_CHAIN_BYTES as u64 * 2)
+                                    .read_to_end(&mut pem)
+                                {
+                                    Ok(_) => match tls.save_client_chain_pem(&pem) {
+                                        Ok(count) => write!(
+                                            ret,
+                                            "{} {}",
+                                            count,
+                                            t!("tls.client_import_done", locales::LANG)
+                                        )
+                                        .ok(),
+                                        Err(e) => write!(ret, "{e}\n").ok(),
+                                    },
+                                    Err(e) => write!(ret, "{e}\n").ok(),
+                                };
+                            }
+                            Err(e) => {
+                                write!(ret, "{e}\n").ok();
+                            }
+                        }
+                    }
+                    None => {
+                        write!(
+                            ret,
+                            "\tclient import <url>\t{}\n",
+                            t!("tls.client_import_cmd", locales::LANG)
+                        )
+                        .ok();
+                    }
+                },
+                Some("delete") => {
+                    tls.del_client_identity().ok();
+                    write!(ret, "{}", t!("tls.client_delete_done", locales::LANG)).ok();
+                }
+                // show the client certificate & pending csr
+                _ => {
+                    match tls.client_chain() {
+                        Some(chain) => {
+                            for cert in chain {
+                                if let Ok((_, x509)) =
+                                    x509_parser::prelude::parse_x509_certificate(cert.as_ref())
+                                {
+                                    write!(ret, "🪪 {}\n", x509.subject()).ok();
+                                }
+                            }
+                        }
+                        None => {
+                            write!(ret, "{}\n", t!("tls.client_none", locales::LANG)).ok();
+                        }
+                    }
+                    if let Some(csr) = tls.client_csr() {
+                        write!(ret, "{csr}").ok();
+                    }
+                }
+            }
+        }
+        // show the fingerprint of the self-signed device certificate used by on-device tls servers
+        Some("server") => {
+            let tls = Tls::new();
+            if let Some("reset") = tokens.next() {
+                tls.del_server_identity().ok();
+            }
+            match tls.server_fingerprint() {
+                Ok(fingerprint) => {
+                    write!(ret, "{}\n{}", t!("tls.server_fingerprint", locales::LANG), fingerprint).ok();
+                    tls.server_fingerprint_modal().ok();
+                }
+                Err(e) => write!(ret, "{e}\n").ok(),
+            };
+        }
+        // delete expired trusted CA Certificates
+        Some("prune") => {
+            let tls = Tls::new();
+            match tokens.next() {
+                // enable/disable pruning each time a connection is made
+                Some("auto") => {
+                    match tokens.next() {
+                        Some("on") => {
+                            tls.set_autoprune(true).ok();
+                        }
+                        Some("off") => {
+                            tls.set_autoprune(false).ok();
+                        }
+                        _ => {}
+                    }
+                    let state = if tls.autoprune() { "on" } else { "off" };
+                    write!(ret, "{} {state}", t!("tls.autoprune_state", locales::LANG)).ok();
+                }
+                _ => {
+                    let count = tls.prune_expired();
+                    write!(ret, "{} {}", count, t!("tls.prune_done", locales::LANG)).ok();
+                }
+            }
+        }
+        // certificate revocation lists
+        Some("crl") => {
+            let tls = Tls::new();
+            match tokens.next() {
+                // fetch a DER or PEM encoded CRL from a url
+                Some("fetch") => match tokens.next() {
+                    Some(url) => {
+                        let agent =
+                            ureq::builder().tls_connector(Arc::new(crate::xtls::TlsConnector {})).build();
+                        match agent.get(url).call() {
+                            Ok(response) => {
+                                let mut crl = Vec::<u8>::new();
+                                match response
+                                    .into_reader()
+                                    .take(crate::crl::MAX_CRL_BYTES as u64 * 2)
+                                    .read_to_end(&mut crl)
+                                {
+                                    Ok(_) => match tls.save_crl(&crl) {
+                                        Ok(issuer) => {
+                                            write!(ret, "{} {}", t!("tls.crl_done", locales::LANG), issuer)
+                                                .ok()
+                                        }
+                                        Err(e) => write!(ret, "{e}\n").ok(),
+                                    },
+                                    Err(e) => write!(ret, "{e}\n").ok(),
+                                };
+                            }
+                            Err(e) => {
+                                write!(ret, "{e}\n").ok();
+                            }
+                        }
+                    }
+                    None => {
+                        write!(ret, "\tcrl fetch <url>\t{}\n", t!("tls.crl_fetch_cmd", locales::LANG)).ok();
+                    }
+                },
+                Some("deleteall") => {
+                    tls.del_all_crl().ok();
+                    write!(ret, "{}", t!("tls.crl_deleteall_done", locales::LANG)).ok();
+                }
+                _ => {
+                    for issuer in tls.crl_issuers() {
+                        write!(ret, "🚫 {}\n", issuer).ok();
+                    }
+                }
+            }
+        }
         // save/trust all Root CA's in webpki-roots en-masse
         #[cfg(feature = "rootCA")]
         Some("mozilla") => {
@@ -50,8 +274,7 @@ pub fn shellchat<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Option
             count = 0;
             let tls = Tls::new();
             for ta in webpki_roots::TLS_SERVER_ROOTS {
-                let ota = crate::OwnedTrustAnchor::from(ta);
-                tls.save_ta(&ota).unwrap_or_else(|e| log::warn!("{e}"));
+                tls.save_mozilla_ta(ta).unwrap_or_else(|e| log::warn!("{e}"));
                 modals.update_progress(count).expect("no progress");
                 count += 1;
             }
@@ -81,9 +304,13 @@ pub fn shellchat<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Option
             log::info!("starting TLS run");
             log::info!("build TLS client config");
             let tls = Tls::new();
-            let config = rustls::ClientConfig::builder()
-                .with_root_certificates(tls.root_store())
-                .with_no_client_auth();
+            let config = match tls.client_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    write!(ret, "{e}\n").ok();
+                    return Ok(Some(ret));
+                }
+            };
             let target = match tokens.next() {
                 Some(target) => target,
                 None => "bunnyfoo.com",
@@ -158,11 +385,32 @@ pub fn shellchat<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Option
             write!(ret, "\tdeleteall\t{}\n", t!("tls.deleteall_cmd", locales::LANG)).ok();
             write!(ret, "\thelp\n").ok();
             write!(ret, "\tlist\t{}\n", t!("tls.list_cmd", locales::LANG)).ok();
+            write!(ret, "\tprune\t{}\n", t!("tls.prune_cmd", locales::LANG)).ok();
+            write!(ret, "\tprune auto [on|off]\t{}\n", t!("tls.autoprune_cmd", locales::LANG)).ok();
+            write!(ret, "\tcrl\t{}\n", t!("tls.crl_cmd", locales::LANG)).ok();
+            write!(ret, "\tcrl fetch <url>\t{}\n", t!("tls.crl_fetch_cmd", locales::LANG)).ok();
+            write!(ret, "\tcrl deleteall\t{}\n", t!("tls.crl_deleteall_cmd", locales::LANG)).ok();
             #[cfg(feature = "rootCA")]
             write!(ret, "\tmozilla\t{}\n", t!("tls.mozilla_cmd", locales::LANG)).ok();
             write!(ret, "\tinspect <host>\t{}\n", t!("tls.inspect_cmd", locales::LANG)).ok();
+            write!(ret, "\tpins\t{}\n", t!("tls.pins_cmd", locales::LANG)).ok();
+            write!(ret, "\tunpin <host>\t{}\n", t!("tls.unpin_cmd", locales::LANG)).ok();
+            write!(ret, "\ttofu [on|off]\t{}\n", t!("tls.tofu_cmd", locales::LANG)).ok();
+            write!(ret, "\tclient\t{}\n", t!("tls.client_cmd", locales::LANG)).ok();
+            write!(ret, "\tclient csr <cn>\t{}\n", t!("tls.client_csr_cmd", locales::LANG)).ok();
+            write!(ret, "\tclient import <url>\t{}\n", t!("tls.client_import_cmd", locales::LANG)).ok();
+            write!(ret, "\tclient delete\t{}\n", t!("tls.client_delete_cmd", locales::LANG)).ok();
+            write!(ret, "\tserver [reset]\t{}\n", t!("tls.server_cmd", locales::LANG)).ok();
             write!(ret, "\ttest <host>\t{}\n", t!("tls.test_cmd", locales::LANG)).ok();
         }
     }
     Ok(Some(ret))
 }
+
+/// Returns the number of whole days from now until the time provided (seconds since the unix epoch)
+fn days_until(time: i64) -> i64 {
+    match crate::wall_clock() {
+        Some(now) => (time - now) / (24 * 60 * 60),
+        None => 0,
+    }
+}
@@ -0,0 +1,118 @@
+// Certificate Revocation Lists
+//
+// CRLs are optional: any CRL held in the pddb (fetched with `net tls crl fetch <url>` or
+// side-loaded into the `tls.crl` dictionary) is fed into the rustls verifier. The revocation
+// status of a certificate not covered by any CRL is allowed to remain unknown.
+use std::io::{Error, ErrorKind, Read, Write};
+
+use rustls::pki_types::CertificateRevocationListDer;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::parse_x509_crl;
+
+use crate::Tls;
+
+/// PDDB Dict for DER encoded certificate revocation lists
+const TLS_CRL_DICT: &str = "tls.crl";
+/// the maximum size of a single CRL held in the pddb
+pub const MAX_CRL_BYTES: usize = 64 * 1024;
+
+impl Tls {
+    /// Saves a CRL to the pddb, keyed by issuer
+    ///
+    /// # Arguments
+    ///
+    /// * `crl` - a DER or PEM encoded certificate revocation list
+    ///
+    /// # Returns
+    ///
+    /// the pddb key of the saved CRL
+    pub fn save_crl(&self, crl: &[u8]) -> Result<String, Error> {
+        let der =
+            match Pem::iter_from_buffer(crl).filter_map(|pem| pem.ok()).find(|pem| pem.label == "X509 CRL") {
+                Some(pem) => pem.contents,
+                None => crl.to_vec(),
+            };
+        if der.len() > MAX_CRL_BYTES {
+            return Err(Error::new(ErrorKind::InvalidData, "crl too large"));
+        }
+        let key = match parse_x509_crl(&der) {
+            Ok((_, crl)) => {
+                log::info!("crl {} with {} revoked", crl.issuer(), crl.iter_revoked_certificates().count());
+                crl_key(&crl.issuer().to_string())
+            }
+            Err(e) => {
+                log::warn!("failed to parse crl: {e}");
+                return Err(Error::from(ErrorKind::InvalidData));
+            }
+        };
+        self.pddb.delete_key(TLS_CRL_DICT, &key, None).ok();
+        match self.pddb.get(TLS_CRL_DICT, &key, None, true, true, Some(der.len()), None::<fn()>) {
+            Ok(mut pddb_key) => match pddb_key.write(&der) {
+                Ok(len) => {
+                    self.pddb.sync().ok();
+                    log::info!("Wrote {} bytes to {}:{}", len, TLS_CRL_DICT, key);
+                    Ok(key)
+                }
+                Err(e) => {
+                    log::warn!("Error writing {}:{}: {:?}", TLS_CRL_DICT, key, e);
+                    Err(e)
+                }
+            },
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_CRL_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns all CRLs held in the pddb
+    pub fn crls(&self) -> Vec<CertificateRevocationListDer<'static>> {
+        match self.pddb.list_keys(TLS_CRL_DICT, None) {
+            Ok(list) => list
+                .iter()
+                .filter_map(|key| {
+                    match self.pddb.get(TLS_CRL_DICT, key, None, false, false, None, None::<fn()>) {
+                        Ok(mut pddb_key) => {
+                            let mut bytes = Vec::<u8>::new();
+                            match pddb_key.read_to_end(&mut bytes) {
+                                Ok(_) => Some(CertificateRevocationListDer::from(bytes)),
+                                Err(e) => {
+                                    log::warn!("failed to read {}:{}: {e}", TLS_CRL_DICT, key);
+                                    None
+                                }
+                            }
+                        }
+                        Err(_) => None,
+                    }
+                })
+                .collect(),
+            Err(_) => Vec::<CertificateRevocationListDer>::new(),
+        }
+    }
+
+    /// Returns the pddb keys (issuers) of all CRLs held in the pddb
+    pub fn crl_issuers(&self) -> Vec<String> {
+        match self.pddb.list_keys(TLS_CRL_DICT, None) {
+            Ok(list) => list,
+            Err(_) => Vec::<String>::new(),
+        }
+    }
+
+    /// Deletes ALL CRLs from the pddb
+    pub fn del_all_crl(&self) -> Result<(), Error> {
+        match self.pddb.delete_dict(TLS_CRL_DICT, None) {
+            Ok(_) => {
+                log::info!("Deleted {}\n", TLS_CRL_DICT);
+                self.pddb.sync().or_else(|e| Ok::<(), Error>(log::warn!("{e}"))).ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to delete {}: {:?}", TLS_CRL_DICT, e);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Returns a pddb key for a CRL issuer, truncated to the pddb key length
+fn crl_key(issuer: &str) -> String { crate::key_prefix(issuer, crate::KEY_NAME_LEN - 1).to_string() }
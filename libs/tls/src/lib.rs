@@ -1,6 +1,12 @@
+pub mod client;
 pub mod cmd;
+pub mod crl;
 mod danger;
+#[cfg(feature = "rootCA")]
+mod mozilla;
 pub mod ota;
+pub mod pin;
+pub mod server;
 pub mod xtls;
 
 use std::convert::{Into, TryFrom, TryInto};
@@ -11,6 +17,7 @@ use std::sync::Arc;
 use locales::t;
 use modals::Modals;
 use ota::OwnedTrustAnchor;
+use pin::{pin_key, Pin, PIN_LEN};
 use rkyv::{
     de::deserializers::AllocDeserializer,
     ser::{serializers::WriteSerializer, Serializer},
@@ -23,6 +30,53 @@ use xous_names::XousNames;
 
 /// PDDB Dict for tls trusted certificates keys
 const TLS_TRUSTED_DICT: &str = "tls.trusted";
+/// PDDB Dict for tls per-host pins keyed by hostname
+const TLS_PINNED_DICT: &str = "tls.pinned";
+/// PDDB Dict for tls settings
+const TLS_CONFIG_DICT: &str = "tls.config";
+/// PDDB key for the trust-on-first-use setting
+const TLS_TOFU_KEY: &str = "tofu";
+/// PDDB key for the setting to prune expired trust-anchors automatically
+const TLS_AUTOPRUNE_KEY: &str = "autoprune";
+/// maximum number of pins held for a single host (leaf + intermediates)
+const MAX_PINS: usize = 8;
+/// PDDB Dict for the expiry (not_after) of tls trusted certificates, keyed as per TLS_TRUSTED_DICT
+const TLS_EXPIRY_DICT: &str = "tls.expiry";
+/// trust-anchors expiring within this period are flagged as soon-to-expire
+pub const EXPIRY_WARNING_SECS: i64 = 30 * 24 * 60 * 60;
+/// the wall-clock is considered to be unset before this time (2024-01-01)
+const WALL_CLOCK_VALID_AFTER: i64 = 1_704_067_200;
+// mirror of pddb::KEY_NAME_LEN (see ota::OwnedTrustAnchor::pddb_key())
+pub(crate) const KEY_NAME_LEN: usize = 127 - 8 - 8 - 8 - 4 - 4;
+
+/// The expiry status of a trust-anchor (with not_after in seconds since the unix epoch)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Expiry {
+    /// no record of expiry, or the wall-clock is unset
+    Unknown,
+    Valid(i64),
+    ExpiringSoon(i64),
+    Expired(i64),
+}
+
+impl Expiry {
+    /// Classifies a trust-anchor by its not_after against the wall-clock (both in seconds since
+    /// the unix epoch); either being None gives `Unknown`
+    pub fn new(not_after: Option<i64>, now: Option<i64>) -> Expiry {
+        match (not_after, now) {
+            (Some(not_after), Some(now)) => {
+                if not_after < now {
+                    Expiry::Expired(not_after)
+                } else if not_after < now + EXPIRY_WARNING_SECS {
+                    Expiry::ExpiringSoon(not_after)
+                } else {
+                    Expiry::Valid(not_after)
+                }
+            }
+            _ => Expiry::Unknown,
+        }
+    }
+}
 
 pub struct Tls {
     pddb: pddb::Pddb,
@@ -63,21 +117,17 @@ impl Tls {
         match modals.get_checkbox(t!("tls.check_trust_prompt", locales::LANG)) {
             Ok(trusted) => {
                 trusted.iter().for_each(|cert| log::info!("trusts {}", cert));
-                modals
-                    .get_check_index()
-                    .unwrap()
-                    .iter()
-                    .map(|i| &certificates[*i].1)
-                    .map(|x509| OwnedTrustAnchor::from_x509(x509))
-                    .filter_map(|ta| ta.ok())
-                    .for_each(|ta| {
-                        self.save_ta(&ta).unwrap_or_else(|e| {
-                            log::warn!("failed to save cert: {e}");
-                            modals
-                                .show_notification(format!("failed to save:\n{:?}\n{e}", &ta).as_str(), None)
-                                .expect("modal failed");
-                        });
+                modals.get_check_index().unwrap().iter().map(|i| &certificates[*i].1).for_each(|x509| {
+                    self.save_x509(x509).unwrap_or_else(|e| {
+                        log::warn!("failed to save cert: {e}");
+                        modals
+                            .show_notification(
+                                format!("failed to save:\n{}\n{e}", x509.subject()).as_str(),
+                                None,
+                            )
+                            .expect("modal failed");
                     });
+                });
                 trusted.len()
             }
             _ => {
@@ -104,6 +154,7 @@ impl Tls {
             }
             Err(e) => log::warn!("failed to delete {}: {:?}", TLS_TRUSTED_DICT, e),
         }
+        self.pddb.delete_dict(TLS_EXPIRY_DICT, None).ok();
         Ok(count)
     }
 
@@ -120,9 +171,137 @@ impl Tls {
             }
             Err(e) => log::warn!("failed to delete {}:{}: {:?}", TLS_TRUSTED_DICT, key, e),
         }
+        self.pddb.delete_key(TLS_EXPIRY_DICT, key, None).ok();
         return Ok(());
     }
 
+    /// Saves an x509 Certificate to the pddb as a tls trust-anchor, along with its expiry
+    ///
+    /// # Arguments
+    ///
+    /// * `x509` - a trusted x509 Certificate
+    pub fn save_x509(&self, x509: &X509Certificate) -> Result<(), Error> {
+        let ta = OwnedTrustAnchor::from_x509(x509)?;
+        self.save_ta(&ta)?;
+        self.save_expiry(&ta.pddb_key()?, x509.validity().not_after.timestamp())
+    }
+
+    /// Saves a bundled Mozilla Root CA to the pddb as a tls trust-anchor, along with its expiry
+    ///
+    /// # Arguments
+    ///
+    /// * `ta` - one of `webpki_roots::TLS_SERVER_ROOTS`
+    #[cfg(feature = "rootCA")]
+    pub fn save_mozilla_ta(&self, ta: &TrustAnchor) -> Result<(), Error> {
+        let ota = OwnedTrustAnchor::from(ta);
+        self.save_ta(&ota)?;
+        match mozilla::not_after(ta.subject_public_key_info.as_ref()) {
+            Some(not_after) => self.save_expiry(&ota.pddb_key()?, not_after),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the expiry of the trusted x509 Certificates offered, where missing
+    ///
+    /// Trust-anchors saved before expiries were recorded have no record, so they can't be
+    /// flagged or pruned until the Certificate is seen again.
+    ///
+    /// # Arguments
+    ///
+    /// * `certs` - x509 Certificates, typically the chain offered by a host
+    ///
+    /// # Returns
+    ///
+    /// the number of expiries recorded
+    pub fn record_expiry(&self, certs: &[CertificateDer]) -> usize {
+        let mut count = 0;
+        for cert in certs {
+            let x509 = match parse_x509_certificate(cert.as_ref()) {
+                Ok((_, x509)) => x509,
+                Err(_) => continue,
+            };
+            let key = match OwnedTrustAnchor::from_x509(&x509).and_then(|ta| ta.pddb_key()) {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+            if self.get_ota(&key).is_some()
+                && self.ta_expiry(&key).is_none()
+                && self.save_expiry(&key, x509.validity().not_after.timestamp()).is_ok()
+            {
+                log::info!("recorded expiry of trust-anchor {key}");
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn save_expiry(&self, key: &str, not_after: i64) -> Result<(), Error> {
+        match self.pddb.get(TLS_EXPIRY_DICT, key, None, true, true, Some(8), None::<fn()>) {
+            Ok(mut pddb_key) => {
+                pddb_key.write(&not_after.to_be_bytes())?;
+                self.pddb.sync().ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_EXPIRY_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the expiry (not_after) of a tls trust-anchor in seconds since the unix epoch
+    ///
+    /// Trust-anchors saved without the originating x509 Certificate have no record of expiry,
+    /// with the exception of the bundled Mozilla Root CAs (see `save_mozilla_ta`).
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - pddb key holding the trust-anchor
+    pub fn ta_expiry(&self, key: &str) -> Option<i64> {
+        match self.pddb.get(TLS_EXPIRY_DICT, key, None, false, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut bytes = [0u8; 8];
+                match pddb_key.read(&mut bytes) {
+                    Ok(8) => Some(i64::from_be_bytes(bytes)),
+                    _ => None,
+                }
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the expiry status of a tls trust-anchor, checked against the wall-clock
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - pddb key holding the trust-anchor
+    pub fn ta_expiry_status(&self, key: &str) -> Expiry { Expiry::new(self.ta_expiry(key), wall_clock()) }
+
+    /// Deletes all expired tls trust-anchors from the pddb
+    ///
+    /// Nothing is deleted if the wall-clock has not been set.
+    ///
+    /// # Returns
+    ///
+    /// the number of trust-anchors deleted
+    pub fn prune_expired(&self) -> usize {
+        let expired = match self.pddb.list_keys(TLS_EXPIRY_DICT, None) {
+            Ok(list) => expired_keys(
+                list.into_iter().map(|key| {
+                    let not_after = self.ta_expiry(&key);
+                    (key, not_after)
+                }),
+                wall_clock(),
+            ),
+            Err(_) => Vec::<String>::new(),
+        };
+        for key in expired.iter() {
+            log::info!("pruning expired trust-anchor {key}");
+            self.del_rota(key).ok();
+        }
+        expired.len()
+    }
+
     /// Saves a tls trust-anchor to the pddb
     ///
     /// # Arguments
@@ -283,10 +462,17 @@ impl Tls {
 
     /// Returns a RootCertStore containing all trusted (saved) TrustAnchors
     ///
+    /// Expired trust-anchors are pruned from the pddb beforehand, unless automatic pruning has
+    /// been turned off (see `set_autoprune`).
+    ///
     /// # Returns
     ///
     /// a RootCertStore suitable for rustls
     pub fn root_store(&self) -> RootCertStore {
+        if self.autoprune() {
+            // drop dead trust-anchors accumulated while the device was not in use
+            self.prune_expired();
+        }
         let mut root_store = RootCertStore::empty();
         let trusted = match self.pddb.list_keys(TLS_TRUSTED_DICT, None) {
             Ok(list) => list
@@ -380,11 +566,8 @@ impl Tls {
     pub fn inspect(&self, host: &str) -> Result<usize, Error> {
         match self.probe(host) {
             Ok(certs) => {
-                if certs.len() > 0 {
-                    Ok(self.trust_modal(certs.to_vec()))
-                } else {
-                    Ok(0)
-                }
+                self.record_expiry(&certs);
+                if certs.len() > 0 { Ok(self.trust_modal(certs.to_vec())) } else { Ok(0) }
             }
             Err(e) => {
                 log::warn!("failed to probe {host}: {e}");
@@ -409,10 +592,13 @@ impl Tls {
     /// true if the user trusts at least one of the Certificates offered by the host.
     pub fn accessible(&self, host: &str, inspect: bool) -> bool {
         match self.probe(host) {
-            Ok(certs) => match certs.iter().find(|&cert| self.is_trusted_cert(cert.clone())) {
-                Some(_) => true,
-                None => inspect && (self.trust_modal(certs.to_vec()) > 0),
-            },
+            Ok(certs) => {
+                self.record_expiry(&certs);
+                match certs.iter().find(|&cert| self.is_trusted_cert(cert.clone())) {
+                    Some(_) => true,
+                    None => inspect && (self.trust_modal(certs.to_vec()) > 0),
+                }
+            }
             Err(e) => {
                 log::warn!("failed to probe {host}: {e}");
                 false
@@ -420,8 +606,159 @@ impl Tls {
         }
     }
 
-    pub fn client_config(&self) -> ClientConfig {
-        rustls::ClientConfig::builder().with_root_certificates(self.root_store()).with_no_client_auth()
+    /// Returns the pins (SPKI hashes) saved in the pddb for a host
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - the pinned host (i.e. matrix.example.org)
+    pub fn get_pins(&self, host: &str) -> Option<Vec<Pin>> {
+        match self.pddb.get(TLS_PINNED_DICT, &pin_key(host), None, false, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut bytes = Vec::<u8>::new();
+                match pddb_key.read_to_end(&mut bytes) {
+                    Ok(_) => {
+                        let pins: Vec<Pin> = bytes
+                            .chunks_exact(PIN_LEN)
+                            .map(|chunk| chunk.try_into().unwrap())
+                            .collect::<Vec<Pin>>();
+                        if pins.len() > 0 { Some(pins) } else { None }
+                    }
+                    Err(e) => {
+                        log::warn!("failed to read pins for {host}: {e}");
+                        None
+                    }
+                }
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Saves the pins (SPKI hashes) for a host to the pddb, replacing any existing pins
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - the pinned host (i.e. matrix.example.org)
+    /// * `pins` - the SPKI hashes of the leaf & intermediate certificates offered by the host
+    pub fn save_pins(&self, host: &str, pins: &[Pin]) -> Result<(), Error> {
+        let key = pin_key(host);
+        self.pddb.delete_key(TLS_PINNED_DICT, &key, None).ok();
+        let bytes: Vec<u8> = pins.iter().take(MAX_PINS).flatten().copied().collect();
+        match self.pddb.get(TLS_PINNED_DICT, &key, None, true, true, Some(MAX_PINS * PIN_LEN), None::<fn()>) {
+            Ok(mut pddb_key) => match pddb_key.write(&bytes) {
+                Ok(len) => {
+                    self.pddb.sync().ok();
+                    log::info!("Wrote {} bytes to {}:{}", len, TLS_PINNED_DICT, key);
+                    Ok(())
+                }
+                Err(e) => {
+                    log::warn!("Error writing {}:{}: {:?}", TLS_PINNED_DICT, key, e);
+                    Err(e)
+                }
+            },
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_PINNED_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Deletes the pins for a host from the pddb
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - the pinned host (i.e. matrix.example.org)
+    pub fn del_pins(&self, host: &str) -> Result<(), Error> {
+        let key = pin_key(host);
+        match self.pddb.delete_key(TLS_PINNED_DICT, &key, None) {
+            Ok(_) => {
+                log::info!("Deleted {}:{}\n", TLS_PINNED_DICT, key);
+                self.pddb.sync().or_else(|e| Ok::<(), Error>(log::warn!("{e}"))).ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to delete {}:{}: {:?}", TLS_PINNED_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns a Vec of all pinned hosts
+    pub fn pinned(&self) -> Vec<String> {
+        match self.pddb.list_keys(TLS_PINNED_DICT, None) {
+            Ok(list) => list,
+            Err(_) => Vec::<String>::new(),
+        }
+    }
+
+    /// Returns true if trust-on-first-use pinning is enabled
+    pub fn tofu(&self) -> bool { self.config_flag(TLS_TOFU_KEY, false) }
+
+    /// Enables or disables trust-on-first-use pinning
+    ///
+    /// In TOFU mode, the SPKI hashes of the certificates offered by a host on first contact are
+    /// saved to the pddb, and a subsequent mismatch raises a warning. A host with an untrusted
+    /// (i.e. self-signed) certificate may also be trusted on first use.
+    pub fn set_tofu(&self, tofu: bool) -> Result<(), Error> { self.set_config_flag(TLS_TOFU_KEY, tofu) }
+
+    /// Returns true if expired trust-anchors are pruned automatically (the default)
+    pub fn autoprune(&self) -> bool { self.config_flag(TLS_AUTOPRUNE_KEY, true) }
+
+    /// Enables or disables automatic pruning of expired trust-anchors
+    ///
+    /// When enabled, expired trust-anchors are deleted from the pddb each time a RootCertStore is
+    /// built (i.e. on each tls connection). When disabled, they are only deleted by `prune_expired`.
+    pub fn set_autoprune(&self, autoprune: bool) -> Result<(), Error> {
+        self.set_config_flag(TLS_AUTOPRUNE_KEY, autoprune)
+    }
+
+    fn config_flag(&self, key: &str, default: bool) -> bool {
+        match self.pddb.get(TLS_CONFIG_DICT, key, None, false, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut byte = [0u8; 1];
+                match pddb_key.read(&mut byte) {
+                    Ok(1) => byte[0] != 0,
+                    _ => default,
+                }
+            }
+            Err(_) => default,
+        }
+    }
+
+    fn set_config_flag(&self, key: &str, value: bool) -> Result<(), Error> {
+        match self.pddb.get(TLS_CONFIG_DICT, key, None, true, true, Some(1), None::<fn()>) {
+            Ok(mut pddb_key) => {
+                pddb_key.write(&[value as u8])?;
+                self.pddb.sync().ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_CONFIG_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns a rustls ClientConfig verifying hosts against the trusted RootCertStore,
+    /// any CRLs and any per-host pins saved in the pddb
+    ///
+    /// The client identity (if issued) is presented to hosts requesting client authentication.
+    /// An identity that cannot be used (e.g. the key fails to unwrap) is an error, rather than
+    /// a silent fallback to connecting without one.
+    pub fn client_config(&self) -> Result<ClientConfig, Error> {
+        let verifier =
+            Arc::new(pin::PinnedCertificateVerification::new(self.root_store(), self.crls(), self.tofu()));
+        let builder = rustls::ClientConfig::builder().dangerous().with_custom_certificate_verifier(verifier);
+        match self.client_identity() {
+            Ok(Some((chain, key))) => builder.with_client_auth_cert(chain, key).map_err(|e| {
+                log::warn!("failed to configure client certificate: {e}");
+                Error::new(ErrorKind::InvalidData, "failed to configure client certificate")
+            }),
+            Ok(None) => Ok(builder.with_no_client_auth()),
+            Err(e) => {
+                log::warn!("failed to load client identity: {e}");
+                Err(e)
+            }
+        }
     }
 
     /// Construct a tls-stream on the tcp-stream provided
@@ -441,7 +778,7 @@ impl Tls {
     ) -> Result<rustls::StreamOwned<ClientConnection, TcpStream>, Error> {
         match host.to_owned().try_into() {
             Ok(server_name) => {
-                match rustls::ClientConnection::new(Arc::new(self.client_config()), server_name) {
+                match rustls::ClientConnection::new(Arc::new(self.client_config()?), server_name) {
                     Ok(conn) => Ok(rustls::StreamOwned::new(conn, sock)),
                     Err(_) => Err(Error::new(ErrorKind::Other, "failed to configure client connection")),
                 }
@@ -454,6 +791,34 @@ impl Tls {
     }
 }
 
+/// Returns the wall-clock time in seconds since the unix epoch, or None if the clock has not been set
+pub fn wall_clock() -> Option<i64> {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(now) if now.as_secs() as i64 > WALL_CLOCK_VALID_AFTER => Some(now.as_secs() as i64),
+        _ => None,
+    }
+}
+
+/// Returns the keys of the trust-anchors that have expired, given their not_after (if recorded)
+///
+/// Nothing has expired if the wall-clock has not been set.
+fn expired_keys(expiries: impl IntoIterator<Item = (String, Option<i64>)>, now: Option<i64>) -> Vec<String> {
+    expiries
+        .into_iter()
+        .filter(|(_, not_after)| matches!(Expiry::new(*not_after, now), Expiry::Expired(_)))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Returns the longest prefix of `name` that is at most `len` bytes, cut on a char boundary
+pub(crate) fn key_prefix(name: &str, len: usize) -> &str {
+    let mut end = std::cmp::min(name.len(), len);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
 // https://stackoverflow.com/questions/57029974/how-to-split-string-into-chunks-in-rust-to-insert-spaces
 // insert a space between each hex value
 fn open_hex(text: &str) -> String {
@@ -464,3 +829,37 @@ fn open_hex(text: &str) -> String {
         })
         .collect::<String>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_750_000_000;
+
+    #[test]
+    fn expiry_classification() {
+        assert_eq!(Expiry::new(None, Some(NOW)), Expiry::Unknown);
+        assert_eq!(Expiry::new(Some(NOW), None), Expiry::Unknown);
+        assert_eq!(Expiry::new(Some(NOW - 1), Some(NOW)), Expiry::Expired(NOW - 1));
+        // expires this very second: not yet expired, but flagged
+        assert_eq!(Expiry::new(Some(NOW), Some(NOW)), Expiry::ExpiringSoon(NOW));
+        let soon = NOW + EXPIRY_WARNING_SECS - 1;
+        assert_eq!(Expiry::new(Some(soon), Some(NOW)), Expiry::ExpiringSoon(soon));
+        let later = NOW + EXPIRY_WARNING_SECS;
+        assert_eq!(Expiry::new(Some(later), Some(NOW)), Expiry::Valid(later));
+    }
+
+    #[test]
+    fn prune_selects_only_expired() {
+        let expiries = vec![
+            ("old".to_string(), Some(NOW - 1)),
+            ("soon".to_string(), Some(NOW + 60)),
+            ("fine".to_string(), Some(NOW + 10 * EXPIRY_WARNING_SECS)),
+            ("unrecorded".to_string(), None),
+            ("older".to_string(), Some(0)),
+        ];
+        assert_eq!(expired_keys(expiries.clone(), Some(NOW)), vec!["old", "older"]);
+        // an unset wall-clock prunes nothing
+        assert!(expired_keys(expiries, None).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+// Expiry of the Mozilla Root CA certificates bundled by webpki-roots
+//
+// webpki-roots carries only the trust-anchors (subject & spki) and not the certificates, so the
+// not_after of each is lost when `net tls mozilla` saves them. This table puts it back. It was
+// generated from the certificates quoted in the webpki-roots 0.26.0 source, so it must be
+// regenerated whenever the webpki-roots version pinned in Cargo.toml changes (the test below
+// catches a stale table).
+use sha2::{Digest, Sha256};
+
+/// (the first 8 bytes of the SHA-256 of the trust-anchor spki (big-endian), not_after in seconds
+/// since the unix epoch)
+#[rustfmt::skip]
+const EXPIRY: [(u64, i64); 146] = [
+    (0x524ae1e8f0ded44e, 2399587199), // DigiCert TLS ECC P384 Root G5
+    (0x44a86fcd19947a90, 2408546753), // CommScope Public Trust RSA Root-01
+    (0x86cfbcb8997665be, 2144764536), // Entrust Root Certification Authority - EC1
+    (0x70b7ee92c73068de, 1924956366), // AffirmTrust Commercial
+    (0xbcdb1c09156778cd, 2054800799), // D-TRUST EV Root CA 1 2020
+    (0x1b30949713b50476, 1924956504), // AffirmTrust Networking
+    (0x9b89fa558dea2d41, 1893455999), // COMODO Certification Authority
+    (0x5f0b8f0507bc50ac, 2408549742), // CommScope Public Trust ECC Root-01
+    (0xa9f0857aaeb802a8, 2307897000), // emSign Root CA - C1
+    (0x53e6e93cdb15f511, 2035215556), // Starfield Class 2 Certification Authority
+    (0x021d207e0e4615eb, 1747094340), // Baltimore CyberTrust Root
+    (0xf91f89bccf099fd8, 2147471999), // COMODO ECC Certification Authority
+    (0xd36130285204e17c, 2147483647), // GlobalSign
+    (0x24aec002d0d83ad0, 2221603200), // Amazon Root CA 3
+    (0xba60e572c6f7eecb, 1953313604), // QuoVadis Root CA 3
+    (0xa19275a5622b550a, 2221603200), // Amazon Root CA 2
+    (0xfd33a4317c8f9042, 2244305723), // SSL.com EV Root Certification Authority ECC
+    (0x77d541bd7b4d52c6, 2332410954), // Telia Root CA v2
+    (0xb1ac74436a7a9e31, 2147408236), // Security Communication RootCA3
+    (0x1e22a05d7a346acc, 2144305645), // Izenpe.com
+    (0x0b41b37d0c524bd5, 2049321600), // GlobalSign
+    (0xeef6011f78b783fa, 2145916799), // Starfield Root Certificate Authority - G2
+    (0x502ddfe97a021030, 2345273876), // TunTrust Root CA
+    (0xb5121737650a84eb, 2285086477), // SSL.com EV Root Certification Authority RSA R2
+    (0x615de3c9dda45a87, 2408550293), // CommScope Public Trust ECC Root-02
+    (0xb6a349b19365ae64, 2021046812), // IdenTrust Public Sector Root CA 1
+    (0x43279c62f4b58287, 2321940404), // vTrus ECC Root CA
+    (0x5f13b70daf1a7ef6, 2147169600), // DigiCert Global Root G3
+    (0xce41eaa7e6f2692b, 2244305643), // SSL.com Root Certification Authority ECC
+    (0x07b72935a39ec8bb, 2097705600), // GTS Root R4
+    (0x633a95e86eefade3, 2097705600), // GTS Root R3
+    (0x569c4dddef7e0f4f, 2289338164), // Microsoft ECC Root Certificate Authority 2017
+    (0xc0dbafcdf53e1311, 2399587199), // DigiCert TLS RSA4096 Root G5
+    (0x1bb2ffd79d621781, 1893324618), // Microsec e-Szigno Root CA 2009
+    (0xa8a6185f56e86ecc, 1952035200), // DigiCert Assured ID Root CA
+    (0xbc0823fc357c07c6, 2370596469), // HARICA TLS ECC Root CA 2021
+    (0xcab0f2c8bbee0c48, 2307897000), // emSign Root CA - G1
+    (0x7ec561f8164447b4, 1893441126), // Secure Global CA
+    (0x40040b0e8f7095f3, 1952035200), // DigiCert Global Root CA
+    (0x3396a4bb5062f531, 1922896554), // Entrust Root Certification Authority - G2
+    (0x1f1f8e10c480636b, 2224665432), // Hellenic Academic and Research Institutions ECC RootCA 2015
+    (0x17b3f79aac1f8b3f, 2145916799), // Go Daddy Root Certificate Authority - G2
+    (0x18cb85af0bbb31f4, 2329374355), // TUBITAK Kamu SM SSL Kok Sertifikasi - Surum 1
+    (0x3868abb48d233a08, 1888562158), // D-TRUST Root Class 3 CA 2 2009
+    (0x2a6731b32542c365, 2405116800), // GlobalSign Root R46
+    (0xb935f7b6ca6d138a, 2147471999), // USERTrust ECC Certification Authority
+    (0xf382d817a14bae2e, 1874725239), // Security Communication RootCA2
+    (0x5787a9aef2286820, 2147471999), // COMODO RSA Certification Authority
+    (0x0473e8b37badb625, 2147169600), // DigiCert Trusted Root G4
+    (0xd52895d7f8c3d79e, 2334217053), // AC RAIZ FNMT-RCM SERVIDORES SEGUROS
+    (0x8e011b9748343272, 2292435403), // Trustwave Global ECC P384 Certification Authority
+    (0x66ca42f52f57076b, 1916306522), // Actalis Authentication Root CA
+    (0xafade74b09101f1d, 2145916799), // Starfield Services Root Certificate Authority - G2
+    (0xa2af4f6bb483349c, 2365125501), // BJCA Global Root CA2
+    (0x4e6e6d519e9d6ae9, 2093613727), // Autoridad de Certificacion Firmaprofesional CIF A62634068
+    (0x98b77dca04de1c87, 1924963199), // TWCA Global Root CA
+    (0x2efca675a0e06476, 2285375386), // Hongkong Post Root CA 3
+    (0x05b68279969af87a, 1893413257), // Certum Trusted Network CA
+    (0x1cf73dc7001d3eb2, 1893380821), // CFCA EV ROOT
+    (0xb4e95b6242e0d387, 2021047943), // IdenTrust Commercial Root CA 1
+    (0xad1b05ba35cf7aaa, 2108536235), // SwissSign Gold CA - G2
+    (0xe55141379600fbcf, 2275291655), // certSIGN ROOT CA G2
+    (0x8cdb3b12264c770a, 2231510400), // ISRG Root X2
+    (0x16db54073b95e0cd, 2310967494), // Certum EC-384 CA
+    (0x11a483e2d7469f46, 2206365031), // OISTE WISeKey Global Root GB CA
+    (0x90d3c6d2fcf2653a, 1859728101), // NetLock Arany (Class Gold) Főtanúsítvány
+    (0x6d6bd67832cc5826, 1879596912), // Entrust.net Certification Authority (2048)
+    (0x84783e3ee6bb8002, 2240575836), // AffirmTrust Premium
+    (0x19becf587187dab0, 1795812822), // Entrust Root Certification Authority
+    (0xc750348eebb93bc5, 1952035200), // DigiCert High Assurance EV Root CA
+    (0x509ba39ab69c9791, 2406153600), // Certainly Root R1
+    (0xc31dde47225cfe33, 2405289599), // Sectigo Public Server Authentication Root R46
+    (0x0ce36d0d751f2a89, 2147169600), // DigiCert Assured ID Root G2
+    (0xa415da1ec5613213, 2147169600), // DigiCert Assured ID Root G3
+    (0xc98903cfe44d29e3, 2249803269), // Atos TrustedRoot Root CA RSA TLS 2021
+    (0x58fb9752aece2dd7, 2283242313), // OISTE WISeKey Global Root GC CA
+    (0x33bdaf76b3ef0773, 2244303579), // SSL.com Root Certification Authority RSA
+    (0xaf4e5c3a77508906, 1953311013), // QuoVadis Root CA 2
+    (0xe5004cd7da050648, 2307897000), // emSign ECC Root CA - G3
+    (0xae5be05eb17362aa, 2273171192), // QuoVadis Root CA 3 G3
+    (0xe9f8d653b3befd12, 2134252799), // NAVER Global Root Certification Authority
+    (0x96c1855e2b5f79f0, 2321940245), // vTrus Root CA
+    (0x7ffa1b7bce4e710f, 2273160464), // QuoVadis Root CA 1 G3
+    (0x307cc3a29520bcd6, 2097705600), // GTS Root R2
+    (0x3f9b5c85a22deafd, 2147299200), // Amazon Root CA 1
+    (0x054affdb2ff915d8, 2418309261), // SSL.com TLS RSA Root CA 2022
+    (0x0b1f591f9b149932, 2273165972), // QuoVadis Root CA 2 G3
+    (0xa4dc4a03f8e9841a, 2011823999), // T-TeleSec GlobalRoot Class 2
+    (0xb753d04564451509, 2292435310), // Trustwave Global ECC P256 Certification Authority
+    (0xa3a3ecdc1a9d6620, 2064567878), // ISRG Root X1
+    (0xeff2b171694e757f, 2292435252), // Trustwave Global Certification Authority
+    (0x2cbee3346b8de3bb, 2234853483), // Buypass Class 2 Root CA
+    (0xd8096537d7022dc6, 2222899200), // GLOBALTRUST 2020
+    (0xfa04ef785cd6989d, 1924940257), // ACCVRAIZ1
+    (0x880a4552dc2ce9e4, 2240524800), // UCA Global G2 Root
+    (0x0b79da2f68adc1fa, 2224663881), // Hellenic Academic and Research Institutions RootCA 2015
+    (0xdd3fb9f310d2d526, 2076392610), // SZAFIR ROOT CA2
+    (0x206c4696957d92b2, 2147483647), // GlobalSign
+    (0x09392b0897221d96, 2249803582), // Atos TrustedRoot Root CA ECC TLS 2021
+    (0x4cc1e6485de41b9f, 2406153600), // Certainly Root E1
+    (0x1f3cf86ee083fd64, 2422427996), // Certum Trusted Network CA 2
+    (0x19eb98b5bf0464fc, 2307897000), // emSign ECC Root CA - C3
+    (0xda383f18eb1d1a96, 2240576424), // AffirmTrust Premium ECC
+    (0xb333aee1bac6e737, 2410308622), // TrustAsia Global Root CA G4
+    (0x920c982dc7c10375, 2108536366), // SwissSign Silver CA - G2
+    (0x2d46ef481f90523c, 2292322026), // e-Szigno Root CA 2017
+    (0xeec795a639312899, 1870318607), // SecureSign RootCA11
+    (0x0f765722dac26eb9, 1924963199), // TWCA Root Certification Authority
+    (0x8c48783bdc4f71f2, 2240582399), // GDCA TrustAUTH R5 ROOT
+    (0x55b594bb24eff614, 2050194687), // ePKI Root Certification Authority
+    (0x7981fe1dc726f1be, 2418309227), // SSL.com TLS ECC Root CA 2022
+    (0xd6b64f50f8fbd2da, 2035213580), // Go Daddy Class 2 Certification Authority
+    (0xe353c291d461ff31, 2289337223), // Microsoft RSA Root Certificate Authority 2017
+    (0x687b75081cb00f61, 2405289599), // Sectigo Public Server Authentication Root E46
+    (0x8f5dbad9e88607ec, 2147169600), // DigiCert Global Root G2
+    (0x0f0cc3451cbe8cdf, 1868522400), // GlobalSign
+    (0x4eb29a4a4f1496cc, 2405116800), // GlobalSign Root E46
+    (0xc2010dee2931d06c, 2051242639), // XRamp Global Certification Authority
+    (0x92d4432a18cfea1e, 2177366400), // UCA Extended Validation Root
+    (0xb7f5ad131143546a, 2011768347), // Certigna Root CA
+    (0x3066deb0d0d15398, 1861919999), // AAA Certificate Services
+    (0x1634378d928fde2a, 2097705600), // GTS Root R1
+    (0x6b03401f5a226b75, 2370596137), // HARICA TLS RSA Root CA 2021
+    (0x5fb951293760960c, 2145526876), // Entrust Root Certification Authority - G4
+    (0xfa1522078112a269, 1981713650), // TeliaSonera Root CA v1
+    (0x581249ef6fddf404, 2410308619), // TrustAsia Global Root CA G3
+    (0xb8219eba6ed32074, 2289374130), // CA Disig Root R2
+    (0xeaae5ffa9e9be177, 2221603200), // Amazon Root CA 4
+    (0xcefa582b3320c6c3, 1814281985), // Certigna
+    (0xcea9ebee9afd5bad, 2054799899), // D-TRUST BR Root CA 1 2020
+    (0xac768106f5130b01, 2147404528), // Security Communication ECC RootCA1
+    (0x5531ec63418d0636, 1696047649), // Security Communication RootCA1
+    (0xaf56069df56b9efb, 2147471999), // USERTrust RSA Certification Authority
+    (0xff239ab9253c3b2e, 1940952004), // certSIGN ROOT CA
+    (0xa5b7cf5f9c3cbe8d, 1893456000), // AC RAIZ FNMT-RCM
+    (0x43cced8799da4bc1, 1832673600), // GlobalSign Root CA
+    (0x1a46756ee3948abd, 2234852938), // Buypass Class 3 Root CA
+    (0x9ae4b18c0d2dc42c, 1888563046), // D-TRUST Root Class 3 CA 2 EV 2009
+    (0x4bb89cd8663d02c4, 2145887999), // HiPKI Root CA - G1
+    (0xc713eff8ae96e28d, 1893440455), // SecureTrust CA
+    (0x828e782754a4d8a4, 1924991999), // Atos TrustedRoot 2011
+    (0x739f704e917857fe, 2365125377), // BJCA Global Root CA1
+    (0x096d93670678614f, 2198311238), // ANF Secure Server Root CA
+    (0xe9e18c9a6fc10d23, 2011823999), // T-TeleSec GlobalRoot Class 3
+    (0x90e4848be162da2c, 2310120613), // Certum Trusted Root CA
+    (0xe3ee9bd2b8e0beea, 2408548602), // CommScope Public Trust RSA Root-02
+];
+
+/// Returns the not_after of a bundled Mozilla Root CA, in seconds since the unix epoch
+///
+/// # Arguments
+///
+/// * `spki` - the subject_public_key_info of the trust-anchor
+pub(crate) fn not_after(spki: &[u8]) -> Option<i64> {
+    let digest = Sha256::digest(spki);
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    let prefix = u64::from_be_bytes(prefix);
+    EXPIRY.iter().find(|(p, _)| *p == prefix).map(|(_, not_after)| *not_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_bundled_root_has_an_expiry() {
+        assert_eq!(webpki_roots::TLS_SERVER_ROOTS.len(), EXPIRY.len());
+        for ta in webpki_roots::TLS_SERVER_ROOTS {
+            assert!(not_after(ta.subject_public_key_info.as_ref()).is_some());
+        }
+    }
+}
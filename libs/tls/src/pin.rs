@@ -0,0 +1,445 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use locales::t;
+use modals::Modals;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{
+    CertificateDer, CertificateRevocationListDer, Der, ServerName, TrustAnchor, UnixTime,
+};
+use rustls::{CertificateError, DigitallySignedStruct, Error, RootCertStore, SignatureScheme};
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::{parse_x509_certificate, ASN1Time};
+use xous_names::XousNames;
+
+use crate::{key_prefix, Tls, KEY_NAME_LEN};
+
+/// The length of a SHA-256 hash of a SubjectPublicKeyInfo
+pub const PIN_LEN: usize = 32;
+
+/// A per-host pin: the SHA-256 hash of a DER encoded SubjectPublicKeyInfo
+pub type Pin = [u8; PIN_LEN];
+
+/// Returns the SHA-256 hash of the SubjectPublicKeyInfo in the Certificate
+pub fn spki_pin(cert: &CertificateDer) -> Option<Pin> {
+    match parse_x509_certificate(cert.as_ref()) {
+        Ok((_, x509)) => {
+            let mut hasher = Sha256::new();
+            hasher.update(x509.public_key().raw);
+            Some(hasher.finalize().into())
+        }
+        Err(e) => {
+            log::warn!("failed to get x509 from Certificate: {e}");
+            None
+        }
+    }
+}
+
+/// Returns the pddb key for the pins of a host. A host too long for a pddb key is cut short on a char
+/// boundary and tagged with a hash of the whole host, so that two long hosts don't share their pins.
+pub(crate) fn pin_key(host: &str) -> String {
+    let host = host.to_ascii_lowercase();
+    if host.len() < KEY_NAME_LEN {
+        return host;
+    }
+    let tag: String = Sha256::digest(host.as_bytes())[..8].iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{} {}", key_prefix(&host, KEY_NAME_LEN - 2 - tag.len()), tag)
+}
+
+/// How the pins saved for a host match the Certificate chain it offered
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PinMatch {
+    /// the SubjectPublicKeyInfo of the leaf Certificate is pinned
+    Leaf,
+    /// the chain was verified through a pinned intermediate or trust-anchor
+    Path,
+    Mismatch,
+}
+
+/// Matches the pins saved for a host against the Certificate chain it offered
+///
+/// # Arguments
+///
+/// * `pins` - the pins saved for the host
+/// * `leaf` - the pin of the leaf Certificate offered
+/// * `path` - the pins of the intermediates & trust-anchor that the chain was verified through, which is
+///   empty if the chain does not verify. Intermediates that were merely offered alongside the leaf must not
+///   be included, as anyone can append a public intermediate to a self-signed leaf.
+pub fn pin_match(pins: &[Pin], leaf: &Pin, path: &[Pin]) -> PinMatch {
+    if pins.contains(leaf) {
+        PinMatch::Leaf
+    } else if path.iter().any(|pin| pins.contains(pin)) {
+        PinMatch::Path
+    } else {
+        PinMatch::Mismatch
+    }
+}
+
+/// Returns the SHA-256 hash of a SubjectPublicKeyInfo held by a TrustAnchor
+///
+/// webpki strips the outer SEQUENCE from the SubjectPublicKeyInfo of a TrustAnchor,
+/// so it is restored here to match the pin of the corresponding Certificate.
+fn anchor_pin(spki: &[u8]) -> Pin {
+    let len = spki.len();
+    let header: Vec<u8> = match len {
+        0..=0x7F => vec![0x30, len as u8],
+        0x80..=0xFF => vec![0x30, 0x81, len as u8],
+        _ => vec![0x30, 0x82, (len >> 8) as u8, len as u8],
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(&header);
+    hasher.update(spki);
+    hasher.finalize().into()
+}
+
+/// Returns the hostname used as the pddb key for the pins of a host
+pub fn pin_host(server_name: &ServerName) -> String {
+    match server_name {
+        ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+        ServerName::IpAddress(ip) => format!("{:?}", ip),
+        _ => format!("{:?}", server_name),
+    }
+}
+
+/// Formats a Pin as colon separated hex, suitable for out-of-band comparison
+pub fn pin_hex(pin: &Pin) -> String {
+    pin.iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(":")
+}
+
+/// The PinnedCertificateVerification composes per-host SPKI pins with the default
+/// rustls WebPkiVerifier built on the trusted RootCertStore.
+///
+/// * A host with pins must offer a leaf Certificate with a matching SubjectPublicKeyInfo, or a chain that
+///   verifies to a trusted root through a pinned intermediate or trust-anchor. Only a matching leaf pin
+///   accepts a chain that does not terminate at a trusted root (i.e. a self-signed Certificate), so long as
+///   it is valid for the host and in date.
+/// * A host without pins is verified against the RootCertStore as usual. In TOFU mode the leaf & intermediate
+///   pins are recorded on first contact, and the user is offered the chance to trust-on-first-use a host that
+///   does not chain to a trusted root.
+/// * A pin mismatch raises a modal warning, and the connection is refused unless the user explicitly replaces
+///   the pins for the host.
+/// * Any CRLs provided are checked, but a certificate not covered by a CRL is not rejected.
+#[derive(Debug)]
+pub struct PinnedCertificateVerification {
+    pub roots: RootCertStore,
+    pub crls: Vec<CertificateRevocationListDer<'static>>,
+    pub supported: WebPkiSupportedAlgorithms,
+    pub tofu: bool,
+}
+
+impl PinnedCertificateVerification {
+    pub fn new(
+        mut roots: RootCertStore,
+        crls: Vec<CertificateRevocationListDer<'static>>,
+        tofu: bool,
+    ) -> Self {
+        if roots.is_empty() {
+            // rustls::ServerCertVerifierBuilder::build() returns a
+            // `CertVerifierBuilderError` if no trust anchors have been provided.
+            // see StifledCertificateVerification::new()
+            let single_bogus_ta_to_avoid_error_on_empty_roots = TrustAnchor {
+                subject: Der::from_slice(b"bogus subject"),
+                subject_public_key_info: Der::from_slice(b"bogus subject_public_key_info"),
+                name_constraints: None,
+            };
+            roots.roots.push(single_bogus_ta_to_avoid_error_on_empty_roots);
+        }
+        Self { roots, crls, supported: ring::default_provider().signature_verification_algorithms, tofu }
+    }
+
+    /// Returns the pins of the intermediates & trust-anchor through which the chain from `end_entity`
+    /// verifies to a trusted root, or an empty Vec if there is no such chain
+    pub fn path_pins(
+        &self,
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
+        now: UnixTime,
+    ) -> Vec<Pin> {
+        let cert = match webpki::EndEntityCert::try_from(end_entity) {
+            Ok(cert) => cert,
+            Err(_) => return Vec::new(),
+        };
+        match cert.verify_for_usage(
+            self.supported.all,
+            &self.roots.roots,
+            intermediates,
+            now,
+            webpki::KeyUsage::server_auth(),
+            None,
+            None,
+        ) {
+            Ok(path) => path
+                .intermediate_certificates()
+                .filter_map(|cert| spki_pin(&cert.der()))
+                .chain(std::iter::once(anchor_pin(path.anchor().subject_public_key_info.as_ref())))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Checks that a Certificate, which does not chain to a trusted root,
+    /// is at least valid for the host and in date.
+    fn verify_untrusted_leaf(
+        &self,
+        end_entity: &CertificateDer,
+        server_name: &ServerName,
+        now: UnixTime,
+    ) -> Result<(), Error> {
+        match webpki::EndEntityCert::try_from(end_entity) {
+            Ok(cert) => {
+                if cert.verify_is_valid_for_subject_name(server_name).is_err() {
+                    return Err(Error::InvalidCertificate(CertificateError::NotValidForName));
+                }
+            }
+            Err(_) => return Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
+        }
+        match parse_x509_certificate(end_entity.as_ref()) {
+            Ok((_, x509)) => match ASN1Time::from_timestamp(now.as_secs() as i64) {
+                Ok(time) => {
+                    if time < x509.validity().not_before {
+                        Err(Error::InvalidCertificate(CertificateError::NotValidYet))
+                    } else if time > x509.validity().not_after {
+                        Err(Error::InvalidCertificate(CertificateError::Expired))
+                    } else {
+                        Ok(())
+                    }
+                }
+                Err(_) => Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
+            },
+            Err(_) => Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
+        }
+    }
+}
+
+impl ServerCertVerifier for PinnedCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
+        server_name: &ServerName,
+        ocsp: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let webpki_verifier = match WebPkiServerVerifier::builder(Arc::new(self.roots.clone()))
+            .with_crls(self.crls.clone())
+            .allow_unknown_revocation_status()
+            .build()
+        {
+            Ok(verifier) => verifier,
+            Err(e) => {
+                log::warn!("failed to build WebPkiServerVerifier: {e}");
+                return Err(Error::General("failed to build WebPkiServerVerifier".to_string()));
+            }
+        };
+        let verified = webpki_verifier.verify_server_cert(end_entity, intermediates, server_name, ocsp, now);
+        let host = pin_host(server_name);
+        let leaf_pin = match spki_pin(end_entity) {
+            Some(pin) => pin,
+            None => return Err(Error::InvalidCertificate(CertificateError::BadEncoding)),
+        };
+        // all pins offered are recorded, but an intermediate pin only matches once the chain verifies
+        let offered: Vec<Pin> =
+            std::iter::once(end_entity).chain(intermediates.iter()).filter_map(spki_pin).collect();
+        let tls = Tls::new();
+        match tls.get_pins(&host) {
+            Some(pins) => {
+                let path = match verified {
+                    Ok(_) => self.path_pins(end_entity, intermediates, now),
+                    Err(_) => Vec::new(),
+                };
+                match pin_match(&pins, &leaf_pin, &path) {
+                    PinMatch::Leaf => {
+                        log::info!("leaf pin match: {host}");
+                        match verified {
+                            Ok(ok) => Ok(ok),
+                            Err(Error::InvalidCertificate(CertificateError::UnknownIssuer)) => {
+                                self.verify_untrusted_leaf(end_entity, server_name, now)?;
+                                Ok(ServerCertVerified::assertion())
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                    PinMatch::Path => {
+                        log::info!("chain pin match: {host}");
+                        verified
+                    }
+                    PinMatch::Mismatch => {
+                        log::warn!("pin MISMATCH: {host}");
+                        let modals = modals()?;
+                        let warning = format!(
+                            "{}\n{host}\n\n{}\n{}",
+                            t!("tls.pin_mismatch", locales::LANG),
+                            t!("tls.pin_offered", locales::LANG),
+                            pin_hex(&leaf_pin)
+                        );
+                        modals.show_notification(&warning, None).map_err(modal_error)?;
+                        let replace = match verified {
+                            Ok(_) | Err(Error::InvalidCertificate(CertificateError::UnknownIssuer)) => {
+                                yes_no_modal(&modals, t!("tls.pin_replace_prompt", locales::LANG))?
+                            }
+                            Err(_) => false,
+                        };
+                        if replace {
+                            if verified.is_err() {
+                                self.verify_untrusted_leaf(end_entity, server_name, now)?;
+                            }
+                            tls.save_pins(&host, &offered).ok();
+                            Ok(ServerCertVerified::assertion())
+                        } else {
+                            Err(Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure))
+                        }
+                    }
+                }
+            }
+            None => match verified {
+                Ok(ok) => {
+                    if self.tofu {
+                        log::info!("tofu: pinning {host}");
+                        tls.save_pins(&host, &offered).ok();
+                    }
+                    Ok(ok)
+                }
+                Err(Error::InvalidCertificate(CertificateError::UnknownIssuer)) if self.tofu => {
+                    self.verify_untrusted_leaf(end_entity, server_name, now)?;
+                    let modals = modals()?;
+                    let prompt = format!(
+                        "{}\n{host}\n\n{}",
+                        t!("tls.pin_tofu_prompt", locales::LANG),
+                        pin_hex(&leaf_pin)
+                    );
+                    if yes_no_modal(&modals, &prompt)? {
+                        tls.save_pins(&host, &offered).ok();
+                        Ok(ServerCertVerified::assertion())
+                    } else {
+                        Err(Error::InvalidCertificate(CertificateError::UnknownIssuer))
+                    }
+                }
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(message, cert, dss, &self.supported)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(message, cert, dss, &self.supported)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> { self.supported.supported_schemes() }
+}
+
+/// Connects to the modals server, failing the handshake if it is unavailable
+fn modals() -> Result<Modals, Error> {
+    let xns = XousNames::new().map_err(modal_error)?;
+    Modals::new(&xns).map_err(modal_error)
+}
+
+fn modal_error(e: xous::Error) -> Error {
+    log::warn!("modal failed: {:?}", e);
+    Error::General("modal failed".to_string())
+}
+
+fn yes_no_modal(modals: &Modals, prompt: &str) -> Result<bool, Error> {
+    modals.add_list_item(t!("tls.yes", locales::LANG)).map_err(modal_error)?;
+    modals.add_list_item(t!("tls.no", locales::LANG)).map_err(modal_error)?;
+    modals.get_radiobutton(prompt).map_err(modal_error)?;
+    match modals.get_radio_index() {
+        Ok(button) => Ok(button == 0),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+
+    use super::*;
+
+    fn cert(name: &str, ca: bool) -> Certificate {
+        let mut params = CertificateParams::new(vec![name.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        if ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+        Certificate::from_params(params).unwrap()
+    }
+
+    fn der(cert: &Certificate, signer: &Certificate) -> CertificateDer<'static> {
+        CertificateDer::from(cert.serialize_der_with_signer(signer).unwrap())
+    }
+
+    /// A root CA, an intermediate CA, and a leaf for example.com issued by the intermediate
+    fn chain()
+    -> (PinnedCertificateVerification, CertificateDer<'static>, CertificateDer<'static>, Certificate) {
+        let root = cert("root ca", true);
+        let intermediate = cert("intermediate ca", true);
+        let leaf = cert("example.com", false);
+        let mut roots = RootCertStore::empty();
+        roots.add(der(&root, &root)).unwrap();
+        let verifier = PinnedCertificateVerification::new(roots, Vec::new(), false);
+        (verifier, der(&leaf, &intermediate), der(&intermediate, &root), intermediate)
+    }
+
+    #[test]
+    fn self_signed_leaf_with_pinned_intermediate_is_refused() {
+        let (verifier, leaf, intermediate, _) = chain();
+        // the pins recorded on first contact with the genuine host
+        let pins = vec![spki_pin(&leaf).unwrap(), spki_pin(&intermediate).unwrap()];
+        // a MITM presents its own self-signed leaf, with the genuine (public) intermediate appended
+        let forged = cert("example.com", false);
+        let forged = der(&forged, &forged);
+        let path = verifier.path_pins(&forged, &[intermediate], UnixTime::now());
+        assert!(path.is_empty());
+        assert_eq!(pin_match(&pins, &spki_pin(&forged).unwrap(), &path), PinMatch::Mismatch);
+    }
+
+    #[test]
+    fn pins_match_leaf_or_verified_path() {
+        let (verifier, leaf, intermediate, issuer) = chain();
+        let pins = vec![spki_pin(&leaf).unwrap(), spki_pin(&intermediate).unwrap()];
+        let path = verifier.path_pins(&leaf, std::slice::from_ref(&intermediate), UnixTime::now());
+        assert!(path.contains(&spki_pin(&intermediate).unwrap()));
+        assert_eq!(pin_match(&pins, &spki_pin(&leaf).unwrap(), &path), PinMatch::Leaf);
+
+        // a renewed leaf with a new key, issued by the pinned intermediate
+        let renewed = der(&cert("example.com", false), &issuer);
+        let path = verifier.path_pins(&renewed, &[intermediate], UnixTime::now());
+        assert_eq!(pin_match(&pins, &spki_pin(&renewed).unwrap(), &path), PinMatch::Path);
+    }
+
+    #[test]
+    fn pin_key_fits_and_keeps_long_hosts_apart() {
+        assert_eq!(pin_key("Matrix.Example.org"), "matrix.example.org");
+        // multi-byte chars straddle the cut, which used to panic
+        let long = "ü".repeat(KEY_NAME_LEN);
+        let key = pin_key(&long);
+        assert!(key.len() < KEY_NAME_LEN);
+        assert!(key.starts_with("üü"));
+        let other = format!("{}x", long);
+        assert_ne!(pin_key(&other), key);
+        assert_eq!(pin_key(&long), key);
+    }
+
+    #[test]
+    fn anchor_pin_matches_certificate_pin() {
+        let root = cert("root ca", true);
+        let root_der = der(&root, &root);
+        let mut roots = RootCertStore::empty();
+        roots.add(root_der.clone()).unwrap();
+        assert_eq!(anchor_pin(roots.roots[0].subject_public_key_info.as_ref()), spki_pin(&root_der).unwrap());
+    }
+}
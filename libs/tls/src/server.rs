@@ -0,0 +1,191 @@
+// Server-side tls for on-device services
+//
+// A self-signed device certificate is generated on first use and persisted in the pddb.
+// There is no CA to vouch for the device, so the certificate fingerprint is displayed
+// for out-of-band verification by the user (i.e. compare with the browser or pin it).
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+use locales::t;
+use modals::Modals;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use sha2::{Digest, Sha256};
+use xous_names::XousNames;
+
+use crate::Tls;
+
+/// PDDB Dict for the tls server identity
+const TLS_SERVER_DICT: &str = "tls.server";
+/// PDDB key for the DER encoded self-signed device certificate
+const TLS_SERVER_CERT: &str = "cert";
+/// PDDB key for the PKCS#8 DER encoded device private key
+const TLS_SERVER_KEY: &str = "key";
+/// the subject alt names of the self-signed device certificate
+const TLS_SERVER_NAMES: [&str; 2] = ["precursor.local", "precursor"];
+
+impl Tls {
+    /// Returns the server identity (self-signed certificate & private key) from the pddb,
+    /// generating and saving a new identity on first use
+    pub fn server_identity(&self) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>), Error> {
+        if let (Some(cert), Some(key)) = (self.server_read(TLS_SERVER_CERT), self.server_read(TLS_SERVER_KEY))
+        {
+            return Ok((CertificateDer::from(cert), PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key))));
+        }
+        log::info!("generating self-signed tls server certificate");
+        let names: Vec<String> = TLS_SERVER_NAMES.iter().map(|name| name.to_string()).collect();
+        let cert = rcgen::generate_simple_self_signed(names).map_err(|e| {
+            log::warn!("failed to generate self-signed certificate: {e}");
+            Error::new(ErrorKind::Other, "failed to generate self-signed certificate")
+        })?;
+        let cert_der = cert.serialize_der().map_err(|e| {
+            log::warn!("failed to serialize self-signed certificate: {e}");
+            Error::new(ErrorKind::Other, "failed to serialize self-signed certificate")
+        })?;
+        let key_der = cert.serialize_private_key_der();
+        self.server_write(TLS_SERVER_KEY, &key_der)?;
+        self.server_write(TLS_SERVER_CERT, &cert_der)?;
+        Ok((CertificateDer::from(cert_der), PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_der))))
+    }
+
+    /// Returns the SHA-256 fingerprint of the device certificate as colon separated hex
+    pub fn server_fingerprint(&self) -> Result<String, Error> {
+        let (cert, _key) = self.server_identity()?;
+        let mut hasher = Sha256::new();
+        hasher.update(cert.as_ref());
+        let digest: [u8; 32] = hasher.finalize().into();
+        Ok(crate::pin::pin_hex(&digest))
+    }
+
+    /// Presents the device certificate fingerprint in a modal (and QR code) for
+    /// out-of-band verification
+    pub fn server_fingerprint_modal(&self) -> Result<(), Error> {
+        let fingerprint = self.server_fingerprint()?;
+        let xns = XousNames::new().map_err(modal_error)?;
+        let modals = Modals::new(&xns).map_err(modal_error)?;
+        let text = format!("{}\n\n{}", t!("tls.server_fingerprint", locales::LANG), fingerprint);
+        modals.show_notification(&text, Some(&fingerprint)).map_err(modal_error)
+    }
+
+    /// Deletes the server identity from the pddb - a new identity is generated on next use
+    pub fn del_server_identity(&self) -> Result<(), Error> {
+        match self.pddb.delete_dict(TLS_SERVER_DICT, None) {
+            Ok(_) => {
+                log::info!("Deleted {}\n", TLS_SERVER_DICT);
+                self.pddb.sync().or_else(|e| Ok::<(), Error>(log::warn!("{e}"))).ok();
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("failed to delete {}: {:?}", TLS_SERVER_DICT, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns a rustls ServerConfig presenting the self-signed device certificate
+    pub fn server_config(&self) -> Result<ServerConfig, Error> {
+        let (cert, key) = self.server_identity()?;
+        rustls::ServerConfig::builder().with_no_client_auth().with_single_cert(vec![cert], key).map_err(|e| {
+            log::warn!("failed to configure tls server: {e}");
+            Error::new(ErrorKind::Other, "failed to configure tls server")
+        })
+    }
+
+    fn server_write(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.pddb.delete_key(TLS_SERVER_DICT, key, None).ok();
+        match self.pddb.get(TLS_SERVER_DICT, key, None, true, true, Some(bytes.len()), None::<fn()>) {
+            Ok(mut pddb_key) => match pddb_key.write(bytes) {
+                Ok(len) => {
+                    self.pddb.sync().ok();
+                    log::info!("Wrote {} bytes to {}:{}", len, TLS_SERVER_DICT, key);
+                    Ok(())
+                }
+                Err(e) => {
+                    log::warn!("Error writing {}:{}: {:?}", TLS_SERVER_DICT, key, e);
+                    Err(e)
+                }
+            },
+            Err(e) => {
+                log::warn!("failed to create {}:{}\n{}", TLS_SERVER_DICT, key, e);
+                Err(e)
+            }
+        }
+    }
+
+    fn server_read(&self, key: &str) -> Option<Vec<u8>> {
+        match self.pddb.get(TLS_SERVER_DICT, key, None, false, false, None, None::<fn()>) {
+            Ok(mut pddb_key) => {
+                let mut bytes = Vec::<u8>::new();
+                match pddb_key.read_to_end(&mut bytes) {
+                    Ok(_) if bytes.len() > 0 => Some(bytes),
+                    Ok(_) => None,
+                    Err(e) => {
+                        log::warn!("failed to read {}:{}: {e}", TLS_SERVER_DICT, key);
+                        None
+                    }
+                }
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// A TcpListener that accepts tls connections with the self-signed device certificate
+pub struct TlsServer {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+}
+
+impl TlsServer {
+    /// Binds a TcpListener to addr, and configures tls with the device certificate
+    /// (generated and saved to the pddb on first use)
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - the local address to listen on (i.e. "0.0.0.0:443")
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<TlsServer, Error> {
+        let config = Tls::new().server_config()?;
+        let listener = TcpListener::bind(addr)?;
+        Ok(TlsServer { listener, config: Arc::new(config) })
+    }
+
+    /// Accepts a new incoming connection, and completes the tls handshake
+    ///
+    /// # Returns
+    ///
+    /// an owned rustls stream and the address of the remote peer
+    pub fn accept(&self) -> Result<(StreamOwned<ServerConnection, TcpStream>, SocketAddr), Error> {
+        let (mut sock, addr) = self.listener.accept()?;
+        let mut conn = self.connection()?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut sock)?;
+        }
+        Ok((StreamOwned::new(conn, sock), addr))
+    }
+
+    /// Returns an iterator over the tls connections received on this listener
+    ///
+    /// Each connection is returned before the tls handshake, which completes on first read/write.
+    /// This allows the handshake to take place on a worker thread.
+    pub fn incoming(
+        &self,
+    ) -> impl Iterator<Item = Result<StreamOwned<ServerConnection, TcpStream>, Error>> + '_ {
+        self.listener.incoming().map(move |sock| Ok(StreamOwned::new(self.connection()?, sock?)))
+    }
+
+    /// Returns the local socket address of this listener
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> { self.listener.local_addr() }
+
+    fn connection(&self) -> Result<ServerConnection, Error> {
+        ServerConnection::new(self.config.clone()).map_err(|e| {
+            log::warn!("failed to create ServerConnection: {e}");
+            Error::new(ErrorKind::Other, "failed to configure server connection")
+        })
+    }
+}
+
+fn modal_error(e: xous::Error) -> Error {
+    log::warn!("modal failed: {:?}", e);
+    Error::new(ErrorKind::Other, "modal failed")
+}
@@ -18,11 +18,18 @@ impl ureq::TlsConnector for TlsConnector {
         match ServerName::try_from(dns_name.to_owned()) {
             Ok(server_name) => {
                 loop {
-                    // refresh rustls client config with current root_store
+                    // refresh rustls client config with current root_store & pins
                     let tls = Tls::new();
-                    let config = rustls::ClientConfig::builder()
-                        .with_root_certificates(tls.root_store())
-                        .with_no_client_auth();
+                    let config = match tls.client_config() {
+                        Ok(config) => config,
+                        Err(e) => {
+                            log::warn!("{e}");
+                            return Err(ureq::Error::Status(
+                                495,
+                                Response::new(495, "tls", "client certificate unavailable").unwrap(),
+                            ));
+                        }
+                    };
                     match rustls::ClientConnection::new(Arc::new(config), server_name.clone()) {
                         Ok(mut connection) => {
                             log::info!("tls handshake started");
@@ -36,6 +43,14 @@ impl ureq::TlsConnector for TlsConnector {
                                 Err(e) => {
                                     if let Some(inner) = e.get_ref() {
                                         if let Some(rustls_error) = inner.downcast_ref::<rustls::Error>() {
+                                            // the user has refused a certificate that does not match the pins
+                                            if let rustls::Error::InvalidCertificate(
+                                                rustls::CertificateError::ApplicationVerificationFailure,
+                                            ) = rustls_error
+                                            {
+                                                log::warn!("{e}");
+                                                break;
+                                            }
                                             if let rustls::Error::InvalidCertificate(_) = rustls_error {
                                                 if let Ok(certs) = tls.probe(dns_name) {
                                                     if certs.len() > 0 {
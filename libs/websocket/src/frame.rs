@@ -0,0 +1,245 @@
+// RFC 6455 framing
+use std::convert::TryInto;
+
+pub(crate) const OP_CONTINUATION: u8 = 0x0;
+pub(crate) const OP_TEXT: u8 = 0x1;
+pub(crate) const OP_BINARY: u8 = 0x2;
+pub(crate) const OP_CLOSE: u8 = 0x8;
+pub(crate) const OP_PING: u8 = 0x9;
+pub(crate) const OP_PONG: u8 = 0xA;
+
+/// control frames can't be fragmented and carry at most this much
+const CONTROL_MAX: usize = 125;
+
+pub(crate) struct Frame {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn is_control(&self) -> bool { self.opcode & 0x8 != 0 }
+}
+
+/// Encodes a client frame, which is always masked with `mask`
+pub(crate) fn encode(opcode: u8, fin: bool, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 14);
+    out.push(if fin { 0x80 } else { 0 } | opcode);
+    match payload.len() {
+        len if len < 126 => out.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            out.push(0x80 | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(0x80 | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(&mask);
+    out.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+    out
+}
+
+/// Decodes the frame at the front of `buf`, returning it with the number of bytes it took, or
+/// `None` if more bytes are needed. Servers must not mask, and no extensions are negotiated, so
+/// either of those is a protocol error.
+pub(crate) fn decode(buf: &[u8], max_len: usize) -> Result<Option<(Frame, usize)>, &'static str> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buf[0] & 0x80 != 0;
+    let opcode = buf[0] & 0xf;
+    if buf[0] & 0x70 != 0 {
+        return Err("reserved bits set");
+    }
+    if buf[1] & 0x80 != 0 {
+        return Err("masked frame from server");
+    }
+    let (len, header) = match buf[1] & 0x7f {
+        126 => match buf.get(2..4) {
+            Some(b) => (u16::from_be_bytes(b.try_into().unwrap()) as u64, 4),
+            None => return Ok(None),
+        },
+        127 => match buf.get(2..10) {
+            Some(b) => (u64::from_be_bytes(b.try_into().unwrap()), 10),
+            None => return Ok(None),
+        },
+        len => (len as u64, 2),
+    };
+    match opcode {
+        OP_CONTINUATION | OP_TEXT | OP_BINARY => {
+            if len > max_len as u64 {
+                return Err("frame too long");
+            }
+        }
+        OP_CLOSE | OP_PING | OP_PONG => {
+            if !fin || len > CONTROL_MAX as u64 {
+                return Err("bad control frame");
+            }
+        }
+        _ => return Err("unknown opcode"),
+    }
+    let end = header + len as usize;
+    match buf.get(header..end) {
+        Some(payload) => Ok(Some((Frame { fin, opcode, payload: payload.to_vec() }, end))),
+        None => Ok(None),
+    }
+}
+
+/// Puts fragmented messages back together
+pub(crate) struct Assembler {
+    opcode: Option<u8>,
+    data: Vec<u8>,
+    max_len: usize,
+}
+
+impl Assembler {
+    pub fn new(max_len: usize) -> Self { Assembler { opcode: None, data: Vec::new(), max_len } }
+
+    /// Adds a data frame, returning the opcode and payload of the message once it is complete
+    pub fn push(&mut self, frame: Frame) -> Result<Option<(u8, Vec<u8>)>, &'static str> {
+        match (frame.opcode, self.opcode) {
+            (OP_CONTINUATION, None) => return Err("continuation without a message"),
+            (OP_CONTINUATION, Some(_)) => {}
+            (_, Some(_)) => return Err("new message before the last one finished"),
+            (opcode, None) => self.opcode = Some(opcode),
+        }
+        if self.data.len() + frame.payload.len() > self.max_len {
+            return Err("message too long");
+        }
+        self.data.extend_from_slice(&frame.payload);
+        if !frame.fin {
+            return Ok(None);
+        }
+        Ok(self.opcode.take().map(|opcode| (opcode, std::mem::take(&mut self.data))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    /// Builds an unmasked frame, as a server sends it
+    fn server_frame(opcode: u8, fin: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = encode(opcode, fin, payload, [0; 4]);
+        // drop the mask bit and the (all zero) masking key
+        let header = frame.len() - payload.len();
+        frame[1] &= 0x7f;
+        frame.drain(header - 4..header);
+        frame
+    }
+
+    #[test]
+    fn encode_masks_payload() {
+        // the masked "Hello" example of RFC 6455 section 5.7
+        let frame = encode(OP_TEXT, true, b"Hello", MASK);
+        assert_eq!(frame, [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]);
+        let unfinished = encode(OP_BINARY, false, b"", MASK);
+        assert_eq!(unfinished, [0x02, 0x80, 0x37, 0xfa, 0x21, 0x3d]);
+    }
+
+    #[test]
+    fn encode_extended_lengths() {
+        let payload = vec![0x5a; 125];
+        let frame = encode(OP_BINARY, true, &payload, MASK);
+        assert_eq!(frame[1], 0x80 | 125);
+        assert_eq!(frame.len(), 2 + 4 + 125);
+
+        let payload = vec![0x5a; 126];
+        let frame = encode(OP_BINARY, true, &payload, MASK);
+        assert_eq!(&frame[1..4], [0x80 | 126, 0, 126]);
+        assert_eq!(frame.len(), 4 + 4 + 126);
+
+        let payload = vec![0x5a; u16::MAX as usize + 1];
+        let frame = encode(OP_BINARY, true, &payload, MASK);
+        assert_eq!(&frame[1..10], [0x80 | 127, 0, 0, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(frame.len(), 10 + 4 + payload.len());
+        let unmasked: Vec<u8> = frame[14..].iter().zip(MASK.iter().cycle()).map(|(b, m)| b ^ m).collect();
+        assert_eq!(unmasked, payload);
+    }
+
+    #[test]
+    fn decode_lengths() {
+        for len in [0, 125, 126, u16::MAX as usize, u16::MAX as usize + 1] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let bytes = server_frame(OP_BINARY, true, &payload);
+            let (frame, used) = decode(&bytes, 1 << 20).unwrap().unwrap();
+            assert_eq!(used, bytes.len());
+            assert!(frame.fin);
+            assert_eq!(frame.opcode, OP_BINARY);
+            assert_eq!(frame.payload, payload);
+            // a partial frame, whether cut in the header or in the payload, waits for more
+            assert!(decode(&bytes[..1], 1 << 20).unwrap().is_none());
+            if len > 0 {
+                assert!(decode(&bytes[..bytes.len() - 1], 1 << 20).unwrap().is_none());
+            }
+        }
+        // a 64-bit length cut short
+        assert!(decode(&[0x82, 127, 0, 0, 0], 1 << 20).unwrap().is_none());
+        // the next frame in the buffer is left alone
+        let mut bytes = server_frame(OP_TEXT, true, b"one");
+        bytes.extend(server_frame(OP_TEXT, true, b"two"));
+        let (frame, used) = decode(&bytes, 1024).unwrap().unwrap();
+        assert_eq!((frame.payload.as_slice(), used), (&b"one"[..], 5));
+    }
+
+    #[test]
+    fn decode_rejects_bad_frames() {
+        assert!(decode(&encode(OP_TEXT, true, b"masked", MASK), 1024).is_err());
+        let mut reserved = server_frame(OP_TEXT, true, b"rsv");
+        reserved[0] |= 0x40;
+        assert!(decode(&reserved, 1024).is_err());
+        assert!(decode(&server_frame(0x3, true, b""), 1024).is_err());
+        assert!(decode(&server_frame(OP_BINARY, true, &[0; 1025]), 1024).is_err());
+    }
+
+    #[test]
+    fn control_frame_limits() {
+        let (frame, _) = decode(&server_frame(OP_PING, true, &[0; 125]), 1024).unwrap().unwrap();
+        assert!(frame.is_control());
+        assert!(decode(&server_frame(OP_PING, true, &[0; 126]), 1024).is_err());
+        assert!(decode(&server_frame(OP_PONG, false, b""), 1024).is_err());
+        assert!(decode(&server_frame(OP_CLOSE, false, &[0x03, 0xe8]), 1024).is_err());
+        // the data frame limit doesn't apply to control frames
+        assert!(decode(&server_frame(OP_CLOSE, true, &[0x03, 0xe8]), 1).unwrap().is_some());
+    }
+
+    fn frame(opcode: u8, fin: bool, payload: &[u8]) -> Frame {
+        Frame { fin, opcode, payload: payload.to_vec() }
+    }
+
+    #[test]
+    fn assembler_reassembles_fragments() {
+        let mut assembler = Assembler::new(16);
+        assert_eq!(
+            assembler.push(frame(OP_TEXT, true, b"whole")).unwrap(),
+            Some((OP_TEXT, b"whole".to_vec()))
+        );
+
+        assert_eq!(assembler.push(frame(OP_BINARY, false, b"frag")).unwrap(), None);
+        assert_eq!(assembler.push(frame(OP_CONTINUATION, false, b"men")).unwrap(), None);
+        assert_eq!(
+            assembler.push(frame(OP_CONTINUATION, true, b"ted")).unwrap(),
+            Some((OP_BINARY, b"fragmented".to_vec()))
+        );
+        // the assembler is ready for the next message
+        assert_eq!(assembler.push(frame(OP_TEXT, true, b"")).unwrap(), Some((OP_TEXT, Vec::new())));
+    }
+
+    #[test]
+    fn assembler_rejects_bad_sequences() {
+        let mut assembler = Assembler::new(16);
+        assert!(assembler.push(frame(OP_CONTINUATION, true, b"orphan")).is_err());
+
+        let mut assembler = Assembler::new(16);
+        assembler.push(frame(OP_TEXT, false, b"first")).unwrap();
+        assert!(assembler.push(frame(OP_TEXT, true, b"second")).is_err());
+
+        let mut assembler = Assembler::new(8);
+        assembler.push(frame(OP_BINARY, false, b"12345")).unwrap();
+        assert!(assembler.push(frame(OP_CONTINUATION, true, b"6789")).is_err());
+    }
+}
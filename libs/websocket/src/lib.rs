@@ -0,0 +1,449 @@
+// WebSocket client
+//
+// Opens a ws:// or wss:// connection (the latter through the `tls` connector, so untrusted
+// certificates get the usual trust prompt) and hands every message the server sends to a Xous
+// server as a message, so an app can take push updates in its main loop instead of keeping a thread
+// blocked on a long poll. A thread per connection reads frames, answers pings, sends its own pings
+// when the connection goes quiet and reassembles fragmented messages.
+//
+// Each message arrives as a `Message::Move` with the `id` given to `WebSocket::connect()`; decode it
+// with `Event::from_message()`. The last one is always an `Event::Closed`.
+mod frame;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use frame::*;
+use sha1::{Digest, Sha1};
+use tls::xtls::TlsConnector;
+use ureq::{ReadWrite, TlsConnector as _};
+use url::Url;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// how long the reader lets go of the stream so that messages can be sent
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const RESPONSE_HEADER_MAX: usize = 8192;
+
+const KIND_TEXT: usize = 1;
+const KIND_BINARY: usize = 2;
+const KIND_CLOSED: usize = 3;
+
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+/// never sent; reported when the connection ended without a close frame
+pub const CLOSE_ABNORMAL: u16 = 1006;
+pub const CLOSE_INVALID_DATA: u16 = 1007;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// extra request headers, e.g. `Authorization`
+    pub headers: Vec<(String, String)>,
+    /// offered in `Sec-WebSocket-Protocol`
+    pub protocols: Vec<String>,
+    /// a ping is sent after this long without hearing from the server
+    pub ping_interval: Duration,
+    /// the connection is dropped if a ping or close isn't answered within this long
+    pub pong_timeout: Duration,
+    /// longest message accepted, after reassembly
+    pub max_message: usize,
+    /// messages longer than this are sent in fragments
+    pub fragment_len: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            headers: Vec::new(),
+            protocols: Vec::new(),
+            ping_interval: Duration::from_secs(30),
+            pong_timeout: Duration::from_secs(10),
+            max_message: 1024 * 1024,
+            fragment_len: 16 * 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Url(String),
+    Io(io::Error),
+    Tls(String),
+    /// the server didn't switch protocols; the status line or reason
+    Handshake(String),
+    Closed,
+    Xous(xous::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Url(e) => write!(f, "bad url: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Tls(e) => write!(f, "tls error: {}", e),
+            Error::Handshake(e) => write!(f, "websocket handshake failed: {}", e),
+            Error::Closed => write!(f, "websocket is closed"),
+            Error::Xous(e) => write!(f, "xous error: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self { Error::Io(e) }
+}
+
+/// A message delivered to the app's server
+#[derive(Debug)]
+pub enum Event<'a> {
+    Text(&'a str),
+    Binary(&'a [u8]),
+    /// the close code and reason; the connection is gone
+    Closed(u16, &'a str),
+}
+
+impl<'a> Event<'a> {
+    pub fn from_message(msg: &'a xous::MemoryMessage) -> Option<Event<'a>> {
+        let len = msg.valid.map(|v| v.get()).unwrap_or(0).min(msg.buf.len());
+        // Safety: `u8` contains no undefined values
+        let data = unsafe { &msg.buf.as_slice::<u8>()[..len] };
+        match msg.offset.map(|o| o.get()) {
+            Some(KIND_TEXT) => std::str::from_utf8(data).ok().map(Event::Text),
+            Some(KIND_BINARY) => Some(Event::Binary(data)),
+            Some(KIND_CLOSED) => {
+                let (code, reason) = close_payload(data);
+                Some(Event::Closed(code, reason))
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Shared {
+    stream: Mutex<Box<dyn ReadWrite>>,
+    open: AtomicBool,
+    trng: trng::Trng,
+}
+
+impl Shared {
+    /// Sends one message, fragmented if need be; the lock is held throughout so that messages
+    /// sent from different threads don't interleave
+    fn send(&self, opcode: u8, payload: &[u8], fragment_len: usize) -> Result<(), Error> {
+        let mut stream = self.stream.lock().unwrap();
+        let mut chunks = payload.chunks(fragment_len.max(1)).peekable();
+        let mut first = true;
+        if payload.is_empty() {
+            stream.write_all(&encode(opcode, true, payload, self.mask()?))?;
+        }
+        while let Some(chunk) = chunks.next() {
+            let op = if first { opcode } else { OP_CONTINUATION };
+            stream.write_all(&encode(op, chunks.peek().is_none(), chunk, self.mask()?))?;
+            first = false;
+        }
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn mask(&self) -> Result<[u8; 4], Error> {
+        self.trng.get_u32().map(|m| m.to_ne_bytes()).map_err(Error::Xous)
+    }
+}
+
+pub struct WebSocket {
+    shared: Arc<Shared>,
+    fragment_len: usize,
+    protocol: Option<String>,
+}
+
+impl WebSocket {
+    /// Connects to `url` and starts delivering messages to `cid` with `id` as the opcode
+    pub fn connect(url: &str, cid: xous::CID, id: usize) -> Result<WebSocket, Error> {
+        WebSocket::connect_with(url, Config::default(), cid, id)
+    }
+
+    pub fn connect_with(url: &str, config: Config, cid: xous::CID, id: usize) -> Result<WebSocket, Error> {
+        let url = Url::parse(url).map_err(|e| Error::Url(e.to_string()))?;
+        let secure = match url.scheme() {
+            "ws" => false,
+            "wss" => true,
+            scheme => return Err(Error::Url(format!("{} is not a websocket scheme", scheme))),
+        };
+        let host = url.host_str().ok_or_else(|| Error::Url("no host".to_string()))?;
+        let port = url.port_or_known_default().unwrap_or(if secure { 443 } else { 80 });
+        let addr = (host, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Url(format!("{} did not resolve", host)))?;
+        let tcp = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        tcp.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let mut stream: Box<dyn ReadWrite> = if secure {
+            TlsConnector {}.connect(host, Box::new(tcp)).map_err(|e| Error::Tls(e.to_string()))?
+        } else {
+            Box::new(tcp)
+        };
+
+        let xns = xous_names::XousNames::new().unwrap();
+        let trng = trng::Trng::new(&xns).map_err(Error::Xous)?;
+        let mut nonce = [0u8; 16];
+        for word in nonce.chunks_mut(4) {
+            word.copy_from_slice(&trng.get_u32().map_err(Error::Xous)?.to_ne_bytes());
+        }
+        let key = base64::encode(nonce);
+        let protocol = handshake(stream.as_mut(), &url, &key, &config)?;
+        if let Some(socket) = stream.socket() {
+            socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        }
+        log::info!("websocket connected to {}", url);
+
+        let shared = Arc::new(Shared { stream: Mutex::new(stream), open: AtomicBool::new(true), trng });
+        let fragment_len = config.fragment_len;
+        thread::spawn({
+            let shared = shared.clone();
+            move || {
+                let (code, reason) = run(&shared, &config, cid, id);
+                log::info!("websocket closed: {} {}", code, reason);
+                shared.open.store(false, Ordering::SeqCst);
+                if let Some(socket) = shared.stream.lock().unwrap().socket() {
+                    socket.shutdown(std::net::Shutdown::Both).ok();
+                }
+                let mut payload = code.to_be_bytes().to_vec();
+                payload.extend_from_slice(reason.as_bytes());
+                deliver(cid, id, KIND_CLOSED, &payload).ok();
+            }
+        });
+        Ok(WebSocket { shared, fragment_len, protocol })
+    }
+
+    /// The subprotocol the server picked from `Config::protocols`
+    pub fn protocol(&self) -> Option<&str> { self.protocol.as_deref() }
+
+    pub fn is_open(&self) -> bool { self.shared.open.load(Ordering::SeqCst) }
+
+    pub fn send_text(&self, text: &str) -> Result<(), Error> { self.send(OP_TEXT, text.as_bytes()) }
+
+    pub fn send_binary(&self, data: &[u8]) -> Result<(), Error> { self.send(OP_BINARY, data) }
+
+    /// Starts the closing handshake; `Event::Closed` follows once the server agrees or times out
+    pub fn close(&self, code: u16, reason: &str) -> Result<(), Error> {
+        if !self.shared.open.swap(false, Ordering::SeqCst) {
+            return Err(Error::Closed);
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend(reason.bytes().take(CLOSE_REASON_MAX));
+        self.shared.send(OP_CLOSE, &payload, self.fragment_len)
+    }
+
+    fn send(&self, opcode: u8, payload: &[u8]) -> Result<(), Error> {
+        if !self.is_open() {
+            return Err(Error::Closed);
+        }
+        self.shared.send(opcode, payload, self.fragment_len)
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if self.is_open() {
+            self.close(CLOSE_GOING_AWAY, "").ok();
+        }
+    }
+}
+
+/// a close frame has room for the code and 123 bytes of reason
+const CLOSE_REASON_MAX: usize = 123;
+
+/// Sends the upgrade request and checks the response, returning the subprotocol the server picked
+fn handshake(
+    stream: &mut dyn ReadWrite,
+    url: &Url,
+    key: &str,
+    config: &Config,
+) -> Result<Option<String>, Error> {
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or(""), port),
+        None => url.host_str().unwrap_or("").to_string(),
+    };
+    let target = &url[url::Position::BeforePath..url::Position::AfterQuery];
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+        if target.is_empty() { "/" } else { target },
+        host,
+        key
+    );
+    if !config.protocols.is_empty() {
+        request.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", config.protocols.join(", ")));
+    }
+    for (name, value) in config.headers.iter() {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    // a byte at a time, so that no frame sent right after the response is read with it
+    let mut response = Vec::new();
+    let mut byte = [0u8; 1];
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() > RESPONSE_HEADER_MAX {
+            return Err(Error::Handshake("response header too long".to_string()));
+        }
+        match stream.read(&mut byte)? {
+            0 => return Err(Error::Handshake("connection closed".to_string())),
+            _ => response.push(byte[0]),
+        }
+    }
+    let response = String::from_utf8_lossy(&response);
+    let mut lines = response.split("\r\n");
+    let status = lines.next().unwrap_or("");
+    if status.split(' ').nth(1) != Some("101") {
+        return Err(Error::Handshake(status.to_string()));
+    }
+    let mut accept = None;
+    let mut protocol = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "sec-websocket-accept" => accept = Some(value.trim().to_string()),
+                "sec-websocket-protocol" => protocol = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    let expected = base64::encode(Sha1::digest(format!("{}{}", key, GUID).as_bytes()));
+    if accept.as_deref() != Some(expected.as_str()) {
+        return Err(Error::Handshake("bad Sec-WebSocket-Accept".to_string()));
+    }
+    if protocol.as_ref().map(|p| !config.protocols.contains(p)).unwrap_or(false) {
+        return Err(Error::Handshake("server picked a subprotocol that wasn't offered".to_string()));
+    }
+    Ok(protocol)
+}
+
+/// Reads frames until the connection closes, returning the close code and reason
+fn run(shared: &Shared, config: &Config, cid: xous::CID, id: usize) -> (u16, String) {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let mut assembler = Assembler::new(config.max_message);
+    let mut last_rx = Instant::now();
+    let mut ping_sent: Option<Instant> = None;
+    let mut close_seen: Option<Instant> = None;
+    'conn: loop {
+        let read = shared.stream.lock().unwrap().read(&mut chunk);
+        match read {
+            Ok(0) => break (CLOSE_ABNORMAL, "connection closed".to_string()),
+            Ok(len) => {
+                buf.extend_from_slice(&chunk[..len]);
+                last_rx = Instant::now();
+                ping_sent = None;
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(e) => break (CLOSE_ABNORMAL, e.to_string()),
+        }
+
+        loop {
+            let frame = match decode(&buf, config.max_message) {
+                Ok(Some((frame, used))) => {
+                    buf.drain(..used);
+                    frame
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    shared.send(OP_CLOSE, &CLOSE_PROTOCOL_ERROR.to_be_bytes(), config.fragment_len).ok();
+                    break 'conn (CLOSE_PROTOCOL_ERROR, e.to_string());
+                }
+            };
+            if frame.is_control() {
+                match frame.opcode {
+                    OP_PING => {
+                        shared.send(OP_PONG, &frame.payload, config.fragment_len).ok();
+                    }
+                    OP_CLOSE => {
+                        // echo it, unless this answers our own close
+                        if shared.open.swap(false, Ordering::SeqCst) {
+                            shared
+                                .send(
+                                    OP_CLOSE,
+                                    &frame.payload[..frame.payload.len().min(2)],
+                                    config.fragment_len,
+                                )
+                                .ok();
+                        }
+                        let (code, reason) = close_payload(&frame.payload);
+                        break 'conn (code, reason.to_string());
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            match assembler.push(frame) {
+                Ok(Some((OP_TEXT, text))) => {
+                    if std::str::from_utf8(&text).is_err() {
+                        shared.send(OP_CLOSE, &CLOSE_INVALID_DATA.to_be_bytes(), config.fragment_len).ok();
+                        break 'conn (CLOSE_INVALID_DATA, "text message is not UTF-8".to_string());
+                    }
+                    deliver(cid, id, KIND_TEXT, &text).ok();
+                }
+                Ok(Some((_, data))) => {
+                    deliver(cid, id, KIND_BINARY, &data).ok();
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    shared.send(OP_CLOSE, &CLOSE_PROTOCOL_ERROR.to_be_bytes(), config.fragment_len).ok();
+                    break 'conn (CLOSE_PROTOCOL_ERROR, e.to_string());
+                }
+            }
+        }
+
+        if !shared.open.load(Ordering::SeqCst) {
+            // we sent a close; give the server a while to answer it
+            match close_seen {
+                Some(t) if t.elapsed() > config.pong_timeout => break (CLOSE_NORMAL, String::new()),
+                Some(_) => {}
+                None => close_seen = Some(Instant::now()),
+            }
+        } else if let Some(t) = ping_sent {
+            if t.elapsed() > config.pong_timeout {
+                break (CLOSE_ABNORMAL, "no answer to ping".to_string());
+            }
+        } else if last_rx.elapsed() > config.ping_interval {
+            if shared.send(OP_PING, &[], config.fragment_len).is_err() {
+                break (CLOSE_ABNORMAL, "couldn't send ping".to_string());
+            }
+            ping_sent = Some(Instant::now());
+        }
+    }
+}
+
+fn close_payload(data: &[u8]) -> (u16, &str) {
+    match data {
+        [hi, lo, reason @ ..] => (u16::from_be_bytes([*hi, *lo]), std::str::from_utf8(reason).unwrap_or("")),
+        // a close frame without a code
+        _ => (1005, ""),
+    }
+}
+
+/// Moves `data` into a fresh page-aligned buffer and sends it to `cid`
+fn deliver(cid: xous::CID, id: usize, kind: usize, data: &[u8]) -> Result<(), xous::Error> {
+    let len = ((data.len() + 4095) & !4095).max(4096);
+    let mut buf = xous::map_memory(None, None, len, xous::MemoryFlags::R | xous::MemoryFlags::W)?;
+    // Safety: `u8` contains no undefined values
+    unsafe { buf.as_slice_mut::<u8>()[..data.len()].copy_from_slice(data) };
+    let msg = xous::MemoryMessage {
+        id,
+        buf,
+        offset: xous::MemoryAddress::new(kind),
+        valid: xous::MemorySize::new(data.len()),
+    };
+    xous::send_message(cid, xous::Message::Move(msg)).map(|_| ()).map_err(|e| {
+        xous::unmap_memory(buf).ok();
+        e
+    })
+}
@@ -21,6 +21,10 @@ pub mod regular;
 pub mod tall;
 #[cfg(not(feature = "cramium-soc"))]
 pub mod small;
+#[cfg(not(feature = "cramium-soc"))]
+pub mod he;
+#[cfg(not(feature = "cramium-soc"))]
+pub mod ar;
 
 #[cfg(feature = "cramium-soc")]
 pub mod emoji;
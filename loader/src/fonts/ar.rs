@@ -0,0 +1,560 @@
+#![cfg_attr(rustfmt, rustfmt_skip)]
+// DO NOT MAKE EDITS HERE because this file is automatically generated.
+// To make changes, see <xous_root>/services/graphics-server/src/blitstr2/codegen/main.go
+
+// This code includes bitmap glyphs extracted from PNG sprite sheet documents created
+// from the Unifont 15.1.05 glyph source, unifont.hex, as packaged in version
+// 1.1.0+data-15.1.05 of the unifont crate from https://crates.io/crates/unifont.
+//
+// Unifont 15 is dual-licensed under the SIL Open Font License (OFL) version 1.1
+// and the GNU GPL 2+ with the GNU font embedding exception.
+//
+// **This code is using Unifont 15 under the SIL OFL-1.1 option.**
+//
+// This code uses lists of Hebrew and Arabic codepoints derived from the Unicode Data
+// Files for Unicode 14, UnicodeData.txt, taking the characters of the Hebrew
+// (U+0590-U+05FF), Arabic (U+0600-U+06FF) and Arabic Presentation Forms-B
+// (U+FE70-U+FEFF) blocks that are assigned and not format characters.
+//
+// ===============================================================================
+//
+// The SIL Open Font License version 1.1 is copied below, and is also
+// available with a FAQ at http://scripts.sil.org/OFL.
+//
+//
+// -----------------------------------------------------------
+// SIL OPEN FONT LICENSE Version 1.1 - 26 February 2007
+// -----------------------------------------------------------
+//
+// PREAMBLE
+// The goals of the Open Font License (OFL) are to stimulate worldwide
+// development of collaborative font projects, to support the font creation
+// efforts of academic and linguistic communities, and to provide a free and
+// open framework in which fonts may be shared and improved in partnership
+// with others.
+//
+// The OFL allows the licensed fonts to be used, studied, modified and
+// redistributed freely as long as they are not sold by themselves. The
+// fonts, including any derivative works, can be bundled, embedded,
+// redistributed and/or sold with any software provided that any reserved
+// names are not used by derivative works. The fonts and derivatives,
+// however, cannot be released under any other type of license. The
+// requirement for fonts to remain under this license does not apply
+// to any document created using the fonts or their derivatives.
+//
+// DEFINITIONS
+// "Font Software" refers to the set of files released by the Copyright
+// Holder(s) under this license and clearly marked as such. This may
+// include source files, build scripts and documentation.
+//
+// "Reserved Font Name" refers to any names specified as such after the
+// copyright statement(s).
+//
+// "Original Version" refers to the collection of Font Software components as
+// distributed by the Copyright Holder(s).
+//
+// "Modified Version" refers to any derivative made by adding to, deleting,
+// or substituting -- in part or in whole -- any of the components of the
+// Original Version, by changing formats or by porting the Font Software to a
+// new environment.
+//
+// "Author" refers to any designer, engineer, programmer, technical
+// writer or other person who contributed to the Font Software.
+//
+// PERMISSION & CONDITIONS
+// Permission is hereby granted, free of charge, to any person obtaining
+// a copy of the Font Software, to use, study, copy, merge, embed, modify,
+// redistribute, and sell modified and unmodified copies of the Font
+// Software, subject to the following conditions:
+//
+// 1) Neither the Font Software nor any of its individual components,
+// in Original or Modified Versions, may be sold by itself.
+//
+// 2) Original or Modified Versions of the Font Software may be bundled,
+// redistributed and/or sold with any software, provided that each copy
+// contains the above copyright notice and this license. These can be
+// included either as stand-alone text files, human-readable headers or
+// in the appropriate machine-readable metadata fields within text or
+// binary files as long as those fields can be easily viewed by the user.
+//
+// 3) No Modified Version of the Font Software may use the Reserved Font
+// Name(s) unless explicit written permission is granted by the corresponding
+// Copyright Holder. This restriction only applies to the primary font name as
+// presented to the users.
+//
+// 4) The name(s) of the Copyright Holder(s) or the Author(s) of the Font
+// Software shall not be used to promote, endorse or advertise any
+// Modified Version, except to acknowledge the contribution(s) of the
+// Copyright Holder(s) and the Author(s) or with their explicit written
+// permission.
+//
+// 5) The Font Software, modified or unmodified, in part or in whole,
+// must be distributed entirely under this license, and must not be
+// distributed under any other license. The requirement for fonts to
+// remain under this license does not apply to any document created
+// using the Font Software.
+//
+// TERMINATION
+// This license becomes null and void if any of the above conditions are
+// not met.
+//
+// DISCLAIMER
+// THE FONT SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO ANY WARRANTIES OF
+// MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT
+// OF COPYRIGHT, PATENT, TRADEMARK, OR OTHER RIGHT. IN NO EVENT SHALL THE
+// COPYRIGHT HOLDER BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY,
+// INCLUDING ANY GENERAL, SPECIAL, INDIRECT, INCIDENTAL, OR CONSEQUENTIAL
+// DAMAGES, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF THE USE OR INABILITY TO USE THE FONT SOFTWARE OR FROM
+// OTHER DEALINGS IN THE FONT SOFTWARE.
+//
+//
+// ===============================================================================
+//
+// UNICODE, INC. LICENSE AGREEMENT - DATA FILES AND SOFTWARE
+//
+// See Terms of Use <https://www.unicode.org/copyright.html>
+// for definitions of Unicode Inc.’s Data Files and Software.
+//
+// NOTICE TO USER: Carefully read the following legal agreement.
+// BY DOWNLOADING, INSTALLING, COPYING OR OTHERWISE USING UNICODE INC.'S
+// DATA FILES ("DATA FILES"), AND/OR SOFTWARE ("SOFTWARE"),
+// YOU UNEQUIVOCALLY ACCEPT, AND AGREE TO BE BOUND BY, ALL OF THE
+// TERMS AND CONDITIONS OF THIS AGREEMENT.
+// IF YOU DO NOT AGREE, DO NOT DOWNLOAD, INSTALL, COPY, DISTRIBUTE OR USE
+// THE DATA FILES OR SOFTWARE.
+//
+// COPYRIGHT AND PERMISSION NOTICE
+//
+// Copyright © 1991-2022 Unicode, Inc. All rights reserved.
+// Distributed under the Terms of Use in https://www.unicode.org/copyright.html.
+//
+// Permission is hereby granted, free of charge, to any person obtaining
+// a copy of the Unicode data files and any associated documentation
+// (the "Data Files") or Unicode software and any associated documentation
+// (the "Software") to deal in the Data Files or Software
+// without restriction, including without limitation the rights to use,
+// copy, modify, merge, publish, distribute, and/or sell copies of
+// the Data Files or Software, and to permit persons to whom the Data Files
+// or Software are furnished to do so, provided that either
+// (a) this copyright and permission notice appear with all copies
+// of the Data Files or Software, or
+// (b) this copyright and permission notice appear in associated
+// Documentation.
+//
+// THE DATA FILES AND SOFTWARE ARE PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE
+// WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT OF THIRD PARTY RIGHTS.
+// IN NO EVENT SHALL THE COPYRIGHT HOLDER OR HOLDERS INCLUDED IN THIS
+// NOTICE BE LIABLE FOR ANY CLAIM, OR ANY SPECIAL INDIRECT OR CONSEQUENTIAL
+// DAMAGES, OR ANY DAMAGES WHATSOEVER RESULTING FROM LOSS OF USE,
+//
+// DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR OTHER
+// TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THE DATA FILES OR SOFTWARE.
+//
+// Except as contained in this notice, the name of a copyright holder
+// shall not be used in advertising or otherwise to promote the sale,
+// use or other dealings in these Data Files or Software without prior
+// written authorization of the copyright holder.
+//
+//! ar Font
+#![allow(dead_code)]
+#[link_section = ".fontdata"]
+#[no_mangle]
+#[used]
+/// Packed 16px * 16px glyph pattern data.
+/// Pixels are packed in row-major order with LSB of first pixel word
+/// containing the top left pixel. Bit of 0 means clear, 1 means set
+pub static AR_GLYPHS: [u32; 3104] = [
+    0x0a800000, 0x05840a87, 0x00880088, 0x00100090, 0x01200720, 0x00c00140, 0x004000c0, 0x00000040,
+    0x01000200, 0x02840087, 0x04880108, 0x00100310, 0x01200720, 0x00c00140, 0x004000c0, 0x00000040,
+    0x00000000, 0x00000000, 0x065c0980, 0x02880353, 0x01040208, 0x000000f8, 0x00000000, 0x00000000,
+    0x00000000, 0x00100000, 0x000a0010, 0x00040008, 0x00020004, 0x00110002, 0x00000005, 0x00000000,
+    0x00000000, 0x00100000, 0x000a0010, 0x00040008, 0x00020004, 0x00110042, 0x00000005, 0x00000000,
+    0x00000000, 0x000c000c, 0x00900060, 0x008000e0, 0x010f0070, 0x02000200, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00020000, 0x00030001, 0x00000003, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000c0018, 0x00010002, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000e0000, 0x0c110011, 0x000003fe, 0x00000000,
+    0x00000000, 0x00180000, 0x00040004, 0x00040018, 0x004a0032, 0x00240046, 0x0001001a, 0x00000000,
+    0x00180000, 0x00270024, 0x0000001a, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00300000, 0x007f0009, 0x00010001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x023f0000, 0x01040208, 0x000200e2, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x04300004, 0x023f0449, 0x00010185, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x027f0200, 0x01000280, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00020000, 0x0046003a, 0x0000003f, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00140014, 0x00160014, 0x00fe0001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00040000, 0x00040000, 0x00030004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x000e0006, 0x00010002, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000003,
+    0x00000000, 0x00000000, 0x00000000, 0x00020000, 0x00030001, 0x00030000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x0011000e, 0x00020001, 0x00340004, 0x00000038, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00020000, 0x00050000, 0x00000000,
+    0x00000000, 0x003e0000, 0x00010041, 0x00040002, 0x00080008, 0x00080000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00090025, 0x000e0011, 0x000a0004, 0x00000004,
+    0x00000000, 0x00000000, 0x00000000, 0x000e0000, 0x000f0001, 0x00010002, 0x00000000, 0x00000000,
+    0x00400000, 0x0001003e, 0x00080008, 0x00080008, 0x00080008, 0x00000008, 0x00000000, 0x00000000,
+    0x00010006, 0x00010006, 0x00040004, 0x00040004, 0x00040004, 0x00000004, 0x00000000, 0x00000000,
+    0x00000000, 0x00040018, 0x00040018, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00020002, 0x00020002, 0x00020002, 0x00000002, 0x00010006, 0x00010006,
+    0x00000000, 0x00000000, 0x0002000c, 0x0002000c, 0x00310000, 0x00090009, 0x00210031, 0x0000001e,
+    0x00000000, 0x00010000, 0x00010001, 0x00010001, 0x00010001, 0x00000001, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00080000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000009, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x0000000a, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00010002, 0x00010009, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000002, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00540040, 0x003d0054, 0x00090009, 0x00000006,
+    0x00000000, 0x00080000, 0x00240000, 0x00000000, 0x00540040, 0x003d0054, 0x00090009, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004c0030, 0x00090039, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004c0030, 0x00090039, 0x00060009, 0x00000000,
+    0x00000000, 0x00020000, 0x00020002, 0x001a0002, 0x00220026, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00020000, 0x00020012, 0x001a0002, 0x00220026, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x000c0000, 0x00020012, 0x0002001c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00000008, 0x000c0000, 0x00020012, 0x0002001c, 0x00010001, 0x001e0021,
+    0x00000000, 0x0020000a, 0x00080010, 0x00020004, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00080010, 0x00020004, 0x0041003c, 0x0000007e, 0x00000014, 0x00000008,
+    0x00000000, 0x00000000, 0x000a0004, 0x00000011, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000005, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00020000, 0x00050000, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000012, 0x001c0000, 0x00280024, 0x00210032, 0x000e0011, 0x00000000,
+    0x00000000, 0x00200000, 0x00240028, 0x00220028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00220020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0018000e, 0x0001001e, 0x00010001, 0x00010001,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00290010, 0x00110009, 0x001e0021, 0x000a0000,
+    0x0003000c, 0x0003000c, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00180018, 0x000a0013, 0x00000007, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00030004, 0x0003000c, 0x00000000,
+    0x00000000, 0x0003000c, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x001c000c, 0x00020004, 0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0003000c,
+    0x00000000, 0x00100000, 0x000d0014, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00090006, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00400000, 0x0001003e, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00010006, 0x00010006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010006, 0x00010006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010000, 0x00020002,
+    0x00080010, 0x00070004, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00090009, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x000f0000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00110000, 0x0004000a, 0x00000004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00040000, 0x000a0004, 0x00000011, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00030003, 0x00000000,
+    0x00070006, 0x00080004, 0x00000010, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00040009, 0x00090002, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00080030, 0x000d0032,
+    0x00000000, 0x00000000, 0x00000000, 0x00070002, 0x00000002, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00010000, 0x00020001, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00110000, 0x00060009, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00490000, 0x001e0025, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00100000, 0x00040008, 0x000c0002, 0x00010002, 0x001e0021, 0x00000000, 0x00000000,
+    0x00000000, 0x000c0000, 0x00120012, 0x00210021, 0x00210021, 0x000c0012, 0x00000000, 0x00000000,
+    0x00000000, 0x00010000, 0x0010001e, 0x00200010, 0x00200020, 0x00400040, 0x00000000, 0x00000000,
+    0x00000000, 0x00410000, 0x00220041, 0x00140022, 0x00080014, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00080008, 0x00140014, 0x00220022, 0x00410041, 0x00000000, 0x00000000,
+    0x00000000, 0x000c0000, 0x00110012, 0x00160019, 0x00200020, 0x00400040, 0x00000000, 0x00000000,
+    0x00000000, 0x00100010, 0x00090009, 0x00040004, 0x00120012, 0x00010001, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00080000, 0x00080008, 0x00000007, 0x00000000,
+    0x00000000, 0x00030000, 0x00020003, 0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00080000, 0x007f0008, 0x0036001c, 0x00000022, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x001c0000, 0x00280024, 0x00210032, 0x000e0011, 0x00000000,
+    0x00010001, 0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00140008, 0x0001001e, 0x00040004, 0x00040004, 0x00040004, 0x00000004, 0x00000000, 0x00000000,
+    0x00080030, 0x000d0032, 0x00080000, 0x00080008, 0x00080008, 0x00000008, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00080008, 0x00080008, 0x00080008, 0x00000008, 0x00080030, 0x000d0032,
+    0x00000000, 0x000e0000, 0x000f0001, 0x00010002, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00310000, 0x00310009, 0x00010009, 0x00010001, 0x00000001, 0x00000000, 0x00000000,
+    0x00000000, 0x00600000, 0x00600010, 0x00040010, 0x000a000a, 0x0008000e, 0x00040008, 0x00000003,
+    0x00000000, 0x00660006, 0x00620014, 0x00040011, 0x000a000a, 0x0008000e, 0x00040008, 0x00000003,
+    0x00000000, 0x00300000, 0x00300008, 0x00000008, 0x00150008, 0x00090005, 0x00110011, 0x0000000e,
+    0x00000000, 0x00020002, 0x000e000e, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000004, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00000008, 0x00000008,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00210022, 0x0008001e, 0x00080014, 0x00000000,
+    0x00000000, 0x0000000a, 0x00000004, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00000014, 0x00000008,
+    0x00000000, 0x0000000a, 0x0000000a, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00000014, 0x00000014,
+    0x00000000, 0x000c0000, 0x000c0002, 0x00000002, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00040000, 0x00040000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00010002, 0x00010015, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00090002, 0x00090001, 0x001c0022,
+    0x00000000, 0x00040000, 0x00120000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0002003f, 0x00150001, 0x00090001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0002003f, 0x00150001, 0x00150001, 0x001c0022,
+    0x00020000, 0x000e0002, 0x0000000e, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0008001f, 0x00080014, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0000001f, 0x00000008, 0x00000000,
+    0x00020000, 0x000e0002, 0x0000000e, 0x00100008, 0x00210020, 0x0000001f, 0x00000008, 0x00000000,
+    0x00000000, 0x000a0000, 0x00000000, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0000001f, 0x0000000a, 0x00000000,
+    0x00000000, 0x00000004, 0x0000000a, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x0000000a, 0x00000004, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x0000000a, 0x0000000a, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00080008, 0x00380038, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x000a0011, 0x00000004, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00200020, 0x00190010, 0x0010002e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000026,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00100010, 0x00090008, 0x00200056,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00240020, 0x00090010, 0x00000026,
+    0x00000000, 0x00000000, 0x00240000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00080000, 0x00140000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00240000, 0x00240000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000010, 0x00400000, 0x00540054, 0x0009003d, 0x00060029, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00400000, 0x00540054, 0x0005003d, 0x00020055, 0x00000020,
+    0x00000000, 0x00000008, 0x00000024, 0x00400000, 0x00540054, 0x0005003d, 0x00020055, 0x00000020,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004c0030, 0x00050039, 0x00020055, 0x00000000,
+    0x00000000, 0x00080000, 0x00240000, 0x00000000, 0x004c0030, 0x00090039, 0x00060009, 0x00000000,
+    0x00000000, 0x00120000, 0x002a0002, 0x001a0002, 0x00220026, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x000c0000, 0x00020012, 0x0002001c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000008, 0x00000000,
+    0x00000000, 0x00000010, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000008, 0x00000000,
+    0x00080000, 0x00240000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000012, 0x00000004,
+    0x00280000, 0x00280000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000010, 0x001c0000, 0x00280024, 0x00210032, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x001c0000, 0x00280024, 0x00210032, 0x000e0011, 0x00000000,
+    0x00000000, 0x00200000, 0x00080010, 0x00020004, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x0c000000, 0x00c00300, 0x00080030, 0x20011ff0, 0x00001ffe, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00580030, 0x00020024, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00080000, 0x00200000, 0x00240028, 0x00220028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000004, 0x0020000a, 0x00240028, 0x00220028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00240028, 0x00220028, 0x00210021, 0x0000001e, 0x00000014, 0x00000008,
+    0x00100000, 0x00240008, 0x00080012, 0x00020004, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00100000, 0x00240008, 0x00580032, 0x00020024, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x000a0000, 0x00180060, 0x00300046, 0x0002000c, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00100000, 0x00240008, 0x00080012, 0x00020004, 0x0041003c, 0x0000007e, 0x00000014, 0x00000000,
+    0x00100000, 0x00240008, 0x00080012, 0x00020004, 0x0041003c, 0x0000007e, 0x00000008, 0x00000008,
+    0x00000002, 0x00180065, 0x00300046, 0x0002000c, 0x0041003c, 0x0000007e, 0x00000000, 0x00000000,
+    0x00440000, 0x00100028, 0x00200000, 0x00200020, 0x00220020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00200000, 0x00200000, 0x00200020, 0x00200020, 0x00220020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00000010, 0x00000048, 0x00200020, 0x00200020, 0x00220020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00220020, 0x001e0021, 0x00140000, 0x00080000,
+    0x00000000, 0x00000000, 0x00080000, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00080000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00000000,
+    0x00000000, 0x00040004, 0x001c001c, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00080000, 0x00000000, 0x00220010, 0x00210021, 0x001e0011, 0x00100028,
+    0x00000000, 0x00040000, 0x00120000, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x001a0004, 0x002c002a, 0x00010012, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0002003f, 0x00150001, 0x00090001, 0x001c0022,
+    0x00060000, 0x00060001, 0x00000001, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00040000, 0x0001007a, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x0002000c, 0x0002000c, 0x00040000, 0x0001007a, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x0000000a, 0x00040000, 0x0001007a, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00240024, 0x00220038, 0x00120025, 0x0000000f,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00240024, 0x0020003c, 0x0009001e, 0x00000006,
+    0x00000000, 0x00220000, 0x00080014, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x000c000c, 0x00040008, 0x00180002, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00080000, 0x00100010, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00080000, 0x00220014, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000014, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000008, 0x00000024, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00540020, 0x00250016, 0x00440044, 0x00000038,
+    0x00000000, 0x00000000, 0x000a0011, 0x00000004, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000008, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00090025, 0x000e0011, 0x00040000, 0x00040000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00090025, 0x000e0011, 0x000a0000, 0x00040000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00080000, 0x00040014, 0x00010002, 0x0000007f,
+    0x00000000, 0x00000000, 0x00060000, 0x00060001, 0x00080001, 0x00040014, 0x00010002, 0x0000007f,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x07480208, 0x000107fe, 0x0000007f, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00880248, 0x000100fe, 0x0000007f, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00100000, 0x003c0028, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00120011, 0x0008000c, 0x0000000c, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x0004003f, 0x00840012, 0x00000078, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000002, 0x00000005, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x01480000, 0x000901f9, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00800000, 0x1ffc0140, 0x180c1414, 0x322611c4, 0x32265225, 0x180c11c4, 0x1ffc1414, 0x00800140,
+    0x00090006, 0x00060009, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00090006, 0x00090009, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x0018003f, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00070006, 0x00010001, 0x00000002, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x01f90148, 0x00060009,
+    0x0000001f, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000a0004, 0x0008000e, 0x00000007, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00020004, 0x007f0001, 0x00000000, 0x00000000,
+    0x00040000, 0x00010002, 0x0000007f, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00080000, 0x00210021, 0x0000003e, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00800000, 0x02a00140, 0x0aa80550, 0x2a2a1554, 0x15541554, 0x15d41554, 0x17f41414, 0x7fff3006,
+    0x000a0004, 0x000a0011, 0x00000004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00040000, 0x0011000a, 0x0004000a,
+    0x00030000, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010003, 0x00020001,
+    0x00000000, 0x00140008, 0x00000022, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00280010, 0x00000044, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00070002, 0x00000002, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00010000, 0x00020001, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00110000, 0x00060009, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00490000, 0x001e0025, 0x00040002, 0x00080004, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x000c0000, 0x00030012, 0x001a0027, 0x00040002, 0x00040004, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00080008, 0x00140014, 0x00220022, 0x00550049, 0x00000063, 0x00000000,
+    0x00000000, 0x00000000, 0x0022001c, 0x00020002, 0x0030000c, 0x0003000c, 0x00000000, 0x00000000,
+    0x00000000, 0x00410000, 0x00220041, 0x00140022, 0x00080014, 0x00080008, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00080008, 0x00140014, 0x00220022, 0x00410041, 0x00000000, 0x00000000,
+    0x00000000, 0x000c0000, 0x00110012, 0x00160019, 0x00200020, 0x00400040, 0x00000000, 0x00000000,
+    0x00000000, 0x00000008, 0x00000024, 0x00400000, 0x00540054, 0x0009003d, 0x00060029, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004c0030, 0x00090039, 0x00060029, 0x00000000,
+    0x00000000, 0x00000000, 0x00000008, 0x000c0000, 0x00020012, 0x0002001c, 0x00010009, 0x001e0021,
+    0x00000000, 0x00000000, 0x00000000, 0x0001000e, 0x0002000f, 0x00000001, 0x000a000a, 0x000a000a,
+    0x00000000, 0x00000000, 0x00000000, 0x00080000, 0x00240014, 0x0002003a, 0x00280029, 0x00280028,
+    0x00000000, 0x00040000, 0x0011000a, 0x001a0004, 0x002c002a, 0x00010012, 0x00000000, 0x00000000,
+    0x000c0000, 0x000c0003, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00300000, 0x0030000c, 0x0000000c, 0x00000000, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x00180018, 0x000a0013, 0x00000007, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010002, 0x00010001, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0003000c, 0x0003000c, 0x00000000,
+    0x00000000, 0x000c0000, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00300000, 0x0000000c, 0x00000000, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x001c000c, 0x00020004, 0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00700030, 0x00080010, 0x00000004, 0x00000000, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0003000c,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000000ff, 0x00000000, 0x000c0030,
+    0x00000000, 0x00100000, 0x000d0014, 0x00000003, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x001a0028, 0x00000006, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00060000, 0x00090009, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00180000, 0x00240024, 0x00000018, 0x00000000, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x000e0000, 0x000f0001, 0x00010002, 0x00000000, 0x00000000,
+    0x00400000, 0x0001003e, 0x00080008, 0x00080008, 0x00080008, 0x00000008, 0x00000000, 0x00000000,
+    0x00100000, 0x0001000e, 0x00040004, 0x00040004, 0x00040004, 0x000000f8, 0x00000000, 0x00000000,
+    0x00010006, 0x00010006, 0x00040004, 0x00040004, 0x00040004, 0x00000004, 0x00000000, 0x00000000,
+    0x00010006, 0x00010006, 0x00020002, 0x00020002, 0x00020002, 0x0000007c, 0x00000000, 0x00000000,
+    0x00000000, 0x00040018, 0x00040018, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00040018, 0x00040018, 0x00180000, 0x00240024, 0x0020007c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00020002, 0x00020002, 0x00020002, 0x00000002, 0x00010006, 0x00010006,
+    0x00000000, 0x00000000, 0x00020002, 0x00020002, 0x00020002, 0x0000007c, 0x00010006, 0x00010006,
+    0x00000000, 0x00000000, 0x00010006, 0x00010006, 0x00310000, 0x00090009, 0x00210031, 0x0000001e,
+    0x00000000, 0x00000000, 0x00010006, 0x00010006, 0x00010000, 0x00210061, 0x001e0021, 0x00000000,
+    0x00000000, 0x00300000, 0x00300008, 0x00000008, 0x00200020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00300000, 0x00300008, 0x00000008, 0x00200020, 0x000000df, 0x00000000, 0x00000000,
+    0x00000000, 0x00010000, 0x00010001, 0x00010001, 0x00010001, 0x00000001, 0x00000000, 0x00000000,
+    0x00000000, 0x00010000, 0x00010001, 0x00010001, 0x00010001, 0x0000003e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000001e, 0x00080000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00210022, 0x0000007e, 0x00080000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00200020, 0x0000003f, 0x00080000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00200020, 0x000000ff, 0x00080000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000009, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00120000, 0x00080000, 0x000a000c, 0x000f0009, 0x00000038, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00210022, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00200020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000014, 0x00100000, 0x00200020, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x00100000, 0x00210022, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x00100000, 0x00210022, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x00100000, 0x00200020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000004, 0x00000012, 0x00100000, 0x00200020, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00010002, 0x00010009, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0014003f, 0x00010062, 0x00010009, 0x003c0042,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0060001c, 0x0000007f, 0x00000008, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0060001c, 0x000000ff, 0x00000008, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0014003f, 0x00010062, 0x00010001, 0x003c0042,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0030000e, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0030000e, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0004003f, 0x00010002, 0x00010001, 0x001c0022,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0014003f, 0x00010062, 0x00010001, 0x003c0042,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0030000e, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x0030000e, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100008, 0x00210020, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000002, 0x00100008, 0x00210020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000002, 0x00100008, 0x00210020, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00200010, 0x00200060, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00200010, 0x00200020, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00200010, 0x00200060, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00400000, 0x00540054, 0x0009003d, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00400000, 0x00540054, 0x0009007d, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00400000, 0x00540054, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00400000, 0x00540054, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00240000, 0x00000000, 0x00540040, 0x003d0054, 0x00090009, 0x00000006,
+    0x00000000, 0x00080000, 0x00240000, 0x00400000, 0x00540054, 0x0009007d, 0x00060009, 0x00000000,
+    0x00000000, 0x00080000, 0x00240000, 0x00400000, 0x00540054, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00240000, 0x00400000, 0x00540054, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004c0030, 0x00090039, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004c0030, 0x00090079, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004a0030, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x004a0030, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004c0030, 0x00090039, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004c0030, 0x00090079, 0x00060009, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004a0030, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00100000, 0x00000000, 0x004a0030, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00020000, 0x00020002, 0x001a0002, 0x00220026, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00020000, 0x00020002, 0x001a0002, 0x00220026, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00040000, 0x00040004, 0x00340004, 0x0044004c, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00040000, 0x00040004, 0x00340004, 0x0044004c, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00020000, 0x00020012, 0x001a0002, 0x00220026, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00020000, 0x00020012, 0x001a0002, 0x00220026, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00040000, 0x00040024, 0x00340004, 0x0044004c, 0x0000003f, 0x00000000, 0x00000000,
+    0x00000000, 0x00040000, 0x00040024, 0x00340004, 0x0044004c, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x000c0000, 0x00020012, 0x0002001c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00060006, 0x0002007c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00040024, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00100018, 0x000000ef, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000008, 0x000c0000, 0x00020012, 0x0002001c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00060006, 0x0002007c, 0x00010001, 0x001e0021,
+    0x00000000, 0x00000000, 0x00000010, 0x00180000, 0x00040024, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000010, 0x00180000, 0x00100018, 0x000000ef, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00300000, 0x00300028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00300000, 0x00300028, 0x00210021, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00000000, 0x0024001c, 0x00300028, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00080000, 0x00000000, 0x0024001c, 0x00300028, 0x000000df, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000012, 0x001c0000, 0x00280024, 0x00210032, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00000012, 0x001c0000, 0x00280024, 0x00210072, 0x000e0011, 0x00000000,
+    0x00000000, 0x00120000, 0x00000000, 0x0024001c, 0x00300028, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00120000, 0x00000000, 0x0024001c, 0x00300028, 0x000000df, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00240028, 0x00220028, 0x00210021, 0x0000001e, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00240028, 0x00220028, 0x00210021, 0x0000007e, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00080010, 0x00020004, 0x0040003c, 0x0000007f, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00080010, 0x00020004, 0x0040003c, 0x000000ff, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00220020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00620020, 0x00210021, 0x001e0011, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00200020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00200020, 0x00200020, 0x00200020, 0x000000df, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0018000e, 0x0001001e, 0x00010001, 0x00010001,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0018000e, 0x0001003e, 0x00010001, 0x00010001,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x004c0028, 0x00000077, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x004c0028, 0x000000f7, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00220010, 0x00210021, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00000000, 0x00220010, 0x00210061, 0x000e0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00000008, 0x00100000, 0x00200020, 0x0000001f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000008, 0x00100000, 0x00200020, 0x000000df, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00060002, 0x00090009, 0x0000000f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00080008, 0x000a000c, 0x000f0009, 0x00000038, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00080000, 0x003c0010, 0x005c0054, 0x0000006f, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00300000, 0x00280028, 0x002800ff, 0x00000030, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00240024, 0x0020003c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00180000, 0x00240024, 0x0020007c, 0x00090010, 0x00000006,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00290010, 0x00110009, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00110061, 0x00210021, 0x0000001e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00290010, 0x00110009, 0x001e0021, 0x000a0000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00110061, 0x001e0021, 0x000a0000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00200010, 0x0000001f, 0x00000014, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00100000, 0x00200010, 0x000000df, 0x00000014, 0x00000000,
+    0x00000000, 0x004e0010, 0x00440041, 0x00500048, 0x00300020, 0x00380028, 0x00000000, 0x00000000,
+    0x00000000, 0x004e0010, 0x00440041, 0x00500048, 0x00500070, 0x000600c8, 0x00000000, 0x00000000,
+    0x00000000, 0x00210006, 0x00210026, 0x00280024, 0x00180010, 0x001c0014, 0x00000000, 0x00000000,
+    0x00000000, 0x00210006, 0x00210026, 0x00280024, 0x00280038, 0x00030064, 0x00000000, 0x00000000,
+    0x00000000, 0x00100010, 0x00120011, 0x00080014, 0x000a000c, 0x0000000e, 0x0002000c, 0x0002000c,
+    0x00000000, 0x00200000, 0x00220020, 0x00280024, 0x00280038, 0x00030064, 0x00040018, 0x00040018,
+    0x00000000, 0x00100000, 0x00110010, 0x00140012, 0x000c0008, 0x000e000a, 0x00000000, 0x00000000,
+    0x00000000, 0x00200000, 0x00220020, 0x00280024, 0x00280038, 0x00030064, 0x00000000, 0x00000000,
+];
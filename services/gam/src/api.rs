@@ -2,7 +2,7 @@ mod rkyv_enum;
 // note: many enums in the API are isolated to this file.
 #[cfg(feature = "ditherpunk")]
 use graphics_server::api::Tile;
-use graphics_server::api::{Gid, Point};
+use graphics_server::api::{Gid, Point, Screenshot};
 pub use rkyv_enum::*;
 use xous_ipc::String;
 
@@ -111,6 +111,34 @@ pub struct UxRegistration {
     /// ignored and does nothing
     pub focuschange_id: Option<u32>,
 }
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct ScreenshotListener {
+    /// the status bar's token, as only the status bar is trusted to save screenshots
+    pub token: [u32; 4],
+    pub listener: [u32; 4],
+    pub opcode: u32,
+}
+/// A copy of the screen, taken only for the screenshot listener
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct ScreenshotCapture {
+    /// the token of the screenshot listener, which is the status bar's
+    pub token: [u32; 4],
+    pub shot: Screenshot,
+}
+/// The longest text, in bytes, of an `Announcement` or a `CanvasDescription`
+pub const SCREEN_READER_TEXT_LEN: usize = 512;
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct Announcement {
+    /// the app token of the context making the announcement; it is only spoken while that context has focus
+    pub token: [u32; 4],
+    pub text: String<SCREEN_READER_TEXT_LEN>,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct CanvasDescription {
+    pub canvas: Gid,
+    /// an empty description clears it
+    pub text: String<SCREEN_READER_TEXT_LEN>,
+}
 #[cfg(feature = "unsafe-app-loading")]
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub struct NameRegistration {
@@ -227,6 +255,28 @@ pub(crate) enum Opcode {
     /// Register a name that can acquire a token. This is only intended to be used with pre-registered apps
     #[cfg(feature = "unsafe-app-loading")]
     RegisterName = 34,
+
+    /// Copy the screen for the screenshot listener, unless a canvas that vetoes screenshots is on screen
+    Screenshot = 35,
+
+    /// Ask the screenshot listener to take and save a screenshot
+    ScreenshotRequest = 36,
+
+    /// Register the screenshot listener. Only the status bar may do this.
+    ScreenshotListener = 37,
+
+    /// Speak a line through the screen reader, if it is on and the sender has focus
+    Announce = 38,
+
+    /// Attach a text description to a canvas, which the screen reader reads when the canvas' context takes
+    /// focus
+    DescribeCanvas = 39,
+
+    /// Veto screenshots while any canvas of the sender's context is on screen
+    VetoScreenshots = 40,
+
+    /// Query whether the screen reader is on
+    ScreenReaderOn = 41,
 }
 
 // small wart -- we have to reset the size of a modal to max size for resize computations
@@ -12,7 +12,7 @@
 // As of now, the current version is 0.7.x and there isn't a timeline yet for 0.8.
 #![allow(dead_code)]
 
-use graphics_server::api::{Circle, Line, Rectangle, RoundedRectangle, TextView};
+use graphics_server::api::{Circle, EllipticArc, Line, Polygon, Rectangle, RoundedRectangle, TextView};
 
 use crate::*;
 
@@ -22,6 +22,10 @@ pub enum GamObjectType {
     Circ(Circle),
     Rect(Rectangle),
     RoundRect(RoundedRectangle),
+    /// a line drawn with the full `stroke_width` of its style, with rounded ends
+    ThickLine(Line),
+    Poly(Polygon),
+    Arc(EllipticArc),
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
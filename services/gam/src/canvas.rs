@@ -39,6 +39,8 @@ pub struct Canvas {
     clip_rect: Rectangle,
     // trust level, 255 is most trusted
     trust_level: u8,
+    // screenshots are refused while the canvas is on screen
+    screenshot_veto: bool,
     // enables scroll/pan of objects within a region
     pan_offset: Point,
     // track the drawing state of the canvas
@@ -69,6 +71,7 @@ impl Canvas {
             Canvas {
                 clip_rect,
                 trust_level,
+                screenshot_veto: false,
                 state: Rc::new(RefCell::new(CanvasState::OffScreenDrawable)),
                 gid: Gid::new(gid),
                 pan_offset: pan_offset.unwrap(),
@@ -78,6 +81,7 @@ impl Canvas {
             Canvas {
                 clip_rect,
                 trust_level,
+                screenshot_veto: false,
                 state: Rc::new(RefCell::new(CanvasState::OffScreenDrawable)),
                 gid: Gid::new(gid),
                 pan_offset: Point::new(0, 0),
@@ -108,6 +112,10 @@ impl Canvas {
 
     pub fn set_trust_level(&mut self, level: u8) { self.trust_level = level; }
 
+    pub fn screenshot_veto(&self) -> bool { self.screenshot_veto }
+
+    pub fn set_screenshot_veto(&mut self, veto: bool) { self.screenshot_veto = veto; }
+
     pub fn state(&self) -> CanvasState { *self.state.borrow() }
 
     pub fn is_onscreen(&self) -> bool {
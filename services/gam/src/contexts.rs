@@ -1,13 +1,14 @@
 use std::collections::HashMap;
 
 use enum_dispatch::enum_dispatch;
-use gam::MAIN_MENU_NAME;
+use gam::{MAIN_MENU_NAME, STATUS_BAR_NAME};
 use graphics_server::*;
 use ime_plugin_api::{ImeFrontEndApi, ImefDescriptor};
 use log::info;
 use xous_ipc::{Buffer, String};
 
 use crate::api::Opcode;
+use crate::screenreader::{ScreenReader, WPM_STEP};
 use crate::*;
 
 // todo:
@@ -15,6 +16,21 @@ use crate::*;
 //   reduce latency
 
 pub(crate) const MISC_CONTEXT_DEFAULT_TRUST: u8 = 127;
+/// F1 starts the GAM's shortcuts: they run when F1 and a second key are pressed together, or the second key
+/// is pressed within `CHORD_MS` of F1
+const CHORD_KEY: char = '\u{0011}';
+const CHORD_MS: u64 = 500;
+/// F1 then F4 takes a screenshot
+const SCREENSHOT_KEY: char = '\u{0014}';
+/// F1 then F2 says the last thing the screen reader said again
+const REPEAT_KEY: char = '\u{0012}';
+/// F1 then F3 turns the screen reader on and off
+const READER_KEY: char = '\u{0013}';
+/// F1 then up or down makes the screen reader speak faster or slower
+const FASTER_KEY: char = '↑';
+const SLOWER_KEY: char = '↓';
+/// F1 then left reads the status bar
+const STATUS_KEY: char = '←';
 
 #[derive(PartialEq, Eq)]
 pub(crate) enum LayoutBehavior {
@@ -119,6 +135,13 @@ pub(crate) struct ContextManager {
     tt: ticktimer_server::Ticktimer,
     /// used to suppress the main menu from activating until the boot PIN has been requested
     allow_mainmenu: bool,
+    /// connection and opcode of the server that takes screenshots on request
+    screenshot_listener: Option<(xous::CID, u32)>,
+    /// when the first key of a shortcut chord was last pressed
+    chord_started: Option<u64>,
+    reader: ScreenReader,
+    /// the status bar's canvas, which the screen reader can read on request
+    status_canvas: Option<Gid>,
 }
 impl ContextManager {
     pub fn new(xns: &xous_names::XousNames) -> Self {
@@ -142,6 +165,10 @@ impl ContextManager {
             trng: trng::Trng::new(&xns).expect("couldn't connect to trng"),
             tt: ticktimer_server::Ticktimer::new().unwrap(),
             allow_mainmenu: false,
+            screenshot_listener: None,
+            chord_started: None,
+            reader: ScreenReader::new(),
+            status_canvas: None,
         }
     }
 
@@ -556,6 +583,7 @@ impl ContextManager {
                 let last_token = context.app_token;
                 self.last_context = self.focused_context;
                 self.focused_context = Some(last_token);
+                self.read_focus(token);
             }
             log::trace!("context stack: {:x?}", self.context_stack);
             if self.context_stack.len() > 1 {
@@ -727,12 +755,144 @@ impl ContextManager {
 
     pub(crate) fn allow_mainmenu(&mut self) { self.allow_mainmenu = true; }
 
+    pub(crate) fn set_screenshot_listener(&mut self, registration: ScreenshotListener) {
+        if Some(registration.token) != self.find_app_token_by_name(STATUS_BAR_NAME) {
+            log::warn!("screenshot listener registration with a bad token, ignoring");
+            return;
+        }
+        if self.screenshot_listener.is_some() {
+            log::warn!("screenshot listener is already registered, ignoring");
+            return;
+        }
+        match xous::connect(xous::SID::from_array(registration.listener)) {
+            Ok(cid) => self.screenshot_listener = Some((cid, registration.opcode)),
+            Err(e) => log::error!("couldn't connect to the screenshot listener: {:?}", e),
+        }
+    }
+
+    /// True if `token` is the registered screenshot listener's
+    pub(crate) fn is_screenshot_listener(&self, token: [u32; 4]) -> bool {
+        self.screenshot_listener.is_some() && Some(token) == self.find_app_token_by_name(STATUS_BAR_NAME)
+    }
+
+    pub(crate) fn request_screenshot(&self) {
+        if let Some((cid, opcode)) = self.screenshot_listener {
+            xous::send_message(cid, xous::Message::new_scalar(opcode as usize, 0, 0, 0, 0)).ok();
+        } else {
+            log::warn!("screenshot requested, but nobody is listening for it");
+        }
+    }
+
+    /// Veto screenshots while any canvas of the context of `token` is on screen
+    pub(crate) fn veto_screenshots(&self, token: [u32; 4], canvases: &mut HashMap<Gid, Canvas>) {
+        if let Some(context) = self.contexts.get(&token) {
+            for gr in context.layout.get_gids().iter() {
+                if let Some(canvas) = canvases.get_mut(&gr.gid) {
+                    canvas.set_screenshot_veto(true);
+                }
+            }
+        } else {
+            log::warn!("screenshot veto with a bad token, ignoring");
+        }
+    }
+
+    /// True if a canvas on screen vetoes screenshots: a password box, at `BOOT_CONTEXT_TRUSTLEVEL - 1`, or
+    /// a canvas of a context that vetoed screenshots
+    pub(crate) fn screenshot_vetoed(&self, canvases: &HashMap<Gid, Canvas>) -> bool {
+        canvases.values().any(|canvas| {
+            canvas.is_onscreen()
+                && (canvas.screenshot_veto() || canvas.trust_level() == BOOT_CONTEXT_TRUSTLEVEL - 1)
+        })
+    }
+
+    /// Runs the shortcut for the first key in `keys` that completes a chord. Returns false if there is none.
+    fn run_shortcut(&mut self, keys: [char; 4]) -> bool {
+        for &key in keys.iter() {
+            match key {
+                SCREENSHOT_KEY => self.request_screenshot(),
+                // the screen reader's keys stay with the app in images that can't speak
+                _ if !ScreenReader::available() => continue,
+                REPEAT_KEY => self.reader.repeat(),
+                READER_KEY => self.reader.toggle(),
+                FASTER_KEY => self.reader.set_rate(WPM_STEP),
+                SLOWER_KEY => self.reader.set_rate(-WPM_STEP),
+                STATUS_KEY => {
+                    if let Some(text) = self.status_canvas.and_then(|gid| self.reader.description(gid)) {
+                        let text = text.to_string();
+                        self.reader.read(&text);
+                    }
+                }
+                _ => continue,
+            }
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn set_status_canvas(&mut self, gid: Gid) { self.status_canvas = Some(gid); }
+
+    /// Speaks the descriptions of the canvases of the context that just took focus, or its name if it
+    /// hasn't described them
+    fn read_focus(&mut self, token: [u32; 4]) {
+        let text = if let Some(context) = self.get_context_by_token(token) {
+            let descriptions: Vec<&str> =
+                context.layout.get_gids().iter().filter_map(|gr| self.reader.description(gr.gid)).collect();
+            if descriptions.is_empty() {
+                self.tm.lookup_name(&token).unwrap_or_default()
+            } else {
+                descriptions.join(". ")
+            }
+        } else {
+            return;
+        };
+        let now = self.tt.elapsed_ms();
+        self.reader.focus(&text, now);
+    }
+
+    /// Speaks `announcement` if it comes from the focused context
+    pub(crate) fn announce(&mut self, announcement: &Announcement) {
+        if self.focused_context != Some(announcement.token) {
+            log::debug!("announcement from a context without focus, ignoring");
+            return;
+        }
+        let now = self.tt.elapsed_ms();
+        self.reader.announce(announcement.text.to_str(), now);
+    }
+
+    /// Sets the description of a canvas. Descriptions of canvases that no longer exist are dropped on the
+    /// way, so the table doesn't outgrow the canvases.
+    pub(crate) fn describe_canvas(
+        &mut self,
+        description: &CanvasDescription,
+        canvases: &HashMap<Gid, Canvas>,
+    ) {
+        if !canvases.contains_key(&description.canvas) {
+            log::warn!("description for an unknown canvas, ignoring");
+            return;
+        }
+        self.reader.prune(|gid| canvases.contains_key(gid));
+        self.reader.describe(description.canvas, description.text.to_str());
+    }
+
+    pub(crate) fn screen_reader_on(&self) -> bool { self.reader.enabled() }
+
     pub(crate) fn key_event(
         &mut self,
         keys: [char; 4],
         gfx: &graphics_server::Gfx,
         canvases: &mut HashMap<Gid, Canvas>,
     ) {
+        // the key that completes a shortcut chord is eaten, so the focused app doesn't act on it
+        let now = self.tt.elapsed_ms();
+        let chord_started = self.chord_started.take();
+        if (keys.contains(&CHORD_KEY) || chord_started.map_or(false, |t| now.saturating_sub(t) < CHORD_MS))
+            && self.run_shortcut(keys)
+        {
+            return;
+        }
+        if keys.contains(&CHORD_KEY) {
+            self.chord_started = Some(now);
+        }
         // only pop up the menu if the primary key hit is the menu key (search just the first entry of keys);
         // reject multi-key hits only pop up the menu if it isn't already popped up
         if keys[0] == '∴' {
@@ -20,8 +20,10 @@ pub use graphics_server::api::PixelColor;
 #[cfg(feature = "ditherpunk")]
 pub use graphics_server::api::Tile;
 pub use graphics_server::api::{Circle, Gid, Line, RoundedRectangle, TokenClaim};
+pub use graphics_server::api::{DrawStyle, EllipticArc, FillPattern, Polygon, POLYGON_MAX_POINTS};
 pub use graphics_server::api::{Point, Rectangle};
-pub use graphics_server::api::{TextOp, TextView};
+pub use graphics_server::api::Screenshot;
+pub use graphics_server::api::{TextOp, TextSpan, TextView};
 use ime_plugin_api::{ApiToken, ImefCallback};
 use num_traits::*;
 use xous::{send_message, Message, CID};
@@ -221,6 +223,25 @@ impl Gam {
         buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_| ())
     }
 
+    /// Draw a line `line.style.stroke_width` pixels wide, up to 16, with rounded ends
+    pub fn draw_thick_line(&self, gid: Gid, line: Line) -> Result<(), xous::Error> {
+        let go = GamObject { canvas: gid, obj: GamObjectType::ThickLine(line) };
+        let buf = Buffer::into_buf(go).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_| ())
+    }
+
+    pub fn draw_polygon(&self, gid: Gid, poly: Polygon) -> Result<(), xous::Error> {
+        let go = GamObject { canvas: gid, obj: GamObjectType::Poly(poly) };
+        let buf = Buffer::into_buf(go).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_| ())
+    }
+
+    pub fn draw_arc(&self, gid: Gid, arc: EllipticArc) -> Result<(), xous::Error> {
+        let go = GamObject { canvas: gid, obj: GamObjectType::Arc(arc) };
+        let buf = Buffer::into_buf(go).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_| ())
+    }
+
     pub fn draw_list(&self, list: GamObjectList) -> Result<(), xous::Error> {
         let buf = Buffer::into_buf(list).or(Err(xous::Error::InternalError))?;
         buf.lend(self.conn, Opcode::RenderObjectList.to_u32().unwrap()).map(|_| ())
@@ -316,6 +337,88 @@ impl Gam {
         Ok(returned_claim.token)
     }
 
+    /// Copy the screen. Only the screenshot listener may do this, with its `token`.
+    ///
+    /// Fails with `AccessDenied` for any other token, or while a canvas that vetoes screenshots is on
+    /// screen: a password box, or a canvas of a context that called `veto_screenshots()`.
+    pub fn screenshot(&self, token: [u32; 4]) -> Result<Screenshot, xous::Error> {
+        let capture = ScreenshotCapture { token, shot: Screenshot::new() };
+        let mut buf = Buffer::into_buf(capture).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::Screenshot.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let capture = buf.to_original::<ScreenshotCapture, _>().unwrap();
+        if capture.shot.captured { Ok(capture.shot) } else { Err(xous::Error::AccessDenied) }
+    }
+
+    /// Refuse screenshots while any canvas of the context of `token` (its app token) is on screen. For a
+    /// context that shows secrets, such as the shared modal.
+    pub fn veto_screenshots(&self, token: [u32; 4]) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(
+                Opcode::VetoScreenshots.to_usize().unwrap(),
+                token[0] as usize,
+                token[1] as usize,
+                token[2] as usize,
+                token[3] as usize,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// Ask for a screenshot to be taken and saved, as the screenshot key chord does
+    pub fn request_screenshot(&self) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::ScreenshotRequest.to_usize().unwrap(), 0, 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Register the server that takes and saves screenshots on request. Only the status bar may do this.
+    pub fn register_screenshot_listener(
+        &self,
+        token: [u32; 4],
+        sid: xous::SID,
+        opcode: u32,
+    ) -> Result<(), xous::Error> {
+        let registration = ScreenshotListener { token, listener: sid.to_array(), opcode };
+        let buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::ScreenshotListener.to_u32().unwrap())
+            .or(Err(xous::Error::InternalError))
+            .map(|_| ())
+    }
+
+    /// Speak `text` through the screen reader, if the screen reader is on and the context of `token` (its
+    /// app token) has focus. Text longer than `SCREEN_READER_TEXT_LEN` bytes is cut short.
+    pub fn announce(&self, token: [u32; 4], text: &str) -> Result<(), xous::Error> {
+        let announcement = Announcement { token, text: String::from_str(screen_reader_text(text)) };
+        let buf = Buffer::into_buf(announcement).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::Announce.to_u32().unwrap()).map(|_| ())
+    }
+
+    /// Attach a text description to a canvas, for the screen reader to read when the canvas' context takes
+    /// focus; modals describe their canvas with their prompt, for example. An empty `text` clears the
+    /// description.
+    pub fn describe_canvas(&self, gid: Gid, text: &str) -> Result<(), xous::Error> {
+        let description = CanvasDescription { canvas: gid, text: String::from_str(screen_reader_text(text)) };
+        let buf = Buffer::into_buf(description).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DescribeCanvas.to_u32().unwrap()).map(|_| ())
+    }
+
+    /// True if the screen reader is on. Apps that keep a changing canvas description, such as the status
+    /// bar's clock, can check this to skip the updates while nothing reads them.
+    pub fn screen_reader_on(&self) -> Result<bool, xous::Error> {
+        let response = send_message(
+            self.conn,
+            Message::new_blocking_scalar(Opcode::ScreenReaderOn.to_usize().unwrap(), 0, 0, 0, 0),
+        )?;
+        if let xous::Result::Scalar1(on) = response {
+            if on != 0 { Ok(true) } else { Ok(false) }
+        } else {
+            panic!("GAM_API: unexpected return value: {:#?}", response);
+        }
+    }
+
     #[cfg(feature = "unsafe-app-loading")]
     pub fn register_name(&self, name: &str, auth_token: [u32; 4]) -> Result<(), xous::Error> {
         let name_registration = NameRegistration { name: String::<128>::from_str(name), auth_token };
@@ -554,6 +657,15 @@ impl Gam {
     }
 }
 
+/// The longest start of `text` that fits in a screen reader message, cut between characters
+fn screen_reader_text(text: &str) -> &str {
+    let mut end = text.len().min(SCREEN_READER_TEXT_LEN);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
 use core::sync::atomic::{AtomicU32, Ordering};
 static REFCOUNT: AtomicU32 = AtomicU32::new(0);
 impl Drop for Gam {
@@ -13,6 +13,7 @@ use layouts::*;
 mod contexts;
 use contexts::*;
 mod bip39;
+mod screenreader;
 
 use core::sync::atomic::{AtomicU32, Ordering};
 use std::collections::HashMap;
@@ -36,6 +37,14 @@ fn imef_cb(s: String<4000>) {
         buf.lend(cb_to_main_conn, Opcode::InputLine.to_u32().unwrap()).unwrap();
     }
 }
+
+/// Draw the inverted spans of a TextView as plain text
+fn strip_span_invert(tv: &mut TextView) {
+    for span in tv.spans.iter_mut().flatten() {
+        span.invert = false;
+    }
+}
+
 fn main() -> ! {
     #[cfg(not(feature = "ditherpunk"))]
     wrapped_main();
@@ -87,6 +96,7 @@ fn wrapped_main() -> ! {
     status_canvas.set_onscreen(true);
     status_canvas.set_drawable(true);
     let status_gid = status_canvas.gid().gid();
+    context_mgr.set_status_canvas(status_canvas.gid());
     canvases.insert(status_canvas.gid(), status_canvas);
     recompute_canvases(&canvases);
 
@@ -247,6 +257,16 @@ fn wrapped_main() -> ! {
                                 continue;
                             }
                         }
+                        // inverted spans are held to the same checks as inverted text, but are stripped
+                        // rather than aborting the render
+                        let mut span_invert = tv.spans().any(|span| span.invert);
+                        if span_invert && tv.token.map_or(false, |token| !context_mgr.is_token_valid(token)) {
+                            log::error!(
+                                "Attempt to draw inverted spans without valid credentials. Stripping."
+                            );
+                            strip_span_invert(&mut tv);
+                            span_invert = false;
+                        }
 
                         log::trace!("render request for {:?}", tv);
 
@@ -262,6 +282,13 @@ fn wrapped_main() -> ! {
                                 );
                                 continue;
                             }
+                            if span_invert & (canvas.trust_level() < BOOT_CONTEXT_TRUSTLEVEL - 1) {
+                                log::error!(
+                                    "Attempt to draw inverted spans without sufficient trust level: {}. Stripping.",
+                                    canvas.trust_level()
+                                );
+                                strip_span_invert(&mut tv);
+                            }
                             // first, figure out if we should even be drawing to this canvas.
                             if canvas.is_drawable() {
                                 // set the clip rectangle according to the canvas' location
@@ -457,6 +484,23 @@ fn wrapped_main() -> ! {
                                 gfx.draw_rounded_rectangle_clipped(rr, canvas.clip_rect())
                                     .expect("couldn't draw rounded rectangle");
                             }
+                            GamObjectType::ThickLine(mut line) => {
+                                line.translate(canvas.clip_rect().tl);
+                                line.translate(canvas.pan_offset());
+                                gfx.draw_thick_line_clipped(line, canvas.clip_rect())
+                                    .expect("couldn't draw thick line");
+                            }
+                            GamObjectType::Poly(mut poly) => {
+                                poly.translate(canvas.clip_rect().tl);
+                                poly.translate(canvas.pan_offset());
+                                gfx.draw_polygon_clipped(poly, canvas.clip_rect())
+                                    .expect("couldn't draw polygon");
+                            }
+                            GamObjectType::Arc(mut arc) => {
+                                arc.translate(canvas.clip_rect().tl);
+                                arc.translate(canvas.pan_offset());
+                                gfx.draw_arc_clipped(arc, canvas.clip_rect()).expect("couldn't draw arc");
+                            }
                         }
                         canvas.do_drawn().expect("couldn't set canvas to drawn");
                     } else {
@@ -508,6 +552,25 @@ fn wrapped_main() -> ! {
                                             .push(ClipObjectType::RoundRect(rr), canvas.clip_rect())
                                             .unwrap();
                                     }
+                                    GamObjectType::ThickLine(mut line) => {
+                                        line.translate(canvas.clip_rect().tl);
+                                        line.translate(canvas.pan_offset());
+                                        obj_list
+                                            .push(ClipObjectType::ThickLine(line), canvas.clip_rect())
+                                            .unwrap();
+                                    }
+                                    GamObjectType::Poly(mut poly) => {
+                                        poly.translate(canvas.clip_rect().tl);
+                                        poly.translate(canvas.pan_offset());
+                                        obj_list
+                                            .push(ClipObjectType::Poly(poly), canvas.clip_rect())
+                                            .unwrap();
+                                    }
+                                    GamObjectType::Arc(mut arc) => {
+                                        arc.translate(canvas.clip_rect().tl);
+                                        arc.translate(canvas.pan_offset());
+                                        obj_list.push(ClipObjectType::Arc(arc), canvas.clip_rect()).unwrap();
+                                    }
                                 }
                             } else {
                                 break;
@@ -790,6 +853,47 @@ fn wrapped_main() -> ! {
                 gfx.set_devboot(true).ok(); // indicate to users that we are no longer in a codebase that is exclusively trusted code
                 context_mgr.register_name(registration.name.to_str(), &registration.auth_token);
             }
+            Some(Opcode::Screenshot) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let token = buffer.to_original::<ScreenshotCapture, _>().unwrap().token;
+                let shot = if !context_mgr.is_screenshot_listener(token) {
+                    log::warn!("screenshot with a bad token, refusing");
+                    Screenshot::new()
+                } else if context_mgr.screenshot_vetoed(&canvases) {
+                    log::info!("screenshot vetoed by a secure canvas on screen");
+                    Screenshot::new()
+                } else {
+                    gfx.screenshot().unwrap_or_else(|e| {
+                        log::error!("couldn't take a screenshot: {:?}", e);
+                        Screenshot::new()
+                    })
+                };
+                buffer.replace(ScreenshotCapture { token, shot }).unwrap();
+            }
+            Some(Opcode::ScreenshotRequest) => context_mgr.request_screenshot(),
+            Some(Opcode::ScreenshotListener) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let registration = buffer.to_original::<ScreenshotListener, _>().unwrap();
+                context_mgr.set_screenshot_listener(registration);
+            }
+            Some(Opcode::Announce) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let announcement = buffer.to_original::<Announcement, _>().unwrap();
+                context_mgr.announce(&announcement);
+            }
+            Some(Opcode::DescribeCanvas) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let description = buffer.to_original::<CanvasDescription, _>().unwrap();
+                context_mgr.describe_canvas(&description, &canvases);
+            }
+            Some(Opcode::VetoScreenshots) => msg_scalar_unpack!(msg, t1, t2, t3, t4, {
+                let token = [t1 as u32, t2 as u32, t3 as u32, t4 as u32];
+                context_mgr.veto_screenshots(token, &mut canvases);
+            }),
+            Some(Opcode::ScreenReaderOn) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                xous::return_scalar(msg.sender, if context_mgr.screen_reader_on() { 1 } else { 0 }).unwrap();
+            }),
             Some(Opcode::Quit) => break,
             None => {
                 log::error!("unhandled message {:?}", msg);
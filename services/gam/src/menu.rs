@@ -3,8 +3,6 @@
 pub use graphics_server::api::{DrawStyle, PixelColor, TextBounds};
 pub use graphics_server::*;
 use num_traits::*;
-#[cfg(feature = "tts")]
-use tts_frontend::TtsFrontend;
 use xous_ipc::{Buffer, String};
 
 use crate::api::*;
@@ -25,8 +23,6 @@ pub struct Menu<'a> {
     pub canvas_width: Option<i16>,
     pub helper_data: Option<Buffer<'a>>,
     pub name: std::string::String,
-    #[cfg(feature = "tts")]
-    pub tts: TtsFrontend,
 }
 
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
@@ -65,8 +61,6 @@ impl<'a> Menu<'a> {
             gam.request_content_canvas(authtoken.unwrap()).expect("couldn't get my content canvas from GAM");
         let line_height =
             gam.glyph_height_hint(crate::SYSTEM_STYLE).expect("couldn't get glyph height hint") as i16 + 2;
-        #[cfg(feature = "tts")]
-        let tts = TtsFrontend::new(&xns).unwrap();
         Menu {
             sid,
             gam,
@@ -81,8 +75,6 @@ impl<'a> Menu<'a> {
             canvas_width: None,
             helper_data: None,
             name: std::string::String::from(name),
-            #[cfg(feature = "tts")]
-            tts,
         }
     }
 
@@ -219,8 +211,7 @@ impl<'a> Menu<'a> {
 
         if with_marker {
             write!(item_tv.text, "\u{25B6}").unwrap();
-            #[cfg(feature = "tts")]
-            self.tts.tts_simple(item.name.as_str().unwrap()).unwrap();
+            self.gam.announce(self.authtoken, item.name.as_str().unwrap()).ok();
         } else {
             write!(item_tv.text, "\t").unwrap();
         }
@@ -358,6 +349,13 @@ impl<'a> Menu<'a> {
             match k {
                 '∴' => {
                     let mi = self.items[self.index];
+                    // announce while the menu still has focus: the GAM ignores announcements from
+                    // contexts that are not on top
+                    if mi.action_conn.is_some() {
+                        self.gam
+                            .announce(self.authtoken, &format!("select {}", mi.name.as_str().unwrap()))
+                            .ok();
+                    }
                     // give up focus before issuing the command, as some commands conflict with loss of
                     // focus...
                     if mi.close_on_select {
@@ -368,12 +366,6 @@ impl<'a> Menu<'a> {
                     }
                     if let Some(action) = mi.action_conn {
                         log::debug!("doing menu action for {}", mi.name);
-                        #[cfg(feature = "tts")]
-                        {
-                            let mut phrase = "select ".to_string();
-                            phrase.push_str(mi.name.as_str().unwrap());
-                            self.tts.tts_blocking(&phrase).unwrap();
-                        }
                         match mi.action_payload {
                             MenuPayload::Scalar(args) => {
                                 xous::send_message(
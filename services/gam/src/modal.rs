@@ -56,6 +56,15 @@ pub trait ActionApi {
     /// navigation is one of '∴' | '←' | '→' | '↑' | '↓'
     fn key_action(&mut self, _key: char) -> Option<ValidatorErr> { None }
     fn set_action_opcode(&mut self, _op: u32) {}
+    /// What the screen reader should say about the last `key_action`, such as the key typed. The Modal
+    /// announces it, as the action has no app token of its own.
+    fn take_announcement(&mut self) -> Option<std::string::String> { None }
+}
+
+/// What the screen reader says for a key typed into a password: that a character was typed, and how many
+/// there are now, but not which one
+pub(crate) fn password_announcement(count: usize) -> std::string::String {
+    locales::t!("screenreader.character", locales::LANG).replace("{count}", &count.to_string())
 }
 
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
@@ -265,6 +274,8 @@ fn recompute_canvas(modal: &mut Modal, top_text: Option<&str>, bot_text: Option<
         }
         modal.top_text = Some(top_tv);
     }
+    // the prompt is what the screen reader says when the modal comes up
+    modal.gam.describe_canvas(modal.canvas, top_text.unwrap_or("")).ok();
     total_height += modal.margin;
 
     // compute height of action item
@@ -551,6 +562,9 @@ impl<'a> Modal<'a> {
             if k != '\u{0}' {
                 log::debug!("got key '{}'", k);
                 let err = self.action.key_action(k);
+                if let Some(text) = self.action.take_announcement() {
+                    self.gam.announce(self.authtoken, &text).ok();
+                }
                 if let Some(err_msg) = err {
                     self.modify(None, None, false, Some(err_msg.to_str()), false, None);
                 }
@@ -21,6 +21,8 @@ pub struct Bip39Entry {
     line_height: Cell<i16>,
     margin: Cell<i16>,
     gam: crate::Gam,
+    /// what the screen reader should say about the last key
+    announcement: Option<String>,
 }
 
 impl Default for Bip39Entry {
@@ -44,6 +46,7 @@ impl Default for Bip39Entry {
             line_height: Cell::new(0),
             margin: Cell::new(0),
             gam,
+            announcement: None,
         }
     }
 }
@@ -65,6 +68,8 @@ impl ActionApi for Bip39Entry {
 
     fn is_password(&self) -> bool { self.is_password }
 
+    fn take_announcement(&mut self) -> Option<String> { self.announcement.take() }
+
     /// The total canvas height is computed with this API call
     /// The canvas height is not dynamically adjustable for modals.
     fn height(&self, glyph_height: i16, margin: i16, _modal: &Modal) -> i16 {
@@ -388,12 +393,7 @@ impl ActionApi for Bip39Entry {
             }
             '\u{8}' => {
                 // backspace
-                #[cfg(feature = "tts")]
-                {
-                    let xns = xous_names::XousNames::new().unwrap();
-                    let tts = tts_frontend::TtsFrontend::new(&xns).unwrap();
-                    tts.tts_blocking(locales::t!("input.delete-tts", locales::LANG)).unwrap();
-                }
+                self.announcement = Some(t!("input.delete-tts", locales::LANG).to_string());
                 if self.user_input.len() > 0 {
                     // don't backspace if we have no string.
                     self.user_input.pop();
@@ -415,12 +415,9 @@ impl ActionApi for Bip39Entry {
                 }
             }
             _ => {
-                // text entry
-                #[cfg(feature = "tts")]
-                {
-                    let xns = xous_names::XousNames::new().unwrap();
-                    let tts = tts_frontend::TtsFrontend::new(&xns).unwrap();
-                    tts.tts_blocking(&k.to_string()).unwrap();
+                // text entry; the words of a secret are not spoken aloud, only counted
+                if !self.is_password {
+                    self.announcement = Some(k.to_string());
                 }
                 if k.is_ascii_alphabetic() {
                     // ignore any other input, since it's invalid.
@@ -432,6 +429,10 @@ impl ActionApi for Bip39Entry {
                     self.suggested_words =
                         self.gam.bip39_suggestions(&self.user_input).unwrap_or(Vec::<String>::new());
                     self.suggestion_index.set(0);
+                    if self.is_password {
+                        let count = self.user_input.chars().count();
+                        self.announcement = Some(crate::modal::password_announcement(count));
+                    }
                 }
             }
         }
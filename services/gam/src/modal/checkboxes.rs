@@ -2,8 +2,6 @@ use core::fmt::Write;
 
 use graphics_server::api::*;
 use locales::t;
-#[cfg(feature = "tts")]
-use tts_frontend::TtsFrontend;
 use xous_ipc::Buffer;
 
 use crate::*;
@@ -16,13 +14,9 @@ pub struct CheckBoxes {
     pub action_payload: CheckBoxPayload,
     pub select_index: i16,
     gam: crate::Gam,
-    #[cfg(feature = "tts")]
-    pub tts: TtsFrontend,
 }
 impl CheckBoxes {
     pub fn new(action_conn: xous::CID, action_opcode: u32) -> Self {
-        #[cfg(feature = "tts")]
-        let tts = TtsFrontend::new(&xous_names::XousNames::new().unwrap()).unwrap();
         CheckBoxes {
             items: Vec::new(),
             action_conn,
@@ -30,8 +24,6 @@ impl CheckBoxes {
             action_payload: CheckBoxPayload::new(),
             select_index: 0,
             gam: crate::Gam::new(&xous_names::XousNames::new().unwrap()).unwrap(),
-            #[cfg(feature = "tts")]
-            tts,
         }
     }
 
@@ -77,10 +69,13 @@ impl ActionApi for CheckBoxes {
             let item_line_count: i16 = 1 + item.as_str().chars().filter(|c| *c == '\n').count() as i16;
             cur_line_height = modal.line_height * item_line_count + item_line_count; // extra pixel between lines
             if cur_line == self.select_index {
-                #[cfg(feature = "tts")]
-                {
-                    self.tts.tts_simple(item.as_str()).unwrap();
-                }
+                // the screen reader says the item under the cursor, and whether it is checked
+                let state = if self.action_payload.contains(item.as_str()) {
+                    t!("checkbox.check", locales::LANG)
+                } else {
+                    t!("checkbox.uncheck", locales::LANG)
+                };
+                modal.gam.announce(modal.authtoken, &format!("{}{}", state, item.as_str())).ok();
                 // draw the cursor
                 tv.text.clear();
                 tv.bounds_computed = None;
@@ -127,15 +122,16 @@ impl ActionApi for CheckBoxes {
             ));
             write!(tv, "\u{25B6}").unwrap(); // right arrow emoji. use unicode numbers, because text editors do funny shit with emojis
             modal.gam.post_textview(&mut tv).expect("couldn't post tv");
-            #[cfg(feature = "tts")]
-            {
-                self.tts.tts_blocking(t!("checkbox.select_and_close_tts", locales::LANG)).unwrap();
-                for item in self.action_payload.payload().iter() {
-                    if let Some(name) = item {
-                        self.tts.tts_blocking(name.as_str()).unwrap();
-                    }
-                }
-            }
+            let payload = self.action_payload.payload();
+            let checked: Vec<&str> =
+                payload.iter().filter_map(|item| item.as_ref().map(|name| name.as_str())).collect();
+            modal
+                .gam
+                .announce(
+                    modal.authtoken,
+                    &format!("{}{}", t!("checkbox.select_and_close_tts", locales::LANG), checked.join(", ")),
+                )
+                .ok();
         }
         // draw the "OK" line
         tv.text.clear();
@@ -181,13 +177,9 @@ impl ActionApi for CheckBoxes {
             '∴' | '\u{d}' => {
                 if (self.select_index as usize) < self.items.len() {
                     let item_name = self.items[self.select_index as usize].as_str();
+                    // the redraw that follows says whether the item is now checked
                     if self.action_payload.contains(item_name) {
                         self.action_payload.remove(item_name);
-                        #[cfg(feature = "tts")]
-                        {
-                            self.tts.tts_blocking(t!("checkbox.uncheck", locales::LANG)).unwrap();
-                            self.tts.tts_blocking(item_name).unwrap();
-                        }
                     } else {
                         if !self.action_payload.add(item_name) {
                             log::warn!(
@@ -195,12 +187,6 @@ impl ActionApi for CheckBoxes {
                                 MAX_ITEMS
                             );
                             log::warn!("The attempted item '{}' was not selected.", item_name);
-                        } else {
-                            #[cfg(feature = "tts")]
-                            {
-                                self.tts.tts_blocking(t!("checkbox.check", locales::LANG)).unwrap();
-                                self.tts.tts_blocking(item_name).unwrap();
-                            }
                         }
                     }
                 } else {
@@ -2,8 +2,6 @@ use core::fmt::Write;
 
 use graphics_server::api::*;
 use locales::t;
-#[cfg(feature = "tts")]
-use tts_frontend::TtsFrontend;
 use xous_ipc::Buffer;
 
 use crate::*;
@@ -17,13 +15,9 @@ pub struct RadioButtons {
     pub select_index: i16,                  // the current candidate to be selected
     pub is_password: bool,
     gam: crate::Gam,
-    #[cfg(feature = "tts")]
-    pub tts: TtsFrontend,
 }
 impl RadioButtons {
     pub fn new(action_conn: xous::CID, action_opcode: u32) -> Self {
-        #[cfg(feature = "tts")]
-        let tts = TtsFrontend::new(&xous_names::XousNames::new().unwrap()).unwrap();
         RadioButtons {
             items: Vec::new(),
             action_conn,
@@ -32,8 +26,6 @@ impl RadioButtons {
             select_index: 0,
             is_password: false,
             gam: crate::Gam::new(&xous_names::XousNames::new().unwrap()).unwrap(),
-            #[cfg(feature = "tts")]
-            tts,
         }
     }
 
@@ -83,9 +75,12 @@ impl ActionApi for RadioButtons {
         for item in self.items.iter() {
             let cur_y = at_height + cur_line * modal.line_height + modal.margin * 2;
             if cur_line == self.select_index {
-                #[cfg(feature = "tts")]
-                {
-                    self.tts.tts_simple(item.as_str()).unwrap();
+                // the screen reader says the item under the cursor, and if it is the selection
+                if item.as_str() == self.action_payload.as_str() {
+                    let text = format!("{}{}", t!("radio.selected_tts", locales::LANG), item.as_str());
+                    modal.gam.announce(modal.authtoken, &text).ok();
+                } else {
+                    modal.gam.announce(modal.authtoken, item.as_str()).ok();
                 }
                 // draw the cursor
                 tv.text.clear();
@@ -132,11 +127,12 @@ impl ActionApi for RadioButtons {
             ));
             write!(tv, "\u{25B6}").unwrap(); // right arrow emoji. use unicode numbers, because text editors do funny shit with emojis
             modal.gam.post_textview(&mut tv).expect("couldn't post tv");
-            #[cfg(feature = "tts")]
-            {
-                self.tts.tts_blocking(t!("radio.select_and_close_tts", locales::LANG)).unwrap();
-                self.tts.tts_blocking(self.action_payload.as_str()).unwrap();
-            }
+            let text = format!(
+                "{}{}",
+                t!("radio.select_and_close_tts", locales::LANG),
+                self.action_payload.as_str()
+            );
+            modal.gam.announce(modal.authtoken, &text).ok();
         }
         // draw the "OK" line
         tv.text.clear();
@@ -181,13 +177,9 @@ impl ActionApi for RadioButtons {
             }
             '∴' | '\u{d}' => {
                 if self.select_index < self.items.len() as i16 {
+                    // the redraw that follows announces the new selection
                     self.action_payload =
                         RadioButtonPayload::new(self.items[self.select_index as usize].as_str());
-                    #[cfg(feature = "tts")]
-                    {
-                        self.tts.tts_blocking(t!("radio.selection_tts", locales::LANG)).unwrap();
-                        self.tts.tts_simple(self.items[self.select_index as usize].as_str()).unwrap();
-                    }
                 } else {
                     // the OK button select
                     // relinquish focus before returning the result
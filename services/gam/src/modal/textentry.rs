@@ -58,6 +58,8 @@ pub struct TextEntry {
     /// in the box. The height of a given line may be limited to make sure there is enough space for
     /// later lines to be rendered.
     action_payloads_allowed_heights: RefCell<Vec<i16>>,
+    /// what the screen reader should say about the last key
+    announcement: Option<std::string::String>,
 }
 
 impl Default for TextEntry {
@@ -74,6 +76,7 @@ impl Default for TextEntry {
             field_height: Cell::new(0),
             keys_hit: [false; MAX_FIELDS as usize],
             action_payloads_allowed_heights: RefCell::new(Vec::new()),
+            announcement: None,
         }
     }
 }
@@ -141,6 +144,8 @@ impl ActionApi for TextEntry {
 
     fn is_password(&self) -> bool { self.is_password }
 
+    fn take_announcement(&mut self) -> Option<std::string::String> { self.announcement.take() }
+
     /// The total canvas height is computed with this API call
     /// The canvas height is not dynamically adjustable for modals.
     fn height(&self, glyph_height: i16, margin: i16, modal: &Modal) -> i16 {
@@ -682,12 +687,7 @@ impl ActionApi for TextEntry {
             '\u{8}' => {
                 // backspace
                 self.keys_hit[self.selected_field as usize] = true;
-                #[cfg(feature = "tts")]
-                {
-                    let xns = xous_names::XousNames::new().unwrap();
-                    let tts = tts_frontend::TtsFrontend::new(&xns).unwrap();
-                    tts.tts_blocking(locales::t!("input.delete-tts", locales::LANG)).unwrap();
-                }
+                self.announcement = Some(locales::t!("input.delete-tts", locales::LANG).to_string());
                 if payload.placeholder_persist && payload.placeholder.is_some() && payload.content.len() == 0
                 {
                     // copy the placeholder into the content string before processing the backspace
@@ -759,11 +759,9 @@ impl ActionApi for TextEntry {
                     payload.content.append(payload.placeholder.unwrap().to_str()).ok();
                 }
                 self.keys_hit[self.selected_field as usize] = true;
-                #[cfg(feature = "tts")]
-                {
-                    let xns = xous_names::XousNames::new().unwrap();
-                    let tts = tts_frontend::TtsFrontend::new(&xns).unwrap();
-                    tts.tts_blocking(&k.to_string()).unwrap();
+                // a password is not spoken aloud, only counted
+                if !self.is_password {
+                    self.announcement = Some(k.to_string());
                 }
                 match k {
                     '\u{f701}' | '\u{f700}' => (),
@@ -793,6 +791,10 @@ impl ActionApi for TextEntry {
                         }
                         log::trace!("****update payload: {}", payload.content);
                         payload.dirty = true;
+                        if self.is_password {
+                            let count = payload.content.as_str().map_or(0, |s| s.chars().count());
+                            self.announcement = Some(crate::modal::password_announcement(count));
+                        }
                     }
                 }
             }
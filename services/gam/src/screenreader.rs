@@ -0,0 +1,153 @@
+//! The screen reader speaks what has focus through the `tts` service: the canvas descriptions (or the name)
+//! of a context when it takes focus, and the announcements the focused context makes, such as the selected
+//! menu item. Speech is only available in images built with the `tts` feature; elsewhere the reader is off
+//! and can't be turned on.
+
+use std::collections::HashMap;
+
+use graphics_server::Gid;
+use locales::t;
+#[cfg(feature = "tts")]
+use tts_frontend::TtsFrontend;
+
+/// Speech rate of the tts service on boot
+const DEFAULT_WPM: u32 = 350;
+const MIN_WPM: u32 = 150;
+const MAX_WPM: u32 = 650;
+/// How much one press of the rate chord changes the speech rate
+pub(crate) const WPM_STEP: i32 = 25;
+/// An announcement made within this long of a focus change is spoken after the focus text, instead of
+/// cutting it off
+const FOLLOW_UP_MS: u64 = 1000;
+
+pub(crate) struct ScreenReader {
+    /// connected on first use, so the GAM doesn't wait on the tts service at boot
+    #[cfg(feature = "tts")]
+    tts: Option<TtsFrontend>,
+    enabled: bool,
+    wpm: u32,
+    /// the last thing spoken, for re-reading
+    last: String,
+    /// what the last focus change said, and when, until an announcement picks it up
+    focus: Option<(String, u64)>,
+    /// text descriptions apps attach to their canvases
+    descriptions: HashMap<Gid, String>,
+}
+impl ScreenReader {
+    pub(crate) fn new() -> Self {
+        ScreenReader {
+            #[cfg(feature = "tts")]
+            tts: None,
+            enabled: Self::available(),
+            wpm: DEFAULT_WPM,
+            last: String::new(),
+            focus: None,
+            descriptions: HashMap::new(),
+        }
+    }
+
+    /// True if this image can speak at all
+    pub(crate) fn available() -> bool { cfg!(feature = "tts") }
+
+    pub(crate) fn enabled(&self) -> bool { self.enabled }
+
+    pub(crate) fn toggle(&mut self) {
+        if !Self::available() {
+            return;
+        }
+        // the change is spoken either way, so the user knows which way it went
+        self.enabled = !self.enabled;
+        if self.enabled {
+            self.say(t!("screenreader.on", locales::LANG));
+        } else {
+            self.say(t!("screenreader.off", locales::LANG));
+        }
+    }
+
+    /// Changes the speech rate by `delta` words per minute, and says the new rate
+    pub(crate) fn set_rate(&mut self, delta: i32) {
+        if !self.enabled {
+            return;
+        }
+        self.wpm = (self.wpm as i32 + delta).clamp(MIN_WPM as i32, MAX_WPM as i32) as u32;
+        #[cfg(feature = "tts")]
+        if let Some(tts) = &self.tts {
+            tts.set_words_per_minute(self.wpm).ok();
+        }
+        self.say(&t!("screenreader.rate", locales::LANG).replace("{wpm}", &self.wpm.to_string()));
+    }
+
+    /// Says the last thing spoken again
+    pub(crate) fn repeat(&mut self) {
+        if self.enabled && !self.last.is_empty() {
+            let last = self.last.clone();
+            self.say(&last);
+        }
+    }
+
+    /// Speaks `text`, if the screen reader is on
+    pub(crate) fn read(&mut self, text: &str) {
+        if self.enabled && !text.is_empty() {
+            self.say(text);
+        }
+    }
+
+    /// Speaks `text` for a context that just took focus. `now` is the ticktimer time in ms.
+    pub(crate) fn focus(&mut self, text: &str, now: u64) {
+        if self.enabled {
+            self.say(text);
+            self.focus = Some((text.to_string(), now));
+        }
+    }
+
+    /// Speaks an announcement from the focused context. `now` is the ticktimer time in ms.
+    pub(crate) fn announce(&mut self, text: &str, now: u64) {
+        if !self.enabled || text.is_empty() {
+            return;
+        }
+        // speech is cut off by whatever is said next, and a context usually announces its selection as soon
+        // as it draws, so keep the focus text in front of it
+        match self.focus.take() {
+            Some((focus, at)) if now.saturating_sub(at) < FOLLOW_UP_MS => {
+                self.say(&format!("{}. {}", focus, text))
+            }
+            _ => self.say(text),
+        }
+    }
+
+    /// Sets the description of `gid`; an empty `text` removes it
+    pub(crate) fn describe(&mut self, gid: Gid, text: &str) {
+        if text.is_empty() {
+            self.descriptions.remove(&gid);
+        } else {
+            self.descriptions.insert(gid, text.to_string());
+        }
+    }
+
+    /// Drops the descriptions of canvases for which `exists` is false
+    pub(crate) fn prune(&mut self, exists: impl Fn(&Gid) -> bool) {
+        self.descriptions.retain(|gid, _| exists(gid));
+    }
+
+    pub(crate) fn description(&self, gid: Gid) -> Option<&str> {
+        self.descriptions.get(&gid).map(|d| d.as_str())
+    }
+
+    fn say(&mut self, text: &str) {
+        self.last = text.to_string();
+        #[cfg(feature = "tts")]
+        {
+            if self.tts.is_none() {
+                let xns = xous_names::XousNames::new().unwrap();
+                let tts = TtsFrontend::new(&xns).expect("couldn't connect to the tts service");
+                if self.wpm != DEFAULT_WPM {
+                    tts.set_words_per_minute(self.wpm).ok();
+                }
+                self.tts = Some(tts);
+            }
+            if let Some(tts) = &self.tts {
+                tts.tts_simple(text).ok();
+            }
+        }
+    }
+}
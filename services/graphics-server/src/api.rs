@@ -26,6 +26,8 @@ pub mod glyphstyle;
 pub use glyphstyle::*;
 pub mod blitstr2;
 pub use blitstr2::*;
+pub mod screenshot;
+pub use screenshot::*;
 #[cfg(feature = "ditherpunk")]
 pub mod tile;
 use std::hash::{Hash, Hasher};
@@ -120,6 +122,14 @@ pub(crate) enum Opcode {
     /// draw the boot logo (for continuity as apps initialize)
     DrawBootLogo,
 
+    /// copy the framebuffer into a Screenshot. Only the GAM connects to the graphics server, and it
+    /// decides whether a screenshot may be taken.
+    Screenshot,
+
+    /// write the framebuffer to a PNG file (hosted mode only)
+    #[cfg(not(target_os = "xous"))]
+    Snapshot,
+
     Quit,
 }
 
@@ -132,6 +142,10 @@ pub enum ClipObjectType {
     XorLine(Line),
     #[cfg(feature = "ditherpunk")]
     Tile(Tile),
+    /// a line drawn with the full `stroke_width` of its style, with rounded ends
+    ThickLine(Line),
+    Poly(Polygon),
+    Arc(EllipticArc),
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
@@ -142,9 +156,9 @@ pub struct ClipObject {
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub struct ClipObjectList {
-    // ClipObject is 28 bytes, so 32 of these takes 896 bytes, which is less than a 4k page (the minimum
-    // amount that gets remapped) we limit the length to 32 so we can use the Default initializer to set
-    // the None's on the array, otherwise it gets a bit painful.
+    // ClipObject is 28 bytes for the basic shapes and about 90 bytes for a Polygon, so 32 of these takes
+    // less than a 4k page (the minimum amount that gets remapped) we limit the length to 32 so we can use
+    // the Default initializer to set the None's on the array, otherwise it gets a bit painful.
     pub list: [Option<ClipObject>; 32],
     free: usize,
 }
@@ -32,6 +32,10 @@ pub struct GlyphSprite {
     pub double: bool,
     // flag for 32-bit wide glyph sets
     pub large: bool,
+    // draw a line under the character
+    pub underline: bool,
+    // the character is in right-to-left text, so an insertion point before it is drawn on its right
+    pub rtl: bool,
 }
 
 /// A TypesetWord is a Word that has beet turned into sprites and placed at a specific location on the canvas,
@@ -53,4 +57,7 @@ pub(crate) struct TypesetWord {
     pub non_drawable: bool,
     /// the position in the originating abstract string of the first character in the word
     pub strpos: usize,
+    /// if not 0, the height of the line the word is on, and glyphs are aligned on the bottom of the line
+    /// instead of the top
+    pub line_height: i16,
 }
@@ -0,0 +1,82 @@
+use crate::api::{LINES, WIDTH};
+
+/// Words per line of the framebuffer in a `Screenshot`
+pub const SCREENSHOT_WORDS_PER_LINE: usize = 11;
+const SCREENSHOT_WORDS: usize = SCREENSHOT_WORDS_PER_LINE * LINES as usize;
+
+/// The size of a BMP file header and info header, and of the two color palette that follows
+const BMP_HEADER_LEN: usize = 14 + 40 + 8;
+
+/// A copy of the framebuffer, for a screenshot
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Screenshot {
+    /// `SCREENSHOT_WORDS_PER_LINE` words per line, where a set bit is a light pixel
+    pub fb: [u32; SCREENSHOT_WORDS],
+    /// false until the framebuffer is copied in, and if the screenshot was refused
+    pub captured: bool,
+}
+
+impl Screenshot {
+    pub fn new() -> Self { Screenshot { fb: [0; SCREENSHOT_WORDS], captured: false } }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.fb[y * SCREENSHOT_WORDS_PER_LINE + x / 32] & (1 << (x % 32)) != 0
+    }
+
+    /// Encode as a monochrome (1 bit per pixel) BMP file
+    pub fn to_bmp(&self) -> Vec<u8> {
+        let (width, lines) = (WIDTH as usize, LINES as usize);
+        // rows are padded to a multiple of 4 bytes
+        let row_len = ((width + 31) / 32) * 4;
+        let file_len = BMP_HEADER_LEN + row_len * lines;
+        let mut bmp = Vec::with_capacity(file_len);
+        // file header
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&(file_len as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&(BMP_HEADER_LEN as u32).to_le_bytes());
+        // info header: size, width, height, planes, bits per pixel, no compression, image size,
+        // resolution (unspecified), colors used, important colors
+        for word in [40, width as u32, lines as u32].iter() {
+            bmp.extend_from_slice(&word.to_le_bytes());
+        }
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        for word in [0, (row_len * lines) as u32, 0, 0, 2, 0].iter() {
+            bmp.extend_from_slice(&word.to_le_bytes());
+        }
+        // palette, as blue, green, red, reserved: 0 is dark, 1 is light
+        bmp.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0x00]);
+        // rows go from the bottom of the image up, with the leftmost pixel in the top bit
+        for y in (0..lines).rev() {
+            let mut row = vec![0u8; row_len];
+            for x in (0..width).filter(|&x| self.pixel(x, y)) {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+            bmp.extend_from_slice(&row);
+        }
+        bmp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bmp_layout() {
+        let mut shot = Screenshot::new();
+        // light pixels at the top left and bottom right corners
+        shot.fb[0] = 1;
+        shot.fb[SCREENSHOT_WORDS - 1] = 1 << ((WIDTH as usize - 1) % 32);
+        let bmp = shot.to_bmp();
+        assert_eq!(bmp.len(), BMP_HEADER_LEN + 44 * LINES as usize);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(u32::from_le_bytes([bmp[2], bmp[3], bmp[4], bmp[5]]) as usize, bmp.len());
+        // the bottom line comes first
+        let bottom = &bmp[BMP_HEADER_LEN..BMP_HEADER_LEN + 44];
+        assert_eq!((bottom[0], bottom[41]), (0x00, 0x01));
+        let top = &bmp[bmp.len() - 44..];
+        assert_eq!((top[0], top[41]), (0x80, 0x00));
+    }
+}
@@ -1,6 +1,6 @@
 use core::cmp::{max, min};
 
-use crate::api::{ClipRect, DrawStyle, Point};
+use crate::api::{ClipRect, DrawStyle, FillPattern, PixelColor, Point};
 use crate::op::{HEIGHT, WIDTH};
 
 #[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -286,6 +286,101 @@ impl RoundedRectangle {
     pub fn translate(&mut self, offset: Point) { self.border.translate(offset); }
 }
 
+//////////////////////////// POLYGON
+
+/// The most vertices a `Polygon` can have, which keeps it small enough to batch in a `ClipObjectList`
+pub const POLYGON_MAX_POINTS: usize = 16;
+
+#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Polygon {
+    /// The vertices, of which the first `len` are used. The last vertex is joined back to the first.
+    pub points: [Point; POLYGON_MAX_POINTS],
+    pub len: u8,
+
+    /// Drawing style: the stroke outlines the polygon, and the fill covers its inside (by the even-odd
+    /// rule, for polygons that cross themselves)
+    pub style: DrawStyle,
+
+    /// The pattern the fill is dithered with
+    pub pattern: FillPattern,
+}
+
+impl Polygon {
+    pub fn new(style: DrawStyle) -> Polygon {
+        Polygon { points: [Point::zero(); POLYGON_MAX_POINTS], len: 0, style, pattern: FillPattern::Solid }
+    }
+
+    /// Make a polygon from its vertices, or `None` if there are more than `POLYGON_MAX_POINTS`
+    pub fn from_points(points: &[Point], style: DrawStyle) -> Option<Polygon> {
+        let mut polygon = Polygon::new(style);
+        for &point in points {
+            polygon.push(point).ok()?;
+        }
+        Some(polygon)
+    }
+
+    pub fn push(&mut self, point: Point) -> Result<(), Point> {
+        if (self.len as usize) < POLYGON_MAX_POINTS {
+            self.points[self.len as usize] = point;
+            self.len += 1;
+            Ok(())
+        } else {
+            Err(point)
+        }
+    }
+
+    pub fn points(&self) -> &[Point] { &self.points[..(self.len as usize).min(POLYGON_MAX_POINTS)] }
+
+    pub fn translate(&mut self, offset: Point) {
+        let len = self.points().len();
+        for point in self.points[..len].iter_mut() {
+            *point += offset;
+        }
+    }
+}
+
+//////////////////////////// ELLIPTIC ARC
+
+/// A part of an ellipse. Angles are in degrees, clockwise on the screen from the positive x axis (3
+/// o'clock), so a gauge that sweeps from 7:30 to 4:30 starts at 135 and sweeps 270.
+#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct EllipticArc {
+    pub center: Point,
+    /// The horizontal and vertical radii
+    pub radii: Point,
+    /// Where the arc starts
+    pub start: i16,
+    /// How far the arc goes from the start: positive is clockwise, and 360 or more is the whole ellipse
+    pub sweep: i16,
+
+    /// Drawing style: the stroke is drawn along the arc, centered on it, and the fill covers the sector
+    /// between the arc and the center (or the whole ellipse)
+    pub style: DrawStyle,
+
+    /// The pattern the fill is dithered with
+    pub pattern: FillPattern,
+}
+
+impl EllipticArc {
+    /// An arc that is only stroked, one pixel wide
+    pub fn new(center: Point, radii: Point, start: i16, sweep: i16) -> EllipticArc {
+        let style = DrawStyle { fill_color: None, ..DrawStyle::stroke_color(PixelColor::Dark) };
+        EllipticArc::new_with_style(center, radii, start, sweep, style)
+    }
+
+    pub fn new_with_style(
+        center: Point,
+        radii: Point,
+        start: i16,
+        sweep: i16,
+        style: DrawStyle,
+    ) -> EllipticArc {
+        EllipticArc { center, radii, start, sweep, style, pattern: FillPattern::Solid }
+    }
+
+    pub fn translate(&mut self, offset: Point) { self.center += offset; }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
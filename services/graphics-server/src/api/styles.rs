@@ -95,6 +95,39 @@ impl From<DrawStyle> for usize {
     }
 }
 
+/// A pattern to dither the fill of a shape with, for shades between dark and light. Where the pattern is
+/// set the fill color is drawn, and elsewhere the other color, so a patterned fill is still opaque. The
+/// pattern is aligned to the screen, so that shapes filled with the same pattern next to each other meet
+/// without a seam.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum FillPattern {
+    /// every pixel is the fill color
+    #[default]
+    Solid,
+    /// one pixel in four is the fill color
+    Sparse,
+    /// a checkerboard
+    Checker,
+    /// three pixels in four are the fill color
+    Dense,
+    /// diagonal lines, one pixel in four
+    Hatch,
+}
+
+impl FillPattern {
+    /// Whether the pixel at `(x, y)` on the screen takes the fill color
+    pub fn is_set(&self, x: i16, y: i16) -> bool {
+        let (x, y) = (x.rem_euclid(4), y.rem_euclid(4));
+        match self {
+            FillPattern::Solid => true,
+            FillPattern::Sparse => x % 2 == 0 && y % 2 == 0,
+            FillPattern::Checker => (x + y) % 2 == 0,
+            FillPattern::Dense => x % 2 == 0 || y % 2 == 0,
+            FillPattern::Hatch => (x + y) % 4 == 0,
+        }
+    }
+}
+
 /// A single pixel
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Pixel(pub Point, pub PixelColor);
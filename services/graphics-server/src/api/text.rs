@@ -74,6 +74,31 @@ impl From<usize> for TextOp {
 // roughly 168 bytes to represent the rest of the struct, and we want to fill out the 4096 byte page with text
 const TEXTVIEW_LEN: usize = 3072;
 pub const TEXTVIEW_DEFAULT_STYLE: GlyphStyle = GlyphStyle::Regular;
+/// The most style spans a TextView can hold
+pub const TEXTVIEW_MAX_SPANS: usize = 8;
+
+/// A run of text in a TextView that is drawn in its own style, instead of the TextView's `style`.
+/// `start` and `end` are offsets in characters (not bytes) into the text, and `end` is exclusive.
+///
+/// Glyphs of different heights on one line are aligned on the bottom of the line. Like the TextView's
+/// `invert`, a span's `invert` is only respected on a trusted canvas; the GAM strips it otherwise.
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, PartialEq)]
+pub struct TextSpan {
+    pub start: u32,
+    pub end: u32,
+    pub style: GlyphStyle,
+    pub invert: bool,
+    pub underline: bool,
+}
+impl TextSpan {
+    pub fn new(start: usize, end: usize, style: GlyphStyle) -> Self {
+        TextSpan { start: start as u32, end: end as u32, style, invert: false, underline: false }
+    }
+
+    pub fn contains(&self, charpos: usize) -> bool {
+        (self.start as usize) <= charpos && charpos < (self.end as usize)
+    }
+}
 
 #[derive(Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct TextView {
@@ -117,6 +142,9 @@ pub struct TextView {
     pub busy_animation_state: Option<u32>,
 
     pub text: String<3072>,
+
+    // runs of the text drawn in a style other than `style`; see `write_span()`
+    pub spans: [Option<TextSpan>; TEXTVIEW_MAX_SPANS],
 }
 impl TextView {
     pub fn new(canvas: Gid, bounds_hint: TextBounds) -> Self {
@@ -143,6 +171,7 @@ impl TextView {
             overflow: None,
             dry_run: false,
             busy_animation_state: None,
+            spans: [None; TEXTVIEW_MAX_SPANS],
         }
     }
 
@@ -158,7 +187,32 @@ impl TextView {
 
     pub fn to_str(&self) -> &str { self.text.as_str().unwrap() }
 
-    pub fn clear_str(&mut self) { self.text.clear() }
+    pub fn clear_str(&mut self) {
+        self.text.clear();
+        self.clear_spans();
+    }
+
+    /// Add a span of styled text. Fails if the TextView already has `TEXTVIEW_MAX_SPANS` spans.
+    pub fn add_span(&mut self, span: TextSpan) -> Result<(), xous::Error> {
+        let slot = self.spans.iter_mut().find(|s| s.is_none()).ok_or(xous::Error::OutOfMemory)?;
+        *slot = Some(span);
+        Ok(())
+    }
+
+    /// Append `s` to the text, drawn in `style`. The span is returned so that it can be inverted or
+    /// underlined, e.g. `tv.write_span("Alice", GlyphStyle::Bold)?.underline = true;`
+    pub fn write_span(&mut self, s: &str, style: GlyphStyle) -> Result<&mut TextSpan, xous::Error> {
+        let slot = self.spans.iter().position(|s| s.is_none()).ok_or(xous::Error::OutOfMemory)?;
+        let start = self.to_str().chars().count();
+        self.text.append(s)?;
+        self.spans[slot] = Some(TextSpan::new(start, start + s.chars().count(), style));
+        Ok(self.spans[slot].as_mut().unwrap())
+    }
+
+    pub fn clear_spans(&mut self) { self.spans = [None; TEXTVIEW_MAX_SPANS]; }
+
+    /// The spans of styled text, in the order they were added
+    pub fn spans(&self) -> impl Iterator<Item = &TextSpan> { self.spans.iter().filter_map(|s| s.as_ref()) }
 
     pub fn populate_from(&mut self, t: &TextView) {
         self.canvas = t.canvas;
@@ -181,6 +235,7 @@ impl TextView {
         self.clip_rect = t.clip_rect;
         self.dry_run = t.dry_run;
         self.insertion = t.insertion;
+        self.spans = t.spans;
     }
 }
 
@@ -1,11 +1,13 @@
 #![cfg_attr(not(target_os = "none"), allow(dead_code))]
 
+use std::io;
 use std::sync::{mpsc, Arc, Mutex};
 
 use minifb::{Key, Window, WindowOptions};
 
 use crate::api::Point;
 use crate::api::{LINES, WIDTH};
+use crate::snapshot::{Frame, Recorder};
 
 const HEIGHT: i16 = LINES;
 
@@ -20,6 +22,9 @@ const MAX_FPS: u64 = 60;
 const DARK_COLOUR: u32 = 0xB5B5AD;
 const LIGHT_COLOUR: u32 = 0x1B1B19;
 
+/// Run without a window, for CI runners: set by the `headless` feature, or by `XOUS_HEADLESS`
+fn headless() -> bool { cfg!(feature = "headless") || std::env::var_os("XOUS_HEADLESS").is_some() }
+
 /// The channel for the backend to communicate back to the main thread that it
 /// has claimed.
 pub struct MainThreadToken(mpsc::SyncSender<MinifbThread>);
@@ -67,6 +72,7 @@ pub struct XousDisplay {
     emulated_buffer: [u32; FB_SIZE],
     srfb: [u32; FB_SIZE],
     devboot: bool,
+    recorder: Recorder,
 }
 
 /// Encapsulates the data passed to the thread handling minifb screen updates
@@ -90,7 +96,13 @@ impl XousDisplay {
         let thread_params = MinifbThread { native_buffer: Arc::clone(&native_buffer) };
         main_thread_token.0.send(thread_params).unwrap();
 
-        XousDisplay { native_buffer, emulated_buffer: [0u32; FB_SIZE], srfb: [0u32; FB_SIZE], devboot: true }
+        XousDisplay {
+            native_buffer,
+            emulated_buffer: [0u32; FB_SIZE],
+            srfb: [0u32; FB_SIZE],
+            devboot: true,
+            recorder: Recorder::from_env(),
+        }
     }
 
     pub fn set_devboot(&mut self, ena: bool) {
@@ -126,6 +138,19 @@ impl XousDisplay {
 
     pub fn redraw(&mut self) { self.emulated_to_native(); }
 
+    /// A copy of the framebuffer
+    pub fn frame(&self) -> Frame { Frame::from_fb(&self.emulated_buffer, WIDTH_WORDS) }
+
+    /// Write the framebuffer to a PNG file, returning its sequence number
+    pub fn snapshot(&mut self) -> io::Result<usize> { self.recorder.snapshot(self.frame()) }
+
+    /// Write the framebuffer to a PNG file after a `Flush`, if snapshots are taken on every `Flush`
+    pub fn snapshot_flush(&mut self) {
+        if self.recorder.every_flush() {
+            self.recorder.flush(self.frame());
+        }
+    }
+
     fn emulated_to_native(&mut self) {
         const DEVBOOT_LINE: usize = 7;
         let mut native_buffer = self.native_buffer.lock().unwrap();
@@ -150,6 +175,14 @@ impl XousDisplay {
 
 impl MinifbThread {
     pub fn run_while(self, mut predicate: impl FnMut() -> bool) {
+        if headless() {
+            // the framebuffer is only seen in snapshots
+            log::info!("GFX|hosted: running headless");
+            while predicate() {
+                std::thread::sleep(std::time::Duration::from_micros(1000 * 1000 / MAX_FPS));
+            }
+            return;
+        }
         let mut window = Window::new(
             "Precursor",
             WIDTH as usize,
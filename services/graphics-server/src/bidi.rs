@@ -0,0 +1,577 @@
+//! Bidirectional text: the embedding levels of the Unicode Bidirectional Algorithm (UAX #9), the
+//! reordering of lines for display, and the contextual shaping of Arabic.
+//!
+//! This is a compact implementation, sized for the text that is shown and edited on the device:
+//! - explicit embeddings, overrides and isolates are not supported, so rules X1-X10 reduce to one level per
+//!   paragraph, and the formatting characters are treated as neutrals;
+//! - character classes are looked up from the ranges of the scripts concerned rather than the full Unicode
+//!   database, with letters of all other scripts taken as left-to-right;
+//! - shaping uses the Arabic Presentation Forms-B, which cover the letters of Arabic itself but not those
+//!   added for Persian, Urdu and other languages; those are shown in their isolated forms.
+//!
+//! The glyphs for these scripts are the `he` and `ar` sets in `blitstr2`, where the forms of a letter that
+//! join the one before it are drawn with no gap on their right, so that joined letters touch.
+//!
+//! Everything works on `char` positions, and shaping maps each character to exactly one display
+//! character, so that positions in the display text are still positions in the original text.
+
+/// Bidirectional character types (UAX #9, table 4), less those of the explicit formatting characters
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BidiClass {
+    /// left-to-right letter
+    L,
+    /// right-to-left letter
+    R,
+    /// Arabic letter
+    AL,
+    /// European number
+    EN,
+    /// European number separator
+    ES,
+    /// European number terminator
+    ET,
+    /// Arabic number
+    AN,
+    /// common number separator
+    CS,
+    /// nonspacing mark
+    NSM,
+    /// paragraph separator
+    B,
+    /// segment separator
+    S,
+    /// whitespace
+    WS,
+    /// other neutral
+    ON,
+}
+use BidiClass::*;
+
+/// Ranges of characters and their classes; the first range to hold a character gives its class
+const CLASS_RANGES: &[(u32, u32, BidiClass)] = &[
+    (0x0009, 0x0009, S),
+    (0x000a, 0x000a, B),
+    (0x000b, 0x000b, S),
+    (0x000c, 0x000c, WS),
+    (0x000d, 0x000d, B),
+    (0x001c, 0x001e, B),
+    (0x001f, 0x001f, S),
+    (0x0020, 0x0020, WS),
+    (0x0023, 0x0025, ET),
+    (0x002b, 0x002b, ES),
+    (0x002c, 0x002c, CS),
+    (0x002d, 0x002d, ES),
+    (0x002e, 0x002f, CS),
+    (0x0030, 0x0039, EN),
+    (0x003a, 0x003a, CS),
+    (0x0085, 0x0085, B),
+    (0x00a0, 0x00a0, CS),
+    (0x00a2, 0x00a5, ET),
+    (0x00b0, 0x00b1, ET),
+    (0x00b2, 0x00b3, EN),
+    (0x00b9, 0x00b9, EN),
+    (0x0300, 0x036f, NSM),
+    (0x0591, 0x05bd, NSM),
+    (0x05bf, 0x05bf, NSM),
+    (0x05c1, 0x05c2, NSM),
+    (0x05c4, 0x05c5, NSM),
+    (0x05c7, 0x05c7, NSM),
+    (0x0590, 0x05ff, R),
+    (0x0600, 0x0605, AN),
+    (0x060c, 0x060c, CS),
+    (0x0610, 0x061a, NSM),
+    (0x064b, 0x065f, NSM),
+    (0x0660, 0x0669, AN),
+    (0x066a, 0x066a, ET),
+    (0x066b, 0x066c, AN),
+    (0x0670, 0x0670, NSM),
+    (0x06d6, 0x06dc, NSM),
+    (0x06dd, 0x06dd, AN),
+    (0x06df, 0x06e4, NSM),
+    (0x06e7, 0x06e8, NSM),
+    (0x06ea, 0x06ed, NSM),
+    (0x06f0, 0x06f9, EN),
+    (0x0600, 0x07bf, AL),
+    (0x07c0, 0x085f, R),
+    (0x0860, 0x08ff, AL),
+    (0x2000, 0x200a, WS),
+    (0x2028, 0x2028, WS),
+    (0x2029, 0x2029, B),
+    (0x2030, 0x2034, ET),
+    (0x205f, 0x205f, WS),
+    (0x20a0, 0x20cf, ET),
+    (0x3000, 0x3000, WS),
+    (0xfb1d, 0xfb4f, R),
+    (0xfb50, 0xfdff, AL),
+    (0xfe70, 0xfefe, AL),
+    (0x10800, 0x10fff, R),
+    (0x1e800, 0x1efff, R),
+];
+
+pub fn bidi_class(ch: char) -> BidiClass {
+    let c = ch as u32;
+    match CLASS_RANGES.iter().find(|(lo, hi, _)| *lo <= c && c <= *hi) {
+        Some((_, _, class)) => *class,
+        None if ch.is_alphanumeric() => L,
+        None => ON,
+    }
+}
+
+/// True for characters of right-to-left scripts
+pub fn is_rtl(ch: char) -> bool { matches!(bidi_class(ch), R | AL) }
+
+/// True for the levels of right-to-left text, which are the odd ones
+pub fn is_rtl_level(level: u8) -> bool { level & 1 == 1 }
+
+/// The embedding levels of a text, and the level of the paragraph each character is in
+pub struct BidiLevels {
+    pub levels: Vec<u8>,
+    pub paragraph: Vec<u8>,
+}
+
+impl BidiLevels {
+    /// Resolve the levels of a text, in which paragraphs are separated by newlines
+    pub fn resolve(text: &[char]) -> Self {
+        let mut levels = Vec::with_capacity(text.len());
+        let mut paragraph = Vec::with_capacity(text.len());
+        let mut start = 0;
+        while start < text.len() {
+            // the paragraph separator belongs to the paragraph it ends
+            let end =
+                text[start..].iter().position(|&c| bidi_class(c) == B).map_or(text.len(), |n| start + n + 1);
+            let para = paragraph_level(&text[start..end]);
+            levels.extend(resolve_paragraph(&text[start..end], para));
+            paragraph.resize(end, para);
+            start = end;
+        }
+        BidiLevels { levels, paragraph }
+    }
+}
+
+/// The level of a paragraph: 1 if its first strong character is right-to-left, else 0 (rules P2, P3)
+pub fn paragraph_level(text: &[char]) -> u8 {
+    match text.iter().map(|&c| bidi_class(c)).find(|c| matches!(c, L | R | AL)) {
+        Some(R) | Some(AL) => 1,
+        _ => 0,
+    }
+}
+
+fn is_neutral(class: BidiClass) -> bool { matches!(class, B | S | WS | ON) }
+
+/// Resolve the levels of the characters of one paragraph (rules W1-W7, N1-N2, I1-I2, and L1 but for the
+/// trailing whitespace of lines, which is left to `visual_order()`)
+fn resolve_paragraph(text: &[char], para: u8) -> Vec<u8> {
+    let sos = if is_rtl_level(para) { R } else { L };
+    let mut classes: Vec<BidiClass> = text.iter().map(|&c| bidi_class(c)).collect();
+    let n = classes.len();
+    // W1: nonspacing marks take the class of the character before them
+    for i in 0..n {
+        if classes[i] == NSM {
+            classes[i] = if i == 0 { sos } else { classes[i - 1] };
+        }
+    }
+    // W2: European numbers after Arabic letters are Arabic numbers; W3: Arabic letters are right-to-left
+    let mut strong = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            L | R => strong = *class,
+            AL => {
+                strong = AL;
+                *class = R;
+            }
+            EN if strong == AL => *class = AN,
+            _ => {}
+        }
+    }
+    // W4: a single separator between two numbers of the same kind joins them
+    for i in 1..n.saturating_sub(1) {
+        match (classes[i - 1], classes[i], classes[i + 1]) {
+            (EN, ES, EN) | (EN, CS, EN) => classes[i] = EN,
+            (AN, CS, AN) => classes[i] = AN,
+            _ => {}
+        }
+    }
+    // W5: terminators next to European numbers are part of them
+    let mut i = 0;
+    while i < n {
+        if classes[i] == ET {
+            let start = i;
+            while i < n && classes[i] == ET {
+                i += 1;
+            }
+            if (start > 0 && classes[start - 1] == EN) || (i < n && classes[i] == EN) {
+                classes[start..i].iter_mut().for_each(|c| *c = EN);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    // W6: other separators and terminators are neutral; W7: European numbers after left-to-right letters
+    // are left-to-right
+    let mut strong = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            ES | ET | CS => *class = ON,
+            L | R => strong = *class,
+            EN if strong == L => *class = L,
+            _ => {}
+        }
+    }
+    // N1, N2: neutrals between characters of the same direction take that direction, and otherwise the
+    // direction of the paragraph; numbers count as right-to-left
+    let direction = |c: BidiClass| if c == L { L } else { R };
+    let mut i = 0;
+    while i < n {
+        if is_neutral(classes[i]) {
+            let start = i;
+            while i < n && is_neutral(classes[i]) {
+                i += 1;
+            }
+            let before = if start == 0 { sos } else { direction(classes[start - 1]) };
+            let after = if i == n { sos } else { direction(classes[i]) };
+            let resolved = if before == after { before } else { sos };
+            classes[start..i].iter_mut().for_each(|c| *c = resolved);
+        } else {
+            i += 1;
+        }
+    }
+    // I1, I2
+    let mut levels: Vec<u8> = classes
+        .iter()
+        .map(|&c| match (is_rtl_level(para), c) {
+            (false, R) => para + 1,
+            (false, AN) | (false, EN) => para + 2,
+            (true, L) | (true, AN) | (true, EN) => para + 1,
+            _ => para,
+        })
+        .collect();
+    // L1: separators, and the whitespace before them, are at the paragraph level
+    let mut reset = true;
+    for i in (0..n).rev() {
+        match bidi_class(text[i]) {
+            B | S => {
+                levels[i] = para;
+                reset = true;
+            }
+            WS if reset => levels[i] = para,
+            _ => reset = false,
+        }
+    }
+    levels
+}
+
+/// The visual order of a line: the positions of its characters, left to right (rule L2), given the
+/// resolved level of each character, and the level of the paragraph for its trailing whitespace (L1)
+pub fn visual_order(levels: &[u8], trailing_ws: usize, para: u8) -> Vec<usize> {
+    let mut levels = levels.to_vec();
+    let n = levels.len();
+    levels[n - trailing_ws.min(n)..].iter_mut().for_each(|l| *l = para);
+    let mut order: Vec<usize> = (0..n).collect();
+    let highest = levels.iter().copied().max().unwrap_or(0);
+    let lowest_odd = levels.iter().copied().filter(|&l| is_rtl_level(l)).min().unwrap_or(highest + 1);
+    // from the highest level down to the lowest odd one, reverse every run at that level or higher
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < n {
+            if levels[order[i]] >= level {
+                let start = i;
+                while i < n && levels[order[i]] >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    order
+}
+
+/// The mirror image of a character, for characters at right-to-left levels (rule L4)
+pub fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '<' => '>',
+        '>' => '<',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '≤' => '≥',
+        '≥' => '≤',
+        _ => ch,
+    }
+}
+
+/// Where a character is absorbed into a ligature with the one before it, the display text has this in its
+/// place, to be drawn with no width
+pub const ABSORBED: char = '\u{fdd0}';
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Joining {
+    /// joins on both sides
+    Dual,
+    /// joins only to the character before it
+    Right,
+    /// joins on both sides, but has no forms of its own (tatweel, zero-width joiner)
+    Causing,
+    /// is skipped over when joining (marks)
+    Transparent,
+    None,
+}
+
+/// The isolated presentation form of an Arabic letter, and its joining type. The final, initial and medial
+/// forms follow the isolated one, in that order.
+fn presentation_forms(ch: char) -> Option<(u32, Joining)> {
+    const DUAL: Joining = Joining::Dual;
+    const RIGHT: Joining = Joining::Right;
+    Some(match ch {
+        '\u{0621}' => (0xfe80, Joining::None),
+        '\u{0622}' => (0xfe81, RIGHT),
+        '\u{0623}' => (0xfe83, RIGHT),
+        '\u{0624}' => (0xfe85, RIGHT),
+        '\u{0625}' => (0xfe87, RIGHT),
+        '\u{0626}' => (0xfe89, DUAL),
+        '\u{0627}' => (0xfe8d, RIGHT),
+        '\u{0628}' => (0xfe8f, DUAL),
+        '\u{0629}' => (0xfe93, RIGHT),
+        '\u{062a}' => (0xfe95, DUAL),
+        '\u{062b}' => (0xfe99, DUAL),
+        '\u{062c}' => (0xfe9d, DUAL),
+        '\u{062d}' => (0xfea1, DUAL),
+        '\u{062e}' => (0xfea5, DUAL),
+        '\u{062f}' => (0xfea9, RIGHT),
+        '\u{0630}' => (0xfeab, RIGHT),
+        '\u{0631}' => (0xfead, RIGHT),
+        '\u{0632}' => (0xfeaf, RIGHT),
+        '\u{0633}' => (0xfeb1, DUAL),
+        '\u{0634}' => (0xfeb5, DUAL),
+        '\u{0635}' => (0xfeb9, DUAL),
+        '\u{0636}' => (0xfebd, DUAL),
+        '\u{0637}' => (0xfec1, DUAL),
+        '\u{0638}' => (0xfec5, DUAL),
+        '\u{0639}' => (0xfec9, DUAL),
+        '\u{063a}' => (0xfecd, DUAL),
+        '\u{0641}' => (0xfed1, DUAL),
+        '\u{0642}' => (0xfed5, DUAL),
+        '\u{0643}' => (0xfed9, DUAL),
+        '\u{0644}' => (0xfedd, DUAL),
+        '\u{0645}' => (0xfee1, DUAL),
+        '\u{0646}' => (0xfee5, DUAL),
+        '\u{0647}' => (0xfee9, DUAL),
+        '\u{0648}' => (0xfeed, RIGHT),
+        '\u{0649}' => (0xfeef, RIGHT),
+        '\u{064a}' => (0xfef1, DUAL),
+        _ => return None,
+    })
+}
+
+fn joining(ch: char) -> Joining {
+    match presentation_forms(ch) {
+        Some((_, joining)) => joining,
+        None if ch == '\u{0640}' || ch == '\u{200d}' => Joining::Causing,
+        None if bidi_class(ch) == NSM => Joining::Transparent,
+        None => Joining::None,
+    }
+}
+
+/// The isolated form of the ligature of lam with an alef, which follows the lam; the final form follows
+fn lam_alef(alef: char) -> Option<u32> {
+    match alef {
+        '\u{0622}' => Some(0xfef5),
+        '\u{0623}' => Some(0xfef7),
+        '\u{0625}' => Some(0xfef9),
+        '\u{0627}' => Some(0xfefb),
+        _ => None,
+    }
+}
+
+/// Replace Arabic letters with the presentation forms for their positions in words, and lam-alef pairs
+/// with their ligatures, with `ABSORBED` in place of the alef
+pub fn shape_arabic(text: &[char]) -> Vec<char> {
+    let mut shaped = text.to_vec();
+    // the nearest characters on either side that aren't transparent
+    let neighbour = |i: usize, forward: bool| -> Option<usize> {
+        if forward {
+            (i + 1..text.len()).find(|&j| joining(text[j]) != Joining::Transparent)
+        } else {
+            (0..i).rev().find(|&j| joining(text[j]) != Joining::Transparent)
+        }
+    };
+    let mut i = 0;
+    while i < text.len() {
+        if let Some((isolated, join)) = presentation_forms(text[i]) {
+            let joins_before = join != Joining::None
+                && neighbour(i, false).is_some_and(|j| {
+                    matches!(joining(text[j]), Joining::Dual | Joining::Causing) && shaped[j] != ABSORBED
+                });
+            let next = neighbour(i, true);
+            if text[i] == '\u{0644}' {
+                if let Some(ligature) = next.filter(|&j| j == i + 1).and_then(|j| lam_alef(text[j])) {
+                    shaped[i] = char::from_u32(ligature + joins_before as u32).unwrap_or(text[i]);
+                    shaped[i + 1] = ABSORBED;
+                    i += 2;
+                    continue;
+                }
+            }
+            let joins_after = join == Joining::Dual
+                && next.is_some_and(|j| {
+                    matches!(joining(text[j]), Joining::Dual | Joining::Right | Joining::Causing)
+                });
+            let form = match (join, joins_before, joins_after) {
+                (Joining::Dual, true, true) => 3,
+                (Joining::Dual, false, true) => 2,
+                (_, true, _) => 1,
+                _ => 0,
+            };
+            shaped[i] = char::from_u32(isolated + form).unwrap_or(text[i]);
+        }
+        i += 1;
+    }
+    shaped
+}
+
+/// Returns true if a character of the display text joins the one before it, which is drawn on its right: the
+/// final and medial presentation forms, the final lam-alef ligatures, and tatweel. These are drawn with no
+/// gap on their right.
+pub fn joins_before(ch: char) -> bool {
+    let code = ch as u32;
+    match code {
+        0x0640 => true,
+        0xfef5..=0xfefc => (code - 0xfef5) % 2 == 1,
+        0xfe80..=0xfef4 => {
+            ('\u{0621}'..='\u{064a}').filter_map(presentation_forms).any(|(isolated, join)| {
+                (join != Joining::None && code == isolated + 1)
+                    || (join == Joining::Dual && code == isolated + 3)
+            })
+        }
+        _ => false,
+    }
+}
+
+/// The display text for a text with right-to-left characters in it: Arabic shaped, and characters at
+/// right-to-left levels mirrored. The display text has the same number of characters as the text.
+pub fn display_text(text: &[char], levels: &BidiLevels) -> Vec<char> {
+    shape_arabic(text)
+        .into_iter()
+        .zip(levels.levels.iter())
+        .map(|(ch, level)| if is_rtl_level(*level) { mirror(ch) } else { ch })
+        .collect()
+}
+
+/// Move an insertion point one character to the left or right on the screen, in a one-line text. In
+/// right-to-left runs that is the opposite of moving forward or back in the text.
+///
+/// `insertion` is the position of the character the insertion point is before, from 0 to the length of
+/// the text.
+pub fn visual_step(text: &str, insertion: usize, right: bool) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let n = chars.len();
+    if !chars.iter().any(|&c| is_rtl(c)) {
+        return if right { (insertion + 1).min(n) } else { insertion.saturating_sub(1) };
+    }
+    let levels = BidiLevels::resolve(&chars);
+    let order = visual_order(&levels.levels, 0, levels.paragraph.first().copied().unwrap_or(0));
+    let mut column = vec![0; n];
+    for (x, &i) in order.iter().enumerate() {
+        column[i] = x;
+    }
+    // the gap between characters on the screen that an insertion point is drawn in: at the leading edge
+    // of the character after it, or the trailing edge of the last character
+    let gap = |i: usize| -> usize {
+        let (c, trailing) = if i < n { (i, false) } else { (n - 1, true) };
+        if is_rtl_level(levels.levels[c]) != trailing { column[c] + 1 } else { column[c] }
+    };
+    if n == 0 {
+        return 0;
+    }
+    let from = gap(insertion.min(n));
+    let mut to = from;
+    loop {
+        to = if right { to + 1 } else { to.wrapping_sub(1) };
+        if to > n {
+            return insertion;
+        }
+        // several insertion points can share a gap where runs of opposite directions meet; take the
+        // nearest in the text
+        if let Some(i) =
+            (0..=n).filter(|&i| gap(i) == to).min_by_key(|&i| (i as isize - insertion as isize).abs())
+        {
+            return i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> { s.chars().collect() }
+
+    fn visual(s: &str) -> String {
+        let text = chars(s);
+        let levels = BidiLevels::resolve(&text);
+        visual_order(&levels.levels, 0, levels.paragraph[0]).iter().map(|&i| text[i]).collect()
+    }
+
+    #[test]
+    fn levels() {
+        // Hebrew in an English paragraph, and the reverse; numbers after Hebrew run with it
+        assert_eq!(BidiLevels::resolve(&chars("ab אב 12")).levels, vec![0, 0, 0, 1, 1, 1, 2, 2]);
+        assert_eq!(BidiLevels::resolve(&chars("אב 12 ab")).levels, vec![1, 1, 1, 2, 2, 1, 2, 2]);
+        // each paragraph has its own level
+        assert_eq!(BidiLevels::resolve(&chars("a\nא")).paragraph, vec![0, 0, 1]);
+        // Arabic digits after Arabic letters are Arabic numbers
+        assert_eq!(BidiLevels::resolve(&chars("ب 12")).levels, vec![1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn reorder() {
+        assert_eq!(visual("abc אבג def"), "abc גבא def");
+        assert_eq!(visual("אבג 123 דה"), "הד 123 גבא");
+        // the punctuation at the end of a right-to-left paragraph ends up on its left
+        assert_eq!(visual("שלום world!"), "!world םולש");
+    }
+
+    #[test]
+    fn shaping() {
+        // beh, teh, reh: initial, medial, final (reh doesn't join the letter after it)
+        assert_eq!(shape_arabic(&chars("بتر")), chars("\u{fe91}\u{fe98}\u{feae}"));
+        // lam-alef is a ligature, in its final form after a beh
+        assert_eq!(shape_arabic(&chars("بلا")), vec!['\u{fe91}', '\u{fefc}', ABSORBED]);
+        // marks don't break joining
+        assert_eq!(shape_arabic(&chars("بَت")), chars("\u{fe91}\u{064e}\u{fe96}"));
+        assert_eq!(display_text(&chars("א(ב)"), &BidiLevels::resolve(&chars("א(ב)"))), chars("א)ב("));
+    }
+
+    #[test]
+    fn joining_forms() {
+        // the forms of beh and of reh that join the letter before them
+        let beh = ['\u{fe8f}', '\u{fe90}', '\u{fe91}', '\u{fe92}'].map(joins_before);
+        assert_eq!(beh, [false, true, false, true]);
+        assert_eq!(['\u{fead}', '\u{feae}'].map(joins_before), [false, true]);
+        // hamza has no joining forms, and lam-alef is final after an isolated form
+        assert!(!joins_before('\u{fe80}'));
+        assert_eq!(['\u{fefb}', '\u{fefc}'].map(joins_before), [false, true]);
+        assert!(joins_before('\u{0640}'));
+        assert!(!joins_before('\u{0628}') && !joins_before('a'));
+        // seen, lam-alef, meem: alef doesn't join the letter after it
+        let shaped: Vec<bool> = shape_arabic(&chars("سلام")).into_iter().map(joins_before).collect();
+        assert_eq!(shaped, [false, true, false, false]);
+    }
+
+    #[test]
+    fn insertion_steps() {
+        // left to right text is unchanged
+        assert_eq!((visual_step("abc", 1, false), visual_step("abc", 1, true)), (0, 2));
+        // in right-to-left text, the left arrow moves forward in the text
+        assert_eq!(visual_step("אבג", 1, false), 2);
+        assert_eq!(visual_step("אבג", 1, true), 0);
+        assert_eq!(visual_step("אבג", 3, false), 3);
+    }
+}
@@ -19,6 +19,8 @@ pub const NULL_GLYPH_SPRITE: GlyphSprite = GlyphSprite {
     insert: false,
     double: false,
     large: false,
+    underline: false,
+    rtl: false,
 };
 
 /// Unicode replacement character
@@ -118,6 +118,26 @@ config = {
       glyphTrim: "proportional",
       rustout: "../fonts/small.rs",
       loaderout: "../../../../../loader/src/fonts/small.rs",
+    },
+    {
+      name: "he",
+      sprites: "src_data/hebrew.png", size: 16, cols: 30, gutter: 2, border: 2,
+      legal: "src_data/unifont15_legal.txt",
+      index: "src_data/hebrew.codepoints",
+      indexType: "txt-row-major",
+      glyphTrim: "proportional",
+      rustout: "../fonts/he.rs",
+      loaderout: "../../../../../loader/src/fonts/he.rs",
+    },
+    {
+      name: "ar",
+      sprites: "src_data/arabic.png", size: 16, cols: 30, gutter: 2, border: 2,
+      legal: "src_data/unifont15_legal.txt",
+      index: "src_data/arabic.codepoints",
+      indexType: "txt-row-major",
+      glyphTrim: "proportional",
+      rustout: "../fonts/ar.rs",
+      loaderout: "../../../../../loader/src/fonts/ar.rs",
     }
   ]
 }
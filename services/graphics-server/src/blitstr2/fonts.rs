@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 //
 #![allow(dead_code)]
+pub mod ar;
 pub mod bold;
 pub mod emoji;
+pub mod he;
 pub mod ja;
 pub mod kr;
 pub mod mono;
@@ -32,6 +34,8 @@ pub fn small_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -56,6 +60,8 @@ pub fn regular_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -80,6 +86,8 @@ pub fn large_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: true,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -104,6 +112,8 @@ pub fn extra_large_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: true,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -128,6 +138,8 @@ pub fn bold_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -152,6 +164,8 @@ pub fn mono_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -176,6 +190,8 @@ pub fn tall_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: true,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -200,6 +216,8 @@ pub fn emoji_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -224,6 +242,8 @@ pub fn emoji_large_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: true,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -248,6 +268,8 @@ pub fn zh_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -272,6 +294,8 @@ pub fn ja_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -296,6 +320,61 @@ pub fn kr_glyph(ch: char) -> Result<GlyphSprite, usize> {
                     insert: false,
                     double: false,
                     large: false,
+                    underline: false,
+                    rtl: false,
+                }),
+                false => Err(0),
+            }
+        }
+        _ => Err(1),
+    }
+}
+
+pub fn he_glyph(ch: char) -> Result<GlyphSprite, usize> {
+    match he::CODEPOINTS.binary_search(&(ch as u32)) {
+        Ok(n) => {
+            let offset = n << 3;
+            let end = offset + 8;
+            match end <= he::glyphs().len() {
+                true => Ok(GlyphSprite {
+                    glyph: &he::glyphs()[offset..end],
+                    wide: he::WIDTHS[n],
+                    high: he::MAX_HEIGHT,
+                    kern: DEFAULT_KERN,
+                    ch,
+                    invert: false,
+                    insert: false,
+                    double: false,
+                    large: false,
+                    underline: false,
+                    rtl: false,
+                }),
+                false => Err(0),
+            }
+        }
+        _ => Err(1),
+    }
+}
+
+pub fn ar_glyph(ch: char) -> Result<GlyphSprite, usize> {
+    match ar::CODEPOINTS.binary_search(&(ch as u32)) {
+        Ok(n) => {
+            let offset = n << 3;
+            let end = offset + 8;
+            match end <= ar::glyphs().len() {
+                true => Ok(GlyphSprite {
+                    glyph: &ar::glyphs()[offset..end],
+                    wide: ar::WIDTHS[n],
+                    high: ar::MAX_HEIGHT,
+                    // joined letters touch: the one a letter joins is drawn on its right
+                    kern: if crate::bidi::joins_before(ch) { 0 } else { DEFAULT_KERN },
+                    ch,
+                    invert: false,
+                    insert: false,
+                    double: false,
+                    large: false,
+                    underline: false,
+                    rtl: false,
                 }),
                 false => Err(0),
             }
@@ -0,0 +1,467 @@
+#![cfg_attr(rustfmt, rustfmt_skip)]
+// DO NOT MAKE EDITS HERE because this file is automatically generated.
+// To make changes, see <xous_root>/services/graphics-server/src/blitstr2/codegen/main.go
+//
+// This code includes bitmap glyphs extracted from PNG sprite sheet documents created
+// from the Unifont 15.1.05 glyph source, unifont.hex, as packaged in version
+// 1.1.0+data-15.1.05 of the unifont crate from https://crates.io/crates/unifont.
+//
+// Unifont 15 is dual-licensed under the SIL Open Font License (OFL) version 1.1
+// and the GNU GPL 2+ with the GNU font embedding exception.
+//
+// **This code is using Unifont 15 under the SIL OFL-1.1 option.**
+//
+// This code uses lists of Hebrew and Arabic codepoints derived from the Unicode Data
+// Files for Unicode 14, UnicodeData.txt, taking the characters of the Hebrew
+// (U+0590-U+05FF), Arabic (U+0600-U+06FF) and Arabic Presentation Forms-B
+// (U+FE70-U+FEFF) blocks that are assigned and not format characters.
+//
+// ===============================================================================
+//
+// The SIL Open Font License version 1.1 is copied below, and is also
+// available with a FAQ at http://scripts.sil.org/OFL.
+//
+//
+// -----------------------------------------------------------
+// SIL OPEN FONT LICENSE Version 1.1 - 26 February 2007
+// -----------------------------------------------------------
+//
+// PREAMBLE
+// The goals of the Open Font License (OFL) are to stimulate worldwide
+// development of collaborative font projects, to support the font creation
+// efforts of academic and linguistic communities, and to provide a free and
+// open framework in which fonts may be shared and improved in partnership
+// with others.
+//
+// The OFL allows the licensed fonts to be used, studied, modified and
+// redistributed freely as long as they are not sold by themselves. The
+// fonts, including any derivative works, can be bundled, embedded,
+// redistributed and/or sold with any software provided that any reserved
+// names are not used by derivative works. The fonts and derivatives,
+// however, cannot be released under any other type of license. The
+// requirement for fonts to remain under this license does not apply
+// to any document created using the fonts or their derivatives.
+//
+// DEFINITIONS
+// "Font Software" refers to the set of files released by the Copyright
+// Holder(s) under this license and clearly marked as such. This may
+// include source files, build scripts and documentation.
+//
+// "Reserved Font Name" refers to any names specified as such after the
+// copyright statement(s).
+//
+// "Original Version" refers to the collection of Font Software components as
+// distributed by the Copyright Holder(s).
+//
+// "Modified Version" refers to any derivative made by adding to, deleting,
+// or substituting -- in part or in whole -- any of the components of the
+// Original Version, by changing formats or by porting the Font Software to a
+// new environment.
+//
+// "Author" refers to any designer, engineer, programmer, technical
+// writer or other person who contributed to the Font Software.
+//
+// PERMISSION & CONDITIONS
+// Permission is hereby granted, free of charge, to any person obtaining
+// a copy of the Font Software, to use, study, copy, merge, embed, modify,
+// redistribute, and sell modified and unmodified copies of the Font
+// Software, subject to the following conditions:
+//
+// 1) Neither the Font Software nor any of its individual components,
+// in Original or Modified Versions, may be sold by itself.
+//
+// 2) Original or Modified Versions of the Font Software may be bundled,
+// redistributed and/or sold with any software, provided that each copy
+// contains the above copyright notice and this license. These can be
+// included either as stand-alone text files, human-readable headers or
+// in the appropriate machine-readable metadata fields within text or
+// binary files as long as those fields can be easily viewed by the user.
+//
+// 3) No Modified Version of the Font Software may use the Reserved Font
+// Name(s) unless explicit written permission is granted by the corresponding
+// Copyright Holder. This restriction only applies to the primary font name as
+// presented to the users.
+//
+// 4) The name(s) of the Copyright Holder(s) or the Author(s) of the Font
+// Software shall not be used to promote, endorse or advertise any
+// Modified Version, except to acknowledge the contribution(s) of the
+// Copyright Holder(s) and the Author(s) or with their explicit written
+// permission.
+//
+// 5) The Font Software, modified or unmodified, in part or in whole,
+// must be distributed entirely under this license, and must not be
+// distributed under any other license. The requirement for fonts to
+// remain under this license does not apply to any document created
+// using the Font Software.
+//
+// TERMINATION
+// This license becomes null and void if any of the above conditions are
+// not met.
+//
+// DISCLAIMER
+// THE FONT SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO ANY WARRANTIES OF
+// MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT
+// OF COPYRIGHT, PATENT, TRADEMARK, OR OTHER RIGHT. IN NO EVENT SHALL THE
+// COPYRIGHT HOLDER BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY,
+// INCLUDING ANY GENERAL, SPECIAL, INDIRECT, INCIDENTAL, OR CONSEQUENTIAL
+// DAMAGES, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF THE USE OR INABILITY TO USE THE FONT SOFTWARE OR FROM
+// OTHER DEALINGS IN THE FONT SOFTWARE.
+//
+//
+// ===============================================================================
+//
+// UNICODE, INC. LICENSE AGREEMENT - DATA FILES AND SOFTWARE
+//
+// See Terms of Use <https://www.unicode.org/copyright.html>
+// for definitions of Unicode Inc.’s Data Files and Software.
+//
+// NOTICE TO USER: Carefully read the following legal agreement.
+// BY DOWNLOADING, INSTALLING, COPYING OR OTHERWISE USING UNICODE INC.'S
+// DATA FILES ("DATA FILES"), AND/OR SOFTWARE ("SOFTWARE"),
+// YOU UNEQUIVOCALLY ACCEPT, AND AGREE TO BE BOUND BY, ALL OF THE
+// TERMS AND CONDITIONS OF THIS AGREEMENT.
+// IF YOU DO NOT AGREE, DO NOT DOWNLOAD, INSTALL, COPY, DISTRIBUTE OR USE
+// THE DATA FILES OR SOFTWARE.
+//
+// COPYRIGHT AND PERMISSION NOTICE
+//
+// Copyright © 1991-2022 Unicode, Inc. All rights reserved.
+// Distributed under the Terms of Use in https://www.unicode.org/copyright.html.
+//
+// Permission is hereby granted, free of charge, to any person obtaining
+// a copy of the Unicode data files and any associated documentation
+// (the "Data Files") or Unicode software and any associated documentation
+// (the "Software") to deal in the Data Files or Software
+// without restriction, including without limitation the rights to use,
+// copy, modify, merge, publish, distribute, and/or sell copies of
+// the Data Files or Software, and to permit persons to whom the Data Files
+// or Software are furnished to do so, provided that either
+// (a) this copyright and permission notice appear with all copies
+// of the Data Files or Software, or
+// (b) this copyright and permission notice appear in associated
+// Documentation.
+//
+// THE DATA FILES AND SOFTWARE ARE PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE
+// WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT OF THIRD PARTY RIGHTS.
+// IN NO EVENT SHALL THE COPYRIGHT HOLDER OR HOLDERS INCLUDED IN THIS
+// NOTICE BE LIABLE FOR ANY CLAIM, OR ANY SPECIAL INDIRECT OR CONSEQUENTIAL
+// DAMAGES, OR ANY DAMAGES WHATSOEVER RESULTING FROM LOSS OF USE,
+//
+// DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR OTHER
+// TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THE DATA FILES OR SOFTWARE.
+//
+// Except as contained in this notice, the name of a copyright holder
+// shall not be used in advertising or otherwise to promote the sale,
+// use or other dealings in these Data Files or Software without prior
+// written authorization of the copyright holder.
+//
+//! he Font
+#![allow(dead_code)]
+
+/// Maximum height of glyph patterns in this bitmap typeface.
+pub const MAX_HEIGHT: u8 = 16;
+
+/// Unicode character codepoints corresponding to glyph sprites in GLYPHS array.
+/// Indended use:
+///  1. Do binary search on CODEPOINTS to find index of the codepoint corresponding
+///     to the glyph you want to locate
+///  2. Multiply resulting CODEPOINTS index by 8 (<<3) to get index into GLYPHS for
+///     the corresponding glyph sprite (because 16*16px sprite size is 8*u32)
+pub const CODEPOINTS: [u32; 88] = [
+0x00591,
+0x00592,
+0x00593,
+0x00594,
+0x00595,
+0x00596,
+0x00597,
+0x00598,
+0x00599,
+0x0059A,
+0x0059B,
+0x0059C,
+0x0059D,
+0x0059E,
+0x0059F,
+0x005A0,
+0x005A1,
+0x005A2,
+0x005A3,
+0x005A4,
+0x005A5,
+0x005A6,
+0x005A7,
+0x005A8,
+0x005A9,
+0x005AA,
+0x005AB,
+0x005AC,
+0x005AD,
+0x005AE,
+0x005AF,
+0x005B0,
+0x005B1,
+0x005B2,
+0x005B3,
+0x005B4,
+0x005B5,
+0x005B6,
+0x005B7,
+0x005B8,
+0x005B9,
+0x005BA,
+0x005BB,
+0x005BC,
+0x005BD,
+0x005BE,
+0x005BF,
+0x005C0,
+0x005C1,
+0x005C2,
+0x005C3,
+0x005C4,
+0x005C5,
+0x005C6,
+0x005C7,
+0x005D0,
+0x005D1,
+0x005D2,
+0x005D3,
+0x005D4,
+0x005D5,
+0x005D6,
+0x005D7,
+0x005D8,
+0x005D9,
+0x005DA,
+0x005DB,
+0x005DC,
+0x005DD,
+0x005DE,
+0x005DF,
+0x005E0,
+0x005E1,
+0x005E2,
+0x005E3,
+0x005E4,
+0x005E5,
+0x005E6,
+0x005E7,
+0x005E8,
+0x005E9,
+0x005EA,
+0x005EF,
+0x005F0,
+0x005F1,
+0x005F2,
+0x005F3,
+0x005F4,
+];
+
+#[cfg(any(feature="precursor", feature="renode", feature="cramium-soc"))]
+pub(crate) static GLYPH_LOCATION: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+pub(crate) const GLYPH_LEN: usize = 704;
+
+pub(crate) fn glyphs() -> &'static [u32] {
+    #[cfg(any(feature="precursor", feature="renode", feature="cramium-soc"))]
+    unsafe {
+        let data: *const u32 = core::mem::transmute(GLYPH_LOCATION.load(core::sync::atomic::Ordering::SeqCst));
+        core::slice::from_raw_parts(data, GLYPH_LEN)
+    }
+
+    #[cfg(not(target_os = "xous"))]
+    &GLYPHS
+}
+
+#[cfg(not(target_os = "xous"))]
+/// Packed 16px * 16px glyph pattern data.
+/// Pixels are packed in row-major order with LSB of first pixel word
+/// containing the top left pixel. Bit of 0 means clear, 1 means set
+pub const GLYPHS: [u32; 704] = [
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00040000, 0x0011000e,
+    0x000c0000, 0x00330000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x0003000c, 0x0003000c, 0x0003000c, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00030000, 0x00030000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x000d0000, 0x000d0001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010000, 0x00060001,
+    0x000e0004, 0x000e001f, 0x00000004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00120000, 0x00290025, 0x00000012, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00030000, 0x00040004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00020000, 0x00020001,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x000b000b, 0x00070008,
+    0x00060000, 0x00010001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00060000, 0x00010001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00360000, 0x00090009, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00420000, 0x004200a5, 0x00000024, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00020000, 0x00020005, 0x00000001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x000a0000, 0x00010006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00110000, 0x0004000e,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00040000, 0x00070004,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00020000, 0x00020001,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00040000, 0x00030004,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00240000, 0x001b0024,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00060000, 0x00060001, 0x00060008,
+    0x00030000, 0x00040004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00020000, 0x00020005, 0x00000004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00060009,
+    0x00020000, 0x00020001, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00040000, 0x00070004, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010000, 0x00060001,
+    0x00120000, 0x00150015, 0x00000009, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00060000, 0x00090009, 0x00000006, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00030000, 0x00030000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00150000, 0x00120000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00170000, 0x00100000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00170000, 0x00120002,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00030003,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00330033,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00330000, 0x000c0000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x0000000f,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x001f0000, 0x00040004,
+    0x00000000, 0x00030003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00030003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010000, 0x00100004,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000003, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00010000, 0x00010001,
+    0x00000000, 0x003f0000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x000f0000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00010000, 0x00010001, 0x00010001, 0x00010001, 0x00010001, 0x00000000,
+    0x00000000, 0x00030003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00030003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00070002, 0x00000002, 0x00020000, 0x00020007, 0x00000000,
+    0x00030003, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00030003,
+    0x00000000, 0x00000000, 0x00070000, 0x00010001, 0x00010001, 0x00010001, 0x000f0001, 0x00000000,
+    0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x001f0000, 0x00040004,
+    0x00000000, 0x00000000, 0x00210000, 0x00220021, 0x001a0024, 0x00110009, 0x00210021, 0x00000000,
+    0x00000000, 0x00000000, 0x001f0000, 0x00100010, 0x00100010, 0x00100010, 0x007f0010, 0x00000000,
+    0x00000000, 0x00000000, 0x00060000, 0x00080008, 0x00100008, 0x00140018, 0x00210022, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00100010, 0x00100010, 0x00100010, 0x00100010, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00200020, 0x00220022, 0x00220022, 0x00220022, 0x00000000,
+    0x00000000, 0x00000000, 0x00070000, 0x00040004, 0x00040004, 0x00040004, 0x00040004, 0x00000000,
+    0x00000000, 0x00000000, 0x00030000, 0x0014000c, 0x00040004, 0x00040004, 0x00040004, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00220022, 0x00220022, 0x00220022, 0x00220022, 0x00000000,
+    0x00000000, 0x00000000, 0x00310000, 0x00210029, 0x00210021, 0x00210021, 0x003f0021, 0x00000000,
+    0x00000000, 0x00000000, 0x00070000, 0x00040004, 0x00000004, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00200020, 0x00200020, 0x00200020, 0x00200020, 0x00200020,
+    0x00000000, 0x00000000, 0x003f0000, 0x00200020, 0x00200020, 0x00200020, 0x001f0020, 0x00000000,
+    0x00000000, 0x00010000, 0x001f0001, 0x00100010, 0x00100010, 0x00080008, 0x00030004, 0x00000000,
+    0x00000000, 0x00000000, 0x007f0000, 0x00440044, 0x00440044, 0x00440044, 0x007c0044, 0x00000000,
+    0x00000000, 0x00000000, 0x00390000, 0x00420046, 0x00410041, 0x00410041, 0x00710041, 0x00000000,
+    0x00000000, 0x00000000, 0x00070000, 0x00040004, 0x00040004, 0x00040004, 0x00040004, 0x00040004,
+    0x00000000, 0x00000000, 0x000e0000, 0x00080008, 0x00080008, 0x00080008, 0x000f0008, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00210021, 0x00210021, 0x00210021, 0x000f0011, 0x00000000,
+    0x00000000, 0x00000000, 0x00480000, 0x00480048, 0x00480048, 0x00480048, 0x007f0048, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00210021, 0x00270021, 0x00200020, 0x00200020, 0x00200020,
+    0x00000000, 0x00000000, 0x003f0000, 0x00210021, 0x00270021, 0x00200020, 0x003f0020, 0x00000000,
+    0x00000000, 0x00000000, 0x00110000, 0x00090011, 0x00030005, 0x00010001, 0x00010001, 0x00010001,
+    0x00000000, 0x00000000, 0x00210000, 0x00120021, 0x0008000c, 0x00200010, 0x003f0020, 0x00000000,
+    0x00000000, 0x00000000, 0x003f0000, 0x00220022, 0x00120012, 0x0002000a, 0x00020002, 0x00020002,
+    0x00000000, 0x00000000, 0x003f0000, 0x00200020, 0x00200020, 0x00200020, 0x00200020, 0x00000000,
+    0x00000000, 0x00000000, 0x00490000, 0x00490049, 0x00490049, 0x00490049, 0x007f0049, 0x00000000,
+    0x00000000, 0x00000000, 0x003e0000, 0x00220022, 0x00220022, 0x00220022, 0x00230022, 0x00000000,
+    0x000e0000, 0x00080008, 0x00770008, 0x00440044, 0x00000044, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00770000, 0x00440044, 0x00440044, 0x00440044, 0x00440044, 0x00000000,
+    0x00000000, 0x00000000, 0x00770000, 0x00440044, 0x00400044, 0x00400040, 0x00400040, 0x00000000,
+    0x00000000, 0x00000000, 0x00770000, 0x00440044, 0x00000044, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00040000, 0x00010002, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+    0x00000000, 0x00000000, 0x00240000, 0x00090012, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
+];
+
+/// Widths for proportional glyphs
+pub const WIDTHS: [u8; 88] = [
+5,
+6,
+4,
+2,
+4,
+3,
+5,
+6,
+3,
+2,
+4,
+3,
+3,
+6,
+8,
+3,
+4,
+5,
+3,
+2,
+3,
+6,
+4,
+3,
+3,
+4,
+2,
+3,
+3,
+5,
+4,
+2,
+5,
+5,
+5,
+2,
+6,
+6,
+4,
+5,
+2,
+2,
+5,
+2,
+1,
+6,
+4,
+1,
+2,
+2,
+3,
+2,
+2,
+4,
+5,
+6,
+7,
+6,
+6,
+6,
+3,
+5,
+6,
+6,
+3,
+6,
+6,
+5,
+7,
+7,
+3,
+4,
+6,
+7,
+6,
+6,
+5,
+6,
+6,
+6,
+7,
+6,
+7,
+7,
+7,
+7,
+3,
+6,
+];
+
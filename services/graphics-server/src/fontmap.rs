@@ -45,7 +45,15 @@ pub const SMALL_OFFSET: usize = 0x10dde0;
 #[cfg(not(feature = "cramium-soc"))]
 pub const SMALL_LEN: usize = 0x19e0;
 #[cfg(not(feature = "cramium-soc"))]
-pub const FONT_TOTAL_LEN: usize = 0x10f7c0;
+pub const HE_OFFSET: usize = 0x10f7c0;
+#[cfg(not(feature = "cramium-soc"))]
+pub const HE_LEN: usize = 0xb00;
+#[cfg(not(feature = "cramium-soc"))]
+pub const AR_OFFSET: usize = 0x1102c0;
+#[cfg(not(feature = "cramium-soc"))]
+pub const AR_LEN: usize = 0x3080;
+#[cfg(not(feature = "cramium-soc"))]
+pub const FONT_TOTAL_LEN: usize = 0x113340;
 
 #[cfg(feature = "cramium-soc")]
 pub const EMOJI_OFFSET: usize = 0x0;
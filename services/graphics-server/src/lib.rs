@@ -5,12 +5,16 @@ pub mod api;
 #[cfg(feature = "ditherpunk")]
 pub use api::Tile;
 pub use api::{
-    Circle, ClipObject, ClipObjectList, ClipObjectType, ClipRect, Cursor, DrawStyle, Gid, GlyphStyle, Line,
-    PixelColor, Point, Rectangle, RoundedRectangle, TextBounds, TextOp, TextView, TokenClaim,
+    Circle, ClipObject, ClipObjectList, ClipObjectType, ClipRect, Cursor, DrawStyle, EllipticArc, FillPattern,
+    Gid, GlyphStyle, Line, PixelColor, Point, Polygon, Rectangle, RoundedRectangle, Screenshot, TextBounds,
+    TextOp, TextSpan, TextView, TokenClaim, POLYGON_MAX_POINTS,
 };
 pub mod op;
 
+pub mod bidi;
 pub mod fontmap;
+#[cfg(not(target_os = "xous"))]
+pub mod snapshot;
 pub use api::ArchivedBulkRead;
 pub use api::BulkRead;
 use api::Opcode; // if you prefer to map the api into your local namespace
@@ -102,6 +106,30 @@ impl Gfx {
             .map(|_| ())
     }
 
+    /// Copy the framebuffer. The graphics server is only reachable by the GAM, so apps take screenshots
+    /// with `Gam::screenshot()`, which can refuse them.
+    pub fn screenshot(&self) -> Result<Screenshot, xous::Error> {
+        let mut buf = Buffer::into_buf(Screenshot::new()).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::Screenshot.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        buf.to_original::<Screenshot, _>().or(Err(xous::Error::InternalError))
+    }
+
+    /// Write the framebuffer to a PNG file, returning its sequence number (hosted mode only)
+    ///
+    /// See the `snapshot` module for where the file goes.
+    #[cfg(not(target_os = "xous"))]
+    pub fn snapshot(&self) -> Result<usize, xous::Error> {
+        match send_message(
+            self.conn,
+            Message::new_blocking_scalar(Opcode::Snapshot.to_usize().unwrap(), 0, 0, 0, 0),
+        ) {
+            // the sequence number is off by one, leaving 0 for failure
+            Ok(xous::Result::Scalar1(0)) => Err(xous::Error::InternalError),
+            Ok(xous::Result::Scalar1(count)) => Ok(count - 1),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+
     pub fn screen_size(&self) -> Result<Point, xous::Error> {
         let response = send_message(
             self.conn,
@@ -182,6 +210,25 @@ impl Gfx {
         buf.lend(self.conn, Opcode::DrawClipObject.to_u32().unwrap()).map(|_| ())
     }
 
+    /// Draw a line `line.style.stroke_width` pixels wide
+    pub fn draw_thick_line_clipped(&self, line: Line, clip: Rectangle) -> Result<(), xous::Error> {
+        let co = ClipObject { clip, obj: ClipObjectType::ThickLine(line) };
+        let buf = Buffer::into_buf(co).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawClipObject.to_u32().unwrap()).map(|_| ())
+    }
+
+    pub fn draw_polygon_clipped(&self, poly: Polygon, clip: Rectangle) -> Result<(), xous::Error> {
+        let co = ClipObject { clip, obj: ClipObjectType::Poly(poly) };
+        let buf = Buffer::into_buf(co).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawClipObject.to_u32().unwrap()).map(|_| ())
+    }
+
+    pub fn draw_arc_clipped(&self, arc: EllipticArc, clip: Rectangle) -> Result<(), xous::Error> {
+        let co = ClipObject { clip, obj: ClipObjectType::Arc(arc) };
+        let buf = Buffer::into_buf(co).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawClipObject.to_u32().unwrap()).map(|_| ())
+    }
+
     #[cfg(feature = "ditherpunk")]
     pub fn draw_tile_clipped(&self, tile: Tile, clip: Rectangle) -> Result<(), xous::Error> {
         let co = ClipObject { clip, obj: ClipObjectType::Tile(tile) };
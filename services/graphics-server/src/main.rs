@@ -16,6 +16,9 @@ use api::*;
 
 mod blitstr2;
 mod wordwrap;
+// visual_step() is for the IME, through the library
+#[allow(dead_code)]
+mod bidi;
 #[macro_use]
 mod style_macros;
 
@@ -39,6 +42,13 @@ use crate::wordwrap::*;
 #[cfg(feature = "gfx-testing")]
 mod testing;
 
+#[cfg(not(target_os = "xous"))]
+#[allow(dead_code)] // the golden image comparison is for tests, thru the library
+mod snapshot;
+
+#[cfg(not(target_os = "xous"))]
+mod script;
+
 fn draw_boot_logo(display: &mut XousDisplay) { display.blit_screen(&poweron::LOGO_MAP); }
 
 #[cfg(any(feature = "precursor", feature = "renode"))]
@@ -65,10 +75,14 @@ fn map_fonts() -> MemoryRange {
         "mapping regular font to 0x{:08x}",
         fontregion.as_ptr() as usize + fontmap::REGULAR_OFFSET as usize
     );
+    blitstr2::fonts::ar::GLYPH_LOCATION
+        .store((fontregion.as_ptr() as usize + fontmap::AR_OFFSET as usize) as u32, Ordering::SeqCst);
     blitstr2::fonts::bold::GLYPH_LOCATION
         .store((fontregion.as_ptr() as usize + fontmap::BOLD_OFFSET as usize) as u32, Ordering::SeqCst);
     blitstr2::fonts::emoji::GLYPH_LOCATION
         .store((fontregion.as_ptr() as usize + fontmap::EMOJI_OFFSET as usize) as u32, Ordering::SeqCst);
+    blitstr2::fonts::he::GLYPH_LOCATION
+        .store((fontregion.as_ptr() as usize + fontmap::HE_OFFSET as usize) as u32, Ordering::SeqCst);
     blitstr2::fonts::ja::GLYPH_LOCATION
         .store((fontregion.as_ptr() as usize + fontmap::JA_OFFSET as usize) as u32, Ordering::SeqCst);
     blitstr2::fonts::kr::GLYPH_LOCATION
@@ -186,6 +200,10 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
 
     let screen_clip = Rectangle::new(Point::new(0, 0), display.screen_size());
 
+    // scripted input for end-to-end UI tests, when `XOUS_SCRIPT` is set
+    #[cfg(not(target_os = "xous"))]
+    let script = script::start_from_env(xous::connect(sid).expect("couldn't connect the script"));
+
     display.redraw();
 
     // register a suspend/resume listener
@@ -239,6 +257,15 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                         ClipObjectType::Tile(tile) => {
                             op::tile(display.native_buffer(), tile, Some(obj.clip));
                         }
+                        ClipObjectType::ThickLine(line) => {
+                            op::thick_line(display.native_buffer(), line, Some(obj.clip));
+                        }
+                        ClipObjectType::Poly(poly) => {
+                            op::polygon(display.native_buffer(), poly, Some(obj.clip));
+                        }
+                        ClipObjectType::Arc(arc) => {
+                            op::elliptic_arc(display.native_buffer(), arc, Some(obj.clip));
+                        }
                     }
                 }
                 Some(Opcode::DrawClipObjectList) => {
@@ -266,6 +293,15 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                                 ClipObjectType::Tile(tile) => {
                                     op::tile(display.native_buffer(), tile, Some(obj.clip));
                                 }
+                                ClipObjectType::ThickLine(line) => {
+                                    op::thick_line(display.native_buffer(), line, Some(obj.clip));
+                                }
+                                ClipObjectType::Poly(poly) => {
+                                    op::polygon(display.native_buffer(), poly, Some(obj.clip));
+                                }
+                                ClipObjectType::Arc(arc) => {
+                                    op::elliptic_arc(display.native_buffer(), arc, Some(obj.clip));
+                                }
                             }
                         } else {
                             // stop at the first None entry -- if the sender packed the list with a hole in
@@ -320,6 +356,7 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                         tv.to_str(),
                         &typeset_extent,
                         &tv.style,
+                        &tv.spans().copied().collect::<Vec<TextSpan>>(),
                         if let Some(i) = tv.insertion { Some(i as usize) } else { None },
                     );
                     let composition = typesetter.typeset(if tv.ellipsis {
@@ -503,6 +540,10 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                     tv.overflow = Some(composition.final_overflow());
 
                     tv.bounds_computed = Some(clear_rect);
+                    #[cfg(not(target_os = "xous"))]
+                    if let (Some(screen), false) = (&script, tv.dry_run()) {
+                        screen.lock().unwrap().draw_text(tv.to_str());
+                    }
                     log::trace!("cursor ret {:?}, bounds ret {:?}", tv.cursor, tv.bounds_computed);
                     // pack our data back into the buffer to return
                     buffer.replace(tv).unwrap();
@@ -510,6 +551,12 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                 Some(Opcode::Flush) => {
                     log::trace!("***gfx flush*** redraw##");
                     display.redraw();
+                    #[cfg(not(target_os = "xous"))]
+                    display.snapshot_flush();
+                    #[cfg(not(target_os = "xous"))]
+                    if let Some(screen) = &script {
+                        screen.lock().unwrap().flush(display.frame());
+                    }
                 }
                 Some(Opcode::Clear) => {
                     let mut r = Rectangle::full_screen();
@@ -560,6 +607,26 @@ fn wrapped_main(main_thread_token: backend::MainThreadToken) -> ! {
                     display.blit_screen(&poweron::LOGO_MAP);
                     display.redraw();
                 }),
+                Some(Opcode::Screenshot) => {
+                    let mut buffer =
+                        unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                    let mut shot = Screenshot::new();
+                    shot.fb.copy_from_slice(&display.as_slice()[..shot.fb.len()]);
+                    shot.captured = true;
+                    buffer.replace(shot).unwrap();
+                }
+                #[cfg(not(target_os = "xous"))]
+                Some(Opcode::Snapshot) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                    // the sequence number of the snapshot is returned off by one, to leave 0 for failure
+                    let ret = match display.snapshot() {
+                        Ok(count) => count + 1,
+                        Err(e) => {
+                            log::warn!("failed to write snapshot: {:?}", e);
+                            0
+                        }
+                    };
+                    xous::return_scalar(msg.sender, ret).expect("couldn't return snapshot");
+                }),
                 Some(Opcode::Devboot) => msg_scalar_unpack!(msg, ena, _, _, _, {
                     if ena != 0 {
                         display.set_devboot(true);
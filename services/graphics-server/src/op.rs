@@ -1,4 +1,7 @@
-use crate::api::{Circle, DrawStyle, Line, Pixel, PixelColor, Point, Rectangle, RoundedRectangle};
+use crate::api::{
+    Circle, DrawStyle, EllipticArc, FillPattern, Line, Pixel, PixelColor, Point, Polygon, Rectangle,
+    RoundedRectangle,
+};
 
 /// LCD Frame buffer bounds
 pub const LCD_WORDS_PER_LINE: usize = 11;
@@ -540,6 +543,249 @@ pub fn rounded_rectangle(fb: &mut LcdFB, rr: RoundedRectangle, clip: Option<Rect
     quadrant(fb, Circle::new_with_style(rri.brq.tl, rr.radius, rr.border.style), Quadrant::BottomRight, clip);
 }
 
+/////////////////////////////////////////////////// polygons, arcs and thick lines
+
+/// Put a pixel if it is on the screen and within the clip rectangle
+fn plot(fb: &mut LcdFB, x: i16, y: i16, color: PixelColor, clip: Option<Rectangle>) {
+    let on_screen = x >= 0 && y >= 0 && x < WIDTH && y < HEIGHT;
+    let in_clip = match clip {
+        Some(clip) => clip.intersects_point(Point::new(x, y)),
+        None => true,
+    };
+    if on_screen && in_clip {
+        put_pixel(fb, x, y, color);
+    }
+}
+
+/// The widest stroke that is drawn: wider ones are drawn this wide, so that the brush, which is stamped at
+/// every pixel along a path, stays small
+const MAX_STROKE_WIDTH: i16 = 16;
+
+/// The offsets of the pixels of a round brush `width` pixels across, at most `MAX_STROKE_WIDTH`. Odd
+/// widths are centered on the pixel, and even widths are half a pixel down and to the right of it.
+fn brush(width: i16) -> Vec<Point> {
+    let width = width.clamp(1, MAX_STROKE_WIDTH);
+    let (lo, hi) = (-(width - 1) / 2, width / 2);
+    // in half pixels, from the center of the brush
+    let center = (lo + hi) as i32;
+    let mut offsets = Vec::new();
+    for y in lo..=hi {
+        for x in lo..=hi {
+            let (dx, dy) = (2 * x as i32 - center, 2 * y as i32 - center);
+            if dx * dx + dy * dy <= (width as i32) * (width as i32) {
+                offsets.push(Point::new(x, y));
+            }
+        }
+    }
+    offsets
+}
+
+/// The part of the segment from `a` to `b` that is on the screen or within `margin` of it, by
+/// Liang-Barsky clipping, so that the work of drawing a segment doesn't depend on how far off the screen
+/// it reaches
+fn clip_segment(a: Point, b: Point, margin: i16) -> Option<(Point, Point)> {
+    let (x0, y0) = (a.x as i64, a.y as i64);
+    let (dx, dy) = (b.x as i64 - x0, b.y as i64 - y0);
+    let (lo, right, bottom) = (-(margin as i64), (WIDTH + margin) as i64 - 1, (HEIGHT + margin) as i64 - 1);
+    // the parameters along the segment where it enters and leaves, as fractions with positive denominators
+    let (mut enter, mut leave) = ((0i64, 1i64), (1i64, 1i64));
+    for (p, q) in [(-dx, x0 - lo), (dx, right - x0), (-dy, y0 - lo), (dy, bottom - y0)] {
+        if p == 0 {
+            if q < 0 {
+                return None;
+            }
+        } else if p < 0 {
+            // entering: t >= q / p
+            let t = (-q, -p);
+            if t.0 * enter.1 > enter.0 * t.1 {
+                enter = t;
+            }
+        } else if q * leave.1 < leave.0 * p {
+            leave = (q, p);
+        }
+    }
+    if enter.0 * leave.1 > leave.0 * enter.1 {
+        return None;
+    }
+    let at = |(n, d): (i64, i64)| {
+        Point::new(
+            (x0 + (2 * dx * n + d).div_euclid(2 * d)) as i16,
+            (y0 + (2 * dy * n + d).div_euclid(2 * d)) as i16,
+        )
+    };
+    Some((at(enter), at(leave)))
+}
+
+/// Stroke the path through `points` with a round brush, so that the segments are joined without gaps
+/// and the ends are rounded. A closed path is joined from the last point back to the first.
+fn stroke_path(
+    fb: &mut LcdFB,
+    points: &[Point],
+    closed: bool,
+    width: i16,
+    color: PixelColor,
+    clip: Option<Rectangle>,
+) {
+    let brush = brush(width);
+    let mut segments: Vec<(Point, Point)> = points.windows(2).map(|w| (w[0], w[1])).collect();
+    match points {
+        [point] => segments.push((*point, *point)),
+        [first, .., last] if closed && points.len() > 2 => segments.push((*last, *first)),
+        _ => {}
+    }
+    let margin = MAX_STROKE_WIDTH / 2 + 1;
+    for (start, end) in segments.into_iter().filter_map(|(a, b)| clip_segment(a, b, margin)) {
+        let (mut x0, mut y0, x1, y1) = (start.x, start.y, end.x, end.y);
+        // Bresenham, as in `line()`
+        let dx = (x1 - x0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let dy = -((y1 - y0).abs());
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            for offset in brush.iter() {
+                plot(fb, x0 + offset.x, y0 + offset.y, color, clip);
+            }
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+}
+
+/// Fill the inside of the polygon through `points`, by the even-odd rule. Each row is filled between
+/// pairs of the edges that cross it, where an edge includes its top end but not its bottom end, so that
+/// a vertex shared by two edges is counted once.
+fn fill_polygon(
+    fb: &mut LcdFB,
+    points: &[Point],
+    color: PixelColor,
+    pattern: FillPattern,
+    clip: Option<Rectangle>,
+) {
+    if points.len() < 3 {
+        return;
+    }
+    let other = if color == PixelColor::Dark { PixelColor::Light } else { PixelColor::Dark };
+    let top = points.iter().map(|p| p.y).min().unwrap().max(0);
+    let bottom = points.iter().map(|p| p.y).max().unwrap().min(HEIGHT - 1);
+    let mut crossings: Vec<i16> = Vec::with_capacity(points.len());
+    for y in top..=bottom {
+        crossings.clear();
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            if (a.y <= y) != (b.y <= y) {
+                let (num, den) =
+                    ((y as i64 - a.y as i64) * (b.x as i64 - a.x as i64), b.y as i64 - a.y as i64);
+                // between a.x and b.x, so it fits
+                crossings.push((a.x as i64 + rounded_div(num, den)) as i16);
+            }
+        }
+        crossings.sort_unstable();
+        for span in crossings.chunks_exact(2) {
+            for x in span[0].max(0)..=span[1].min(WIDTH - 1) {
+                plot(fb, x, y, if pattern.is_set(x, y) { color } else { other }, clip);
+            }
+        }
+    }
+}
+
+/// `num / den`, rounded to the nearest whole number
+fn rounded_div(num: i64, den: i64) -> i64 {
+    let (num, den) = if den < 0 { (-num, -den) } else { (num, den) };
+    (2 * num + den).div_euclid(2 * den)
+}
+
+/// sin of 0 to 90 degrees, in units of 1/16384
+const SINE_TABLE: [i32; 91] = [
+    0, 286, 572, 857, 1143, 1428, 1713, 1997, 2280, 2563, 2845, 3126, 3406, 3686, 3964, 4240, 4516, 4790,
+    5063, 5334, 5604, 5872, 6138, 6402, 6664, 6924, 7182, 7438, 7692, 7943, 8192, 8438, 8682, 8923, 9162,
+    9397, 9630, 9860, 10087, 10311, 10531, 10749, 10963, 11174, 11381, 11585, 11786, 11982, 12176, 12365,
+    12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044, 14189, 14330, 14466, 14598, 14726,
+    14849, 14968, 15082, 15191, 15296, 15396, 15491, 15582, 15668, 15749, 15826, 15897, 15964, 16026, 16083,
+    16135, 16182, 16225, 16262, 16294, 16322, 16344, 16362, 16374, 16382, 16384,
+];
+const SINE_ONE: i32 = 16384;
+
+/// sin of an angle in tenths of a degree, in units of 1/16384, interpolated from `SINE_TABLE`
+fn sine(decidegrees: i32) -> i32 {
+    let quarter = |a: i32| -> i32 {
+        let (degrees, tenths) = ((a / 10) as usize, a % 10);
+        if degrees >= 90 {
+            return SINE_ONE;
+        }
+        SINE_TABLE[degrees] + (SINE_TABLE[degrees + 1] - SINE_TABLE[degrees]) * tenths / 10
+    };
+    let a = decidegrees.rem_euclid(3600);
+    match a / 900 {
+        0 => quarter(a),
+        1 => quarter(1800 - a),
+        2 => -quarter(a - 1800),
+        _ => -quarter(3600 - a),
+    }
+}
+
+/// The points along an arc, close enough together to be joined with straight lines
+fn arc_points(arc: &EllipticArc) -> Vec<Point> {
+    let (rx, ry) = ((arc.radii.x as i32).abs(), (arc.radii.y as i32).abs());
+    let sweep = (arc.sweep as i32 * 10).clamp(-3600, 3600);
+    // about a pixel apart along the widest part of the ellipse: 1/r radians is 573/r tenths of a degree
+    let step = (573 / rx.max(ry).max(1)).max(1);
+    let steps = (sweep.abs() + step - 1) / step;
+    let start = arc.start as i32 * 10;
+    (0..=steps)
+        .map(|i| {
+            let a = start + sweep * i / steps.max(1);
+            let x = arc.center.x as i64 + rounded_div((rx * sine(a + 900)).into(), SINE_ONE.into());
+            let y = arc.center.y as i64 + rounded_div((ry * sine(a)).into(), SINE_ONE.into());
+            let coord = |v: i64| v.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+            Point::new(coord(x), coord(y))
+        })
+        .collect()
+}
+
+pub fn thick_line(fb: &mut LcdFB, l: Line, clip: Option<Rectangle>) {
+    if let Some(color) = l.style.stroke_color {
+        stroke_path(fb, &[l.start, l.end], false, l.style.stroke_width, color, clip);
+    }
+}
+
+pub fn polygon(fb: &mut LcdFB, poly: Polygon, clip: Option<Rectangle>) {
+    if let Some(fill) = poly.style.fill_color {
+        fill_polygon(fb, poly.points(), fill, poly.pattern, clip);
+    }
+    if let Some(stroke) = poly.style.stroke_color {
+        stroke_path(fb, poly.points(), true, poly.style.stroke_width, stroke, clip);
+    }
+}
+
+pub fn elliptic_arc(fb: &mut LcdFB, arc: EllipticArc, clip: Option<Rectangle>) {
+    let points = arc_points(&arc);
+    let whole = arc.sweep.abs() >= 360;
+    if let Some(fill) = arc.style.fill_color {
+        if whole {
+            fill_polygon(fb, &points, fill, arc.pattern, clip);
+        } else {
+            // the sector: the arc, and back through the center
+            let mut sector = points.clone();
+            sector.push(arc.center);
+            fill_polygon(fb, &sector, fill, arc.pattern, clip);
+        }
+    }
+    if let Some(stroke) = arc.style.stroke_color {
+        stroke_path(fb, &points, whole, arc.style.stroke_width, stroke, clip);
+    }
+}
+
 /*
  * Each line of the LCD Frame Buffer and the Tile is represented by a whole
  * number of u32 words, packed with bits, from the left. The right most word
@@ -639,3 +885,125 @@ pub fn tile(fb: &mut LcdFB, tile: Tile, clip: Option<Rectangle>) {
         fb[fb_ln + (FB_WORDS_PER_LINE - 1)] |= 0x1_0000;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::POLYGON_MAX_POINTS;
+
+    fn light_fb() -> LcdFB { [0xFFFF_FFFF; LCD_FRAME_BUF_SIZE] }
+
+    fn is_dark(fb: &LcdFB, x: usize, y: usize) -> bool {
+        fb[y * LCD_WORDS_PER_LINE + x / 32] & (1 << (x % 32)) == 0
+    }
+
+    fn fill(color: PixelColor) -> DrawStyle {
+        DrawStyle { fill_color: Some(color), stroke_color: None, stroke_width: 1 }
+    }
+
+    #[test]
+    fn sines() {
+        assert_eq!(sine(0), 0);
+        assert_eq!(sine(300), SINE_ONE / 2);
+        assert_eq!(sine(900), SINE_ONE);
+        assert_eq!(sine(2700), -SINE_ONE);
+        assert_eq!(sine(-900), -SINE_ONE);
+        assert_eq!(sine(1505), sine(295));
+    }
+
+    #[test]
+    fn polygon_fill() {
+        let mut fb = light_fb();
+        let points = [Point::new(10, 10), Point::new(30, 10), Point::new(10, 30)];
+        polygon(&mut fb, Polygon::from_points(&points, fill(PixelColor::Dark)).unwrap(), None);
+        assert!(is_dark(&fb, 10, 10) && is_dark(&fb, 12, 25) && is_dark(&fb, 25, 12));
+        assert!(!is_dark(&fb, 25, 25) && !is_dark(&fb, 9, 15) && !is_dark(&fb, 15, 9));
+
+        // a patterned fill is opaque, and within the clip rectangle only
+        let mut square = Polygon::new(fill(PixelColor::Dark));
+        for (x, y) in [(50, 50), (60, 50), (60, 60), (50, 60)] {
+            square.push(Point::new(x, y)).unwrap();
+        }
+        square.pattern = FillPattern::Checker;
+        let mut fb = [0; LCD_FRAME_BUF_SIZE];
+        polygon(&mut fb, square, Some(Rectangle::new_coords(0, 0, 55, 100)));
+        assert!(is_dark(&fb, 52, 52) && !is_dark(&fb, 52, 53) && !is_dark(&fb, 53, 52));
+        assert!(is_dark(&fb, 57, 53), "outside of the clip rectangle");
+        assert!(
+            Polygon::from_points(&[Point::zero(); POLYGON_MAX_POINTS + 1], fill(PixelColor::Dark)).is_none()
+        );
+    }
+
+    #[test]
+    fn thick_lines() {
+        let mut fb = light_fb();
+        let mut line = Line::new(Point::new(10, 20), Point::new(40, 20));
+        line.style.stroke_width = 3;
+        thick_line(&mut fb, line, None);
+        assert!((19..=21).all(|y| is_dark(&fb, 25, y)));
+        assert!(!is_dark(&fb, 25, 18) && !is_dark(&fb, 25, 22));
+        // the ends are rounded: the corners of the brush are left out only when it is wider
+        line.style.stroke_width = 5;
+        let mut fb = light_fb();
+        thick_line(&mut fb, line, None);
+        assert!(is_dark(&fb, 8, 20) && !is_dark(&fb, 8, 18));
+
+        // wide strokes are drawn MAX_STROKE_WIDTH wide
+        line.style.stroke_width = i16::MAX;
+        let mut fb = light_fb();
+        thick_line(&mut fb, line, None);
+        assert!(is_dark(&fb, 25, 20 - MAX_STROKE_WIDTH as usize / 2 + 1) && !is_dark(&fb, 25, 10));
+    }
+
+    #[test]
+    fn far_off_screen() {
+        // only the part of a segment near the screen is drawn, at the slope of the whole segment
+        let mut fb = light_fb();
+        let mut line = Line::new(Point::new(i16::MIN, 100), Point::new(i16::MAX, 100));
+        line.style.stroke_width = 1;
+        thick_line(&mut fb, line, None);
+        assert!(is_dark(&fb, 0, 100) && is_dark(&fb, WIDTH as usize - 1, 100) && !is_dark(&fb, 10, 101));
+        let mut fb = light_fb();
+        thick_line(&mut fb, Line::new(Point::new(-1000, -1000), Point::new(1000, 1000)), None);
+        assert!(is_dark(&fb, 0, 0) && is_dark(&fb, 200, 200) && !is_dark(&fb, 200, 210));
+        let clipped = clip_segment(Point::new(-2000, 0), Point::new(-1000, 500), 9);
+        assert!(clipped.is_none());
+        assert_eq!(
+            clip_segment(Point::new(5, 6), Point::new(7, 8), 0),
+            Some((Point::new(5, 6), Point::new(7, 8)))
+        );
+
+        // the extremes of the coordinates don't overflow
+        let corners =
+            [Point::new(i16::MIN, i16::MIN), Point::new(i16::MAX, i16::MIN), Point::new(0, i16::MAX)];
+        let mut fb = light_fb();
+        polygon(&mut fb, Polygon::from_points(&corners, fill(PixelColor::Dark)).unwrap(), None);
+        assert!(is_dark(&fb, 100, 100));
+        let radii = Point::new(i16::MIN, i16::MIN);
+        elliptic_arc(&mut fb, EllipticArc::new(Point::new(i16::MAX, 0), radii, 0, 360), None);
+    }
+
+    #[test]
+    fn arcs() {
+        let center = Point::new(100, 100);
+        let mut fb = light_fb();
+        elliptic_arc(&mut fb, EllipticArc::new(center, Point::new(20, 20), 0, 90), None);
+        // clockwise on the screen, from 3 o'clock to 6 o'clock
+        assert!(is_dark(&fb, 120, 100) && is_dark(&fb, 100, 120) && is_dark(&fb, 114, 114));
+        assert!(!is_dark(&fb, 80, 100) && !is_dark(&fb, 100, 80) && !is_dark(&fb, 110, 110));
+
+        // the fill of an arc is its sector
+        let mut fb = light_fb();
+        let arc = EllipticArc::new_with_style(center, Point::new(30, 20), 0, 90, fill(PixelColor::Dark));
+        elliptic_arc(&mut fb, arc, None);
+        assert!(is_dark(&fb, 110, 105) && is_dark(&fb, 125, 101));
+        assert!(!is_dark(&fb, 95, 95) && !is_dark(&fb, 105, 95) && !is_dark(&fb, 125, 115));
+
+        // and the fill of a whole ellipse is all of it
+        let mut fb = light_fb();
+        let arc = EllipticArc::new_with_style(center, Point::new(30, 20), 45, 360, fill(PixelColor::Dark));
+        elliptic_arc(&mut fb, arc, None);
+        assert!(is_dark(&fb, 75, 100) && is_dark(&fb, 100, 85) && is_dark(&fb, 100, 100));
+        assert!(!is_dark(&fb, 69, 100) && !is_dark(&fb, 100, 79));
+    }
+}
@@ -0,0 +1,304 @@
+//! Scripted input for hosted mode, to run end-to-end UI tests without hardware.
+//!
+//! When `XOUS_SCRIPT` names a scenario script, the graphics server runs it on a thread of its own: keys
+//! are injected into the keyboard server as if typed in the window, and the script waits on text drawn
+//! to the screen and checks regions of the framebuffer against golden images. At the end of the script,
+//! or at the first step that fails, `pass` or `fail: <reason>` is written to `result` in the snapshot
+//! directory (see `snapshot`), and the hosted system is shut down.
+//!
+//! A script has one step per line; blank lines and lines starting with `#` are skipped.
+//!
+//! ```text
+//! timeout <ms>                  how long a `wait` may take (default 10000)
+//! wait <text>                   wait for a TextView holding <text> to be drawn and flushed
+//! type <text>                   type the characters of <text>
+//! key <name> [<name>...]        press keys: enter, space, backspace, up, down, left, right,
+//!                               home (the menu key), f1, f2, f3 or f4
+//! sleep <ms>                    pause
+//! snapshot                      write a PNG snapshot of the screen
+//! expect <x0> <y0> <x1> <y1> <golden.png>
+//!                               compare the screen from (x0, y0) to (x1, y1) with a golden image
+//! ```
+//!
+//! Only text drawn since the last key was injected is seen by `wait`, so that a `wait` after a `key`
+//! sees the response to it. Paths of golden images are relative to the script. Running with
+//! `XOUS_SCRIPT_RECORD` set writes the screen to the golden images instead of comparing them.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use num_traits::ToPrimitive;
+
+use crate::api::{Opcode, Point, Rectangle};
+use crate::snapshot::{snapshot_dir, Frame};
+
+/// The default for how long a `wait` may take, in milliseconds
+const TIMEOUT_MS: u64 = 10_000;
+/// How often a `wait` looks at the text on the screen
+const POLL_MS: u64 = 50;
+/// The most text kept for `wait`, as a safeguard against a script that never injects a key
+const TEXT_MAX: usize = 256;
+
+/// What the script can see of the screen, as of the last `Flush`
+#[derive(Debug, Default)]
+pub struct Screen {
+    frame: Option<Frame>,
+    /// the text of the TextViews drawn since the last `Flush`
+    drawn: Vec<String>,
+    /// the text of the TextViews flushed since the last key was injected
+    shown: Vec<String>,
+}
+
+impl Screen {
+    pub fn draw_text(&mut self, text: &str) { self.drawn.push(text.to_string()); }
+
+    pub fn flush(&mut self, frame: Frame) {
+        self.shown.append(&mut self.drawn);
+        if self.shown.len() > TEXT_MAX {
+            self.shown.drain(..self.shown.len() - TEXT_MAX);
+        }
+        self.frame = Some(frame);
+    }
+
+    fn shows(&self, text: &str) -> bool { self.shown.iter().any(|t| t.contains(text)) }
+}
+
+#[derive(Debug)]
+enum Step {
+    Timeout(u64),
+    Wait(String),
+    Type(String),
+    Key(Vec<char>),
+    Sleep(u64),
+    Snapshot,
+    Expect(Rectangle, PathBuf),
+}
+
+fn key(name: &str) -> Option<char> {
+    Some(match name {
+        "enter" => '\u{000d}',
+        "space" => ' ',
+        "backspace" => '\u{0008}',
+        "up" => '↑',
+        "down" => '↓',
+        "left" => '←',
+        "right" => '→',
+        "home" => '∴',
+        "f1" => '\u{0011}',
+        "f2" => '\u{0012}',
+        "f3" => '\u{0013}',
+        "f4" => '\u{0014}',
+        _ => return None,
+    })
+}
+
+fn number<T: std::str::FromStr>(arg: &str) -> Result<T, String> {
+    arg.parse().map_err(|_| format!("{:?} is not a number", arg))
+}
+
+/// Parse one line of a script
+fn parse_step(line: &str, dir: &Path) -> Result<Step, String> {
+    let (command, arg) = line.split_once(' ').map_or((line, ""), |(c, a)| (c, a.trim()));
+    Ok(match command {
+        "timeout" => Step::Timeout(number(arg)?),
+        "wait" if !arg.is_empty() => Step::Wait(arg.to_string()),
+        "type" if !arg.is_empty() => Step::Type(arg.to_string()),
+        "key" if !arg.is_empty() => Step::Key(
+            arg.split_whitespace()
+                .map(|name| key(name).ok_or(format!("unknown key {:?}", name)))
+                .collect::<Result<_, _>>()?,
+        ),
+        "sleep" => Step::Sleep(number(arg)?),
+        "snapshot" if arg.is_empty() => Step::Snapshot,
+        "expect" => {
+            let args: Vec<&str> = arg.split_whitespace().collect();
+            if args.len() != 5 {
+                return Err("usage: expect <x0> <y0> <x1> <y1> <golden.png>".to_string());
+            }
+            let tl = Point::new(number(args[0])?, number(args[1])?);
+            let br = Point::new(number(args[2])?, number(args[3])?);
+            Step::Expect(Rectangle::new(tl, br), dir.join(args[4]))
+        }
+        _ => return Err(format!("can't parse {:?}", line)),
+    })
+}
+
+/// Parse a script into its steps, numbered by line; `dir` is where golden images are found
+fn parse(script: &str, dir: &Path) -> Result<Vec<(usize, Step)>, String> {
+    let mut steps = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        steps.push((i + 1, parse_step(line, dir).map_err(|e| format!("line {}: {}", i + 1, e))?));
+    }
+    Ok(steps)
+}
+
+struct Runner {
+    screen: Arc<Mutex<Screen>>,
+    kbd: keyboard::Keyboard,
+    gfx_cid: xous::CID,
+    dir: PathBuf,
+    timeout: u64,
+    record: bool,
+}
+
+impl Runner {
+    fn inject(&self, keys: &[char]) {
+        self.screen.lock().unwrap().shown.clear();
+        for &k in keys {
+            self.kbd.hostmode_inject_key(k);
+        }
+    }
+
+    fn step(&mut self, step: &Step) -> Result<(), String> {
+        match step {
+            Step::Timeout(ms) => self.timeout = *ms,
+            Step::Wait(text) => {
+                let start = Instant::now();
+                while !self.screen.lock().unwrap().shows(text) {
+                    if start.elapsed() > Duration::from_millis(self.timeout) {
+                        return Err(format!("timed out waiting for {:?}", text));
+                    }
+                    std::thread::sleep(Duration::from_millis(POLL_MS));
+                }
+            }
+            Step::Type(text) => self.inject(&text.chars().collect::<Vec<char>>()),
+            Step::Key(keys) => self.inject(keys),
+            Step::Sleep(ms) => std::thread::sleep(Duration::from_millis(*ms)),
+            Step::Snapshot => {
+                // through the server, so the snapshot is numbered in order with any others; the
+                // sequence number is returned off by one, leaving 0 for failure
+                match xous::send_message(
+                    self.gfx_cid,
+                    xous::Message::new_blocking_scalar(Opcode::Snapshot.to_usize().unwrap(), 0, 0, 0, 0),
+                ) {
+                    Ok(xous::Result::Scalar1(count)) if count > 0 => {}
+                    _ => return Err("snapshot failed".to_string()),
+                }
+            }
+            Step::Expect(area, golden) => {
+                let frame = self.screen.lock().unwrap().frame.clone().ok_or("nothing drawn yet")?;
+                if self.record {
+                    frame.write_png(golden).map_err(|e| format!("can't write {:?}: {:?}", golden, e))?;
+                    log::info!("recorded {:?}", golden);
+                    return Ok(());
+                }
+                let diff = frame.diff_within(
+                    &Frame::read_png(golden).map_err(|e| format!("can't read {:?}: {:?}", golden, e))?,
+                    area,
+                );
+                if !diff.is_empty() {
+                    let name = golden.file_stem().map_or("expect".into(), |s| s.to_string_lossy());
+                    frame.write_png(&self.dir.join(format!("{}-actual.png", name))).ok();
+                    diff.frame.write_png(&self.dir.join(format!("{}-diff.png", name))).ok();
+                    return Err(format!(
+                        "{} pixels differ from {:?}, within {:?}",
+                        diff.pixels, golden, diff.bounds
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the result of the script, then shut down the hosted system
+fn finish(dir: &Path, result: Result<(), String>) -> ! {
+    let text = match &result {
+        Ok(()) => {
+            log::info!("script passed");
+            "pass".to_string()
+        }
+        Err(e) => {
+            log::error!("script failed: {}", e);
+            format!("fail: {}", e)
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(dir.join("result"), text)) {
+        log::error!("can't write the result of the script: {:?}", e);
+    }
+    xous::rsyscall(xous::SysCall::Shutdown).expect("couldn't shut down");
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Start the script named by `XOUS_SCRIPT`, if any, returning the Screen it watches
+pub fn start_from_env(gfx_cid: xous::CID) -> Option<Arc<Mutex<Screen>>> {
+    let path = PathBuf::from(std::env::var_os("XOUS_SCRIPT")?);
+    let screen = Arc::new(Mutex::new(Screen::default()));
+    let thread_screen = screen.clone();
+    std::thread::spawn(move || {
+        let dir = snapshot_dir();
+        let script = std::fs::read_to_string(&path).map_err(|e| format!("can't read {:?}: {:?}", path, e));
+        let steps = match script.and_then(|s| parse(&s, path.parent().unwrap_or(Path::new(".")))) {
+            Ok(steps) => steps,
+            Err(e) => finish(&dir, Err(e)),
+        };
+        log::info!("running script {:?}", path);
+        let xns = xous_names::XousNames::new().unwrap();
+        let mut runner = Runner {
+            screen: thread_screen,
+            kbd: keyboard::Keyboard::new(&xns).expect("can't connect to KBD for scripted input"),
+            gfx_cid,
+            dir: dir.clone(),
+            timeout: TIMEOUT_MS,
+            record: std::env::var_os("XOUS_SCRIPT_RECORD").is_some(),
+        };
+        for (line, step) in steps.iter() {
+            log::info!("script line {}: {:?}", line, step);
+            if let Err(e) = runner.step(step) {
+                finish(&dir, Err(format!("line {}: {}", line, e)));
+            }
+        }
+        finish(&dir, Ok(()))
+    });
+    Some(screen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_steps() {
+        let script = "# unlock\n\ntimeout 30000\nwait Enter the 'unlock' PIN\ntype a b\nkey enter down\n\
+                      sleep 500\nsnapshot\nexpect 0 0 335 40 golden/status.png\n";
+        let steps = parse(script, Path::new("scenarios")).unwrap();
+        assert_eq!(steps.iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![3, 4, 5, 6, 7, 8, 9]);
+        assert!(matches!(steps[0].1, Step::Timeout(30000)));
+        assert!(matches!(&steps[1].1, Step::Wait(text) if text == "Enter the 'unlock' PIN"));
+        assert!(matches!(&steps[2].1, Step::Type(text) if text == "a b"));
+        assert!(matches!(&steps[3].1, Step::Key(keys) if keys == &['\u{000d}', '↓']));
+        assert!(matches!(steps[4].1, Step::Sleep(500)));
+        assert!(matches!(steps[5].1, Step::Snapshot));
+        match &steps[6].1 {
+            Step::Expect(area, golden) => {
+                assert_eq!((area.tl(), area.br()), (Point::new(0, 0), Point::new(335, 40)));
+                assert_eq!(golden, &PathBuf::from("scenarios/golden/status.png"));
+            }
+            step => panic!("unexpected {:?}", step),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("key enter escape", Path::new(".")).unwrap_err(), "line 1: unknown key \"escape\"");
+        assert!(parse("wait", Path::new(".")).is_err());
+        assert!(parse("sleep soon", Path::new(".")).is_err());
+        assert!(parse("expect 0 0 10 golden.png", Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn screen_text() {
+        let mut screen = Screen::default();
+        screen.draw_text("Enter the 'unlock' PIN code.");
+        assert!(!screen.shows("unlock"));
+        screen.flush(Frame::from_fb(&[0; 11 * 536], 11));
+        assert!(screen.shows("unlock"));
+    }
+}
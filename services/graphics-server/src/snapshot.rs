@@ -0,0 +1,240 @@
+//! Snapshots of the hosted framebuffer as PNG files, and their comparison with golden images.
+//!
+//! A hosted build writes a snapshot on request (`Gfx::snapshot()`), or on every `Flush` that changed the
+//! screen when `XOUS_SNAPSHOT_FLUSH` is set. Snapshots are numbered in order, as `00000.png` etc, in
+//! the directory named by `XOUS_SNAPSHOT_DIR` (default `target/snapshots`). A test then compares a
+//! snapshot with its golden image using `compare()`.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::api::{Point, Rectangle, LINES, WIDTH};
+
+const WIDTH_PX: usize = WIDTH as usize;
+const LINES_PX: usize = LINES as usize;
+
+/// The default directory of the snapshots, relative to where the hosted build is run
+const SNAPSHOT_DIR: &str = "target/snapshots";
+
+/// The directory of the snapshots, set by `XOUS_SNAPSHOT_DIR`
+pub fn snapshot_dir() -> PathBuf {
+    std::env::var_os("XOUS_SNAPSHOT_DIR").map_or(PathBuf::from(SNAPSHOT_DIR), PathBuf::from)
+}
+
+fn png_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// A monochrome screen image, with `true` for a light pixel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pixels: Vec<bool>,
+}
+
+impl Frame {
+    /// Copy a framebuffer of `words_per_line` 32-bit words per line, where a set bit is a light pixel
+    pub fn from_fb(fb: &[u32], words_per_line: usize) -> Self {
+        let mut pixels = Vec::with_capacity(WIDTH_PX * LINES_PX);
+        for line in fb.chunks(words_per_line).take(LINES_PX) {
+            for x in 0..WIDTH_PX {
+                pixels.push(line[x / 32] & (1 << (x % 32)) != 0);
+            }
+        }
+        Frame { pixels }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool { self.pixels[y * WIDTH_PX + x] }
+
+    /// Read a PNG of the size of the screen, in any color type; a pixel is light from mid-grey up
+    pub fn read_png(path: &Path) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        // bit depths below 8 and palettes are expanded to 8 bit samples
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(png_error)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(png_error)?;
+        if info.width as usize != WIDTH_PX || info.height as usize != LINES_PX {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is {}x{}, not {}x{}", path, info.width, info.height, WIDTH_PX, LINES_PX),
+            ));
+        }
+        let sample_bytes = if info.bit_depth == png::BitDepth::Sixteen { 2 } else { 1 };
+        let pixel_bytes = info.color_type.samples() * sample_bytes;
+        // grey, or the mean of red, green and blue, with any alpha ignored
+        let colors = if info.color_type.samples() >= 3 { 3 } else { 1 };
+        let mut pixels = Vec::with_capacity(WIDTH_PX * LINES_PX);
+        for line in buf.chunks(info.line_size).take(LINES_PX) {
+            for pixel in line.chunks(pixel_bytes).take(WIDTH_PX) {
+                let sum: usize = (0..colors).map(|c| pixel[c * sample_bytes] as usize).sum();
+                pixels.push(sum / colors >= 0x80);
+            }
+        }
+        Ok(Frame { pixels })
+    }
+
+    /// Write a 1 bit greyscale PNG
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(File::create(path)?), WIDTH_PX as u32, LINES_PX as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        let line_bytes = (WIDTH_PX + 7) / 8;
+        let mut data = vec![0u8; line_bytes * LINES_PX];
+        for (i, _) in self.pixels.iter().enumerate().filter(|(_, light)| **light) {
+            let (x, y) = (i % WIDTH_PX, i / WIDTH_PX);
+            data[y * line_bytes + x / 8] |= 0x80 >> (x % 8);
+        }
+        writer.write_image_data(&data).map_err(png_error)
+    }
+
+    /// Compare with another Frame, pixel by pixel
+    pub fn diff(&self, other: &Frame) -> Diff { self.diff_within(other, &Rectangle::full_screen()) }
+
+    /// Compare with another Frame, pixel by pixel, within `area` (inclusive of its edges)
+    pub fn diff_within(&self, other: &Frame, area: &Rectangle) -> Diff {
+        let mut diff =
+            Diff { pixels: 0, bounds: None, frame: Frame { pixels: vec![true; self.pixels.len()] } };
+        let (mut tl, mut br) = (Point::new(i16::MAX, i16::MAX), Point::new(i16::MIN, i16::MIN));
+        for (i, (a, b)) in self.pixels.iter().zip(other.pixels.iter()).enumerate() {
+            let (x, y) = ((i % WIDTH_PX) as i16, (i / WIDTH_PX) as i16);
+            if x < area.tl().x || x > area.br().x || y < area.tl().y || y > area.br().y {
+                continue;
+            }
+            if a != b {
+                tl = Point::new(tl.x.min(x), tl.y.min(y));
+                br = Point::new(br.x.max(x), br.y.max(y));
+                diff.pixels += 1;
+                diff.frame.pixels[i] = false;
+            }
+        }
+        if diff.pixels > 0 {
+            diff.bounds = Some(Rectangle::new(tl, br));
+        }
+        diff
+    }
+}
+
+/// The differences between two Frames
+#[derive(Debug)]
+pub struct Diff {
+    /// the number of pixels that differ
+    pub pixels: usize,
+    /// the smallest Rectangle holding every pixel that differs
+    pub bounds: Option<Rectangle>,
+    /// the pixels that differ, in dark on light, to be written out for inspection
+    pub frame: Frame,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool { self.pixels == 0 }
+}
+
+/// Compare a snapshot with its golden image
+///
+/// A test would typically assert `compare(snapshot, golden)?.is_empty()`, and write out `frame` of
+/// the Diff otherwise.
+pub fn compare(snapshot: &Path, golden: &Path) -> io::Result<Diff> {
+    Ok(Frame::read_png(snapshot)?.diff(&Frame::read_png(golden)?))
+}
+
+/// Writes the snapshots of a hosted framebuffer
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    /// write a snapshot on every `Flush` that changed the screen
+    every_flush: bool,
+    count: usize,
+    last: Option<Frame>,
+}
+
+impl Recorder {
+    /// A Recorder set up by `XOUS_SNAPSHOT_DIR` and `XOUS_SNAPSHOT_FLUSH`
+    pub fn from_env() -> Self {
+        let every_flush = std::env::var_os("XOUS_SNAPSHOT_FLUSH").is_some();
+        Recorder { dir: snapshot_dir(), every_flush, count: 0, last: None }
+    }
+
+    /// Write a snapshot, returning its sequence number
+    pub fn snapshot(&mut self, frame: Frame) -> io::Result<usize> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{:05}.png", self.count));
+        frame.write_png(&path)?;
+        log::info!("snapshot {:?}", path);
+        self.last = Some(frame);
+        self.count += 1;
+        Ok(self.count - 1)
+    }
+
+    pub fn every_flush(&self) -> bool { self.every_flush }
+
+    /// Write a snapshot after a `Flush`, if the screen has changed since the last one
+    pub fn flush(&mut self, frame: Frame) {
+        if self.last.as_ref() != Some(&frame) {
+            if let Err(e) = self.snapshot(frame) {
+                log::warn!("failed to write snapshot: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDS: usize = 11;
+
+    fn test_fb() -> Vec<u32> {
+        // light, with a dark square from (10, 20) to (49, 59)
+        let mut fb = vec![0xffff_ffff; WORDS * LINES_PX];
+        for y in 20..60 {
+            for x in 10..50 {
+                fb[y * WORDS + x / 32] &= !(1 << (x % 32));
+            }
+        }
+        fb
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let frame = Frame::from_fb(&test_fb(), WORDS);
+        assert!(frame.pixel(0, 0));
+        assert!(!frame.pixel(10, 20));
+        let path = std::env::temp_dir().join(format!("gfx-snapshot-{}.png", std::process::id()));
+        frame.write_png(&path).unwrap();
+        let read = Frame::read_png(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(frame, read);
+        assert!(frame.diff(&read).is_empty());
+    }
+
+    #[test]
+    fn diff_bounds() {
+        let frame = Frame::from_fb(&test_fb(), WORDS);
+        let mut fb = test_fb();
+        fb[100 * WORDS + 3] &= !(1 << 4); // (100, 100)
+        fb[110 * WORDS + 3] &= !(1 << 9); // (105, 110)
+        let diff = frame.diff(&Frame::from_fb(&fb, WORDS));
+        assert_eq!(diff.pixels, 2);
+        let bounds = diff.bounds.unwrap();
+        assert_eq!((bounds.tl(), bounds.br()), (Point::new(100, 100), Point::new(105, 110)));
+        assert!(!diff.frame.pixel(105, 110));
+        assert!(diff.frame.pixel(10, 20));
+    }
+
+    #[test]
+    fn diff_within_area() {
+        let frame = Frame::from_fb(&test_fb(), WORDS);
+        let mut fb = test_fb();
+        fb[100 * WORDS + 3] &= !(1 << 4); // (100, 100)
+        fb[300 * WORDS + 3] &= !(1 << 4); // (100, 300)
+        let other = Frame::from_fb(&fb, WORDS);
+        let area = Rectangle::new(Point::new(0, 0), Point::new(WIDTH - 1, 200));
+        let diff = frame.diff_within(&other, &area);
+        assert_eq!(diff.pixels, 1);
+        assert_eq!(diff.bounds.unwrap().tl(), Point::new(100, 100));
+        assert!(frame.diff_within(&other, &Rectangle::new(Point::new(0, 0), Point::new(99, 99))).is_empty());
+    }
+}
@@ -41,9 +41,15 @@ macro_rules! zh_rules {
                         Ok(g) => g,
                         _ => match kr_glyph($ch) {
                             Ok(g) => g,
-                            _ => match $base_style(REPLACEMENT) {
+                            _ => match he_glyph($ch) {
                                 Ok(g) => g,
-                                _ => NULL_GLYPH_SPRITE,
+                                _ => match ar_glyph($ch) {
+                                    Ok(g) => g,
+                                    _ => match $base_style(REPLACEMENT) {
+                                        Ok(g) => g,
+                                        _ => NULL_GLYPH_SPRITE,
+                                    },
+                                },
                             },
                         },
                     },
@@ -65,9 +71,15 @@ macro_rules! jp_rules {
                         Ok(g) => g,
                         _ => match kr_glyph($ch) {
                             Ok(g) => g,
-                            _ => match $base_style(REPLACEMENT) {
+                            _ => match he_glyph($ch) {
                                 Ok(g) => g,
-                                _ => NULL_GLYPH_SPRITE,
+                                _ => match ar_glyph($ch) {
+                                    Ok(g) => g,
+                                    _ => match $base_style(REPLACEMENT) {
+                                        Ok(g) => g,
+                                        _ => NULL_GLYPH_SPRITE,
+                                    },
+                                },
                             },
                         },
                     },
@@ -89,9 +101,15 @@ macro_rules! kr_rules {
                         Ok(g) => g,
                         _ => match zh_glyph($ch) {
                             Ok(g) => g,
-                            _ => match $base_style(REPLACEMENT) {
+                            _ => match he_glyph($ch) {
                                 Ok(g) => g,
-                                _ => NULL_GLYPH_SPRITE,
+                                _ => match ar_glyph($ch) {
+                                    Ok(g) => g,
+                                    _ => match $base_style(REPLACEMENT) {
+                                        Ok(g) => g,
+                                        _ => NULL_GLYPH_SPRITE,
+                                    },
+                                },
                             },
                         },
                     },
@@ -128,9 +146,15 @@ macro_rules! english_rules {
                         Ok(g) => g,
                         _ => match kr_glyph($ch) {
                             Ok(g) => g,
-                            _ => match $base_style(REPLACEMENT) {
+                            _ => match he_glyph($ch) {
                                 Ok(g) => g,
-                                _ => NULL_GLYPH_SPRITE,
+                                _ => match ar_glyph($ch) {
+                                    Ok(g) => g,
+                                    _ => match $base_style(REPLACEMENT) {
+                                        Ok(g) => g,
+                                        _ => NULL_GLYPH_SPRITE,
+                                    },
+                                },
                             },
                         },
                     },
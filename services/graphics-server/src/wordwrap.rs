@@ -1,6 +1,10 @@
-use crate::api::{glyph_to_height_hint, Cursor, GlyphSprite, GlyphStyle, Point, Pt, Rectangle, TypesetWord};
+use crate::api::{
+    glyph_to_height_hint, Cursor, DrawStyle, GlyphSprite, GlyphStyle, PixelColor, Point, Pt, Rectangle,
+    TextSpan, TypesetWord,
+};
 #[allow(unused_imports)]
 use crate::backend::{FB_LINES, FB_SIZE, FB_WIDTH_PIXELS};
+use crate::bidi::{self, BidiLevels};
 /// Wordwrap stratgey
 ///
 /// Strings are submitted to the Wordwrapper, and they are split into lines, and then into lexical words.
@@ -16,9 +20,14 @@ use crate::backend::{FB_LINES, FB_SIZE, FB_WIDTH_PIXELS};
 ///
 /// The exact GlyphSprite chosen is picked based on a hierarchy that starts with a hint based on
 /// `locales::LANG`, then rules based on the `base_style: GlyphStyle` field, which allows for all the
-/// text within a given string to be eg. small, regular, monospace, bold (mixing of different styles is
-/// not yet supported, but could be in the future if we add some sort of markup parsing to the text
-/// stream).
+/// text within a given string to be eg. small, regular, monospace, bold. Runs of text can be given other
+/// styles with `TextSpan`s, which may also invert or underline them. When there are spans, glyphs of
+/// different heights on one line are aligned on the bottom of the line.
+///
+/// Text with right-to-left characters in it is laid out with the bidirectional algorithm (see `bidi`):
+/// Arabic is shaped and mirrored characters are swapped before the words are wrapped, and then each line
+/// is reordered for display. Lines of right-to-left paragraphs are set against the right edge of the
+/// text.
 ///
 /// The location of the GlyphSprites do a "Best effort" to fit the words within the `bounds` based on the
 /// designated rule without word-wrapping. If a single word overflows one line width, it will be broken
@@ -39,6 +48,7 @@ impl TypesetWord {
             height: 0,
             non_drawable: false,
             strpos,
+            line_height: 0,
         }
     }
 
@@ -50,6 +60,7 @@ impl TypesetWord {
             height: gs.high as i16,
             non_drawable: false,
             strpos,
+            line_height: 0,
         }
     }
 
@@ -121,7 +132,9 @@ impl ComposedType {
                 // buffer, but the blitstr2 was written assuming only positive offsets. Handle
                 // this here.
                 let maybe_x = offset.x + point.x as i16;
-                let maybe_y = offset.y + point.y as i16;
+                let maybe_y = offset.y
+                    + point.y as i16
+                    + if word.line_height > 0 { word.line_height - glyph.high as i16 } else { 0 };
                 let mut renderable = true;
                 // allow MAX_GLYPH_MARGIN so we can get partial rendering of text that's slightly off screen
                 if maybe_x < (clip_rect.tl().x - MAX_GLYPH_MARGIN) || maybe_x > clip_rect.br().x {
@@ -165,17 +178,30 @@ impl ComposedType {
                             cr,
                         );
                     }
+                    if glyph.underline {
+                        let color = if glyph.invert ^ invert { PixelColor::Light } else { PixelColor::Dark };
+                        let y = maybe_y + glyph.high as i16 - 1;
+                        crate::op::line(
+                            frbuf,
+                            crate::api::Line::new_with_style(
+                                Point::new(maybe_x, y),
+                                Point::new(maybe_x + (glyph.wide + glyph.kern) as i16 - 1, y),
+                                DrawStyle::new(color, color, 1),
+                            ),
+                            Some(clip_rect),
+                            false,
+                        );
+                    }
                     if glyph.insert {
                         // log::info!("insert at {},{}", glyph.ch, strpos - 1);
                         // draw the insertion point after the glyph's position
+                        // in right-to-left text, the character after the insertion point is to its left
+                        let caret_x = if glyph.rtl { maybe_x + glyph.wide as i16 } else { maybe_x - 1 };
                         crate::op::line(
                             frbuf,
                             crate::api::Line::new(
-                                crate::api::Point::new(maybe_x as i16 - 1, maybe_y as _),
-                                crate::api::Point::new(
-                                    maybe_x as i16 - 1,
-                                    maybe_y as i16 + glyph.high as i16,
-                                ),
+                                crate::api::Point::new(caret_x, maybe_y as _),
+                                crate::api::Point::new(caret_x, maybe_y as i16 + glyph.high as i16),
                             ),
                             Some(clip_rect),
                             invert,
@@ -223,13 +249,30 @@ pub(crate) struct Typesetter {
     insertion_point: Option<usize>,
     s: String,
     base_style: GlyphStyle,
+    spans: Vec<TextSpan>,
+    /// the bidirectional levels of the text, if it has right-to-left characters in it
+    bidi: Option<BidiLevels>,
     overflow: bool,
     max_width: i16,
     last_line_height: usize, // scorecarding for the very last line on the loop exit
 }
 impl Typesetter {
-    pub fn setup(s: &str, extent: &Pt, base_style: &GlyphStyle, insertion_point: Option<usize>) -> Self {
+    pub fn setup(
+        s: &str,
+        extent: &Pt,
+        base_style: &GlyphStyle,
+        spans: &[TextSpan],
+        insertion_point: Option<usize>,
+    ) -> Self {
         let bb = ClipRect::new(0, 0, extent.x, extent.y);
+        let chars: Vec<char> = s.chars().collect();
+        let bidi =
+            if chars.iter().any(|&c| bidi::is_rtl(c)) { Some(BidiLevels::resolve(&chars)) } else { None };
+        // the display text has the same number of characters as the text, so positions are unchanged
+        let s: String = match &bidi {
+            Some(levels) => bidi::display_text(&chars, levels).into_iter().collect(),
+            None => String::from(s),
+        };
         let mut space = style_glyph(' ', base_style);
         space.kern = 0;
         let mut ellipsis = style_glyph('…', base_style);
@@ -256,7 +299,9 @@ impl Typesetter {
             ellipsis,
             large_space,
             base_style: base_style.clone(),
-            s: String::from(s),
+            spans: spans.to_vec(),
+            bidi,
+            s,
             insertion_point,
             overflow: false,
             max_width: 0,
@@ -381,8 +426,14 @@ impl Typesetter {
                 //    line for it.
                 // 5. The evolving word fits a line but doesn't fit this line, and there is no more space at
                 //    all.
-                let mut gs =
-                    if ch != '\t' { style_glyph(ch, &self.base_style) } else { self.large_space.clone() };
+                let mut gs = if ch == bidi::ABSORBED {
+                    // the second character of a ligature takes no space
+                    GlyphSprite { glyph: &[], wide: 0, kern: 0, ch, ..NULL_GLYPH_SPRITE }
+                } else if ch != '\t' {
+                    self.span_glyph(style_glyph(ch, self.span().map_or(&self.base_style, |s| &s.style)))
+                } else {
+                    self.large_space.clone()
+                };
                 if self.is_insert_point() {
                     gs.insert = true;
                 }
@@ -421,6 +472,7 @@ impl Typesetter {
                                 self.move_candidate_to_newline();
                                 // now set the overflowed character on the new line so our state is synched up
                                 self.candidate.push(gs_pop);
+                                self.commit_candidate_glyph(&gs_pop);
                             } else {
                                 // case 3
                                 // similar to the one-line iterator exit, but with a call to overflow at the
@@ -465,6 +517,12 @@ impl Typesetter {
         if self.candidate.gs.len() > 0 {
             self.commit_candidate_word(&mut composition);
         }
+        if let Some(levels) = &self.bidi {
+            composition = reorder_lines(composition, levels, self.max_width);
+        }
+        if !self.spans.is_empty() {
+            align_lines(&mut composition);
+        }
         let ret = ComposedType::new(
             composition,
             ClipRect::new(
@@ -509,6 +567,30 @@ impl Typesetter {
 
     fn is_word_longer_than_line(&self) -> bool { self.candidate.width >= (self.bb.max.x - self.bb.min.x) }
 
+    /// The span styling the character at `charpos`, if any
+    fn span(&self) -> Option<&TextSpan> { self.spans.iter().find(|s| s.contains(self.charpos)) }
+
+    /// Apply the inversion and underlining of the span at `charpos` to a glyph
+    fn span_glyph(&self, mut gs: GlyphSprite) -> GlyphSprite {
+        if let Some(span) = self.span() {
+            gs.invert ^= span.invert;
+            gs.underline = span.underline;
+        }
+        gs
+    }
+
+    /// A space in the style of the span at `charpos`, so that inverted and underlined spans are unbroken
+    fn space_glyph(&self) -> GlyphSprite {
+        match self.span() {
+            Some(span) => {
+                let mut space = self.span_glyph(style_glyph(' ', &span.style));
+                space.kern = 0;
+                space
+            }
+            None => self.space,
+        }
+    }
+
     fn is_insert_point(&self) -> bool {
         if let Some(ip) = self.insertion_point {
             if ip == self.charpos { true } else { false }
@@ -581,15 +663,15 @@ impl Typesetter {
     /// set to our space point, because the caller will have already stashed the previously formed word
     fn try_append_space(&mut self, composition: &mut Vec<TypesetWord>) -> bool {
         assert!(self.candidate.gs.len() == 0, "self.candidate was not set to a new state prior to this call");
-        if (self.cursor.pt.x + self.space.wide as i16) < self.bb.max.x {
+        let mut candidate_space = self.space_glyph();
+        if (self.cursor.pt.x + candidate_space.wide as i16) < self.bb.max.x {
             // our candidate word is "just as space"
-            let mut candidate_space = self.space.clone();
             if self.is_insert_point() {
                 candidate_space.insert = true;
             }
             self.candidate.push(candidate_space);
             self.commit_candidate_glyph(&candidate_space);
-            self.cursor.line_height = self.cursor.line_height.max(self.space.high as usize);
+            self.cursor.line_height = self.cursor.line_height.max(candidate_space.high as usize);
             // if we're at the beginning of a line, mark the candidate word (that just contains a space) as
             // non-drawable
             if self.cursor.pt.x == self.bb.min.x {
@@ -614,6 +696,76 @@ impl Typesetter {
     }
 }
 
+/// The number of words on each line of a composition, in order
+fn line_lengths(composition: &[TypesetWord]) -> Vec<usize> {
+    let mut lengths = Vec::new();
+    let mut start = 0;
+    while start < composition.len() {
+        let y = composition[start].origin.y;
+        let len =
+            composition[start..].iter().position(|w| w.origin.y != y).unwrap_or(composition.len() - start);
+        lengths.push(len);
+        start += len;
+    }
+    lengths
+}
+
+/// Set the line height of each word to the height of the tallest word on its line, so that glyphs of
+/// mixed heights are rendered on a common bottom line
+fn align_lines(composition: &mut [TypesetWord]) {
+    let mut start = 0;
+    for len in line_lengths(composition) {
+        let line = &mut composition[start..start + len];
+        let line_height = line.iter().map(|w| w.height).max().unwrap_or(0);
+        for word in line.iter_mut() {
+            word.line_height = line_height;
+        }
+        start += len;
+    }
+}
+
+/// Reorder the lines of a composition that have right-to-left text on them, each into one word of glyphs
+/// in visual order. Lines of right-to-left paragraphs are set against the right edge, at `width`.
+fn reorder_lines(composition: Vec<TypesetWord>, bidi: &BidiLevels, width: i16) -> Vec<TypesetWord> {
+    let lengths = line_lengths(&composition);
+    let mut words = composition.into_iter();
+    let mut reordered = Vec::new();
+    for len in lengths {
+        // words that aren't drawn, like the marks of hard newlines, are left as they are
+        let (blank, drawn): (Vec<TypesetWord>, Vec<TypesetWord>) =
+            words.by_ref().take(len).partition(|w| w.non_drawable);
+        reordered.extend(blank);
+        // the position in the text of each glyph; the ellipsis of an overflow is past the end of the text
+        let glyphs: Vec<(usize, GlyphSprite)> = drawn
+            .iter()
+            .flat_map(|w| w.gs.iter().enumerate().map(move |(k, gs)| (w.strpos + k, *gs)))
+            .collect();
+        let last = bidi.levels.len().saturating_sub(1);
+        let para = match glyphs.first() {
+            Some((pos, _)) => bidi.paragraph.get((*pos).min(last)).copied().unwrap_or(0),
+            None => continue,
+        };
+        let levels: Vec<u8> =
+            glyphs.iter().map(|(pos, _)| bidi.levels.get(*pos).copied().unwrap_or(para)).collect();
+        if !bidi::is_rtl_level(para) && !levels.iter().any(|&l| bidi::is_rtl_level(l)) {
+            reordered.extend(drawn);
+            continue;
+        }
+        let trailing_ws = glyphs.iter().rev().take_while(|(_, gs)| gs.ch.is_whitespace()).count();
+        let mut line = TypesetWord::new(drawn[0].origin, drawn[0].strpos);
+        for i in bidi::visual_order(&levels, trailing_ws, para) {
+            let mut gs = glyphs[i].1;
+            gs.rtl = bidi::is_rtl_level(levels[i]);
+            line.push(gs);
+        }
+        if bidi::is_rtl_level(para) {
+            line.origin.x = line.origin.x.max(width - line.width);
+        }
+        reordered.push(line);
+    }
+    reordered
+}
+
 #[allow(dead_code)]
 fn tsw_debug(tsw: &TypesetWord) {
     let mut s = String::new();
@@ -644,3 +796,72 @@ pub fn style_glyph(ch: char, base_style: &GlyphStyle) -> GlyphSprite {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typeset(s: &str, width: i16, spans: &[TextSpan]) -> ComposedType {
+        Typesetter::setup(s, &Pt::new(width, 200), &GlyphStyle::Regular, spans, None)
+            .typeset(OverflowStrategy::Abort)
+    }
+
+    fn glyphs(composed: &ComposedType) -> Vec<GlyphSprite> {
+        composed.words.iter().filter(|w| !w.non_drawable).flat_map(|w| w.gs.iter().copied()).collect()
+    }
+
+    #[test]
+    fn span_styles() {
+        let mut name = TextSpan::new(0, 5, GlyphStyle::Bold);
+        name.underline = true;
+        let mut tag = TextSpan::new(8, 11, GlyphStyle::Monospace);
+        tag.invert = true;
+        let gs = glyphs(&typeset("Bob: hi #42", 300, &[name, tag]));
+        assert_eq!(gs.iter().map(|g| g.ch).collect::<String>(), "Bob: hi #42");
+        assert_eq!(gs[0].wide, style_glyph('B', &GlyphStyle::Bold).wide);
+        // the underline runs through the space that ends the span, but no further
+        assert!(gs[..5].iter().all(|g| g.underline) && !gs[5].underline);
+        assert!(gs[8..].iter().all(|g| g.invert) && !gs[7].invert);
+        assert_eq!(gs[9].wide, style_glyph('4', &GlyphStyle::Monospace).wide);
+    }
+
+    #[test]
+    fn mixed_heights_share_a_bottom() {
+        let composed = typeset("big small", 300, &[TextSpan::new(0, 3, GlyphStyle::Large)]);
+        let large = style_glyph('b', &GlyphStyle::Large).high as i16;
+        assert!(composed.words.iter().all(|w| w.line_height == large));
+        assert_eq!(composed.bb_height(), large);
+        // without spans, the layout is unchanged
+        assert!(typeset("big small", 300, &[]).words.iter().all(|w| w.line_height == 0));
+    }
+
+    #[test]
+    fn spans_follow_a_broken_word() {
+        // a word too long for a line is broken across lines, and the span still starts at its character
+        let s = "abcdefghijklmnopqrstuvwxyz";
+        let composed = typeset(s, 60, &[TextSpan::new(20, 26, GlyphStyle::Bold)]);
+        assert!(composed.words.len() > 1);
+        let gs = glyphs(&composed);
+        assert_eq!(gs.iter().map(|g| g.ch).collect::<String>(), s);
+        for (i, g) in gs.iter().enumerate() {
+            let style = if i < 20 { GlyphStyle::Regular } else { GlyphStyle::Bold };
+            assert_eq!(g.glyph.as_ptr(), style_glyph(g.ch, &style).glyph.as_ptr(), "glyph {} of {:?}", i, s);
+        }
+    }
+    #[test]
+    fn right_to_left_lines() {
+        // the run is reversed for display, and the parentheses in it are mirrored
+        let gs = glyphs(&typeset("\u{05d0}\u{05d1} (x)", 300, &[]));
+        assert_eq!(gs.iter().take(4).map(|g| g.ch).collect::<String>(), "(x) ");
+        assert_eq!(gs.iter().map(|g| g.rtl).collect::<Vec<bool>>(), [true, false, true, true, true, true]);
+        // a right-to-left paragraph is set against the right edge, and a left-to-right one is left alone
+        let composed = typeset("\u{05d0}\u{05d1}\nabc def", 300, &[]);
+        let lines: Vec<&TypesetWord> = composed.words.iter().filter(|w| !w.non_drawable).collect();
+        let right = lines.iter().map(|w| w.origin.x + w.width).max().unwrap();
+        assert!(lines[0].origin.x > 0);
+        assert_eq!(lines[0].origin.x + lines[0].width, right);
+        assert_eq!(lines[1].origin.x, 0);
+        // text without right-to-left characters is not reordered
+        assert!(glyphs(&typeset("abc (x)", 300, &[])).iter().all(|g| !g.rtl));
+    }
+}
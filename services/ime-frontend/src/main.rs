@@ -300,12 +300,11 @@ impl InputTracker {
                 match k {
                     '\u{0000}' => (),
                     '←' => {
-                        // move insertion point back
+                        // move insertion point left on the screen, which is forward in right-to-left text
                         if !self.menu_mode {
-                            if self.insertion > 0 {
-                                log::debug!("moving insertion point back");
-                                self.insertion -= 1;
-                            }
+                            log::debug!("moving insertion point left");
+                            self.insertion =
+                                graphics_server::bidi::visual_step(&self.line, self.insertion, false);
                             do_redraw = true;
                             self.pred_phrase.clear(); // don't track predictions on edits
                             self.can_unpick = false;
@@ -316,9 +315,8 @@ impl InputTracker {
                     }
                     '→' => {
                         if !self.menu_mode {
-                            if self.insertion < self.characters {
-                                self.insertion += 1;
-                            }
+                            self.insertion =
+                                graphics_server::bidi::visual_step(&self.line, self.insertion, true);
                             do_redraw = true;
                             self.pred_phrase.clear();
                             self.can_unpick = false;
@@ -766,6 +766,7 @@ fn main() -> ! {
     // connections expected:
     //  - GAM
     //  - graphics (if building for hosted mode)
+    //  - graphics scripted input (hosted mode, see graphics-server/src/script.rs)
     //  - oqc (for factory test)
     //  - status sub system (for setting the layout, autobacklight feature)
     //  - USB (for getting layout)
@@ -775,7 +776,7 @@ fn main() -> ! {
     #[cfg(all(any(feature = "precursor", feature = "renode"), feature = "dvt"))] // dvt build has less in it
     let kbd_sid = xns.register_name(api::SERVER_NAME_KBD, Some(4)).expect("can't register server");
     #[cfg(not(target_os = "xous"))]
-    let kbd_sid = xns.register_name(api::SERVER_NAME_KBD, Some(5)).expect("can't register server");
+    let kbd_sid = xns.register_name(api::SERVER_NAME_KBD, Some(6)).expect("can't register server");
     log::trace!("registered with NS -- {:?}", kbd_sid);
 
     // Create a new kbd object
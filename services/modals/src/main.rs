@@ -126,6 +126,8 @@ fn wrapped_main() -> ! {
         DEFAULT_STYLE,
         8,
     );
+    // the shared modal takes passwords and shows secrets such as backup phrases on behalf of other apps
+    renderer_modal.gam.veto_screenshots(renderer_modal.authtoken).expect("couldn't veto screenshots");
     renderer_modal.spawn_helper(
         modals_sid,
         renderer_modal.sid,
@@ -7,6 +7,7 @@ pub use ping::NetPingCallback;
 pub(crate) use tcp::*;
 
 pub mod rkyv_enum;
+use std::convert::TryInto;
 use std::fmt;
 use std::fmt::Debug;
 use std::io::Write;
@@ -25,6 +26,21 @@ use xous_semver::SemVer;
 pub(crate) const SERVER_NAME_NET: &str = "_Middleware Network Server_";
 #[allow(dead_code)]
 pub const AP_DICT_NAME: &'static str = "wlan.networks";
+/// PDDB dictionary holding a `WifiProfile` for each saved network, keyed by SSID. The passwords stay
+/// in `AP_DICT_NAME`; a network without an entry here uses `WifiProfile::default()`.
+#[allow(dead_code)]
+pub const PROFILE_DICT_NAME: &'static str = "wlan.profiles";
+/// PDDB dictionary for network settings that are not specific to a saved network
+#[allow(dead_code)]
+pub const NET_CONFIG_DICT_NAME: &'static str = "net.config";
+/// Key in `NET_CONFIG_DICT_NAME` holding the connectivity-check URL, overriding
+/// `CONNECTIVITY_CHECK_DEFAULT`
+#[allow(dead_code)]
+pub const CONNECTIVITY_CHECK_KEY: &'static str = "connectivity_check";
+/// A plain `http` URL that answers `204 No Content`. A captive portal intercepts the request and
+/// answers with (a redirect to) its sign-in page instead.
+#[allow(dead_code)]
+pub const CONNECTIVITY_CHECK_DEFAULT: &'static str = "http://connectivitycheck.gstatic.com/generate_204";
 
 #[allow(dead_code)]
 /// minimum revision required for compatibility with Net crate
@@ -361,6 +377,82 @@ pub(crate) enum Opcode {
     StdTcpStreamShutdown = 46,
 
     LoopbackRx = 47,
+
+    /// WireGuard tunnel: returns a `WgStatus` (memory message)
+    WgGetStatus = 48,
+
+    /// WireGuard tunnel: returns the `Option<[u8; 32]>` Curve25519 public key of this device
+    /// (memory message). The key pair is generated and saved to the PDDB on first use.
+    WgGetPublicKey = 49,
+
+    /// WireGuard tunnel: saves a `WgPeerConfig` to the PDDB (memory message), and returns a
+    /// `NetMemResponse`. The configuration takes effect the next time the tunnel is brought up.
+    WgSetPeer = 50,
+
+    /// WireGuard tunnel: returns the `Option<WgPeerConfig>` saved in the PDDB (memory message)
+    WgGetPeer = 51,
+
+    /// WireGuard tunnel: BlockingScalar call to bring the tunnel up (arg1 = 1) or down (arg1 = 0).
+    /// Returns 1 on success, 0 if there is no usable configuration in the PDDB.
+    WgUpDown = 52,
+
+    /// WireGuard tunnel: BlockingScalar call that brings the tunnel down, and deletes the keys and
+    /// peer configuration from the PDDB
+    WgClear = 53,
+
+    /// [Internal] connection manager: applies an `Option<WifiStaticIpv4>` (memory message). `Some`
+    /// configures the interface in place of DHCP, `None` goes back to the DHCP configuration.
+    WlanStaticConfig = 54,
+
+    /// Returns the `Connectivity` found by the check that runs after the WLAN interface acquires an
+    /// address (memory message). Updates are also pushed to `SubscribeWifiStats` subscribers.
+    GetConnectivity = 55,
+
+    /// Returns the `Ipv6Conf` of the WLAN interface (memory message)
+    GetIpv6Config = 56,
+
+    /// Packet capture: BlockingScalar call that discards any previous capture and starts teeing
+    /// frames into a ring of arg1 bytes (0 for `CAPTURE_DEFAULT_BYTES`), truncating each frame to
+    /// arg2 bytes (0 for no limit). Returns 1.
+    CaptureStart = 57,
+
+    /// Packet capture: BlockingScalar call that stops the capture, keeping the frames for export.
+    /// Returns the number of frames held.
+    CaptureStop = 58,
+
+    /// Packet capture: returns a `CaptureStatus` (memory message)
+    CaptureGetStatus = 59,
+
+    /// Packet capture: fills a lent page-aligned buffer with the capture as pcapng, starting at byte
+    /// `offset`. `valid` is set to the number of bytes written, which is less than the buffer length
+    /// for the last chunk. The pcapng is generated when `offset` is 0, so a capture that is still
+    /// running can be exported consistently.
+    CaptureExport = 60,
+
+    /// BlockingScalar call for the UDP socket whose fd is in the upper 16 bits of the opcode to join
+    /// the IPv4 multicast group in arg1 (big-endian), on the interface in arg2 (ignored, there is
+    /// only one). Returns 0, or a `NetError` code. The interface reports the group with IGMP when
+    /// the first socket joins it.
+    StdUdpJoinMulticastV4 = 61,
+
+    /// BlockingScalar call to leave an IPv4 multicast group, with the same arguments as
+    /// `StdUdpJoinMulticastV4`. Groups are also left when the socket is closed.
+    StdUdpLeaveMulticastV4 = 62,
+
+    /// BlockingScalar call to allow (arg1 = 1) or forbid (arg1 = 0) the UDP socket whose fd is in
+    /// the upper 16 bits of the opcode to send to and receive from broadcast addresses. Returns 0,
+    /// or a `NetError` code.
+    StdUdpSetBroadcast = 63,
+
+    /// BlockingScalar call that returns 1 if the UDP socket whose fd is in the upper 16 bits of the
+    /// opcode may use broadcast addresses, and 0 otherwise
+    StdUdpGetBroadcast = 64,
+
+    /// [Internal] WireGuard tunnel: the PDDB is mounted, so the keys and peer configuration can be read
+    WgPddbMounted = 65,
+
+    /// [Internal] connection manager: the `Connectivity` of the WLAN interface changed (memory message)
+    ConnectivityUpdate = 66,
     // do not use any numbers higher than 0x8000 as that is reserved for the nonblocking flag
 }
 #[allow(dead_code)]
@@ -385,6 +477,191 @@ pub(crate) struct SsidList {
     pub(crate) state: ScanState,
 }
 
+/// A static IPv4 configuration, used in place of DHCP
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WifiStaticIpv4 {
+    pub addr: [u8; 4],
+    pub prefix: u8,
+    pub gateway: [u8; 4],
+    /// `None` uses the gateway as the DNS server
+    pub dns: Option<[u8; 4]>,
+}
+impl WifiStaticIpv4 {
+    /// The netmask corresponding to `prefix`
+    pub fn mask(&self) -> [u8; 4] {
+        u32::MAX.checked_shl(32 - self.prefix.min(32) as u32).unwrap_or(0).to_be_bytes()
+    }
+}
+
+const WIFI_PROFILE_VERSION: u8 = 1;
+const WIFI_PROFILE_LEN: usize = 1 + 3 + 1 + 4 + 1 + 4 + 5;
+
+/// How the connection manager treats a saved network
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WifiProfile {
+    /// networks with a higher priority are joined first; among equal priorities, the strongest signal wins
+    pub priority: u8,
+    /// the network does not broadcast its SSID, so it is joined without being seen in a scan first
+    pub hidden: bool,
+    /// join the network automatically. Networks that are not auto-joined are kept, but ignored.
+    pub autojoin: bool,
+    /// `None` to use DHCP
+    pub static_ipv4: Option<WifiStaticIpv4>,
+}
+impl Default for WifiProfile {
+    fn default() -> Self { Self { priority: 0, hidden: false, autojoin: true, static_ipv4: None } }
+}
+impl WifiProfile {
+    /// Encodes the profile for storage in `PROFILE_DICT_NAME`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![WIFI_PROFILE_VERSION, self.priority, self.hidden as u8, self.autojoin as u8];
+        match self.static_ipv4 {
+            Some(ip) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&ip.addr);
+                bytes.push(ip.prefix);
+                bytes.extend_from_slice(&ip.gateway);
+                match ip.dns {
+                    Some(dns) => {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&dns);
+                    }
+                    None => bytes.extend_from_slice(&[0u8; 5]),
+                }
+            }
+            None => bytes.extend_from_slice(&[0u8; WIFI_PROFILE_LEN - 4]),
+        }
+        bytes
+    }
+
+    /// Decodes a profile stored in `PROFILE_DICT_NAME`. Returns `None` if the record is not recognized.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != WIFI_PROFILE_LEN || bytes[0] != WIFI_PROFILE_VERSION {
+            return None;
+        }
+        let static_ipv4 = if bytes[4] != 0 {
+            Some(WifiStaticIpv4 {
+                addr: bytes[5..9].try_into().unwrap(),
+                prefix: bytes[9],
+                gateway: bytes[10..14].try_into().unwrap(),
+                dns: if bytes[14] != 0 { Some(bytes[15..19].try_into().unwrap()) } else { None },
+            })
+        } else {
+            None
+        };
+        Some(Self { priority: bytes[1], hidden: bytes[2] != 0, autojoin: bytes[3] != 0, static_ipv4 })
+    }
+}
+
+/// Maximum number of IPv6 DNS servers kept from router advertisements or DHCPv6
+pub const IPV6_DNS_MAX: usize = 3;
+
+/// The IPv6 configuration of the WLAN interface
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct Ipv6Conf {
+    /// EUI-64 link-local address, always configured
+    pub link_local: [u8; 16],
+    /// address formed from a prefix advertised by the router, `None` until a router answers
+    pub addr: Option<[u8; 16]>,
+    pub prefix_len: u8,
+    /// link-local address of the default router
+    pub router: Option<[u8; 16]>,
+    pub dns: [Option<[u8; 16]>; IPV6_DNS_MAX],
+}
+
+/// Whether the network that the WLAN interface joined actually reaches the internet
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct Connectivity {
+    pub state: ConnectivityState,
+    /// The sign-in page of the captive portal, if `state` is `Captive`
+    pub portal: Option<xous_ipc::String<256>>,
+}
+
+/// Ring size of a packet capture started without one
+pub const CAPTURE_DEFAULT_BYTES: usize = 256 * 1024;
+/// Largest ring a packet capture can use; requests for more are clamped
+pub const CAPTURE_MAX_BYTES: usize = 1024 * 1024;
+/// Captures saved by `net capture save` go in this PDDB dictionary
+pub const CAPTURE_DICT_NAME: &'static str = "net.capture";
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct CaptureStatus {
+    pub running: bool,
+    /// frames held in the ring
+    pub frames: u32,
+    /// frames pushed out of the ring by newer ones
+    pub dropped: u32,
+    /// size of the capture once exported as pcapng
+    pub pcapng_len: u32,
+    pub ring_len: u32,
+}
+
+/// PDDB dictionary holding the WireGuard device key and peer configuration. The dictionary is
+/// created in the most recently unlocked basis, so the tunnel can be kept in a secret basis.
+pub const WG_DICT_NAME: &'static str = "wg.tunnel";
+/// Maximum number of routes through the WireGuard tunnel
+pub const WG_MAX_ROUTES: usize = 8;
+
+/// An IPv4 prefix routed through the WireGuard tunnel. `0.0.0.0/0` routes all traffic.
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WgRoute {
+    pub addr: [u8; 4],
+    pub prefix: u8,
+}
+
+/// The configuration of the (single) WireGuard peer
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct WgPeerConfig {
+    /// Curve25519 public key of the peer
+    pub public_key: [u8; 32],
+    pub preshared_key: Option<[u8; 32]>,
+    /// IPv4 address and UDP port of the peer. The WLAN interface is IPv4 only.
+    pub endpoint: [u8; 4],
+    pub port: u16,
+    /// Our address inside the tunnel
+    pub address: [u8; 4],
+    /// DNS server to use while the tunnel is up
+    pub dns: Option<[u8; 4]>,
+    /// persistent keepalive interval in seconds, 0 to disable
+    pub keepalive: u16,
+    pub routes: [Option<WgRoute>; WG_MAX_ROUTES],
+    /// bring the tunnel up automatically whenever an IP configuration is acquired
+    pub autoconnect: bool,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct WgStatus {
+    pub state: WgState,
+    /// seconds since the last completed handshake
+    pub last_handshake_secs: Option<u64>,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+/// Name of the server that wraps and unwraps keys with the root keys on behalf of other processes,
+/// so that `tls` can store the client identity key encrypted. It holds the net crate's one connection
+/// to the root keys, and runs on its own thread because an unwrap can block on a password prompt.
+pub(crate) const SERVER_NAME_KEYWRAP: &str = "_Net key wrapper_";
+/// Largest key the wrapper accepts; mirrors `root_keys::api::MAX_WRAP_DATA`
+pub const KEYWRAP_MAX_DATA: usize = 2048;
+
+#[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
+pub(crate) enum KeyWrapOpcode {
+    /// `KeyWrapIpc` memory message: wrap `data[..len]`
+    Wrap,
+    /// `KeyWrapIpc` memory message: unwrap `data[..len]` into `expected_len` bytes
+    Unwrap,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+pub(crate) struct KeyWrapIpc {
+    pub data: [u8; KEYWRAP_MAX_DATA + 8],
+    pub len: u32,
+    pub expected_len: u32,
+    /// set by the server; a failed (un)wrap is returned with `ok == false` and `len == 0`
+    pub ok: bool,
+}
+
 /// These opcodes are reserved for private SIDs shared from a DNS server to
 /// reconfigure DNS on IP change/update.
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
@@ -401,6 +678,8 @@ pub(crate) enum PrivateDnsOp {
 pub(crate) enum WifiStateCallback {
     Update,
     Drop,
+    /// the result of a connectivity check changed
+    Connectivity,
 }
 #[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
 pub(crate) struct WifiStateSubscription {
@@ -415,12 +694,12 @@ pub(crate) enum NetCallback {
 }
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum NetError {
     // Ok = 0,
     Unaddressable = 1,
     SocketInUse = 2,
-    // AccessDenied = 3,
+    AccessDenied = 3,
     Invalid = 4,
     // Finished = 5,
     LibraryError = 6,
@@ -108,3 +108,26 @@ pub(crate) enum TcpMgmtCode {
     Flush(bool),
     CloseListener,
 }
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq, Eq)]
+pub enum WgState {
+    #[default]
+    Off,
+    /// The tunnel is up, but there is no current session with the peer
+    Handshaking,
+    /// A handshake with the peer has completed within the session lifetime
+    Up,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ConnectivityState {
+    /// Not connected, or the check has not completed yet
+    #[default]
+    Unknown,
+    Online,
+    /// A captive portal intercepted the check; the user has to sign in before the network is usable
+    Captive,
+    /// The check could not reach the connectivity-check server, or got an answer that was neither the
+    /// expected one nor a portal
+    Offline,
+}
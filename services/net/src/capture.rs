@@ -0,0 +1,170 @@
+// Packet capture
+//
+// Wi-Fi problems on users' networks are hard to reproduce, so field engineers can have `NetPhy` tee
+// every frame it passes to and from smoltcp into a bounded ring, and export it as pcapng. When the
+// ring is full the oldest frames are dropped. Frames are recorded as the stack sees them, that is,
+// inside the WireGuard tunnel when it is up, and timestamps count milliseconds since boot.
+use std::collections::VecDeque;
+
+use crate::api::*;
+
+/// bytes a frame adds to the pcapng on top of its (padded) data, counted against the ring size so
+/// that the export stays close to it
+const EPB_OVERHEAD: usize = 44;
+const SHB_LEN: usize = 28;
+const IDB_LEN: usize = 32;
+const LINKTYPE_ETHERNET: u16 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Inbound,
+    Outbound,
+}
+
+struct Frame {
+    timestamp_ms: u64,
+    direction: Direction,
+    original_len: usize,
+    data: Vec<u8>,
+}
+
+pub(crate) struct PacketCapture {
+    frames: VecDeque<Frame>,
+    ring_len: usize,
+    used: usize,
+    /// 0 keeps whole frames
+    snaplen: usize,
+    running: bool,
+    dropped: u32,
+    /// the pcapng being exported
+    export: Option<Vec<u8>>,
+}
+
+impl PacketCapture {
+    pub(crate) fn new(ring_len: usize, snaplen: usize) -> PacketCapture {
+        let ring_len = match ring_len {
+            0 => CAPTURE_DEFAULT_BYTES,
+            len => len.min(CAPTURE_MAX_BYTES),
+        };
+        PacketCapture {
+            frames: VecDeque::new(),
+            ring_len,
+            used: 0,
+            snaplen,
+            running: true,
+            dropped: 0,
+            export: None,
+        }
+    }
+
+    pub(crate) fn stop(&mut self) { self.running = false; }
+
+    pub(crate) fn record(&mut self, timestamp_ms: u64, direction: Direction, frame: &[u8]) {
+        if !self.running {
+            return;
+        }
+        let len = if self.snaplen == 0 { frame.len() } else { frame.len().min(self.snaplen) };
+        let size = len + EPB_OVERHEAD;
+        if size > self.ring_len {
+            self.dropped += 1;
+            return;
+        }
+        while self.used + size > self.ring_len {
+            let oldest = self.frames.pop_front().unwrap();
+            self.used -= oldest.data.len() + EPB_OVERHEAD;
+            self.dropped += 1;
+        }
+        self.used += size;
+        self.frames.push_back(Frame {
+            timestamp_ms,
+            direction,
+            original_len: frame.len(),
+            data: frame[..len].to_vec(),
+        });
+    }
+
+    pub(crate) fn status(&self) -> CaptureStatus {
+        CaptureStatus {
+            running: self.running,
+            frames: self.frames.len() as u32,
+            dropped: self.dropped,
+            pcapng_len: self.pcapng_len() as u32,
+            ring_len: self.ring_len as u32,
+        }
+    }
+
+    fn pcapng_len(&self) -> usize {
+        let frames: usize = self.frames.iter().map(|f| EPB_OVERHEAD + pad4(f.data.len())).sum();
+        SHB_LEN + IDB_LEN + frames
+    }
+
+    /// Copies the pcapng from byte `offset` into `buf`, returning the number of bytes copied. The
+    /// pcapng is regenerated when `offset` is 0.
+    pub(crate) fn export(&mut self, offset: usize, buf: &mut [u8]) -> usize {
+        if offset == 0 || self.export.is_none() {
+            self.export = Some(self.to_pcapng());
+        }
+        let pcapng = self.export.as_ref().unwrap();
+        let len = pcapng.len().saturating_sub(offset).min(buf.len());
+        if len > 0 {
+            buf[..len].copy_from_slice(&pcapng[offset..offset + len]);
+        }
+        if len < buf.len() {
+            // that was the last chunk
+            self.export = None;
+        }
+        len
+    }
+
+    pub(crate) fn to_pcapng(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.pcapng_len());
+        // section header: byte-order magic, version 1.0, unknown section length
+        block(&mut out, 0x0A0D_0D0A, |b| {
+            b.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+            b.extend_from_slice(&1u16.to_le_bytes());
+            b.extend_from_slice(&0u16.to_le_bytes());
+            b.extend_from_slice(&(-1i64).to_le_bytes());
+        });
+        // interface description, with timestamps in milliseconds (if_tsresol = 3)
+        block(&mut out, 1, |b| {
+            b.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+            b.extend_from_slice(&0u16.to_le_bytes());
+            b.extend_from_slice(&(self.snaplen as u32).to_le_bytes());
+            b.extend_from_slice(&[9, 0, 1, 0, 3, 0, 0, 0]);
+            b.extend_from_slice(&[0; 4]);
+        });
+        for frame in self.frames.iter() {
+            // enhanced packet, with the direction in epb_flags
+            block(&mut out, 6, |b| {
+                b.extend_from_slice(&0u32.to_le_bytes());
+                b.extend_from_slice(&((frame.timestamp_ms >> 32) as u32).to_le_bytes());
+                b.extend_from_slice(&(frame.timestamp_ms as u32).to_le_bytes());
+                b.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+                b.extend_from_slice(&(frame.original_len as u32).to_le_bytes());
+                b.extend_from_slice(&frame.data);
+                b.resize(pad4(b.len()), 0);
+                let flags: u32 = match frame.direction {
+                    Direction::Inbound => 1,
+                    Direction::Outbound => 2,
+                };
+                b.extend_from_slice(&[2, 0, 4, 0]);
+                b.extend_from_slice(&flags.to_le_bytes());
+                b.extend_from_slice(&[0; 4]);
+            });
+        }
+        out
+    }
+}
+
+fn pad4(len: usize) -> usize { (len + 3) & !3 }
+
+/// Appends a pcapng block of `block_type` with the body written by `body`
+fn block(out: &mut Vec<u8>, block_type: u32, body: impl FnOnce(&mut Vec<u8>)) {
+    let mut b = Vec::new();
+    body(&mut b);
+    let total = (b.len() + 12) as u32;
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(&b);
+    out.extend_from_slice(&total.to_le_bytes());
+}
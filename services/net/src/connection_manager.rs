@@ -12,7 +12,7 @@ use xous::{msg_blocking_scalar_unpack, msg_scalar_unpack, send_message, try_send
 use xous_ipc::Buffer;
 
 use crate::api::*;
-use crate::ComIntSources;
+use crate::{connectivity, ComIntSources};
 
 #[allow(dead_code)]
 const BOOT_POLL_INTERVAL_MS: usize = 4_758; // a slightly faster poll during boot so we acquire wifi faster once PDDB is mounted
@@ -24,6 +24,7 @@ const INTERVALS_BEFORE_RETRY: usize = 3; // how many poll intervals we'll wait b
 const SCAN_COUNT_MAX: usize = 5;
 const SSID_SCAN_AGING_THRESHOLD: Duration = Duration::from_secs(5); // time before a scan is considered "stale" and needs to be redone
 const SSID_RESULT_AGING_THRESHOLD: Duration = Duration::from_secs(60); // time before an individual scan result is retired for being "too rarely seen"
+const CONNECTIVITY_RECHECK_INTERVALS: usize = 4; // how many poll intervals between checks while behind a captive portal, or offline
 
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
 pub(crate) enum ConnectionManagerOpcode {
@@ -39,6 +40,8 @@ pub(crate) enum ConnectionManagerOpcode {
     ComInt,
     SuspendResume,
     EcReset,
+    /// A connectivity check finished with the enclosed `Connectivity` (memory message)
+    ConnectivityResult,
     Quit,
 }
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
@@ -70,7 +73,7 @@ enum SsidScanState {
 }
 
 #[derive(Eq, PartialEq, Clone, Debug)]
-struct SsidOrdByRssi {
+pub(crate) struct SsidOrdByRssi {
     pub ssid: String,
     pub rssi: u8,
     pub last_seen: Instant,
@@ -85,7 +88,7 @@ impl PartialOrd for SsidOrdByRssi {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) }
 }
 
-pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU32>) {
+pub(crate) fn connection_manager(sid: xous::SID, net_cid: xous::CID, activity_interval: Arc<AtomicU32>) {
     let tt = ticktimer_server::Ticktimer::new().unwrap();
     let xns = xous_names::XousNames::new().unwrap();
     let mut com = com::Com::new(&xns).unwrap();
@@ -116,6 +119,11 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
     // keyed on String so that dups of ssid records are replaced
     let mut ssid_list = HashMap::<String, SsidOrdByRssi>::new();
     let mut ssid_attempted = HashSet::<String>::new();
+    // static configuration of the network being joined, `None` if it uses DHCP
+    let mut static_ipv4: Option<WifiStaticIpv4> = None;
+    let mut connectivity = Connectivity::default();
+    let mut connectivity_checking = false;
+    let mut connectivity_recheck_count = 0;
     let mut wait_count = 0;
     let mut scan_count = 0;
 
@@ -311,7 +319,32 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                             wifi_state = match ConnectResult::decode_u16(raw_arg as u16) {
                                 ConnectResult::Success => {
                                     activity_interval.store(0, Ordering::SeqCst);
-                                    WifiState::WaitDhcp
+                                    if let Some(config) = static_ipv4 {
+                                        // there is no DHCP to wait for: configure the interface, and relay
+                                        // the connection to the subscribers
+                                        set_static_config(net_cid, static_ipv4);
+                                        wifi_stats_cache = com.wlan_status().unwrap();
+                                        apply_static_config(&mut wifi_stats_cache, &config);
+                                        for &sub in status_subscribers.keys() {
+                                            let buf = Buffer::into_buf(com::WlanStatusIpc::from_status(
+                                                wifi_stats_cache,
+                                            ))
+                                            .or(Err(xous::Error::InternalError))
+                                            .unwrap();
+                                            match buf.send(sub, WifiStateCallback::Update.to_u32().unwrap()) {
+                                                Err(e) => {
+                                                    log::warn!(
+                                                        "Couldn't update wifi state subscriber: {:?}",
+                                                        e
+                                                    )
+                                                }
+                                                _ => (),
+                                            }
+                                        }
+                                        WifiState::Connected
+                                    } else {
+                                        WifiState::WaitDhcp
+                                    }
                                 }
                                 ConnectResult::NoMatchingAp => WifiState::InvalidAp,
                                 ConnectResult::Timeout => WifiState::Retry,
@@ -375,6 +408,9 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                             // changed
                             if wifi_state != WifiState::Off {
                                 wifi_stats_cache = com.wlan_status().unwrap();
+                                if let Some(config) = static_ipv4.as_ref() {
+                                    apply_static_config(&mut wifi_stats_cache, config);
+                                }
                                 log::debug!("stats update: {:?}", wifi_stats_cache);
                                 for &sub in status_subscribers.keys() {
                                     let buf =
@@ -388,7 +424,9 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                         _ => (),
                                     }
                                 }
-                                if wifi_stats_cache.ipv4.dhcp == com_rs::DhcpState::Bound {
+                                if wifi_stats_cache.ipv4.dhcp == com_rs::DhcpState::Bound
+                                    || static_ipv4.is_some()
+                                {
                                     wifi_state = WifiState::Connected;
                                 } else {
                                     wifi_state = WifiState::WaitDhcp;
@@ -430,7 +468,9 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                     wifi_stats_cache.link_state
                                 );
                                 netmgr.reset();
-                            } else if wifi_stats_cache.ipv4.dhcp != com_rs::DhcpState::Bound {
+                            } else if wifi_stats_cache.ipv4.dhcp != com_rs::DhcpState::Bound
+                                && static_ipv4.is_none()
+                            {
                                 log::info!(
                                     "DHCP state mismatch: moving state to disconnected ({:?})",
                                     wifi_stats_cache.ipv4.dhcp
@@ -478,8 +518,9 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                         }
                                         SsidScanState::Idle(_last_scan_time) => {
                                             scan_count = 0;
+                                            let profiles = load_profiles(&pddb, ap_list);
                                             if let Some(ssid) =
-                                                get_next_ssid(&mut ssid_list, &mut ssid_attempted, ap_list)
+                                                get_next_ssid(&ssid_list, &mut ssid_attempted, &profiles)
                                             {
                                                 let mut wpa_pw_file = pddb
                                                     .get(
@@ -497,14 +538,19 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                                     let pw = std::str::from_utf8(&wp_pw_raw[..readlen])
                                                         .expect("password was not valid utf-8");
                                                     log::info!("Attempting wifi connection: {}", ssid);
+                                                    // the previous network may have been configured
+                                                    // statically; the new one is configured on connect
+                                                    static_ipv4 = profiles[&ssid].static_ipv4;
+                                                    set_static_config(net_cid, None);
                                                     com.wlan_set_ssid(&ssid).expect("couldn't set SSID");
                                                     com.wlan_set_pass(pw).expect("couldn't set password");
                                                     com.wlan_join().expect("couldn't issue join command");
                                                     wifi_state = WifiState::Connecting;
                                                 }
                                             } else {
-                                                // no SSIDs available, scan again
-                                                log::info!("No SSIDs found, restarting SSID scan...");
+                                                // no SSIDs available, or all of them have been tried: scan
+                                                // again
+                                                log::info!("No untried SSIDs found, restarting SSID scan...");
                                                 com.set_ssid_scanning(true).unwrap();
                                                 scan_state = SsidScanState::Scanning;
                                             }
@@ -558,6 +604,9 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                     // relay status updates to any subscribers that want to know if a state
                                     // has changed
                                     wifi_stats_cache = com.wlan_status().unwrap();
+                                    if let Some(config) = static_ipv4.as_ref() {
+                                        apply_static_config(&mut wifi_stats_cache, config);
+                                    }
                                     log::debug!("stats update: {:?}", wifi_stats_cache);
                                     for &sub in status_subscribers.keys() {
                                         let buf = Buffer::into_buf(com::WlanStatusIpc::from_status(
@@ -590,6 +639,18 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                 }
 
                 if wifi_state == WifiState::Connected {
+                    // keep checking until the user signs in to the portal, or the upstream comes back
+                    if (connectivity.state == ConnectivityState::Captive
+                        || connectivity.state == ConnectivityState::Offline)
+                        && !connectivity_checking
+                    {
+                        connectivity_recheck_count += 1;
+                        if connectivity_recheck_count >= CONNECTIVITY_RECHECK_INTERVALS {
+                            connectivity_recheck_count = 0;
+                            connectivity_checking = true;
+                            start_connectivity_check(self_cid, connectivity::check_url(&pddb));
+                        }
+                    }
                     if let Some(ssid_stats) = wifi_stats_cache.ssid.as_mut() {
                         let rssi_u8 = com.wlan_get_rssi().ok().unwrap_or(255);
                         // only send an update if the RSSI changed
@@ -779,6 +840,7 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
             Some(ConnectionManagerOpcode::DisconnectAndStop) => {
                 run.store(false, Ordering::SeqCst);
                 wifi_state = WifiState::Off;
+                static_ipv4 = None;
                 com.wlan_leave().expect("couldn't issue leave command");
                 ssid_list.clear();
                 intervals_without_activity = 0;
@@ -814,6 +876,17 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                 intervals_without_activity = 0;
                 scan_count = 0;
             }),
+            Some(ConnectionManagerOpcode::ConnectivityResult) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let result = buffer.to_original::<Connectivity, _>().unwrap();
+                connectivity_checking = false;
+                // the link may have gone down while the check was running
+                if wifi_state == WifiState::Connected && result != connectivity {
+                    log::info!("connectivity: {:?} {:?}", result.state, result.portal);
+                    connectivity = result;
+                    notify_connectivity(net_cid, &status_subscribers, connectivity);
+                }
+            }
             Some(ConnectionManagerOpcode::Quit) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
                 send_message(
                     run_cid,
@@ -829,66 +902,138 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                 log::error!("couldn't convert opcode: {:?}", msg);
             }
         }
+
+        // check every new connection for a captive portal, and forget the result once it is gone
+        if wifi_state == WifiState::Connected {
+            if connectivity.state == ConnectivityState::Unknown && !connectivity_checking {
+                connectivity_checking = true;
+                connectivity_recheck_count = 0;
+                start_connectivity_check(self_cid, connectivity::check_url(&pddb));
+            }
+        } else if connectivity.state != ConnectivityState::Unknown {
+            connectivity = Connectivity::default();
+            notify_connectivity(net_cid, &status_subscribers, connectivity);
+        }
     }
     unsafe { xous::disconnect(self_cid).ok() };
     xous::destroy_server(sid).unwrap();
 }
 
-fn get_next_ssid(
-    ssid_list_map: &mut HashMap<String, SsidOrdByRssi>,
-    ssid_attempted: &mut HashSet<String>,
-    ap_list: HashSet<String>,
-) -> Option<String> {
-    log::trace!("ap_list: {:?}", ap_list);
-    log::trace!("ssid_list: {:?}", ssid_list_map);
-    // 0. convert the HashMap of ssid_list into a HashSet
-    let mut ssid_list = HashSet::<String>::new();
-    for ssid in ssid_list_map.keys() {
-        ssid_list.insert(ssid.to_string());
-    }
-    // 1. find the intersection of ap_list and ssid_list to create a candidate_list
-    let all_candidate_list_ref = ap_list.intersection(&ssid_list).collect::<HashSet<_>>();
-    // this copy is required to perform the next set computation
-    let mut all_candidate_list = HashSet::<String>::new();
-    for c in all_candidate_list_ref {
-        all_candidate_list.insert(String::from(c));
+/// Loads the profiles of the saved networks in `ap_list`
+fn load_profiles(pddb: &pddb::Pddb, ap_list: HashSet<String>) -> HashMap<String, WifiProfile> {
+    let mut profiles = HashMap::<String, WifiProfile>::new();
+    for ap in ap_list {
+        let profile = match pddb.get(PROFILE_DICT_NAME, &ap, None, false, false, None, None::<fn()>) {
+            Ok(mut key) => {
+                let mut bytes = Vec::<u8>::new();
+                key.read_to_end(&mut bytes).ok();
+                WifiProfile::decode(&bytes).unwrap_or_else(|| {
+                    log::warn!("profile for {} is not readable, using defaults", ap);
+                    WifiProfile::default()
+                })
+            }
+            // networks saved before profiles existed don't have one
+            Err(_) => WifiProfile::default(),
+        };
+        profiles.insert(ap, profile);
     }
-    log::trace!("intersection: {:?}", all_candidate_list);
+    profiles
+}
 
-    log::trace!("ssids already attempted: {:?}", ssid_attempted);
-    // 2. find the complement of ssid_attempted and candidate_list
-    let untried_candidate_list_ref = all_candidate_list.difference(ssid_attempted).collect::<HashSet<_>>();
-    // this copy breaks the mutability issue with changing ssid_attempted after the difference is computed
-    let mut untried_candidate_list = HashSet::<String>::new();
-    for c in untried_candidate_list_ref {
-        untried_candidate_list.insert(String::from(c));
+/// Tells the net server to configure the interface with `config` instead of DHCP, or to go back to
+/// DHCP if `config` is `None`.
+fn set_static_config(net_cid: xous::CID, config: Option<WifiStaticIpv4>) {
+    let buf = Buffer::into_buf(config).or(Err(xous::Error::InternalError)).unwrap();
+    match buf.send(net_cid, Opcode::WlanStaticConfig.to_u32().unwrap()) {
+        Err(e) => log::warn!("Couldn't set the static IP configuration: {:?}", e),
+        _ => (),
     }
-    log::trace!("untried_candidates: {:?}", untried_candidate_list);
+}
 
-    if untried_candidate_list.len() > 0 {
-        if let Some(candidate) = untried_candidate_list.into_iter().next() {
-            ssid_attempted.insert(candidate.to_string());
-            log::debug!("SSID connect attempt: {:?}", candidate);
-            Some(candidate.to_string())
-        } else {
-            log::error!("We should have had at least one item in the candidate list, but found none.");
-            None
+/// Replaces the DHCP-derived parts of the status reported by the EC with a static configuration
+fn apply_static_config(status: &mut WlanStatus, config: &WifiStaticIpv4) {
+    status.ipv4.addr = config.addr;
+    status.ipv4.gtwy = config.gateway;
+    status.ipv4.mask = config.mask();
+    status.ipv4.dns1 = config.dns.unwrap_or(config.gateway);
+    status.ipv4.dns2 = [0, 0, 0, 0];
+    status.ipv4.dhcp = com_rs::DhcpState::Bound;
+}
+
+/// Runs a connectivity check on its own thread, as it can block for a while. The result is reported back
+/// with `ConnectivityResult`.
+fn start_connectivity_check(cm_cid: xous::CID, url: String) {
+    let _ = std::thread::spawn(move || {
+        let result = connectivity::probe(&url);
+        let buf = Buffer::into_buf(result).or(Err(xous::Error::InternalError)).unwrap();
+        match buf.send(cm_cid, ConnectionManagerOpcode::ConnectivityResult.to_u32().unwrap()) {
+            Err(e) => log::warn!("Couldn't report the connectivity check result: {:?}", e),
+            _ => (),
+        }
+    });
+}
+
+/// Relays a change of connectivity to the net server, which answers `GetConnectivity` from its copy, and
+/// to the subscribers
+fn notify_connectivity(
+    net_cid: xous::CID,
+    status_subscribers: &HashMap<xous::CID, WifiStateSubscription>,
+    connectivity: Connectivity,
+) {
+    let buf = Buffer::into_buf(connectivity).or(Err(xous::Error::InternalError)).unwrap();
+    match buf.send(net_cid, Opcode::ConnectivityUpdate.to_u32().unwrap()) {
+        Err(e) => log::warn!("Couldn't update the net server connectivity: {:?}", e),
+        _ => (),
+    }
+    for &sub in status_subscribers.keys() {
+        let buf = Buffer::into_buf(connectivity).or(Err(xous::Error::InternalError)).unwrap();
+        match buf.send(sub, WifiStateCallback::Connectivity.to_u32().unwrap()) {
+            Err(e) => log::warn!("Couldn't update wifi state subscriber: {:?}", e),
+            _ => (),
         }
+    }
+}
+
+/// Picks the next network to join: the untried, auto-joined network with the highest priority, and among
+/// those the one with the strongest signal. Hidden networks can't be seen in a scan, so they are always
+/// candidates, ranked after the visible networks of the same priority. Returns `None` when there are no
+/// candidates, or when all of them have been tried, in which case the next call starts over.
+pub(crate) fn get_next_ssid(
+    ssid_list: &HashMap<String, SsidOrdByRssi>,
+    ssid_attempted: &mut HashSet<String>,
+    profiles: &HashMap<String, WifiProfile>,
+) -> Option<String> {
+    log::trace!("profiles: {:?}", profiles);
+    log::trace!("ssid_list: {:?}", ssid_list);
+    log::trace!("ssids already attempted: {:?}", ssid_attempted);
+    let mut candidates = profiles
+        .iter()
+        .filter(|(_ssid, profile)| profile.autojoin)
+        .filter_map(|(ssid, profile)| match ssid_list.get(ssid) {
+            Some(seen) => Some((ssid, profile.priority, seen.rssi)),
+            // rssi is in -dBm, so 255 ranks last
+            None if profile.hidden => Some((ssid, profile.priority, u8::MAX)),
+            None => None,
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)).then(a.0.cmp(b.0)));
+    log::trace!("candidates: {:?}", candidates);
+
+    if let Some((candidate, _priority, _rssi)) =
+        candidates.iter().find(|(ssid, _priority, _rssi)| !ssid_attempted.contains(*ssid))
+    {
+        ssid_attempted.insert(candidate.to_string());
+        log::debug!("SSID connect attempt: {:?}", candidate);
+        Some(candidate.to_string())
     } else {
-        // clear the ssid_attempted list and start from scratch
-        log::debug!("Exhausted all candidates, starting over again...");
-        ssid_attempted.clear();
-        if let Some(candidate) = all_candidate_list.iter().next() {
-            ssid_attempted.insert(candidate.to_string());
-            log::debug!("SSID connect attempt: {:?}", candidate);
-            Some(candidate.to_string())
-        } else {
+        if candidates.is_empty() {
             log::info!("No SSID candidates visible. Debug dump:");
-            log::info!("ap_list: {:?}", ap_list);
-            log::info!("ssid_list: {:?}", ssid_list_map);
-            log::info!("candidate list: {:?}", all_candidate_list);
-            log::info!("untried candidate list: {:?}", untried_candidate_list);
-            None
+            log::info!("profiles: {:?}", profiles);
+            log::info!("ssid_list: {:?}", ssid_list);
+        } else {
+            log::debug!("Exhausted all candidates, starting over again...");
         }
+        ssid_attempted.clear();
+        None
     }
 }
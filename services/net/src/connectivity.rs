@@ -0,0 +1,143 @@
+// Captive portal detection
+//
+// Hotel and café networks commonly hand out an address with DHCP, then intercept all traffic until
+// the user signs in on a web page. The link looks healthy, but every TLS connection fails. To tell
+// these networks apart, the connection manager fetches a connectivity-check URL over plain HTTP once
+// the interface is configured: the check server answers `204 No Content`, while a portal answers
+// with a redirect to its sign-in page, or with the page itself.
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::api::*;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// only the status line and headers are of interest
+const PROBE_RESPONSE_MAX: usize = 4096;
+
+/// Returns the connectivity-check URL configured in the PDDB, or the default one
+pub(crate) fn check_url(pddb: &pddb::Pddb) -> String {
+    if let Ok(mut key) =
+        pddb.get(NET_CONFIG_DICT_NAME, CONNECTIVITY_CHECK_KEY, None, false, false, None, None::<fn()>)
+    {
+        let mut url = String::new();
+        if key.read_to_string(&mut url).is_ok() && split_url(url.trim()).is_some() {
+            return url.trim().to_string();
+        }
+        log::warn!("connectivity check URL in the PDDB is not a plain http URL, using the default");
+    }
+    CONNECTIVITY_CHECK_DEFAULT.to_string()
+}
+
+/// Fetches `url`, and classifies the network by the response. Blocks for up to a few times
+/// `PROBE_TIMEOUT`, so it should not be called from a server loop.
+pub(crate) fn probe(url: &str) -> Connectivity {
+    let offline = Connectivity { state: ConnectivityState::Offline, portal: None };
+    let (host, port, path) = match split_url(url) {
+        Some(parts) => parts,
+        None => {
+            log::warn!("connectivity check URL is not a plain http URL: {}", url);
+            return offline;
+        }
+    };
+    // portals generally answer DNS queries, so a failed lookup means there is no usable upstream
+    let addr = match (host, port).to_socket_addrs().ok().and_then(|mut addrs| addrs.next()) {
+        Some(addr) => addr,
+        None => {
+            log::info!("connectivity check: couldn't resolve {}", host);
+            return offline;
+        }
+    };
+    let mut stream = match TcpStream::connect_timeout(&addr, PROBE_TIMEOUT) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::info!("connectivity check: couldn't connect to {:?}: {:?}", addr, e);
+            return offline;
+        }
+    };
+    stream.set_read_timeout(Some(PROBE_TIMEOUT)).ok();
+    stream.set_write_timeout(Some(PROBE_TIMEOUT)).ok();
+    let host_header = if port == 80 { host.to_string() } else { format!("{}:{}", host, port) };
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Xous\r\nConnection: close\r\n\r\n",
+        path, host_header
+    );
+    if let Err(e) = stream.write_all(request.as_bytes()) {
+        log::info!("connectivity check: couldn't send request: {:?}", e);
+        return offline;
+    }
+    let mut response = Vec::new();
+    let mut chunk = [0u8; 512];
+    while response.len() < PROBE_RESPONSE_MAX {
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => response.extend_from_slice(&chunk[..len]),
+            // a timeout after the headers arrived still leaves a usable response
+            Err(_) => break,
+        }
+        if response.windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    parse_response(url, &response)
+}
+
+/// Classifies the network by the response to a request for `url`
+///
+/// Only a redirect to a sign-in page, or a page served in place of the check, is taken for a portal. Any
+/// other answer (an error, an empty `200`, a redirect to nowhere) says the upstream isn't usable, but not
+/// where to sign in, so it is reported as `Offline` and checked again later.
+pub(crate) fn parse_response(url: &str, response: &[u8]) -> Connectivity {
+    let offline = Connectivity { state: ConnectivityState::Offline, portal: None };
+    let text = String::from_utf8_lossy(response);
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((&text, ""));
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .filter(|line| line.starts_with("HTTP/"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok());
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+    let header =
+        |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, value)| *value);
+    let portal = match status {
+        Some(204) => return Connectivity { state: ConnectivityState::Online, portal: None },
+        Some(code) if (300..400).contains(&code) => match header("location") {
+            Some(location) if !location.is_empty() => location,
+            _ => return offline,
+        },
+        Some(200) => {
+            // only the start of the body is read, so also trust the headers announcing one
+            let has_body = !body.is_empty()
+                || header("content-length").and_then(|len| len.parse::<usize>().ok()).unwrap_or(0) > 0
+                || header("transfer-encoding").is_some_and(|te| te.eq_ignore_ascii_case("chunked"));
+            if !has_body {
+                return offline;
+            }
+            // a portal that serves its page in place of the check URL is signed into at that URL
+            url
+        }
+        _ => return offline,
+    };
+    Connectivity {
+        state: ConnectivityState::Captive,
+        portal: Some(xous_ipc::String::<256>::from_str(portal)),
+    }
+}
+
+/// Splits a plain `http://host[:port][/path]` URL into its parts
+fn split_url(url: &str) -> Option<(&str, u16, &str)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().ok()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() { None } else { Some((host, port, path)) }
+}
@@ -12,6 +12,10 @@ use smoltcp::wire::{
     Ipv4Packet, Ipv4Repr, /* IpProtocol, TcpPacket, TcpRepr, IpAddress, UdpPacket, UdpRepr */
 };
 
+use crate::capture::{Direction, PacketCapture};
+#[cfg(unix)]
+use crate::hosted::HostLink;
+use crate::wireguard::{WgTunnel, WgTx, WG_FRAME_MTU};
 use crate::{IPV4_ADDRESS, MAC_ADDRESS_LSB, MAC_ADDRESS_MSB};
 
 pub struct NetPhy {
@@ -22,6 +26,32 @@ pub struct NetPhy {
     loopback_conn: xous::CID,
     // tracks the length (and count) of the loopback packets pending
     loopback_pending: Arc<Mutex<VecDeque<u16>>>,
+    // WireGuard tunnel, if it is up
+    tunnel: Option<WgTunnel>,
+    // hosted builds can exchange frames with the host instead of the stubbed EC
+    #[cfg(unix)]
+    host: Option<HostLink>,
+    // packet capture, kept after it is stopped until it is exported
+    capture: Option<PacketCapture>,
+}
+
+/// Where outgoing frames go: the EC, or the host link on hosted builds that have one
+#[derive(Clone, Copy)]
+struct FrameSink<'a> {
+    com: &'a Com,
+    #[cfg(unix)]
+    host: Option<&'a HostLink>,
+}
+
+impl FrameSink<'_> {
+    fn send(&self, frame: &[u8]) {
+        #[cfg(unix)]
+        if let Some(host) = self.host {
+            host.send(frame);
+            return;
+        }
+        self.com.wlan_send_packet(frame).expect("driver error sending WLAN packet");
+    }
 }
 
 impl<'a> NetPhy {
@@ -33,6 +63,40 @@ impl<'a> NetPhy {
             rx_avail: None,
             loopback_conn,
             loopback_pending: Arc::new(Mutex::new(VecDeque::new())),
+            tunnel: None,
+            #[cfg(unix)]
+            host: None,
+            capture: None,
+        }
+    }
+
+    /// Starts a packet capture, discarding any previous one
+    pub(crate) fn capture_start(&mut self, ring_len: usize, snaplen: usize) {
+        self.capture = Some(PacketCapture::new(ring_len, snaplen));
+    }
+
+    pub(crate) fn capture_mut(&mut self) -> Option<&mut PacketCapture> { self.capture.as_mut() }
+
+    /// Attaches the interface to the host instead of the EC
+    #[cfg(unix)]
+    pub(crate) fn set_host_link(&mut self, host: HostLink) { self.host = Some(host); }
+
+    /// Brings the WireGuard tunnel up (`Some`) or down (`None`)
+    pub(crate) fn set_tunnel(&mut self, tunnel: Option<WgTunnel>) { self.tunnel = tunnel; }
+
+    pub(crate) fn tunnel(&self) -> Option<&WgTunnel> { self.tunnel.as_ref() }
+
+    pub(crate) fn tunnel_mut(&mut self) -> Option<&mut WgTunnel> { self.tunnel.as_mut() }
+
+    /// Runs the WireGuard protocol timers, if the tunnel is up
+    pub(crate) fn tunnel_update_timers(&mut self) {
+        let sink = FrameSink {
+            com: &self.com,
+            #[cfg(unix)]
+            host: self.host.as_ref(),
+        };
+        if let Some(tunnel) = self.tunnel.as_mut() {
+            tunnel.update_timers(|frame| sink.send(frame));
         }
     }
 
@@ -52,43 +116,80 @@ impl phy::Device for NetPhy {
     type RxToken<'a> = NetPhyRxToken<'a>;
     type TxToken<'a> = NetPhyTxToken<'a>;
 
-    fn receive(
-        &mut self,
-        _instant: smoltcp::time::Instant,
-    ) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+    fn receive(&mut self, instant: smoltcp::time::Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let timestamp_ms = instant.total_millis() as u64;
         let csum_copy = self.capabilities().checksum.clone();
+        let sink = FrameSink {
+            com: &self.com,
+            #[cfg(unix)]
+            host: self.host.as_ref(),
+        };
         if let Some(rx_len) = self.loopback_pending.lock().unwrap().pop_front() {
             log::debug!("loopback injected {} bytes", rx_len);
             // loopback takes precedence
             self.com
                 .wlan_fetch_loopback_packet(&mut self.rx_buffer[..rx_len as usize])
                 .expect("Couldn't call wlan_fetch_packet in device adapter");
+            if let Some(capture) = self.capture.as_mut() {
+                capture.record(timestamp_ms, Direction::Inbound, &self.rx_buffer[..rx_len as usize]);
+            }
 
             Some((
                 NetPhyRxToken { buf: &mut self.rx_buffer[..rx_len as usize] },
                 NetPhyTxToken {
                     buf: &mut self.tx_buffer[..],
-                    com: &self.com,
+                    sink,
                     loopback_conn: self.loopback_conn,
                     loopback_count: self.loopback_pending.clone(),
                     caps: csum_copy,
+                    tunnel: self.tunnel.as_mut(),
+                    capture: self.capture.as_mut(),
+                    timestamp_ms,
                 },
             ))
         } else {
-            if let Some(rx_len) = self.rx_avail.take() {
+            #[cfg(unix)]
+            let host_rx = match sink.host {
+                Some(host) => host.recv(&mut self.rx_buffer),
+                None => None,
+            };
+            #[cfg(not(unix))]
+            let host_rx: Option<usize> = None;
+            let rx_len = match host_rx {
+                Some(rx_len) => Some(rx_len),
+                None => match self.rx_avail.take() {
+                    Some(rx_len) => {
+                        self.com
+                            .wlan_fetch_packet(&mut self.rx_buffer[..rx_len as usize])
+                            .expect("Couldn't call wlan_fetch_packet in device adapter");
+                        Some(rx_len as usize)
+                    }
+                    None => None,
+                },
+            };
+            if let Some(mut rx_len) = rx_len {
                 log::debug!("device rx of {} bytes", rx_len);
-                self.com
-                    .wlan_fetch_packet(&mut self.rx_buffer[..rx_len as usize])
-                    .expect("Couldn't call wlan_fetch_packet in device adapter");
+                if let Some(tunnel) = self.tunnel.as_mut() {
+                    match tunnel.decapsulate_frame(&mut self.rx_buffer[..rx_len], |frame| sink.send(frame)) {
+                        Some(len) => rx_len = len,
+                        None => return None,
+                    }
+                }
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.record(timestamp_ms, Direction::Inbound, &self.rx_buffer[..rx_len]);
+                }
 
                 Some((
-                    NetPhyRxToken { buf: &mut self.rx_buffer[..rx_len as usize] },
+                    NetPhyRxToken { buf: &mut self.rx_buffer[..rx_len] },
                     NetPhyTxToken {
                         buf: &mut self.tx_buffer[..],
-                        com: &self.com,
+                        sink,
                         loopback_conn: self.loopback_conn,
                         loopback_count: self.loopback_pending.clone(),
                         caps: csum_copy,
+                        tunnel: self.tunnel.as_mut(),
+                        capture: self.capture.as_mut(),
+                        timestamp_ms,
                     },
                 ))
             } else {
@@ -98,21 +199,28 @@ impl phy::Device for NetPhy {
         }
     }
 
-    fn transmit(&mut self, _instant: smoltcp::time::Instant) -> Option<Self::TxToken<'_>> {
+    fn transmit(&mut self, instant: smoltcp::time::Instant) -> Option<Self::TxToken<'_>> {
         let csum_copy = self.capabilities().checksum.clone();
         log::debug!("device tx");
         Some(NetPhyTxToken {
             buf: &mut self.tx_buffer[..],
-            com: &self.com,
+            sink: FrameSink {
+                com: &self.com,
+                #[cfg(unix)]
+                host: self.host.as_ref(),
+            },
             loopback_conn: self.loopback_conn,
             loopback_count: self.loopback_pending.clone(),
             caps: csum_copy,
+            tunnel: self.tunnel.as_mut(),
+            capture: self.capture.as_mut(),
+            timestamp_ms: instant.total_millis() as u64,
         })
     }
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut caps = DeviceCapabilities::default();
-        caps.max_transmission_unit = NET_MTU;
+        caps.max_transmission_unit = if self.tunnel.is_some() { WG_FRAME_MTU } else { NET_MTU };
         caps.max_burst_size = Some(1);
         caps.medium = Medium::Ethernet;
         caps
@@ -136,10 +244,13 @@ impl<'a, 'c> phy::RxToken for NetPhyRxToken<'a> {
 
 pub struct NetPhyTxToken<'a> {
     buf: &'a mut [u8],
-    com: &'a Com,
+    sink: FrameSink<'a>,
     loopback_conn: xous::CID,
     loopback_count: Arc<Mutex<VecDeque<u16>>>,
     caps: ChecksumCapabilities,
+    tunnel: Option<&'a mut WgTunnel>,
+    capture: Option<&'a mut PacketCapture>,
+    timestamp_ms: u64,
 }
 impl<'a> NetPhyTxToken<'a> {
     /// Initiates the Rx side of things to read out the loopback packet that was queued
@@ -187,18 +298,21 @@ impl<'a> NetPhyTxToken<'a> {
         }
         let pkt = frame.into_inner().to_vec();
         log::debug!("stuffing arp {:?}", pkt);
-        self.com.wlan_queue_loopback(&pkt);
+        self.sink.com.wlan_queue_loopback(&pkt);
         self.loopback_rx(pkt.len());
     }
 }
 
 impl<'a> phy::TxToken for NetPhyTxToken<'a> {
-    fn consume<R, F>(self, len: usize, f: F) -> R
+    fn consume<R, F>(mut self, len: usize, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
     {
         let result = f(&mut self.buf[..len]);
         log::debug!("txlen: {}", len);
+        if let Some(capture) = self.capture.as_mut() {
+            capture.record(self.timestamp_ms, Direction::Outbound, &self.buf[..len]);
+        }
 
         {
             // this is a hack to make loopbacks work on smoltcp. Work-around taken from Redox, but tracking
@@ -212,12 +326,12 @@ impl<'a> phy::TxToken for NetPhyTxToken<'a> {
                     log::debug!("loopback packet redirect");
                     // override the destination address to be our own address
                     eth_frame.set_dst_addr(smoltcp::wire::EthernetAddress(local_hwaddr));
-                    self.com.wlan_queue_loopback(&self.buf[..len]);
+                    self.sink.com.wlan_queue_loopback(&self.buf[..len]);
                     self.loopback_rx(len);
                     return result;
                 } else if eth_frame.dst_addr().as_bytes() == local_hwaddr {
                     log::debug!("loopback return packet redirect");
-                    self.com.wlan_queue_loopback(&self.buf[..len]);
+                    self.sink.com.wlan_queue_loopback(&self.buf[..len]);
                     self.loopback_rx(len);
                     return result;
                 }
@@ -314,7 +428,7 @@ impl<'a> phy::TxToken for NetPhyTxToken<'a> {
                                     */
 
                                     let buf_to_send = eth_frame.into_inner();
-                                    self.com.wlan_queue_loopback(buf_to_send);
+                                    self.sink.com.wlan_queue_loopback(buf_to_send);
                                     /* // for double-checking the RX packet
                                         if let Ok(check_frame) = EthernetFrame::new_checked(&buf_to_send) {
                                             if let Ok(check_pkt) = Ipv4Packet::new_checked(&check_frame.payload()) {
@@ -395,9 +509,20 @@ impl<'a> phy::TxToken for NetPhyTxToken<'a> {
                 }
             }
         }
+        // packets routed through the WireGuard tunnel are sent to the peer instead
+        if let Some(tunnel) = self.tunnel {
+            match tunnel.encapsulate_frame(&mut self.buf[..len]) {
+                WgTx::Bypass => {}
+                WgTx::Send(frame) => {
+                    self.sink.send(frame);
+                    return result;
+                }
+                WgTx::Consumed => return result,
+            }
+        }
         // forward the packet on if it's not a loopback (loopback will call return early and exit before
         // getting to this line)
-        self.com.wlan_send_packet(&self.buf[..len]).expect("driver error sending WLAN packet");
+        self.sink.send(&self.buf[..len]);
 
         result
     }
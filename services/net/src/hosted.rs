@@ -0,0 +1,201 @@
+// Hosted-mode frame link
+//
+// In hosted builds the `com` service is a stub, so frames handed to it go nowhere and the smoltcp
+// path can't be exercised. Setting `XOUS_NET_TAP=<ifname>` connects the interface to a Linux TAP
+// device instead, and `XOUS_NET_FIXTURE=1` connects it to `fixture::Fixture`, a DHCP and DNS
+// stand-in that runs in a thread on the other end of a socket pair. Either way the interface then
+// configures itself with DHCP, as it would on a real network.
+//
+// The TAP device must already exist and be owned by the user running Xous, with a DHCP server
+// listening on it, e.g.:
+//
+//   sudo ip tuntap add dev tap0 mode tap user $USER
+//   sudo ip addr add 10.0.2.1/24 dev tap0 && sudo ip link set tap0 up
+//   sudo dnsmasq --interface=tap0 --bind-interfaces --dhcp-range=10.0.2.15,10.0.2.50
+pub(crate) mod fixture;
+
+use std::collections::VecDeque;
+#[cfg(target_os = "linux")]
+use std::fs::File;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use com::api::{Ipv4Conf, NET_MTU};
+use num_traits::*;
+use smoltcp::socket::dhcpv4;
+
+/// frames beyond this many waiting for the interface are dropped, like a full EC buffer would
+const RX_QUEUE_MAX: usize = 64;
+
+enum Endpoint {
+    #[cfg(target_os = "linux")]
+    Tap(File),
+    Socket(UnixDatagram),
+}
+
+impl Endpoint {
+    fn send(&self, frame: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Endpoint::Tap(file) => io::Write::write(&mut &*file, frame),
+            Endpoint::Socket(socket) => socket.send(frame),
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Endpoint::Tap(file) => io::Read::read(&mut &*file, buf),
+            Endpoint::Socket(socket) => socket.recv(buf),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Endpoint> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Endpoint::Tap(file) => Ok(Endpoint::Tap(file.try_clone()?)),
+            Endpoint::Socket(socket) => Ok(Endpoint::Socket(socket.try_clone()?)),
+        }
+    }
+}
+
+/// Exchanges Ethernet frames with the host in place of the EC
+pub(crate) struct HostLink {
+    tx: Endpoint,
+    rx: Arc<Mutex<VecDeque<Vec<u8>>>>,
+}
+
+impl HostLink {
+    /// Opens the link selected by the environment, if any. `pump_cid` is sent a `NetPump` whenever
+    /// frames arrive.
+    pub(crate) fn from_env(pump_cid: xous::CID) -> Option<HostLink> {
+        #[cfg(target_os = "linux")]
+        if let Ok(name) = std::env::var("XOUS_NET_TAP") {
+            match open_tap(&name).and_then(|tap| HostLink::start(Endpoint::Tap(tap), pump_cid)) {
+                Ok(link) => {
+                    log::info!("hosted network is attached to TAP device {}", name);
+                    return Some(link);
+                }
+                Err(e) => log::error!("couldn't attach to TAP device {}: {:?}", name, e),
+            }
+        }
+        if std::env::var("XOUS_NET_FIXTURE").map(|v| v != "0").unwrap_or(false) {
+            let link = UnixDatagram::pair().and_then(|(ours, theirs)| {
+                fixture::Fixture::new().spawn(theirs);
+                HostLink::start(Endpoint::Socket(ours), pump_cid)
+            });
+            match link {
+                Ok(link) => {
+                    log::info!("hosted network is attached to the test fixture");
+                    return Some(link);
+                }
+                Err(e) => log::error!("couldn't start the network test fixture: {:?}", e),
+            }
+        }
+        None
+    }
+
+    fn start(endpoint: Endpoint, pump_cid: xous::CID) -> io::Result<HostLink> {
+        let rx = Arc::new(Mutex::new(VecDeque::new()));
+        let reader = endpoint.try_clone()?;
+        thread::spawn({
+            let rx = rx.clone();
+            move || {
+                let mut buf = [0u8; NET_MTU];
+                loop {
+                    let len = match reader.recv(&mut buf) {
+                        Ok(len) => len,
+                        Err(e) => {
+                            log::error!(
+                                "hosted network link failed, no more frames will be received: {:?}",
+                                e
+                            );
+                            return;
+                        }
+                    };
+                    {
+                        let mut rx = rx.lock().unwrap();
+                        if rx.len() >= RX_QUEUE_MAX {
+                            log::warn!("hosted network rx queue is full, dropping a frame");
+                            continue;
+                        }
+                        rx.push_back(buf[..len].to_vec());
+                    }
+                    xous::try_send_message(
+                        pump_cid,
+                        xous::Message::new_scalar(crate::Opcode::NetPump.to_usize().unwrap(), 0, 0, 0, 0),
+                    )
+                    .ok();
+                }
+            }
+        });
+        Ok(HostLink { tx: endpoint, rx })
+    }
+
+    pub(crate) fn send(&self, frame: &[u8]) {
+        if let Err(e) = self.tx.send(frame) {
+            log::warn!("hosted network couldn't send a {}-byte frame: {:?}", frame.len(), e);
+        }
+    }
+
+    /// Copies the next received frame into `buf`, returning its length
+    pub(crate) fn recv(&self, buf: &mut [u8]) -> Option<usize> {
+        let frame = self.rx.lock().unwrap().pop_front()?;
+        let len = frame.len().min(buf.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+        Some(len)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_tap(name: &str) -> io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+    #[repr(C)]
+    struct IfReq {
+        name: [u8; libc::IFNAMSIZ],
+        flags: libc::c_short,
+        _pad: [u8; 22],
+    }
+    if name.is_empty() || name.len() >= libc::IFNAMSIZ {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "bad interface name"));
+    }
+    let file = std::fs::OpenOptions::new().read(true).write(true).open("/dev/net/tun")?;
+    let mut ifreq = IfReq {
+        name: [0; libc::IFNAMSIZ],
+        flags: (libc::IFF_TAP | libc::IFF_NO_PI) as libc::c_short,
+        _pad: [0; 22],
+    };
+    ifreq.name[..name.len()].copy_from_slice(name.as_bytes());
+    if unsafe { libc::ioctl(file.as_raw_fd(), TUNSETIFF as _, &mut ifreq) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Converts a DHCP event into the configuration the EC would have reported, along with the prefix
+/// length. Returns `None` once the lease is lost.
+pub(crate) fn dhcp_config(event: dhcpv4::Event, mac: [u8; 6]) -> Option<(Ipv4Conf, u8)> {
+    match event {
+        dhcpv4::Event::Configured(config) => {
+            let dns = |i: usize| config.dns_servers.get(i).map(|a| a.0).unwrap_or([0; 4]);
+            let prefix = config.address.prefix_len();
+            Some((
+                Ipv4Conf {
+                    dhcp: com_rs::DhcpState::Bound,
+                    mac,
+                    addr: config.address.address().0,
+                    gtwy: config.router.map(|r| r.0).unwrap_or([0; 4]),
+                    mask: u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0).to_be_bytes(),
+                    dns1: dns(0),
+                    dns2: dns(1),
+                },
+                prefix,
+            ))
+        }
+        dhcpv4::Event::Deconfigured => None,
+    }
+}
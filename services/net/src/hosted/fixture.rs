@@ -0,0 +1,287 @@
+// DHCP and DNS stand-in for hosted-mode tests
+//
+// The fixture plays the network the interface is attached to: it is the gateway at `GATEWAY`,
+// answers ARP for it and pings to it, leases `CLIENT_ADDR` over DHCP, and resolves the names it was
+// given over DNS. It also keeps track of the multicast groups the interface reports with IGMP.
+// Everything else is dropped, so tests that need a peer beyond the gateway should use a TAP device
+// instead.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::os::unix::net::UnixDatagram;
+use std::thread;
+
+use com::api::NET_MTU;
+
+pub(crate) const FIXTURE_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+pub(crate) const GATEWAY: [u8; 4] = [10, 0, 2, 1];
+pub(crate) const CLIENT_ADDR: [u8; 4] = [10, 0, 2, 15];
+pub(crate) const NETMASK: [u8; 4] = [255, 255, 255, 0];
+/// resolves to the gateway
+pub(crate) const FIXTURE_HOST: &str = "fixture.test";
+const LEASE_SECS: u32 = 86400;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_IGMP: u8 = 2;
+const IP_PROTO_UDP: u8 = 17;
+const DHCP_MAGIC: [u8; 4] = [99, 130, 83, 99];
+const DHCP_DISCOVER: u8 = 1;
+const DHCP_OFFER: u8 = 2;
+const DHCP_REQUEST: u8 = 3;
+const DHCP_ACK: u8 = 5;
+const DNS_TYPE_A: u16 = 1;
+const IGMP_V1_REPORT: u8 = 0x12;
+const IGMP_V2_REPORT: u8 = 0x16;
+const IGMP_LEAVE: u8 = 0x17;
+
+pub(crate) struct Fixture {
+    hosts: HashMap<String, [u8; 4]>,
+    groups: RefCell<Vec<[u8; 4]>>,
+}
+
+impl Fixture {
+    pub(crate) fn new() -> Fixture {
+        let mut hosts = HashMap::new();
+        hosts.insert(FIXTURE_HOST.to_string(), GATEWAY);
+        Fixture { hosts, groups: RefCell::new(Vec::new()) }
+    }
+
+    /// Adds a name for the DNS server to resolve
+    pub(crate) fn with_host(mut self, name: &str, addr: [u8; 4]) -> Fixture {
+        self.hosts.insert(name.to_ascii_lowercase(), addr);
+        self
+    }
+
+    /// Serves the interface on the other end of `socket` until it goes away
+    pub(crate) fn spawn(self, socket: UnixDatagram) {
+        thread::spawn(move || {
+            let mut buf = [0u8; NET_MTU];
+            while let Ok(len) = socket.recv(&mut buf) {
+                if let Some(reply) = self.handle(&buf[..len]) {
+                    if socket.send(&reply).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    /// The multicast groups the interface is a member of, in the order they were reported
+    pub(crate) fn groups(&self) -> Vec<[u8; 4]> { self.groups.borrow().clone() }
+
+    /// Returns the frame sent in response to `frame`, if any
+    pub(crate) fn handle(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        if frame.len() < 14 {
+            return None;
+        }
+        let src_mac: [u8; 6] = frame[6..12].try_into().unwrap();
+        let payload = &frame[14..];
+        let ethertype = u16::from_be_bytes([frame[12], frame[13]]);
+        let payload = match ethertype {
+            ETHERTYPE_ARP => self.arp(payload)?,
+            ETHERTYPE_IPV4 => self.ipv4(payload)?,
+            _ => return None,
+        };
+        let mut reply = src_mac.to_vec();
+        reply.extend_from_slice(&FIXTURE_MAC);
+        reply.extend_from_slice(&ethertype.to_be_bytes());
+        reply.extend_from_slice(&payload);
+        Some(reply)
+    }
+
+    fn arp(&self, arp: &[u8]) -> Option<Vec<u8>> {
+        // Ethernet/IPv4 requests for the gateway only
+        if arp.len() < 28 || arp[..8] != [0, 1, 8, 0, 6, 4, 0, 1] || arp[24..28] != GATEWAY {
+            return None;
+        }
+        let mut reply = vec![0, 1, 8, 0, 6, 4, 0, 2];
+        reply.extend_from_slice(&FIXTURE_MAC);
+        reply.extend_from_slice(&GATEWAY);
+        reply.extend_from_slice(&arp[8..18]);
+        Some(reply)
+    }
+
+    fn ipv4(&self, packet: &[u8]) -> Option<Vec<u8>> {
+        if packet.len() < 20 || packet[0] >> 4 != 4 {
+            return None;
+        }
+        let header_len = (packet[0] & 0xf) as usize * 4;
+        let total_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        let body = packet.get(header_len..total_len)?;
+        let src: [u8; 4] = packet[12..16].try_into().unwrap();
+        let dst: [u8; 4] = packet[16..20].try_into().unwrap();
+        match packet[9] {
+            IP_PROTO_ICMP if dst == GATEWAY => {
+                // echo request becomes echo reply
+                if body.len() < 8 || body[0] != 8 {
+                    return None;
+                }
+                let mut icmp = body.to_vec();
+                icmp[0] = 0;
+                icmp[2..4].copy_from_slice(&[0, 0]);
+                let sum = checksum(&[&icmp]);
+                icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+                Some(ipv4_packet(GATEWAY, src, IP_PROTO_ICMP, &icmp))
+            }
+            IP_PROTO_IGMP => {
+                self.igmp(body);
+                None
+            }
+            IP_PROTO_UDP if body.len() >= 8 => {
+                let src_port = u16::from_be_bytes([body[0], body[1]]);
+                let dst_port = u16::from_be_bytes([body[2], body[3]]);
+                match dst_port {
+                    67 => {
+                        let reply = self.dhcp(&body[8..])?;
+                        Some(udp_packet(GATEWAY, [255; 4], 67, 68, &reply))
+                    }
+                    53 if dst == GATEWAY => {
+                        let reply = self.dns(&body[8..])?;
+                        Some(udp_packet(GATEWAY, src, 53, src_port, &reply))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn igmp(&self, message: &[u8]) {
+        if message.len() < 8 {
+            return;
+        }
+        let group: [u8; 4] = message[4..8].try_into().unwrap();
+        let mut groups = self.groups.borrow_mut();
+        match message[0] {
+            IGMP_V1_REPORT | IGMP_V2_REPORT if !groups.contains(&group) => groups.push(group),
+            IGMP_LEAVE => groups.retain(|g| *g != group),
+            _ => {}
+        }
+    }
+
+    fn dhcp(&self, request: &[u8]) -> Option<Vec<u8>> {
+        if request.len() < 240 || request[0] != 1 || request[236..240] != DHCP_MAGIC {
+            return None;
+        }
+        let mut msg_type = None;
+        let mut options = &request[240..];
+        while let Some((&code, rest)) = options.split_first() {
+            match code {
+                0 => options = rest,
+                255 => break,
+                _ => {
+                    let len = *rest.first()? as usize;
+                    let data = rest.get(1..1 + len)?;
+                    if code == 53 && len == 1 {
+                        msg_type = Some(data[0]);
+                    }
+                    options = &rest[1 + len..];
+                }
+            }
+        }
+        let reply_type = match msg_type? {
+            DHCP_DISCOVER => DHCP_OFFER,
+            DHCP_REQUEST => DHCP_ACK,
+            _ => return None,
+        };
+        let mut reply = vec![0u8; 240];
+        reply[..4].copy_from_slice(&[2, 1, 6, 0]);
+        // transaction ID, seconds and flags are echoed
+        reply[4..12].copy_from_slice(&request[4..12]);
+        reply[16..20].copy_from_slice(&CLIENT_ADDR);
+        reply[20..24].copy_from_slice(&GATEWAY);
+        reply[28..44].copy_from_slice(&request[28..44]);
+        reply[236..240].copy_from_slice(&DHCP_MAGIC);
+        reply.extend_from_slice(&[53, 1, reply_type]);
+        reply.extend_from_slice(&[54, 4]);
+        reply.extend_from_slice(&GATEWAY);
+        reply.extend_from_slice(&[51, 4]);
+        reply.extend_from_slice(&LEASE_SECS.to_be_bytes());
+        reply.extend_from_slice(&[1, 4]);
+        reply.extend_from_slice(&NETMASK);
+        reply.extend_from_slice(&[3, 4]);
+        reply.extend_from_slice(&GATEWAY);
+        reply.extend_from_slice(&[6, 4]);
+        reply.extend_from_slice(&GATEWAY);
+        reply.push(255);
+        Some(reply)
+    }
+
+    fn dns(&self, query: &[u8]) -> Option<Vec<u8>> {
+        // one question, standard query
+        if query.len() < 12 || query[2] & 0xf8 != 0 || query[4..6] != [0, 1] {
+            return None;
+        }
+        let mut labels = Vec::new();
+        let mut pos = 12;
+        loop {
+            let len = *query.get(pos)? as usize;
+            pos += 1;
+            if len == 0 {
+                break;
+            }
+            labels.push(std::str::from_utf8(query.get(pos..pos + len)?).ok()?.to_ascii_lowercase());
+            pos += len;
+        }
+        let qtype = u16::from_be_bytes(query.get(pos..pos + 2)?.try_into().unwrap());
+        let question = query.get(12..pos + 4)?;
+        let addr = self.hosts.get(&labels.join("."));
+        let answer = addr.filter(|_| qtype == DNS_TYPE_A);
+
+        let mut reply = query[..2].to_vec();
+        // response, recursion desired and available, NXDOMAIN for unknown names
+        reply.extend_from_slice(&[0x81, if addr.is_some() { 0x80 } else { 0x83 }]);
+        reply.extend_from_slice(&[0, 1, 0, answer.is_some() as u8, 0, 0, 0, 0]);
+        reply.extend_from_slice(question);
+        if let Some(addr) = answer {
+            // name is a pointer to the question
+            reply.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+            reply.extend_from_slice(addr);
+        }
+        Some(reply)
+    }
+}
+
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    for part in parts {
+        for chunk in part.chunks(2) {
+            sum += u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]) as u32;
+        }
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn ipv4_packet(src: [u8; 4], dst: [u8; 4], protocol: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0x45, 0];
+    packet.extend_from_slice(&(20 + body.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+    packet.extend_from_slice(&src);
+    packet.extend_from_slice(&dst);
+    let sum = checksum(&[&packet]);
+    packet[10..12].copy_from_slice(&sum.to_be_bytes());
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn udp_packet(src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let len = (8 + payload.len() as u16).to_be_bytes();
+    let mut udp = src_port.to_be_bytes().to_vec();
+    udp.extend_from_slice(&dst_port.to_be_bytes());
+    udp.extend_from_slice(&len);
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+    let pseudo = [&src[..], &dst[..], &[0, IP_PROTO_UDP], &len[..]].concat();
+    let sum = match checksum(&[&pseudo, &udp]) {
+        // an all-zero checksum means there is none
+        0 => 0xffff,
+        sum => sum,
+    };
+    udp[6..8].copy_from_slice(&sum.to_be_bytes());
+    ipv4_packet(src, dst, IP_PROTO_UDP, &udp)
+}
@@ -0,0 +1,491 @@
+// IPv6 address configuration
+//
+// The EC only does DHCP for IPv4, so IPv6 is configured here, on top of smoltcp. The interface always
+// has an EUI-64 link-local address. Once the link is up, routers are solicited, and a global address is
+// formed from the first autonomous /64 prefix that they advertise (SLAAC, RFC 4862). DNS servers come
+// from the RDNSS option of the advertisement (RFC 8106), or, if the router sets the M or O flag, from a
+// stateless DHCPv6 Information-Request (RFC 8415).
+//
+// Duplicate address detection is not performed: the interface identifier is derived from the MAC
+// address, which is unique on the link.
+use std::convert::TryInto;
+
+use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::socket::{raw, udp};
+use smoltcp::wire::{IpAddress, IpCidr, IpEndpoint, IpProtocol, IpVersion, Ipv6Address};
+
+use crate::api::*;
+
+const RTR_SOLICITATIONS: u32 = 3;
+const RTR_SOLICITATION_INTERVAL_MS: u64 = 4_000;
+const DHCPV6_TRANSMISSIONS: u32 = 3;
+const DHCPV6_RETRANSMIT_MS: u64 = 2_000;
+const DHCPV6_CLIENT_PORT: u16 = 546;
+const DHCPV6_SERVER_PORT: u16 = 547;
+/// the SLAAC interface identifier is 64 bits
+const SLAAC_PREFIX_LEN: u8 = 64;
+
+const ALL_ROUTERS: [u8; 16] = [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02];
+const ALL_DHCP_SERVERS: [u8; 16] = [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0, 0x02];
+
+const IPV6_HEADER_LEN: usize = 40;
+const NEXT_HEADER_ICMPV6: u8 = 58;
+const ICMPV6_ROUTER_SOLICIT: u8 = 133;
+const ICMPV6_ROUTER_ADVERT: u8 = 134;
+const NDISC_OPT_SOURCE_LLADDR: u8 = 1;
+const NDISC_OPT_PREFIX_INFO: u8 = 3;
+const NDISC_OPT_RDNSS: u8 = 25;
+const RA_FLAG_MANAGED: u8 = 0x80;
+const RA_FLAG_OTHER: u8 = 0x40;
+const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+
+const DHCPV6_INFORMATION_REQUEST: u8 = 11;
+const DHCPV6_REPLY: u8 = 7;
+const DHCPV6_OPT_CLIENTID: u16 = 1;
+const DHCPV6_OPT_ORO: u16 = 6;
+const DHCPV6_OPT_ELAPSED_TIME: u16 = 8;
+const DHCPV6_OPT_DNS_SERVERS: u16 = 23;
+
+/// An autonomous prefix from a router advertisement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AdvertisedPrefix {
+    pub prefix: [u8; 16],
+    pub len: u8,
+    /// seconds, `u32::MAX` is infinite
+    pub valid_lifetime: u32,
+}
+
+/// The parts of a router advertisement that matter for address configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RouterAdvert {
+    /// seconds; 0 means the router is not a default router
+    pub router_lifetime: u16,
+    /// addresses are assigned with DHCPv6
+    pub managed: bool,
+    /// other configuration (DNS) is available with DHCPv6
+    pub other: bool,
+    pub prefixes: Vec<AdvertisedPrefix>,
+    pub dns: Vec<[u8; 16]>,
+}
+
+struct Dhcpv6Request {
+    xid: [u8; 3],
+    transmissions: u32,
+    next_ms: u64,
+}
+
+pub(crate) struct Ipv6Autoconf {
+    mac: [u8; 6],
+    raw_handle: SocketHandle,
+    dhcp_handle: SocketHandle,
+    solicitations: u32,
+    next_solicit_ms: u64,
+    /// address, prefix length, and expiry
+    addr: Option<([u8; 16], u8, Option<u64>)>,
+    /// link-local address of the default router, and expiry
+    router: Option<([u8; 16], u64)>,
+    dns: Vec<[u8; 16]>,
+    dhcp: Option<Dhcpv6Request>,
+    xid_seed: u32,
+}
+
+impl Ipv6Autoconf {
+    /// Adds the sockets for router advertisements and DHCPv6 to `sockets`, and the link-local address to
+    /// `iface`
+    pub(crate) fn new(mac: [u8; 6], seed: u32, iface: &mut Interface, sockets: &mut SocketSet) -> Self {
+        let raw_rx = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 2], vec![0; 2048]);
+        let raw_tx = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 2], vec![0; 256]);
+        let raw_handle = sockets.add(raw::Socket::new(IpVersion::Ipv6, IpProtocol::Icmpv6, raw_rx, raw_tx));
+        let dhcp_rx = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 1024]);
+        let dhcp_tx = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 256]);
+        let mut dhcp_socket = udp::Socket::new(dhcp_rx, dhcp_tx);
+        dhcp_socket.bind(DHCPV6_CLIENT_PORT).expect("couldn't bind the DHCPv6 client port");
+        let dhcp_handle = sockets.add(dhcp_socket);
+
+        let mut autoconf = Ipv6Autoconf {
+            mac,
+            raw_handle,
+            dhcp_handle,
+            solicitations: RTR_SOLICITATIONS,
+            next_solicit_ms: 0,
+            addr: None,
+            router: None,
+            dns: Vec::new(),
+            dhcp: None,
+            xid_seed: seed,
+        };
+        autoconf.set_mac(mac, iface);
+        autoconf
+    }
+
+    /// Replaces the link-local address, as the interface was rebuilt with a new MAC address
+    pub(crate) fn set_mac(&mut self, mac: [u8; 6], iface: &mut Interface) {
+        let old = link_local(self.mac);
+        self.mac = mac;
+        let new = link_local(mac);
+        iface.update_ip_addrs(|addrs| {
+            addrs.retain(|cidr| cidr.address() != IpAddress::Ipv6(Ipv6Address::from_bytes(&old)));
+            if addrs.push(IpCidr::new(IpAddress::Ipv6(Ipv6Address::from_bytes(&new)), 64)).is_err() {
+                log::warn!("no room for the IPv6 link-local address");
+            }
+        });
+    }
+
+    /// The link (re)connected: solicit routers, rather than waiting for the next periodic advertisement
+    pub(crate) fn link_up(&mut self, now_ms: u64) {
+        self.solicitations = 0;
+        self.next_solicit_ms = now_ms;
+    }
+
+    /// The link is gone: drop everything learned from it. Returns true if DNS servers were removed.
+    pub(crate) fn link_down(&mut self, iface: &mut Interface) -> bool {
+        self.solicitations = RTR_SOLICITATIONS;
+        self.dhcp = None;
+        self.remove_addr(iface);
+        if self.router.take().is_some() {
+            iface.routes_mut().remove_default_ipv6_route();
+        }
+        let had_dns = !self.dns.is_empty();
+        self.dns.clear();
+        had_dns
+    }
+
+    /// The address to originate packets from: the global address if there is one
+    pub(crate) fn source_address(&self) -> Ipv6Address {
+        Ipv6Address::from_bytes(&self.addr.map(|(addr, _, _)| addr).unwrap_or(link_local(self.mac)))
+    }
+
+    pub(crate) fn dns_servers(&self) -> &[[u8; 16]] { &self.dns }
+
+    pub(crate) fn config(&self) -> Ipv6Conf {
+        let mut dns = [None; IPV6_DNS_MAX];
+        for (slot, server) in dns.iter_mut().zip(self.dns.iter()) {
+            *slot = Some(*server);
+        }
+        Ipv6Conf {
+            link_local: link_local(self.mac),
+            addr: self.addr.map(|(addr, _, _)| addr),
+            prefix_len: self.addr.map(|(_, len, _)| len).unwrap_or(0),
+            router: self.router.map(|(router, _)| router),
+            dns,
+        }
+    }
+
+    /// Sends solicitations and requests that are due, processes advertisements and replies, and expires
+    /// stale configuration. Returns true if the DNS servers changed.
+    pub(crate) fn poll(&mut self, now_ms: u64, iface: &mut Interface, sockets: &mut SocketSet) -> bool {
+        let mut dns_changed = false;
+
+        if self.solicitations < RTR_SOLICITATIONS && now_ms >= self.next_solicit_ms {
+            let socket = sockets.get_mut::<raw::Socket>(self.raw_handle);
+            match socket.send_slice(&router_solicit(link_local(self.mac), self.mac)) {
+                Ok(_) => log::debug!("sent router solicitation"),
+                Err(e) => log::warn!("couldn't send router solicitation: {:?}", e),
+            }
+            self.solicitations += 1;
+            self.next_solicit_ms = now_ms + RTR_SOLICITATION_INTERVAL_MS;
+        }
+
+        loop {
+            let socket = sockets.get_mut::<raw::Socket>(self.raw_handle);
+            let advert = match socket.recv() {
+                Ok(packet) => parse_ipv6_router_advert(packet),
+                Err(_) => break,
+            };
+            if let Some((router, advert)) = advert {
+                dns_changed |= self.apply_router_advert(now_ms, iface, router, advert);
+            }
+        }
+
+        if let Some(request) = self.dhcp.as_mut() {
+            if request.transmissions < DHCPV6_TRANSMISSIONS && now_ms >= request.next_ms {
+                let socket = sockets.get_mut::<udp::Socket>(self.dhcp_handle);
+                let remote = IpEndpoint::new(
+                    IpAddress::Ipv6(Ipv6Address::from_bytes(&ALL_DHCP_SERVERS)),
+                    DHCPV6_SERVER_PORT,
+                );
+                match socket.send_slice(&dhcpv6_information_request(request.xid, self.mac), remote) {
+                    Ok(_) => log::debug!("sent DHCPv6 information request"),
+                    Err(e) => log::warn!("couldn't send DHCPv6 information request: {:?}", e),
+                }
+                request.transmissions += 1;
+                request.next_ms = now_ms + DHCPV6_RETRANSMIT_MS;
+            }
+        }
+        loop {
+            let socket = sockets.get_mut::<udp::Socket>(self.dhcp_handle);
+            let servers = match socket.recv() {
+                Ok((reply, _meta)) => match self.dhcp.as_ref() {
+                    Some(request) => parse_dhcpv6_reply(request.xid, reply),
+                    None => None,
+                },
+                Err(_) => break,
+            };
+            if let Some(servers) = servers {
+                log::info!("DHCPv6 DNS servers: {:x?}", servers);
+                self.dhcp = None;
+                dns_changed |= self.set_dns(servers);
+            }
+        }
+
+        if let Some((_, _, Some(expiry))) = self.addr {
+            if now_ms >= expiry {
+                log::info!("IPv6 address expired");
+                self.remove_addr(iface);
+            }
+        }
+        if let Some((_, expiry)) = self.router {
+            if now_ms >= expiry {
+                log::info!("IPv6 default router expired");
+                self.router = None;
+                iface.routes_mut().remove_default_ipv6_route();
+            }
+        }
+        dns_changed
+    }
+
+    fn apply_router_advert(
+        &mut self,
+        now_ms: u64,
+        iface: &mut Interface,
+        router: [u8; 16],
+        advert: RouterAdvert,
+    ) -> bool {
+        log::debug!("router advertisement from {:x?}: {:?}", router, advert);
+        // stop soliciting, a router answered
+        self.solicitations = RTR_SOLICITATIONS;
+
+        if advert.router_lifetime == 0 {
+            if self.router.map(|(current, _)| current == router).unwrap_or(false) {
+                self.router = None;
+                iface.routes_mut().remove_default_ipv6_route();
+            }
+        } else {
+            if self.router.map(|(current, _)| current != router).unwrap_or(true) {
+                log::info!("IPv6 default router: {:x?}", router);
+                iface.routes_mut().remove_default_ipv6_route();
+                if iface.routes_mut().add_default_ipv6_route(Ipv6Address::from_bytes(&router)).is_err() {
+                    log::warn!("no room for the IPv6 default route");
+                }
+            }
+            self.router = Some((router, now_ms + advert.router_lifetime as u64 * 1000));
+        }
+
+        for prefix in advert.prefixes.iter().filter(|p| p.len == SLAAC_PREFIX_LEN) {
+            let addr = slaac_address(&prefix.prefix, self.mac);
+            let expiry = match prefix.valid_lifetime {
+                u32::MAX => None,
+                secs => Some(now_ms + secs as u64 * 1000),
+            };
+            match self.addr {
+                Some((current, _, _)) if current == addr => {
+                    if prefix.valid_lifetime == 0 {
+                        self.remove_addr(iface);
+                    } else {
+                        self.addr = Some((addr, prefix.len, expiry));
+                    }
+                }
+                // only one global address is kept; additional prefixes are ignored
+                Some(_) => {}
+                None if prefix.valid_lifetime != 0 => {
+                    let mut added = false;
+                    iface.update_ip_addrs(|addrs| {
+                        added = addrs
+                            .push(IpCidr::new(IpAddress::Ipv6(Ipv6Address::from_bytes(&addr)), prefix.len))
+                            .is_ok();
+                    });
+                    if added {
+                        log::info!("IPv6 address configured: {:x?}/{}", addr, prefix.len);
+                        self.addr = Some((addr, prefix.len, expiry));
+                    } else {
+                        log::warn!("no room for the IPv6 address");
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let mut dns_changed = false;
+        if !advert.dns.is_empty() {
+            dns_changed = self.set_dns(advert.dns);
+        } else if (advert.managed || advert.other) && self.dns.is_empty() && self.dhcp.is_none() {
+            self.xid_seed = self.xid_seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let xid = self.xid_seed.to_be_bytes();
+            self.dhcp =
+                Some(Dhcpv6Request { xid: [xid[1], xid[2], xid[3]], transmissions: 0, next_ms: now_ms });
+        }
+        dns_changed
+    }
+
+    fn set_dns(&mut self, mut servers: Vec<[u8; 16]>) -> bool {
+        servers.truncate(IPV6_DNS_MAX);
+        if servers != self.dns {
+            self.dns = servers;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove_addr(&mut self, iface: &mut Interface) {
+        if let Some((addr, _, _)) = self.addr.take() {
+            iface.update_ip_addrs(|addrs| {
+                addrs.retain(|cidr| cidr.address() != IpAddress::Ipv6(Ipv6Address::from_bytes(&addr)))
+            });
+        }
+    }
+}
+
+/// EUI-64 interface identifier (RFC 4291 appendix A)
+fn interface_id(mac: [u8; 6]) -> [u8; 8] {
+    [mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]
+}
+
+pub(crate) fn link_local(mac: [u8; 6]) -> [u8; 16] {
+    let mut addr = [0u8; 16];
+    addr[0] = 0xfe;
+    addr[1] = 0x80;
+    addr[8..].copy_from_slice(&interface_id(mac));
+    addr
+}
+
+/// The address formed from a /64 `prefix` and the interface identifier
+pub(crate) fn slaac_address(prefix: &[u8; 16], mac: [u8; 6]) -> [u8; 16] {
+    let mut addr = *prefix;
+    addr[8..].copy_from_slice(&interface_id(mac));
+    addr
+}
+
+/// Internet checksum over the IPv6 pseudo-header and `payload`
+fn checksum(src: &[u8; 16], dst: &[u8; 16], next_header: u8, payload: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut add = |bytes: &[u8]| {
+        for chunk in bytes.chunks(2) {
+            sum += u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]) as u32;
+        }
+    };
+    add(src);
+    add(dst);
+    add(&(payload.len() as u32).to_be_bytes());
+    add(&[0, 0, 0, next_header]);
+    add(payload);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// An IPv6 packet carrying a router solicitation to all routers
+pub(crate) fn router_solicit(src: [u8; 16], mac: [u8; 6]) -> Vec<u8> {
+    let mut icmp = vec![ICMPV6_ROUTER_SOLICIT, 0, 0, 0, 0, 0, 0, 0, NDISC_OPT_SOURCE_LLADDR, 1];
+    icmp.extend_from_slice(&mac);
+    let sum = checksum(&src, &ALL_ROUTERS, NEXT_HEADER_ICMPV6, &icmp);
+    icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    let mut packet = vec![0x60, 0, 0, 0];
+    packet.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+    // neighbor discovery messages must have a hop limit of 255
+    packet.extend_from_slice(&[NEXT_HEADER_ICMPV6, 255]);
+    packet.extend_from_slice(&src);
+    packet.extend_from_slice(&ALL_ROUTERS);
+    packet.extend_from_slice(&icmp);
+    packet
+}
+
+/// Returns the source address and contents of an IPv6 packet carrying a valid router advertisement
+pub(crate) fn parse_ipv6_router_advert(packet: &[u8]) -> Option<([u8; 16], RouterAdvert)> {
+    if packet.len() < IPV6_HEADER_LEN || packet[0] >> 4 != 6 || packet[6] != NEXT_HEADER_ICMPV6 {
+        return None;
+    }
+    // a hop limit of 255 and a link-local source prove the advertisement originated on this link
+    let src: [u8; 16] = packet[8..24].try_into().unwrap();
+    if packet[7] != 255 || src[0] != 0xfe || src[1] & 0xc0 != 0x80 {
+        return None;
+    }
+    let len = u16::from_be_bytes([packet[4], packet[5]]) as usize;
+    let icmp = packet.get(IPV6_HEADER_LEN..IPV6_HEADER_LEN + len)?;
+    Some((src, parse_router_advert(icmp)?))
+}
+
+/// Parses an ICMPv6 router advertisement message
+pub(crate) fn parse_router_advert(icmp: &[u8]) -> Option<RouterAdvert> {
+    if icmp.len() < 16 || icmp[0] != ICMPV6_ROUTER_ADVERT || icmp[1] != 0 {
+        return None;
+    }
+    let mut advert = RouterAdvert {
+        router_lifetime: u16::from_be_bytes([icmp[6], icmp[7]]),
+        managed: icmp[5] & RA_FLAG_MANAGED != 0,
+        other: icmp[5] & RA_FLAG_OTHER != 0,
+        prefixes: Vec::new(),
+        dns: Vec::new(),
+    };
+    let mut options = &icmp[16..];
+    while options.len() >= 2 {
+        let len = options[1] as usize * 8;
+        if len == 0 || len > options.len() {
+            // malformed, RFC 4861 says to discard the whole message
+            return None;
+        }
+        let option = &options[..len];
+        match option[0] {
+            NDISC_OPT_PREFIX_INFO if len == 32 => {
+                if option[3] & PREFIX_FLAG_AUTONOMOUS != 0 {
+                    advert.prefixes.push(AdvertisedPrefix {
+                        prefix: option[16..32].try_into().unwrap(),
+                        len: option[2],
+                        valid_lifetime: u32::from_be_bytes(option[4..8].try_into().unwrap()),
+                    });
+                }
+            }
+            NDISC_OPT_RDNSS if len >= 24 => {
+                let lifetime = u32::from_be_bytes(option[4..8].try_into().unwrap());
+                if lifetime != 0 {
+                    for server in option[8..].chunks_exact(16) {
+                        advert.dns.push(server.try_into().unwrap());
+                    }
+                }
+            }
+            _ => {}
+        }
+        options = &options[len..];
+    }
+    Some(advert)
+}
+
+/// A stateless DHCPv6 Information-Request for DNS servers
+pub(crate) fn dhcpv6_information_request(xid: [u8; 3], mac: [u8; 6]) -> Vec<u8> {
+    let mut msg = vec![DHCPV6_INFORMATION_REQUEST, xid[0], xid[1], xid[2]];
+    // DUID-LL: type 3, hardware type 1 (Ethernet)
+    msg.extend_from_slice(&DHCPV6_OPT_CLIENTID.to_be_bytes());
+    msg.extend_from_slice(&10u16.to_be_bytes());
+    msg.extend_from_slice(&[0, 3, 0, 1]);
+    msg.extend_from_slice(&mac);
+    msg.extend_from_slice(&DHCPV6_OPT_ORO.to_be_bytes());
+    msg.extend_from_slice(&2u16.to_be_bytes());
+    msg.extend_from_slice(&DHCPV6_OPT_DNS_SERVERS.to_be_bytes());
+    msg.extend_from_slice(&DHCPV6_OPT_ELAPSED_TIME.to_be_bytes());
+    msg.extend_from_slice(&2u16.to_be_bytes());
+    msg.extend_from_slice(&0u16.to_be_bytes());
+    msg
+}
+
+/// Returns the DNS servers in a DHCPv6 reply to the request with transaction ID `xid`
+pub(crate) fn parse_dhcpv6_reply(xid: [u8; 3], reply: &[u8]) -> Option<Vec<[u8; 16]>> {
+    if reply.len() < 4 || reply[0] != DHCPV6_REPLY || reply[1..4] != xid {
+        return None;
+    }
+    let mut servers = Vec::new();
+    let mut options = &reply[4..];
+    while options.len() >= 4 {
+        let code = u16::from_be_bytes([options[0], options[1]]);
+        let len = u16::from_be_bytes([options[2], options[3]]) as usize;
+        let data = options.get(4..4 + len)?;
+        if code == DHCPV6_OPT_DNS_SERVERS {
+            for server in data.chunks_exact(16) {
+                servers.push(server.try_into().unwrap());
+            }
+        }
+        options = &options[4 + len..];
+    }
+    Some(servers)
+}
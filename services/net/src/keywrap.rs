@@ -0,0 +1,68 @@
+// Key wrapping on behalf of other processes
+//
+// `tls` stores the private key of the client identity in the PDDB, wrapped with the user's root key.
+// The root keys server only accepts a handful of connections, so rather than every process that links
+// `tls` opening its own, the wrap and unwrap requests are funneled through this thread. The
+// connection is opened on the first request, and the thread is separate from the net main loop
+// because `RootKeys` may put up a password prompt and block until the user answers it.
+use num_traits::*;
+use root_keys::api::{AesRootkeyType, KeywrapError};
+use xous_ipc::Buffer;
+
+use crate::api::*;
+
+pub(crate) fn keywrap_server(sid: xous::SID) {
+    let xns = xous_names::XousNames::new().unwrap();
+    let mut rootkeys: Option<root_keys::RootKeys> = None;
+    loop {
+        let mut msg = xous::receive_message(sid).unwrap();
+        let id = msg.body.id();
+        let op: Option<KeyWrapOpcode> = FromPrimitive::from_usize(id);
+        let mut buf = match msg.body.memory_message_mut() {
+            Some(mem) => unsafe { Buffer::from_memory_message_mut(mem) },
+            None => {
+                log::error!("keywrap: scalar message for {:?}, ignoring", op);
+                continue;
+            }
+        };
+        let mut request = buf.to_original::<KeyWrapIpc, _>().unwrap();
+        let keys = rootkeys.get_or_insert_with(|| {
+            root_keys::RootKeys::new(&xns, Some(AesRootkeyType::User0)).expect("can't connect to root keys")
+        });
+        let len = (request.len as usize).min(request.data.len());
+        let result = match op {
+            Some(KeyWrapOpcode::Wrap) => keys.wrap_key(&request.data[..len]),
+            Some(KeyWrapOpcode::Unwrap) => {
+                match keys.unwrap_key(&request.data[..len], request.expected_len as usize) {
+                    // a key wrapped with the legacy algorithm still unwraps; the caller only
+                    // needs the plaintext
+                    Err(KeywrapError::UpgradeToNew((key, _upgrade))) => Ok(key),
+                    r => r,
+                }
+            }
+            None => {
+                log::error!("keywrap: unknown opcode {}", id);
+                Err(KeywrapError::InvalidDataSize)
+            }
+        };
+        request.data.iter_mut().for_each(|b| *b = 0);
+        match result {
+            Ok(out) if out.len() <= request.data.len() => {
+                request.data[..out.len()].copy_from_slice(&out);
+                request.len = out.len() as u32;
+                request.ok = true;
+            }
+            Ok(_) => {
+                log::error!("keywrap: result does not fit the response");
+                request.len = 0;
+                request.ok = false;
+            }
+            Err(e) => {
+                log::warn!("keywrap: {:?} failed: {:?}", op, e);
+                request.len = 0;
+                request.ok = false;
+            }
+        }
+        buf.replace(request).expect("couldn't return keywrap response");
+    }
+}
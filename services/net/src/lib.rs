@@ -91,6 +91,13 @@ impl NetManager {
         }
     }
 
+    /// Returns the IPv6 configuration acquired from router advertisements and DHCPv6
+    pub fn get_ipv6_config(&self) -> Result<Ipv6Conf, xous::Error> {
+        let mut buf = Buffer::into_buf(Ipv6Conf::default()).map_err(|_| xous::Error::InternalError)?;
+        buf.lend_mut(self.netconn.conn(), Opcode::GetIpv6Config.to_u32().unwrap())?;
+        buf.to_original::<Ipv6Conf, _>().map_err(|_| xous::Error::InternalError)
+    }
+
     pub fn reset(&self) {
         send_message(
             self.netconn.conn(),
@@ -103,6 +110,26 @@ impl NetManager {
     /// the cached value from the connection manager. The direct call to the COM could cause too much
     /// congestion.
     pub fn wifi_state_subscribe(&mut self, return_cid: CID, opcode: u32) -> Result<(), xous::Error> {
+        self.wifi_state_subscribe_inner(return_cid, opcode, None)
+    }
+
+    /// Like `wifi_state_subscribe`, but also forwards a `Connectivity` to `connectivity_opcode` whenever
+    /// the result of the captive portal check changes.
+    pub fn wifi_state_subscribe_with_connectivity(
+        &mut self,
+        return_cid: CID,
+        opcode: u32,
+        connectivity_opcode: u32,
+    ) -> Result<(), xous::Error> {
+        self.wifi_state_subscribe_inner(return_cid, opcode, Some(connectivity_opcode))
+    }
+
+    fn wifi_state_subscribe_inner(
+        &mut self,
+        return_cid: CID,
+        opcode: u32,
+        connectivity_opcode: Option<u32>,
+    ) -> Result<(), xous::Error> {
         if self.wifi_state_cid.is_none() {
             let onetime_sid = xous::create_server().unwrap();
             let sub = WifiStateSubscription { sid: onetime_sid.to_array(), opcode };
@@ -129,6 +156,18 @@ impl NetManager {
                                 let buf = Buffer::into_buf(sub).expect("couldn't convert to memory message");
                                 buf.lend(return_cid, opcode).expect("couldn't forward state update");
                             }
+                            Some(WifiStateCallback::Connectivity) => {
+                                if let Some(connectivity_opcode) = connectivity_opcode {
+                                    let buffer = unsafe {
+                                        Buffer::from_memory_message(msg.body.memory_message().unwrap())
+                                    };
+                                    let connectivity = buffer.to_original::<Connectivity, _>().unwrap();
+                                    let buf = Buffer::into_buf(connectivity)
+                                        .expect("couldn't convert to memory message");
+                                    buf.lend(return_cid, connectivity_opcode)
+                                        .expect("couldn't forward connectivity update");
+                                }
+                            }
                             Some(WifiStateCallback::Drop) => {
                                 xous::return_scalar(msg.sender, 1).unwrap();
                                 break;
@@ -190,6 +229,13 @@ impl NetManager {
         Ok((ret, ssid_list.state))
     }
 
+    /// Returns whether the joined network reaches the internet, or is behind a captive portal
+    pub fn connectivity(&self) -> Result<Connectivity, xous::Error> {
+        let mut buf = Buffer::into_buf(Connectivity::default()).map_err(|_| xous::Error::InternalError)?;
+        buf.lend_mut(self.netconn.conn(), Opcode::GetConnectivity.to_u32().unwrap())?;
+        buf.to_original::<Connectivity, _>().map_err(|_| xous::Error::InternalError)
+    }
+
     pub fn connection_manager_stop(&self) -> Result<(), xous::Error> {
         send_message(
             self.netconn.conn(),
@@ -229,7 +275,178 @@ impl NetManager {
         )
         .map(|_| ())
     }
+
+    /// Returns the state of the WireGuard tunnel
+    pub fn wg_status(&self) -> Result<WgStatus, xous::Error> {
+        let mut buf = Buffer::into_buf(WgStatus::default()).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::WgGetStatus.to_u32().unwrap())?;
+        buf.to_original::<WgStatus, _>().or(Err(xous::Error::InternalError))
+    }
+
+    /// Returns the WireGuard public key of this device, which is configured on the peer. The key pair is
+    /// generated on first use, and `None` is returned if the PDDB is not mounted.
+    pub fn wg_public_key(&self) -> Result<Option<[u8; 32]>, xous::Error> {
+        let mut buf = Buffer::into_buf(None::<[u8; 32]>).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::WgGetPublicKey.to_u32().unwrap())?;
+        buf.to_original::<Option<[u8; 32]>, _>().or(Err(xous::Error::InternalError))
+    }
+
+    /// Saves the WireGuard peer configuration to the PDDB. It takes effect the next time the
+    /// tunnel is brought up.
+    pub fn wg_set_peer(&self, config: &WgPeerConfig) -> Result<(), xous::Error> {
+        let mut buf = Buffer::into_buf(*config).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::WgSetPeer.to_u32().unwrap())?;
+        match buf.to_original::<NetMemResponse, _>() {
+            Ok(NetMemResponse::Ok) => Ok(()),
+            Ok(NetMemResponse::Invalid) => Err(xous::Error::InvalidString),
+            Ok(NetMemResponse::AccessDenied) => Err(xous::Error::AccessDenied),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+
+    /// Returns the WireGuard peer configuration saved in the PDDB
+    pub fn wg_get_peer(&self) -> Result<Option<WgPeerConfig>, xous::Error> {
+        let mut buf = Buffer::into_buf(None::<WgPeerConfig>).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::WgGetPeer.to_u32().unwrap())?;
+        buf.to_original::<Option<WgPeerConfig>, _>().or(Err(xous::Error::InternalError))
+    }
+
+    /// Brings up the WireGuard tunnel with the configuration saved in the PDDB
+    pub fn wg_up(&self) -> Result<(), xous::Error> { self.wg_up_down(true) }
+
+    pub fn wg_down(&self) -> Result<(), xous::Error> { self.wg_up_down(false) }
+
+    fn wg_up_down(&self, up: bool) -> Result<(), xous::Error> {
+        match send_message(
+            self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::WgUpDown.to_usize().unwrap(), up as usize, 0, 0, 0),
+        )? {
+            xous::Result::Scalar1(1) => Ok(()),
+            _ => Err(xous::Error::UseBeforeInit),
+        }
+    }
+
+    /// Brings down the WireGuard tunnel, and deletes its keys and peer configuration from the PDDB
+    pub fn wg_clear(&self) -> Result<(), xous::Error> {
+        send_message(
+            self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::WgClear.to_usize().unwrap(), 0, 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Starts capturing the frames passing through the WLAN interface into a ring of `ring_len` bytes
+    /// (0 for `CAPTURE_DEFAULT_BYTES`), discarding any previous capture. Frames are truncated to
+    /// `snaplen` bytes; 0 keeps them whole.
+    pub fn capture_start(&self, ring_len: usize, snaplen: usize) -> Result<(), xous::Error> {
+        send_message(
+            self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::CaptureStart.to_usize().unwrap(), ring_len, snaplen, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Stops the packet capture, and returns the number of frames held for export
+    pub fn capture_stop(&self) -> Result<u32, xous::Error> {
+        match send_message(
+            self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::CaptureStop.to_usize().unwrap(), 0, 0, 0, 0),
+        )? {
+            xous::Result::Scalar1(frames) => Ok(frames as u32),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+
+    pub fn capture_status(&self) -> Result<CaptureStatus, xous::Error> {
+        let mut buf = Buffer::into_buf(CaptureStatus::default()).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::CaptureGetStatus.to_u32().unwrap())?;
+        buf.to_original::<CaptureStatus, _>().or(Err(xous::Error::InternalError))
+    }
+
+    /// Returns the packet capture as a pcapng file. The capture may still be running.
+    pub fn capture_export(&self) -> Result<Vec<u8>, xous::Error> {
+        const CHUNK_LEN: usize = 64 * 1024;
+        let chunk = xous::map_memory(None, None, CHUNK_LEN, xous::MemoryFlags::R | xous::MemoryFlags::W)?;
+        let mut pcapng = Vec::new();
+        let result = loop {
+            let msg = xous::MemoryMessage {
+                id: Opcode::CaptureExport.to_usize().unwrap(),
+                buf: chunk,
+                offset: xous::MemoryAddress::new(pcapng.len()),
+                valid: None,
+            };
+            match send_message(self.netconn.conn(), Message::MutableBorrow(msg)) {
+                Ok(xous::Result::MemoryReturned(_, valid)) => {
+                    let len = valid.map(|v| v.get()).unwrap_or(0).min(CHUNK_LEN);
+                    // Safety: `u8` contains no undefined values
+                    pcapng.extend_from_slice(unsafe { &chunk.as_slice::<u8>()[..len] });
+                    if len < CHUNK_LEN {
+                        break Ok(pcapng);
+                    }
+                }
+                Ok(_) => break Err(xous::Error::InternalError),
+                Err(e) => break Err(e),
+            }
+        };
+        xous::unmap_memory(chunk).ok();
+        result
+    }
 }
 impl Drop for NetManager {
     fn drop(&mut self) { self.wifi_state_unsubscribe().unwrap(); }
 }
+
+static KEYWRAP_REFCOUNT: AtomicU32 = AtomicU32::new(0);
+/// Wraps and unwraps keys with the user's root key, by way of the key wrap server in the net crate.
+/// Meant for small secrets (at most `KEYWRAP_MAX_DATA` bytes) that are kept in the PDDB. Either call
+/// may block on a password prompt.
+#[derive(Debug)]
+pub struct KeyWrapper {
+    conn: CID,
+}
+impl KeyWrapper {
+    pub fn new(xns: &xous_names::XousNames) -> Result<Self, xous::Error> {
+        let conn = xns.request_connection_blocking(api::SERVER_NAME_KEYWRAP)?;
+        KEYWRAP_REFCOUNT.fetch_add(1, Ordering::Relaxed);
+        Ok(KeyWrapper { conn })
+    }
+
+    pub fn wrap(&self, key: &[u8]) -> Result<Vec<u8>, xous::Error> {
+        self.transact(KeyWrapOpcode::Wrap, key, 0)
+    }
+
+    /// `expected_len` is the length of the plaintext key
+    pub fn unwrap(&self, wrapped: &[u8], expected_len: usize) -> Result<Vec<u8>, xous::Error> {
+        self.transact(KeyWrapOpcode::Unwrap, wrapped, expected_len)
+    }
+
+    fn transact(&self, op: KeyWrapOpcode, data: &[u8], expected_len: usize) -> Result<Vec<u8>, xous::Error> {
+        if data.len() > KEYWRAP_MAX_DATA + 8 || expected_len > KEYWRAP_MAX_DATA {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut request = KeyWrapIpc {
+            data: [0u8; KEYWRAP_MAX_DATA + 8],
+            len: data.len() as u32,
+            expected_len: expected_len as u32,
+            ok: false,
+        };
+        request.data[..data.len()].copy_from_slice(data);
+        let mut buf = Buffer::into_buf(request).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, op.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let response = buf.to_original::<KeyWrapIpc, _>().unwrap();
+        if response.ok {
+            Ok(response.data[..response.len as usize].to_vec())
+        } else {
+            Err(xous::Error::AccessDenied)
+        }
+    }
+}
+impl Drop for KeyWrapper {
+    fn drop(&mut self) {
+        if KEYWRAP_REFCOUNT.fetch_sub(1, Ordering::Relaxed) == 1 {
+            unsafe {
+                xous::disconnect(self.conn).unwrap();
+            }
+        }
+    }
+}
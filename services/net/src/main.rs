@@ -14,14 +14,21 @@ use com::api::{ComIntSources, Ipv4Conf};
 use num_traits::*;
 use std_tcplistener::*;
 
+mod capture;
 mod connection_manager;
+mod connectivity;
 mod device;
+#[cfg(unix)]
+mod hosted;
+mod ipv6;
+mod keywrap;
+mod wireguard;
 
 #[cfg(test)]
 mod tests;
 
 use core::num::NonZeroU64;
-use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, Ordering};
 use std::cmp::Ordering as CmpOrdering;
 use std::collections::HashMap;
 use std::convert::TryInto;
@@ -33,6 +40,8 @@ use byteorder::{ByteOrder, NetworkEndian};
 use smoltcp::iface::SocketHandle;
 use smoltcp::iface::{Config, Interface, SocketSet};
 use smoltcp::phy::{Device, Tracer};
+#[cfg(unix)]
+use smoltcp::socket::dhcpv4;
 use smoltcp::socket::{icmp, tcp, udp};
 use smoltcp::time::{Duration, Instant};
 use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address};
@@ -42,6 +51,8 @@ use xous_ipc::Buffer;
 
 // 0 indicates no address is currently assigned
 pub static IPV4_ADDRESS: AtomicU32 = AtomicU32::new(0);
+// prefix length of the subnet of IPV4_ADDRESS
+pub static IPV4_PREFIX_LEN: AtomicU8 = AtomicU8::new(0);
 // stash the MAC address for inserstion as a loopback target. Coded as big-end bytes.
 pub static MAC_ADDRESS_LSB: AtomicU32 = AtomicU32::new(0);
 pub static MAC_ADDRESS_MSB: AtomicU16 = AtomicU16::new(0);
@@ -133,6 +144,114 @@ fn set_com_ints(com_int_list: &mut Vec<ComIntSources>) {
     com_int_list.push(ComIntSources::Invalid);
 }
 
+/// Points the DNS server at the DNS of the WireGuard tunnel if it has one, otherwise at the DNS servers of
+/// the WLAN IPv4 and IPv6 configurations.
+fn notify_dns(
+    dns_allclear_hook: &mut XousScalarEndpoint,
+    dns_ipv4_hook: &mut XousScalarEndpoint,
+    dns_ipv6_hook: &mut XousScalarEndpoint,
+    net_config: Option<&Ipv4Conf>,
+    ipv6: &ipv6::Ipv6Autoconf,
+    tunnel: Option<&wireguard::WgTunnel>,
+) {
+    let mut servers = Vec::<[u8; 4]>::new();
+    let mut servers_ipv6 = Vec::<[u8; 16]>::new();
+    if let Some(dns) = tunnel.and_then(|tunnel| tunnel.config().dns) {
+        // the tunnel only carries IPv4, queries to IPv6 servers would bypass it
+        servers.push(dns);
+    } else {
+        if let Some(config) = net_config {
+            servers.push(config.dns1);
+            // the current implementation always returns 0.0.0.0 as the second dns,
+            // ignore this if that's what we've got; otherwise, pass it on.
+            if config.dns2 != [0, 0, 0, 0] {
+                servers.push(config.dns2);
+            }
+        }
+        servers_ipv6.extend_from_slice(ipv6.dns_servers());
+    }
+    dns_allclear_hook.notify();
+    for server in servers {
+        dns_ipv4_hook.notify_custom_args([Some(u32::from_be_bytes(server)), None, None, None]);
+    }
+    for server in servers_ipv6 {
+        let mut words = [None; 4];
+        for (word, bytes) in words.iter_mut().zip(server.chunks_exact(4)) {
+            *word = Some(u32::from_be_bytes(bytes.try_into().unwrap()));
+        }
+        dns_ipv6_hook.notify_custom_args(words);
+    }
+}
+
+/// The MAC address of the interface, as last reported by the EC
+fn mac_address() -> [u8; 6] {
+    let mut mac = [0u8; 6];
+    mac[0..2].copy_from_slice(&MAC_ADDRESS_MSB.load(Ordering::SeqCst).to_be_bytes());
+    mac[2..6].copy_from_slice(&MAC_ADDRESS_LSB.load(Ordering::SeqCst).to_be_bytes());
+    mac
+}
+
+/// The prefix length of a netmask handed out by DHCP, /24 if the mask is missing or malformed
+fn mask_prefix(mask: [u8; 4]) -> u8 {
+    let mask = u32::from_be_bytes(mask);
+    if mask != 0 && mask.leading_ones() == mask.count_ones() { mask.count_ones() as u8 } else { 24 }
+}
+
+/// Configures the interface with a newly acquired IPv4 configuration
+fn set_ipv4_config(
+    iface: &mut Interface,
+    phy: &mut device::NetPhy,
+    trng: &mut trng::Trng,
+    wg_store: Option<&mut wireguard::WgStore>,
+    config: &Ipv4Conf,
+    prefix: u8,
+) {
+    // update a static variable that tracks this, useful for e.g. UDP bind address checking
+    IPV4_ADDRESS.store(u32::from_be_bytes(config.addr), Ordering::SeqCst);
+    IPV4_PREFIX_LEN.store(prefix, Ordering::SeqCst);
+
+    if config.addr != [127, 0, 0, 1] {
+        // note: ARP cache is stale. Maybe that's ok?
+        iface.update_ip_addrs(|ip_addrs| {
+            // IPv6 addresses are managed by `ipv6::Ipv6Autoconf`
+            ip_addrs.retain(|cidr| matches!(cidr, IpCidr::Ipv6(_)));
+            ip_addrs
+                .push(IpCidr::new(
+                    IpAddress::v4(config.addr[0], config.addr[1], config.addr[2], config.addr[3]),
+                    prefix,
+                ))
+                .unwrap();
+            // ...and the loopback interface
+            ip_addrs.push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8)).unwrap();
+        });
+    } else {
+        log::warn!("Attempt to update the loopback interface! Ignoring.");
+    }
+    // reset the default route, in case it has changed
+    iface.routes_mut().remove_default_ipv4_route();
+    iface
+        .routes_mut()
+        .add_default_ipv4_route(Ipv4Address::new(
+            config.gtwy[0],
+            config.gtwy[1],
+            config.gtwy[2],
+            config.gtwy[3],
+        ))
+        .unwrap();
+
+    if let Some(tunnel) = phy.tunnel_mut() {
+        // the gateway may have changed along with the network
+        tunnel.forget_gateway();
+    } else if let Some(store) = wg_store {
+        if store.peer().is_some_and(|peer| peer.autoconnect) {
+            if let Some(tunnel) = store.tunnel(trng) {
+                log::info!("bringing up the wireguard tunnel");
+                phy.set_tunnel(Some(tunnel));
+            }
+        }
+    }
+}
+
 fn main() -> ! {
     log_server::init_wait().unwrap();
     log::set_max_level(log::LevelFilter::Info);
@@ -165,6 +284,12 @@ fn main() -> ! {
     com.ints_get_active(&mut com_int_list).ok();
     log::debug!("COM pending interrupts after enabling: {:?}", com_int_list);
     let mut net_config: Option<Ipv4Conf> = None;
+    // set by the connection manager when the network is configured statically
+    let mut static_ipv4: Option<WifiStaticIpv4> = None;
+    // pushed by the connection manager whenever it changes
+    let mut connectivity = Connectivity::default();
+    // the WireGuard keys and peer, read once the PDDB is mounted
+    let mut wg_store: Option<wireguard::WgStore> = None;
 
     // ----------- build the device
     let mut config_valid = true;
@@ -211,7 +336,17 @@ fn main() -> ! {
     };
     config.random_seed = trng.get_u64().unwrap();
 
-    let device = device::NetPhy::new(&xns, net_cid);
+    #[allow(unused_mut)]
+    let mut device = device::NetPhy::new(&xns, net_cid);
+    // in hosted mode, the interface can be attached to the host and configure itself with DHCP
+    #[cfg(unix)]
+    let host_link = match hosted::HostLink::from_env(net_cid) {
+        Some(link) => {
+            device.set_host_link(link);
+            true
+        }
+        None => false,
+    };
     let mut device = Tracer::new(device, |_timestamp, _printer| {
         log::trace!("{}", _printer);
     });
@@ -230,6 +365,9 @@ fn main() -> ! {
         let icmp_socket = sockets.get_mut::<icmp::Socket>(icmp_handle);
         icmp_socket.bind(icmp::Endpoint::Ident(PING_IDENT)).expect("couldn't bind to icmp socket");
     }
+    let mut ipv6 = ipv6::Ipv6Autoconf::new(mac_address(), trng.get_u32().unwrap(), &mut iface, &mut sockets);
+    #[cfg(unix)]
+    let host_dhcp = if host_link { Some(sockets.add(dhcpv4::Socket::new())) } else { None };
 
     // ------------- libstd variant -----------
     // Each process keeps track of its own sockets. These are kept in a Vec. When a handle
@@ -273,6 +411,9 @@ fn main() -> ! {
     // incoming UDP socket data.
     let mut udp_rx_waiting: Vec<Option<UdpStdState>> = Vec::new();
 
+    // Multicast groups and broadcast permission of the UDP sockets
+    let mut udp_membership = UdpMembership::default();
+
     // ------------- native variant -----------
     let mut seq: u16 = 0;
     // this record stores the origin time + IP address of the outgoing ping sequence number
@@ -307,7 +448,25 @@ fn main() -> ! {
     thread::spawn({
         let activity_interval = activity_interval.clone();
         move || {
-            connection_manager::connection_manager(cm_sid, activity_interval);
+            connection_manager::connection_manager(cm_sid, net_conn, activity_interval);
+        }
+    });
+
+    // wraps keys with the root keys for `tls`; see keywrap.rs
+    let keywrap_sid =
+        xns.register_name(api::SERVER_NAME_KEYWRAP, None).expect("can't register key wrap server");
+    thread::spawn(move || keywrap::keywrap_server(keywrap_sid));
+
+    // reads the WireGuard configuration once the PDDB is mounted, without stalling the main loop
+    thread::spawn({
+        let net_conn = net_conn.clone();
+        move || {
+            pddb::Pddb::new().is_mounted_blocking();
+            xous::send_message(
+                net_conn,
+                Message::new_scalar(Opcode::WgPddbMounted.to_usize().unwrap(), 0, 0, 0, 0),
+            )
+            .expect("couldn't notify the PDDB mount");
         }
     });
 
@@ -353,6 +512,16 @@ fn main() -> ! {
                             &mut device,
                             Instant::from_millis(timer.elapsed_ms() as i64),
                         );
+                        ipv6.set_mac(hw_config.mac, &mut iface);
+                        for group in udp_membership.groups() {
+                            iface
+                                .join_multicast_group(
+                                    &mut device,
+                                    group,
+                                    Instant::from_millis(timer.elapsed_ms() as i64),
+                                )
+                                .ok();
+                        }
                         config_valid = true;
                     } else {
                         // else, config_valid stays false, and we try again next time around
@@ -501,9 +670,7 @@ fn main() -> ! {
                             icmp_repr.emit(&mut icmp_packet, &device_caps.checksum);
                         }
                         IpAddress::Ipv6(_) => {
-                            // not sure if this is a valid thing to do, to just assign the source some number
-                            // like this??
-                            let src_ipv6 = IpAddress::v6(0xfdaa, 0, 0, 0, 0, 0, 0, 1);
+                            let src_ipv6 = IpAddress::Ipv6(ipv6.source_address());
                             let icmp_repr = Icmpv6Repr::EchoRequest {
                                 ident: PING_IDENT,
                                 seq_no: seq,
@@ -1001,6 +1168,7 @@ fn main() -> ! {
                     &mut iface,
                     &mut sockets,
                     &mut udp_rx_waiting,
+                    &udp_membership,
                     process_sockets.entry(pid).or_default(),
                 );
             }
@@ -1008,7 +1176,13 @@ fn main() -> ! {
             Some(Opcode::StdUdpTx) => {
                 log::debug!("StdUdpTx");
                 let pid = msg.sender.pid();
-                std_udp_tx(msg, &mut iface, &mut sockets, process_sockets.entry(pid).or_default());
+                std_udp_tx(
+                    msg,
+                    &mut iface,
+                    &mut sockets,
+                    &udp_membership,
+                    process_sockets.entry(pid).or_default(),
+                );
                 xous::try_send_message(
                     net_conn,
                     Message::new_scalar(Opcode::NetPump.to_usize().unwrap(), 0, 0, 0, 0),
@@ -1035,6 +1209,15 @@ fn main() -> ! {
                 };
                 sockets.get_mut::<udp::Socket>(handle).close();
                 sockets.remove(handle);
+                for group in udp_membership.close(handle) {
+                    iface
+                        .leave_multicast_group(
+                            &mut device,
+                            group,
+                            Instant::from_millis(timer.elapsed_ms() as i64),
+                        )
+                        .ok();
+                }
                 if let Some(response) = msg.body.memory_message_mut() {
                     unsafe { response.buf.as_slice_mut::<u8>()[0] = 0 };
                 } else if !msg.body.has_memory() && msg.body.is_blocking() {
@@ -1042,6 +1225,54 @@ fn main() -> ! {
                 }
             }
 
+            Some(Opcode::StdUdpJoinMulticastV4) | Some(Opcode::StdUdpLeaveMulticastV4) => {
+                let pid = msg.sender.pid();
+                let join = msg.body.id() & 0x7fff == Opcode::StdUdpJoinMulticastV4.to_usize().unwrap();
+                std_udp_multicast(
+                    msg,
+                    join,
+                    &mut iface,
+                    &mut device,
+                    Instant::from_millis(timer.elapsed_ms() as i64),
+                    &mut sockets,
+                    &mut udp_membership,
+                    process_sockets.entry(pid).or_default(),
+                );
+                // send the IGMP report
+                xous::try_send_message(
+                    net_conn,
+                    Message::new_scalar(Opcode::NetPump.to_usize().unwrap(), 0, 0, 0, 0),
+                )
+                .ok();
+            }
+
+            Some(Opcode::StdUdpSetBroadcast) => {
+                let pid = msg.sender.pid();
+                std_udp_set_broadcast(
+                    msg,
+                    &mut sockets,
+                    &mut udp_membership,
+                    process_sockets.entry(pid).or_default(),
+                );
+            }
+
+            Some(Opcode::StdUdpGetBroadcast) => {
+                let pid = msg.sender.pid();
+                let connection_idx = msg.body.id() >> 16;
+                // Only work with blockingscalar messages
+                if !msg.body.is_blocking() || msg.body.has_memory() {
+                    respond_with_error(msg, NetError::LibraryError);
+                    continue;
+                }
+
+                if let Some(Some(connection)) = process_sockets.entry(pid).or_default().get(connection_idx) {
+                    let broadcast = udp_membership.broadcast(*connection);
+                    xous::return_scalar(msg.sender, if broadcast { 1 } else { 0 }).ok();
+                } else {
+                    respond_with_error(msg, NetError::Invalid);
+                }
+            }
+
             Some(Opcode::ComInterrupt) => {
                 com_int_list.clear();
                 match com.ints_get_active(&mut com_int_list) {
@@ -1081,10 +1312,13 @@ fn main() -> ! {
                                     log::warn!("Battery is critical! TODO: go into SHIP mode");
                                 }
                                 ComIntSources::WlanIpConfigUpdate => {
-                                    // right now the WLAN implementation only does IPV4. So IPV6 compatibility
-                                    // ends here. if IPV6 gets added to
-                                    // the EC/COM bus, ideally this is one of a couple spots in Xous that
-                                    // needs a tweak.
+                                    // the EC only does DHCP for IPv4; IPv6 is configured by
+                                    // `ipv6::Ipv6Autoconf` once the link
+                                    // is up.
+                                    if static_ipv4.is_some() {
+                                        log::info!("Network is configured statically, ignoring DHCP config");
+                                        continue;
+                                    }
                                     let config = match com.wlan_get_config() {
                                         Ok(config) => config,
                                         Err(e) => {
@@ -1103,62 +1337,23 @@ fn main() -> ! {
                                         xous::BOOKEND_END
                                     );
                                     net_config = Some(config);
-                                    // update a static variable that tracks this, useful for e.g. UDP bind
-                                    // address checking
-                                    IPV4_ADDRESS.store(u32::from_be_bytes(config.addr), Ordering::SeqCst);
-
-                                    if config.addr != [127, 0, 0, 1] {
-                                        // note: ARP cache is stale. Maybe that's ok?
-                                        iface.update_ip_addrs(|ip_addrs| {
-                                            ip_addrs.clear();
-                                            ip_addrs
-                                                .push(IpCidr::new(
-                                                    IpAddress::v4(
-                                                        config.addr[0],
-                                                        config.addr[1],
-                                                        config.addr[2],
-                                                        config.addr[3],
-                                                    ),
-                                                    24,
-                                                ))
-                                                .unwrap();
-                                            // ...and the loopback interface
-                                            ip_addrs
-                                                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
-                                                .unwrap();
-                                        });
-                                    } else {
-                                        log::warn!("Attempt to update the loopback interface! Ignoring.");
-                                    }
-                                    // reset the default route, in case it has changed
-                                    iface.routes_mut().remove_default_ipv4_route();
-                                    iface
-                                        .routes_mut()
-                                        .add_default_ipv4_route(Ipv4Address::new(
-                                            config.gtwy[0],
-                                            config.gtwy[1],
-                                            config.gtwy[2],
-                                            config.gtwy[3],
-                                        ))
-                                        .unwrap();
-
-                                    dns_allclear_hook.notify();
-                                    dns_ipv4_hook.notify_custom_args([
-                                        Some(u32::from_be_bytes(config.dns1)),
-                                        None,
-                                        None,
-                                        None,
-                                    ]);
-                                    // the current implementation always returns 0.0.0.0 as the second dns,
-                                    // ignore this if that's what we've got; otherwise, pass it on.
-                                    if config.dns2 != [0, 0, 0, 0] {
-                                        dns_ipv4_hook.notify_custom_args([
-                                            Some(u32::from_be_bytes(config.dns2)),
-                                            None,
-                                            None,
-                                            None,
-                                        ]);
-                                    }
+                                    set_ipv4_config(
+                                        &mut iface,
+                                        device.get_mut(),
+                                        &mut trng,
+                                        wg_store.as_mut(),
+                                        &config,
+                                        mask_prefix(config.mask),
+                                    );
+                                    ipv6.link_up(timer.elapsed_ms());
+                                    notify_dns(
+                                        &mut dns_allclear_hook,
+                                        &mut dns_ipv4_hook,
+                                        &mut dns_ipv6_hook,
+                                        net_config.as_ref(),
+                                        &ipv6,
+                                        device.get_ref().tunnel(),
+                                    );
                                 }
                                 ComIntSources::WlanRxReady => {
                                     activity_interval.store(0, Ordering::Relaxed); // reset the activity interval to 0
@@ -1259,9 +1454,53 @@ fn main() -> ! {
             }),
             Some(Opcode::NetPump) => msg_scalar_unpack!(msg, _, _, _, _, {
                 log::trace!("NetPump");
+                device.get_mut().tunnel_update_timers();
                 let now = timer.elapsed_ms();
                 let timestamp = Instant::from_millis(now as i64);
-                if !iface.poll(timestamp, &mut device, &mut sockets) {
+                let readiness_changed = iface.poll(timestamp, &mut device, &mut sockets);
+                if ipv6.poll(now, &mut iface, &mut sockets) {
+                    notify_dns(
+                        &mut dns_allclear_hook,
+                        &mut dns_ipv4_hook,
+                        &mut dns_ipv6_hook,
+                        net_config.as_ref(),
+                        &ipv6,
+                        device.get_ref().tunnel(),
+                    );
+                }
+                #[cfg(unix)]
+                if let Some(handle) = host_dhcp {
+                    if let Some(event) = sockets.get_mut::<dhcpv4::Socket>(handle).poll() {
+                        match hosted::dhcp_config(event, mac_address()) {
+                            Some((config, prefix)) => {
+                                log::info!("Hosted network config acquired: {:?}", config);
+                                net_config = Some(config);
+                                set_ipv4_config(
+                                    &mut iface,
+                                    device.get_mut(),
+                                    &mut trng,
+                                    wg_store.as_mut(),
+                                    &config,
+                                    prefix,
+                                );
+                                ipv6.link_up(now);
+                            }
+                            None => {
+                                log::warn!("Hosted network lost its DHCP lease");
+                                net_config = None;
+                            }
+                        }
+                        notify_dns(
+                            &mut dns_allclear_hook,
+                            &mut dns_ipv4_hook,
+                            &mut dns_ipv6_hook,
+                            net_config.as_ref(),
+                            &ipv6,
+                            device.get_ref().tunnel(),
+                        );
+                    }
+                }
+                if !readiness_changed {
                     // nothing to do, continue on.
                     log::debug!("No change to socket readiness");
                     continue;
@@ -1749,9 +1988,7 @@ fn main() -> ! {
                                 }
 
                                 IpAddress::Ipv6(_) => {
-                                    // NOTE: actually not sure what src_ipv6 should be. This is just from an
-                                    // example.
-                                    let src_ipv6 = IpAddress::v6(0xfdaa, 0, 0, 0, 0, 0, 0, 1);
+                                    let src_ipv6 = IpAddress::Ipv6(ipv6.source_address());
                                     let icmp_packet = Icmpv6Packet::new_checked(&payload).unwrap();
                                     let icmp_repr = Icmpv6Repr::parse(
                                         &remote_addr,
@@ -1902,6 +2139,51 @@ fn main() -> ! {
                 let ret_list = buf.to_original::<SsidList, _>().expect("couldn't restore original");
                 buffer.replace(ret_list).expect("couldn't return config");
             }
+            Some(Opcode::GetIpv6Config) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                buffer.replace(ipv6.config()).expect("couldn't return config");
+            }
+            Some(Opcode::CaptureStart) => msg_blocking_scalar_unpack!(msg, ring_len, snaplen, _, _, {
+                log::info!("starting packet capture");
+                device.get_mut().capture_start(ring_len, snaplen);
+                xous::return_scalar(msg.sender, 1).unwrap();
+            }),
+            Some(Opcode::CaptureStop) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                let frames = match device.get_mut().capture_mut() {
+                    Some(capture) => {
+                        capture.stop();
+                        capture.status().frames as usize
+                    }
+                    None => 0,
+                };
+                log::info!("stopped packet capture, holding {} frames", frames);
+                xous::return_scalar(msg.sender, frames).unwrap();
+            }),
+            Some(Opcode::CaptureGetStatus) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let status = device.get_mut().capture_mut().map(|c| c.status()).unwrap_or_default();
+                buffer.replace(status).expect("couldn't return capture status");
+            }
+            Some(Opcode::CaptureExport) => {
+                let body = msg.body.memory_message_mut().unwrap();
+                let offset = body.offset.map(|o| o.get()).unwrap_or(0);
+                let len = match device.get_mut().capture_mut() {
+                    Some(capture) => capture.export(offset, unsafe { body.buf.as_slice_mut::<u8>() }),
+                    None => 0,
+                };
+                body.valid = xous::MemorySize::new(len);
+            }
+            Some(Opcode::GetConnectivity) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                buffer.replace(connectivity).expect("couldn't return connectivity");
+            }
+            Some(Opcode::ConnectivityUpdate) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                connectivity = buffer.to_original::<Connectivity, _>().unwrap();
+            }
             Some(Opcode::ConnMgrStartStop) => msg_scalar_unpack!(msg, code, _, _, _, {
                 let cm_op = match code {
                     0 => connection_manager::ConnectionManagerOpcode::Stop.to_usize().unwrap(),
@@ -1924,9 +2206,182 @@ fn main() -> ! {
                     _ => (),
                 };
             }),
+            Some(Opcode::WgPddbMounted) => msg_scalar_unpack!(msg, _, _, _, _, {
+                let store = wg_store.get_or_insert_with(wireguard::WgStore::load);
+                // an address acquired before the mount didn't get to bring up the tunnel
+                if net_config.is_some()
+                    && device.get_ref().tunnel().is_none()
+                    && store.peer().is_some_and(|peer| peer.autoconnect)
+                {
+                    if let Some(tunnel) = store.tunnel(&mut trng) {
+                        log::info!("bringing up the wireguard tunnel");
+                        device.get_mut().set_tunnel(Some(tunnel));
+                        notify_dns(
+                            &mut dns_allclear_hook,
+                            &mut dns_ipv4_hook,
+                            &mut dns_ipv6_hook,
+                            net_config.as_ref(),
+                            &ipv6,
+                            device.get_ref().tunnel(),
+                        );
+                    }
+                }
+            }),
+            Some(Opcode::WgGetStatus) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let status = match device.get_ref().tunnel() {
+                    Some(tunnel) => tunnel.status(),
+                    None => WgStatus::default(),
+                };
+                buffer.replace(status).expect("couldn't return wireguard status");
+            }
+            Some(Opcode::WgGetPublicKey) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let public_key = wg_store.as_mut().and_then(|store| store.public_key(&mut trng));
+                buffer.replace(public_key).expect("couldn't return wireguard public key");
+            }
+            Some(Opcode::WgSetPeer) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let config = buffer.to_original::<WgPeerConfig, _>().unwrap();
+                let response = if config.endpoint == [0, 0, 0, 0]
+                    || config.port == 0
+                    || config.routes.iter().flatten().any(|route| route.prefix > 32)
+                {
+                    NetMemResponse::Invalid
+                } else if let Some(store) = wg_store.as_mut() {
+                    match store.set_peer(&mut trng, config) {
+                        Ok(_) => NetMemResponse::Ok,
+                        Err(e) => {
+                            log::error!("couldn't save the wireguard peer: {:?}", e);
+                            NetMemResponse::LibraryError
+                        }
+                    }
+                } else {
+                    NetMemResponse::AccessDenied
+                };
+                buffer.replace(response).expect("couldn't return wireguard response");
+            }
+            Some(Opcode::WgGetPeer) => {
+                let mut buffer =
+                    unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let config = wg_store.as_ref().and_then(|store| store.peer());
+                buffer.replace(config).expect("couldn't return wireguard peer");
+            }
+            Some(Opcode::WgUpDown) => msg_blocking_scalar_unpack!(msg, up, _, _, _, {
+                let ok = if up != 0 {
+                    match wg_store.as_mut().and_then(|store| store.tunnel(&mut trng)) {
+                        Some(tunnel) => {
+                            log::info!("bringing up the wireguard tunnel");
+                            device.get_mut().set_tunnel(Some(tunnel));
+                            true
+                        }
+                        None => false,
+                    }
+                } else {
+                    log::info!("bringing down the wireguard tunnel");
+                    device.get_mut().set_tunnel(None);
+                    true
+                };
+                if ok {
+                    notify_dns(
+                        &mut dns_allclear_hook,
+                        &mut dns_ipv4_hook,
+                        &mut dns_ipv6_hook,
+                        net_config.as_ref(),
+                        &ipv6,
+                        device.get_ref().tunnel(),
+                    );
+                }
+                xous::return_scalar(msg.sender, if ok { 1 } else { 0 }).unwrap();
+            }),
+            Some(Opcode::WgClear) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                if device.get_ref().tunnel().is_some() {
+                    device.get_mut().set_tunnel(None);
+                    notify_dns(
+                        &mut dns_allclear_hook,
+                        &mut dns_ipv4_hook,
+                        &mut dns_ipv6_hook,
+                        net_config.as_ref(),
+                        &ipv6,
+                        None,
+                    );
+                }
+                if let Some(store) = wg_store.as_mut() {
+                    store.clear();
+                }
+                xous::return_scalar(msg.sender, 1).unwrap();
+            }),
+            Some(Opcode::WlanStaticConfig) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                static_ipv4 = buffer.to_original::<Option<WifiStaticIpv4>, _>().unwrap();
+                if let Some(static_config) = static_ipv4 {
+                    let mut config = net_config.unwrap_or_default();
+                    config.mac[0..2].copy_from_slice(&MAC_ADDRESS_MSB.load(Ordering::SeqCst).to_be_bytes());
+                    config.mac[2..6].copy_from_slice(&MAC_ADDRESS_LSB.load(Ordering::SeqCst).to_be_bytes());
+                    config.addr = static_config.addr;
+                    config.gtwy = static_config.gateway;
+                    config.mask = static_config.mask();
+                    config.dns1 = static_config.dns.unwrap_or(static_config.gateway);
+                    config.dns2 = [0, 0, 0, 0];
+                    // apps check for a bound lease to know that the address is usable
+                    config.dhcp = com_rs::DhcpState::Bound;
+                    log::info!("Static network config: {:?}", config);
+                    net_config = Some(config);
+                    set_ipv4_config(
+                        &mut iface,
+                        device.get_mut(),
+                        &mut trng,
+                        wg_store.as_mut(),
+                        &config,
+                        static_config.prefix,
+                    );
+                    ipv6.link_up(timer.elapsed_ms());
+                    notify_dns(
+                        &mut dns_allclear_hook,
+                        &mut dns_ipv4_hook,
+                        &mut dns_ipv6_hook,
+                        net_config.as_ref(),
+                        &ipv6,
+                        device.get_ref().tunnel(),
+                    );
+                } else if net_config.take().is_some() {
+                    // back to DHCP: drop the address, like `Reset`, until a lease is acquired
+                    log::info!("Static network config cleared, waiting for DHCP");
+                    IPV4_ADDRESS.store(0, Ordering::SeqCst);
+                    IPV4_PREFIX_LEN.store(0, Ordering::SeqCst);
+                    iface.update_ip_addrs(|ip_addrs| {
+                        ip_addrs.retain(|cidr| match cidr {
+                            IpCidr::Ipv4(cidr) => cidr.address().is_loopback(),
+                            _ => true,
+                        })
+                    });
+                    iface.routes_mut().remove_default_ipv4_route();
+                    if let Some(tunnel) = device.get_mut().tunnel_mut() {
+                        tunnel.forget_gateway();
+                    }
+                    // the EC restarts DHCP when it joins the next network, and reports the lease with
+                    // `WlanIpConfigUpdate`; the hosted link runs its own client
+                    #[cfg(unix)]
+                    if let Some(handle) = host_dhcp {
+                        sockets.get_mut::<dhcpv4::Socket>(handle).reset();
+                    }
+                    notify_dns(
+                        &mut dns_allclear_hook,
+                        &mut dns_ipv4_hook,
+                        &mut dns_ipv6_hook,
+                        None,
+                        &ipv6,
+                        device.get_ref().tunnel(),
+                    );
+                }
+            }
             Some(Opcode::Reset) => {
                 // reset the DHCP address
                 IPV4_ADDRESS.store(0, Ordering::SeqCst);
+                IPV4_PREFIX_LEN.store(0, Ordering::SeqCst);
                 // ack any pending ints
                 com_int_list.clear();
                 com.ints_get_active(&mut com_int_list).ok();
@@ -1938,6 +2393,7 @@ fn main() -> ! {
 
                 // note: ARP cache isn't reset
                 iface.routes_mut().remove_default_ipv4_route();
+                ipv6.link_down(&mut iface);
                 dns_allclear_hook.notify();
 
                 match try_send_message(
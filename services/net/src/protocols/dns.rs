@@ -168,9 +168,11 @@ impl DnsServerManager {
 
     pub fn set_freeze(&mut self, freeze: bool) { self.freeze = freeze; }
 
-    /// Get one of the DNS servers. Which one we get, we don't know!
+    /// Get one of the DNS servers. Which one we get, we don't know! IPv4 servers are preferred, as the
+    /// link is only up once it has an IPv4 configuration; IPv6 servers are handed out if there are no others.
     pub fn get_random(&self) -> Option<IpAddr> {
-        if let Some(&addr) = self.servers.lock().unwrap().iter().next() { Some(addr) } else { None }
+        let servers = self.servers.lock().unwrap();
+        servers.iter().find(|addr| addr.is_ipv4()).or(servers.iter().next()).copied()
     }
 }
 
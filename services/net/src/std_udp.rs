@@ -1,4 +1,6 @@
-use smoltcp::wire::{IpAddress, IpEndpoint};
+use std::collections::HashSet;
+
+use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 use ticktimer_server::Ticktimer;
 
 use crate::*;
@@ -7,6 +9,74 @@ use crate::*;
 ///
 /// Sockets are stored in the PID/SocketHandle HashMap `process_sockets` (this is shared with TCP)
 /// `recv` requests create `UpdStdState` objects, that are stored in a `udp_rx` Vec.
+/// Multicast group membership and broadcast permission are kept in `UdpMembership`.
+
+/// Multicast groups joined, and broadcast permission granted, by libstd UDP sockets
+///
+/// smoltcp joins multicast groups per interface, so the interface joins a group when the first
+/// socket does, and leaves it when the last one leaves or closes. A socket that is in a group or
+/// allows broadcast is bound to the unspecified address instead of our IP address, so that
+/// datagrams sent to the group or broadcast address reach it.
+#[derive(Default)]
+pub(crate) struct UdpMembership {
+    groups: HashMap<SocketHandle, Vec<Ipv4Address>>,
+    broadcast: HashSet<SocketHandle>,
+}
+
+impl UdpMembership {
+    /// Records that `handle` joined `group`, returning true if the interface has to join it too
+    pub(crate) fn join(&mut self, handle: SocketHandle, group: Ipv4Address) -> Result<bool, NetError> {
+        if self.groups.get(&handle).map(|g| g.contains(&group)).unwrap_or(false) {
+            return Err(NetError::SocketInUse);
+        }
+        let first = !self.is_joined(group);
+        self.groups.entry(handle).or_default().push(group);
+        Ok(first)
+    }
+
+    /// Records that `handle` left `group`, returning true if the interface has to leave it too
+    pub(crate) fn leave(&mut self, handle: SocketHandle, group: Ipv4Address) -> Result<bool, NetError> {
+        let groups = self.groups.get_mut(&handle).ok_or(NetError::Unaddressable)?;
+        let index = groups.iter().position(|g| *g == group).ok_or(NetError::Unaddressable)?;
+        groups.swap_remove(index);
+        if groups.is_empty() {
+            self.groups.remove(&handle);
+        }
+        Ok(!self.is_joined(group))
+    }
+
+    /// Forgets a closed socket, returning the groups the interface has to leave
+    pub(crate) fn close(&mut self, handle: SocketHandle) -> Vec<Ipv4Address> {
+        self.broadcast.remove(&handle);
+        let groups = self.groups.remove(&handle).unwrap_or_default();
+        groups.into_iter().filter(|g| !self.is_joined(*g)).collect()
+    }
+
+    pub(crate) fn set_broadcast(&mut self, handle: SocketHandle, broadcast: bool) {
+        if broadcast {
+            self.broadcast.insert(handle);
+        } else {
+            self.broadcast.remove(&handle);
+        }
+    }
+
+    pub(crate) fn broadcast(&self, handle: SocketHandle) -> bool { self.broadcast.contains(&handle) }
+
+    /// Whether `handle` has to stay bound to the unspecified address
+    pub(crate) fn wildcard(&self, handle: SocketHandle) -> bool {
+        self.broadcast.contains(&handle) || self.groups.contains_key(&handle)
+    }
+
+    /// Every group joined, for joining again after the interface is rebuilt
+    pub(crate) fn groups(&self) -> Vec<Ipv4Address> {
+        let mut groups: Vec<Ipv4Address> = self.groups.values().flatten().copied().collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+
+    fn is_joined(&self, group: Ipv4Address) -> bool { self.groups.values().any(|g| g.contains(&group)) }
+}
 
 pub(crate) fn std_udp_bind(
     mut msg: xous::MessageEnvelope,
@@ -70,6 +140,7 @@ pub(crate) fn std_udp_rx(
     iface: &mut Interface,
     sockets: &mut SocketSet,
     udp_rx_waiting: &mut Vec<Option<UdpStdState>>,
+    membership: &UdpMembership,
     our_sockets: &Vec<Option<SocketHandle>>,
 ) {
     let connection_handle_index = (msg.body.id() >> 16) & 0xffff;
@@ -118,7 +189,8 @@ pub(crate) fn std_udp_rx(
     // the underlying smoltcp library can't handle unspecified source addresses
     // because the library itself works with multiple interfaces and has no default resolution mechanism
     // this may eventually get fixed see https://github.com/smoltcp-rs/smoltcp/issues/599
-    if socket.endpoint().addr.expect("UDP endpoint missing") != IpAddress::Ipv4(local_addr) {
+    // (multicast and broadcast sockets are the exception, see `UdpMembership`)
+    if !membership.wildcard(*handle) && socket.endpoint().addr != Some(IpAddress::Ipv4(local_addr)) {
         if socket.is_open() {
             socket.close();
         }
@@ -184,6 +256,7 @@ pub(crate) fn std_udp_tx(
     mut msg: xous::MessageEnvelope,
     iface: &mut Interface,
     sockets: &mut SocketSet,
+    membership: &UdpMembership,
     our_sockets: &Vec<Option<SocketHandle>>,
 ) {
     // unpack meta
@@ -223,43 +296,205 @@ pub(crate) fn std_udp_tx(
         remote_port,
         &bytes[21..21 + len as usize]
     );
-    let local_addr = match iface.ipv4_addr() {
-        Some(addr) => addr,
-        None => {
-            std_failure(msg, NetError::Unaddressable);
-            return;
+    let data = &bytes[21..21 + len as usize];
+    match udp_send(iface, sockets, membership, *handle, data, IpEndpoint::new(address, remote_port)) {
+        Ok(()) => unsafe {
+            body.buf.as_slice_mut()[0] = 0;
+        },
+        Err(e) => {
+            std_failure(msg, e);
         }
-    };
-    let socket = sockets.get_mut::<udp::Socket>(*handle);
+    }
+}
+
+/// Queues `data` on `handle` for `remote`
+pub(crate) fn udp_send(
+    iface: &Interface,
+    sockets: &mut SocketSet,
+    membership: &UdpMembership,
+    handle: SocketHandle,
+    data: &[u8],
+    remote: IpEndpoint,
+) -> Result<(), NetError> {
+    let local_addr = iface.ipv4_addr().ok_or(NetError::Unaddressable)?;
+    // like POSIX, broadcasts need permission (`SO_BROADCAST`)
+    if is_broadcast(iface, remote.addr) && !membership.broadcast(handle) {
+        return Err(NetError::AccessDenied);
+    }
+    let socket = sockets.get_mut::<udp::Socket>(handle);
     let port = socket.endpoint().port;
     // force the local address to correspond to our (one and only) IP address
     // the underlying smoltcp library can't handle unspecified source addresses
     // because the library itself works with multiple interfaces and has no default resolution mechanism
     // this may eventually get fixed see https://github.com/smoltcp-rs/smoltcp/issues/599
-    if socket.endpoint().addr.expect("UDP TX endpoint missing") != IpAddress::Ipv4(local_addr) {
+    // (multicast and broadcast sockets are the exception, see `UdpMembership`)
+    if !membership.wildcard(handle) && socket.endpoint().addr != Some(IpAddress::Ipv4(local_addr)) {
         if socket.is_open() {
             socket.close();
         }
-        if let Err(e) =
-            socket.bind(IpEndpoint { addr: IpAddress::Ipv4(local_addr), port }).map_err(|e| match e {
-                smoltcp::socket::udp::BindError::InvalidState => NetError::WouldBlock,
-                smoltcp::socket::udp::BindError::Unaddressable => NetError::Unaddressable,
-            })
-        {
-            std_failure(msg, e);
+        socket.bind(IpEndpoint { addr: IpAddress::Ipv4(local_addr), port }).map_err(|e| match e {
+            smoltcp::socket::udp::BindError::InvalidState => NetError::WouldBlock,
+            smoltcp::socket::udp::BindError::Unaddressable => NetError::Unaddressable,
+        })?;
+    }
+    // the only type of error returned from smoltcp in this case is if the destination is not
+    // addressible.
+    socket.send_slice(data, remote).map_err(|_| NetError::Unaddressable)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn std_udp_multicast<D: Device + ?Sized>(
+    msg: xous::MessageEnvelope,
+    join: bool,
+    iface: &mut Interface,
+    device: &mut D,
+    timestamp: Instant,
+    sockets: &mut SocketSet,
+    membership: &mut UdpMembership,
+    our_sockets: &Vec<Option<SocketHandle>>,
+) {
+    let connection_handle_index = (msg.body.id() >> 16) & 0xffff;
+    // Only work with blockingscalar messages
+    let args = match msg.body.scalar_message() {
+        Some(args) if msg.body.is_blocking() => args,
+        _ => {
+            std_failure(msg, NetError::LibraryError);
             return;
         }
+    };
+    let handle = match our_sockets.get(connection_handle_index) {
+        Some(Some(val)) => *val,
+        _ => {
+            std_failure(msg, NetError::Invalid);
+            return;
+        }
+    };
+    let group = Ipv4Address::from_bytes(&(args.arg1 as u32).to_be_bytes());
+    log::debug!("udp fd {} {} {}", connection_handle_index, if join { "joins" } else { "leaves" }, group);
+    let result = if join {
+        udp_join_multicast(iface, device, timestamp, sockets, membership, handle, group)
+    } else {
+        udp_leave_multicast(iface, device, timestamp, membership, handle, group)
+    };
+    match result {
+        Ok(()) => {
+            xous::return_scalar(msg.sender, 0).ok();
+        }
+        Err(e) => {
+            std_failure(msg, e);
+        }
     }
-    match socket.send_slice(&bytes[21..21 + len as usize], IpEndpoint::new(address, remote_port)) {
-        Ok(_) => unsafe {
-            body.buf.as_slice_mut()[0] = 0;
-        },
-        Err(_e) => {
-            // the only type of error returned from smoltcp in this case is if the destination is not
-            // addressible.
-            std_failure(msg, NetError::Unaddressable);
+}
+
+/// Joins `handle` to `group`; the interface reports the group with IGMP if it is the first socket in it
+pub(crate) fn udp_join_multicast<D: Device + ?Sized>(
+    iface: &mut Interface,
+    device: &mut D,
+    timestamp: Instant,
+    sockets: &mut SocketSet,
+    membership: &mut UdpMembership,
+    handle: SocketHandle,
+    group: Ipv4Address,
+) -> Result<(), NetError> {
+    if !group.is_multicast() {
+        return Err(NetError::Invalid);
+    }
+    if membership.join(handle, group)? {
+        if let Err(e) = iface.join_multicast_group(device, group, timestamp) {
+            log::warn!("couldn't join multicast group {}: {:?}", group, e);
+            membership.leave(handle, group).ok();
+            return Err(NetError::LibraryError);
+        }
+    }
+    bind_wildcard(sockets.get_mut::<udp::Socket>(handle))
+}
+
+/// Takes `handle` out of `group`; the interface leaves the group if it was the last socket in it
+pub(crate) fn udp_leave_multicast<D: Device + ?Sized>(
+    iface: &mut Interface,
+    device: &mut D,
+    timestamp: Instant,
+    membership: &mut UdpMembership,
+    handle: SocketHandle,
+    group: Ipv4Address,
+) -> Result<(), NetError> {
+    if !group.is_multicast() {
+        return Err(NetError::Invalid);
+    }
+    if membership.leave(handle, group)? {
+        iface.leave_multicast_group(device, group, timestamp).ok();
+    }
+    Ok(())
+}
+
+pub(crate) fn std_udp_set_broadcast(
+    msg: xous::MessageEnvelope,
+    sockets: &mut SocketSet,
+    membership: &mut UdpMembership,
+    our_sockets: &Vec<Option<SocketHandle>>,
+) {
+    let connection_handle_index = (msg.body.id() >> 16) & 0xffff;
+    // Only work with blockingscalar messages
+    let args = match msg.body.scalar_message() {
+        Some(args) if msg.body.is_blocking() => args,
+        _ => {
+            std_failure(msg, NetError::LibraryError);
+            return;
+        }
+    };
+    let handle = match our_sockets.get(connection_handle_index) {
+        Some(Some(val)) => *val,
+        _ => {
+            std_failure(msg, NetError::Invalid);
             return;
         }
+    };
+    match udp_set_broadcast(sockets, membership, handle, args.arg1 != 0) {
+        Ok(()) => {
+            xous::return_scalar(msg.sender, 0).ok();
+        }
+        Err(e) => {
+            std_failure(msg, e);
+        }
+    }
+}
+
+/// Allows or forbids `handle` to use broadcast addresses
+pub(crate) fn udp_set_broadcast(
+    sockets: &mut SocketSet,
+    membership: &mut UdpMembership,
+    handle: SocketHandle,
+    broadcast: bool,
+) -> Result<(), NetError> {
+    membership.set_broadcast(handle, broadcast);
+    // the next rx or tx binds it back to our address if it no longer needs the wildcard
+    if broadcast { bind_wildcard(sockets.get_mut::<udp::Socket>(handle)) } else { Ok(()) }
+}
+
+/// Binds `socket` to the unspecified address, keeping its port
+fn bind_wildcard(socket: &mut udp::Socket) -> Result<(), NetError> {
+    if socket.endpoint().addr.is_none() {
+        return Ok(());
+    }
+    let port = socket.endpoint().port;
+    socket.close();
+    socket.bind(IpListenEndpoint { addr: None, port }).map_err(|e| match e {
+        smoltcp::socket::udp::BindError::InvalidState => NetError::SocketInUse,
+        smoltcp::socket::udp::BindError::Unaddressable => NetError::Unaddressable,
+    })
+}
+
+/// Whether `address` is the limited broadcast address or the broadcast address of our subnet
+fn is_broadcast(iface: &Interface, address: IpAddress) -> bool {
+    match address {
+        IpAddress::Ipv4(addr) => {
+            addr.is_broadcast()
+                || iface.ip_addrs().iter().any(|cidr| match cidr {
+                    IpCidr::Ipv4(cidr) => cidr.broadcast() == Some(addr),
+                    _ => false,
+                })
+        }
+        _ => false,
     }
 }
 
@@ -818,3 +818,619 @@ fn connect_timeout_valid() {
     let addr = listener.local_addr().unwrap();
     TcpStream::connect_timeout(&addr, Duration::from_secs(2)).unwrap();
 }
+
+#[test]
+fn wg_peer_roundtrip() {
+    use crate::api::{WgPeerConfig, WgRoute};
+    use crate::wireguard::{decode_peer, encode_peer};
+
+    let mut config = WgPeerConfig::default();
+    config.public_key = [0x5a; 32];
+    config.preshared_key = Some([0xa5; 32]);
+    config.endpoint = [203, 0, 113, 7];
+    config.port = 51820;
+    config.address = [10, 8, 0, 2];
+    config.dns = Some([10, 8, 0, 1]);
+    config.keepalive = 25;
+    config.routes[0] = Some(WgRoute { addr: [10, 0, 0, 0], prefix: 8 });
+    config.routes[3] = Some(WgRoute { addr: [0, 0, 0, 0], prefix: 0 });
+    config.autoconnect = true;
+
+    let decoded = decode_peer(&encode_peer(&config)).unwrap();
+    assert_eq!(decoded.public_key, config.public_key);
+    assert_eq!(decoded.preshared_key, config.preshared_key);
+    assert_eq!(decoded.endpoint, config.endpoint);
+    assert_eq!(decoded.port, config.port);
+    assert_eq!(decoded.address, config.address);
+    assert_eq!(decoded.dns, config.dns);
+    assert_eq!(decoded.keepalive, config.keepalive);
+    assert_eq!(decoded.routes, config.routes);
+    assert_eq!(decoded.autoconnect, config.autoconnect);
+
+    assert!(decode_peer(&encode_peer(&config)[1..]).is_none());
+}
+
+#[test]
+fn wg_translate_checksums() {
+    use smoltcp::phy::ChecksumCapabilities;
+    use smoltcp::wire::{IpAddress, IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr, UdpPacket, UdpRepr};
+
+    let caps = ChecksumCapabilities::default();
+    let src = Ipv4Address::new(192, 168, 1, 20);
+    let dst = Ipv4Address::new(198, 51, 100, 1);
+    let payload = b"tunneled";
+    let udp_repr = UdpRepr { src_port: 49152, dst_port: 53 };
+    let ip_repr = Ipv4Repr {
+        src_addr: src,
+        dst_addr: dst,
+        next_header: IpProtocol::Udp,
+        payload_len: udp_repr.header_len() + payload.len(),
+        hop_limit: 64,
+    };
+    let mut bytes = vec![0u8; ip_repr.buffer_len() + ip_repr.payload_len];
+    let mut packet = Ipv4Packet::new_unchecked(&mut bytes[..]);
+    ip_repr.emit(&mut packet, &caps);
+    udp_repr.emit(
+        &mut UdpPacket::new_unchecked(packet.payload_mut()),
+        &IpAddress::Ipv4(src),
+        &IpAddress::Ipv4(dst),
+        payload.len(),
+        |buf| buf.copy_from_slice(payload),
+        &caps,
+    );
+
+    let tunnel_addr = Ipv4Address::new(10, 8, 0, 2);
+    crate::wireguard::translate(&mut packet, Some(tunnel_addr), None);
+    assert_eq!(packet.src_addr(), tunnel_addr);
+    assert!(packet.verify_checksum());
+    let datagram = UdpPacket::new_checked(packet.payload_mut()).unwrap();
+    assert!(datagram.verify_checksum(&IpAddress::Ipv4(tunnel_addr), &IpAddress::Ipv4(dst)));
+    assert_eq!(datagram.payload(), payload);
+}
+
+#[test]
+fn dhcp_mask_prefix() {
+    use crate::mask_prefix;
+
+    assert_eq!(mask_prefix([255, 255, 255, 0]), 24);
+    assert_eq!(mask_prefix([255, 255, 240, 0]), 20);
+    assert_eq!(mask_prefix([255, 255, 255, 255]), 32);
+    // a missing or non-contiguous mask falls back to a /24
+    assert_eq!(mask_prefix([0, 0, 0, 0]), 24);
+    assert_eq!(mask_prefix([255, 0, 255, 0]), 24);
+}
+
+#[test]
+fn wifi_profile_roundtrip() {
+    use crate::api::{WifiProfile, WifiStaticIpv4};
+
+    let profile = WifiProfile {
+        priority: 7,
+        hidden: true,
+        autojoin: false,
+        static_ipv4: Some(WifiStaticIpv4 {
+            addr: [10, 0, 4, 20],
+            prefix: 22,
+            gateway: [10, 0, 4, 1],
+            dns: Some([10, 0, 4, 2]),
+        }),
+    };
+    assert_eq!(WifiProfile::decode(&profile.encode()), Some(profile));
+    assert_eq!(WifiProfile::decode(&WifiProfile::default().encode()), Some(WifiProfile::default()));
+    assert_eq!(profile.static_ipv4.unwrap().mask(), [255, 255, 252, 0]);
+    assert!(WifiProfile::decode(&profile.encode()[1..]).is_none());
+}
+
+#[test]
+fn wifi_next_ssid_order() {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::api::WifiProfile;
+    use crate::connection_manager::{get_next_ssid, SsidOrdByRssi};
+
+    let mut ssid_list = HashMap::new();
+    for (ssid, rssi) in [("cafe", 40u8), ("home", 70), ("guest", 30)] {
+        ssid_list.insert(ssid.to_string(), SsidOrdByRssi::new(ssid.to_string(), rssi));
+    }
+    let mut profiles = HashMap::new();
+    profiles.insert("cafe".to_string(), WifiProfile::default());
+    profiles.insert("home".to_string(), WifiProfile { priority: 5, ..Default::default() });
+    profiles.insert("office".to_string(), WifiProfile { priority: 5, hidden: true, ..Default::default() });
+    profiles.insert("guest".to_string(), WifiProfile { autojoin: false, ..Default::default() });
+    profiles.insert("away".to_string(), WifiProfile::default());
+
+    let mut attempted = HashSet::new();
+    // priority first, then signal strength, with hidden networks last among their priority
+    assert_eq!(get_next_ssid(&ssid_list, &mut attempted, &profiles).as_deref(), Some("home"));
+    assert_eq!(get_next_ssid(&ssid_list, &mut attempted, &profiles).as_deref(), Some("office"));
+    assert_eq!(get_next_ssid(&ssid_list, &mut attempted, &profiles).as_deref(), Some("cafe"));
+    // every candidate has been tried: rescan, and start over
+    assert_eq!(get_next_ssid(&ssid_list, &mut attempted, &profiles), None);
+    assert_eq!(get_next_ssid(&ssid_list, &mut attempted, &profiles).as_deref(), Some("home"));
+}
+
+#[test]
+fn connectivity_check_response() {
+    use crate::api::ConnectivityState;
+    use crate::connectivity::parse_response;
+
+    let url = "http://check.example/generate_204";
+    let online = parse_response(url, b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+    assert_eq!(online.state, ConnectivityState::Online);
+    assert!(online.portal.is_none());
+
+    let redirect = parse_response(
+        url,
+        b"HTTP/1.1 302 Found\r\nServer: portal\r\nlocation: http://10.1.1.1/login?r=x\r\n\r\n",
+    );
+    assert_eq!(redirect.state, ConnectivityState::Captive);
+    assert_eq!(redirect.portal.unwrap().as_str().unwrap(), "http://10.1.1.1/login?r=x");
+
+    // a portal serving its page in place of the check
+    let inline = parse_response(url, b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\n\r\n<html>");
+    assert_eq!(inline.state, ConnectivityState::Captive);
+    assert_eq!(inline.portal.unwrap().as_str().unwrap(), url);
+
+    let announced = parse_response(url, b"HTTP/1.1 200 OK\r\nContent-Length: 1024\r\n\r\n");
+    assert_eq!(announced.state, ConnectivityState::Captive);
+
+    // answers that don't lead to a sign-in page
+    for response in [
+        &b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"[..],
+        b"HTTP/1.1 302 Found\r\nServer: portal\r\n\r\n",
+        b"HTTP/1.1 301 Moved Permanently\r\nLocation: \r\n\r\n",
+        b"HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found",
+        b"HTTP/1.1 503 Service Unavailable\r\n\r\n",
+    ] {
+        let connectivity = parse_response(url, response);
+        assert_eq!(connectivity.state, ConnectivityState::Offline);
+        assert!(connectivity.portal.is_none());
+    }
+
+    assert_eq!(parse_response(url, b"").state, ConnectivityState::Offline);
+    assert_eq!(parse_response(url, b"\x16\x03\x01garbage").state, ConnectivityState::Offline);
+}
+
+#[test]
+fn ipv6_slaac_addresses() {
+    use crate::ipv6::{link_local, slaac_address};
+
+    let mac = [0x00, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f];
+    assert_eq!(Ipv6Addr::from(link_local(mac)), "fe80::21b:2cff:fe3d:4e5f".parse::<Ipv6Addr>().unwrap());
+    let prefix = "2001:db8:1:2::".parse::<Ipv6Addr>().unwrap().octets();
+    assert_eq!(
+        Ipv6Addr::from(slaac_address(&prefix, mac)),
+        "2001:db8:1:2:21b:2cff:fe3d:4e5f".parse::<Ipv6Addr>().unwrap()
+    );
+}
+
+#[test]
+fn ipv6_router_advert() {
+    use crate::ipv6::{parse_ipv6_router_advert, router_solicit, AdvertisedPrefix};
+
+    let router = "fe80::1".parse::<Ipv6Addr>().unwrap().octets();
+    let prefix = "2001:db8:1:2::".parse::<Ipv6Addr>().unwrap().octets();
+    let dns = "2001:db8::53".parse::<Ipv6Addr>().unwrap().octets();
+    // router lifetime 1800s, O flag
+    let mut icmp = vec![134, 0, 0, 0, 64, 0x40, 0x07, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+    // prefix information: /64, on-link and autonomous, valid for 86400s
+    icmp.extend_from_slice(&[3, 4, 64, 0xc0, 0x00, 0x01, 0x51, 0x80, 0x00, 0x00, 0x38, 0x40, 0, 0, 0, 0]);
+    icmp.extend_from_slice(&prefix);
+    // recursive DNS server, valid for 600s
+    icmp.extend_from_slice(&[25, 3, 0, 0, 0x00, 0x00, 0x02, 0x58]);
+    icmp.extend_from_slice(&dns);
+    let mut packet = vec![0x60, 0, 0, 0];
+    packet.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[58, 255]);
+    packet.extend_from_slice(&router);
+    packet.extend_from_slice(&"ff02::1".parse::<Ipv6Addr>().unwrap().octets());
+    packet.extend_from_slice(&icmp);
+
+    let (src, advert) = parse_ipv6_router_advert(&packet).unwrap();
+    assert_eq!(src, router);
+    assert_eq!(advert.router_lifetime, 1800);
+    assert!(advert.other && !advert.managed);
+    assert_eq!(advert.prefixes, vec![AdvertisedPrefix { prefix, len: 64, valid_lifetime: 86400 }]);
+    assert_eq!(advert.dns, vec![dns]);
+
+    // advertisements that may have been forwarded from off-link are dropped
+    packet[7] = 64;
+    assert!(parse_ipv6_router_advert(&packet).is_none());
+
+    // the checksum of the solicitation, including the pseudo-header, must fold to zero
+    let mac = [0x00, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f];
+    let rs = router_solicit(crate::ipv6::link_local(mac), mac);
+    let mut sum: u32 = 58 + (rs.len() as u32 - 40);
+    for chunk in rs[8..].chunks(2) {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    assert_eq!(sum, 0xffff);
+}
+
+#[test]
+fn ipv6_dhcpv6_reply() {
+    use crate::ipv6::{dhcpv6_information_request, parse_dhcpv6_reply};
+
+    let xid = [0x12, 0x34, 0x56];
+    let request = dhcpv6_information_request(xid, [0x00, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f]);
+    assert_eq!(&request[..4], &[11, 0x12, 0x34, 0x56]);
+
+    let dns1 = "2001:db8::53".parse::<Ipv6Addr>().unwrap().octets();
+    let dns2 = "2001:db8::54".parse::<Ipv6Addr>().unwrap().octets();
+    let mut reply = vec![7, 0x12, 0x34, 0x56];
+    // server identifier, skipped
+    reply.extend_from_slice(&[0, 2, 0, 4, 0, 3, 0, 1]);
+    reply.extend_from_slice(&[0, 23, 0, 32]);
+    reply.extend_from_slice(&dns1);
+    reply.extend_from_slice(&dns2);
+    assert_eq!(parse_dhcpv6_reply(xid, &reply), Some(vec![dns1, dns2]));
+    assert_eq!(parse_dhcpv6_reply([0, 0, 0], &reply), None);
+    // truncated option
+    assert_eq!(parse_dhcpv6_reply(xid, &reply[..reply.len() - 1]), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn hosted_fixture_dhcp_dns() {
+    use crate::hosted::fixture::{Fixture, CLIENT_ADDR, FIXTURE_HOST, FIXTURE_MAC, GATEWAY};
+
+    let mac = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+    let fixture = Fixture::new().with_host("peer.test", [10, 0, 2, 7]);
+    let frame = |ethertype: u16, payload: &[u8]| {
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&mac);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    };
+    // checksums are left empty, the fixture doesn't verify them
+    let udp = |src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16, payload: &[u8]| {
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&(28 + payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 0, 0, 64, 17, 0, 0]);
+        ip.extend_from_slice(&src);
+        ip.extend_from_slice(&dst);
+        ip.extend_from_slice(&src_port.to_be_bytes());
+        ip.extend_from_slice(&dst_port.to_be_bytes());
+        ip.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0]);
+        ip.extend_from_slice(payload);
+        frame(0x0800, &ip)
+    };
+
+    // ARP for the gateway
+    let mut arp = vec![0, 1, 8, 0, 6, 4, 0, 1];
+    arp.extend_from_slice(&mac);
+    arp.extend_from_slice(&CLIENT_ADDR);
+    arp.extend_from_slice(&[0; 6]);
+    arp.extend_from_slice(&GATEWAY);
+    let reply = fixture.handle(&frame(0x0806, &arp)).unwrap();
+    assert_eq!(&reply[..6], &mac);
+    assert_eq!(&reply[14 + 8..14 + 14], &FIXTURE_MAC);
+    assert_eq!(&reply[14 + 14..14 + 18], &GATEWAY);
+
+    // DHCP discover gets an offer for the client address
+    let mut discover = vec![0u8; 240];
+    discover[..4].copy_from_slice(&[1, 1, 6, 0]);
+    discover[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    discover[28..34].copy_from_slice(&mac);
+    discover[236..240].copy_from_slice(&[99, 130, 83, 99]);
+    discover.extend_from_slice(&[53, 1, 1, 255]);
+    let reply = fixture.handle(&udp([0; 4], [255; 4], 68, 67, &discover)).unwrap();
+    let bootp = &reply[14 + 28..];
+    assert_eq!(bootp[0], 2);
+    assert_eq!(&bootp[4..8], &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(&bootp[16..20], &CLIENT_ADDR);
+    assert_eq!(&bootp[240..243], &[53, 1, 2]);
+
+    // DNS
+    let query = |name: &str| {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.extend_from_slice(&[0, 0, 1, 0, 1]);
+        query
+    };
+    let reply = fixture.handle(&udp(CLIENT_ADDR, GATEWAY, 5353, 53, &query("peer.test"))).unwrap();
+    let dns = &reply[14 + 28..];
+    assert_eq!(&dns[..4], &[0x12, 0x34, 0x81, 0x80]);
+    assert_eq!(&dns[dns.len() - 4..], &[10, 0, 2, 7]);
+    let reply = fixture.handle(&udp(CLIENT_ADDR, GATEWAY, 5353, 53, &query(FIXTURE_HOST))).unwrap();
+    assert_eq!(&reply[reply.len() - 4..], &GATEWAY);
+    let reply = fixture.handle(&udp(CLIENT_ADDR, GATEWAY, 5353, 53, &query("nowhere.test"))).unwrap();
+    assert_eq!(reply[14 + 28 + 3] & 0xf, 3);
+
+    // anything else is dropped
+    assert!(fixture.handle(&udp(CLIENT_ADDR, [10, 0, 2, 9], 5353, 53, &query("peer.test"))).is_none());
+}
+
+#[test]
+fn capture_ring_pcapng() {
+    use std::convert::TryInto;
+
+    use crate::capture::{Direction, PacketCapture};
+
+    let word = |b: &[u8], at: usize| u32::from_le_bytes(b[at..at + 4].try_into().unwrap());
+    // room for two 100-byte frames with their 44 bytes of pcapng overhead
+    let mut capture = PacketCapture::new(300, 0);
+    capture.record(1_000, Direction::Outbound, &[0xaa; 100]);
+    capture.record(2_000, Direction::Inbound, &[0xbb; 100]);
+    capture.record(3_000, Direction::Outbound, &[0xcc; 99]);
+    let status = capture.status();
+    assert_eq!((status.frames, status.dropped), (2, 1));
+
+    let pcapng = capture.to_pcapng();
+    assert_eq!(pcapng.len(), status.pcapng_len as usize);
+    // section header, then interface description
+    assert_eq!(word(&pcapng, 0), 0x0A0D_0D0A);
+    assert_eq!(word(&pcapng, 8), 0x1A2B_3C4D);
+    assert_eq!(word(&pcapng, 28), 1);
+    // enhanced packet blocks: oldest frame was dropped, the 99-byte one is padded
+    let epb = 28 + 32;
+    assert_eq!(word(&pcapng, epb), 6);
+    assert_eq!(word(&pcapng, epb + 4), 144);
+    assert_eq!(word(&pcapng, epb + 16), 2_000);
+    assert_eq!(pcapng[epb + 28], 0xbb);
+    assert_eq!(word(&pcapng, epb + 28 + 100 + 4), 1); // inbound
+    let epb = epb + 144;
+    assert_eq!(word(&pcapng, epb + 4), 144);
+    assert_eq!((word(&pcapng, epb + 20), word(&pcapng, epb + 24)), (99, 99));
+    assert_eq!(word(&pcapng, epb + 28 + 100 + 4), 2); // outbound
+    assert_eq!(epb + 144, pcapng.len());
+
+    // export in chunks, and snaplen truncation
+    let mut exported = Vec::new();
+    let mut chunk = [0u8; 64];
+    loop {
+        let len = capture.export(exported.len(), &mut chunk);
+        exported.extend_from_slice(&chunk[..len]);
+        if len < chunk.len() {
+            break;
+        }
+    }
+    assert_eq!(exported, pcapng);
+    let mut capture = PacketCapture::new(0, 60);
+    capture.record(0, Direction::Inbound, &[0; 1500]);
+    let pcapng = capture.to_pcapng();
+    assert_eq!((word(&pcapng, 60 + 20), word(&pcapng, 60 + 24)), (60, 1500));
+    capture.stop();
+    capture.record(0, Direction::Inbound, &[0; 1500]);
+    assert_eq!(capture.status().frames, 1);
+}
+
+#[test]
+fn udp_membership_refcount() {
+    use smoltcp::iface::SocketSet;
+    use smoltcp::socket::udp;
+    use smoltcp::wire::Ipv4Address;
+
+    use crate::api::NetError;
+    use crate::std_udp::UdpMembership;
+
+    let mut sockets = SocketSet::new(vec![]);
+    let mut socket = || {
+        let buffer = || udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 64]);
+        sockets.add(udp::Socket::new(buffer(), buffer()))
+    };
+    let (a, b) = (socket(), socket());
+    let group = Ipv4Address::new(239, 255, 77, 1);
+    let other = Ipv4Address::new(224, 0, 0, 251);
+
+    let mut membership = UdpMembership::default();
+    assert!(!membership.wildcard(a));
+    // the interface joins with the first socket, and leaves with the last
+    assert_eq!(membership.join(a, group), Ok(true));
+    assert_eq!(membership.join(b, group), Ok(false));
+    assert_eq!(membership.join(a, group), Err(NetError::SocketInUse));
+    assert_eq!(membership.join(b, other), Ok(true));
+    assert!(membership.wildcard(a) && membership.wildcard(b));
+    assert_eq!(membership.groups(), vec![other, group]);
+    assert_eq!(membership.leave(a, group), Ok(false));
+    assert_eq!(membership.leave(a, group), Err(NetError::Unaddressable));
+    assert!(!membership.wildcard(a));
+    assert_eq!(membership.close(b), vec![group, other]);
+    assert!(membership.groups().is_empty());
+
+    membership.set_broadcast(a, true);
+    assert!(membership.broadcast(a) && membership.wildcard(a) && !membership.broadcast(b));
+    assert!(membership.close(a).is_empty());
+    assert!(!membership.broadcast(a));
+}
+
+/// An interface with the fixture's lease on a loopback device, and a few UDP sockets bound to it, for
+/// driving the StdUdp multicast and broadcast opcode handlers
+#[cfg(unix)]
+struct UdpHarness {
+    device: smoltcp::phy::Loopback,
+    iface: smoltcp::iface::Interface,
+    sockets: smoltcp::iface::SocketSet<'static>,
+    membership: crate::std_udp::UdpMembership,
+    fixture: crate::hosted::fixture::Fixture,
+}
+
+#[cfg(unix)]
+impl UdpHarness {
+    fn new() -> UdpHarness {
+        use smoltcp::iface::{Config, Interface, SocketSet};
+        use smoltcp::phy::{Loopback, Medium};
+        use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+
+        use crate::hosted::fixture::{Fixture, CLIENT_ADDR};
+
+        let mut device = Loopback::new(Medium::Ethernet);
+        let config =
+            Config::new(HardwareAddress::Ethernet(EthernetAddress([0x02, 0x11, 0x22, 0x33, 0x44, 0x55])));
+        let mut iface = Interface::new(config, &mut device, smoltcp::time::Instant::ZERO);
+        let [a, b, c, d] = CLIENT_ADDR;
+        iface.update_ip_addrs(|addrs| addrs.push(IpCidr::new(IpAddress::v4(a, b, c, d), 24)).unwrap());
+        UdpHarness {
+            device,
+            iface,
+            sockets: SocketSet::new(vec![]),
+            membership: Default::default(),
+            fixture: Fixture::new(),
+        }
+    }
+
+    /// Adds a socket bound to our address, as `StdUdpBind` does
+    fn socket(&mut self, port: u16) -> smoltcp::iface::SocketHandle {
+        use smoltcp::socket::udp;
+        use smoltcp::wire::{IpAddress, IpListenEndpoint};
+
+        let buffer = || udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let mut socket = udp::Socket::new(buffer(), buffer());
+        let [a, b, c, d] = crate::hosted::fixture::CLIENT_ADDR;
+        socket.bind(IpListenEndpoint { addr: Some(IpAddress::v4(a, b, c, d)), port }).unwrap();
+        self.sockets.add(socket)
+    }
+
+    fn join(
+        &mut self,
+        handle: smoltcp::iface::SocketHandle,
+        group: [u8; 4],
+    ) -> Result<(), crate::api::NetError> {
+        let UdpHarness { device, iface, sockets, membership, .. } = self;
+        let group = smoltcp::wire::Ipv4Address(group);
+        crate::std_udp::udp_join_multicast(iface, device, Self::now(), sockets, membership, handle, group)
+    }
+
+    fn leave(
+        &mut self,
+        handle: smoltcp::iface::SocketHandle,
+        group: [u8; 4],
+    ) -> Result<(), crate::api::NetError> {
+        let UdpHarness { device, iface, membership, .. } = self;
+        let group = smoltcp::wire::Ipv4Address(group);
+        crate::std_udp::udp_leave_multicast(iface, device, Self::now(), membership, handle, group)
+    }
+
+    /// Hands the frames the interface sent to the fixture, returning how many there were
+    fn deliver(&mut self) -> usize {
+        use smoltcp::phy::{Device, RxToken};
+
+        let fixture = &self.fixture;
+        let mut frames = 0;
+        while let Some((rx, _)) = self.device.receive(Self::now()) {
+            rx.consume(|frame| fixture.handle(frame));
+            frames += 1;
+        }
+        frames
+    }
+
+    /// Receives a datagram from the gateway to `dst`, returning the payload if `handle` got it
+    fn receive(&mut self, handle: smoltcp::iface::SocketHandle, dst: [u8; 4], port: u16) -> Option<Vec<u8>> {
+        use smoltcp::phy::{ChecksumCapabilities, Device, TxToken};
+        use smoltcp::socket::udp;
+        use smoltcp::wire::{IpAddress, IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr, UdpPacket, UdpRepr};
+
+        use crate::hosted::fixture::{FIXTURE_MAC, GATEWAY};
+
+        let caps = ChecksumCapabilities::default();
+        let payload = b"to everyone";
+        let (src, dst) = (Ipv4Address(GATEWAY), Ipv4Address(dst));
+        let udp_repr = UdpRepr { src_port: 4242, dst_port: port };
+        let ip_repr = Ipv4Repr {
+            src_addr: src,
+            dst_addr: dst,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + payload.len(),
+            hop_limit: 64,
+        };
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&FIXTURE_MAC);
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let mut packet = vec![0u8; ip_repr.buffer_len() + ip_repr.payload_len];
+        let mut ip = Ipv4Packet::new_unchecked(&mut packet[..]);
+        ip_repr.emit(&mut ip, &caps);
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(ip.payload_mut()),
+            &IpAddress::Ipv4(src),
+            &IpAddress::Ipv4(dst),
+            payload.len(),
+            |buf| buf.copy_from_slice(payload),
+            &caps,
+        );
+        frame.extend_from_slice(&packet);
+        // the loopback device hands back whatever it is given to send
+        self.device.transmit(Self::now()).unwrap().consume(frame.len(), |buf| buf.copy_from_slice(&frame));
+        self.iface.poll(Self::now(), &mut self.device, &mut self.sockets);
+        self.sockets.get_mut::<udp::Socket>(handle).recv().ok().map(|(data, _)| data.to_vec())
+    }
+
+    fn now() -> smoltcp::time::Instant { smoltcp::time::Instant::ZERO }
+}
+
+#[cfg(unix)]
+#[test]
+fn udp_multicast_join_leave() {
+    use crate::api::NetError;
+
+    let group = [239, 255, 77, 1];
+    let mut net = UdpHarness::new();
+    let (a, b) = (net.socket(5000), net.socket(5001));
+
+    // the interface reports the group when the first socket joins, and leaves it with the last
+    assert_eq!(net.join(a, group), Ok(()));
+    assert_eq!(net.deliver(), 1);
+    assert_eq!(net.fixture.groups(), vec![group]);
+    assert_eq!(net.join(b, group), Ok(()));
+    assert_eq!(net.deliver(), 0);
+    // joining twice, or leaving a group that wasn't joined, are errors
+    assert_eq!(net.join(a, group), Err(NetError::SocketInUse));
+    assert_eq!(net.leave(a, group), Ok(()));
+    assert_eq!(net.leave(a, group), Err(NetError::Unaddressable));
+    assert_eq!(net.deliver(), 0);
+    assert_eq!(net.fixture.groups(), vec![group]);
+    assert_eq!(net.leave(b, group), Ok(()));
+    assert_eq!(net.deliver(), 1);
+    assert!(net.fixture.groups().is_empty());
+    // not a multicast address
+    assert_eq!(net.join(a, [10, 0, 0, 1]), Err(NetError::Invalid));
+    assert_eq!(net.deliver(), 0);
+
+    // datagrams to the group reach the member
+    assert_eq!(net.join(a, group), Ok(()));
+    net.deliver();
+    assert_eq!(net.receive(a, group, 5000).as_deref(), Some(&b"to everyone"[..]));
+}
+
+#[cfg(unix)]
+#[test]
+fn udp_broadcast() {
+    use smoltcp::socket::udp;
+    use smoltcp::wire::{IpAddress, IpEndpoint};
+
+    use crate::api::NetError;
+    use crate::std_udp::{udp_send, udp_set_broadcast};
+
+    let mut net = UdpHarness::new();
+    let socket = net.socket(5002);
+    let send = |net: &mut UdpHarness, dst: IpAddress| {
+        udp_send(&net.iface, &mut net.sockets, &net.membership, socket, b"hello", IpEndpoint::new(dst, 5002))
+    };
+    let limited = IpAddress::v4(255, 255, 255, 255);
+    let subnet = IpAddress::v4(10, 0, 2, 255);
+
+    // like POSIX, broadcasts need permission
+    assert!(!net.membership.broadcast(socket));
+    assert_eq!(send(&mut net, limited), Err(NetError::AccessDenied));
+    assert_eq!(send(&mut net, subnet), Err(NetError::AccessDenied));
+    assert_eq!(udp_set_broadcast(&mut net.sockets, &mut net.membership, socket, true), Ok(()));
+    assert!(net.membership.broadcast(socket));
+    assert_eq!(net.sockets.get::<udp::Socket>(socket).endpoint().addr, None);
+    assert_eq!(send(&mut net, limited), Ok(()));
+    assert_eq!(send(&mut net, subnet), Ok(()));
+    assert_eq!(net.receive(socket, [255; 4], 5002).as_deref(), Some(&b"to everyone"[..]));
+
+    // without it, the socket goes back to our address
+    assert_eq!(udp_set_broadcast(&mut net.sockets, &mut net.membership, socket, false), Ok(()));
+    assert!(!net.membership.broadcast(socket));
+    assert_eq!(send(&mut net, limited), Err(NetError::AccessDenied));
+    assert_eq!(send(&mut net, IpAddress::v4(10, 0, 2, 7)), Ok(()));
+    assert_eq!(net.sockets.get::<udp::Socket>(socket).endpoint().addr, Some(IpAddress::v4(10, 0, 2, 15)));
+}
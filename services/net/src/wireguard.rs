@@ -0,0 +1,558 @@
+// WireGuard tunnel
+//
+// The tunnel is implemented in the device layer rather than as a second smoltcp Interface: all of
+// the libstd sockets live in a single SocketSet polled by a single Interface, so a packet bound
+// for the tunnel is an ordinary IPv4 frame addressed to the default gateway. The frame is
+// intercepted on transmit, its source address is translated to the tunnel address, and it is
+// encapsulated and sent to the peer in a UDP datagram. Datagrams from the peer are decapsulated
+// on receive, the destination is translated back to the WLAN address, and the packet is handed
+// to smoltcp as if it had arrived from the gateway.
+//
+// Destinations on the local subnet, loopback, broadcast/multicast, and the peer endpoint itself
+// are never tunneled.
+mod noise;
+
+use core::sync::atomic::Ordering;
+use std::io::{Read, Write};
+
+use com::api::NET_MTU;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, IpAddress, IpProtocol, Ipv4Address, Ipv4Cidr,
+    Ipv4Packet, Ipv4Repr, TcpPacket, UdpPacket, UdpRepr,
+};
+
+use self::noise::{PublicKey, StaticSecret, Tunn, TunnResult};
+use crate::api::*;
+use crate::{IPV4_ADDRESS, IPV4_PREFIX_LEN, MAC_ADDRESS_LSB, MAC_ADDRESS_MSB};
+
+/// MTU inside the tunnel: leaves room for the outer IPv4, UDP and WireGuard headers in a 1500 byte frame
+pub const WG_MTU: usize = 1420;
+/// the largest Ethernet frame smoltcp may emit while the tunnel is up
+pub const WG_FRAME_MTU: usize = WG_MTU + ETHERNET_HEADER_LEN;
+/// the local UDP port of the tunnel
+const WG_LOCAL_PORT: u16 = 51820;
+/// a session is no longer accepted by the peer after this many seconds without a new handshake
+const WG_REJECT_AFTER_SECS: u64 = 180;
+const ETHERNET_HEADER_LEN: usize = 14;
+const UDP_HEADER_LEN: usize = 8;
+
+/// PDDB key for the Curve25519 private key of the device
+const WG_PRIVATE_KEY: &str = "private";
+/// PDDB key for the encoded `WgPeerConfig`
+const WG_PEER_KEY: &str = "peer";
+/// version of the `WgPeerConfig` encoding in the PDDB
+const WG_PEER_VERSION: u8 = 1;
+
+/// The outcome of passing an outgoing frame through the tunnel
+pub(crate) enum WgTx<'a> {
+    /// the frame is not routed through the tunnel, and should be sent as-is
+    Bypass,
+    /// the frame was encapsulated: send this frame to the peer instead
+    Send(&'a [u8]),
+    /// the frame was taken by the tunnel (i.e. queued pending a handshake), and nothing is sent
+    Consumed,
+}
+
+pub(crate) struct WgTunnel {
+    tunn: Tunn,
+    config: WgPeerConfig,
+    /// MAC address of the default gateway, learned from traffic routed off the local subnet
+    gateway_mac: Option<EthernetAddress>,
+    /// scratch space for the WireGuard protocol messages
+    scratch: Vec<u8>,
+    /// the outgoing frame to the peer
+    frame: Vec<u8>,
+    caps: ChecksumCapabilities,
+}
+
+impl WgTunnel {
+    pub(crate) fn new(private: StaticSecret, config: WgPeerConfig, index: u32) -> Result<Self, &'static str> {
+        let keepalive = if config.keepalive != 0 { Some(config.keepalive) } else { None };
+        let tunn =
+            Tunn::new(private, PublicKey::from(config.public_key), config.preshared_key, keepalive, index)?;
+        Ok(WgTunnel {
+            tunn,
+            config,
+            gateway_mac: None,
+            scratch: vec![0u8; NET_MTU],
+            frame: Vec::with_capacity(NET_MTU),
+            caps: ChecksumCapabilities::default(),
+        })
+    }
+
+    pub(crate) fn config(&self) -> &WgPeerConfig { &self.config }
+
+    pub(crate) fn status(&self) -> WgStatus {
+        let (last_handshake, tx_bytes, rx_bytes) = self.tunn.stats();
+        let last_handshake_secs = last_handshake.map(|elapsed| elapsed.as_secs());
+        let state = match last_handshake_secs {
+            Some(secs) if secs < WG_REJECT_AFTER_SECS => WgState::Up,
+            _ => WgState::Handshaking,
+        };
+        WgStatus { state, last_handshake_secs, tx_bytes: tx_bytes as u64, rx_bytes: rx_bytes as u64 }
+    }
+
+    /// Forgets the gateway, i.e. when the WLAN configuration changes
+    pub(crate) fn forget_gateway(&mut self) { self.gateway_mac = None; }
+
+    /// Inspects an outgoing Ethernet frame, and encapsulates it if it is routed through the tunnel
+    pub(crate) fn encapsulate_frame(&mut self, frame: &mut [u8]) -> WgTx<'_> {
+        let mut eth_frame = match EthernetFrame::new_checked(frame) {
+            Ok(eth_frame) => eth_frame,
+            Err(_) => return WgTx::Bypass,
+        };
+        if eth_frame.ethertype() != EthernetProtocol::Ipv4 {
+            return WgTx::Bypass;
+        }
+        let dst_mac = eth_frame.dst_addr();
+        let dst = match Ipv4Packet::new_checked(eth_frame.payload_mut()) {
+            Ok(packet) => packet.dst_addr(),
+            Err(_) => return WgTx::Bypass,
+        };
+        if is_off_subnet(dst) {
+            // an off-subnet destination is addressed to the MAC of the default gateway
+            self.gateway_mac = Some(dst_mac);
+        }
+        if !self.is_routed(dst) {
+            return WgTx::Bypass;
+        }
+        let mut packet = Ipv4Packet::new_unchecked(eth_frame.payload_mut());
+        translate(&mut packet, Some(Ipv4Address(self.config.address)), None);
+        let len = packet.total_len() as usize;
+        let datagram_len = match self.tunn.encapsulate(&packet.into_inner()[..len], &mut self.scratch) {
+            // this is the handshake initiation if there is no session yet; the packet is queued meanwhile
+            TunnResult::WriteToNetwork(datagram) => datagram.len(),
+            TunnResult::Err(e) => {
+                log::warn!("wg: couldn't encapsulate packet to {:?}: {:?}", dst, e);
+                return WgTx::Consumed;
+            }
+            _ => return WgTx::Consumed,
+        };
+        self.frame_datagram(datagram_len)
+    }
+
+    /// Inspects an incoming Ethernet frame, and decapsulates it if it is a datagram from the peer.
+    /// Any protocol messages due to the peer in response are passed to `send`.
+    ///
+    /// # Returns
+    ///
+    /// the length of the frame to hand to smoltcp, which is rewritten in place if it came from the
+    /// peer, or `None` if there is nothing to hand on
+    pub(crate) fn decapsulate_frame<F: FnMut(&[u8])>(
+        &mut self,
+        frame: &mut [u8],
+        mut send: F,
+    ) -> Option<usize> {
+        let endpoint = Ipv4Address(self.config.endpoint);
+        let (gateway_mac, start, end) = {
+            let eth_frame = match EthernetFrame::new_checked(&frame[..]) {
+                Ok(eth_frame) => eth_frame,
+                Err(_) => return Some(frame.len()),
+            };
+            if eth_frame.ethertype() != EthernetProtocol::Ipv4 {
+                return Some(frame.len());
+            }
+            let packet = match Ipv4Packet::new_checked(eth_frame.payload()) {
+                Ok(packet) => packet,
+                Err(_) => return Some(frame.len()),
+            };
+            if is_off_subnet(packet.src_addr()) {
+                self.gateway_mac = Some(eth_frame.src_addr());
+            }
+            if packet.src_addr() != endpoint || packet.next_header() != IpProtocol::Udp {
+                return Some(frame.len());
+            }
+            match UdpPacket::new_checked(packet.payload()) {
+                Ok(datagram) => {
+                    if datagram.src_port() != self.config.port || datagram.dst_port() != WG_LOCAL_PORT {
+                        return Some(frame.len());
+                    }
+                }
+                Err(_) => return Some(frame.len()),
+            }
+            let start = ETHERNET_HEADER_LEN + packet.header_len() as usize + UDP_HEADER_LEN;
+            (eth_frame.src_addr(), start, ETHERNET_HEADER_LEN + packet.total_len() as usize)
+        };
+
+        let mut datagram = &frame[start..end];
+        loop {
+            match self.tunn.decapsulate(datagram, &mut self.scratch) {
+                TunnResult::WriteToNetwork(response) => {
+                    // handshake response, cookie reply, or the packets queued pending the handshake
+                    let len = response.len();
+                    if let WgTx::Send(out) = self.frame_datagram(len) {
+                        send(out);
+                    }
+                    // keep going until there is nothing more to send
+                    datagram = &[];
+                }
+                TunnResult::WriteToTunnelV4(packet) => {
+                    let len = packet.len();
+                    return self.deliver(frame, len, gateway_mac);
+                }
+                TunnResult::WriteToTunnelV6(_packet) => {
+                    log::debug!("wg: dropping ipv6 packet from the tunnel");
+                    return None;
+                }
+                TunnResult::Err(e) => {
+                    log::warn!("wg: couldn't decapsulate datagram: {:?}", e);
+                    return None;
+                }
+                TunnResult::Done => return None,
+            }
+        }
+    }
+
+    /// Runs the protocol timers: handshake (re)initiation, keepalives and session expiry.
+    /// Any protocol messages due to the peer are passed to `send`.
+    pub(crate) fn update_timers<F: FnMut(&[u8])>(&mut self, mut send: F) {
+        let mut len = match self.tunn.update_timers(&mut self.scratch) {
+            TunnResult::WriteToNetwork(datagram) => Some(datagram.len()),
+            TunnResult::Err(e) => {
+                log::debug!("wg timers: {:?}", e);
+                None
+            }
+            _ => None,
+        };
+        if len.is_none() && self.tunn.time_since_last_handshake().is_none() {
+            // no session: initiate a handshake without waiting for traffic, so that the state of the
+            // tunnel is known as soon as it is brought up. This is a no-op if one is already in flight.
+            if let TunnResult::WriteToNetwork(datagram) =
+                self.tunn.format_handshake_initiation(&mut self.scratch, false)
+            {
+                len = Some(datagram.len());
+            }
+        }
+        if let Some(len) = len {
+            if let WgTx::Send(out) = self.frame_datagram(len) {
+                send(out);
+            }
+        }
+    }
+
+    /// Returns true if a packet to `dst` is routed through the tunnel
+    fn is_routed(&self, dst: Ipv4Address) -> bool {
+        if !is_off_subnet(dst) || dst.0 == self.config.endpoint {
+            return false;
+        }
+        self.config
+            .routes
+            .iter()
+            .flatten()
+            .any(|route| Ipv4Cidr::new(Ipv4Address(route.addr), route.prefix.min(32)).contains_addr(&dst))
+    }
+
+    /// Wraps the WireGuard message in `self.scratch[..len]` in a UDP datagram to the peer
+    fn frame_datagram(&mut self, len: usize) -> WgTx<'_> {
+        let gateway_mac = match self.gateway_mac {
+            Some(mac) => mac,
+            None => {
+                // the protocol timers retry the handshake, by which time the gateway should be known
+                log::debug!("wg: gateway not known yet, dropping datagram");
+                return WgTx::Consumed;
+            }
+        };
+        let local = Ipv4Address::from_bytes(&IPV4_ADDRESS.load(Ordering::SeqCst).to_be_bytes());
+        let endpoint = Ipv4Address(self.config.endpoint);
+        let udp_repr = UdpRepr { src_port: WG_LOCAL_PORT, dst_port: self.config.port };
+        let ip_repr = Ipv4Repr {
+            src_addr: local,
+            dst_addr: endpoint,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + len,
+            hop_limit: 64,
+        };
+        let scratch = &self.scratch;
+        let caps = &self.caps;
+        let out = &mut self.frame;
+        out.clear();
+        out.resize(ETHERNET_HEADER_LEN + ip_repr.buffer_len() + ip_repr.payload_len, 0);
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut out[..]);
+        eth_frame.set_dst_addr(gateway_mac);
+        eth_frame.set_src_addr(local_mac());
+        eth_frame.set_ethertype(EthernetProtocol::Ipv4);
+        let mut packet = Ipv4Packet::new_unchecked(eth_frame.payload_mut());
+        ip_repr.emit(&mut packet, caps);
+        let mut datagram = UdpPacket::new_unchecked(packet.payload_mut());
+        udp_repr.emit(
+            &mut datagram,
+            &IpAddress::Ipv4(local),
+            &IpAddress::Ipv4(endpoint),
+            len,
+            |buf| buf.copy_from_slice(&scratch[..len]),
+            caps,
+        );
+        WgTx::Send(&self.frame[..])
+    }
+
+    /// Rewrites `frame` with the decapsulated packet in `self.scratch[..len]`, as if it had arrived
+    /// from the gateway
+    fn deliver(&mut self, frame: &mut [u8], len: usize, gateway_mac: EthernetAddress) -> Option<usize> {
+        let total = ETHERNET_HEADER_LEN + len;
+        if total > frame.len() {
+            log::warn!("wg: decapsulated packet larger than the datagram, dropping");
+            return None;
+        }
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut frame[..total]);
+        eth_frame.set_dst_addr(local_mac());
+        eth_frame.set_src_addr(gateway_mac);
+        eth_frame.set_ethertype(EthernetProtocol::Ipv4);
+        eth_frame.payload_mut().copy_from_slice(&self.scratch[..len]);
+        let mut packet = match Ipv4Packet::new_checked(eth_frame.payload_mut()) {
+            Ok(packet) => packet,
+            Err(_) => return None,
+        };
+        if packet.dst_addr() != Ipv4Address(self.config.address) {
+            log::warn!("wg: dropping packet for {:?}, which is not our tunnel address", packet.dst_addr());
+            return None;
+        }
+        let local = Ipv4Address::from_bytes(&IPV4_ADDRESS.load(Ordering::SeqCst).to_be_bytes());
+        translate(&mut packet, None, Some(local));
+        Some(total)
+    }
+}
+
+/// Rewrites the source and/or destination address of an IPv4 packet, and fixes up the checksums
+pub(crate) fn translate<T: AsRef<[u8]> + AsMut<[u8]>>(
+    packet: &mut Ipv4Packet<T>,
+    src: Option<Ipv4Address>,
+    dst: Option<Ipv4Address>,
+) {
+    if let Some(src) = src {
+        packet.set_src_addr(src);
+    }
+    if let Some(dst) = dst {
+        packet.set_dst_addr(dst);
+    }
+    packet.fill_checksum();
+    let src_addr = IpAddress::Ipv4(packet.src_addr());
+    let dst_addr = IpAddress::Ipv4(packet.dst_addr());
+    match packet.next_header() {
+        IpProtocol::Tcp => {
+            if let Ok(mut segment) = TcpPacket::new_checked(packet.payload_mut()) {
+                segment.fill_checksum(&src_addr, &dst_addr);
+            }
+        }
+        IpProtocol::Udp => {
+            if let Ok(mut datagram) = UdpPacket::new_checked(packet.payload_mut()) {
+                datagram.fill_checksum(&src_addr, &dst_addr);
+            }
+        }
+        // ICMP has no pseudo-header, so the checksum is unaffected
+        _ => {}
+    }
+}
+
+/// Returns true if `addr` is reached through the default gateway
+fn is_off_subnet(addr: Ipv4Address) -> bool {
+    let local = Ipv4Address::from_bytes(&IPV4_ADDRESS.load(Ordering::SeqCst).to_be_bytes());
+    let prefix = IPV4_PREFIX_LEN.load(Ordering::SeqCst).min(32);
+    !(addr.is_loopback()
+        || addr.is_broadcast()
+        || addr.is_multicast()
+        || addr.is_unspecified()
+        || Ipv4Cidr::new(local, prefix).contains_addr(&addr))
+}
+
+fn local_mac() -> EthernetAddress {
+    let mut local_hwaddr = [0u8; 6];
+    local_hwaddr[2..6].copy_from_slice(&MAC_ADDRESS_LSB.load(Ordering::SeqCst).to_be_bytes());
+    local_hwaddr[0..2].copy_from_slice(&MAC_ADDRESS_MSB.load(Ordering::SeqCst).to_be_bytes());
+    EthernetAddress(local_hwaddr)
+}
+
+/// The keys and peer configuration in the PDDB. They are read once, when the PDDB is mounted, and
+/// kept in memory from then on so that bringing up the tunnel doesn't go back to the PDDB.
+pub(crate) struct WgStore {
+    pddb: pddb::Pddb,
+    private: Option<StaticSecret>,
+    peer: Option<WgPeerConfig>,
+}
+
+impl WgStore {
+    /// Reads the keys and peer configuration. The PDDB must be mounted.
+    pub(crate) fn load() -> Self {
+        let pddb = pddb::Pddb::new();
+        let private = read_private_key(&pddb);
+        let peer = load_peer(&pddb);
+        WgStore { pddb, private, peer }
+    }
+
+    pub(crate) fn peer(&self) -> Option<WgPeerConfig> { self.peer }
+
+    /// Returns the device private key, generating and saving a new key pair on first use
+    pub(crate) fn private_key(&mut self, trng: &mut trng::Trng) -> Option<StaticSecret> {
+        if self.private.is_none() {
+            self.private = new_private_key(&self.pddb, trng);
+        }
+        self.private.clone()
+    }
+
+    /// Returns the public key of the device, generating the key pair on first use
+    pub(crate) fn public_key(&mut self, trng: &mut trng::Trng) -> Option<[u8; 32]> {
+        self.private_key(trng).map(|private| PublicKey::from(&private).to_bytes())
+    }
+
+    /// Saves the peer configuration, making sure there is a key pair to go with it
+    pub(crate) fn set_peer(
+        &mut self,
+        trng: &mut trng::Trng,
+        config: WgPeerConfig,
+    ) -> Result<(), std::io::Error> {
+        self.private_key(trng).ok_or(std::io::Error::from(std::io::ErrorKind::Other))?;
+        save_peer(&self.pddb, &config)?;
+        self.peer = Some(config);
+        Ok(())
+    }
+
+    /// Deletes the keys and peer configuration from the PDDB
+    pub(crate) fn clear(&mut self) {
+        match self.pddb.delete_dict(WG_DICT_NAME, None) {
+            Ok(_) => {
+                self.pddb.sync().ok();
+            }
+            Err(e) => log::warn!("couldn't delete {}: {:?}", WG_DICT_NAME, e),
+        }
+        self.private = None;
+        self.peer = None;
+    }
+
+    /// Builds a tunnel to the configured peer, or returns `None` if there is none
+    pub(crate) fn tunnel(&mut self, trng: &mut trng::Trng) -> Option<WgTunnel> {
+        let config = self.peer?;
+        let private = self.private_key(trng)?;
+        // the local session index is 24 bits wide
+        match WgTunnel::new(private, config, trng.get_u32().unwrap() >> 8) {
+            Ok(tunnel) => Some(tunnel),
+            Err(e) => {
+                log::error!("couldn't create the wireguard tunnel: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn read_private_key(pddb: &pddb::Pddb) -> Option<StaticSecret> {
+    let mut key = pddb.get(WG_DICT_NAME, WG_PRIVATE_KEY, None, false, false, None, None::<fn()>).ok()?;
+    let mut bytes = [0u8; 32];
+    match key.read_exact(&mut bytes) {
+        Ok(_) => Some(StaticSecret::from(bytes)),
+        Err(e) => {
+            log::warn!("couldn't read {}:{}: {:?}", WG_DICT_NAME, WG_PRIVATE_KEY, e);
+            None
+        }
+    }
+}
+
+fn new_private_key(pddb: &pddb::Pddb, trng: &mut trng::Trng) -> Option<StaticSecret> {
+    log::info!("generating wireguard key pair");
+    let mut bytes = [0u8; 32];
+    trng.fill_bytes_via_next(&mut bytes);
+    match pddb.get(WG_DICT_NAME, WG_PRIVATE_KEY, None, true, true, Some(bytes.len()), None::<fn()>) {
+        Ok(mut key) => match key.write_all(&bytes) {
+            Ok(_) => {
+                pddb.sync().ok();
+                Some(StaticSecret::from(bytes))
+            }
+            Err(e) => {
+                log::error!("couldn't save wireguard key: {:?}", e);
+                None
+            }
+        },
+        Err(e) => {
+            log::error!("couldn't create {}:{}: {:?}", WG_DICT_NAME, WG_PRIVATE_KEY, e);
+            None
+        }
+    }
+}
+
+fn save_peer(pddb: &pddb::Pddb, config: &WgPeerConfig) -> Result<(), std::io::Error> {
+    let bytes = encode_peer(config);
+    pddb.delete_key(WG_DICT_NAME, WG_PEER_KEY, None).ok();
+    let mut key = pddb.get(WG_DICT_NAME, WG_PEER_KEY, None, true, true, Some(bytes.len()), None::<fn()>)?;
+    key.write_all(&bytes)?;
+    pddb.sync().ok();
+    Ok(())
+}
+
+fn load_peer(pddb: &pddb::Pddb) -> Option<WgPeerConfig> {
+    let mut key = pddb.get(WG_DICT_NAME, WG_PEER_KEY, None, false, false, None, None::<fn()>).ok()?;
+    let mut bytes = Vec::<u8>::new();
+    key.read_to_end(&mut bytes).ok()?;
+    decode_peer(&bytes)
+}
+
+/// Encodes a `WgPeerConfig` for storage in the PDDB
+pub(crate) fn encode_peer(config: &WgPeerConfig) -> Vec<u8> {
+    let mut bytes = vec![WG_PEER_VERSION];
+    bytes.extend_from_slice(&config.public_key);
+    match config.preshared_key {
+        Some(psk) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&psk);
+        }
+        None => bytes.extend_from_slice(&[0u8; 33]),
+    }
+    bytes.extend_from_slice(&config.endpoint);
+    bytes.extend_from_slice(&config.port.to_be_bytes());
+    bytes.extend_from_slice(&config.address);
+    match config.dns {
+        Some(dns) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&dns);
+        }
+        None => bytes.extend_from_slice(&[0u8; 5]),
+    }
+    bytes.extend_from_slice(&config.keepalive.to_be_bytes());
+    for route in config.routes.iter() {
+        match route {
+            Some(route) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&route.addr);
+                bytes.push(route.prefix);
+            }
+            None => bytes.extend_from_slice(&[0u8; 6]),
+        }
+    }
+    bytes.push(config.autoconnect as u8);
+    bytes
+}
+
+/// Decodes a `WgPeerConfig` from the PDDB
+pub(crate) fn decode_peer(bytes: &[u8]) -> Option<WgPeerConfig> {
+    if bytes.len() != 1 + 32 + 33 + 4 + 2 + 4 + 5 + 2 + WG_MAX_ROUTES * 6 + 1 || bytes[0] != WG_PEER_VERSION {
+        log::warn!("unrecognized wireguard peer configuration");
+        return None;
+    }
+    let mut config = WgPeerConfig::default();
+    let mut rest = &bytes[1..];
+    let mut take = |n: usize| {
+        let (head, tail) = rest.split_at(n);
+        rest = tail;
+        head
+    };
+    config.public_key.copy_from_slice(take(32));
+    let psk = take(33);
+    if psk[0] != 0 {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&psk[1..]);
+        config.preshared_key = Some(key);
+    }
+    config.endpoint.copy_from_slice(take(4));
+    config.port = u16::from_be_bytes([take(1)[0], take(1)[0]]);
+    config.address.copy_from_slice(take(4));
+    let dns = take(5);
+    if dns[0] != 0 {
+        config.dns = Some([dns[1], dns[2], dns[3], dns[4]]);
+    }
+    config.keepalive = u16::from_be_bytes([take(1)[0], take(1)[0]]);
+    for route in config.routes.iter_mut() {
+        let entry = take(6);
+        if entry[0] != 0 {
+            *route = Some(WgRoute { addr: [entry[1], entry[2], entry[3], entry[4]], prefix: entry[5] });
+        }
+    }
+    config.autoconnect = take(1)[0] != 0;
+    Some(config)
+}
@@ -0,0 +1,866 @@
+// WireGuard protocol
+//
+// The parts of WireGuard (https://www.wireguard.com/papers/wireguard.pdf) needed for the single
+// peer of wireguard.rs: the Noise_IKpsk2 handshake in either role, cookie replies, transport data
+// with replay protection, and the timers that keep a session alive and rekey it. Routing and
+// framing stay in wireguard.rs. Only pure-Rust RustCrypto primitives are used, so nothing here
+// depends on platform support beyond `getrandom`, which Xous provides through the TRNG.
+//
+// Left out, because a device with a single peer doesn't need them: sending cookie replies when
+// under load, handshake rate limiting, and roaming (the peer endpoint is fixed by the configuration).
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use blake2::digest::consts::U16;
+use blake2::digest::{Digest, FixedOutput, KeyInit, Mac};
+use blake2::{Blake2s256, Blake2sMac};
+use chacha20poly1305::aead::AeadInPlace;
+use chacha20poly1305::{ChaCha20Poly1305, Nonce, Tag, XChaCha20Poly1305, XNonce};
+use hmac::SimpleHmac;
+pub(crate) use x25519_dalek::{PublicKey, StaticSecret};
+
+const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &[u8] = b"mac1----";
+const LABEL_COOKIE: &[u8] = b"cookie--";
+
+const HANDSHAKE_INIT: u8 = 1;
+const HANDSHAKE_RESP: u8 = 2;
+const COOKIE_REPLY: u8 = 3;
+const DATA: u8 = 4;
+const HANDSHAKE_INIT_LEN: usize = 148;
+const HANDSHAKE_RESP_LEN: usize = 92;
+const COOKIE_REPLY_LEN: usize = 64;
+const DATA_HEADER_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+/// the largest packet encapsulated: a whole Ethernet payload
+const MAX_PACKET_LEN: usize = 1500;
+
+const REKEY_AFTER_MESSAGES: u64 = 1 << 60;
+const REJECT_AFTER_MESSAGES: u64 = u64::MAX - (1 << 13);
+const REKEY_AFTER_TIME: Duration = Duration::from_secs(120);
+const REJECT_AFTER_TIME: Duration = Duration::from_secs(180);
+const REKEY_ATTEMPT_TIME: Duration = Duration::from_secs(90);
+const REKEY_TIMEOUT: Duration = Duration::from_secs(5);
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+const COOKIE_LIFETIME: Duration = Duration::from_secs(120);
+/// packets held while a handshake is in flight
+const MAX_QUEUED: usize = 16;
+/// counters accepted out of order, in 64 bit words
+const REPLAY_WORDS: usize = 32;
+const REPLAY_BITS: u64 = 64 * REPLAY_WORDS as u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WireGuardError {
+    DestinationBufferTooSmall,
+    InvalidPacket,
+    InvalidMac,
+    InvalidAeadTag,
+    /// the handshake is not from (or not for) the configured peer
+    WrongPeer,
+    /// a replayed handshake initiation
+    WrongTai64nTimestamp,
+    /// no session or handshake uses this receiver index
+    WrongIndex,
+    DuplicateCounter,
+    ExpiredSession,
+    /// the handshake was retried for `REKEY_ATTEMPT_TIME` without a response
+    ConnectionExpired,
+}
+
+/// The outcome of a call into the tunnel, borrowing the destination buffer
+#[derive(Debug)]
+pub(crate) enum TunnResult<'a> {
+    Done,
+    Err(WireGuardError),
+    /// a message for the peer
+    WriteToNetwork(&'a mut [u8]),
+    /// a packet that came through the tunnel
+    WriteToTunnelV4(&'a mut [u8]),
+    WriteToTunnelV6(&'a mut [u8]),
+}
+
+/// A handshake initiation waiting for its response
+struct Handshake {
+    local_index: u32,
+    chaining_key: [u8; 32],
+    hash: [u8; 32],
+    ephemeral: StaticSecret,
+    sent: Instant,
+}
+
+struct Session {
+    local_index: u32,
+    remote_index: u32,
+    sender: ChaCha20Poly1305,
+    receiver: ChaCha20Poly1305,
+    send_counter: u64,
+    replay: ReplayWindow,
+    established: Instant,
+    /// sessions we initiated are the ones we rekey
+    initiator: bool,
+}
+
+impl Session {
+    fn usable(&self, now: Instant) -> bool {
+        now.duration_since(self.established) < REJECT_AFTER_TIME && self.send_counter < REJECT_AFTER_MESSAGES
+    }
+}
+
+/// A sliding window over the receive counters of a session
+struct ReplayWindow {
+    /// one more than the highest counter received
+    next: u64,
+    bits: [u64; REPLAY_WORDS],
+}
+
+impl ReplayWindow {
+    fn new() -> Self { ReplayWindow { next: 0, bits: [0; REPLAY_WORDS] } }
+
+    fn bit(counter: u64) -> (usize, u64) {
+        let bit = counter % REPLAY_BITS;
+        ((bit / 64) as usize, 1 << (bit % 64))
+    }
+
+    /// Returns true if `counter` is neither a duplicate nor too old
+    fn check(&self, counter: u64) -> bool {
+        if counter >= self.next {
+            return true;
+        }
+        if self.next - counter >= REPLAY_BITS {
+            return false;
+        }
+        let (word, mask) = Self::bit(counter);
+        self.bits[word] & mask == 0
+    }
+
+    fn mark(&mut self, counter: u64) {
+        if counter >= self.next {
+            // slide the window, forgetting the counters that fall out of it
+            if counter - self.next >= REPLAY_BITS {
+                self.bits = [0; REPLAY_WORDS];
+            } else {
+                for old in self.next..counter {
+                    let (word, mask) = Self::bit(old);
+                    self.bits[word] &= !mask;
+                }
+            }
+            self.next = counter + 1;
+        }
+        let (word, mask) = Self::bit(counter);
+        self.bits[word] |= mask;
+    }
+}
+
+/// A WireGuard tunnel to a single peer
+pub(crate) struct Tunn {
+    private: StaticSecret,
+    public: PublicKey,
+    peer: PublicKey,
+    psk: [u8; 32],
+    keepalive: Option<Duration>,
+    /// DH(private, peer), fixed for the life of the tunnel
+    static_static: [u8; 32],
+    /// key for the mac1 of messages to the peer
+    mac1_peer: [u8; 32],
+    /// key for the mac1 of messages from the peer
+    mac1_ours: [u8; 32],
+    /// key for the cookies sent by the peer
+    cookie_key: [u8; 32],
+    index_base: u32,
+    index_counter: u8,
+    handshake: Option<Handshake>,
+    /// when the current series of handshake attempts began
+    handshake_started: Option<Instant>,
+    cookie: Option<([u8; 16], Instant)>,
+    /// mac1 of the last handshake message sent, which a cookie reply is bound to
+    last_mac1: Option<[u8; 16]>,
+    /// the greatest timestamp of a handshake initiation from the peer
+    last_timestamp: [u8; 12],
+    current: Option<Session>,
+    /// the session replaced by a rekey, kept for the packets still in flight
+    previous: Option<Session>,
+    /// a session we responded to, until the peer confirms it by sending data
+    next: Option<Session>,
+    queue: VecDeque<Vec<u8>>,
+    last_handshake: Option<Instant>,
+    last_sent: Option<Instant>,
+    /// data was received and nothing has been sent since
+    keepalive_due: Option<Instant>,
+    /// data was sent and nothing has been received since
+    reply_due: Option<Instant>,
+    tx_bytes: usize,
+    rx_bytes: usize,
+}
+
+impl Tunn {
+    /// # Arguments
+    ///
+    /// * `keepalive` - persistent keepalive interval in seconds
+    /// * `index` - 24 random bits, the top of the local session indices
+    pub(crate) fn new(
+        private: StaticSecret,
+        peer: PublicKey,
+        psk: Option<[u8; 32]>,
+        keepalive: Option<u16>,
+        index: u32,
+    ) -> Result<Self, &'static str> {
+        let static_static = private.diffie_hellman(&peer).to_bytes();
+        if static_static == [0u8; 32] {
+            return Err("the peer public key is invalid");
+        }
+        let public = PublicKey::from(&private);
+        Ok(Tunn {
+            public,
+            peer,
+            psk: psk.unwrap_or([0u8; 32]),
+            keepalive: keepalive.map(|secs| Duration::from_secs(secs as u64)),
+            static_static,
+            mac1_peer: hash(&[LABEL_MAC1, peer.as_bytes()]),
+            mac1_ours: hash(&[LABEL_MAC1, public.as_bytes()]),
+            cookie_key: hash(&[LABEL_COOKIE, peer.as_bytes()]),
+            private,
+            index_base: index << 8,
+            index_counter: 0,
+            handshake: None,
+            handshake_started: None,
+            cookie: None,
+            last_mac1: None,
+            last_timestamp: [0u8; 12],
+            current: None,
+            previous: None,
+            next: None,
+            queue: VecDeque::new(),
+            last_handshake: None,
+            last_sent: None,
+            keepalive_due: None,
+            reply_due: None,
+            tx_bytes: 0,
+            rx_bytes: 0,
+        })
+    }
+
+    /// Returns the time since the current session was established, if there is one
+    pub(crate) fn time_since_last_handshake(&self) -> Option<Duration> {
+        match self.current {
+            Some(_) => self.last_handshake.map(|t| t.elapsed()),
+            None => None,
+        }
+    }
+
+    /// Returns the time since the last handshake, and the payload bytes sent & received
+    pub(crate) fn stats(&self) -> (Option<Duration>, usize, usize) {
+        (self.time_since_last_handshake(), self.tx_bytes, self.rx_bytes)
+    }
+
+    /// Encapsulates an IP packet for the peer. Without a session, the packet is queued and a
+    /// handshake initiation is returned instead (if one is not already in flight).
+    pub(crate) fn encapsulate<'a>(&mut self, src: &[u8], dst: &'a mut [u8]) -> TunnResult<'a> {
+        let now = Instant::now();
+        if self.current.as_ref().is_some_and(|session| session.usable(now)) {
+            return self.send_data(src, dst, now);
+        }
+        if !src.is_empty() {
+            if self.queue.len() >= MAX_QUEUED {
+                self.queue.pop_front();
+            }
+            self.queue.push_back(src.to_vec());
+        }
+        self.format_handshake_initiation(dst, false)
+    }
+
+    /// Consumes a message from the peer. When the result is `WriteToNetwork`, call again with an
+    /// empty `datagram` until `Done` to flush the packets queued during the handshake.
+    pub(crate) fn decapsulate<'a>(&mut self, datagram: &[u8], dst: &'a mut [u8]) -> TunnResult<'a> {
+        let now = Instant::now();
+        if datagram.is_empty() {
+            return match self.current.as_ref().is_some_and(|session| session.usable(now)) {
+                true => match self.queue.pop_front() {
+                    Some(packet) => self.send_data(&packet, dst, now),
+                    None => TunnResult::Done,
+                },
+                false => TunnResult::Done,
+            };
+        }
+        let result = match (datagram[0], datagram.len()) {
+            (_, len) if len < 4 || datagram[1..4] != [0, 0, 0] => Err(WireGuardError::InvalidPacket),
+            (HANDSHAKE_INIT, HANDSHAKE_INIT_LEN) => self.consume_initiation(datagram, dst, now),
+            (HANDSHAKE_RESP, HANDSHAKE_RESP_LEN) => self.consume_response(datagram, dst, now),
+            (COOKIE_REPLY, COOKIE_REPLY_LEN) => self.consume_cookie(datagram, now).map(|_| None),
+            (DATA, len) if len >= DATA_HEADER_LEN + TAG_LEN => return self.consume_data(datagram, dst, now),
+            _ => Err(WireGuardError::InvalidPacket),
+        };
+        match result {
+            Ok(Some(len)) => TunnResult::WriteToNetwork(&mut dst[..len]),
+            Ok(None) => TunnResult::Done,
+            Err(e) => TunnResult::Err(e),
+        }
+    }
+
+    /// Runs the protocol timers: handshake retries and rekeying, keepalives, and session expiry
+    pub(crate) fn update_timers<'a>(&mut self, dst: &'a mut [u8]) -> TunnResult<'a> {
+        let now = Instant::now();
+        if let Some(established) = self.last_handshake {
+            if now.duration_since(established) >= REJECT_AFTER_TIME * 3 {
+                // nothing can be sent or received on these anymore
+                self.current = None;
+                self.previous = None;
+                self.next = None;
+                self.last_handshake = None;
+            }
+        }
+        if let Some(handshake) = self.handshake.as_ref() {
+            if now.duration_since(handshake.sent) < REKEY_TIMEOUT {
+                return TunnResult::Done;
+            }
+            if self.handshake_started.is_some_and(|t| now.duration_since(t) >= REKEY_ATTEMPT_TIME) {
+                self.handshake = None;
+                self.handshake_started = None;
+                self.queue.clear();
+                return TunnResult::Err(WireGuardError::ConnectionExpired);
+            }
+            return self.format_handshake_initiation(dst, true);
+        }
+        let rekey = match self.current.as_ref() {
+            Some(session) => {
+                session.initiator
+                    && (now.duration_since(session.established) >= REKEY_AFTER_TIME
+                        || session.send_counter >= REKEY_AFTER_MESSAGES)
+            }
+            None => false,
+        };
+        let unanswered =
+            self.reply_due.is_some_and(|t| now.duration_since(t) >= KEEPALIVE_TIMEOUT + REKEY_TIMEOUT);
+        if rekey || unanswered {
+            self.reply_due = None;
+            return self.format_handshake_initiation(dst, false);
+        }
+        let passive = self.keepalive_due.is_some_and(|t| now.duration_since(t) >= KEEPALIVE_TIMEOUT);
+        let persistent = match (self.keepalive, self.last_sent) {
+            (Some(interval), Some(sent)) => now.duration_since(sent) >= interval,
+            _ => false,
+        };
+        if (passive || persistent) && self.current.is_some() {
+            return self.encapsulate(&[], dst);
+        }
+        TunnResult::Done
+    }
+
+    /// Returns a handshake initiation for the peer, unless one is already in flight and `force` is
+    /// not set
+    pub(crate) fn format_handshake_initiation<'a>(
+        &mut self,
+        dst: &'a mut [u8],
+        force: bool,
+    ) -> TunnResult<'a> {
+        if self.handshake.is_some() && !force {
+            return TunnResult::Done;
+        }
+        if dst.len() < HANDSHAKE_INIT_LEN {
+            return TunnResult::Err(WireGuardError::DestinationBufferTooSmall);
+        }
+        let now = Instant::now();
+        let local_index = self.new_index();
+        let ephemeral = random_secret();
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let mut chaining_key = hash(&[CONSTRUCTION]);
+        let mut h = hash(&[&hash(&[&chaining_key, IDENTIFIER]), self.peer.as_bytes()]);
+
+        let msg = &mut dst[..HANDSHAKE_INIT_LEN];
+        msg[..4].copy_from_slice(&[HANDSHAKE_INIT, 0, 0, 0]);
+        msg[4..8].copy_from_slice(&local_index.to_le_bytes());
+        msg[8..40].copy_from_slice(ephemeral_public.as_bytes());
+        chaining_key = kdf::<1>(&chaining_key, ephemeral_public.as_bytes())[0];
+        h = hash(&[&h, ephemeral_public.as_bytes()]);
+        let [ck, key] = kdf::<2>(&chaining_key, ephemeral.diffie_hellman(&self.peer).as_bytes());
+        chaining_key = ck;
+        msg[40..72].copy_from_slice(self.public.as_bytes());
+        seal(&key, 0, &mut msg[40..88], &h);
+        h = hash(&[&h, &msg[40..88]]);
+        let [ck, key] = kdf::<2>(&chaining_key, &self.static_static);
+        chaining_key = ck;
+        msg[88..100].copy_from_slice(&tai64n());
+        seal(&key, 0, &mut msg[88..116], &h);
+        h = hash(&[&h, &msg[88..116]]);
+        self.add_macs(msg);
+
+        self.handshake = Some(Handshake { local_index, chaining_key, hash: h, ephemeral, sent: now });
+        self.handshake_started.get_or_insert(now);
+        self.last_sent = Some(now);
+        TunnResult::WriteToNetwork(msg)
+    }
+
+    /// Handles a handshake initiation from the peer, writing our response to `dst`
+    fn consume_initiation(
+        &mut self,
+        msg: &[u8],
+        dst: &mut [u8],
+        now: Instant,
+    ) -> Result<Option<usize>, WireGuardError> {
+        if mac(&self.mac1_ours, &[&msg[..116]]) != msg[116..132] {
+            return Err(WireGuardError::InvalidMac);
+        }
+        if dst.len() < HANDSHAKE_RESP_LEN {
+            return Err(WireGuardError::DestinationBufferTooSmall);
+        }
+        let remote_index = u32::from_le_bytes([msg[4], msg[5], msg[6], msg[7]]);
+        let mut initiator_ephemeral = [0u8; 32];
+        initiator_ephemeral.copy_from_slice(&msg[8..40]);
+        let initiator_ephemeral = PublicKey::from(initiator_ephemeral);
+
+        let mut chaining_key = hash(&[CONSTRUCTION]);
+        let mut h = hash(&[&hash(&[&chaining_key, IDENTIFIER]), self.public.as_bytes()]);
+        chaining_key = kdf::<1>(&chaining_key, initiator_ephemeral.as_bytes())[0];
+        h = hash(&[&h, initiator_ephemeral.as_bytes()]);
+        let [ck, key] = kdf::<2>(&chaining_key, self.private.diffie_hellman(&initiator_ephemeral).as_bytes());
+        chaining_key = ck;
+        let mut peer_static = [0u8; 48];
+        peer_static.copy_from_slice(&msg[40..88]);
+        open(&key, 0, &mut peer_static, &h)?;
+        if peer_static[..32] != self.peer.as_bytes()[..] {
+            return Err(WireGuardError::WrongPeer);
+        }
+        h = hash(&[&h, &msg[40..88]]);
+        let [ck, key] = kdf::<2>(&chaining_key, &self.static_static);
+        chaining_key = ck;
+        let mut timestamp = [0u8; 28];
+        timestamp.copy_from_slice(&msg[88..116]);
+        open(&key, 0, &mut timestamp, &h)?;
+        if timestamp[..12] <= self.last_timestamp[..] {
+            return Err(WireGuardError::WrongTai64nTimestamp);
+        }
+        self.last_timestamp.copy_from_slice(&timestamp[..12]);
+        h = hash(&[&h, &msg[88..116]]);
+
+        let local_index = self.new_index();
+        let ephemeral = random_secret();
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let resp = &mut dst[..HANDSHAKE_RESP_LEN];
+        resp[..4].copy_from_slice(&[HANDSHAKE_RESP, 0, 0, 0]);
+        resp[4..8].copy_from_slice(&local_index.to_le_bytes());
+        resp[8..12].copy_from_slice(&remote_index.to_le_bytes());
+        resp[12..44].copy_from_slice(ephemeral_public.as_bytes());
+        chaining_key = kdf::<1>(&chaining_key, ephemeral_public.as_bytes())[0];
+        h = hash(&[&h, ephemeral_public.as_bytes()]);
+        chaining_key = kdf::<1>(&chaining_key, ephemeral.diffie_hellman(&initiator_ephemeral).as_bytes())[0];
+        chaining_key = kdf::<1>(&chaining_key, ephemeral.diffie_hellman(&self.peer).as_bytes())[0];
+        let [ck, tau, key] = kdf::<3>(&chaining_key, &self.psk);
+        chaining_key = ck;
+        h = hash(&[&h, &tau]);
+        seal(&key, 0, &mut resp[44..60], &h);
+        self.add_macs(resp);
+
+        let [receive, send] = kdf::<2>(&chaining_key, &[]);
+        self.next = Some(new_session(local_index, remote_index, &send, &receive, now, false));
+        self.last_sent = Some(now);
+        Ok(Some(HANDSHAKE_RESP_LEN))
+    }
+
+    /// Handles the response to our handshake initiation. The session is established, and the
+    /// first queued packet (or else a keepalive, to confirm the session) is written to `dst`.
+    fn consume_response(
+        &mut self,
+        msg: &[u8],
+        dst: &mut [u8],
+        now: Instant,
+    ) -> Result<Option<usize>, WireGuardError> {
+        if mac(&self.mac1_ours, &[&msg[..60]]) != msg[60..76] {
+            return Err(WireGuardError::InvalidMac);
+        }
+        let remote_index = u32::from_le_bytes([msg[4], msg[5], msg[6], msg[7]]);
+        let receiver = u32::from_le_bytes([msg[8], msg[9], msg[10], msg[11]]);
+        let handshake = match self.handshake.as_ref() {
+            Some(handshake) if handshake.local_index == receiver => handshake,
+            _ => return Err(WireGuardError::WrongIndex),
+        };
+        let mut responder_ephemeral = [0u8; 32];
+        responder_ephemeral.copy_from_slice(&msg[12..44]);
+        let responder_ephemeral = PublicKey::from(responder_ephemeral);
+
+        let mut chaining_key = kdf::<1>(&handshake.chaining_key, responder_ephemeral.as_bytes())[0];
+        let mut h = hash(&[&handshake.hash, responder_ephemeral.as_bytes()]);
+        chaining_key =
+            kdf::<1>(&chaining_key, handshake.ephemeral.diffie_hellman(&responder_ephemeral).as_bytes())[0];
+        chaining_key =
+            kdf::<1>(&chaining_key, self.private.diffie_hellman(&responder_ephemeral).as_bytes())[0];
+        let [ck, tau, key] = kdf::<3>(&chaining_key, &self.psk);
+        chaining_key = ck;
+        h = hash(&[&h, &tau]);
+        let mut empty = [0u8; TAG_LEN];
+        empty.copy_from_slice(&msg[44..60]);
+        open(&key, 0, &mut empty, &h)?;
+
+        let [send, receive] = kdf::<2>(&chaining_key, &[]);
+        let session = new_session(receiver, remote_index, &send, &receive, now, true);
+        self.previous = self.current.replace(session);
+        self.handshake = None;
+        self.handshake_started = None;
+        self.cookie = None;
+        self.last_handshake = Some(now);
+        let packet = self.queue.pop_front().unwrap_or_default();
+        match self.send_data(&packet, dst, now) {
+            TunnResult::WriteToNetwork(out) => Ok(Some(out.len())),
+            TunnResult::Err(e) => Err(e),
+            _ => Ok(None),
+        }
+    }
+
+    /// Handles a cookie reply to our last handshake message; the retry carries the cookie in mac2
+    fn consume_cookie(&mut self, msg: &[u8], now: Instant) -> Result<(), WireGuardError> {
+        let receiver = u32::from_le_bytes([msg[4], msg[5], msg[6], msg[7]]);
+        let known = self.handshake.as_ref().map(|h| h.local_index) == Some(receiver)
+            || self.next.as_ref().map(|s| s.local_index) == Some(receiver);
+        let last_mac1 = match self.last_mac1 {
+            Some(mac1) if known => mac1,
+            _ => return Err(WireGuardError::WrongIndex),
+        };
+        let cipher = XChaCha20Poly1305::new_from_slice(&self.cookie_key).unwrap();
+        let mut cookie = [0u8; 16];
+        cookie.copy_from_slice(&msg[32..48]);
+        cipher
+            .decrypt_in_place_detached(
+                XNonce::from_slice(&msg[8..32]),
+                &last_mac1,
+                &mut cookie,
+                Tag::from_slice(&msg[48..64]),
+            )
+            .map_err(|_| WireGuardError::InvalidAeadTag)?;
+        self.cookie = Some((cookie, now));
+        Ok(())
+    }
+
+    fn consume_data<'a>(&mut self, msg: &[u8], dst: &'a mut [u8], now: Instant) -> TunnResult<'a> {
+        let receiver = u32::from_le_bytes([msg[4], msg[5], msg[6], msg[7]]);
+        let counter =
+            u64::from_le_bytes([msg[8], msg[9], msg[10], msg[11], msg[12], msg[13], msg[14], msg[15]]);
+        let len = msg.len() - DATA_HEADER_LEN - TAG_LEN;
+        if len > dst.len() {
+            return TunnResult::Err(WireGuardError::DestinationBufferTooSmall);
+        }
+        let confirms_next = self.next.as_ref().map(|s| s.local_index) == Some(receiver);
+        let session = match self
+            .current
+            .iter_mut()
+            .chain(self.previous.iter_mut())
+            .chain(self.next.iter_mut())
+            .find(|session| session.local_index == receiver)
+        {
+            Some(session) => session,
+            None => return TunnResult::Err(WireGuardError::WrongIndex),
+        };
+        if now.duration_since(session.established) >= REJECT_AFTER_TIME {
+            return TunnResult::Err(WireGuardError::ExpiredSession);
+        }
+        if counter >= REJECT_AFTER_MESSAGES || !session.replay.check(counter) {
+            return TunnResult::Err(WireGuardError::DuplicateCounter);
+        }
+        dst[..len].copy_from_slice(&msg[DATA_HEADER_LEN..DATA_HEADER_LEN + len]);
+        if session
+            .receiver
+            .decrypt_in_place_detached(
+                &nonce(counter),
+                &[],
+                &mut dst[..len],
+                Tag::from_slice(&msg[DATA_HEADER_LEN + len..]),
+            )
+            .is_err()
+        {
+            return TunnResult::Err(WireGuardError::InvalidAeadTag);
+        }
+        session.replay.mark(counter);
+        if confirms_next {
+            // the peer has started using the session we responded to
+            self.previous = self.current.take();
+            self.current = self.next.take();
+            self.last_handshake = Some(now);
+        }
+        self.reply_due = None;
+        self.rx_bytes += len;
+        if len == 0 {
+            return TunnResult::Done;
+        }
+        self.keepalive_due.get_or_insert(now);
+        // the padding is not part of the packet: take its length from the IP header
+        match dst[0] >> 4 {
+            4 if len >= 20 => {
+                let total = u16::from_be_bytes([dst[2], dst[3]]) as usize;
+                if total < 20 || total > len {
+                    return TunnResult::Err(WireGuardError::InvalidPacket);
+                }
+                TunnResult::WriteToTunnelV4(&mut dst[..total])
+            }
+            6 if len >= 40 => {
+                let total = 40 + u16::from_be_bytes([dst[4], dst[5]]) as usize;
+                if total > len {
+                    return TunnResult::Err(WireGuardError::InvalidPacket);
+                }
+                TunnResult::WriteToTunnelV6(&mut dst[..total])
+            }
+            _ => TunnResult::Err(WireGuardError::InvalidPacket),
+        }
+    }
+
+    /// Encrypts `packet` (empty for a keepalive) on the current session
+    fn send_data<'a>(&mut self, packet: &[u8], dst: &'a mut [u8], now: Instant) -> TunnResult<'a> {
+        if packet.len() > MAX_PACKET_LEN {
+            return TunnResult::Err(WireGuardError::InvalidPacket);
+        }
+        let padded = (packet.len() + 15) & !15;
+        let len = DATA_HEADER_LEN + padded + TAG_LEN;
+        if dst.len() < len {
+            return TunnResult::Err(WireGuardError::DestinationBufferTooSmall);
+        }
+        let session = match self.current.as_mut() {
+            Some(session) if session.usable(now) => session,
+            _ => return TunnResult::Err(WireGuardError::ExpiredSession),
+        };
+        let counter = session.send_counter;
+        session.send_counter += 1;
+        let msg = &mut dst[..len];
+        msg[..4].copy_from_slice(&[DATA, 0, 0, 0]);
+        msg[4..8].copy_from_slice(&session.remote_index.to_le_bytes());
+        msg[8..16].copy_from_slice(&counter.to_le_bytes());
+        msg[DATA_HEADER_LEN..DATA_HEADER_LEN + packet.len()].copy_from_slice(packet);
+        msg[DATA_HEADER_LEN + packet.len()..DATA_HEADER_LEN + padded].iter_mut().for_each(|b| *b = 0);
+        let (payload, tag) = msg[DATA_HEADER_LEN..].split_at_mut(padded);
+        let t = session.sender.encrypt_in_place_detached(&nonce(counter), &[], payload).unwrap();
+        tag.copy_from_slice(&t);
+        self.tx_bytes += packet.len();
+        self.last_sent = Some(now);
+        self.keepalive_due = None;
+        if !packet.is_empty() {
+            self.reply_due.get_or_insert(now);
+        }
+        TunnResult::WriteToNetwork(msg)
+    }
+
+    /// Fills in mac1, and mac2 if there is a fresh cookie, of a handshake message
+    fn add_macs(&mut self, msg: &mut [u8]) {
+        let len = msg.len();
+        let mac1 = mac(&self.mac1_peer, &[&msg[..len - 32]]);
+        msg[len - 32..len - 16].copy_from_slice(&mac1);
+        let mac2 = match self.cookie {
+            Some((cookie, received)) if received.elapsed() < COOKIE_LIFETIME => {
+                mac(&cookie, &[&msg[..len - 16]])
+            }
+            _ => [0u8; 16],
+        };
+        msg[len - 16..].copy_from_slice(&mac2);
+        self.last_mac1 = Some(mac1);
+    }
+
+    fn new_index(&mut self) -> u32 {
+        self.index_counter = self.index_counter.wrapping_add(1);
+        self.index_base | self.index_counter as u32
+    }
+}
+
+fn new_session(
+    local_index: u32,
+    remote_index: u32,
+    send: &[u8; 32],
+    receive: &[u8; 32],
+    now: Instant,
+    initiator: bool,
+) -> Session {
+    Session {
+        local_index,
+        remote_index,
+        sender: ChaCha20Poly1305::new_from_slice(send).unwrap(),
+        receiver: ChaCha20Poly1305::new_from_slice(receive).unwrap(),
+        send_counter: 0,
+        replay: ReplayWindow::new(),
+        established: now,
+        initiator,
+    }
+}
+
+fn random_secret() -> StaticSecret {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("no entropy for the wireguard handshake");
+    StaticSecret::from(bytes)
+}
+
+/// The TAI64N label of the current time
+fn tai64n() -> [u8; 12] {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut label = [0u8; 12];
+    label[..8].copy_from_slice(&(0x400000000000000a + now.as_secs()).to_be_bytes());
+    label[8..].copy_from_slice(&now.subsec_nanos().to_be_bytes());
+    label
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Blake2s256::new();
+    for part in parts {
+        Digest::update(&mut hasher, part);
+    }
+    hasher.finalize().into()
+}
+
+/// Keyed BLAKE2s-128
+fn mac(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = <Blake2sMac<U16> as KeyInit>::new_from_slice(key).unwrap();
+    for part in parts {
+        Mac::update(&mut mac, part);
+    }
+    mac.finalize_fixed().into()
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut hmac = <SimpleHmac<Blake2s256> as KeyInit>::new_from_slice(key).unwrap();
+    for part in parts {
+        Mac::update(&mut hmac, part);
+    }
+    hmac.finalize().into_bytes().into()
+}
+
+/// HKDF with HMAC-BLAKE2s, returning N keys
+fn kdf<const N: usize>(chaining_key: &[u8; 32], input: &[u8]) -> [[u8; 32]; N] {
+    let prk = hmac(chaining_key, &[input]);
+    let mut keys = [[0u8; 32]; N];
+    let mut previous: Option<[u8; 32]> = None;
+    for (i, key) in keys.iter_mut().enumerate() {
+        *key = match previous {
+            Some(previous) => hmac(&prk, &[&previous, &[i as u8 + 1]]),
+            None => hmac(&prk, &[&[1u8]]),
+        };
+        previous = Some(*key);
+    }
+    keys
+}
+
+fn nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    Nonce::clone_from_slice(&nonce)
+}
+
+/// Encrypts `buf[..len - 16]` in place, and writes the tag to the last 16 bytes
+fn seal(key: &[u8; 32], counter: u64, buf: &mut [u8], aad: &[u8]) {
+    let (plaintext, tag) = buf.split_at_mut(buf.len() - TAG_LEN);
+    let cipher = ChaCha20Poly1305::new_from_slice(key).unwrap();
+    let t = cipher.encrypt_in_place_detached(&nonce(counter), aad, plaintext).unwrap();
+    tag.copy_from_slice(&t);
+}
+
+/// Decrypts `buf[..len - 16]` in place, checking the tag in the last 16 bytes
+fn open(key: &[u8; 32], counter: u64, buf: &mut [u8], aad: &[u8]) -> Result<(), WireGuardError> {
+    let (ciphertext, tag) = buf.split_at_mut(buf.len() - TAG_LEN);
+    let cipher = ChaCha20Poly1305::new_from_slice(key).unwrap();
+    cipher
+        .decrypt_in_place_detached(&nonce(counter), aad, ciphertext, Tag::from_slice(tag))
+        .map_err(|_| WireGuardError::InvalidAeadTag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (Tunn, Tunn) {
+        let a = random_secret();
+        let b = random_secret();
+        let a_public = PublicKey::from(&a);
+        let b_public = PublicKey::from(&b);
+        let psk = Some([7u8; 32]);
+        (Tunn::new(a, b_public, psk, None, 1).unwrap(), Tunn::new(b, a_public, psk, None, 2).unwrap())
+    }
+
+    fn ipv4_packet(len: usize) -> Vec<u8> {
+        let mut packet = vec![0u8; len];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&(len as u16).to_be_bytes());
+        packet[20..].iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        packet
+    }
+
+    fn written(result: TunnResult) -> Vec<u8> {
+        match result {
+            TunnResult::WriteToNetwork(out) => out.to_vec(),
+            other => panic!("expected a message for the network, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn handshake_and_data_in_both_directions() {
+        let (mut a, mut b) = pair();
+        let mut buf = [0u8; 2048];
+        let packet = ipv4_packet(61);
+
+        // a queues the packet and initiates
+        let init = written(a.encapsulate(&packet, &mut buf));
+        assert_eq!(init.len(), HANDSHAKE_INIT_LEN);
+        let resp = written(b.decapsulate(&init, &mut buf));
+        assert_eq!(resp.len(), HANDSHAKE_RESP_LEN);
+        // the response establishes a's session, and releases the queued packet
+        let data = written(a.decapsulate(&resp, &mut buf));
+        assert!(matches!(a.decapsulate(&[], &mut buf), TunnResult::Done));
+        assert_eq!(data.len(), DATA_HEADER_LEN + 64 + TAG_LEN);
+        match b.decapsulate(&data, &mut buf) {
+            TunnResult::WriteToTunnelV4(out) => assert_eq!(out, &packet[..]),
+            other => panic!("expected a packet, got {:?}", other),
+        }
+        // which confirms b's session, so b can answer
+        let reply = ipv4_packet(1420);
+        let data = written(b.encapsulate(&reply, &mut buf));
+        match a.decapsulate(&data, &mut buf) {
+            TunnResult::WriteToTunnelV4(out) => assert_eq!(out, &reply[..]),
+            other => panic!("expected a packet, got {:?}", other),
+        }
+        assert!(a.time_since_last_handshake().is_some());
+        assert_eq!(a.stats().1, packet.len());
+    }
+
+    #[test]
+    fn rejects_replays_and_tampering() {
+        let (mut a, mut b) = pair();
+        let mut buf = [0u8; 2048];
+        let init = written(a.format_handshake_initiation(&mut buf, false));
+        let resp = written(b.decapsulate(&init, &mut buf));
+        // a replayed initiation is refused on its timestamp
+        assert!(matches!(
+            b.decapsulate(&init, &mut buf),
+            TunnResult::Err(WireGuardError::WrongTai64nTimestamp)
+        ));
+        let keepalive = written(a.decapsulate(&resp, &mut buf));
+        assert!(matches!(b.decapsulate(&keepalive, &mut buf), TunnResult::Done));
+        assert!(matches!(
+            b.decapsulate(&keepalive, &mut buf),
+            TunnResult::Err(WireGuardError::DuplicateCounter)
+        ));
+
+        let mut data = written(a.encapsulate(&ipv4_packet(40), &mut buf));
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(matches!(b.decapsulate(&data, &mut buf), TunnResult::Err(WireGuardError::InvalidAeadTag)));
+
+        let mut init = written(a.format_handshake_initiation(&mut buf, true));
+        init[20] ^= 1;
+        assert!(matches!(b.decapsulate(&init, &mut buf), TunnResult::Err(WireGuardError::InvalidMac)));
+    }
+
+    #[test]
+    fn refuses_other_peers() {
+        let (_, mut b) = pair();
+        let stranger = random_secret();
+        let mut c = Tunn::new(stranger, b.public, None, None, 3).unwrap();
+        let mut buf = [0u8; 2048];
+        let init = written(c.format_handshake_initiation(&mut buf, false));
+        assert!(matches!(b.decapsulate(&init, &mut buf), TunnResult::Err(WireGuardError::WrongPeer)));
+    }
+
+    #[test]
+    fn replay_window() {
+        let mut window = ReplayWindow::new();
+        for counter in [0, 1, 5, 3, 4000] {
+            assert!(window.check(counter));
+            window.mark(counter);
+            assert!(!window.check(counter));
+        }
+        // 2 was skipped, but has fallen out of the window behind 4000; 3999 is still inside it
+        assert!(!window.check(2));
+        assert!(!window.check(4000 - REPLAY_BITS + 1));
+        assert!(window.check(4000 - REPLAY_BITS + 2));
+        assert!(window.check(3999));
+    }
+}
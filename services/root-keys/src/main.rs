@@ -586,8 +586,9 @@ fn main() -> ! {
           1. Shellchat for test initiation
           2. Main menu -> trigger initialization
           3. PDDB
+          4. Net, wrapping keys on behalf of tls
     */
-    let keys_sid = xns.register_name(api::SERVER_NAME_KEYS, Some(3)).expect("can't register server");
+    let keys_sid = xns.register_name(api::SERVER_NAME_KEYS, Some(4)).expect("can't register server");
 
     let mut keys = RootKeys::new();
     log::info!("Boot FPGA key source: {:?}", keys.fpga_key_source());
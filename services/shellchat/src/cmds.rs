@@ -106,6 +106,8 @@ use ver::*;
 // currently overlaps the PDDB space. We'll fix this eventually, but for now, let's switch to PDDB mode.
 mod backlight;
 use backlight::*;
+mod screenshot;
+use screenshot::*;
 mod accel;
 use accel::*;
 #[cfg(feature = "dbg-ecupdate")]
@@ -343,6 +345,7 @@ impl CmdEnv {
         let mut backlight_cmd = Backlight {};
         let mut accel_cmd = Accel {};
         let mut console_cmd = Console {};
+        let mut screenshot_cmd = ScreenshotCmd {};
         let commands: &mut [&mut dyn ShellCmdApi] = &mut [
             ///// 4. add your command to this array, so that it can be looked up and dispatched
             &mut echo_cmd,
@@ -360,6 +363,7 @@ impl CmdEnv {
             &mut self.ecup_cmd,
             &mut self.trng_cmd,
             &mut console_cmd,
+            &mut screenshot_cmd,
             // &mut self.memtest_cmd,
             &mut self.keys_cmd,
             &mut self.wlan_cmd,
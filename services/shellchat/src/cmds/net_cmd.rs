@@ -16,11 +16,10 @@ use xous::MessageEnvelope;
 use xous_ipc::String;
 #[cfg(feature = "ditherpunk")]
 use {gam::DecodePng, std::str::FromStr};
+#[cfg(feature = "tls")]
+use tls::Tls;
 #[cfg(feature = "websocket")]
-use {
-    tls::Tls,
-    tungstenite::{stream::MaybeTlsStream, WebSocket},
-};
+use tungstenite::{stream::MaybeTlsStream, WebSocket};
 
 use crate::{CommonEnv, ShellCmdApi};
 
@@ -183,6 +182,51 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                         write!(ret, "Usage: tcpget bunniefoo.com/bunnie/test.txt").unwrap();
                     }
                 }
+                // same as `server`, but over tls on port 443 with the self-signed device certificate
+                #[cfg(feature = "tls")]
+                "tlsserver" => {
+                    let tls = Tls::new();
+                    match tls.server_fingerprint_modal() {
+                        Ok(_) => {
+                            thread::spawn({
+                                let boot_instant = env.boot_instant.clone();
+                                move || {
+                                    let listener = match tls::server::TlsServer::bind("0.0.0.0:443") {
+                                        Ok(listener) => listener,
+                                        Err(e) => {
+                                            log::warn!("failed to start tls listener: {:?}", e);
+                                            return;
+                                        }
+                                    };
+                                    let pool = ThreadPool::new(4);
+
+                                    for stream in listener.incoming() {
+                                        let stream = match stream {
+                                            Ok(s) => s,
+                                            Err(e) => {
+                                                log::warn!("Listener returned error: {:?}", e);
+                                                continue;
+                                            }
+                                        };
+
+                                        pool.execute({
+                                            let bi = boot_instant.clone();
+                                            move || {
+                                                handle_connection(stream, bi);
+                                            }
+                                        });
+                                    }
+
+                                    log::info!("demo tls server shutting down.");
+                                }
+                            });
+                            write!(ret, "TLS listener started on port 443").unwrap();
+                        }
+                        Err(e) => {
+                            write!(ret, "failed to configure tls server: {:?}", e).unwrap();
+                        }
+                    }
+                }
                 "server" => {
                     // this is adapted from https://doc.rust-lang.org/book/ch20-03-graceful-shutdown-and-cleanup.html
                     thread::spawn({
@@ -719,6 +763,61 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                         }
                     }
                 }
+                // net wg <sub-command>
+                //     status                         tunnel state, last handshake and traffic
+                //     key                            public key of this device (generated on first use)
+                //     peer <key> <ip:port> <addr>    peer public key, endpoint and tunnel address
+                //     psk <key>|none                 preshared key
+                //     dns <ip>|none                  DNS server while the tunnel is up
+                //     route <cidr>|all|clear         route a prefix (or all traffic) through the tunnel
+                //     keepalive <secs>               persistent keepalive, 0 to disable
+                //     auto on|off                    bring the tunnel up whenever wifi connects
+                //     up|down                        bring the tunnel up or down
+                //     clear                          delete the keys and peer configuration
+                "wg" => {
+                    wg_cmd(&env.netmgr, &mut tokens, &mut ret);
+                }
+                // net ipv6                          addresses, router and DNS servers acquired by
+                // SLAAC/DHCPv6
+                "ipv6" => match env.netmgr.get_ipv6_config() {
+                    Ok(config) => {
+                        write!(ret, "link-local {}", std::net::Ipv6Addr::from(config.link_local)).ok();
+                        match config.addr {
+                            Some(addr) => write!(
+                                ret,
+                                "\naddress {}/{}",
+                                std::net::Ipv6Addr::from(addr),
+                                config.prefix_len
+                            ),
+                            None => write!(ret, "\nno global address"),
+                        }
+                        .ok();
+                        if let Some(router) = config.router {
+                            write!(ret, "\nrouter {}", std::net::Ipv6Addr::from(router)).ok();
+                        }
+                        for dns in config.dns.iter().flatten() {
+                            write!(ret, "\ndns {}", std::net::Ipv6Addr::from(*dns)).ok();
+                        }
+                    }
+                    Err(e) => {
+                        write!(ret, "Couldn't get the IPv6 config: {:?}", e).ok();
+                    }
+                },
+                // net captive <sub-command>
+                //     status                         result of the captive portal check
+                //     url <http://host/path>|default URL checked after joining a network
+                "captive" => {
+                    captive_cmd(&env.netmgr, &mut tokens, &mut ret);
+                }
+                // net capture <sub-command>
+                //     start [kib] [snaplen]          tee WLAN frames into a ring, replacing the last capture
+                //     stop                           stop capturing, keeping the frames
+                //     status                         frames held and pcapng size
+                //     save [name]                    save the capture as pcapng in the PDDB
+                //     dump                           write the capture as base64 pcapng to the debug console
+                "capture" => {
+                    capture_cmd(&env.netmgr, &mut tokens, &mut ret);
+                }
                 #[cfg(any(feature = "precursor", feature = "renode"))]
                 "ping" => {
                     if let Some(name) = tokens.next() {
@@ -864,7 +963,7 @@ enum Responses {
     Buzz,
 }
 
-fn handle_connection(mut stream: TcpStream, boot_instant: Instant) {
+fn handle_connection<S: Read + Write>(mut stream: S, boot_instant: Instant) {
     // the result is implementation dependent, on Xous hardware, this is effectively the same as
     // ticktimer.elapsed_ms()
     let elapsed_time = Instant::now().duration_since(boot_instant);
@@ -1010,6 +1109,360 @@ impl Worker {
     }
 }
 
+fn wg_cmd<'a>(netmgr: &net::NetManager, tokens: &mut impl Iterator<Item = &'a str>, ret: &mut String<1024>) {
+    use core::fmt::Write;
+    let helpstring = "net wg [status] [key] [peer key ip:port addr] [psk key|none] [dns ip|none] [route cidr|all|clear] [keepalive secs] [auto on|off] [up] [down] [clear]";
+    let sub_cmd = tokens.next().unwrap_or("status");
+    match sub_cmd {
+        "status" => match netmgr.wg_status() {
+            Ok(status) => {
+                write!(ret, "WireGuard: {:?}", status.state).ok();
+                if let Some(secs) = status.last_handshake_secs {
+                    write!(ret, "\nLast handshake {}s ago", secs).ok();
+                }
+                if status.state != net::WgState::Off {
+                    write!(ret, "\nTx {} bytes, Rx {} bytes", status.tx_bytes, status.rx_bytes).ok();
+                }
+                if let Ok(Some(config)) = netmgr.wg_get_peer() {
+                    write!(
+                        ret,
+                        "\nPeer {}\nEndpoint {}:{}\nAddress {}",
+                        base64::encode(config.public_key),
+                        std::net::Ipv4Addr::from(config.endpoint),
+                        config.port,
+                        std::net::Ipv4Addr::from(config.address),
+                    )
+                    .ok();
+                    for route in config.routes.iter().flatten() {
+                        write!(ret, "\nRoute {}/{}", std::net::Ipv4Addr::from(route.addr), route.prefix).ok();
+                    }
+                }
+            }
+            Err(e) => {
+                write!(ret, "Couldn't get the WireGuard status: {:?}", e).ok();
+            }
+        },
+        "key" => match netmgr.wg_public_key() {
+            Ok(Some(key)) => {
+                write!(ret, "{}", base64::encode(key)).ok();
+            }
+            _ => {
+                write!(ret, "No key: is the PDDB mounted?").ok();
+            }
+        },
+        "up" => {
+            match netmgr.wg_up() {
+                Ok(_) => write!(ret, "WireGuard tunnel up"),
+                Err(_) => write!(ret, "Couldn't bring the tunnel up: set a peer first"),
+            }
+            .ok();
+        }
+        "down" => {
+            netmgr.wg_down().ok();
+            write!(ret, "WireGuard tunnel down").ok();
+        }
+        "clear" => {
+            netmgr.wg_clear().ok();
+            write!(ret, "WireGuard keys and peer deleted").ok();
+        }
+        "peer" | "psk" | "dns" | "route" | "keepalive" | "auto" => {
+            let mut config = match netmgr.wg_get_peer() {
+                Ok(Some(config)) => config,
+                _ if sub_cmd == "peer" => net::WgPeerConfig::default(),
+                _ => {
+                    write!(ret, "Set a peer first: net wg peer key ip:port addr").ok();
+                    return;
+                }
+            };
+            let valid = match sub_cmd {
+                "peer" => {
+                    let key = tokens.next().and_then(wg_key);
+                    let endpoint = tokens.next().and_then(|ep| ep.parse::<std::net::SocketAddrV4>().ok());
+                    // accept the wg-quick style address/prefix
+                    let address = tokens
+                        .next()
+                        .and_then(|addr| addr.split('/').next())
+                        .and_then(|addr| addr.parse::<std::net::Ipv4Addr>().ok());
+                    match (key, endpoint, address) {
+                        (Some(key), Some(endpoint), Some(address)) => {
+                            config.public_key = key;
+                            config.endpoint = endpoint.ip().octets();
+                            config.port = endpoint.port();
+                            config.address = address.octets();
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                "psk" => match tokens.next() {
+                    Some("none") => {
+                        config.preshared_key = None;
+                        true
+                    }
+                    Some(psk) => {
+                        config.preshared_key = wg_key(psk);
+                        config.preshared_key.is_some()
+                    }
+                    None => false,
+                },
+                "dns" => match tokens.next() {
+                    Some("none") => {
+                        config.dns = None;
+                        true
+                    }
+                    Some(ip) => {
+                        config.dns = ip.parse::<std::net::Ipv4Addr>().ok().map(|ip| ip.octets());
+                        config.dns.is_some()
+                    }
+                    None => false,
+                },
+                "route" => {
+                    let route = match tokens.next() {
+                        Some("clear") => {
+                            config.routes = Default::default();
+                            None
+                        }
+                        Some("all") => Some(net::WgRoute { addr: [0, 0, 0, 0], prefix: 0 }),
+                        Some(cidr) => {
+                            let mut parts = cidr.split('/');
+                            match (
+                                parts.next().and_then(|addr| addr.parse::<std::net::Ipv4Addr>().ok()),
+                                parts.next().map_or(Some(32), |prefix| prefix.parse::<u8>().ok()),
+                            ) {
+                                (Some(addr), Some(prefix)) if prefix <= 32 => {
+                                    Some(net::WgRoute { addr: addr.octets(), prefix })
+                                }
+                                _ => {
+                                    write!(ret, "Invalid route: {}", cidr).ok();
+                                    return;
+                                }
+                            }
+                        }
+                        None => {
+                            write!(ret, "Missing route: net wg route cidr|all|clear").ok();
+                            return;
+                        }
+                    };
+                    match route {
+                        Some(route) if config.routes.contains(&Some(route)) => true,
+                        Some(route) => match config.routes.iter_mut().find(|slot| slot.is_none()) {
+                            Some(slot) => {
+                                *slot = Some(route);
+                                true
+                            }
+                            None => {
+                                write!(ret, "Too many routes (max {})", net::WG_MAX_ROUTES).ok();
+                                return;
+                            }
+                        },
+                        None => true,
+                    }
+                }
+                "keepalive" => match tokens.next().and_then(|secs| secs.parse::<u16>().ok()) {
+                    Some(secs) => {
+                        config.keepalive = secs;
+                        true
+                    }
+                    None => false,
+                },
+                _ => match tokens.next() {
+                    Some("on") => {
+                        config.autoconnect = true;
+                        true
+                    }
+                    Some("off") => {
+                        config.autoconnect = false;
+                        true
+                    }
+                    _ => false,
+                },
+            };
+            if !valid {
+                write!(ret, "{}", helpstring).ok();
+                return;
+            }
+            match netmgr.wg_set_peer(&config) {
+                Ok(_) => write!(ret, "WireGuard peer saved. Takes effect on: net wg up"),
+                Err(e) => write!(ret, "Couldn't save the WireGuard peer: {:?}", e),
+            }
+            .ok();
+        }
+        _ => {
+            write!(ret, "{}", helpstring).ok();
+        }
+    }
+}
+
+fn captive_cmd<'a>(
+    netmgr: &net::NetManager,
+    tokens: &mut impl Iterator<Item = &'a str>,
+    ret: &mut String<1024>,
+) {
+    use core::fmt::Write;
+    let helpstring = "net captive [status] [url http://host/path|default]";
+    match tokens.next().unwrap_or("status") {
+        "status" => match netmgr.connectivity() {
+            Ok(connectivity) => {
+                write!(ret, "Connectivity: {:?}", connectivity.state).ok();
+                if let Some(portal) = connectivity.portal {
+                    write!(ret, "\nSign in at: {}", portal).ok();
+                }
+            }
+            Err(e) => {
+                write!(ret, "Couldn't get the connectivity state: {:?}", e).ok();
+            }
+        },
+        "url" => {
+            let url = match tokens.next() {
+                Some("default") => None,
+                Some(url) if url.starts_with("http://") => Some(url),
+                _ => {
+                    write!(ret, "{}", helpstring).ok();
+                    return;
+                }
+            };
+            match set_check_url(url) {
+                Ok(_) => write!(
+                    ret,
+                    "Checking {} on the next connection",
+                    url.unwrap_or(net::CONNECTIVITY_CHECK_DEFAULT)
+                ),
+                Err(e) => write!(ret, "Couldn't save the check URL: {:?}", e),
+            }
+            .ok();
+        }
+        _ => {
+            write!(ret, "{}", helpstring).ok();
+        }
+    }
+}
+
+fn capture_cmd<'a>(
+    netmgr: &net::NetManager,
+    tokens: &mut impl Iterator<Item = &'a str>,
+    ret: &mut String<1024>,
+) {
+    use core::fmt::Write;
+    let helpstring = "net capture [start [kib] [snaplen]] [stop] [status] [save [name]] [dump]";
+    match tokens.next().unwrap_or("status") {
+        "start" => {
+            let kib = tokens.next().map(|t| t.parse::<usize>());
+            let snaplen = tokens.next().map(|t| t.parse::<usize>());
+            match (kib.unwrap_or(Ok(0)), snaplen.unwrap_or(Ok(0))) {
+                (Ok(kib), Ok(snaplen)) => match kib.checked_mul(1024) {
+                    Some(ring_len) => match netmgr.capture_start(ring_len, snaplen) {
+                        Ok(_) => write!(ret, "Capturing"),
+                        Err(e) => write!(ret, "Couldn't start the capture: {:?}", e),
+                    },
+                    None => write!(ret, "The ring is at most {} KiB", net::CAPTURE_MAX_BYTES / 1024),
+                }
+                .ok(),
+                _ => write!(ret, "{}", helpstring).ok(),
+            };
+        }
+        "stop" => {
+            match netmgr.capture_stop() {
+                Ok(frames) => write!(ret, "Stopped, holding {} frames", frames),
+                Err(e) => write!(ret, "Couldn't stop the capture: {:?}", e),
+            }
+            .ok();
+        }
+        "status" => match netmgr.capture_status() {
+            Ok(status) => {
+                write!(
+                    ret,
+                    "{}: {} frames ({} dropped), {} of {} bytes",
+                    if status.running { "Capturing" } else { "Stopped" },
+                    status.frames,
+                    status.dropped,
+                    status.pcapng_len,
+                    status.ring_len
+                )
+                .ok();
+            }
+            Err(e) => {
+                write!(ret, "Couldn't get the capture status: {:?}", e).ok();
+            }
+        },
+        "save" => {
+            let name = tokens.next().unwrap_or("latest");
+            match netmgr.capture_export() {
+                Ok(pcapng) => match save_capture(name, &pcapng) {
+                    Ok(_) => {
+                        write!(ret, "Saved {} bytes to {}:{}", pcapng.len(), net::CAPTURE_DICT_NAME, name)
+                    }
+                    Err(e) => write!(ret, "Couldn't save the capture: {:?}", e),
+                },
+                Err(e) => write!(ret, "Couldn't export the capture: {:?}", e),
+            }
+            .ok();
+        }
+        "dump" => {
+            match netmgr.capture_export() {
+                Ok(pcapng) => {
+                    // bookended so a host script can pick the file out of the console log
+                    log::info!("{}PCAPNG.BEGIN,{}{}", xous::BOOKEND_START, pcapng.len(), xous::BOOKEND_END);
+                    for chunk in pcapng.chunks(768) {
+                        log::info!(
+                            "{}PCAPNG,{}{}",
+                            xous::BOOKEND_START,
+                            base64::encode(chunk),
+                            xous::BOOKEND_END
+                        );
+                    }
+                    log::info!("{}PCAPNG.END{}", xous::BOOKEND_START, xous::BOOKEND_END);
+                    write!(ret, "Wrote {} bytes to the debug console", pcapng.len())
+                }
+                Err(e) => write!(ret, "Couldn't export the capture: {:?}", e),
+            }
+            .ok();
+        }
+        _ => {
+            write!(ret, "{}", helpstring).ok();
+        }
+    }
+}
+
+fn save_capture(name: &str, pcapng: &[u8]) -> std::io::Result<()> {
+    let pddb = pddb::Pddb::new();
+    pddb.delete_key(net::CAPTURE_DICT_NAME, name, None).ok();
+    let mut key =
+        pddb.get(net::CAPTURE_DICT_NAME, name, None, true, true, Some(pcapng.len()), None::<fn()>)?;
+    key.write_all(pcapng)?;
+    pddb.sync()
+}
+
+/// Saves the connectivity-check URL to the PDDB; `None` goes back to the default
+fn set_check_url(url: Option<&str>) -> std::io::Result<()> {
+    let pddb = pddb::Pddb::new();
+    pddb.delete_key(net::NET_CONFIG_DICT_NAME, net::CONNECTIVITY_CHECK_KEY, None).ok();
+    if let Some(url) = url {
+        let mut key = pddb.get(
+            net::NET_CONFIG_DICT_NAME,
+            net::CONNECTIVITY_CHECK_KEY,
+            None,
+            true,
+            true,
+            Some(url.len()),
+            None::<fn()>,
+        )?;
+        key.write_all(url.as_bytes())?;
+    }
+    pddb.sync()
+}
+
+/// Decodes a base64 WireGuard key
+fn wg_key(b64: &str) -> Option<[u8; 32]> {
+    match base64::decode(b64) {
+        Ok(bytes) if bytes.len() == 32 => {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&bytes);
+            Some(key)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(feature = "ditherpunk")]
 fn heap_usage() -> usize {
     match xous::rsyscall(xous::SysCall::IncreaseHeap(0, xous::MemoryFlags::R))
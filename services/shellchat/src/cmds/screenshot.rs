@@ -0,0 +1,25 @@
+use xous_ipc::String;
+
+use crate::{CommonEnv, ShellCmdApi};
+
+#[derive(Debug)]
+pub struct ScreenshotCmd {}
+
+impl<'a> ShellCmdApi<'a> for ScreenshotCmd {
+    cmd_api!(screenshot);
+
+    // inserts boilerplate for command API
+
+    fn process(
+        &mut self,
+        _args: String<1024>,
+        env: &mut CommonEnv,
+    ) -> Result<Option<String<1024>>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::<1024>::new();
+        // the status bar takes the screenshot and saves it to the PDDB, under the `screenshots` dictionary
+        env.gam.request_screenshot()?;
+        write!(ret, "Screenshot requested").unwrap();
+        Ok(Some(ret))
+    }
+}
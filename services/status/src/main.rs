@@ -9,6 +9,7 @@ use appmenu::*;
 mod app_autogen;
 mod ecup;
 mod preferences;
+mod screenshot;
 mod wifi;
 
 use core::fmt::Write;
@@ -90,12 +91,17 @@ pub(crate) enum StatusOpcode {
     BatteryDisconnect,
     /// for returning wifi stats
     WifiStats,
+    /// for returning the result of the captive portal check
+    WifiConnectivity,
 
     /// Forces EC update
     ForceEcUpdate,
 
     /// Raise the preferences menu
     Preferences,
+
+    /// Take a screenshot and save it to the PDDB, at the request of the GAM
+    Screenshot,
     Quit,
 }
 
@@ -319,6 +325,8 @@ fn wrapped_main() -> ! {
     gam.post_textview(&mut uptime_tv).expect("|status: can't draw battery stats");
     log::debug!("|status: screensize as reported: {:?}", screensize);
     log::debug!("|status: uptime initialized to '{:?}'", uptime_tv);
+    // what the battery stats line last said, for the screen reader's description of the status bar
+    let mut battstats_text = std::string::String::new();
 
     // initialize to some "sane" mid-point defaults, so we don't trigger errors later on before the first real
     // battstat reading comes
@@ -342,6 +350,12 @@ fn wrapped_main() -> ! {
     security_tv.draw_border = false;
     security_tv.margin = Point::new(0, 0);
     security_tv.token = gam.claim_token(gam::STATUS_BAR_NAME).expect("couldn't request token"); // this is a shared magic word to identify this process
+    gam.register_screenshot_listener(
+        security_tv.token.unwrap(),
+        status_sid,
+        StatusOpcode::Screenshot.to_u32().unwrap(),
+    )
+    .expect("couldn't register for screenshot requests");
     security_tv.clear_area = true;
     security_tv.invert = true;
     write!(&mut security_tv, "{}", t!("secnote.startup", locales::LANG)).unwrap();
@@ -686,8 +700,15 @@ fn wrapped_main() -> ! {
 
     // ---------------------- final cleanup before entering main loop
     log::debug!("subscribe to wifi updates");
-    netmgr.wifi_state_subscribe(cb_cid, StatusOpcode::WifiStats.to_u32().unwrap()).unwrap();
+    netmgr
+        .wifi_state_subscribe_with_connectivity(
+            cb_cid,
+            StatusOpcode::WifiStats.to_u32().unwrap(),
+            StatusOpcode::WifiConnectivity.to_u32().unwrap(),
+        )
+        .unwrap();
     let mut wifi_status: WlanStatus = WlanStatus::from_ipc(WlanStatusIpc::default());
+    let mut connectivity = net::Connectivity::default();
 
     #[cfg(feature = "tts")]
     thread::spawn({
@@ -1093,6 +1114,23 @@ fn wrapped_main() -> ! {
                             bars(&gam, status_gid, &wifi_bars, Point { x: 310, y: 13 }, (3, 2), 3, 2);
                             write!(&mut battstats_tv, "{}", ssid.name.as_str().unwrap_or("UTF-8 Error"),)
                                 .unwrap();
+                            if connectivity.state == net::ConnectivityState::Captive {
+                                write!(&mut battstats_tv, " {}", t!("stats.captive", locales::LANG)).unwrap();
+                            }
+                            // flag traffic going through the WireGuard tunnel
+                            match netmgr.wg_status().map(|status| status.state) {
+                                Ok(net::WgState::Up) => {
+                                    write!(&mut battstats_tv, " {}", t!("stats.vpn_up", locales::LANG))
+                                        .unwrap()
+                                }
+                                Ok(net::WgState::Handshaking) => write!(
+                                    &mut battstats_tv,
+                                    " {}",
+                                    t!("stats.vpn_handshaking", locales::LANG)
+                                )
+                                .unwrap(),
+                                _ => {}
+                            }
                         } else {
                             if wifi_status.link_state == com_rs::LinkState::ResetHold {
                                 write!(&mut battstats_tv, "{}", t!("stats.wifi_off", locales::LANG)).unwrap();
@@ -1104,6 +1142,7 @@ fn wrapped_main() -> ! {
                     }
                 }
                 gam.post_textview(&mut battstats_tv).expect("|status: can't draw battery stats");
+                battstats_text = battstats_tv.to_string();
                 if let Some(bounds) = battstats_tv.bounds_computed {
                     if bounds.height() as i16 > screensize.y / 2 + 1 {
                         // the clipping rectangle limits the bounds to the overall height of the status area,
@@ -1120,6 +1159,31 @@ fn wrapped_main() -> ! {
                     unsafe { xous_ipc::Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 wifi_status = WlanStatus::from_ipc(buffer.to_original::<com::WlanStatusIpc, _>().unwrap());
             }
+            Some(StatusOpcode::WifiConnectivity) => {
+                let buffer =
+                    unsafe { xous_ipc::Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let update = buffer.to_original::<net::Connectivity, _>().unwrap();
+                if update.state == net::ConnectivityState::Captive
+                    && connectivity.state != net::ConnectivityState::Captive
+                {
+                    // the modal blocks until it is dismissed, so show it from its own thread
+                    let ssid = wifi_status.ssid.map(|s| s.name.to_string()).unwrap_or_default();
+                    let url = update.portal.map(|p| p.to_string()).unwrap_or_default();
+                    thread::spawn(move || {
+                        let xns = xous_names::XousNames::new().unwrap();
+                        let modals = modals::Modals::new(&xns).unwrap();
+                        modals
+                            .show_notification(
+                                &t!("stats.captive_portal", locales::LANG)
+                                    .replace("{ssid}", &ssid)
+                                    .replace("{url}", &url),
+                                None,
+                            )
+                            .ok();
+                    });
+                }
+                connectivity = update;
+            }
             Some(StatusOpcode::Preferences) => {
                 ticktimer.sleep_ms(100).ok(); // yield for a moment to allow the previous menu to close
                 gam.raise_menu(gam::PREFERENCES_MENU_NAME).unwrap();
@@ -1267,6 +1331,10 @@ fn wrapped_main() -> ! {
                     )
                     .expect("|status: can't write string");
                     gam.post_textview(&mut uptime_tv).expect("|status: can't draw uptime");
+                    // the clock changes every second: only keep its description current while it is read
+                    if gam.screen_reader_on().unwrap_or(false) {
+                        gam.describe_canvas(status_gid, &format!("{}. {}", uptime_tv, battstats_text)).ok();
+                    }
                     if let Some(bounds) = uptime_tv.bounds_computed {
                         if bounds.height() as i16 > screensize.y / 2 + 1 {
                             // the clipping rectangle limits the bounds to the overall height of the status
@@ -1669,6 +1737,11 @@ fn wrapped_main() -> ! {
                     }
                 });
             }
+            Some(StatusOpcode::Screenshot) => {
+                // in a thread, so the status bar keeps updating while the notification is up
+                let token = security_tv.token.unwrap();
+                thread::spawn(move || screenshot::take_screenshot(token));
+            }
             Some(StatusOpcode::Quit) => {
                 xous::return_scalar(msg.sender, 1).ok();
                 break;
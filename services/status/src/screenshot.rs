@@ -0,0 +1,62 @@
+use std::io::Write;
+
+use locales::t;
+
+/// The PDDB dictionary that screenshots are saved to, one BMP file per key
+pub(crate) const SCREENSHOT_DICT: &str = "screenshots";
+const SCREENSHOT_PREFIX: &str = "screenshot-";
+const SCREENSHOT_SUFFIX: &str = ".bmp";
+
+/// The name of the next screenshot, numbered one past the highest already saved
+fn next_key(keys: &[String]) -> String {
+    let last = keys
+        .iter()
+        .filter_map(|k| {
+            k.strip_prefix(SCREENSHOT_PREFIX)?.strip_suffix(SCREENSHOT_SUFFIX)?.parse::<u32>().ok()
+        })
+        .max();
+    format!("{}{:04}{}", SCREENSHOT_PREFIX, last.map_or(0, |n| n + 1), SCREENSHOT_SUFFIX)
+}
+
+fn save(shot: &gam::Screenshot) -> std::io::Result<String> {
+    let pddb = pddb::Pddb::new();
+    let key_name = next_key(&pddb.list_keys(SCREENSHOT_DICT, None).unwrap_or_default());
+    let bmp = shot.to_bmp();
+    let mut key = pddb.get(SCREENSHOT_DICT, &key_name, None, true, true, Some(bmp.len()), None::<fn()>)?;
+    key.write_all(&bmp)?;
+    pddb.sync()?;
+    Ok(key_name)
+}
+
+/// Take a screenshot and save it to the PDDB, then tell the user how that went. The screen is copied
+/// before the notification is raised, so the notification is never in the screenshot.
+///
+/// `token` is the status bar's, which the GAM checks against the screenshot listener's.
+pub(crate) fn take_screenshot(token: [u32; 4]) {
+    let xns = xous_names::XousNames::new().unwrap();
+    let gam = gam::Gam::new(&xns).expect("can't connect to GAM");
+    let modals = modals::Modals::new(&xns).expect("can't connect to Modals");
+    let shot = gam.screenshot(token);
+    if !pddb::Pddb::new().is_mounted_nonblocking() {
+        modals.show_notification(t!("screenshot.no_pddb", locales::LANG), None).ok();
+        return;
+    }
+    let note = match shot {
+        Ok(shot) => match save(&shot) {
+            Ok(key_name) => {
+                log::info!("screenshot saved to {}:{}", SCREENSHOT_DICT, key_name);
+                t!("screenshot.saved", locales::LANG).replace("{name}", &key_name)
+            }
+            Err(e) => {
+                log::error!("couldn't save screenshot: {:?}", e);
+                t!("screenshot.fail", locales::LANG).to_string()
+            }
+        },
+        Err(xous::Error::AccessDenied) => t!("screenshot.vetoed", locales::LANG).to_string(),
+        Err(e) => {
+            log::error!("couldn't take screenshot: {:?}", e);
+            t!("screenshot.fail", locales::LANG).to_string()
+        }
+    };
+    modals.show_notification(&note, None).ok();
+}
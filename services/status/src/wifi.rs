@@ -1,6 +1,8 @@
 use core::fmt::Display;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::net::Ipv4Addr;
 
+use gam::modal::{TextEntryPayload, ValidatorErr};
 use locales::t;
 use net::ScanState;
 use num_traits::*;
@@ -14,6 +16,7 @@ pub enum WlanManOp {
     AddNetworkManually,
     KnownNetworks,
     DeleteNetwork,
+    EditNetwork,
 }
 
 impl Display for WlanManOp {
@@ -24,6 +27,7 @@ impl Display for WlanManOp {
             Self::Status => write!(f, "{}", t!("wlan.status", locales::LANG)),
             Self::DeleteNetwork => write!(f, "{}", t!("wlan.delete", locales::LANG)),
             Self::KnownNetworks => write!(f, "{}", t!("wlan.list_known", locales::LANG)),
+            Self::EditNetwork => write!(f, "{}", t!("wlan.edit", locales::LANG)),
         }
     }
 }
@@ -119,7 +123,7 @@ impl WLANMan {
     pub fn actions(&self) -> Vec<WlanManOp> {
         use WlanManOp::*;
 
-        vec![ScanForNetworks, Status, AddNetworkManually, KnownNetworks, DeleteNetwork]
+        vec![ScanForNetworks, Status, AddNetworkManually, KnownNetworks, EditNetwork, DeleteNetwork]
     }
 
     #[allow(dead_code)] // just in case we need this later
@@ -357,10 +361,169 @@ impl WLANMan {
         self.pddb
             .delete_key(net::AP_DICT_NAME, &ssid_to_be_deleted, None)
             .map_err(|e| WLANError::PDDBIoError(e))?;
+        // networks saved without changing their settings don't have a profile
+        self.pddb.delete_key(net::PROFILE_DICT_NAME, &ssid_to_be_deleted, None).ok();
 
         self.pddb.sync().map_err(|e| WLANError::PDDBIoError(e))
     }
 
+    fn load_profile(&self, ssid: &str) -> net::WifiProfile {
+        match self.pddb.get(net::PROFILE_DICT_NAME, ssid, None, false, false, None, None::<fn()>) {
+            Ok(mut entry) => {
+                let mut bytes = Vec::<u8>::new();
+                entry.read_to_end(&mut bytes).ok();
+                net::WifiProfile::decode(&bytes).unwrap_or_default()
+            }
+            Err(_) => net::WifiProfile::default(),
+        }
+    }
+
+    fn store_profile(&mut self, ssid: &str, profile: &net::WifiProfile) -> Result<(), WLANError> {
+        let bytes = profile.encode();
+        self.netmgr.connection_manager_stop().unwrap();
+        self.pddb.delete_key(net::PROFILE_DICT_NAME, ssid, None).ok();
+        let mut entry = self.pddb.get(
+            net::PROFILE_DICT_NAME,
+            ssid,
+            Some(".System"),
+            true,
+            true,
+            Some(bytes.len()),
+            Some(|| {}),
+        )?;
+        let len = entry.write(&bytes)?;
+        if len != bytes.len() {
+            return Err(WLANError::PDDBWriteError(len, bytes.len()));
+        }
+        self.pddb.sync()?;
+        // the new settings are used the next time the network is joined
+        self.netmgr.connection_manager_run().unwrap();
+        Ok(())
+    }
+
+    fn yes_no(&self, prompt: &str) -> bool {
+        self.modals.add_list(vec![t!("prefs.yes", locales::LANG), t!("prefs.no", locales::LANG)]).unwrap();
+        self.modals.get_radiobutton(prompt).unwrap() == t!("prefs.yes", locales::LANG)
+    }
+
+    fn edit_network(&mut self) -> Result<(), WLANError> {
+        let networks = match self.pddb.list_keys(net::AP_DICT_NAME, None) {
+            Ok(list) => list,
+            Err(_) => Vec::new(),
+        };
+
+        if networks.is_empty() {
+            self.modals.show_notification(t!("wlan.no_known_networks", locales::LANG), None).unwrap();
+            return Ok(());
+        }
+
+        let cancel_item = t!("wlan.cancel", locales::LANG);
+        self.modals.add_list(networks.iter().map(|s| s.as_str()).collect()).unwrap();
+        self.modals.add_list_item(cancel_item).unwrap();
+
+        let ssid = self.modals.get_radiobutton(t!("wlan.choose_edit", locales::LANG)).unwrap();
+        if ssid.eq(cancel_item) {
+            return Ok(());
+        }
+
+        let mut profile = self.load_profile(&ssid);
+        let on_off = |b: bool| if b { t!("prefs.yes", locales::LANG) } else { t!("prefs.no", locales::LANG) };
+        let ip = match profile.static_ipv4 {
+            Some(ip) => format!("{}/{}", format_ip(ip.addr), ip.prefix),
+            None => t!("wlan.dhcp", locales::LANG).to_string(),
+        };
+        let summary = format!(
+            "{}\n\n ▪ {}: {}\n ▪ {}: {}\n ▪ {}: {}\n ▪ {}: {}",
+            ssid,
+            t!("wlan.priority", locales::LANG),
+            profile.priority,
+            t!("wlan.hidden", locales::LANG),
+            on_off(profile.hidden),
+            t!("wlan.autojoin", locales::LANG),
+            on_off(profile.autojoin),
+            t!("wlan.ip_settings", locales::LANG),
+            ip,
+        );
+        let settings = vec![
+            t!("wlan.priority", locales::LANG),
+            t!("wlan.hidden", locales::LANG),
+            t!("wlan.autojoin", locales::LANG),
+            t!("wlan.ip_settings", locales::LANG),
+            cancel_item,
+        ];
+        self.modals.add_list(settings).unwrap();
+        let setting = self.modals.get_radiobutton(&summary).unwrap();
+
+        if setting == t!("wlan.priority", locales::LANG) {
+            let entry = self
+                .modals
+                .alert_builder(&t!("wlan.priority_entry", locales::LANG).replace("{ssid}", &ssid))
+                .field_placeholder_persist(
+                    Some(profile.priority.to_string()),
+                    Some(|text| match text.as_str().parse::<u8>() {
+                        Ok(_) => None,
+                        Err(_) => {
+                            Some(xous_ipc::String::from_str(t!("wlan.priority_invalid", locales::LANG)))
+                        }
+                    }),
+                )
+                .build()
+                .unwrap();
+            profile.priority = entry.content()[0].as_str().parse::<u8>().unwrap();
+        } else if setting == t!("wlan.hidden", locales::LANG) {
+            profile.hidden = self.yes_no(&t!("wlan.hidden_prompt", locales::LANG).replace("{ssid}", &ssid));
+        } else if setting == t!("wlan.autojoin", locales::LANG) {
+            profile.autojoin =
+                self.yes_no(&t!("wlan.autojoin_prompt", locales::LANG).replace("{ssid}", &ssid));
+        } else if setting == t!("wlan.ip_settings", locales::LANG) {
+            let entry = self
+                .modals
+                .alert_builder(&t!("wlan.static_ip_entry", locales::LANG).replace("{ssid}", &ssid))
+                .field_placeholder_persist(
+                    profile.static_ipv4.map(|ip| format!("{}/{}", format_ip(ip.addr), ip.prefix)),
+                    Some(|text| {
+                        if text.as_str().is_empty() || parse_cidr(text.as_str()).is_some() {
+                            None
+                        } else {
+                            Some(xous_ipc::String::from_str(t!("wlan.address_invalid", locales::LANG)))
+                        }
+                    }),
+                )
+                .field_placeholder_persist(
+                    profile.static_ipv4.map(|ip| format_ip(ip.gateway)),
+                    Some(validate_optional_ip),
+                )
+                .field_placeholder_persist(
+                    profile.static_ipv4.and_then(|ip| ip.dns).map(|dns| format_ip(dns)),
+                    Some(validate_optional_ip),
+                )
+                .build()
+                .unwrap();
+            let content = entry.content();
+            profile.static_ipv4 = match parse_cidr(content[0].as_str()) {
+                Some((addr, prefix)) => match content[1].as_str().parse::<Ipv4Addr>() {
+                    Ok(gateway) => Some(net::WifiStaticIpv4 {
+                        addr: addr.octets(),
+                        prefix,
+                        gateway: gateway.octets(),
+                        dns: content[2].as_str().parse::<Ipv4Addr>().ok().map(|dns| dns.octets()),
+                    }),
+                    Err(_) => {
+                        self.modals
+                            .show_notification(t!("wlan.gateway_missing", locales::LANG), None)
+                            .unwrap();
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+        } else {
+            return Ok(());
+        }
+
+        self.store_profile(&ssid, &profile)
+    }
+
     fn consume_menu_action(&mut self, action: WlanManOp) {
         let resp = match action {
             WlanManOp::AddNetworkManually => self.add_new_ssid(),
@@ -368,6 +531,7 @@ impl WLANMan {
             WlanManOp::Status => self.network_status(),
             WlanManOp::DeleteNetwork => self.delete_network(),
             WlanManOp::KnownNetworks => self.known_networks(),
+            WlanManOp::EditNetwork => self.edit_network(),
         };
 
         resp.unwrap_or_else(|error| self.show_error_modal(error));
@@ -383,3 +547,18 @@ impl WLANMan {
 fn format_ip(src: [u8; 4]) -> String {
     src.iter().map(|&id| id.to_string()).collect::<Vec<String>>().join(".")
 }
+
+/// Parses an `a.b.c.d/prefix` address
+fn parse_cidr(src: &str) -> Option<(Ipv4Addr, u8)> {
+    let (addr, prefix) = src.split_once('/')?;
+    let prefix = prefix.parse::<u8>().ok().filter(|&p| p <= 32)?;
+    Some((addr.parse::<Ipv4Addr>().ok()?, prefix))
+}
+
+fn validate_optional_ip(text: TextEntryPayload) -> Option<ValidatorErr> {
+    if text.as_str().is_empty() || text.as_str().parse::<Ipv4Addr>().is_ok() {
+        None
+    } else {
+        Some(xous_ipc::String::from_str(t!("wlan.address_invalid", locales::LANG)))
+    }
+}
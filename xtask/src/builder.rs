@@ -146,6 +146,9 @@ pub(crate) struct Builder {
     no_image: bool,
     /// when Some, specifies a swap region as offset, size
     swap: Option<(u32, u32)>,
+    /// scenario scripts that hosted mode runs headless, one boot per script, instead of running
+    /// interactively
+    scenarios: Vec<PathBuf>,
 }
 
 impl Builder {
@@ -174,6 +177,7 @@ impl Builder {
             dry_run: false,
             no_image: false,
             swap: None,
+            scenarios: Vec::new(),
         }
     }
 
@@ -427,6 +431,50 @@ impl Builder {
         self
     }
 
+    /// run hosted mode headless once for each scenario script, and fail if any script fails. See
+    /// services/graphics-server/src/script.rs for the format of the scripts.
+    pub fn hosted_scenarios(&mut self, scripts: &[PathBuf]) -> &mut Builder {
+        self.scenarios.extend_from_slice(scripts);
+        self
+    }
+
+    /// Boot hosted mode headless with each scenario script in turn. The results and snapshots of a
+    /// script are left in target/scenarios/<script name>.
+    fn run_scenarios(&self, hosted_args: &[&str]) -> Result<(), DynError> {
+        let mut dir = project_root();
+        dir.push("kernel");
+        let mut failed = Vec::new();
+        for script in self.scenarios.iter() {
+            let name = script.file_stem().ok_or("scenario script has no name")?.to_string_lossy();
+            let out = project_root().join("target").join("scenarios").join(name.as_ref());
+            if out.exists() {
+                std::fs::remove_dir_all(&out)?;
+            }
+            std::fs::create_dir_all(&out)?;
+            println!("Running scenario {}...", script.display());
+            let status = Command::new(cargo())
+                .current_dir(&dir)
+                .args(hosted_args)
+                .env("XOUS_HEADLESS", "1")
+                .env("XOUS_SCRIPT", std::fs::canonicalize(script)?)
+                .env("XOUS_SNAPSHOT_DIR", &out)
+                .status()?;
+            // the script writes its result before it shuts down hosted mode
+            let result = std::fs::read_to_string(out.join("result"))
+                .unwrap_or_else(|_| format!("fail: no result (hosted mode exited with {})", status));
+            println!("    {}: {}", name, result);
+            if result != "pass" {
+                failed.push(name.to_string());
+            }
+        }
+        if failed.is_empty() {
+            println!("All {} scenarios passed", self.scenarios.len());
+            Ok(())
+        } else {
+            Err(format!("Scenarios failed: {}", failed.join(", ")).into())
+        }
+    }
+
     /// The builder sets up all the cargo arguments to build a set of packages with features for a respective
     /// target and stream. It also runs the build as well. It's meant to be called only by the `build()`
     /// method, and it gets called repeatedly to build the kernel, loader, and services.
@@ -736,7 +784,9 @@ impl Builder {
             let mut binary_files: Vec<&str> = binary_files_storage.iter().map(|s| s.as_ref()).collect();
             hosted_args.append(&mut binary_files);
 
-            if !self.dry_run {
+            if !self.dry_run && !self.scenarios.is_empty() {
+                self.run_scenarios(&hosted_args)?;
+            } else if !self.dry_run {
                 let mut dir = project_root();
                 dir.push("kernel");
                 println!("Starting hosted mode...");
@@ -316,6 +316,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some("hosted-ci") => {
             builder.target_hosted().add_services(&user_pkgs).hosted_build_only().add_apps(&get_cratespecs());
         }
+        Some("hosted-test") => {
+            // the scenarios cover the vault, so it's in the image unless other apps are named
+            let mut apps = get_cratespecs();
+            if apps.is_empty() {
+                apps.push("vault".to_string());
+            }
+            let mut scenarios: Vec<std::path::PathBuf> =
+                get_flag("--scenario")?.iter().map(std::path::PathBuf::from).collect();
+            if scenarios.is_empty() {
+                for entry in std::fs::read_dir(project_root().join("tools").join("scenarios"))? {
+                    let path = entry?.path();
+                    if path.extension().map_or(false, |ext| ext == "txt") {
+                        scenarios.push(path);
+                    }
+                }
+                scenarios.sort();
+            }
+            builder.target_hosted().add_services(&user_pkgs).hosted_scenarios(&scenarios).add_apps(&apps);
+        }
 
         // ------ Precursor hardware image configs ------
         Some("app-image") => {
@@ -573,6 +592,8 @@ Hosted emulation:
  pddb-btest              PDDB stress tester for secret basis creation/deletion [cratespecs] ignored.
  hosted-debug            Run user image in hosted mode with debug flags. [cratespecs] are apps
  gfx-dev                 Testing mode for graphics primitives. [cratespecs] are services
+ hosted-test             Run the scenario scripts in tools/scenarios headless, or just those given with
+                         [--scenario path]. [cratespecs] are apps (default: vault)
  pddb-dev                Testing for compilation errors on hardware targets on the PDDB.
 
 Renode emulation: